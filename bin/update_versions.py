@@ -49,6 +49,8 @@ VERSION_FILES = [
     (bridge_path('jni'), CARGO_PATTERN),
     (bridge_path('jni', 'testing'), CARGO_PATTERN),
     (bridge_path('node'), CARGO_PATTERN),
+    (bridge_path('python'), CARGO_PATTERN),
+    (bridge_path('wasm'), CARGO_PATTERN),
 ]
 
 
@@ -10,10 +10,26 @@ import static org.signal.libsignal.internal.FilterExceptions.filterExceptions;
 import org.signal.libsignal.internal.Native;
 
 public class DeviceTransferKey {
+  /** The type of key to generate. */
+  public enum KeyType {
+    /** A 4096-bit RSA key. */
+    RSA,
+    /** An ECDSA key over the NIST P-256 curve, which is much faster to generate than RSA. */
+    ECDSA_P256,
+  }
+
   byte[] keyMaterial;
 
   public DeviceTransferKey() {
-    this.keyMaterial = Native.DeviceTransfer_GeneratePrivateKey();
+    this(KeyType.RSA);
+  }
+
+  public DeviceTransferKey(KeyType keyType) {
+    if (keyType == KeyType.ECDSA_P256) {
+      this.keyMaterial = Native.DeviceTransfer_GenerateEcPrivateKey();
+    } else {
+      this.keyMaterial = Native.DeviceTransfer_GeneratePrivateKey();
+    }
   }
 
   public byte[] keyMaterial() {
@@ -24,4 +40,32 @@ public class DeviceTransferKey {
     return filterExceptions(
         () -> Native.DeviceTransfer_GenerateCertificate(this.keyMaterial, name, daysTilExpires));
   }
+
+  /**
+   * Generates a self-signed certificate with explicit subject fields, validity, and serial number.
+   *
+   * @param organization the organization (O), or null to leave it out
+   * @param organizationalUnit the organizational unit (OU), or null to leave it out
+   * @param backdateSeconds how far in the past the certificate's validity starts, to allow for
+   *     clock skew between the two devices
+   * @param randomSerialNumber whether to use a random serial number instead of zero
+   */
+  public byte[] generateCertificate(
+      String commonName,
+      String organization,
+      String organizationalUnit,
+      int daysTilExpires,
+      int backdateSeconds,
+      boolean randomSerialNumber) {
+    return filterExceptions(
+        () ->
+            Native.DeviceTransfer_GenerateCertificateWithParams(
+                this.keyMaterial,
+                commonName,
+                organization,
+                organizationalUnit,
+                daysTilExpires,
+                backdateSeconds,
+                randomSerialNumber));
+  }
 }
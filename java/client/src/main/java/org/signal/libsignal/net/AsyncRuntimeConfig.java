@@ -0,0 +1,60 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.net;
+
+/**
+ * Settings for the background threads libsignal uses for network operations.
+ *
+ * <p>The defaults use one worker thread per CPU core. On constrained devices, consider limiting
+ * the number of worker threads, or running everything on a single thread with {@link
+ * #setUseCurrentThread}.
+ */
+public final class AsyncRuntimeConfig {
+  private int workerThreads = 0;
+  private String threadName = "libsignal-tokio-worker";
+  private boolean useCurrentThread = false;
+
+  /**
+   * Sets the number of worker threads.
+   *
+   * <p>0, the default, uses one worker thread per CPU core. Ignored if {@link
+   * #setUseCurrentThread} is set.
+   */
+  public AsyncRuntimeConfig setWorkerThreads(int workerThreads) {
+    if (workerThreads < 0) {
+      throw new IllegalArgumentException("workerThreads must not be negative");
+    }
+    this.workerThreads = workerThreads;
+    return this;
+  }
+
+  /** Sets the name given to libsignal's threads. */
+  public AsyncRuntimeConfig setThreadName(String threadName) {
+    this.threadName = threadName;
+    return this;
+  }
+
+  /**
+   * Sets whether to run all operations on a single background thread instead of a pool of worker
+   * threads.
+   */
+  public AsyncRuntimeConfig setUseCurrentThread(boolean useCurrentThread) {
+    this.useCurrentThread = useCurrentThread;
+    return this;
+  }
+
+  int getWorkerThreads() {
+    return this.workerThreads;
+  }
+
+  String getThreadName() {
+    return this.threadName;
+  }
+
+  boolean getUseCurrentThread() {
+    return this.useCurrentThread;
+  }
+}
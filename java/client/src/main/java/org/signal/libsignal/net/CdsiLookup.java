@@ -7,6 +7,7 @@ package org.signal.libsignal.net;
 
 import java.io.IOException;
 import java.util.concurrent.ExecutionException;
+import java.util.function.Consumer;
 import org.signal.libsignal.internal.CompletableFuture;
 import org.signal.libsignal.internal.Native;
 import org.signal.libsignal.internal.NativeHandleGuard;
@@ -21,20 +22,64 @@ class CdsiLookup implements NativeHandleGuard.Owner {
         NativeHandleGuard connectionManager =
             new NativeHandleGuard(network.getConnectionManager())) {
 
-      return Native.CdsiLookup_new(
-              asyncRuntime.nativeHandle(),
-              connectionManager.nativeHandle(),
-              username,
-              password,
-              nativeRequest.getHandle())
+      return network
+          .getAsyncContext()
+          .makeCancellable(
+              Native.CdsiLookup_new(
+                  asyncRuntime.nativeHandle(),
+                  connectionManager.nativeHandle(),
+                  username,
+                  password,
+                  nativeRequest.getHandle()))
           .thenApply((Long nativeHandle) -> new CdsiLookup(nativeHandle, network));
     }
   }
 
+  /**
+   * Performs a complete lookup in one call, passing the token to {@code tokenConsumer} before
+   * returning the results.
+   */
+  public static CompletableFuture<CdsiLookupResponse> lookup(
+      Network network,
+      String username,
+      String password,
+      CdsiLookupRequest request,
+      Consumer<byte[]> tokenConsumer)
+      throws IOException, InterruptedException, ExecutionException {
+
+    CdsiLookupRequest.NativeRequest nativeRequest = request.makeNative();
+    try (NativeHandleGuard asyncRuntime = new NativeHandleGuard(network.getAsyncContext());
+        NativeHandleGuard connectionManager =
+            new NativeHandleGuard(network.getConnectionManager())) {
+
+      return network
+          .getAsyncContext()
+          .makeCancellable(
+              Native.CdsiLookup_lookup(
+                  asyncRuntime.nativeHandle(),
+                  connectionManager.nativeHandle(),
+                  username,
+                  password,
+                  nativeRequest.getHandle()))
+          .thenApply(
+              (Long result) -> {
+                try {
+                  tokenConsumer.accept(Native.CdsiLookupResult_token(result));
+                  return (CdsiLookupResponse) Native.CdsiLookupResult_takeResponse(result);
+                } finally {
+                  Native.CdsiLookupResult_Destroy(result);
+                }
+              });
+    }
+  }
+
   public CompletableFuture<CdsiLookupResponse> complete() {
     try (NativeHandleGuard asyncRuntime = new NativeHandleGuard(this.network.getAsyncContext());
         NativeHandleGuard self = new NativeHandleGuard(this)) {
-      return Native.CdsiLookup_complete(asyncRuntime.nativeHandle(), self.nativeHandle())
+      return this.network
+          .getAsyncContext()
+          .makeCancellable(
+              Native.CdsiLookup_complete(asyncRuntime.nativeHandle(), self.nativeHandle()))
           .thenApply(response -> (CdsiLookupResponse) response);
     }
   }
@@ -77,7 +77,9 @@ public class ChatService extends NativeHandleGuard.SimpleOwner {
         asyncContextHandle ->
             guardedMap(
                 chatServiceHandle ->
-                    Native.ChatService_connect_auth(asyncContextHandle, chatServiceHandle)
+                    tokioAsyncContext
+                        .makeCancellable(
+                            Native.ChatService_connect_auth(asyncContextHandle, chatServiceHandle))
                         .thenApply(o -> (DebugInfo) o)));
   }
 
@@ -99,7 +101,10 @@ public class ChatService extends NativeHandleGuard.SimpleOwner {
         asyncContextHandle ->
             guardedMap(
                 chatServiceHandle ->
-                    Native.ChatService_connect_unauth(asyncContextHandle, chatServiceHandle)
+                    tokioAsyncContext
+                        .makeCancellable(
+                            Native.ChatService_connect_unauth(
+                                asyncContextHandle, chatServiceHandle))
                         .thenApply(o -> (DebugInfo) o)));
   }
 
@@ -119,11 +124,13 @@ public class ChatService extends NativeHandleGuard.SimpleOwner {
     try (final NativeHandleGuard asyncContextHandle = new NativeHandleGuard(tokioAsyncContext);
         final NativeHandleGuard chatServiceHandle = new NativeHandleGuard(this);
         final NativeHandleGuard requestHandle = new NativeHandleGuard(internalRequest)) {
-      return Native.ChatService_unauth_send(
-              asyncContextHandle.nativeHandle(),
-              chatServiceHandle.nativeHandle(),
-              requestHandle.nativeHandle(),
-              req.timeoutMillis)
+      return tokioAsyncContext
+          .makeCancellable(
+              Native.ChatService_unauth_send(
+                  asyncContextHandle.nativeHandle(),
+                  chatServiceHandle.nativeHandle(),
+                  requestHandle.nativeHandle(),
+                  req.timeoutMillis))
           .thenApply(o -> (Response) o);
     }
   }
@@ -148,11 +155,13 @@ public class ChatService extends NativeHandleGuard.SimpleOwner {
     try (final NativeHandleGuard asyncContextHandle = new NativeHandleGuard(tokioAsyncContext);
         final NativeHandleGuard chatServiceHandle = new NativeHandleGuard(this);
         final NativeHandleGuard requestHandle = new NativeHandleGuard(internalRequest)) {
-      return Native.ChatService_unauth_send_and_debug(
-              asyncContextHandle.nativeHandle(),
-              chatServiceHandle.nativeHandle(),
-              requestHandle.nativeHandle(),
-              req.timeoutMillis)
+      return tokioAsyncContext
+          .makeCancellable(
+              Native.ChatService_unauth_send_and_debug(
+                  asyncContextHandle.nativeHandle(),
+                  chatServiceHandle.nativeHandle(),
+                  requestHandle.nativeHandle(),
+                  req.timeoutMillis))
           .thenApply(o -> (ResponseAndDebugInfo) o);
     }
   }
@@ -173,11 +182,13 @@ public class ChatService extends NativeHandleGuard.SimpleOwner {
     try (final NativeHandleGuard asyncContextHandle = new NativeHandleGuard(tokioAsyncContext);
         final NativeHandleGuard chatServiceHandle = new NativeHandleGuard(this);
         final NativeHandleGuard requestHandle = new NativeHandleGuard(internalRequest)) {
-      return Native.ChatService_auth_send(
-              asyncContextHandle.nativeHandle(),
-              chatServiceHandle.nativeHandle(),
-              requestHandle.nativeHandle(),
-              req.timeoutMillis)
+      return tokioAsyncContext
+          .makeCancellable(
+              Native.ChatService_auth_send(
+                  asyncContextHandle.nativeHandle(),
+                  chatServiceHandle.nativeHandle(),
+                  requestHandle.nativeHandle(),
+                  req.timeoutMillis))
           .thenApply(o -> (Response) o);
     }
   }
@@ -203,11 +214,13 @@ public class ChatService extends NativeHandleGuard.SimpleOwner {
     try (final NativeHandleGuard asyncContextHandle = new NativeHandleGuard(tokioAsyncContext);
         final NativeHandleGuard chatServiceHandle = new NativeHandleGuard(this);
         final NativeHandleGuard requestHandle = new NativeHandleGuard(internalRequest)) {
-      return Native.ChatService_auth_send_and_debug(
-              asyncContextHandle.nativeHandle(),
-              chatServiceHandle.nativeHandle(),
-              requestHandle.nativeHandle(),
-              req.timeoutMillis)
+      return tokioAsyncContext
+          .makeCancellable(
+              Native.ChatService_auth_send_and_debug(
+                  asyncContextHandle.nativeHandle(),
+                  chatServiceHandle.nativeHandle(),
+                  requestHandle.nativeHandle(),
+                  req.timeoutMillis))
           .thenApply(o -> (ResponseAndDebugInfo) o);
     }
   }
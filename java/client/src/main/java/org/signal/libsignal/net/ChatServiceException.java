@@ -6,10 +6,24 @@
 package org.signal.libsignal.net;
 
 import java.io.IOException;
+import java.util.OptionalInt;
 
 /** Error thrown by Chat Service API. */
 public class ChatServiceException extends IOException {
+  private final OptionalInt httpStatus;
+
   public ChatServiceException(String message) {
     super(message);
+    this.httpStatus = OptionalInt.empty();
+  }
+
+  public ChatServiceException(String message, int httpStatus) {
+    super(message);
+    this.httpStatus = OptionalInt.of(httpStatus);
+  }
+
+  /** The HTTP status sent by the server if it rejected the request, if any. */
+  public OptionalInt getHttpStatus() {
+    return this.httpStatus;
   }
 }
@@ -43,6 +43,29 @@ public class Network {
     this.svr3 = new Svr3(this);
   }
 
+  /**
+   * Creates a {@code Network} whose background threads are configured by {@code runtimeConfig}.
+   *
+   * @throws IOException if the threads could not be started.
+   */
+  public Network(Environment env, String userAgent, AsyncRuntimeConfig runtimeConfig)
+      throws IOException {
+    this.tokioAsyncContext = new TokioAsyncContext(runtimeConfig);
+    this.connectionManager = new ConnectionManager(env, userAgent);
+    this.svr3 = new Svr3(this);
+  }
+
+  /**
+   * Cancels all outstanding operations and stops libsignal's background threads.
+   *
+   * <p>Blocks for up to {@code timeoutMillis} while cancelled operations complete their futures
+   * with a {@link java.util.concurrent.CancellationException}. Operations started afterwards fail
+   * the same way. Calling this more than once has no further effect.
+   */
+  public void shutdown(int timeoutMillis) {
+    this.tokioAsyncContext.shutdown(timeoutMillis);
+  }
+
   /**
    * Sets the proxy host to be used for all new connections (until overridden).
    *
@@ -82,12 +105,7 @@ public class Network {
   public CompletableFuture<CdsiLookupResponse> cdsiLookup(
       String username, String password, CdsiLookupRequest request, Consumer<byte[]> tokenConsumer)
       throws IOException, InterruptedException, ExecutionException {
-    return CdsiLookup.start(this, username, password, request)
-        .thenCompose(
-            (CdsiLookup lookup) -> {
-              tokenConsumer.accept(lookup.getToken());
-              return lookup.complete();
-            });
+    return CdsiLookup.lookup(this, username, password, request, tokenConsumer);
   }
 
   /**
@@ -6,13 +6,27 @@
 package org.signal.libsignal.net;
 
 import java.io.IOException;
+import java.util.OptionalInt;
 
 /**
  * Error thrown by a network failure on a higher level, for example failure to establish a WebSocket
  * connection.
  */
 public class NetworkProtocolException extends IOException {
+  private final OptionalInt httpStatus;
+
   public NetworkProtocolException(String message) {
     super(message);
+    this.httpStatus = OptionalInt.empty();
+  }
+
+  public NetworkProtocolException(String message, int httpStatus) {
+    super(message);
+    this.httpStatus = OptionalInt.of(httpStatus);
+  }
+
+  /** The HTTP status sent by a server that rejected the request, if any. */
+  public OptionalInt getHttpStatus() {
+    return this.httpStatus;
   }
 }
@@ -5,6 +5,9 @@
 
 package org.signal.libsignal.net;
 
+import static org.signal.libsignal.internal.FilterExceptions.filterExceptions;
+
+import java.io.IOException;
 import org.signal.libsignal.internal.CompletableFuture;
 import org.signal.libsignal.internal.Native;
 import org.signal.libsignal.internal.NativeHandleGuard;
@@ -14,11 +17,48 @@ class TokioAsyncContext extends NativeHandleGuard.SimpleOwner {
     super(Native.TokioAsyncContext_new());
   }
 
+  TokioAsyncContext(AsyncRuntimeConfig config) throws IOException {
+    super(
+        filterExceptions(
+            IOException.class,
+            () ->
+                Native.TokioAsyncContext_new_with_config(
+                    config.getWorkerThreads(),
+                    config.getThreadName(),
+                    config.getUseCurrentThread())));
+  }
+
   @SuppressWarnings("unchecked")
   CompletableFuture<Class<Object>> loadClassAsync(String className) {
     return (CompletableFuture<Class<Object>>) Native.AsyncLoadClass(this, className);
   }
 
+  /**
+   * Makes cancelling {@code future} cancel the native task that would have completed it.
+   *
+   * <p>Cancelling a native task drops it, closing any network connections it was using.
+   */
+  <T> CompletableFuture<T> makeCancellable(CompletableFuture<T> future) {
+    long cancellationId = future.getCancellationId();
+    if (cancellationId != 0) {
+      future.setCancellationHandler(
+          () ->
+              guardedRun(
+                  nativeHandle -> Native.TokioAsyncContext_cancel(nativeHandle, cancellationId)));
+    }
+    return future;
+  }
+
+  /**
+   * Cancels all outstanding native tasks and stops the runtime's threads.
+   *
+   * <p>Cancelled tasks are given up to {@code timeoutMillis} to complete their futures. Tasks
+   * started afterwards are cancelled immediately.
+   */
+  void shutdown(int timeoutMillis) {
+    guardedRun(nativeHandle -> Native.TokioAsyncContext_shutdown(nativeHandle, timeoutMillis));
+  }
+
   @Override
   protected void release(final long nativeHandle) {
     Native.TokioAsyncContext_Destroy(nativeHandle);
@@ -47,4 +47,18 @@ public class Pin {
   public static boolean verifyLocalHash(final String encodedHash, final byte[] pin) {
     return filterExceptions(() -> Native.Pin_VerifyLocalHash(encodedHash, pin));
   }
+
+  /**
+   * Estimate how easy a pin is to guess, so that weak pins can be warned about the same way on
+   * every platform.
+   *
+   * @param pin A normalized pin
+   * @return The estimated strength, and the patterns that make the pin easier to guess
+   */
+  public static PinStrength estimateStrength(final String pin) {
+    return filterExceptions(
+        () ->
+            new PinStrength(
+                Native.Pin_EstimateStrengthLevel(pin), Native.Pin_EstimateStrengthWeaknesses(pin)));
+  }
 }
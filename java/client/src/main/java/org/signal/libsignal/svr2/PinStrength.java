@@ -0,0 +1,69 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.svr2;
+
+import java.util.Collections;
+import java.util.EnumSet;
+import java.util.Set;
+
+/** How easy a PIN is to guess, as estimated by {@link Pin#estimateStrength}. */
+public class PinStrength {
+  /** How guessable a PIN is, from worst to best. */
+  public enum Level {
+    /** Among the first PINs an attacker would try. */
+    VERY_WEAK,
+    /** Follows a pattern that narrows down the search considerably. */
+    WEAK,
+    /** No known pattern, but a small search space. */
+    ACCEPTABLE,
+    /** No known pattern and a large search space. */
+    STRONG,
+  }
+
+  /** A pattern found in a PIN that makes it easier to guess. */
+  public enum Weakness {
+    /** Shorter than four characters. */
+    TOO_SHORT,
+    /** One of the most commonly chosen PINs. */
+    COMMON,
+    /** A short group of characters repeated, like {@code 1111} or {@code 1212}. */
+    REPEATED,
+    /** Consecutive characters in order, like {@code 1234}, {@code 9876}, or {@code abcd}. */
+    SEQUENCE,
+    /** A straight line on a phone keypad, like {@code 2580}. */
+    KEYPAD_PATTERN,
+    /** Looks like a date or a year, like {@code 1987} or {@code 311299}. */
+    DATE,
+  }
+
+  private final Level level;
+  private final Set<Weakness> weaknesses;
+
+  PinStrength(int level, int weaknessMask) {
+    this.level = Level.values()[level];
+    EnumSet<Weakness> weaknesses = EnumSet.noneOf(Weakness.class);
+    for (Weakness weakness : Weakness.values()) {
+      if ((weaknessMask & (1 << weakness.ordinal())) != 0) {
+        weaknesses.add(weakness);
+      }
+    }
+    this.weaknesses = Collections.unmodifiableSet(weaknesses);
+  }
+
+  public Level getLevel() {
+    return level;
+  }
+
+  /** Every weakness that was found. */
+  public Set<Weakness> getWeaknesses() {
+    return weaknesses;
+  }
+
+  /** Whether the PIN should be accepted without a warning. */
+  public boolean isAcceptable() {
+    return level.compareTo(Level.ACCEPTABLE) >= 0;
+  }
+}
@@ -7,6 +7,7 @@ package org.signal.libsignal.devicetransfer;
 
 import java.io.ByteArrayInputStream;
 import java.security.cert.CertificateFactory;
+import java.security.cert.X509Certificate;
 import junit.framework.TestCase;
 
 public class DeviceTransferKeyTest extends TestCase {
@@ -15,6 +16,34 @@ public class DeviceTransferKeyTest extends TestCase {
     byte[] certBytes = key.generateCertificate("name", 365);
 
     CertificateFactory cf = CertificateFactory.getInstance("X.509");
-    cf.generateCertificate(new ByteArrayInputStream(certBytes));
+    X509Certificate cert =
+        (X509Certificate) cf.generateCertificate(new ByteArrayInputStream(certBytes));
+    assertEquals("RSA", cert.getPublicKey().getAlgorithm());
+  }
+
+  public void testEcDeviceTransferKey() throws Exception {
+    DeviceTransferKey key = new DeviceTransferKey(DeviceTransferKey.KeyType.ECDSA_P256);
+    byte[] certBytes = key.generateCertificate("name", 365);
+
+    CertificateFactory cf = CertificateFactory.getInstance("X.509");
+    X509Certificate cert =
+        (X509Certificate) cf.generateCertificate(new ByteArrayInputStream(certBytes));
+    assertEquals("EC", cert.getPublicKey().getAlgorithm());
+    cert.verify(cert.getPublicKey());
+  }
+
+  public void testCertificateParams() throws Exception {
+    DeviceTransferKey key = new DeviceTransferKey(DeviceTransferKey.KeyType.ECDSA_P256);
+    byte[] certBytes = key.generateCertificate("name", "Org", null, 30, 60 * 60, true);
+
+    CertificateFactory cf = CertificateFactory.getInstance("X.509");
+    X509Certificate cert =
+        (X509Certificate) cf.generateCertificate(new ByteArrayInputStream(certBytes));
+    assertEquals("O=Org,CN=name", cert.getSubjectX500Principal().getName());
+    assertEquals(cert.getSubjectX500Principal(), cert.getIssuerX500Principal());
+    assertTrue(cert.getSerialNumber().signum() > 0);
+
+    long validityMillis = cert.getNotAfter().getTime() - cert.getNotBefore().getTime();
+    assertEquals(30 * 24 * 60 * 60 + 60 * 60, validityMillis / 1000, 60);
   }
 }
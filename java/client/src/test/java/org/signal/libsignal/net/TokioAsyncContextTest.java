@@ -7,10 +7,15 @@ package org.signal.libsignal.net;
 
 import static org.junit.Assert.*;
 
+import java.util.concurrent.CancellationException;
 import java.util.concurrent.ExecutionException;
 import java.util.concurrent.Future;
 import java.util.concurrent.TimeUnit;
+import java.util.concurrent.TimeoutException;
 import org.junit.Test;
+import org.signal.libsignal.internal.CompletableFuture;
+import org.signal.libsignal.internal.NativeHandleGuard;
+import org.signal.libsignal.internal.NativeTesting;
 
 public class TokioAsyncContextTest {
   @Test
@@ -60,6 +65,43 @@ public class TokioAsyncContextTest {
         cause instanceof ClassNotFoundException || cause instanceof NoClassDefFoundError);
   }
 
+  @Test
+  @SuppressWarnings("unchecked")
+  public void cancelRunningFuture() throws Exception {
+    TokioAsyncContext context = new TokioAsyncContext();
+    CompletableFuture<Object> future;
+    try (NativeHandleGuard guard = new NativeHandleGuard(context)) {
+      future =
+          context.makeCancellable(
+              NativeTesting.TESTING_OnlyCompletesByCancellation(guard.nativeHandle()));
+    }
+    assertThrows(TimeoutException.class, () -> future.get(200, TimeUnit.MILLISECONDS));
+    assertTrue(future.cancel(true));
+    assertTrue(future.isCancelled());
+    assertThrows(CancellationException.class, () -> future.get(10, TimeUnit.SECONDS));
+  }
+
+  @Test
+  @SuppressWarnings("unchecked")
+  public void shutdownCancelsFutures() throws Exception {
+    TokioAsyncContext context =
+        new TokioAsyncContext(
+            new AsyncRuntimeConfig().setThreadName("test-worker").setUseCurrentThread(true));
+    CompletableFuture<Object> future;
+    try (NativeHandleGuard guard = new NativeHandleGuard(context)) {
+      future = NativeTesting.TESTING_OnlyCompletesByCancellation(guard.nativeHandle());
+    }
+    context.shutdown(10_000);
+    assertThrows(CancellationException.class, () -> future.get(10, TimeUnit.SECONDS));
+
+    // Futures started after shutdown are cancelled too.
+    CompletableFuture<Object> lateFuture;
+    try (NativeHandleGuard guard = new NativeHandleGuard(context)) {
+      lateFuture = NativeTesting.TESTING_OnlyCompletesByCancellation(guard.nativeHandle());
+    }
+    assertThrows(CancellationException.class, () -> lateFuture.get(10, TimeUnit.SECONDS));
+  }
+
   @Test
   public void runNetworkClassLoadTestFunction() throws ExecutionException, InterruptedException {
     Network.checkClassesCanBeLoadedAsyncForTest();
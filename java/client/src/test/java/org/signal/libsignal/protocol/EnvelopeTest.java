@@ -0,0 +1,65 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.protocol;
+
+import static org.junit.Assert.assertArrayEquals;
+import static org.junit.Assert.assertEquals;
+import static org.junit.Assert.assertFalse;
+import static org.junit.Assert.assertThrows;
+import static org.junit.Assert.assertTrue;
+
+import java.util.Optional;
+import java.util.UUID;
+import org.junit.Test;
+import org.signal.libsignal.protocol.message.Envelope;
+import org.signal.libsignal.protocol.message.PlaintextContent;
+import org.signal.libsignal.protocol.util.Hex;
+
+public class EnvelopeTest {
+  private static final String SOURCE = "9d0652a3-dcc3-4d11-975f-74d61598733f";
+
+  // A PLAINTEXT_CONTENT envelope from SOURCE.2 at timestamp 1000, with a server GUID.
+  private static final byte[] PLAINTEXT_ENVELOPE =
+      Hex.fromStringCondensedAssert(
+          "08085a2439643036353261332d646363332d346431312d393735662d373464363135393837333366"
+              + "380228e8074202c0804a2463346431366432662d346235632d346431322d613334632d35643462"
+              + "6466613064393565");
+
+  // The same, but missing the source device ID.
+  private static final byte[] INCOMPLETE_SOURCE_ENVELOPE =
+      Hex.fromStringCondensedAssert(
+          "08085a2439643036353261332d646363332d346431312d393735662d373464363135393837333366"
+              + "28e8074202c080");
+
+  @Test
+  public void testParsePlaintextEnvelope() throws Exception {
+    Envelope envelope = new Envelope(PLAINTEXT_ENVELOPE);
+    assertEquals(Envelope.Type.PLAINTEXT_CONTENT, envelope.getType());
+    assertEquals(Optional.of(ServiceId.parseFromString(SOURCE)), envelope.getSourceServiceId());
+    assertEquals(Optional.of(2), envelope.getSourceDeviceId());
+    assertEquals(Optional.empty(), envelope.getDestinationServiceId());
+    assertEquals(1000, envelope.getTimestamp());
+    assertEquals(Optional.empty(), envelope.getServerTimestamp());
+    assertEquals(
+        Optional.of(UUID.fromString("c4d16d2f-4b5c-4d12-a34c-5d4bdfa0d95e")),
+        envelope.getServerGuid());
+    assertTrue(envelope.isUrgent());
+    assertFalse(envelope.isStory());
+    assertFalse(envelope.isEphemeral());
+    assertEquals(Optional.empty(), envelope.getUpdatedPni());
+    assertFalse(envelope.getReportSpamToken().isPresent());
+
+    byte[] content = envelope.getContent();
+    assertArrayEquals(new byte[] {(byte) 0xc0, (byte) 0x80}, content);
+    assertArrayEquals(content, new PlaintextContent(content).serialize());
+  }
+
+  @Test
+  public void testRejectInconsistentEnvelope() {
+    assertThrows(InvalidMessageException.class, () -> new Envelope(INCOMPLETE_SOURCE_ENVELOPE));
+    assertThrows(InvalidMessageException.class, () -> new Envelope(new byte[] {0x08}));
+  }
+}
@@ -11,6 +11,7 @@ import java.io.IOException;
 import java.nio.ByteBuffer;
 import java.nio.ByteOrder;
 import java.nio.charset.StandardCharsets;
+import java.util.EnumSet;
 import org.junit.Test;
 import org.signal.libsignal.protocol.kdf.HKDF;
 import org.signal.libsignal.protocol.util.Hex;
@@ -35,6 +36,21 @@ public class PinTest {
     assertFalse(Pin.verifyLocalHash(pwhash, "badpassword".getBytes(StandardCharsets.UTF_8)));
   }
 
+  @Test
+  public void estimateStrength() {
+    PinStrength weak = Pin.estimateStrength("1234");
+    assertEquals(PinStrength.Level.VERY_WEAK, weak.getLevel());
+    assertEquals(
+        EnumSet.of(PinStrength.Weakness.COMMON, PinStrength.Weakness.SEQUENCE),
+        weak.getWeaknesses());
+    assertFalse(weak.isAcceptable());
+
+    PinStrength strong = Pin.estimateStrength("831649");
+    assertEquals(PinStrength.Level.STRONG, strong.getLevel());
+    assertTrue(strong.getWeaknesses().isEmpty());
+    assertTrue(strong.isAcceptable());
+  }
+
   @Test
   public void known() throws IOException {
     final byte[] salt =
@@ -19,24 +19,76 @@ import java.util.function.Function;
 /** A stripped-down, Android-21-compatible version of java.util.concurrent.CompletableFuture. */
 public class CompletableFuture<T> implements Future<T> {
   private boolean completed;
+  private boolean cancelled;
   private T result;
   private Throwable exception;
   private List<ThenApplyCompleter<T>> consumers;
+  private long cancellationId;
+  private Runnable cancellationHandler;
 
   @CalledFromNative
   public CompletableFuture() {
     this.consumers = new ArrayList<>();
   }
 
+  /**
+   * Records the ID the native async runtime assigned to the task completing this future.
+   *
+   * <p>The ID is used to set a {@linkplain #setCancellationHandler cancellation handler} for the
+   * task once the future has been returned to Java.
+   */
+  @CalledFromNative
+  synchronized void setCancellationId(long cancellationId) {
+    this.cancellationId = cancellationId;
+  }
+
+  /** Returns the ID set by {@link #setCancellationId}, or 0 if cancellation is not supported. */
+  public synchronized long getCancellationId() {
+    return cancellationId;
+  }
+
+  /**
+   * Sets an action to run when this future is cancelled, such as stopping the task that would
+   * have completed it.
+   *
+   * <p>If the future has already been cancelled, the action runs immediately.
+   */
+  public void setCancellationHandler(Runnable handler) {
+    synchronized (this) {
+      if (!cancelled) {
+        this.cancellationHandler = handler;
+        return;
+      }
+    }
+    handler.run();
+  }
+
+  /**
+   * Completes this future with a {@link CancellationException}, if it has not already completed,
+   * and runs the {@linkplain #setCancellationHandler cancellation handler}.
+   *
+   * <p>Futures produced by {@link #thenApply} and similar methods pass cancellation on to the
+   * future they were produced from.
+   */
   @Override
-  public synchronized boolean cancel(boolean mayInterruptIfRunning) {
-    // We do not currently support cancellation.
-    return false;
+  public boolean cancel(boolean mayInterruptIfRunning) {
+    Runnable handler;
+    synchronized (this) {
+      if (completed) return false;
+      cancelled = true;
+      completeExceptionally(new CancellationException());
+      handler = cancellationHandler;
+      cancellationHandler = null;
+    }
+    if (handler != null) {
+      handler.run();
+    }
+    return true;
   }
 
   @Override
   public synchronized boolean isCancelled() {
-    return false;
+    return cancelled;
   }
 
   @Override
@@ -85,6 +137,7 @@ public class CompletableFuture<T> implements Future<T> {
       throws CancellationException, ExecutionException, InterruptedException {
     while (!completed) wait();
 
+    if (cancelled) throw (CancellationException) exception;
     if (exception != null) throw new ExecutionException(exception);
 
     return result;
@@ -150,6 +203,7 @@ public class CompletableFuture<T> implements Future<T> {
             future.completeExceptionally(e);
             return;
           }
+          future.setCancellationHandler(() -> output.cancel(true));
           output.addCompleter(
               new ThenApplyCompleter<>(future::complete, future::completeExceptionally));
         },
@@ -199,6 +253,7 @@ public class CompletableFuture<T> implements Future<T> {
             (T value) -> complete.accept(future, value),
             (Throwable exception) -> completeExceptionally.accept(future, exception));
     this.addCompleter(completer);
+    future.setCancellationHandler(() -> this.cancel(true));
     return future;
   }
 
@@ -15,6 +15,7 @@ import org.signal.libsignal.protocol.state.SignedPreKeyStore;
 import org.signal.libsignal.protocol.state.KyberPreKeyStore;
 import org.signal.libsignal.protocol.groups.state.SenderKeyStore;
 import org.signal.libsignal.protocol.logging.Log;
+import org.signal.libsignal.protocol.logging.PanicReporter;
 import org.signal.libsignal.protocol.logging.SignalProtocolLogger;
 
 import java.io.File;
@@ -187,8 +188,13 @@ public final class Native {
 
   public static native Map Cds2Metrics_extract(byte[] attestationMsg) throws Exception;
 
+  public static native void CdsiLookupResult_Destroy(long handle);
+  public static native Object CdsiLookupResult_takeResponse(long result);
+  public static native byte[] CdsiLookupResult_token(long result);
+
   public static native void CdsiLookup_Destroy(long handle);
   public static native CompletableFuture<Object> CdsiLookup_complete(long asyncRuntime, long lookup);
+  public static native CompletableFuture<Long> CdsiLookup_lookup(long asyncRuntime, long connectionManager, String username, String password, long request);
   public static native CompletableFuture<Long> CdsiLookup_new(long asyncRuntime, long connectionManager, String username, String password, long request);
   public static native byte[] CdsiLookup_token(long lookup);
 
@@ -203,11 +209,16 @@ public final class Native {
 
   public static native void Chat_Destroy(long handle);
 
+  public static native String ConnectionAttempts_SnapshotJson();
+
   public static native void ConnectionManager_Destroy(long handle);
+  public static native void ConnectionManager_apply_route_document(long connectionManager, byte[] signedDocument, long key) throws Exception;
   public static native void ConnectionManager_clear_proxy(long connectionManager);
   public static native long ConnectionManager_new(int environment, String userAgent);
   public static native void ConnectionManager_on_network_change(long connectionManager);
+  public static native void ConnectionManager_set_clock_offset(long connectionManager, int offsetSeconds);
   public static native void ConnectionManager_set_proxy(long connectionManager, String host, int port) throws Exception;
+  public static native void ConnectionManager_set_tls1_3_only(long connectionManager, boolean tls1_3Only);
 
   public static native void CreateCallLinkCredentialPresentation_CheckValidContents(byte[] presentationBytes) throws Exception;
   public static native void CreateCallLinkCredentialPresentation_Verify(byte[] presentationBytes, byte[] roomId, long now, byte[] serverParamsBytes, byte[] callLinkParamsBytes) throws Exception;
@@ -251,6 +262,8 @@ public final class Native {
   public static native long DecryptionErrorMessage_GetTimestamp(long obj) throws Exception;
 
   public static native byte[] DeviceTransfer_GenerateCertificate(byte[] privateKey, String name, int daysToExpire) throws Exception;
+  public static native byte[] DeviceTransfer_GenerateCertificateWithParams(byte[] privateKey, String commonName, String organization, String organizationalUnit, int daysToExpire, int backdateSeconds, boolean randomSerialNumber) throws Exception;
+  public static native byte[] DeviceTransfer_GenerateEcPrivateKey();
   public static native byte[] DeviceTransfer_GeneratePrivateKey();
 
   public static native byte[] ECPrivateKey_Agree(long privateKey, long publicKey) throws Exception;
@@ -269,6 +282,22 @@ public final class Native {
   public static native byte[] ECPublicKey_Serialize(long obj) throws Exception;
   public static native boolean ECPublicKey_Verify(long key, byte[] message, byte[] signature) throws Exception;
 
+  public static native long Envelope_Deserialize(byte[] data) throws Exception;
+  public static native void Envelope_Destroy(long handle);
+  public static native byte[] Envelope_GetContent(long envelope);
+  public static native String Envelope_GetDestinationServiceId(long envelope);
+  public static native boolean Envelope_GetEphemeral(long envelope);
+  public static native byte[] Envelope_GetReportSpamToken(long envelope);
+  public static native String Envelope_GetServerGuid(long envelope);
+  public static native long Envelope_GetServerTimestamp(long envelope);
+  public static native int Envelope_GetSourceDeviceId(long envelope);
+  public static native String Envelope_GetSourceServiceId(long envelope);
+  public static native boolean Envelope_GetStory(long envelope);
+  public static native long Envelope_GetTimestamp(long envelope);
+  public static native int Envelope_GetType(long envelope);
+  public static native String Envelope_GetUpdatedPni(long envelope);
+  public static native boolean Envelope_GetUrgent(long envelope);
+
   public static native void ExpiringProfileKeyCredentialResponse_CheckValidContents(byte[] buffer) throws Exception;
 
   public static native void ExpiringProfileKeyCredential_CheckValidContents(byte[] buffer) throws Exception;
@@ -326,6 +355,10 @@ public final class Native {
 
   public static native byte[] HKDF_DeriveSecrets(int outputLength, byte[] ikm, byte[] label, byte[] salt) throws Exception;
 
+  public static native void HandleAccounting_SetEnabled(boolean enabled);
+  public static native void HandleAccounting_SetLeakThresholdSeconds(int thresholdSecs);
+  public static native String HandleAccounting_SnapshotJson();
+
   public static native void HsmEnclaveClient_CompleteHandshake(long cli, byte[] handshakeReceived) throws Exception;
   public static native void HsmEnclaveClient_Destroy(long handle);
   public static native byte[] HsmEnclaveClient_EstablishedRecv(long cli, byte[] receivedCiphertext) throws Exception;
@@ -349,6 +382,10 @@ public final class Native {
   public static native long IncrementalMac_Initialize(byte[] key, int chunkSize);
   public static native byte[] IncrementalMac_Update(long mac, byte[] bytes, int offset, int length);
 
+  public static native void Instrumentation_Reset();
+  public static native void Instrumentation_SetEnabled(boolean enabled);
+  public static native String Instrumentation_SnapshotJson();
+
   public static native void KyberKeyPair_Destroy(long handle);
   public static native long KyberKeyPair_Generate();
   public static native long KyberKeyPair_GetPublicKey(long keyPair);
@@ -376,6 +413,9 @@ public final class Native {
 
   public static native void Logger_Initialize(int maxLevel, Class loggerClass);
   public static native void Logger_SetMaxLevel(int maxLevel);
+  public static native void Logger_SetPanicReporter(PanicReporter reporter);
+  public static native void Logger_SetTargetLevel(String target, int maxLevel);
+  public static native void Logger_SetTargetRateLimit(String target, int maxPerSecond);
 
   public static native void LookupRequest_Destroy(long handle);
   public static native void LookupRequest_addAciAndAccessKey(long request, byte[] aci, byte[] accessKey) throws Exception;
@@ -403,6 +443,8 @@ public final class Native {
   public static native long PinHash_FromSalt(byte[] pin, byte[] salt) throws Exception;
   public static native long PinHash_FromUsernameMrenclave(byte[] pin, String username, byte[] mrenclave) throws Exception;
 
+  public static native int Pin_EstimateStrengthLevel(String pin) throws Exception;
+  public static native int Pin_EstimateStrengthWeaknesses(String pin) throws Exception;
   public static native String Pin_LocalHash(byte[] pin) throws Exception;
   public static native boolean Pin_VerifyLocalHash(String encodedHash, byte[] pin) throws Exception;
 
@@ -469,7 +511,7 @@ public final class Native {
   public static native void ProtocolAddress_Destroy(long handle);
   public static native int ProtocolAddress_DeviceId(long obj);
   public static native String ProtocolAddress_Name(long obj);
-  public static native long ProtocolAddress_New(String name, int deviceId);
+  public static native long ProtocolAddress_New(String name, int deviceId) throws Exception;
 
   public static native void ReceiptCredentialPresentation_CheckValidContents(byte[] buffer) throws Exception;
   public static native long ReceiptCredentialPresentation_GetReceiptExpirationTime(byte[] presentation);
@@ -498,8 +540,10 @@ public final class Native {
 
   public static native long SealedSessionCipher_DecryptToUsmc(byte[] ctext, IdentityKeyStore identityStore) throws Exception;
   public static native byte[] SealedSessionCipher_Encrypt(long destination, long content, IdentityKeyStore identityKeyStore) throws Exception;
+  public static native int SealedSessionCipher_MessageVersion(byte[] ctext) throws Exception;
   public static native byte[] SealedSessionCipher_MultiRecipientEncrypt(long[] recipients, long[] recipientSessions, byte[] excludedRecipients, long content, IdentityKeyStore identityKeyStore) throws Exception;
   public static native byte[] SealedSessionCipher_MultiRecipientMessageForSingleRecipient(byte[] encodedMultiRecipientMessage) throws Exception;
+  public static native byte[] SealedSessionCipher_RewrapV1AsV2(byte[] ctext, IdentityKeyStore identityStore) throws Exception;
 
   public static native long SenderCertificate_Deserialize(byte[] data) throws Exception;
   public static native void SenderCertificate_Destroy(long handle);
@@ -649,6 +693,8 @@ public final class Native {
   public static native void TokioAsyncContext_Destroy(long handle);
   public static native void TokioAsyncContext_cancel(long context, long rawCancellationId);
   public static native long TokioAsyncContext_new();
+  public static native long TokioAsyncContext_new_with_config(int workerThreads, String threadName, boolean useCurrentThread) throws Exception;
+  public static native void TokioAsyncContext_shutdown(long context, int timeoutMillis);
 
   public static native long UnidentifiedSenderMessageContent_Deserialize(byte[] data) throws Exception;
   public static native void UnidentifiedSenderMessageContent_Destroy(long handle);
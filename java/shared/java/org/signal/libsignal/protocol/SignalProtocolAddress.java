@@ -5,14 +5,20 @@
 
 package org.signal.libsignal.protocol;
 
+import static org.signal.libsignal.internal.FilterExceptions.filterExceptions;
+
 import org.signal.libsignal.internal.Native;
 import org.signal.libsignal.internal.NativeHandleGuard;
 
 public class SignalProtocolAddress implements NativeHandleGuard.Owner {
   private final long unsafeHandle;
 
+  /**
+   * @param deviceId Must be between 1 and 127, inclusive.
+   * @throws IllegalArgumentException if {@code deviceId} is out of range
+   */
   public SignalProtocolAddress(String name, int deviceId) {
-    this.unsafeHandle = Native.ProtocolAddress_New(name, deviceId);
+    this.unsafeHandle = filterExceptions(() -> Native.ProtocolAddress_New(name, deviceId));
   }
 
   public SignalProtocolAddress(ServiceId serviceId, int deviceId) {
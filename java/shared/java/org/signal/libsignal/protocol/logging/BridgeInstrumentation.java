@@ -0,0 +1,49 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.protocol.logging;
+
+import org.signal.libsignal.internal.Native;
+
+/**
+ * Opt-in timing of calls into libsignal's native code.
+ *
+ * <p>While enabled, every call records how long it ran, and asynchronous calls additionally record
+ * how long they waited before starting to run. Timings are aggregated per operation, so they can be
+ * collected in the field to find slow or blocked operations.
+ */
+public class BridgeInstrumentation {
+
+  private BridgeInstrumentation() {}
+
+  /**
+   * Turns recording on or off.
+   *
+   * <p>Turning recording off does not discard timings collected so far; use {@link #reset} for
+   * that.
+   */
+  public static void setEnabled(boolean enabled) {
+    Native.Instrumentation_SetEnabled(enabled);
+  }
+
+  /** Discards all recorded timings. */
+  public static void reset() {
+    Native.Instrumentation_Reset();
+  }
+
+  /**
+   * Returns the timings recorded so far, as a JSON object keyed by operation name (such as {@code
+   * SessionCipher_EncryptMessage}).
+   *
+   * <p>Each operation has {@code duration} and {@code queue_latency} histograms (the latter is
+   * empty for synchronous operations). Each histogram has {@code count}, {@code total_micros}, and
+   * {@code max_micros} fields, plus a {@code buckets} array: {@code buckets[0]} counts durations
+   * under 1µs, and {@code buckets[i]} counts durations from 2<sup>i-1</sup>µs up to 2<sup>i</sup>µs.
+   * The last bucket also counts anything longer.
+   */
+  public static String snapshotJson() {
+    return Native.Instrumentation_SnapshotJson();
+  }
+}
@@ -0,0 +1,54 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.protocol.logging;
+
+import org.signal.libsignal.internal.Native;
+
+/**
+ * Opt-in accounting of native objects held by Java wrappers, to help diagnose native memory growth.
+ *
+ * <p>While enabled, libsignal tracks every native object it hands to Java until the wrapper is
+ * closed or finalized.
+ */
+public class HandleAccounting {
+
+  private HandleAccounting() {}
+
+  /**
+   * Turns tracking on or off.
+   *
+   * <p>Only objects created while tracking is on are counted. Turning tracking off forgets all
+   * tracked objects.
+   */
+  public static void setEnabled(boolean enabled) {
+    Native.HandleAccounting_SetEnabled(enabled);
+  }
+
+  /**
+   * Logs (through {@link SignalProtocolLoggerProvider}) any tracked object that stays alive longer
+   * than {@code thresholdSeconds}, once per object.
+   *
+   * @param thresholdSeconds The age at which an object is reported as a possible leak, or 0 to turn
+   *     off leak detection.
+   */
+  public static void setLeakThresholdSeconds(int thresholdSeconds) {
+    if (thresholdSeconds < 0) {
+      throw new IllegalArgumentException("threshold must not be negative");
+    }
+    Native.HandleAccounting_SetLeakThresholdSeconds(thresholdSeconds);
+  }
+
+  /**
+   * Returns the live tracked objects as a JSON object keyed by type name (such as {@code
+   * SessionRecord}).
+   *
+   * <p>Each type has {@code count}, {@code approximate_bytes}, and {@code oldest_age_secs} fields.
+   * Sizes only count each object itself, not any data it owns.
+   */
+  public static String snapshotJson() {
+    return Native.HandleAccounting_SnapshotJson();
+  }
+}
@@ -80,7 +80,6 @@ public class Log {
     return sw.toString();
   }
 
-  @CalledFromNative
   private static void log(int priority, String tag, String msg) {
     SignalProtocolLogger logger = SignalProtocolLoggerProvider.getProvider();
 
@@ -88,4 +87,14 @@ public class Log {
       logger.log(priority, tag, msg);
     }
   }
+
+  @CalledFromNative
+  private static void log(
+      int priority, String tag, String target, String file, int line, String msg) {
+    SignalProtocolLogger logger = SignalProtocolLoggerProvider.getProvider();
+
+    if (logger != null) {
+      logger.log(priority, tag, target, file, line, msg);
+    }
+  }
 }
@@ -0,0 +1,26 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.protocol.logging;
+
+import org.signal.libsignal.internal.CalledFromNative;
+
+/**
+ * Receives reports of every panic (internal error) in libsignal, e.g. to forward them to a crash
+ * reporter.
+ *
+ * @see SignalProtocolLoggerProvider#setPanicReporter
+ */
+public interface PanicReporter {
+  /**
+   * Called synchronously on the panicking thread, which may not be a Java thread otherwise; it
+   * should return quickly and must not throw.
+   *
+   * @param location The location of the panic in the Rust sources, or null.
+   * @param backtrace A symbolized Rust backtrace, or null if none was captured.
+   */
+  @CalledFromNative
+  void reportPanic(String message, String location, String backtrace);
+}
@@ -0,0 +1,36 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.protocol.logging;
+
+import org.signal.libsignal.internal.CalledFromNative;
+
+/**
+ * Thrown when libsignal hits an internal error (a Rust panic) during a call.
+ *
+ * <p>This always indicates a bug in libsignal. The Rust source location and backtrace are included
+ * when available, so they can be attached to crash reports.
+ */
+public class RustPanicError extends AssertionError {
+  private final String location;
+  private final String rustBacktrace;
+
+  @CalledFromNative
+  public RustPanicError(String message, String location, String rustBacktrace) {
+    super(message);
+    this.location = location;
+    this.rustBacktrace = rustBacktrace;
+  }
+
+  /** The location of the panic in the Rust sources, as {@code file:line:column}, or null. */
+  public String getLocation() {
+    return location;
+  }
+
+  /** A symbolized Rust backtrace from the point of the panic, or null if none was captured. */
+  public String getRustBacktrace() {
+    return rustBacktrace;
+  }
+}
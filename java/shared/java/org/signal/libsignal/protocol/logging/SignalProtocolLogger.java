@@ -15,4 +15,19 @@ public interface SignalProtocolLogger {
   public static final int ASSERT = 7;
 
   public void log(int priority, String tag, String message);
+
+  /**
+   * Logs a message from libsignal's native code, with its source location as separate fields.
+   *
+   * <p>The default implementation formats the location into the message and calls {@link
+   * #log(int, String, String)}.
+   *
+   * @param target The Rust module that produced the message, such as {@code libsignal_net::chat}.
+   * @param file The source file that produced the message, or {@code null} if unknown.
+   * @param line The line within {@code file}, or 0 if unknown.
+   */
+  public default void log(
+      int priority, String tag, String target, String file, int line, String message) {
+    log(priority, tag, (file != null ? file : "<unknown>") + ":" + line + ": " + message);
+  }
 }
@@ -27,6 +27,62 @@ public class SignalProtocolLoggerProvider {
     Native.Logger_Initialize(maxLevel, Log.class);
   }
 
+  /**
+   * Overrides the most verbose level logged for a target in libsignal's native code.
+   *
+   * <p>A target is a Rust crate or module path, such as {@code libsignal_net} or {@code
+   * libsignal_net::chat}; the override also applies to any modules within it. When several
+   * overrides match, the most specific one wins.
+   *
+   * <p>The override replaces the level passed to {@link #initializeLogging} for that target, so it
+   * can make the target either more or less verbose. It never enables targets that libsignal
+   * doesn't log at all, such as its third-party dependencies.
+   *
+   * @param maxLevel The most verbose level that should be logged for {@code target}; logs at this
+   *     level and any more severe level are kept, and less severe ones are dropped. For example,
+   *     {@code WARN} keeps warnings and errors but drops {@code INFO} and below. Should be one of
+   *     the constants from {@link SignalProtocolLogger}.
+   */
+  public static void setTargetLevel(String target, int maxLevel) {
+    if (maxLevel < SignalProtocolLogger.VERBOSE || maxLevel > SignalProtocolLogger.ASSERT) {
+      throw new IllegalArgumentException("invalid log level");
+    }
+    Native.Logger_SetTargetLevel(target, maxLevel);
+  }
+
+  /** Removes an override set with {@link #setTargetLevel}. */
+  public static void clearTargetLevel(String target) {
+    Native.Logger_SetTargetLevel(target, 0);
+  }
+
+  /**
+   * Limits a target in libsignal's native code to {@code maxPerSecond} logs each second.
+   *
+   * <p>Logs over the limit are dropped, and a warning reporting how many were dropped is logged
+   * once the target is logging again. Targets are matched as in {@link #setTargetLevel}.
+   *
+   * @param maxPerSecond The number of logs allowed each second, or 0 to remove the limit.
+   */
+  public static void setTargetRateLimit(String target, int maxPerSecond) {
+    if (maxPerSecond < 0) {
+      throw new IllegalArgumentException("rate limit must not be negative");
+    }
+    Native.Logger_SetTargetRateLimit(target, maxPerSecond);
+  }
+
+  /**
+   * Registers a reporter to be told about every panic (internal error) in libsignal's native code,
+   * replacing any previous one.
+   *
+   * <p>Panics during a call are also thrown as {@link RustPanicError}, but the reporter sees panics
+   * on background threads as well.
+   *
+   * @param reporter The reporter to use, or null to stop reporting panics.
+   */
+  public static void setPanicReporter(PanicReporter reporter) {
+    Native.Logger_SetPanicReporter(reporter);
+  }
+
   public static SignalProtocolLogger getProvider() {
     return provider;
   }
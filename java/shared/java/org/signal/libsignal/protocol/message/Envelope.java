@@ -0,0 +1,194 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+package org.signal.libsignal.protocol.message;
+
+import static org.signal.libsignal.internal.FilterExceptions.filterExceptions;
+
+import java.util.Optional;
+import java.util.UUID;
+import org.signal.libsignal.internal.Native;
+import org.signal.libsignal.internal.NativeHandleGuard;
+import org.signal.libsignal.protocol.InvalidKeyException;
+import org.signal.libsignal.protocol.InvalidMessageException;
+import org.signal.libsignal.protocol.InvalidVersionException;
+import org.signal.libsignal.protocol.LegacyMessageException;
+import org.signal.libsignal.protocol.ServiceId;
+
+/**
+ * An envelope delivered by the server, checked for consistency before any of its content is
+ * decrypted.
+ *
+ * <p>Messages that aren't sealed sender always have a complete source address, and sealed sender
+ * messages never do. The content has already been checked to parse as the kind of message {@link
+ * #getType()} says it is.
+ */
+public final class Envelope implements NativeHandleGuard.Owner {
+  /** The server's {@code Envelope.Type} values that can appear in a valid envelope. */
+  public enum Type {
+    CIPHERTEXT(1),
+    PREKEY_BUNDLE(3),
+    SERVER_DELIVERY_RECEIPT(5),
+    UNIDENTIFIED_SENDER(6),
+    SENDERKEY_MESSAGE(7),
+    PLAINTEXT_CONTENT(8);
+
+    private final int value;
+
+    Type(int value) {
+      this.value = value;
+    }
+
+    public int getValue() {
+      return value;
+    }
+
+    static Type fromValue(int value) {
+      for (Type type : values()) {
+        if (type.value == value) {
+          return type;
+        }
+      }
+      throw new AssertionError("unexpected envelope type " + value);
+    }
+  }
+
+  private final long unsafeHandle;
+
+  @Override
+  @SuppressWarnings("deprecation")
+  protected void finalize() {
+    Native.Envelope_Destroy(this.unsafeHandle);
+  }
+
+  public long unsafeNativeHandleWithoutGuard() {
+    return unsafeHandle;
+  }
+
+  public Envelope(byte[] serialized)
+      throws InvalidMessageException,
+          InvalidVersionException,
+          LegacyMessageException,
+          InvalidKeyException {
+    try {
+      this.unsafeHandle =
+          filterExceptions(
+              InvalidMessageException.class,
+              InvalidVersionException.class,
+              LegacyMessageException.class,
+              InvalidKeyException.class,
+              () -> Native.Envelope_Deserialize(serialized));
+    } catch (IllegalArgumentException e) {
+      // The envelope's fields don't agree with each other.
+      throw new InvalidMessageException(e.getMessage(), e);
+    }
+  }
+
+  /** The sender, for every type of envelope except {@link Type#UNIDENTIFIED_SENDER}. */
+  public Optional<ServiceId> getSourceServiceId() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return parseServiceId(Native.Envelope_GetSourceServiceId(guard.nativeHandle()));
+    }
+  }
+
+  /** The sender's device, present whenever {@link #getSourceServiceId()} is. */
+  public Optional<Integer> getSourceDeviceId() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      int deviceId = Native.Envelope_GetSourceDeviceId(guard.nativeHandle());
+      return deviceId == -1 ? Optional.empty() : Optional.of(deviceId);
+    }
+  }
+
+  /** The local account identity the message was sent to, if the server said. */
+  public Optional<ServiceId> getDestinationServiceId() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return parseServiceId(Native.Envelope_GetDestinationServiceId(guard.nativeHandle()));
+    }
+  }
+
+  /** The timestamp assigned by the sender, in milliseconds since the epoch. */
+  public long getTimestamp() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return Native.Envelope_GetTimestamp(guard.nativeHandle());
+    }
+  }
+
+  /** When the server received the message, in milliseconds since the epoch. */
+  public Optional<Long> getServerTimestamp() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      long timestamp = Native.Envelope_GetServerTimestamp(guard.nativeHandle());
+      return timestamp == 0 ? Optional.empty() : Optional.of(timestamp);
+    }
+  }
+
+  /** The server's identifier for the message. */
+  public Optional<UUID> getServerGuid() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return Optional.ofNullable(Native.Envelope_GetServerGuid(guard.nativeHandle()))
+          .map(UUID::fromString);
+    }
+  }
+
+  public Type getType() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return Type.fromValue(Native.Envelope_GetType(guard.nativeHandle()));
+    }
+  }
+
+  /**
+   * The serialized message, to be decrypted according to {@link #getType()}.
+   *
+   * <p>Empty for a {@link Type#SERVER_DELIVERY_RECEIPT}.
+   */
+  public byte[] getContent() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return Native.Envelope_GetContent(guard.nativeHandle());
+    }
+  }
+
+  public boolean isUrgent() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return Native.Envelope_GetUrgent(guard.nativeHandle());
+    }
+  }
+
+  public boolean isStory() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return Native.Envelope_GetStory(guard.nativeHandle());
+    }
+  }
+
+  public boolean isEphemeral() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return Native.Envelope_GetEphemeral(guard.nativeHandle());
+    }
+  }
+
+  /** Set if the local account's PNI has changed. */
+  public Optional<ServiceId.Pni> getUpdatedPni() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      return parseServiceId(Native.Envelope_GetUpdatedPni(guard.nativeHandle()))
+          .map(id -> (ServiceId.Pni) id);
+    }
+  }
+
+  public Optional<byte[]> getReportSpamToken() {
+    try (NativeHandleGuard guard = new NativeHandleGuard(this)) {
+      byte[] token = Native.Envelope_GetReportSpamToken(guard.nativeHandle());
+      return token.length == 0 ? Optional.empty() : Optional.of(token);
+    }
+  }
+
+  private static Optional<ServiceId> parseServiceId(String serviceIdString) {
+    if (serviceIdString == null) {
+      return Optional.empty();
+    }
+    try {
+      return Optional.of(ServiceId.parseFromString(serviceIdString));
+    } catch (ServiceId.InvalidServiceIdException e) {
+      throw new AssertionError("envelope service IDs are validated when parsing", e);
+    }
+  }
+}
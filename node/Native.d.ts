@@ -179,7 +179,10 @@ export function CallLinkSecretParams_DecryptUserId(paramsBytes: Buffer, userId:
 export function CallLinkSecretParams_DeriveFromRootKey(rootKey: Buffer): Buffer;
 export function CallLinkSecretParams_GetPublicParams(paramsBytes: Buffer): Buffer;
 export function Cds2ClientState_New(mrenclave: Buffer, attestationMsg: Buffer, currentTimestamp: Timestamp): SgxClientState;
+export function CdsiLookupResult_takeResponse(result: Wrapper<CdsiLookupResult>): LookupResponse;
+export function CdsiLookupResult_token(result: Wrapper<CdsiLookupResult>): Buffer;
 export function CdsiLookup_complete(asyncRuntime: Wrapper<TokioAsyncContext>, lookup: Wrapper<CdsiLookup>): Promise<LookupResponse>;
+export function CdsiLookup_lookup(asyncRuntime: Wrapper<TokioAsyncContext>, connectionManager: Wrapper<ConnectionManager>, username: string, password: string, request: Wrapper<LookupRequest>): Promise<CdsiLookupResult>;
 export function CdsiLookup_new(asyncRuntime: Wrapper<TokioAsyncContext>, connectionManager: Wrapper<ConnectionManager>, username: string, password: string, request: Wrapper<LookupRequest>): Promise<CdsiLookup>;
 export function CdsiLookup_token(lookup: Wrapper<CdsiLookup>): Buffer;
 export function ChatService_SetListenerAuth(runtime: Wrapper<TokioAsyncContext>, chat: Wrapper<Chat>, makeListener: MakeChatListener | null): void;
@@ -198,11 +201,15 @@ export function CiphertextMessage_Type(msg: Wrapper<CiphertextMessage>): number;
 export function ComparableBackup_GetComparableString(backup: Wrapper<ComparableBackup>): string;
 export function ComparableBackup_GetUnknownFields(backup: Wrapper<ComparableBackup>): string[];
 export function ComparableBackup_ReadUnencrypted(stream: InputStream, len: bigint, purpose: number): Promise<ComparableBackup>;
+export function ConnectionAttempts_SnapshotJson(): string;
+export function ConnectionManager_apply_route_document(connectionManager: Wrapper<ConnectionManager>, signedDocument: Buffer, key: Wrapper<PublicKey>): void;
 export function ConnectionManager_clear_proxy(connectionManager: Wrapper<ConnectionManager>): void;
 export function ConnectionManager_new(environment: number, userAgent: string): ConnectionManager;
 export function ConnectionManager_on_network_change(connectionManager: Wrapper<ConnectionManager>): void;
 export function ConnectionManager_set_ipv6_enabled(connectionManager: Wrapper<ConnectionManager>, ipv6Enabled: boolean): void;
+export function ConnectionManager_set_clock_offset(connectionManager: Wrapper<ConnectionManager>, offsetSeconds: number): void;
 export function ConnectionManager_set_proxy(connectionManager: Wrapper<ConnectionManager>, host: string, port: number): void;
+export function ConnectionManager_set_tls1_3_only(connectionManager: Wrapper<ConnectionManager>, tls13Only: boolean): void;
 export function CreateCallLinkCredentialPresentation_CheckValidContents(presentationBytes: Buffer): void;
 export function CreateCallLinkCredentialPresentation_Verify(presentationBytes: Buffer, roomId: Buffer, now: Timestamp, serverParamsBytes: Buffer, callLinkParamsBytes: Buffer): void;
 export function CreateCallLinkCredentialRequestContext_CheckValidContents(contextBytes: Buffer): void;
@@ -223,6 +230,20 @@ export function DecryptionErrorMessage_GetDeviceId(obj: Wrapper<DecryptionErrorM
 export function DecryptionErrorMessage_GetRatchetKey(m: Wrapper<DecryptionErrorMessage>): PublicKey | null;
 export function DecryptionErrorMessage_GetTimestamp(obj: Wrapper<DecryptionErrorMessage>): Timestamp;
 export function DecryptionErrorMessage_Serialize(obj: Wrapper<DecryptionErrorMessage>): Buffer;
+export function Envelope_Deserialize(data: Buffer): Envelope;
+export function Envelope_GetContent(envelope: Wrapper<Envelope>): Buffer;
+export function Envelope_GetDestinationServiceId(envelope: Wrapper<Envelope>): string | null;
+export function Envelope_GetEphemeral(envelope: Wrapper<Envelope>): boolean;
+export function Envelope_GetReportSpamToken(envelope: Wrapper<Envelope>): Buffer;
+export function Envelope_GetServerGuid(envelope: Wrapper<Envelope>): string | null;
+export function Envelope_GetServerTimestamp(envelope: Wrapper<Envelope>): Timestamp;
+export function Envelope_GetSourceDeviceId(envelope: Wrapper<Envelope>): number | null;
+export function Envelope_GetSourceServiceId(envelope: Wrapper<Envelope>): string | null;
+export function Envelope_GetStory(envelope: Wrapper<Envelope>): boolean;
+export function Envelope_GetTimestamp(envelope: Wrapper<Envelope>): Timestamp;
+export function Envelope_GetType(envelope: Wrapper<Envelope>): number;
+export function Envelope_GetUpdatedPni(envelope: Wrapper<Envelope>): string | null;
+export function Envelope_GetUrgent(envelope: Wrapper<Envelope>): boolean;
 export function ExpiringProfileKeyCredentialResponse_CheckValidContents(buffer: Buffer): void;
 export function ExpiringProfileKeyCredential_CheckValidContents(buffer: Buffer): void;
 export function ExpiringProfileKeyCredential_GetExpirationTime(credential: Serialized<ExpiringProfileKeyCredential>): Timestamp;
@@ -281,6 +302,9 @@ export function IncrementalMac_CalculateChunkSize(dataSize: number): number;
 export function IncrementalMac_Finalize(mac: Wrapper<IncrementalMac>): Buffer;
 export function IncrementalMac_Initialize(key: Buffer, chunkSize: number): IncrementalMac;
 export function IncrementalMac_Update(mac: Wrapper<IncrementalMac>, bytes: Buffer, offset: number, length: number): Buffer;
+export function Instrumentation_Reset(): void;
+export function Instrumentation_SetEnabled(enabled: boolean): void;
+export function Instrumentation_SnapshotJson(): string;
 export function KyberKeyPair_Generate(): KyberKeyPair;
 export function KyberKeyPair_GetPublicKey(keyPair: Wrapper<KyberKeyPair>): KyberPublicKey;
 export function KyberKeyPair_GetSecretKey(keyPair: Wrapper<KyberKeyPair>): KyberSecretKey;
@@ -387,8 +411,10 @@ export function SealedSenderMultiRecipientMessage_Parse(buffer: Buffer): SealedS
 export function SealedSender_DecryptMessage(message: Buffer, trustRoot: Wrapper<PublicKey>, timestamp: Timestamp, localE164: string | null, localUuid: string, localDeviceId: number, sessionStore: SessionStore, identityStore: IdentityKeyStore, prekeyStore: PreKeyStore, signedPrekeyStore: SignedPreKeyStore, kyberPrekeyStore: KyberPreKeyStore): Promise<SealedSenderDecryptionResult>;
 export function SealedSender_DecryptToUsmc(ctext: Buffer, identityStore: IdentityKeyStore): Promise<UnidentifiedSenderMessageContent>;
 export function SealedSender_Encrypt(destination: Wrapper<ProtocolAddress>, content: Wrapper<UnidentifiedSenderMessageContent>, identityKeyStore: IdentityKeyStore): Promise<Buffer>;
+export function SealedSender_MessageVersion(ctext: Buffer): number;
 export function SealedSender_MultiRecipientEncrypt(recipients: Wrapper<ProtocolAddress>[], recipientSessions: Wrapper<SessionRecord>[], excludedRecipients: Buffer, content: Wrapper<UnidentifiedSenderMessageContent>, identityKeyStore: IdentityKeyStore): Promise<Buffer>;
 export function SealedSender_MultiRecipientMessageForSingleRecipient(encodedMultiRecipientMessage: Buffer): Buffer;
+export function SealedSender_RewrapV1AsV2(ctext: Buffer, identityStore: IdentityKeyStore): Promise<Buffer>;
 export function SenderCertificate_Deserialize(data: Buffer): SenderCertificate;
 export function SenderCertificate_GetCertificate(obj: Wrapper<SenderCertificate>): Buffer;
 export function SenderCertificate_GetDeviceId(obj: Wrapper<SenderCertificate>): number;
@@ -535,6 +561,8 @@ export function TESTING_ServerMessageAck_Create(): ServerMessageAck;
 export function TESTING_TestingHandleType_getValue(handle: Wrapper<TestingHandleType>): number;
 export function TokioAsyncContext_cancel(context: Wrapper<TokioAsyncContext>, rawCancellationId: bigint): void;
 export function TokioAsyncContext_new(): TokioAsyncContext;
+export function TokioAsyncContext_new_with_config(workerThreads: number, threadName: string, useCurrentThread: boolean): TokioAsyncContext;
+export function TokioAsyncContext_shutdown(context: Wrapper<TokioAsyncContext>, timeoutMillis: number): void;
 export function UnidentifiedSenderMessageContent_Deserialize(data: Buffer): UnidentifiedSenderMessageContent;
 export function UnidentifiedSenderMessageContent_GetContentHint(m: Wrapper<UnidentifiedSenderMessageContent>): number;
 export function UnidentifiedSenderMessageContent_GetContents(obj: Wrapper<UnidentifiedSenderMessageContent>): Buffer;
@@ -556,15 +584,20 @@ export function ValidatingMac_Initialize(key: Buffer, chunkSize: number, digests
 export function ValidatingMac_Update(mac: Wrapper<ValidatingMac>, bytes: Buffer, offset: number, length: number): number;
 export function WebpSanitizer_Sanitize(input: SyncInputStream): void;
 export function initLogger(maxLevel: LogLevel, callback: (level: LogLevel, target: string, file: string | null, line: number | null, message: string) => void): void
+export function setLogTargetLevel(target: string, maxLevel: LogLevel | null): void
+export function setLogTargetRateLimit(target: string, maxPerSecond: number | null): void
+export function setPanicReporter(callback: ((message: string, location: string | null, backtrace: string | null) => void) | null): void
 export function test_only_fn_returns_123(): number;
 interface Aes256GcmSiv { readonly __type: unique symbol; }
 interface CdsiLookup { readonly __type: unique symbol; }
+interface CdsiLookupResult { readonly __type: unique symbol; }
 interface Chat { readonly __type: unique symbol; }
 interface CiphertextMessage { readonly __type: unique symbol; }
 interface ComparableBackup { readonly __type: unique symbol; }
 interface ComparableBackup { readonly __type: unique symbol; }
 interface ConnectionManager { readonly __type: unique symbol; }
 interface DecryptionErrorMessage { readonly __type: unique symbol; }
+interface Envelope { readonly __type: unique symbol; }
 interface ExpiringProfileKeyCredential { readonly __type: unique symbol; }
 interface ExpiringProfileKeyCredentialResponse { readonly __type: unique symbol; }
 interface Fingerprint { readonly __type: unique symbol; }
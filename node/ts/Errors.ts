@@ -194,6 +194,8 @@ export type InvalidUsernameLinkEncryptedData = LibSignalErrorCommon & {
 
 export type IoError = LibSignalErrorCommon & {
   code: ErrorCode.IoError;
+  /** The HTTP status sent by a server that rejected the request, if any. */
+  readonly httpStatus?: number;
 };
 
 export type CdsiInvalidTokenError = LibSignalErrorCommon & {
@@ -0,0 +1,60 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+/**
+ * Opt-in timing of calls into libsignal's native code.
+ *
+ * While enabled, every call records how long it ran, and asynchronous calls additionally record how
+ * long they waited before starting to run. Timings are aggregated per operation, so they can be
+ * collected in the field to find slow or blocked operations.
+ *
+ * @module Instrumentation
+ */
+
+import * as Native from '../Native';
+
+/**
+ * A histogram of durations, in microseconds.
+ *
+ * `buckets[0]` counts durations under 1µs, and `buckets[i]` counts durations from 2^(i-1)µs up to
+ * 2^i µs. The last bucket also counts anything longer.
+ */
+export type Histogram = {
+  count: number;
+  total_micros: number;
+  max_micros: number;
+  buckets: number[];
+};
+
+export type OperationTimings = {
+  duration: Histogram;
+  /** Empty for synchronous operations. */
+  queue_latency: Histogram;
+};
+
+/**
+ * Turns recording on or off.
+ *
+ * Turning recording off does not discard timings collected so far; use {@link reset} for that.
+ */
+export function setEnabled(enabled: boolean): void {
+  Native.Instrumentation_SetEnabled(enabled);
+}
+
+/** Discards all recorded timings. */
+export function reset(): void {
+  Native.Instrumentation_Reset();
+}
+
+/**
+ * Returns the timings recorded so far, keyed by operation name (such as
+ * `SessionCipher_EncryptMessage`).
+ */
+export function snapshot(): Record<string, OperationTimings> {
+  return JSON.parse(Native.Instrumentation_SnapshotJson()) as Record<
+    string,
+    OperationTimings
+  >;
+}
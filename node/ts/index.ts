@@ -8,7 +8,7 @@ import * as uuid from 'uuid';
 import * as Errors from './Errors';
 export * from './Errors';
 
-import { Aci, ProtocolAddress, ServiceId } from './Address';
+import { Aci, Pni, ProtocolAddress, ServiceId } from './Address';
 export * from './Address';
 
 export * as usernames from './usernames';
@@ -17,6 +17,8 @@ export * as io from './io';
 
 export * as Net from './net';
 
+export * as Instrumentation from './Instrumentation';
+
 export * as Mp4Sanitizer from './Mp4Sanitizer';
 export * as WebpSanitizer from './WebpSanitizer';
 
@@ -45,6 +47,16 @@ export enum ContentHint {
   Implicit = 2,
 }
 
+// This enum must be kept in sync with the server's Envelope.Type (see service.proto).
+export enum EnvelopeType {
+  Ciphertext = 1,
+  PreKeyBundle = 3,
+  ServerDeliveryReceipt = 5,
+  UnidentifiedSender = 6,
+  SenderKeyMessage = 7,
+  PlaintextContent = 8,
+}
+
 export type Uuid = string;
 
 export class HKDF {
@@ -1504,6 +1516,99 @@ export class DecryptionErrorMessage {
   }
 }
 
+/**
+ * An envelope delivered by the server, checked for consistency before any of its content is
+ * decrypted.
+ *
+ * Messages that aren't sealed sender always have a complete source address, and sealed sender
+ * messages never do. The content has already been checked to parse as the kind of message
+ * {@link #type} says it is.
+ */
+export class Envelope {
+  readonly _nativeHandle: Native.Envelope;
+
+  private constructor(nativeHandle: Native.Envelope) {
+    this._nativeHandle = nativeHandle;
+  }
+
+  static deserialize(buffer: Buffer): Envelope {
+    return new Envelope(Native.Envelope_Deserialize(buffer));
+  }
+
+  /** The sender, for every type of envelope except {@link EnvelopeType.UnidentifiedSender}. */
+  sourceServiceId(): ServiceId | null {
+    const serviceId = Native.Envelope_GetSourceServiceId(this);
+    return serviceId === null
+      ? null
+      : ServiceId.parseFromServiceIdString(serviceId);
+  }
+
+  /** The sender's device, present whenever {@link #sourceServiceId} is. */
+  sourceDeviceId(): number | null {
+    return Native.Envelope_GetSourceDeviceId(this);
+  }
+
+  /** The local account identity the message was sent to, if the server said. */
+  destinationServiceId(): ServiceId | null {
+    const serviceId = Native.Envelope_GetDestinationServiceId(this);
+    return serviceId === null
+      ? null
+      : ServiceId.parseFromServiceIdString(serviceId);
+  }
+
+  /** The timestamp assigned by the sender, in milliseconds since the epoch. */
+  timestamp(): number {
+    return Native.Envelope_GetTimestamp(this);
+  }
+
+  /** When the server received the message, in milliseconds since the epoch. */
+  serverTimestamp(): number | null {
+    const timestamp = Native.Envelope_GetServerTimestamp(this);
+    return timestamp === 0 ? null : timestamp;
+  }
+
+  /** The server's identifier for the message. */
+  serverGuid(): Uuid | null {
+    return Native.Envelope_GetServerGuid(this);
+  }
+
+  type(): EnvelopeType {
+    return Native.Envelope_GetType(this) as EnvelopeType;
+  }
+
+  /**
+   * The serialized message, to be decrypted according to {@link #type}.
+   *
+   * Empty for a {@link EnvelopeType.ServerDeliveryReceipt}.
+   */
+  content(): Buffer {
+    return Native.Envelope_GetContent(this);
+  }
+
+  isUrgent(): boolean {
+    return Native.Envelope_GetUrgent(this);
+  }
+
+  isStory(): boolean {
+    return Native.Envelope_GetStory(this);
+  }
+
+  isEphemeral(): boolean {
+    return Native.Envelope_GetEphemeral(this);
+  }
+
+  /** Set if the local account's PNI has changed. */
+  updatedPni(): Pni | null {
+    const pni = Native.Envelope_GetUpdatedPni(this);
+    return pni === null ? null : Pni.parseFromServiceIdString(pni);
+  }
+
+  reportSpamToken(): Buffer | null {
+    const token = Native.Envelope_GetReportSpamToken(this);
+    return token.length === 0 ? null : token;
+  }
+}
+
 export function processPreKeyBundle(
   bundle: PreKeyBundle,
   address: ProtocolAddress,
@@ -1780,6 +1885,21 @@ export enum LogLevel {
   Trace,
 }
 
+function toNativeLogLevel(level: LogLevel): Native.LogLevel {
+  switch (level) {
+    case LogLevel.Error:
+      return Native.LogLevel.Error;
+    case LogLevel.Warn:
+      return Native.LogLevel.Warn;
+    case LogLevel.Info:
+      return Native.LogLevel.Info;
+    case LogLevel.Debug:
+      return Native.LogLevel.Debug;
+    case LogLevel.Trace:
+      return Native.LogLevel.Trace;
+  }
+}
+
 export function initLogger(
   maxLevel: LogLevel,
   callback: (
@@ -1790,26 +1910,8 @@ export function initLogger(
     message: string
   ) => void
 ): void {
-  let nativeMaxLevel: Native.LogLevel;
-  switch (maxLevel) {
-    case LogLevel.Error:
-      nativeMaxLevel = Native.LogLevel.Error;
-      break;
-    case LogLevel.Warn:
-      nativeMaxLevel = Native.LogLevel.Warn;
-      break;
-    case LogLevel.Info:
-      nativeMaxLevel = Native.LogLevel.Info;
-      break;
-    case LogLevel.Debug:
-      nativeMaxLevel = Native.LogLevel.Debug;
-      break;
-    case LogLevel.Trace:
-      nativeMaxLevel = Native.LogLevel.Trace;
-      break;
-  }
   Native.initLogger(
-    nativeMaxLevel,
+    toNativeLogLevel(maxLevel),
     (nativeLevel, target, file, line, message) => {
       let level: LogLevel;
       switch (nativeLevel) {
@@ -1843,3 +1945,60 @@ export function initLogger(
     }
   );
 }
+
+/**
+ * Overrides the most verbose level logged for `target` (a Rust crate or module path, such as
+ * `libsignal_net` or `libsignal_net::chat`) and any modules within it.
+ *
+ * The most specific override wins. Pass `null` to remove an override.
+ */
+export function setLogTargetLevel(
+  target: string,
+  maxLevel: LogLevel | null
+): void {
+  Native.setLogTargetLevel(
+    target,
+    maxLevel === null ? null : toNativeLogLevel(maxLevel)
+  );
+}
+
+/**
+ * Limits `target` and any modules within it to `maxPerSecond` logs each second.
+ *
+ * Logs over the limit are dropped, and a warning reporting how many were dropped is logged once
+ * the target is logging again. Pass `null` to remove a limit.
+ */
+export function setLogTargetRateLimit(
+  target: string,
+  maxPerSecond: number | null
+): void {
+  Native.setLogTargetRateLimit(target, maxPerSecond);
+}
+
+/** Information about a panic (an internal error) in libsignal. */
+export type PanicReport = {
+  message: string;
+  /** The location of the panic in the Rust sources, as `file:line:column`. */
+  location: string | null;
+  /** A symbolized Rust backtrace from the point of the panic, if one could be captured. */
+  backtrace: string | null;
+};
+
+/**
+ * Registers a callback to be told about every panic in libsignal, e.g. to forward it to a crash
+ * reporter. Pass `null` to stop reporting panics.
+ *
+ * The callback is invoked asynchronously, since the panic may have happened on any thread. Panics
+ * in asynchronous operations also reject the operation's promise with an Error that has a
+ * `rustBacktrace` property, when a backtrace is available.
+ */
+export function setPanicReporter(
+  callback: ((report: PanicReport) => void) | null
+): void {
+  Native.setPanicReporter(
+    callback === null
+      ? null
+      : (message, location, backtrace) =>
+          callback({ message, location, backtrace })
+  );
+}
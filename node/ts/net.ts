@@ -70,6 +70,24 @@ export function newNativeHandle<T>(handle: T): Wrapper<T> {
   };
 }
 
+/**
+ * Settings for the background threads libsignal uses for network operations.
+ *
+ * By default, one worker thread is used per CPU core. On constrained devices, consider limiting
+ * the number of worker threads, or running everything on a single thread with `useCurrentThread`.
+ */
+export type AsyncRuntimeConfig = {
+  /** The number of worker threads. Ignored if `useCurrentThread` is set. */
+  workerThreads?: number;
+  /** The name given to libsignal's threads. */
+  threadName?: string;
+  /**
+   * Whether to run all operations on a single background thread instead of a pool of worker
+   * threads.
+   */
+  useCurrentThread?: boolean;
+};
+
 /** Low-level async runtime control, mostly just exported for testing. */
 export class TokioAsyncContext {
   readonly _nativeHandle: Native.TokioAsyncContext;
@@ -137,7 +155,8 @@ export interface ChatServiceListener extends ConnectionEventsListener {
   /**
    * Called when the server delivers an incoming message to the client.
    *
-   * `timestamp` is in milliseconds.
+   * `envelope` is the server's serialized envelope; use `Envelope.deserialize` to validate
+   * and parse it rather than decoding it by hand. `timestamp` is in milliseconds.
    *
    * If `ack`'s `send` method is not called, the server will leave this message in the message
    * queue and attempt to deliver it again in the future.
@@ -412,14 +431,37 @@ export class Net {
    */
   svr3: Svr3Client;
 
-  constructor(env: Environment, userAgent: string) {
-    this.asyncContext = new TokioAsyncContext(Native.TokioAsyncContext_new());
+  constructor(
+    env: Environment,
+    userAgent: string,
+    runtimeConfig?: AsyncRuntimeConfig
+  ) {
+    this.asyncContext = new TokioAsyncContext(
+      runtimeConfig === undefined
+        ? Native.TokioAsyncContext_new()
+        : Native.TokioAsyncContext_new_with_config(
+            runtimeConfig.workerThreads ?? 0,
+            runtimeConfig.threadName ?? 'libsignal-tokio-worker',
+            runtimeConfig.useCurrentThread ?? false
+          )
+    );
     this.connectionManager = newNativeHandle(
       Native.ConnectionManager_new(env, userAgent)
     );
     this.svr3 = new Svr3ClientImpl(this.asyncContext, this.connectionManager);
   }
 
+  /**
+   * Cancels all outstanding operations and stops libsignal's background threads.
+   *
+   * Blocks for up to `timeoutMillis` while cancelled operations finish, rejecting with a
+   * `Cancelled` error. Operations started afterwards are rejected the same way. Calling this more
+   * than once has no further effect.
+   */
+  shutdown(timeoutMillis: number): void {
+    Native.TokioAsyncContext_shutdown(this.asyncContext, timeoutMillis);
+  }
+
   /**
    * Creates a new instance of {@link AuthenticatedChatService}.
    *
@@ -528,9 +570,9 @@ export class Net {
       returnAcisWithoutUaks
     );
 
-    const lookup = await this.asyncContext.makeCancellable(
+    const result = await this.asyncContext.makeCancellable(
       abortSignal,
-      Native.CdsiLookup_new(
+      Native.CdsiLookup_lookup(
         this.asyncContext,
         this.connectionManager,
         username,
@@ -538,10 +580,7 @@ export class Net {
         request
       )
     );
-    return await this.asyncContext.makeCancellable(
-      abortSignal,
-      Native.CdsiLookup_complete(this.asyncContext, newNativeHandle(lookup))
-    );
+    return Native.CdsiLookupResult_takeResponse(newNativeHandle(result));
   }
 }
 
@@ -2093,6 +2093,45 @@ describe('SignalClient', () => {
     );
   });
 
+  it('Envelope', () => {
+    // A PLAINTEXT_CONTENT envelope from device 2 of `source` at timestamp 1000, with a server GUID.
+    const source = '9d0652a3-dcc3-4d11-975f-74d61598733f';
+    const plaintextEnvelope = Buffer.from(
+      '08085a2439643036353261332d646363332d346431312d393735662d373464363135393837333366' +
+        '380228e8074202c0804a2463346431366432662d346235632d346431322d613334632d35643462' +
+        '6466613064393565',
+      'hex'
+    );
+    const envelope = SignalClient.Envelope.deserialize(plaintextEnvelope);
+    assert.equal(envelope.type(), SignalClient.EnvelopeType.PlaintextContent);
+    assert.equal(envelope.sourceServiceId()?.getServiceIdString(), source);
+    assert.equal(envelope.sourceDeviceId(), 2);
+    assert.isNull(envelope.destinationServiceId());
+    assert.equal(envelope.timestamp(), 1000);
+    assert.isNull(envelope.serverTimestamp());
+    assert.equal(envelope.serverGuid(), 'c4d16d2f-4b5c-4d12-a34c-5d4bdfa0d95e');
+    assert.isTrue(envelope.isUrgent());
+    assert.isFalse(envelope.isStory());
+    assert.isFalse(envelope.isEphemeral());
+    assert.isNull(envelope.updatedPni());
+    assert.isNull(envelope.reportSpamToken());
+    assert.deepEqual(envelope.content(), Buffer.of(0xc0, 0x80));
+    assert.deepEqual(
+      SignalClient.PlaintextContent.deserialize(envelope.content()).serialize(),
+      envelope.content()
+    );
+
+    // The same, but missing the source device ID.
+    const incompleteSourceEnvelope = Buffer.from(
+      '08085a2439643036353261332d646363332d346431312d393735662d373464363135393837333366' +
+        '28e8074202c080',
+      'hex'
+    );
+    assert.throws(() =>
+      SignalClient.Envelope.deserialize(incompleteSourceEnvelope)
+    );
+  });
+
   it('AES-GCM-SIV test vector', () => {
     // RFC 8452, appendix C.2
     const key = Buffer.from(
@@ -109,6 +109,7 @@ pub struct Handshake {
     handshake: snow::HandshakeState,
     initial_request: Vec<u8>,
     claims: Claims,
+    pattern: &'static str,
 }
 
 impl Handshake {
@@ -129,16 +130,46 @@ impl Handshake {
         })
     }
 
+    /// Restarts the handshake with `prologue` mixed into the Noise handshake hash.
+    ///
+    /// The enclave has to use the same prologue for the handshake to complete, which binds
+    /// values exchanged outside the handshake (such as a negotiated protocol version) to the
+    /// attested connection.
+    pub fn with_prologue(self, prologue: &[u8]) -> Result<Self> {
+        let (handshake, initial_request) =
+            Self::start(&self.claims.public_key, self.pattern, prologue)?;
+        Ok(Self {
+            handshake,
+            initial_request,
+            ..self
+        })
+    }
+
     pub(crate) fn with_claims(claims: Claims, typ: HandshakeType) -> Result<UnvalidatedHandshake> {
         let pattern = match typ {
             HandshakeType::PreQuantum => client_connection::NOISE_PATTERN,
             HandshakeType::PostQuantum => client_connection::NOISE_PATTERN_HFS,
         };
+        let (handshake, initial_request) = Self::start(&claims.public_key, pattern, &[])?;
+        Ok(UnvalidatedHandshake(Self {
+            handshake,
+            initial_request,
+            claims,
+            pattern,
+        }))
+    }
+
+    fn start(
+        public_key: &[u8],
+        pattern: &'static str,
+        prologue: &[u8],
+    ) -> Result<(snow::HandshakeState, Vec<u8>)> {
         let mut handshake = snow::Builder::with_resolver(
             pattern.parse().expect("valid"),
             Box::new(snow_resolver::Resolver),
         )
-        .remote_public_key(&claims.public_key)
+        .remote_public_key(public_key)
+        .prologue(prologue)
         .build_initiator()
         .map_err(|_| {
             // The only thing that can go wrong is that claims.public_key is invalid, which isn't a
@@ -159,11 +190,23 @@ impl Handshake {
             .write_message(&[], &mut initial_request)
             .expect("properly sized");
         initial_request.truncate(size);
-        Ok(UnvalidatedHandshake(Self {
-            handshake,
-            initial_request,
-            claims,
-        }))
+        Ok((handshake, initial_request))
+    }
+}
+
+#[cfg(feature = "test-support")]
+impl Handshake {
+    /// Starts a handshake with an enclave whose Noise public key is trusted without attestation.
+    ///
+    /// This exists so that tests can stand up an in-process enclave; it must never be used to
+    /// talk to a real server.
+    pub fn for_testing_with_public_key(public_key: &[u8]) -> Result<Self> {
+        let claims = Claims {
+            public_key: public_key.to_vec(),
+            raft_group_config: None,
+            custom: HashMap::default(),
+        };
+        Ok(Self::with_claims(claims, HandshakeType::PreQuantum)?.skip_raft_validation())
     }
 }
 
@@ -17,6 +17,7 @@ use libsignal_bridge_testing::*;
 use libsignal_protocol::*;
 
 pub mod logging;
+pub mod panics;
 
 #[no_mangle]
 pub unsafe extern "C" fn signal_print_ptr(p: *const std::ffi::c_void) {
@@ -153,6 +154,24 @@ pub unsafe extern "C" fn signal_error_get_tries_remaining(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn signal_error_get_http_status(
+    err: *const SignalFfiError,
+    out: *mut u16,
+) -> *mut SignalFfiError {
+    let err = AssertUnwindSafe(err);
+    run_ffi_safe(|| {
+        let err = err.as_ref().ok_or(NullPointerError)?;
+        let value = err.provide_http_status().map_err(|_| {
+            SignalProtocolError::InvalidArgument(format!(
+                "cannot get http_status from error ({})",
+                err
+            ))
+        })?;
+        write_result_to(out, value)
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn signal_error_get_unknown_fields(
     err: *const SignalFfiError,
@@ -174,6 +193,24 @@ pub unsafe extern "C" fn signal_error_get_unknown_fields(
     })
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn signal_error_get_backtrace(
+    err: *const SignalFfiError,
+    out: *mut *const c_char,
+) -> *mut SignalFfiError {
+    let err = AssertUnwindSafe(err);
+    run_ffi_safe(|| {
+        let err = err.as_ref().ok_or(NullPointerError)?;
+        let value = err.provide_backtrace().map_err(|_| {
+            SignalProtocolError::InvalidArgument(format!(
+                "cannot get backtrace from error ({})",
+                err
+            ))
+        })?;
+        write_result_to(out, value)
+    })
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn signal_error_free(err: *mut SignalFfiError) {
     if !err.is_null() {
@@ -231,7 +268,7 @@ pub unsafe extern "C" fn signal_sealed_session_cipher_decrypt(
             Timestamp::from_epoch_millis(timestamp),
             local_e164,
             local_uuid,
-            local_device_id.into(),
+            DeviceId::try_from(local_device_id).map_err(SignalProtocolError::from)?,
             &mut identity_store,
             &mut session_store,
             &mut prekey_store,
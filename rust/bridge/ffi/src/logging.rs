@@ -3,7 +3,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use std::ffi::{c_char, c_void, CString};
+use std::ffi::{c_char, c_void, CStr, CString};
+
+use libsignal_bridge::logging::RateLimitDecision;
 
 #[repr(C)]
 pub enum LogLevel {
@@ -63,6 +65,33 @@ pub struct FfiLogger {
 unsafe impl Send for FfiLogger {}
 unsafe impl Sync for FfiLogger {}
 
+impl FfiLogger {
+    fn log_impl(
+        &self,
+        target: &str,
+        level: log::Level,
+        file: Option<&str>,
+        line: Option<u32>,
+        message: &str,
+    ) {
+        let target = CString::new(target).expect("no 0 bytes in log target");
+        let file = file.map(|file| CString::new(file).expect("no 0 bytes in file"));
+        let message = CString::new(message).unwrap_or_else(|_| {
+            CString::new(message.replace('\0', "\\0")).expect("We escaped any NULLs")
+        });
+        (self.log)(
+            self.ctx,
+            target.as_ptr(),
+            level.into(),
+            file.as_ref()
+                .map(|file| file.as_ptr())
+                .unwrap_or(std::ptr::null()),
+            line.unwrap_or(0),
+            message.as_ptr(),
+        );
+    }
+}
+
 impl log::Log for FfiLogger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
         libsignal_bridge::logging::log_enabled_in_apps(metadata)
@@ -72,25 +101,28 @@ impl log::Log for FfiLogger {
         if !libsignal_bridge::logging::log_enabled_in_apps(record.metadata()) {
             return;
         }
+        let decision = libsignal_bridge::logging::check_rate_limit(record.target());
+        if decision == RateLimitDecision::Drop {
+            return;
+        }
 
-        let target = CString::new(record.target()).expect("no 0 bytes in log target");
-        let file = record
-            .file()
-            .map(|file| CString::new(file).expect("no 0 bytes in file"));
-        let message = CString::new(record.args().to_string()).unwrap_or_else(|_| {
-            CString::new(record.args().to_string().replace('\0', "\\0"))
-                .expect("We escaped any NULLs")
-        });
-        (self.log)(
-            self.ctx,
-            target.as_ptr(),
-            record.level().into(),
-            file.as_ref()
-                .map(|file| file.as_ptr())
-                .unwrap_or(std::ptr::null()),
-            record.line().unwrap_or(0),
-            message.as_ptr(),
+        self.log_impl(
+            record.target(),
+            record.level(),
+            record.file(),
+            record.line(),
+            &record.args().to_string(),
         );
+
+        if let Some(message) = decision.dropped_logs_message(record.target()) {
+            self.log_impl(
+                "libsignal_ffi",
+                log::Level::Warn,
+                Some(file!()),
+                Some(line!()),
+                &message,
+            );
+        }
     }
 
     fn flush(&self) {
@@ -102,7 +134,7 @@ impl log::Log for FfiLogger {
 pub unsafe extern "C" fn signal_init_logger(max_level: LogLevel, logger: FfiLogger) -> bool {
     match log::set_logger(Box::leak(Box::new(logger))) {
         Ok(_) => {
-            log::set_max_level(log::Level::from(max_level).to_level_filter());
+            libsignal_bridge::logging::set_max_level(log::Level::from(max_level).to_level_filter());
             log::info!(
                 "Initializing libsignal version:{}",
                 env!("CARGO_PKG_VERSION")
@@ -118,3 +150,59 @@ pub unsafe extern "C" fn signal_init_logger(max_level: LogLevel, logger: FfiLogg
         }
     }
 }
+
+/// Converts a C string from the app into a log target, or returns `None` if it isn't valid.
+unsafe fn log_target<'a>(target: *const c_char) -> Option<&'a str> {
+    if target.is_null() {
+        return None;
+    }
+    CStr::from_ptr(target).to_str().ok()
+}
+
+/// Overrides the most verbose level logged for `target` and any modules within it.
+///
+/// Returns `false` if `target` is not a valid UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn signal_set_log_target_level(
+    target: *const c_char,
+    max_level: LogLevel,
+) -> bool {
+    let Some(target) = log_target(target) else {
+        return false;
+    };
+    libsignal_bridge::logging::set_target_level(
+        target,
+        Some(log::Level::from(max_level).to_level_filter()),
+    );
+    true
+}
+
+/// Removes an override set with `signal_set_log_target_level`.
+///
+/// Returns `false` if `target` is not a valid UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn signal_clear_log_target_level(target: *const c_char) -> bool {
+    let Some(target) = log_target(target) else {
+        return false;
+    };
+    libsignal_bridge::logging::set_target_level(target, None);
+    true
+}
+
+/// Limits `target` and any modules within it to `max_per_second` logs each second.
+///
+/// Passing 0 removes the limit. Returns `false` if `target` is not a valid UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn signal_set_log_target_rate_limit(
+    target: *const c_char,
+    max_per_second: u32,
+) -> bool {
+    let Some(target) = log_target(target) else {
+        return false;
+    };
+    libsignal_bridge::logging::set_target_rate_limit(
+        target,
+        (max_per_second != 0).then_some(max_per_second),
+    );
+    true
+}
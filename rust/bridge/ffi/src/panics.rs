@@ -0,0 +1,66 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::ffi::{c_char, c_void, CString};
+
+use libsignal_bridge::support::panics::{self, PanicReport, PanicReporter};
+
+/// Called for every panic in libsignal, on the panicking thread.
+///
+/// `location` and `backtrace` may be null. All strings are only valid for the duration of the call.
+pub type PanicCallback = extern "C" fn(
+    ctx: *mut c_void,
+    message: *const c_char,
+    location: *const c_char,
+    backtrace: *const c_char,
+);
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct FfiPanicReporter {
+    ctx: *mut c_void,
+    report: PanicCallback,
+}
+
+// It's up to the other side of the bridge to provide a Sync-friendly context.
+unsafe impl Send for FfiPanicReporter {}
+unsafe impl Sync for FfiPanicReporter {}
+
+fn to_c_string(s: &str) -> CString {
+    CString::new(s)
+        .unwrap_or_else(|_| CString::new(s.replace('\0', "\\0")).expect("We escaped any NULLs"))
+}
+
+impl PanicReporter for FfiPanicReporter {
+    fn report(&self, report: &PanicReport) {
+        let message = to_c_string(&report.message);
+        let location = report.location.as_deref().map(to_c_string);
+        let backtrace = report.backtrace.as_deref().map(to_c_string);
+        (self.report)(
+            self.ctx,
+            message.as_ptr(),
+            location
+                .as_ref()
+                .map_or(std::ptr::null(), |location| location.as_ptr()),
+            backtrace
+                .as_ref()
+                .map_or(std::ptr::null(), |backtrace| backtrace.as_ptr()),
+        );
+    }
+}
+
+/// Registers a callback to be told about every panic, e.g. to forward it to a crash reporter.
+///
+/// Replaces any previously registered callback.
+#[no_mangle]
+pub unsafe extern "C" fn signal_set_panic_reporter(reporter: FfiPanicReporter) {
+    panics::set_panic_reporter(Some(Box::new(reporter)));
+}
+
+/// Removes the callback registered with `signal_set_panic_reporter`.
+#[no_mangle]
+pub unsafe extern "C" fn signal_clear_panic_reporter() {
+    panics::set_panic_reporter(None);
+}
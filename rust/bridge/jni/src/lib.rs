@@ -163,7 +163,7 @@ pub unsafe extern "C" fn Java_org_signal_libsignal_internal_Native_SealedSender_
                 .iter()
                 .map(|(device_id, registration_id)| {
                     (
-                        u8::try_from(u32::from(*device_id)).expect("checked during parsing"),
+                        u8::from(*device_id),
                         i16::try_from(*registration_id).expect("checked during parsing"),
                     )
                 })
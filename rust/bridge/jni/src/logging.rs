@@ -6,9 +6,11 @@
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::process::abort;
 
-use jni::objects::{AutoLocal, GlobalRef, JClass, JObject, JValue};
+use jni::objects::{AutoLocal, GlobalRef, JClass, JObject, JString, JValue};
 use jni::sys::jint;
 use jni::{JNIEnv, JavaVM};
+use libsignal_bridge::logging::RateLimitDecision;
+use libsignal_bridge::support::panics::{self, PanicReport, PanicReporter};
 use libsignal_bridge::{describe_panic, jni_args};
 
 // Keep this in sync with SignalProtocolLogger.java, as well as the list below.
@@ -73,20 +75,33 @@ impl JniLogger {
         })
     }
 
-    fn log_impl(&self, record: &log::Record) -> jni::errors::Result<()> {
+    fn log_impl(
+        &self,
+        target: &str,
+        level: log::Level,
+        file: Option<&str>,
+        line: Option<u32>,
+        message: &str,
+    ) -> jni::errors::Result<()> {
         let mut env = self.vm.attach_current_thread()?;
-        let level: JavaLogLevel = record.level().into();
-        let message = format!(
-            "{}:{}: {}",
-            record.file().unwrap_or("<unknown>"),
-            record.line().unwrap_or(0),
-            record.args(),
+        let level: JavaLogLevel = level.into();
+        let target = AutoLocal::new(env.new_string(target)?, &env);
+        let file = AutoLocal::new(
+            match file {
+                Some(file) => JObject::from(env.new_string(file)?),
+                None => JObject::null(),
+            },
+            &env,
         );
+        let line = line.map_or(0, |line| line.try_into().unwrap_or(jint::MAX));
         let message = AutoLocal::new(env.new_string(message)?, &env);
         let module = AutoLocal::new(env.new_string("libsignal")?, &env);
         let args = jni_args!((
             level.into() => int,
             module => java.lang.String,
+            target => java.lang.String,
+            file => java.lang.String,
+            line => int,
             message => java.lang.String,
         ) -> void);
         let result = env.call_static_method(&self.logger_class, "log", args.sig, &args.args);
@@ -110,8 +125,27 @@ impl log::Log for JniLogger {
         if !self.enabled(record.metadata()) {
             return;
         }
-        if self.log_impl(record).is_err() {
-            // Drop the error; it's not like we can log it!
+        let decision = libsignal_bridge::logging::check_rate_limit(record.target());
+        if decision == RateLimitDecision::Drop {
+            return;
+        }
+
+        // Drop any errors; it's not like we can log them!
+        let _ = self.log_impl(
+            record.target(),
+            record.level(),
+            record.file(),
+            record.line(),
+            &record.args().to_string(),
+        );
+        if let Some(message) = decision.dropped_logs_message(record.target()) {
+            let _ = self.log_impl(
+                "libsignal_jni",
+                log::Level::Warn,
+                Some(file!()),
+                Some(line!()),
+                &message,
+            );
         }
     }
 
@@ -129,7 +163,7 @@ fn abort_on_panic(f: impl FnOnce()) {
     });
 }
 
-fn set_max_level_from_java_level(max_level: jint) {
+fn level_from_java_level(max_level: jint) -> log::Level {
     // Keep this in sync with SignalProtocolLogger.java.
     let level = match max_level {
         2 => JavaLogLevel::Verbose,
@@ -141,8 +175,17 @@ fn set_max_level_from_java_level(max_level: jint) {
         _ => panic!("invalid log level (see SignalProtocolLogger)"),
     };
     assert!(jint::from(level) == max_level);
+    level.into()
+}
 
-    log::set_max_level(log::Level::from(level).to_level_filter());
+fn set_max_level_from_java_level(max_level: jint) {
+    libsignal_bridge::logging::set_max_level(level_from_java_level(max_level).to_level_filter());
+}
+
+fn target_from_java_string(env: &mut JNIEnv, target: &JString) -> String {
+    env.get_string(target)
+        .expect("valid log target (see SignalProtocolLoggerProvider)")
+        .into()
 }
 
 #[no_mangle]
@@ -190,3 +233,106 @@ pub unsafe extern "C" fn Java_org_signal_libsignal_internal_Native_Logger_1SetMa
 ) {
     abort_on_panic(|| set_max_level_from_java_level(max_level));
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn Java_org_signal_libsignal_internal_Native_Logger_1SetTargetLevel(
+    mut env: JNIEnv,
+    _class: JClass,
+    target: JString,
+    max_level: jint,
+) {
+    abort_on_panic(|| {
+        let target = target_from_java_string(&mut env, &target);
+        // 0 means "no override" (see SignalProtocolLoggerProvider).
+        let level = (max_level != 0).then(|| level_from_java_level(max_level).to_level_filter());
+        libsignal_bridge::logging::set_target_level(&target, level);
+    });
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Java_org_signal_libsignal_internal_Native_Logger_1SetTargetRateLimit(
+    mut env: JNIEnv,
+    _class: JClass,
+    target: JString,
+    max_per_second: jint,
+) {
+    abort_on_panic(|| {
+        let target = target_from_java_string(&mut env, &target);
+        // 0 means "no limit" (see SignalProtocolLoggerProvider).
+        let limit = u32::try_from(max_per_second)
+            .expect("non-negative rate limit (see SignalProtocolLoggerProvider)");
+        libsignal_bridge::logging::set_target_rate_limit(&target, (limit != 0).then_some(limit));
+    });
+}
+
+struct JniPanicReporter {
+    vm: JavaVM,
+    reporter: GlobalRef,
+}
+
+impl JniPanicReporter {
+    fn report_impl(&self, report: &PanicReport) -> jni::errors::Result<()> {
+        let mut env = self.vm.attach_current_thread()?;
+        if env.exception_check()? {
+            // We can't call back into Java with an exception pending.
+            return Ok(());
+        }
+        fn new_optional_string<'a>(
+            env: &mut JNIEnv<'a>,
+            s: Option<&str>,
+        ) -> jni::errors::Result<JObject<'a>> {
+            Ok(match s {
+                Some(s) => env.new_string(s)?.into(),
+                None => JObject::null(),
+            })
+        }
+
+        let message = AutoLocal::new(env.new_string(&report.message)?, &env);
+        let location = new_optional_string(&mut env, report.location.as_deref())?;
+        let location = AutoLocal::new(location, &env);
+        let backtrace = new_optional_string(&mut env, report.backtrace.as_deref())?;
+        let backtrace = AutoLocal::new(backtrace, &env);
+        let args = jni_args!((
+            message => java.lang.String,
+            location => java.lang.String,
+            backtrace => java.lang.String,
+        ) -> void);
+        let result = env.call_method(&self.reporter, "reportPanic", args.sig, &args.args);
+
+        let throwable = env.exception_occurred()?;
+        if **throwable == *JObject::null() {
+            result?;
+        } else {
+            env.exception_clear()?;
+        }
+        Ok(())
+    }
+}
+
+impl PanicReporter for JniPanicReporter {
+    fn report(&self, report: &PanicReport) {
+        // Drop any errors; we're already in the middle of a panic.
+        let _ = self.report_impl(report);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Java_org_signal_libsignal_internal_Native_Logger_1SetPanicReporter(
+    env: JNIEnv,
+    _class: JClass,
+    reporter: JObject,
+) {
+    abort_on_panic(|| {
+        if reporter.is_null() {
+            panics::set_panic_reporter(None);
+            return;
+        }
+        let reporter = JniPanicReporter {
+            vm: env.get_java_vm().expect("can get VM"),
+            reporter: env
+                .new_global_ref(reporter)
+                .expect("can create global reference"),
+        };
+        panics::set_panic_reporter(Some(Box::new(reporter)));
+    });
+}
@@ -26,6 +26,9 @@ use libsignal_bridge_testing::*;
 fn main(mut cx: ModuleContext) -> NeonResult<()> {
     libsignal_bridge::node::register(&mut cx)?;
     cx.export_function("initLogger", logging::init_logger)?;
+    cx.export_function("setLogTargetLevel", logging::set_log_target_level)?;
+    cx.export_function("setLogTargetRateLimit", logging::set_log_target_rate_limit)?;
+    cx.export_function("setPanicReporter", logging::set_panic_reporter)?;
     cx.export_function("IdentityKeyPair_Deserialize", identitykeypair_deserialize)?;
     cx.export_function(
         "SealedSenderMultiRecipientMessage_Parse",
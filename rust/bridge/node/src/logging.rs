@@ -6,7 +6,9 @@
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
+use libsignal_bridge::logging::RateLimitDecision;
 use libsignal_bridge::node::SimpleArgTypeInfo;
+use libsignal_bridge::support::panics::{self, PanicReport, PanicReporter};
 use neon::prelude::*;
 
 /// ts: export const enum LogLevel { Error = 1, Warn, Info, Debug, Trace }
@@ -83,6 +85,10 @@ impl log::Log for NodeLogger {
         if !self.enabled(record.metadata()) {
             return;
         }
+        let decision = libsignal_bridge::logging::check_rate_limit(record.target());
+        if decision == RateLimitDecision::Drop {
+            return;
+        }
 
         let throttle_counter = self.throttle_counter.clone();
 
@@ -121,6 +127,7 @@ impl log::Log for NodeLogger {
         let line = record.line();
         let message = record.args().to_string();
         let level = record.level();
+        let dropped_logs_message = decision.dropped_logs_message(&target);
 
         // Drop any error; it's not like we can log it!
         // Most likely the Node event loop has already shut down.
@@ -132,6 +139,18 @@ impl log::Log for NodeLogger {
                 convert_log_args_to_js(&mut cx, level, &target, file.as_deref(), line, &message);
             log_fn.call(&mut cx, undef, args)?;
 
+            if let Some(dropped_logs_message) = dropped_logs_message {
+                let args = convert_log_args_to_js(
+                    &mut cx,
+                    log::Level::Warn,
+                    "libsignal_node",
+                    Some(file!()),
+                    Some(line!()),
+                    &dropped_logs_message,
+                );
+                log_fn.call(&mut cx, undef, args)?;
+            }
+
             if should_additionally_log_about_dropped_logs {
                 let args = convert_log_args_to_js(
                     &mut cx,
@@ -176,7 +195,7 @@ fn convert_log_args_to_js<'a>(
     [level_arg, target_arg, file_arg, line_arg, message_arg]
 }
 
-fn set_max_level_from_js_level(max_level: u32) {
+fn level_from_js_level(max_level: u32) -> log::Level {
     let level = match max_level {
         1 => LogLevel::Error,
         2 => LogLevel::Warn,
@@ -186,8 +205,11 @@ fn set_max_level_from_js_level(max_level: u32) {
         _ => panic!("invalid log level"),
     };
     assert!(u32::from(level) == max_level);
+    level.into()
+}
 
-    log::set_max_level(log::Level::from(level).to_level_filter());
+fn set_max_level_from_js_level(max_level: u32) {
+    libsignal_bridge::logging::set_max_level(level_from_js_level(max_level).to_level_filter());
 }
 
 /// ts: export function initLogger(maxLevel: LogLevel, callback: (level: LogLevel, target: string, file: string | null, line: number | null, message: string) => void): void
@@ -216,3 +238,83 @@ pub(crate) fn init_logger(mut cx: FunctionContext) -> JsResult<JsUndefined> {
 
     Ok(cx.undefined())
 }
+
+/// ts: export function setLogTargetLevel(target: string, maxLevel: LogLevel | null): void
+pub(crate) fn set_log_target_level(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let target = cx.argument::<JsString>(0)?.value(&mut cx);
+    let max_level_arg = cx.argument::<JsValue>(1)?;
+    let level = if max_level_arg.is_a::<JsNull, _>(&mut cx) {
+        None
+    } else {
+        let max_level_arg = max_level_arg.downcast_or_throw::<JsNumber, _>(&mut cx)?;
+        let max_level = u32::convert_from(&mut cx, max_level_arg)?;
+        Some(level_from_js_level(max_level).to_level_filter())
+    };
+    libsignal_bridge::logging::set_target_level(&target, level);
+    Ok(cx.undefined())
+}
+
+/// ts: export function setLogTargetRateLimit(target: string, maxPerSecond: number | null): void
+pub(crate) fn set_log_target_rate_limit(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let target = cx.argument::<JsString>(0)?.value(&mut cx);
+    let max_per_second_arg = cx.argument::<JsValue>(1)?;
+    let max_per_second = if max_per_second_arg.is_a::<JsNull, _>(&mut cx) {
+        None
+    } else {
+        let max_per_second_arg = max_per_second_arg.downcast_or_throw::<JsNumber, _>(&mut cx)?;
+        Some(u32::convert_from(&mut cx, max_per_second_arg)?)
+    };
+    libsignal_bridge::logging::set_target_rate_limit(&target, max_per_second);
+    Ok(cx.undefined())
+}
+
+const GLOBAL_PANIC_FN_KEY: &str = "__libsignal_panic_fn";
+
+struct NodePanicReporter {
+    channel: Channel,
+}
+
+impl PanicReporter for NodePanicReporter {
+    fn report(&self, report: &PanicReport) {
+        let PanicReport {
+            message,
+            location,
+            backtrace,
+        } = report.clone();
+        // The report is delivered asynchronously, since the panic may be on any thread.
+        // Drop any error; most likely the Node event loop has already shut down.
+        let _ = self.channel.try_send(move |mut cx| {
+            let panic_fn: Handle<JsFunction> = cx.global(GLOBAL_PANIC_FN_KEY)?;
+            let undef = cx.undefined();
+            let message_arg: Handle<JsValue> = cx.string(message).upcast();
+            let location_arg: Handle<JsValue> = match location {
+                Some(location) => cx.string(location).upcast(),
+                None => cx.null().upcast(),
+            };
+            let backtrace_arg: Handle<JsValue> = match backtrace {
+                Some(backtrace) => cx.string(backtrace).upcast(),
+                None => cx.null().upcast(),
+            };
+            panic_fn.call(&mut cx, undef, [message_arg, location_arg, backtrace_arg])?;
+            Ok(())
+        });
+    }
+}
+
+/// ts: export function setPanicReporter(callback: ((message: string, location: string | null, backtrace: string | null) => void) | null): void
+pub(crate) fn set_panic_reporter(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let callback_arg = cx.argument::<JsValue>(0)?;
+    if callback_arg.is_a::<JsNull, _>(&mut cx) {
+        panics::set_panic_reporter(None);
+        return Ok(cx.undefined());
+    }
+    let callback = callback_arg.downcast_or_throw::<JsFunction, _>(&mut cx)?;
+
+    let global = cx.global_object();
+    global.set(&mut cx, GLOBAL_PANIC_FN_KEY, callback)?;
+
+    let mut channel = cx.channel();
+    channel.unref(&mut cx);
+    panics::set_panic_reporter(Some(Box::new(NodePanicReporter { channel })));
+    Ok(cx.undefined())
+}
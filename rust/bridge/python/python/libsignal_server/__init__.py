@@ -0,0 +1,24 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+"""Server-side libsignal operations for Python tooling."""
+
+from ._native import LibSignalError, VerificationFailedError
+from .message_backup import BackupValidationOutcome, validate_backup
+from .sealed_sender import PrivateKey, PublicKey, SenderCertificate, ServerCertificate
+from .zkgroup import ServerPublicParams, ServerSecretParams
+
+__all__ = [
+    'BackupValidationOutcome',
+    'LibSignalError',
+    'PrivateKey',
+    'PublicKey',
+    'SenderCertificate',
+    'ServerCertificate',
+    'ServerPublicParams',
+    'ServerSecretParams',
+    'VerificationFailedError',
+    'validate_backup',
+]
@@ -0,0 +1,69 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+# Type declarations for the entry points generated by `bridge_fn(python = ...)` in
+# rust/bridge/shared. Keep this in sync with the functions marked there.
+
+from typing import List, Optional, final
+
+@final
+class NativeHandle:
+    pass
+
+class LibSignalError(Exception):
+    pass
+
+class VerificationFailedError(LibSignalError):
+    pass
+
+# zkgroup
+def ServerSecretParams_GenerateDeterministic(randomness: bytes) -> NativeHandle: ...
+def ServerSecretParams_Deserialize(buffer: bytes) -> NativeHandle: ...
+def ServerSecretParams_Serialize(handle: NativeHandle) -> bytes: ...
+def ServerSecretParams_GetPublicParams(params: NativeHandle) -> NativeHandle: ...
+def ServerSecretParams_SignDeterministic(params: NativeHandle, randomness: bytes, message: bytes) -> bytes: ...
+def ServerSecretParams_IssueAuthCredentialWithPniZkcDeterministic(server_secret_params: NativeHandle, randomness: bytes, aci: bytes, pni: bytes, redemption_time: int) -> bytes: ...
+def ServerSecretParams_VerifyAuthCredentialPresentation(server_secret_params: NativeHandle, group_public_params: bytes, presentation_bytes: bytes, current_time_in_seconds: int) -> None: ...
+def ServerSecretParams_IssueExpiringProfileKeyCredentialDeterministic(server_secret_params: NativeHandle, randomness: bytes, request: bytes, user_id: bytes, commitment: bytes, expiration_in_seconds: int) -> bytes: ...
+def ServerSecretParams_VerifyProfileKeyCredentialPresentation(server_secret_params: NativeHandle, group_public_params: bytes, presentation_bytes: bytes, current_time_in_seconds: int) -> None: ...
+def ServerSecretParams_IssueReceiptCredentialDeterministic(server_secret_params: NativeHandle, randomness: bytes, request: bytes, receipt_expiration_time: int, receipt_level: int) -> bytes: ...
+def ServerSecretParams_VerifyReceiptCredentialPresentation(server_secret_params: NativeHandle, presentation: bytes) -> None: ...
+def ServerPublicParams_Deserialize(buffer: bytes) -> NativeHandle: ...
+def ServerPublicParams_Serialize(handle: NativeHandle) -> bytes: ...
+def ServerPublicParams_VerifySignature(server_public_params: NativeHandle, message: bytes, notary_signature: bytes) -> None: ...
+def AuthCredentialPresentation_CheckValidContents(presentation_bytes: bytes) -> None: ...
+def ProfileKeyCredentialPresentation_CheckValidContents(presentation_bytes: bytes) -> None: ...
+
+# Keys
+def PrivateKey_Generate() -> NativeHandle: ...
+def PrivateKey_Deserialize(data: bytes) -> NativeHandle: ...
+def PrivateKey_Serialize(obj: NativeHandle) -> bytes: ...
+def PrivateKey_GetPublicKey(k: NativeHandle) -> NativeHandle: ...
+def PublicKey_Deserialize(data: bytes) -> NativeHandle: ...
+def PublicKey_Serialize(obj: NativeHandle) -> bytes: ...
+def PublicKey_Equals(lhs: NativeHandle, rhs: NativeHandle) -> bool: ...
+
+# Sealed sender certificates
+def ServerCertificate_New(key_id: int, server_key: NativeHandle, trust_root: NativeHandle) -> NativeHandle: ...
+def ServerCertificate_Deserialize(data: bytes) -> NativeHandle: ...
+def ServerCertificate_GetSerialized(obj: NativeHandle) -> bytes: ...
+def ServerCertificate_GetKeyId(obj: NativeHandle) -> int: ...
+def ServerCertificate_GetKey(obj: NativeHandle) -> NativeHandle: ...
+def SenderCertificate_New(sender_uuid: str, sender_e164: Optional[str], sender_device_id: int, sender_key: NativeHandle, expiration: int, signer_cert: NativeHandle, signer_key: NativeHandle) -> NativeHandle: ...
+def SenderCertificate_Deserialize(data: bytes) -> NativeHandle: ...
+def SenderCertificate_GetSerialized(obj: NativeHandle) -> bytes: ...
+def SenderCertificate_GetSenderUuid(obj: NativeHandle) -> str: ...
+def SenderCertificate_GetSenderE164(obj: NativeHandle) -> Optional[str]: ...
+def SenderCertificate_GetExpiration(obj: NativeHandle) -> int: ...
+def SenderCertificate_GetDeviceId(obj: NativeHandle) -> int: ...
+def SenderCertificate_GetKey(obj: NativeHandle) -> NativeHandle: ...
+def SenderCertificate_GetServerCertificate(cert: NativeHandle) -> NativeHandle: ...
+def SenderCertificate_Validate(cert: NativeHandle, key: NativeHandle, time: int) -> bool: ...
+
+# Message backups
+def MessageBackupKey_New(master_key: bytes, aci: bytes) -> NativeHandle: ...
+def MessageBackupValidator_Validate(key: NativeHandle, first_stream: bytes, second_stream: bytes, len: int, purpose: int) -> NativeHandle: ...
+def MessageBackupValidationOutcome_getErrorMessage(outcome: NativeHandle) -> Optional[str]: ...
+def MessageBackupValidationOutcome_getUnknownFields(outcome: NativeHandle) -> List[str]: ...
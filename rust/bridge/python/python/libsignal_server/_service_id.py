@@ -0,0 +1,24 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+# The bridge takes service IDs in their 17-byte fixed-width binary form: a kind byte followed by
+# the UUID. Python callers pass just the 16 UUID bytes, since the kind is implied by the argument.
+
+_ACI_KIND = b'\x00'
+_PNI_KIND = b'\x01'
+
+
+def _fixed_width(kind: bytes, uuid_bytes: bytes, name: str) -> bytes:
+    if len(uuid_bytes) != 16:
+        raise ValueError(f'{name} must be 16 bytes')
+    return kind + uuid_bytes
+
+
+def aci(uuid_bytes: bytes) -> bytes:
+    return _fixed_width(_ACI_KIND, uuid_bytes, 'ACI')
+
+
+def pni(uuid_bytes: bytes) -> bytes:
+    return _fixed_width(_PNI_KIND, uuid_bytes, 'PNI')
@@ -0,0 +1,45 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+from typing import List, Optional
+
+from . import _native
+from ._service_id import aci as _aci
+
+# Matches the Rust `Purpose` enum.
+_PURPOSES = {
+    'device_transfer': 0,
+    'remote_backup': 1,
+}
+
+
+class BackupValidationOutcome:
+    """The result of validating a message backup."""
+
+    def __init__(self, native_handle: _native.NativeHandle) -> None:
+        self.error_message: Optional[str] = _native.MessageBackupValidationOutcome_getErrorMessage(native_handle)
+        """A description of why the backup is invalid, or `None` if it is valid."""
+        self.unknown_fields: List[str] = _native.MessageBackupValidationOutcome_getUnknownFields(native_handle)
+        """Fields not recognized by this version of libsignal, which don't make the backup invalid."""
+
+    @property
+    def ok(self) -> bool:
+        return self.error_message is None
+
+
+def validate_backup(backup: bytes, master_key: bytes, aci: bytes, purpose: str = 'remote_backup') -> BackupValidationOutcome:
+    """Validates an encrypted, compressed message backup for the account with the given master key
+    and ACI (the 16 raw bytes of its UUID).
+
+    `purpose` is either "remote_backup" or "device_transfer".
+    """
+    try:
+        purpose_value = _PURPOSES[purpose]
+    except KeyError:
+        raise ValueError(f'unknown backup purpose {purpose!r}') from None
+    key = _native.MessageBackupKey_New(master_key, _aci(aci))
+    # The backup is read twice, once to check its HMAC and once to validate its contents.
+    outcome = _native.MessageBackupValidator_Validate(key, backup, backup, len(backup), purpose_value)
+    return BackupValidationOutcome(outcome)
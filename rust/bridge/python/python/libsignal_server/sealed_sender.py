@@ -0,0 +1,129 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+from typing import Optional
+
+from . import _native
+
+
+class PublicKey:
+    def __init__(self, native_handle: _native.NativeHandle) -> None:
+        self._native_handle = native_handle
+
+    @staticmethod
+    def deserialize(data: bytes) -> 'PublicKey':
+        return PublicKey(_native.PublicKey_Deserialize(data))
+
+    def serialize(self) -> bytes:
+        return _native.PublicKey_Serialize(self._native_handle)
+
+    def __eq__(self, other: object) -> bool:
+        if not isinstance(other, PublicKey):
+            return NotImplemented
+        return _native.PublicKey_Equals(self._native_handle, other._native_handle)
+
+    def __hash__(self) -> int:
+        return hash(self.serialize())
+
+
+class PrivateKey:
+    def __init__(self, native_handle: _native.NativeHandle) -> None:
+        self._native_handle = native_handle
+
+    @staticmethod
+    def generate() -> 'PrivateKey':
+        return PrivateKey(_native.PrivateKey_Generate())
+
+    @staticmethod
+    def deserialize(data: bytes) -> 'PrivateKey':
+        return PrivateKey(_native.PrivateKey_Deserialize(data))
+
+    def serialize(self) -> bytes:
+        return _native.PrivateKey_Serialize(self._native_handle)
+
+    def public_key(self) -> PublicKey:
+        return PublicKey(_native.PrivateKey_GetPublicKey(self._native_handle))
+
+
+class ServerCertificate:
+    """A certificate for a key the server uses to sign sender certificates."""
+
+    def __init__(self, native_handle: _native.NativeHandle) -> None:
+        self._native_handle = native_handle
+
+    @staticmethod
+    def new(key_id: int, key: PublicKey, trust_root: PrivateKey) -> 'ServerCertificate':
+        return ServerCertificate(_native.ServerCertificate_New(key_id, key._native_handle, trust_root._native_handle))
+
+    @staticmethod
+    def deserialize(data: bytes) -> 'ServerCertificate':
+        return ServerCertificate(_native.ServerCertificate_Deserialize(data))
+
+    def serialize(self) -> bytes:
+        return _native.ServerCertificate_GetSerialized(self._native_handle)
+
+    @property
+    def key_id(self) -> int:
+        return _native.ServerCertificate_GetKeyId(self._native_handle)
+
+    @property
+    def key(self) -> PublicKey:
+        return PublicKey(_native.ServerCertificate_GetKey(self._native_handle))
+
+
+class SenderCertificate:
+    """A certificate vouching for a sender's identity key, used in sealed sender messages.
+
+    Timestamps are in milliseconds since the epoch.
+    """
+
+    def __init__(self, native_handle: _native.NativeHandle) -> None:
+        self._native_handle = native_handle
+
+    @staticmethod
+    def new(sender_uuid: str, sender_e164: Optional[str], sender_device_id: int, sender_key: PublicKey, expiration: int, signer: ServerCertificate, signer_key: PrivateKey) -> 'SenderCertificate':
+        return SenderCertificate(_native.SenderCertificate_New(
+            sender_uuid,
+            sender_e164,
+            sender_device_id,
+            sender_key._native_handle,
+            expiration,
+            signer._native_handle,
+            signer_key._native_handle))
+
+    @staticmethod
+    def deserialize(data: bytes) -> 'SenderCertificate':
+        return SenderCertificate(_native.SenderCertificate_Deserialize(data))
+
+    def serialize(self) -> bytes:
+        return _native.SenderCertificate_GetSerialized(self._native_handle)
+
+    @property
+    def sender_uuid(self) -> str:
+        return _native.SenderCertificate_GetSenderUuid(self._native_handle)
+
+    @property
+    def sender_e164(self) -> Optional[str]:
+        return _native.SenderCertificate_GetSenderE164(self._native_handle)
+
+    @property
+    def sender_device_id(self) -> int:
+        return _native.SenderCertificate_GetDeviceId(self._native_handle)
+
+    @property
+    def key(self) -> PublicKey:
+        return PublicKey(_native.SenderCertificate_GetKey(self._native_handle))
+
+    @property
+    def expiration(self) -> int:
+        return _native.SenderCertificate_GetExpiration(self._native_handle)
+
+    @property
+    def server_certificate(self) -> ServerCertificate:
+        return ServerCertificate(_native.SenderCertificate_GetServerCertificate(self._native_handle))
+
+    def validate(self, trust_root: PublicKey, now: int) -> bool:
+        """Checks that the certificate chains up to `trust_root` and hasn't expired as of `now`."""
+        return _native.SenderCertificate_Validate(self._native_handle, trust_root._native_handle, now)
@@ -0,0 +1,90 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+from . import _native
+from ._service_id import aci as _aci, pni as _pni
+
+
+class ServerPublicParams:
+    """The public half of `ServerSecretParams`, as distributed to clients."""
+
+    def __init__(self, native_handle: _native.NativeHandle) -> None:
+        self._native_handle = native_handle
+
+    @staticmethod
+    def deserialize(data: bytes) -> 'ServerPublicParams':
+        return ServerPublicParams(_native.ServerPublicParams_Deserialize(data))
+
+    def serialize(self) -> bytes:
+        return _native.ServerPublicParams_Serialize(self._native_handle)
+
+    def verify_signature(self, message: bytes, signature: bytes) -> None:
+        """Raises `VerificationFailedError` if `signature` is not a signature of `message` by the
+        corresponding secret params."""
+        _native.ServerPublicParams_VerifySignature(self._native_handle, message, signature)
+
+
+class ServerSecretParams:
+    """The server's zkgroup secret parameters, used to issue and verify credentials.
+
+    Methods that take `randomness` are deterministic; pass 32 bytes from `secrets.token_bytes`.
+    ACIs and PNIs are the 16 raw bytes of their UUIDs (`uuid.UUID.bytes`), and times are in
+    seconds since the epoch.
+    """
+
+    def __init__(self, native_handle: _native.NativeHandle) -> None:
+        self._native_handle = native_handle
+
+    @staticmethod
+    def generate(randomness: bytes) -> 'ServerSecretParams':
+        return ServerSecretParams(_native.ServerSecretParams_GenerateDeterministic(randomness))
+
+    @staticmethod
+    def deserialize(data: bytes) -> 'ServerSecretParams':
+        return ServerSecretParams(_native.ServerSecretParams_Deserialize(data))
+
+    def serialize(self) -> bytes:
+        return _native.ServerSecretParams_Serialize(self._native_handle)
+
+    def public_params(self) -> ServerPublicParams:
+        return ServerPublicParams(_native.ServerSecretParams_GetPublicParams(self._native_handle))
+
+    def sign(self, randomness: bytes, message: bytes) -> bytes:
+        return _native.ServerSecretParams_SignDeterministic(self._native_handle, randomness, message)
+
+    def issue_auth_credential_with_pni_zkc(self, randomness: bytes, aci: bytes, pni: bytes, redemption_time: int) -> bytes:
+        """Issues an auth credential for `aci` and `pni`, valid on the day starting at
+        `redemption_time`."""
+        return _native.ServerSecretParams_IssueAuthCredentialWithPniZkcDeterministic(
+            self._native_handle, randomness, _aci(aci), _pni(pni), redemption_time)
+
+    def verify_auth_credential_presentation(self, group_public_params: bytes, presentation: bytes, now: int) -> None:
+        """Raises `VerificationFailedError` if the presentation is invalid or expired as of
+        `now`."""
+        _native.AuthCredentialPresentation_CheckValidContents(presentation)
+        _native.ServerSecretParams_VerifyAuthCredentialPresentation(
+            self._native_handle, group_public_params, presentation, now)
+
+    def issue_expiring_profile_key_credential(self, randomness: bytes, request: bytes, aci: bytes, commitment: bytes, expiration: int) -> bytes:
+        """Issues a profile key credential that expires at `expiration`."""
+        return _native.ServerSecretParams_IssueExpiringProfileKeyCredentialDeterministic(
+            self._native_handle, randomness, request, _aci(aci), commitment, expiration)
+
+    def verify_profile_key_credential_presentation(self, group_public_params: bytes, presentation: bytes, now: int) -> None:
+        """Raises `VerificationFailedError` if the presentation is invalid or expired as of
+        `now`."""
+        if not presentation:
+            raise ValueError('presentation must not be empty')
+        _native.ProfileKeyCredentialPresentation_CheckValidContents(presentation)
+        _native.ServerSecretParams_VerifyProfileKeyCredentialPresentation(
+            self._native_handle, group_public_params, presentation, now)
+
+    def issue_receipt_credential(self, randomness: bytes, request: bytes, expiration: int, receipt_level: int) -> bytes:
+        """Issues a receipt credential that expires at `expiration`."""
+        return _native.ServerSecretParams_IssueReceiptCredentialDeterministic(
+            self._native_handle, randomness, request, expiration, receipt_level)
+
+    def verify_receipt_credential_presentation(self, presentation: bytes) -> None:
+        _native.ServerSecretParams_VerifyReceiptCredentialPresentation(self._native_handle, presentation)
@@ -0,0 +1,17 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Python bindings for the server side of libsignal, for backend and abuse-analysis tooling.
+//!
+//! The entry points themselves are generated by `bridge_fn(python = ...)` in libsignal-bridge;
+//! this crate just collects them into the `libsignal_server._native` extension module. The
+//! `libsignal_server` Python package wraps them in classes.
+
+use pyo3::prelude::*;
+
+#[pymodule]
+fn _native(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    libsignal_bridge::python::register(m)
+}
@@ -0,0 +1,46 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+import os
+import unittest
+
+from libsignal_server import validate_backup
+
+# Shared with the Node tests.
+TEST_BACKUP = os.path.join(os.path.dirname(__file__), '../../../../node/ts/test/new_account.binproto.encrypted')
+MASTER_KEY = b'M' * 32
+ACI = b'\x11' * 16
+
+
+class MessageBackupTest(unittest.TestCase):
+    def test_valid_backup(self) -> None:
+        with open(TEST_BACKUP, 'rb') as f:
+            backup = f.read()
+        outcome = validate_backup(backup, MASTER_KEY, ACI)
+        self.assertTrue(outcome.ok, outcome.error_message)
+        self.assertEqual(outcome.unknown_fields, [])
+
+    def test_empty_backup(self) -> None:
+        outcome = validate_backup(b'', MASTER_KEY, ACI)
+        self.assertFalse(outcome.ok)
+        self.assertEqual(outcome.error_message, 'not enough bytes for an HMAC')
+
+    def test_wrong_key(self) -> None:
+        with open(TEST_BACKUP, 'rb') as f:
+            backup = f.read()
+        outcome = validate_backup(backup, b'N' * 32, ACI)
+        self.assertFalse(outcome.ok)
+
+    def test_invalid_arguments(self) -> None:
+        with self.assertRaises(ValueError):
+            validate_backup(b'', MASTER_KEY, ACI, purpose='archive')
+        with self.assertRaises(ValueError):
+            validate_backup(b'', MASTER_KEY[:31], ACI)
+        with self.assertRaises(ValueError):
+            validate_backup(b'', MASTER_KEY, ACI[:15])
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,65 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+import unittest
+
+from libsignal_server import LibSignalError, PrivateKey, PublicKey, SenderCertificate, ServerCertificate
+
+
+class SealedSenderTest(unittest.TestCase):
+    def test_issue_and_validate(self) -> None:
+        trust_root = PrivateKey.generate()
+        server_key = PrivateKey.generate()
+        server_cert = ServerCertificate.new(1, server_key.public_key(), trust_root)
+        self.assertEqual(server_cert.key_id, 1)
+        self.assertEqual(server_cert.key, server_key.public_key())
+
+        sender_key = PrivateKey.generate().public_key()
+        expiration = 1_700_000_000_000
+        sender_cert = SenderCertificate.new(
+            '9d0652a3-dcc3-4d11-975f-74d61598733f',
+            '+18005550100',
+            3,
+            sender_key,
+            expiration,
+            server_cert,
+            server_key)
+
+        sender_cert = SenderCertificate.deserialize(sender_cert.serialize())
+        self.assertEqual(sender_cert.sender_uuid, '9d0652a3-dcc3-4d11-975f-74d61598733f')
+        self.assertEqual(sender_cert.sender_e164, '+18005550100')
+        self.assertEqual(sender_cert.sender_device_id, 3)
+        self.assertEqual(sender_cert.key, sender_key)
+        self.assertEqual(sender_cert.expiration, expiration)
+        self.assertEqual(sender_cert.server_certificate.serialize(), server_cert.serialize())
+
+        self.assertTrue(sender_cert.validate(trust_root.public_key(), expiration - 1))
+        self.assertFalse(sender_cert.validate(trust_root.public_key(), expiration + 1))
+        self.assertFalse(sender_cert.validate(PrivateKey.generate().public_key(), expiration - 1))
+
+    def test_optional_e164(self) -> None:
+        key = PrivateKey.generate()
+        server_cert = ServerCertificate.new(1, key.public_key(), key)
+        sender_cert = SenderCertificate.new(
+            '9d0652a3-dcc3-4d11-975f-74d61598733f', None, 1, key.public_key(), 0, server_cert, key)
+        self.assertIsNone(sender_cert.sender_e164)
+
+    def test_keys_round_trip(self) -> None:
+        key = PrivateKey.generate()
+        self.assertEqual(PrivateKey.deserialize(key.serialize()).serialize(), key.serialize())
+        public_key = key.public_key()
+        self.assertEqual(PublicKey.deserialize(public_key.serialize()), public_key)
+
+    def test_invalid_input(self) -> None:
+        with self.assertRaises(LibSignalError):
+            PublicKey.deserialize(b'')
+        with self.assertRaises(LibSignalError):
+            ServerCertificate.deserialize(b'\x01\x02\x03')
+        with self.assertRaises(LibSignalError):
+            SenderCertificate.deserialize(b'\x01\x02\x03')
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,69 @@
+#
+# Copyright 2024 Signal Messenger, LLC.
+# SPDX-License-Identifier: AGPL-3.0-only
+#
+
+import unittest
+import uuid
+
+from libsignal_server import LibSignalError, ServerPublicParams, ServerSecretParams, VerificationFailedError
+
+TEST_ARRAY_32 = bytes.fromhex('000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f')
+TEST_ARRAY_32_1 = bytes.fromhex('6465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f80818283')
+TEST_ARRAY_32_2 = bytes.fromhex('c8c9cacbcccdcecfd0d1d2d3d4d5d6d7d8d9dadbdcdddedfe0e1e2e3e4e5e6e7')
+
+
+class ZkGroupTest(unittest.TestCase):
+    def test_server_signatures(self) -> None:
+        # Same as testServerSignatures in the other languages' tests.
+        secret_params = ServerSecretParams.generate(TEST_ARRAY_32)
+        public_params = secret_params.public_params()
+
+        message = TEST_ARRAY_32_1
+        signature = secret_params.sign(TEST_ARRAY_32_2, message)
+        public_params.verify_signature(message, signature)
+        self.assertEqual(
+            signature.hex(),
+            '87d354564d35ef91edba851e0815612e864c227a0471d50c270698604406d003a55473f576cf241fc6b41c6b16e5e63b333c02fe4a33858022fdd7a4ab367b06')
+
+        altered_message = bytes([message[0] ^ 1]) + message[1:]
+        with self.assertRaises(VerificationFailedError):
+            public_params.verify_signature(altered_message, signature)
+
+    def test_serialization_round_trip(self) -> None:
+        secret_params = ServerSecretParams.generate(TEST_ARRAY_32)
+        serialized = secret_params.serialize()
+        self.assertEqual(ServerSecretParams.deserialize(serialized).serialize(), serialized)
+
+        public_serialized = secret_params.public_params().serialize()
+        self.assertEqual(ServerPublicParams.deserialize(public_serialized).serialize(), public_serialized)
+
+    def test_invalid_input(self) -> None:
+        secret_params = ServerSecretParams.generate(TEST_ARRAY_32)
+        with self.assertRaises(LibSignalError):
+            ServerSecretParams.deserialize(b'\x00' * 10)
+        with self.assertRaises(ValueError):
+            secret_params.sign(b'short', b'message')
+        with self.assertRaises(ValueError):
+            secret_params.issue_auth_credential_with_pni_zkc(TEST_ARRAY_32_1, b'too short', uuid.uuid4().bytes, 0)
+        with self.assertRaises(ValueError):
+            secret_params.verify_profile_key_credential_presentation(b'', b'', 0)
+        with self.assertRaises(LibSignalError):
+            secret_params.verify_auth_credential_presentation(b'', b'\xff' * 100, 0)
+        with self.assertRaises(TypeError):
+            # Handles can't be mixed up.
+            ServerPublicParams(secret_params._native_handle).serialize()
+
+    def test_issue_auth_credential(self) -> None:
+        secret_params = ServerSecretParams.generate(TEST_ARRAY_32)
+        aci = uuid.UUID('dc249e7a-56ea-49cd-abce-aa3a0d65f6f0').bytes
+        pni = uuid.UUID('18c7e848-2213-40c1-bd6b-3b69a82dd1f5').bytes
+        redemption_time = 123456 * 86400
+        first = secret_params.issue_auth_credential_with_pni_zkc(TEST_ARRAY_32_1, aci, pni, redemption_time)
+        second = secret_params.issue_auth_credential_with_pni_zkc(TEST_ARRAY_32_1, aci, pni, redemption_time)
+        self.assertEqual(first, second)
+        self.assertNotEqual(first, secret_params.issue_auth_credential_with_pni_zkc(TEST_ARRAY_32_2, aci, pni, redemption_time))
+
+
+if __name__ == '__main__':
+    unittest.main()
@@ -105,6 +105,7 @@ fn bridge_fn_body(
 
     quote! {
         ffi::run_ffi_safe(|| {
+            let __timer = support::instrumentation::CallTimer::start(stringify!(#orig_name));
             #(#input_processing)*
             let __result = #orig_name(#(#input_names),*);
             #await_if_needed;
@@ -159,6 +160,7 @@ fn bridge_io_body(
 
     quote! {
         ffi::run_ffi_safe(|| {
+            let __queued = support::instrumentation::QueuedCall::enqueue(stringify!(#orig_name));
             #load_async_runtime
             #load_promise
             #(#input_saving)*
@@ -166,6 +168,7 @@ fn bridge_io_body(
                 async_runtime,
                 promise,
                 |__cancel| async move {
+                    let __timer = __queued.map(support::instrumentation::QueuedCall::start);
                     let __future = ffi::catch_unwind(std::panic::AssertUnwindSafe(async move {
                         #(#input_loading)*
                         ::tokio::select! {
@@ -102,6 +102,7 @@ fn bridge_fn_body(
 
     quote! {
         jni::run_ffi_safe(&mut env, |env| {
+            let __timer = support::instrumentation::CallTimer::start(stringify!(#orig_name));
             #(#input_processing)*
             let __result = #orig_name(#(#input_names),*);
             #await_if_needed
@@ -152,16 +153,24 @@ fn bridge_io_body(
 
     quote! {
         jni::run_ffi_safe(&mut env, |env| {
+            let __queued = support::instrumentation::QueuedCall::enqueue(stringify!(#orig_name));
             #load_async_runtime
             #(#input_saving)*
             jni::run_future_on_runtime(env, async_runtime, |__cancel| async move {
+                let __timer = __queued.map(support::instrumentation::QueuedCall::start);
                 // Wrap the actual work to catch any panics.
                 let __future = jni::catch_unwind(std::panic::AssertUnwindSafe(async {
                     #(#input_loading)*
-                    let __result = #orig_name(#(#input_names),*).await;
-                    // If the original function can't fail, wrap the result in Ok for uniformity.
-                    // See TransformHelper::ok_if_needed.
-                    Ok(TransformHelper(__result).ok_if_needed()?.0)
+                    ::tokio::select! {
+                        __result = #orig_name(#(#input_names),*) => {
+                            // If the original function can't fail, wrap the result in Ok for uniformity.
+                            // See TransformHelper::ok_if_needed.
+                            Ok(TransformHelper(__result).ok_if_needed()?.0)
+                        }
+                        _ = __cancel => {
+                            Err(jni::SignalJniError::Cancelled)
+                        }
+                    }
                 }));
                 // Pass the stored inputs to the reporter to drop them while attached to the JVM.
 
@@ -3,7 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-//! Generates C, Java, and Node entry points for Rust functions.
+//! Generates C, Java, Node, Python, and wasm entry points for Rust functions.
 //!
 //! The goal of the `bridge_fn` family of macros is to define a cross-language glue layer using
 //! strongly-typed Rust code. You can write a normal top-level Rust function exposing a particular
@@ -81,6 +81,10 @@
 //!   its build.rs. The value should be something like `Java_org_signal_libsignal_internal_Native_`
 //!   to expose the function as a static method of the class `org.signal.libsignal.internal.Native`.
 //! - Node: Use the original function's name.
+//! - Python: Use the original function's name. Unlike the other bridges, Python entry points are
+//!   only generated on request (see below).
+//! - wasm: Use the Node name, so that the TypeScript wrappers can be used with either bridge. Like
+//!   Python, wasm entry points are only generated on request.
 //!
 //! As such, the recommended naming scheme for `bridge_fn` functions is `ObjectOrGroup_Operation`.
 //!
@@ -106,6 +110,12 @@
 //! validate all packages by enabling all three bridges at once. Instead, you can write e.g.
 //! `bridge_fn(jni = false)` to keep from exposing a particular function to Java.
 //!
+//! The Python bridge is for server-side tooling and only covers a small part of the API, so it
+//! works the other way around: a function is exposed to Python only if it is marked with
+//! `bridge_fn(python = true)`, or given an explicit name with `bridge_fn(python = "Name")`. The
+//! wasm bridge, which exposes a subset of the Node API to JavaScript environments that can't load
+//! the Node addon, works the same way with `bridge_fn(wasm = true)`.
+//!
 //! # Adding new argument and result types
 //!
 //! If your argument or result type is a Rust value being wrapped in an opaque box, declare it
@@ -121,16 +131,31 @@
 //!     - `ffi::ArgTypeInfo`
 //!     - `jni::ArgTypeInfo`
 //!     - `node::ArgTypeInfo` and/or `node::AsyncArgTypeInfo`
+//!     - `python::ArgTypeInfo`
+//!     - `wasm::ArgTypeInfo` or `wasm::RefArgTypeInfo`
 //!
 //!     Similarly, result types conform to one or more of the following:
 //!
 //!     - `ffi::ResultTypeInfo`
 //!     - `jni::ResultTypeInfo`
 //!     - `node::ResultTypeInfo`
+//!     - `python::ResultTypeInfo`
+//!     - `wasm::ResultTypeInfo`
 //!
 //!    These traits define how to convert between the bridge type and the Rust type used in the
 //!    function as written. See each individual trait for more info on how to add a new type.
 //!
+//! # Instrumentation
+//!
+//! Every generated entry point records its wall-clock duration under the original function's name
+//! when instrumentation is enabled at runtime (see `support::instrumentation`). Asynchronous entry
+//! points also record their queue latency: the time between the call from the app and the future
+//! first being polled on its runtime. This is off by default, and costs one atomic load per call
+//! when off.
+//!
+//! Because of this, the generated code refers to `support::instrumentation`, so every module using
+//! these macros must have `support` in scope (usually through `use crate::*`).
+//!
 //! # Limitations
 //!
 //! - There is no support for multiple return values, even though some of the FFI entry points
@@ -147,7 +172,9 @@ use syn_mid::ItemFn;
 mod ffi;
 mod jni;
 mod node;
+mod python;
 mod util;
+mod wasm;
 
 fn value_for_meta_key<'a>(
     meta_values: &'a Punctuated<MetaNameValue, Token![,]>,
@@ -178,6 +205,24 @@ fn name_for_meta_key(
     }
 }
 
+/// Like [`name_for_meta_key`], but for bridges that are off unless requested.
+///
+/// `key = true` selects the default name, and leaving out `key` is the same as `key = false`.
+fn opt_in_name_for_meta_key(
+    meta_values: &Punctuated<MetaNameValue, Token![,]>,
+    key: &str,
+    default: impl FnOnce() -> String,
+) -> Result<Option<String>> {
+    match value_for_meta_key(meta_values, key) {
+        None => Ok(None),
+        Some(Expr::Lit(ExprLit {
+            lit: Lit::Bool(LitBool { value: true, .. }),
+            ..
+        })) => Ok(Some(default())),
+        Some(_) => name_for_meta_key(meta_values, key, default),
+    }
+}
+
 #[derive(Clone, Copy)]
 #[cfg_attr(test, derive(Debug, PartialEq))]
 enum ResultKind {
@@ -302,11 +347,33 @@ fn bridge_fn_impl(
         Ok(name) => name,
         Err(error) => return error.to_compile_error().into(),
     };
+    let python_name = match opt_in_name_for_meta_key(&item_names, "python", || {
+        python::name_from_ident(&function.sig.ident)
+    }) {
+        Ok(name) => name,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    let wasm_name = match opt_in_name_for_meta_key(&item_names, "wasm", || {
+        node_name
+            .clone()
+            .unwrap_or_else(|| wasm::name_from_ident(&function.sig.ident))
+    }) {
+        Ok(name) => name,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let ffi_feature = ffi_name.as_ref().map(|_| quote!(feature = "ffi"));
     let jni_feature = jni_name.as_ref().map(|_| quote!(feature = "jni"));
     let node_feature = node_name.as_ref().map(|_| quote!(feature = "node"));
-    let maybe_features = [ffi_feature, jni_feature, node_feature];
+    let python_feature = python_name.as_ref().map(|_| quote!(feature = "python"));
+    let wasm_feature = wasm_name.as_ref().map(|_| quote!(feature = "wasm"));
+    let maybe_features = [
+        ffi_feature,
+        jni_feature,
+        node_feature,
+        python_feature,
+        wasm_feature,
+    ];
     let feature_list = maybe_features.iter().flatten();
 
     // We could early-exit on the Errors returned from generating each wrapper,
@@ -323,6 +390,14 @@ fn bridge_fn_impl(
         node::bridge_fn(&name, &function.sig, &bridging_kind)
             .unwrap_or_else(Error::into_compile_error)
     });
+    let python_fn = python_name.map(|name| {
+        python::bridge_fn(&name, &function.sig, &bridging_kind)
+            .unwrap_or_else(Error::into_compile_error)
+    });
+    let wasm_fn = wasm_name.map(|name| {
+        wasm::bridge_fn(&name, &function.sig, &bridging_kind)
+            .unwrap_or_else(Error::into_compile_error)
+    });
 
     quote!(
         #[allow(non_snake_case, clippy::needless_pass_by_ref_mut)]
@@ -335,11 +410,16 @@ fn bridge_fn_impl(
         #jni_fn
 
         #node_fn
+
+        #python_fn
+
+        #wasm_fn
     )
     .into()
 }
 
-/// Generates C, Java, and Node entry points for a Rust function that returns a value.
+/// Generates C, Java, Node, and (optionally) Python and wasm entry points for a Rust function that
+/// returns a value.
 ///
 /// See the [crate-level documentation](crate) for more information.
 ///
@@ -471,3 +551,38 @@ mod return_type_test {
         }
     }
 }
+
+#[cfg(test)]
+mod opt_in_name_test {
+    use super::*;
+
+    fn python_name(attr: proc_macro2::TokenStream) -> Option<String> {
+        let item_names: Punctuated<MetaNameValue, Token![,]> = parse_quote!(#attr);
+        opt_in_name_for_meta_key(&item_names, "python", || "Default".to_owned()).expect("valid")
+    }
+
+    #[test]
+    fn off_unless_requested() {
+        assert_eq!(python_name(quote!()), None);
+        assert_eq!(python_name(quote!(ffi = "other")), None);
+        assert_eq!(python_name(quote!(python = false)), None);
+    }
+
+    #[test]
+    fn requested() {
+        assert_eq!(
+            python_name(quote!(python = true)),
+            Some("Default".to_owned())
+        );
+        assert_eq!(
+            python_name(quote!(jni = false, python = "Custom")),
+            Some("Custom".to_owned())
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let item_names: Punctuated<MetaNameValue, Token![,]> = parse_quote!(python = 1);
+        assert!(opt_in_name_for_meta_key(&item_names, "python", String::new).is_err());
+    }
+}
@@ -23,6 +23,7 @@ fn bridge_fn_body(orig_name: &Ident, input_args: &[(&Ident, &Type)]) -> TokenStr
     let input_names = input_args.iter().map(|(name, _ty)| name);
 
     quote! {
+        let __timer = support::instrumentation::CallTimer::start(stringify!(#orig_name));
         #(#input_processing)*
         let __result = #orig_name(#(#input_names),*);
         match TransformHelper(__result).ok_if_needed() {
@@ -132,6 +133,7 @@ fn bridge_fn_async_body(
     });
 
     quote! {
+        let __queued = support::instrumentation::QueuedCall::enqueue(stringify!(#orig_name));
         #set_up_async_runtime
         // Use a RefCell so that the early-exit cleanup functions can reference the Neon context
         // without taking ownership.
@@ -145,6 +147,7 @@ fn bridge_fn_async_body(
             async_runtime,
             #custom_name,
             |__cancel| async move {
+                let __timer = __queued.map(support::instrumentation::QueuedCall::start);
                 // Wrap the actual work to catch any panics.
                 let __future = node::catch_unwind(std::panic::AssertUnwindSafe(async {
                     #(#input_loading)*
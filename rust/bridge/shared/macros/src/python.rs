@@ -0,0 +1,94 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::*;
+use syn::*;
+use syn_mid::Signature;
+
+use crate::util::extract_arg_names_and_types;
+use crate::BridgingKind;
+
+pub(crate) fn bridge_fn(
+    name: &str,
+    sig: &Signature,
+    bridging_kind: &BridgingKind,
+) -> Result<TokenStream2> {
+    // Scroll down to the end of the function to see the quote template.
+    // This is the best way to understand what we're trying to produce.
+
+    if let BridgingKind::Io { .. } = bridging_kind {
+        return Err(Error::new(
+            sig.ident.span(),
+            format_args!(
+                "'{}' cannot be exposed to Python; #[bridge_io] is not supported there",
+                sig.ident
+            ),
+        ));
+    }
+
+    let wrapper_name = format_ident!("__bridge_fn_python_{}", name);
+    let registration_name = format_ident!("signal_register_python_{}", name);
+    let orig_name = &sig.ident;
+
+    let input_names_and_types = extract_arg_names_and_types(sig)?;
+    let arg_count = input_names_and_types.len();
+
+    let input_processing = input_names_and_types
+        .iter()
+        .zip(0..)
+        .map(|((name, ty), i)| generate_code_to_load_input(name, ty, i));
+
+    let input_names = input_names_and_types.iter().map(|(name, _ty)| name);
+
+    // "Support" async operations by requiring them to complete synchronously.
+    let await_if_needed = sig.asyncness.map(|_| {
+        quote! {
+            use ::futures_util::future::FutureExt as _;
+            let __result = __result.now_or_never().unwrap();
+        }
+    });
+
+    let name_str = LitStr::new(name, Span::call_site());
+
+    Ok(quote! {
+        #[cfg(feature = "python")]
+        #[allow(non_snake_case)]
+        fn #wrapper_name(
+            py: python::Python<'_>,
+            args: &python::Bound<'_, python::PyTuple>,
+        ) -> python::PyResult<python::PyObject> {
+            python::check_arg_count(#name_str, args, #arg_count)?;
+            let __timer = support::instrumentation::CallTimer::start(stringify!(#orig_name));
+            #(#input_processing)*
+            let __result = #orig_name(#(#input_names),*);
+            #await_if_needed;
+            python::ResultTypeInfo::convert_into(__result, py)
+        }
+
+        #[cfg(feature = "python")]
+        #[no_mangle] // necessary because we are linking as a cdylib
+        #[allow(non_upper_case_globals)]
+        #[linkme::distributed_slice(python::LIBSIGNAL_FNS)]
+        static #registration_name: (&str, python::PyFn) = (#name_str, #wrapper_name);
+    })
+}
+
+/// Produces code to load an input of type `ty` from positional argument #`arg_index` into a local
+/// variable named `name`.
+fn generate_code_to_load_input(name: &Ident, ty: &Type, arg_index: usize) -> TokenStream2 {
+    let name_arg = format_ident!("{}_arg", name);
+    let name_stored = format_ident!("{}_stored", name);
+    quote! {
+        // See python::ArgTypeInfo for information on this two-step process.
+        let #name_arg = python::PyTupleMethods::get_item(args, #arg_index)?;
+        let mut #name_stored = <#ty as python::ArgTypeInfo>::borrow(&#name_arg)?;
+        let #name = <#ty as python::ArgTypeInfo>::load_from(&mut #name_stored);
+    }
+}
+
+pub(crate) fn name_from_ident(ident: &Ident) -> String {
+    ident.to_string()
+}
@@ -0,0 +1,121 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::*;
+use syn::spanned::Spanned;
+use syn::*;
+use syn_mid::Signature;
+
+use crate::util::extract_arg_names_and_types;
+use crate::BridgingKind;
+
+pub(crate) fn bridge_fn(
+    name: &str,
+    sig: &Signature,
+    bridging_kind: &BridgingKind,
+) -> Result<TokenStream2> {
+    // Scroll down to the end of the function to see the quote template.
+    // This is the best way to understand what we're trying to produce.
+
+    if let BridgingKind::Io { .. } = bridging_kind {
+        return Err(Error::new(
+            sig.ident.span(),
+            format_args!(
+                "'{}' cannot be exposed to wasm; #[bridge_io] is not supported there",
+                sig.ident
+            ),
+        ));
+    }
+
+    let wrapper_name = format_ident!("wasm_{}", name);
+    let orig_name = &sig.ident;
+
+    let input_names_and_types = extract_arg_names_and_types(sig)?;
+
+    let params = input_names_and_types
+        .iter()
+        .map(|(name, ty)| generate_param(name, ty))
+        .collect::<Result<Vec<_>>>()?;
+
+    let input_processing = input_names_and_types
+        .iter()
+        .map(|(name, ty)| generate_code_to_load_input(name, ty));
+
+    let input_names = input_names_and_types.iter().map(|(name, _ty)| name);
+
+    // "Support" async operations by requiring them to complete synchronously.
+    let await_if_needed = sig.asyncness.map(|_| {
+        quote! {
+            use ::futures_util::future::FutureExt as _;
+            let __result = __result.now_or_never().unwrap();
+        }
+    });
+
+    let name_str = LitStr::new(name, Span::call_site());
+
+    Ok(quote! {
+        #[cfg(feature = "wasm")]
+        #[wasm::wasm_bindgen::prelude::wasm_bindgen(
+            js_name = #name_str,
+            wasm_bindgen = wasm::wasm_bindgen,
+        )]
+        #[allow(non_snake_case)]
+        pub fn #wrapper_name(
+            #(#params),*
+        ) -> ::std::result::Result<wasm::JsValue, wasm::JsValue> {
+            let __timer = support::instrumentation::CallTimer::start(stringify!(#orig_name));
+            #(#input_processing)*
+            let __result = #orig_name(#(#input_names),*);
+            #await_if_needed;
+            wasm::ResultTypeInfo::convert_into(__result)
+                .map_err(|e| wasm::with_operation(e, #name_str))
+        }
+    })
+}
+
+/// Produces the parameter declaration for an input of type `ty`.
+///
+/// Shared references are passed as references so that handles stay owned by JavaScript; see
+/// `wasm::RefArgTypeInfo`. Everything else is passed by value; see `wasm::WasmArgType`.
+fn generate_param(name: &Ident, ty: &Type) -> Result<TokenStream2> {
+    match ty {
+        Type::Reference(TypeReference {
+            mutability: None,
+            elem,
+            ..
+        }) => Ok(quote!(#name: &<#elem as wasm::RefArgTypeInfo>::ArgType)),
+        Type::Reference(TypeReference {
+            mutability: Some(_),
+            ..
+        }) => Err(Error::new(
+            ty.span(),
+            "mutable references cannot be passed from wasm",
+        )),
+        _ => Ok(quote!(#name: <#ty as wasm::WasmArgType>::ArgType)),
+    }
+}
+
+/// Produces code to load an input of type `ty` from the parameter `name` into a local variable
+/// with the same name.
+fn generate_code_to_load_input(name: &Ident, ty: &Type) -> TokenStream2 {
+    match ty {
+        Type::Reference(TypeReference { elem, .. }) => quote! {
+            let #name = <#elem as wasm::RefArgTypeInfo>::load_from(#name)?;
+        },
+        _ => {
+            let name_stored = format_ident!("{}_stored", name);
+            quote! {
+                // See wasm::ArgTypeInfo for information on this two-step process.
+                let mut #name_stored = <#ty as wasm::ArgTypeInfo>::borrow(#name)?;
+                let #name = <#ty as wasm::ArgTypeInfo>::load_from(&mut #name_stored);
+            }
+        }
+    }
+}
+
+pub(crate) fn name_from_ident(ident: &Ident) -> String {
+    ident.to_string()
+}
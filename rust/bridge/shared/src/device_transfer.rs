@@ -3,7 +3,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use ::device_transfer::{self, KeyFormat};
+use std::time::Duration;
+
+use ::device_transfer::{self, CertificateParams, KeyFormat, SerialNumber};
 use libsignal_bridge_macros::*;
 
 // Not used by the Java bridge.
@@ -27,11 +29,53 @@ fn DeviceTransfer_GeneratePrivateKeyWithFormat(
     device_transfer::create_rsa_private_key(DEVICE_TRANSFER_KEY_BITS, KeyFormat::from(key_format))
 }
 
+#[bridge_fn(node = false)]
+fn DeviceTransfer_GenerateEcPrivateKey() -> Vec<u8> {
+    device_transfer::create_ec_private_key(KeyFormat::Pkcs8).expect("no internal failures")
+}
+
+#[bridge_fn(node = false, jni = false)]
+fn DeviceTransfer_GenerateEcPrivateKeyWithFormat(
+    key_format: u8,
+) -> Result<Vec<u8>, device_transfer::Error> {
+    device_transfer::create_ec_private_key(KeyFormat::from(key_format))
+}
+
 #[bridge_fn(node = false)]
 fn DeviceTransfer_GenerateCertificate(
     private_key: &[u8],
     name: String,
     days_to_expire: u32,
 ) -> Result<Vec<u8>, device_transfer::Error> {
-    device_transfer::create_self_signed_cert(private_key, &name, days_to_expire)
+    let params = CertificateParams {
+        key_type: device_transfer::key_type(private_key)?,
+        ..CertificateParams::new(&name, days_to_expire)
+    };
+    device_transfer::create_self_signed_cert(private_key, &params)
+}
+
+#[bridge_fn(node = false)]
+fn DeviceTransfer_GenerateCertificateWithParams(
+    private_key: &[u8],
+    common_name: String,
+    organization: Option<String>,
+    organizational_unit: Option<String>,
+    days_to_expire: u32,
+    backdate_seconds: u32,
+    random_serial_number: bool,
+) -> Result<Vec<u8>, device_transfer::Error> {
+    let params = CertificateParams {
+        common_name,
+        organization,
+        organizational_unit,
+        validity_days: days_to_expire,
+        backdate: Duration::from_secs(backdate_seconds.into()),
+        serial_number: if random_serial_number {
+            SerialNumber::Random
+        } else {
+            SerialNumber::Zero
+        },
+        key_type: device_transfer::key_type(private_key)?,
+    };
+    device_transfer::create_self_signed_cert(private_key, &params)
 }
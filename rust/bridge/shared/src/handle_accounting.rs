@@ -0,0 +1,47 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::time::Duration;
+
+use libsignal_bridge_macros::*;
+use libsignal_bridge_types::support::handle_accounting;
+
+#[allow(unused_imports)]
+use crate::support::*;
+use crate::*;
+
+// Node handles are owned by the JavaScript garbage collector, so there's nothing to account for.
+
+#[bridge_fn(node = false)]
+fn HandleAccounting_SetEnabled(enabled: bool) {
+    handle_accounting::set_tracking_enabled(enabled)
+}
+
+/// Passing 0 turns off leak detection.
+#[bridge_fn(node = false)]
+fn HandleAccounting_SetLeakThresholdSeconds(threshold_secs: u32) {
+    handle_accounting::set_leak_threshold(
+        (threshold_secs != 0).then(|| Duration::from_secs(threshold_secs.into())),
+    )
+}
+
+/// Returns the live tracked handles as a JSON object keyed by type name.
+///
+/// Each type has `count`, `approximate_bytes`, and `oldest_age_secs` fields.
+#[bridge_fn(node = false)]
+fn HandleAccounting_SnapshotJson() -> String {
+    let types = handle_accounting::snapshot()
+        .into_iter()
+        .map(|(type_name, stats)| {
+            let value = serde_json::json!({
+                "count": stats.count,
+                "approximate_bytes": stats.approximate_bytes,
+                "oldest_age_secs": stats.oldest_age.as_secs(),
+            });
+            (type_name.to_string(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::Value::Object(types).to_string()
+}
@@ -0,0 +1,49 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use libsignal_bridge_macros::*;
+use libsignal_bridge_types::support::instrumentation::{self, Histogram};
+
+#[allow(unused_imports)]
+use crate::support::*;
+use crate::*;
+
+#[bridge_fn]
+fn Instrumentation_SetEnabled(enabled: bool) {
+    instrumentation::set_enabled(enabled)
+}
+
+#[bridge_fn]
+fn Instrumentation_Reset() {
+    instrumentation::reset()
+}
+
+/// Returns the recorded timings as a JSON object keyed by operation name.
+///
+/// Each operation has a `duration` and a `queue_latency` histogram, each of which has `count`,
+/// `total_micros`, `max_micros`, and `buckets` fields. See [`Histogram`] for the bucket boundaries.
+#[bridge_fn]
+fn Instrumentation_SnapshotJson() -> String {
+    fn histogram_json(histogram: &Histogram) -> serde_json::Value {
+        serde_json::json!({
+            "count": histogram.count,
+            "total_micros": histogram.total_micros,
+            "max_micros": histogram.max_micros,
+            "buckets": histogram.buckets.as_slice(),
+        })
+    }
+
+    let operations = instrumentation::snapshot()
+        .iter()
+        .map(|(name, timings)| {
+            let value = serde_json::json!({
+                "duration": histogram_json(&timings.duration),
+                "queue_latency": histogram_json(&timings.queue_latency),
+            });
+            (name.to_string(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::Value::Object(operations).to_string()
+}
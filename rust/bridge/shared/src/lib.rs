@@ -5,10 +5,27 @@
 
 #![allow(clippy::missing_safety_doc)]
 #![deny(clippy::unwrap_used)]
+// Python and wasm only expose the functions that opt in, leaving the rest unused.
+#![cfg_attr(
+    not(any(feature = "ffi", feature = "jni", feature = "node")),
+    allow(dead_code, unused_imports)
+)]
 
-#[cfg(not(any(feature = "ffi", feature = "jni", feature = "node")))]
-compile_error!("Feature \"ffi\", \"jni\", or \"node\" must be enabled for this crate.");
+#[cfg(not(any(
+    feature = "ffi",
+    feature = "jni",
+    feature = "node",
+    feature = "python",
+    feature = "wasm"
+)))]
+compile_error!(
+    "Feature \"ffi\", \"jni\", \"node\", \"python\", or \"wasm\" must be enabled for this crate."
+);
 
+#[cfg(feature = "python")]
+pub use libsignal_bridge_types::python;
+#[cfg(feature = "wasm")]
+pub use libsignal_bridge_types::wasm;
 pub use libsignal_bridge_types::{
     bridge_as_handle, bridge_deserialize, bridge_fixed_length_serializable_fns, bridge_get,
     bridge_handle_fns, bridge_serializable_handle_fns, describe_panic, io, support,
@@ -20,6 +37,8 @@ pub use libsignal_bridge_types::{jni, jni_arg_type, jni_args, jni_class_name, jn
 #[cfg(feature = "node")]
 pub use libsignal_bridge_types::{node, node_register};
 
+pub mod handle_accounting;
+pub mod instrumentation;
 pub mod logging;
 
 pub mod crypto;
@@ -29,8 +48,12 @@ pub mod protocol;
 #[cfg(any(feature = "jni", feature = "ffi"))]
 pub mod device_transfer;
 
+// These depend on BoringSSL and tokio's multi-threaded runtime, which can't be built for wasm.
+#[cfg(not(target_arch = "wasm32"))]
 mod cds2;
+#[cfg(not(target_arch = "wasm32"))]
 mod hsm_enclave;
+#[cfg(not(target_arch = "wasm32"))]
 mod sgx_session;
 
 pub mod zkgroup;
@@ -38,6 +61,7 @@ pub mod zkgroup;
 #[cfg(feature = "ffi")]
 pub mod ias;
 
+#[cfg(not(target_arch = "wasm32"))]
 pub mod net;
 
 // Desktop does not use SVR
@@ -3,43 +3,229 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-/// An implementation of [`log::Log::enabled`] suitable for production Signal apps.
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// The level most recently set by [`set_max_level`], ignoring any per-target overrides.
 ///
-/// Apps may apply additional logging filters on top of what libsignal reports.
-pub fn log_enabled_in_apps(metadata: &log::Metadata) -> bool {
-    let target = metadata.target();
+/// Stored as a `usize` because `log::LevelFilter` converts to one losslessly. Defaults to
+/// `Trace` so that filtering falls back to [`log::max_level`] when no bridge has set a level.
+static BASE_MAX_LEVEL: AtomicUsize = AtomicUsize::new(log::LevelFilter::Trace as usize);
+
+/// Whether [`TARGET_LEVELS`] is non-empty, to skip taking the lock on every log in the common case.
+static HAS_TARGET_LEVELS: AtomicBool = AtomicBool::new(false);
+static TARGET_LEVELS: RwLock<Vec<(String, log::LevelFilter)>> = RwLock::new(Vec::new());
+
+/// Whether [`RATE_LIMITS`] is non-empty, to skip taking the lock on every log in the common case.
+static HAS_RATE_LIMITS: AtomicBool = AtomicBool::new(false);
+static RATE_LIMITS: Mutex<Vec<RateLimit>> = Mutex::new(Vec::new());
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+
+/// Checks whether `target` is `prefix` or a module within `prefix`.
+///
+/// Accepts both "prefix" and "prefix::something".
+fn target_matches(target: &str, prefix: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|remainder| remainder.is_empty() || remainder.starts_with("::"))
+}
+
+fn base_max_level() -> log::LevelFilter {
+    match BASE_MAX_LEVEL.load(Ordering::Relaxed) {
+        0 => log::LevelFilter::Off,
+        1 => log::LevelFilter::Error,
+        2 => log::LevelFilter::Warn,
+        3 => log::LevelFilter::Info,
+        4 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    }
+}
+
+/// Updates [`log::max_level`] to admit anything that the base level or any override admits.
+fn update_global_max_level(target_levels: &[(String, log::LevelFilter)]) {
+    let max_override = target_levels.iter().map(|(_, level)| *level).max();
+    let base = base_max_level();
+    log::set_max_level(max_override.map_or(base, |level| level.max(base)));
+}
+
+/// Sets the most verbose level that will be logged for targets without a more specific level.
+///
+/// Bridges should use this instead of [`log::set_max_level`], so that per-target levels set with
+/// [`set_target_level`] keep working.
+pub fn set_max_level(level: log::LevelFilter) {
+    BASE_MAX_LEVEL.store(level as usize, Ordering::Relaxed);
+    let target_levels = TARGET_LEVELS.read().expect("not poisoned");
+    update_global_max_level(&target_levels);
+}
+
+/// Overrides the level for `target` and any modules within it, or removes the override if `level`
+/// is `None`.
+///
+/// When several overrides match a target, the most specific one wins. Overrides can make a
+/// target more or less verbose than the level passed to [`set_max_level`], but they never enable
+/// targets that [`log_enabled_in_apps`] would otherwise reject outright.
+pub fn set_target_level(target: &str, level: Option<log::LevelFilter>) {
+    let mut target_levels = TARGET_LEVELS.write().expect("not poisoned");
+    target_levels.retain(|(existing, _)| existing != target);
+    if let Some(level) = level {
+        target_levels.push((target.to_owned(), level));
+    }
+    HAS_TARGET_LEVELS.store(!target_levels.is_empty(), Ordering::Relaxed);
+    update_global_max_level(&target_levels);
+}
+
+fn target_level_override(target: &str) -> Option<log::LevelFilter> {
+    if !HAS_TARGET_LEVELS.load(Ordering::Relaxed) {
+        return None;
+    }
+    let target_levels = TARGET_LEVELS.read().expect("not poisoned");
+    target_levels
+        .iter()
+        .filter(|(prefix, _)| target_matches(target, prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, level)| *level)
+}
+
+/// The most verbose level libsignal reports for `target` by default, or `None` if it shouldn't be
+/// logged at all.
+fn default_max_level(target: &str) -> Option<log::LevelFilter> {
     if target.is_empty() {
-        return false;
+        return None;
     }
 
-    let check = |crate_name: &str| {
-        // Accept both "crate_name" and "crate_name::something".
-        target
-            .strip_prefix(crate_name)
-            .is_some_and(|remainder| remainder.is_empty() || remainder.starts_with("::"))
-    };
+    let check = |crate_name: &str| target_matches(target, crate_name);
+    let all = Some(log::LevelFilter::Trace);
 
     // Use a manual jump table to reduce the number of checks we perform on each log message.
     // (The compiler can optimize some switches on strings, but it's hard to convince it to do
     // something smart for checking prefixes *and* exact matches.)
     match target.as_bytes()[0] {
         // libsignal naming patterns:
-        b'l' => target.starts_with("libsignal_"),
-        b's' => target.starts_with("signal_"),
+        b'l' if target.starts_with("libsignal_") => all,
+        b's' if target.starts_with("signal_") => all,
 
         // Other libsignal crates:
-        b'a' => check("attest"),
-        b'd' => check("device_transfer"),
-        b'p' => check("poksho"),
-        b'u' => check("usernames"),
-        b'z' => check("zkgroup") || check("zkcredential"),
+        b'a' if check("attest") => all,
+        b'd' if check("device_transfer") => all,
+        b'p' if check("poksho") => all,
+        b'u' if check("usernames") => all,
+        b'z' if check("zkgroup") || check("zkcredential") => all,
 
         // mediasan crates (only show warnings and errors):
-        b'm' if check("mediasan_common") || check("mp4san") => metadata.level() <= log::Level::Warn,
-        b'w' if check("webpsan") => metadata.level() <= log::Level::Warn,
+        b'm' if check("mediasan_common") || check("mp4san") => Some(log::LevelFilter::Warn),
+        b'w' if check("webpsan") => Some(log::LevelFilter::Warn),
 
         // Otherwise...
-        _ => false,
+        _ => None,
+    }
+}
+
+/// An implementation of [`log::Log::enabled`] suitable for production Signal apps.
+///
+/// Apps may apply additional logging filters on top of what libsignal reports, and may adjust the
+/// level for particular targets with [`set_target_level`].
+pub fn log_enabled_in_apps(metadata: &log::Metadata) -> bool {
+    let target = metadata.target();
+    let Some(default_level) = default_max_level(target) else {
+        return false;
+    };
+    let max_level =
+        target_level_override(target).unwrap_or_else(|| default_level.min(base_max_level()));
+    metadata.level() <= max_level
+}
+
+struct RateLimit {
+    target: String,
+    max_per_window: u32,
+    window_start: Option<Instant>,
+    logged_in_window: u32,
+    dropped: u64,
+}
+
+/// The outcome of checking a log against the limits set with [`set_target_rate_limit`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    /// The log should be delivered.
+    Log,
+    /// The log should be delivered, but earlier logs for the same target were dropped.
+    ///
+    /// Loggers should report the count (see [`RateLimitDecision::dropped_logs_message`]) so that
+    /// gaps in the logs are explained.
+    LogAfterDropping(u64),
+    /// The log should be discarded.
+    Drop,
+}
+
+impl RateLimitDecision {
+    /// A message describing how many logs were dropped for `target`, if any.
+    pub fn dropped_logs_message(&self, target: &str) -> Option<String> {
+        match self {
+            Self::LogAfterDropping(count) => Some(format!(
+                "rate limit exceeded; dropped {count} log(s) from {target}"
+            )),
+            Self::Log | Self::Drop => None,
+        }
+    }
+}
+
+/// Limits `target` and any modules within it to `max_per_second` logs each second, or removes the
+/// limit if `max_per_second` is `None`.
+///
+/// As with [`set_target_level`], the most specific matching limit applies. Logs from all targets
+/// sharing a limit count against the same budget.
+pub fn set_target_rate_limit(target: &str, max_per_second: Option<u32>) {
+    let mut limits = RATE_LIMITS.lock().expect("not poisoned");
+    limits.retain(|limit| limit.target != target);
+    if let Some(max_per_window) = max_per_second {
+        limits.push(RateLimit {
+            target: target.to_owned(),
+            max_per_window,
+            window_start: None,
+            logged_in_window: 0,
+            dropped: 0,
+        });
+    }
+    HAS_RATE_LIMITS.store(!limits.is_empty(), Ordering::Relaxed);
+}
+
+/// Checks (and records) a log for `target` against the limits set with [`set_target_rate_limit`].
+///
+/// Should only be called for logs that are otherwise going to be delivered.
+pub fn check_rate_limit(target: &str) -> RateLimitDecision {
+    check_rate_limit_at(target, Instant::now())
+}
+
+fn check_rate_limit_at(target: &str, now: Instant) -> RateLimitDecision {
+    if !HAS_RATE_LIMITS.load(Ordering::Relaxed) {
+        return RateLimitDecision::Log;
+    }
+    let mut limits = RATE_LIMITS.lock().expect("not poisoned");
+    let Some(limit) = limits
+        .iter_mut()
+        .filter(|limit| target_matches(target, &limit.target))
+        .max_by_key(|limit| limit.target.len())
+    else {
+        return RateLimitDecision::Log;
+    };
+
+    let window_expired = limit.window_start.map_or(true, |start| {
+        now.saturating_duration_since(start) >= RATE_LIMIT_WINDOW
+    });
+    if window_expired {
+        limit.window_start = Some(now);
+        limit.logged_in_window = 0;
+    }
+
+    if limit.logged_in_window >= limit.max_per_window {
+        limit.dropped += 1;
+        return RateLimitDecision::Drop;
+    }
+    limit.logged_in_window += 1;
+    match std::mem::take(&mut limit.dropped) {
+        0 => RateLimitDecision::Log,
+        dropped => RateLimitDecision::LogAfterDropping(dropped),
     }
 }
 
@@ -130,4 +316,94 @@ mod tests {
     fn rejected_double_colon() {
         rejected("::")
     }
+
+    // The following tests modify global state, so each uses its own target.
+
+    #[test]
+    fn per_target_levels() {
+        let metadata = |target: &'static str, level| {
+            log::Metadata::builder().target(target).level(level).build()
+        };
+
+        set_target_level("libsignal_override_test", Some(log::LevelFilter::Warn));
+        assert!(log_enabled_in_apps(&metadata(
+            "libsignal_override_test",
+            log::Level::Warn
+        )));
+        assert!(!log_enabled_in_apps(&metadata(
+            "libsignal_override_test::inner",
+            log::Level::Info
+        )));
+        assert!(log_enabled_in_apps(&metadata(
+            "libsignal_override_test_other",
+            log::Level::Info
+        )));
+
+        // The more specific override wins.
+        set_target_level(
+            "libsignal_override_test::inner",
+            Some(log::LevelFilter::Trace),
+        );
+        assert!(log_enabled_in_apps(&metadata(
+            "libsignal_override_test::inner",
+            log::Level::Trace
+        )));
+        assert!(!log_enabled_in_apps(&metadata(
+            "libsignal_override_test::other",
+            log::Level::Info
+        )));
+
+        set_target_level("libsignal_override_test", None);
+        set_target_level("libsignal_override_test::inner", None);
+        assert!(log_enabled_in_apps(&metadata(
+            "libsignal_override_test::other",
+            log::Level::Info
+        )));
+    }
+
+    #[test]
+    fn target_level_override_cannot_enable_dependencies() {
+        set_target_level("override_dependency_test", Some(log::LevelFilter::Trace));
+        assert!(!log_enabled_in_apps(
+            &log::Metadata::builder()
+                .target("override_dependency_test")
+                .level(log::Level::Error)
+                .build()
+        ));
+        set_target_level("override_dependency_test", None);
+    }
+
+    #[test]
+    fn rate_limit() {
+        const TARGET: &str = "libsignal_rate_limit_test";
+        set_target_rate_limit(TARGET, Some(2));
+
+        let start = Instant::now();
+        assert_eq!(RateLimitDecision::Log, check_rate_limit_at(TARGET, start));
+        assert_eq!(
+            RateLimitDecision::Log,
+            check_rate_limit_at(&format!("{TARGET}::inner"), start)
+        );
+        assert_eq!(RateLimitDecision::Drop, check_rate_limit_at(TARGET, start));
+        assert_eq!(RateLimitDecision::Drop, check_rate_limit_at(TARGET, start));
+        assert_eq!(
+            RateLimitDecision::Log,
+            check_rate_limit_at("libsignal_rate_limit_test_other", start)
+        );
+
+        let next_window = start + RATE_LIMIT_WINDOW;
+        let decision = check_rate_limit_at(TARGET, next_window);
+        assert_eq!(RateLimitDecision::LogAfterDropping(2), decision);
+        assert_eq!(
+            Some("rate limit exceeded; dropped 2 log(s) from libsignal_rate_limit_test"),
+            decision.dropped_logs_message(TARGET).as_deref()
+        );
+        assert_eq!(
+            RateLimitDecision::Log,
+            check_rate_limit_at(TARGET, next_window)
+        );
+
+        set_target_rate_limit(TARGET, None);
+        assert_eq!(RateLimitDecision::Log, check_rate_limit_at(TARGET, start));
+    }
 }
@@ -22,19 +22,19 @@ bridge_handle_fns!(
     node = false
 );
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn MessageBackupKey_New(master_key: &[u8; 32], aci: Aci) -> MessageBackupKey {
     MessageBackupKey::new(master_key, aci)
 }
 
-#[bridge_fn(jni = false, node = false)]
+#[bridge_fn(jni = false, node = false, python = true)]
 fn MessageBackupValidationOutcome_getErrorMessage(
     outcome: &MessageBackupValidationOutcome,
 ) -> Option<&str> {
     outcome.error_message.as_deref()
 }
 
-#[bridge_fn(jni = false, node = false)]
+#[bridge_fn(jni = false, node = false, python = true)]
 fn MessageBackupValidationOutcome_getUnknownFields(
     outcome: &MessageBackupValidationOutcome,
 ) -> Box<[String]> {
@@ -45,7 +45,7 @@ fn MessageBackupValidationOutcome_getUnknownFields(
         .collect()
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 async fn MessageBackupValidator_Validate(
     key: &MessageBackupKey,
     first_stream: &mut dyn InputStream,
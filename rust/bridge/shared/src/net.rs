@@ -11,8 +11,11 @@ use libsignal_bridge_macros::{bridge_fn, bridge_io};
 use libsignal_bridge_types::net::Svr3Clients;
 pub use libsignal_bridge_types::net::{ConnectionManager, Environment, TokioAsyncContext};
 use libsignal_net::auth::Auth;
+use libsignal_net::infra::attempt_log::{self, AttemptResult};
+use libsignal_net::infra::tcp_ssl::TlsPolicy;
 use libsignal_net::svr3::traits::*;
 use libsignal_net::svr3::{self, migrate_backup, restore_with_fallback, OpaqueMaskedShareSet};
+use libsignal_protocol::PublicKey;
 use rand::rngs::OsRng;
 
 use crate::support::*;
@@ -54,11 +57,72 @@ fn ConnectionManager_set_ipv6_enabled(connection_manager: &ConnectionManager, ip
     connection_manager.set_ipv6_enabled(ipv6_enabled)
 }
 
+/// Restricts connections to TLS 1.3 if `tls1_3_only` is set, or allows TLS 1.2 and 1.3 otherwise.
+#[bridge_fn]
+fn ConnectionManager_set_tls1_3_only(connection_manager: &ConnectionManager, tls1_3_only: bool) {
+    connection_manager.set_tls_policy(if tls1_3_only {
+        TlsPolicy::tls1_3_only()
+    } else {
+        TlsPolicy::default()
+    })
+}
+
 #[bridge_fn]
 fn ConnectionManager_on_network_change(connection_manager: &ConnectionManager) {
     connection_manager.on_network_change()
 }
 
+#[bridge_fn]
+fn ConnectionManager_set_clock_offset(connection_manager: &ConnectionManager, offset_seconds: i32) {
+    connection_manager.set_clock_offset(offset_seconds)
+}
+
+#[bridge_fn]
+fn ConnectionManager_apply_route_document(
+    connection_manager: &ConnectionManager,
+    signed_document: &[u8],
+    key: &PublicKey,
+) -> Result<(), std::io::Error> {
+    connection_manager.apply_route_document(signed_document, key)
+}
+
+/// Returns the recent connection attempts as a JSON array, oldest first.
+///
+/// Each attempt has `started_at_ms` (since the Unix epoch), `duration_ms`, `route`, `address`,
+/// `port`, and `result` fields; failed attempts also have `stage` (possibly null) and `error`.
+#[bridge_fn]
+fn ConnectionAttempts_SnapshotJson() -> String {
+    let attempts = attempt_log::recent_attempts()
+        .into_iter()
+        .map(|attempt| {
+            let started_at_ms = attempt
+                .started_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let mut value = serde_json::json!({
+                "started_at_ms": started_at_ms,
+                "duration_ms": attempt.duration.as_millis() as u64,
+                "route": attempt.route_type.to_string(),
+                "address": attempt.address.to_string(),
+                "port": attempt.port.get(),
+            });
+            let result = match attempt.result {
+                AttemptResult::Connected => "connected",
+                AttemptResult::TimedOut => "timed_out",
+                AttemptResult::Failed { stage, error } => {
+                    value["stage"] = stage.map(|stage| stage.to_string()).into();
+                    value["error"] = error.into();
+                    "failed"
+                }
+            };
+            value["result"] = result.into();
+            value
+        })
+        .collect::<Vec<_>>();
+    serde_json::Value::Array(attempts).to_string()
+}
+
 #[bridge_fn]
 fn CreateOTP(username: String, secret: &[u8]) -> String {
     Auth::otp(&username, secret, std::time::SystemTime::now())
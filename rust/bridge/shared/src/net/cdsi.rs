@@ -6,11 +6,12 @@
 use std::convert::TryInto as _;
 
 use libsignal_bridge_macros::{bridge_fn, bridge_io};
-use libsignal_bridge_types::net::cdsi::{CdsiLookup, LookupRequest};
+use libsignal_bridge_types::net::cdsi::{CdsiLookup, CdsiLookupResult, LookupRequest};
 use libsignal_bridge_types::net::{ConnectionManager, TokioAsyncContext};
 use libsignal_net::auth::Auth;
 use libsignal_net::cdsi::{self, AciAndAccessKey, LookupResponse, E164};
 use libsignal_protocol::{Aci, SignalProtocolError};
+use zkgroup::profiles::AccessKey;
 
 use crate::support::*;
 use crate::*;
@@ -43,15 +44,18 @@ fn LookupRequest_addAciAndAccessKey(
     aci: Aci,
     access_key: &[u8],
 ) -> Result<(), SignalProtocolError> {
-    let access_key = access_key
-        .try_into()
-        .map_err(|_: std::array::TryFromSliceError| {
-            SignalProtocolError::InvalidArgument("access_key has wrong number of bytes".to_string())
-        })?;
-    request
-        .lock()
-        .acis_and_access_keys
-        .push(AciAndAccessKey { aci, access_key });
+    let access_key: [u8; 16] =
+        access_key
+            .try_into()
+            .map_err(|_: std::array::TryFromSliceError| {
+                SignalProtocolError::InvalidArgument(
+                    "access_key has wrong number of bytes".to_string(),
+                )
+            })?;
+    request.lock().acis_and_access_keys.push(AciAndAccessKey {
+        aci,
+        access_key: AccessKey::from_bytes(access_key),
+    });
     Ok(())
 }
 
@@ -70,7 +74,10 @@ async fn CdsiLookup_new(
     request: &LookupRequest,
 ) -> Result<CdsiLookup, cdsi::LookupError> {
     let request = std::mem::take(&mut *request.lock());
-    let auth = Auth { username, password };
+    let auth = Auth {
+        username,
+        password: password.into(),
+    };
 
     CdsiLookup::new(connection_manager, auth, request).await
 }
@@ -88,3 +95,34 @@ async fn CdsiLookup_complete(lookup: &CdsiLookup) -> Result<LookupResponse, cdsi
         .collect()
         .await
 }
+
+bridge_handle_fns!(CdsiLookupResult, clone = false);
+
+#[bridge_io(TokioAsyncContext)]
+async fn CdsiLookup_lookup(
+    connection_manager: &ConnectionManager,
+    username: String,
+    password: String,
+    request: &LookupRequest,
+) -> Result<CdsiLookupResult, cdsi::LookupError> {
+    let request = std::mem::take(&mut *request.lock());
+    let auth = Auth {
+        username,
+        password: password.into(),
+    };
+
+    connection_manager
+        .cdsi_lookup(auth, request)
+        .await
+        .map(CdsiLookupResult::from)
+}
+
+#[bridge_fn]
+fn CdsiLookupResult_token(result: &CdsiLookupResult) -> &[u8] {
+    &result.token.0
+}
+
+#[bridge_fn]
+fn CdsiLookupResult_takeResponse(result: &CdsiLookupResult) -> LookupResponse {
+    result.take_response().expect("not taken yet")
+}
@@ -66,7 +66,10 @@ fn ChatService_new(
 ) -> Chat {
     Chat::new(
         connection_manager,
-        Auth { username, password },
+        Auth {
+            username,
+            password: password.into(),
+        },
         receive_stories,
     )
 }
@@ -3,8 +3,11 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::num::NonZeroUsize;
+use std::time::Duration;
+
 use libsignal_bridge_macros::bridge_fn;
-use libsignal_bridge_types::net::tokio::TokioAsyncContext;
+use libsignal_bridge_types::net::tokio::{TokioAsyncContext, TokioAsyncContextConfig};
 
 use crate::support::*;
 use crate::*;
@@ -16,7 +19,28 @@ fn TokioAsyncContext_new() -> TokioAsyncContext {
     TokioAsyncContext::new()
 }
 
+// Creates a runtime with custom settings.
+//
+// A `worker_threads` of 0 uses one worker thread per CPU core.
+#[bridge_fn]
+fn TokioAsyncContext_new_with_config(
+    worker_threads: u32,
+    thread_name: String,
+    use_current_thread: bool,
+) -> Result<TokioAsyncContext, std::io::Error> {
+    TokioAsyncContext::with_config(&TokioAsyncContextConfig {
+        worker_threads: NonZeroUsize::new(worker_threads.try_into().expect("u32 fits in usize")),
+        thread_name,
+        use_current_thread,
+    })
+}
+
 #[bridge_fn]
 fn TokioAsyncContext_cancel(context: &TokioAsyncContext, raw_cancellation_id: u64) {
     context.cancel(raw_cancellation_id.into())
 }
+
+#[bridge_fn]
+fn TokioAsyncContext_shutdown(context: &TokioAsyncContext, timeout_millis: u32) {
+    context.shutdown(Duration::from_millis(timeout_millis.into()))
+}
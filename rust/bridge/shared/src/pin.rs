@@ -4,7 +4,7 @@
 //
 
 use ::attest::svr2::lookup_groupid;
-use ::signal_pin::{local_pin_hash, verify_local_pin_hash, PinHash, Result};
+use ::signal_pin::{estimate_strength, local_pin_hash, verify_local_pin_hash, PinHash, Result};
 use libsignal_bridge_macros::*;
 use signal_pin::Error;
 
@@ -52,3 +52,20 @@ pub fn Pin_LocalHash(pin: &[u8]) -> Result<String> {
 pub fn Pin_VerifyLocalHash(encoded_hash: String, pin: &[u8]) -> Result<bool> {
     verify_local_pin_hash(&encoded_hash, pin)
 }
+
+/// Returns the [`signal_pin::PinStrengthLevel`] of `pin`, as its index from `VeryWeak` (0) to
+/// `Strong` (3).
+#[bridge_fn(node = false)]
+pub fn Pin_EstimateStrengthLevel(pin: String) -> u8 {
+    estimate_strength(&pin).level as u8
+}
+
+/// Returns the [`signal_pin::PinWeakness`]es found in `pin`, as a bitmask where bit `i` is set for
+/// the weakness with index `i`, starting from `TooShort` (0).
+#[bridge_fn(node = false)]
+pub fn Pin_EstimateStrengthWeaknesses(pin: String) -> u32 {
+    estimate_strength(&pin)
+        .weaknesses
+        .into_iter()
+        .fold(0, |mask, weakness| mask | (1 << weakness as u32))
+}
@@ -50,6 +50,7 @@ bridge_handle_fns!(SealedSenderDecryptionResult, ffi = false, jni = false);
 bridge_handle_fns!(KyberKeyPair);
 bridge_handle_fns!(KyberPublicKey);
 bridge_handle_fns!(KyberSecretKey);
+bridge_handle_fns!(Envelope, clone = false);
 
 #[bridge_fn(ffi = false)]
 fn HKDF_DeriveSecrets(
@@ -112,11 +113,11 @@ fn ServiceId_ParseFromServiceIdString(input: String) -> Result<ServiceId> {
 }
 
 #[bridge_fn(ffi = "address_new")]
-fn ProtocolAddress_New(name: String, device_id: u32) -> ProtocolAddress {
-    ProtocolAddress::new(name, device_id.into())
+fn ProtocolAddress_New(name: String, device_id: u32) -> Result<ProtocolAddress> {
+    Ok(ProtocolAddress::new(name, device_id.try_into()?))
 }
 
-#[bridge_fn(ffi = "publickey_deserialize", jni = false)]
+#[bridge_fn(ffi = "publickey_deserialize", jni = false, python = true, wasm = true)]
 fn PublicKey_Deserialize(data: &[u8]) -> Result<PublicKey> {
     PublicKey::deserialize(data)
 }
@@ -131,12 +132,15 @@ fn ECPublicKey_Deserialize(data: &[u8], offset: u32) -> Result<PublicKey> {
 bridge_get!(
     PublicKey::serialize as Serialize -> Vec<u8>,
     ffi = "publickey_serialize",
-    jni = "ECPublicKey_1Serialize"
+    jni = "ECPublicKey_1Serialize",
+    python = true,
+    wasm = true
 );
 bridge_get!(
     PublicKey::public_key_bytes -> &[u8],
     ffi = "publickey_get_public_key_bytes",
-    jni = "ECPublicKey_1GetPublicKeyBytes"
+    jni = "ECPublicKey_1GetPublicKeyBytes",
+    wasm = true
 );
 
 #[bridge_fn(ffi = "address_get_device_id")]
@@ -149,12 +153,17 @@ fn ProtocolAddress_Name(obj: &ProtocolAddress) -> &str {
     obj.name()
 }
 
-#[bridge_fn(ffi = "publickey_equals", node = "PublicKey_Equals")]
+#[bridge_fn(
+    ffi = "publickey_equals",
+    node = "PublicKey_Equals",
+    python = "PublicKey_Equals",
+    wasm = true
+)]
 fn ECPublicKey_Equals(lhs: &PublicKey, rhs: &PublicKey) -> bool {
     lhs == rhs
 }
 
-#[bridge_fn(ffi = "publickey_compare", node = "PublicKey_Compare")]
+#[bridge_fn(ffi = "publickey_compare", node = "PublicKey_Compare", wasm = true)]
 fn ECPublicKey_Compare(key1: &PublicKey, key2: &PublicKey) -> i32 {
     match key1.cmp(key2) {
         std::cmp::Ordering::Less => -1,
@@ -163,12 +172,17 @@ fn ECPublicKey_Compare(key1: &PublicKey, key2: &PublicKey) -> i32 {
     }
 }
 
-#[bridge_fn(ffi = "publickey_verify", node = "PublicKey_Verify")]
+#[bridge_fn(ffi = "publickey_verify", node = "PublicKey_Verify", wasm = true)]
 fn ECPublicKey_Verify(key: &PublicKey, message: &[u8], signature: &[u8]) -> Result<bool> {
     key.verify_signature(message, signature)
 }
 
-#[bridge_fn(ffi = "privatekey_deserialize", jni = "ECPrivateKey_1Deserialize")]
+#[bridge_fn(
+    ffi = "privatekey_deserialize",
+    jni = "ECPrivateKey_1Deserialize",
+    python = true,
+    wasm = true
+)]
 fn PrivateKey_Deserialize(data: &[u8]) -> Result<PrivateKey> {
     PrivateKey::deserialize(data)
 }
@@ -176,28 +190,40 @@ fn PrivateKey_Deserialize(data: &[u8]) -> Result<PrivateKey> {
 bridge_get!(
     PrivateKey::serialize as Serialize -> Vec<u8>,
     ffi = "privatekey_serialize",
-    jni = "ECPrivateKey_1Serialize"
+    jni = "ECPrivateKey_1Serialize",
+    python = true,
+    wasm = true
 );
 
-#[bridge_fn(ffi = "privatekey_generate", node = "PrivateKey_Generate")]
+#[bridge_fn(
+    ffi = "privatekey_generate",
+    node = "PrivateKey_Generate",
+    python = "PrivateKey_Generate",
+    wasm = true
+)]
 fn ECPrivateKey_Generate() -> PrivateKey {
     let mut rng = rand::rngs::OsRng;
     let keypair = KeyPair::generate(&mut rng);
     keypair.private_key
 }
 
-#[bridge_fn(ffi = "privatekey_get_public_key", node = "PrivateKey_GetPublicKey")]
+#[bridge_fn(
+    ffi = "privatekey_get_public_key",
+    node = "PrivateKey_GetPublicKey",
+    python = "PrivateKey_GetPublicKey",
+    wasm = true
+)]
 fn ECPrivateKey_GetPublicKey(k: &PrivateKey) -> Result<PublicKey> {
     k.public_key()
 }
 
-#[bridge_fn(ffi = "privatekey_sign", node = "PrivateKey_Sign")]
+#[bridge_fn(ffi = "privatekey_sign", node = "PrivateKey_Sign", wasm = true)]
 fn ECPrivateKey_Sign(key: &PrivateKey, message: &[u8]) -> Result<Vec<u8>> {
     let mut rng = rand::rngs::OsRng;
     Ok(key.calculate_signature(message, &mut rng)?.into_vec())
 }
 
-#[bridge_fn(ffi = "privatekey_agree", node = "PrivateKey_Agree")]
+#[bridge_fn(ffi = "privatekey_agree", node = "PrivateKey_Agree", wasm = true)]
 fn ECPrivateKey_Agree(private_key: &PrivateKey, public_key: &PublicKey) -> Result<Vec<u8>> {
     Ok(private_key.calculate_agreement(public_key)?.into_vec())
 }
@@ -248,13 +274,13 @@ fn KyberKeyPair_GetSecretKey(key_pair: &KyberKeyPair) -> KyberSecretKey {
     key_pair.secret_key.clone()
 }
 
-#[bridge_fn(ffi = "identitykeypair_serialize")]
+#[bridge_fn(ffi = "identitykeypair_serialize", wasm = true)]
 fn IdentityKeyPair_Serialize(public_key: &PublicKey, private_key: &PrivateKey) -> Vec<u8> {
     let identity_key_pair = IdentityKeyPair::new(IdentityKey::new(*public_key), *private_key);
     identity_key_pair.serialize().into_vec()
 }
 
-#[bridge_fn(ffi = "identitykeypair_sign_alternate_identity")]
+#[bridge_fn(ffi = "identitykeypair_sign_alternate_identity", wasm = true)]
 fn IdentityKeyPair_SignAlternateIdentity(
     public_key: &PublicKey,
     private_key: &PrivateKey,
@@ -598,7 +624,7 @@ fn PreKeyBundle_New(
 
     let bundle = PreKeyBundle::new(
         registration_id,
-        device_id.into(),
+        device_id.try_into()?,
         prekey,
         signed_prekey_id.into(),
         *signed_prekey,
@@ -709,14 +735,14 @@ bridge_get!(
     jni = "SenderKeyRecord_1GetSerialized"
 );
 
-bridge_deserialize!(ServerCertificate::deserialize);
-bridge_get!(ServerCertificate::serialized -> &[u8]);
+bridge_deserialize!(ServerCertificate::deserialize, python = true);
+bridge_get!(ServerCertificate::serialized -> &[u8], python = true);
 bridge_get!(ServerCertificate::certificate -> &[u8]);
 bridge_get!(ServerCertificate::signature -> &[u8]);
-bridge_get!(ServerCertificate::key_id -> u32);
-bridge_get!(ServerCertificate::public_key as GetKey -> PublicKey);
+bridge_get!(ServerCertificate::key_id -> u32, python = true);
+bridge_get!(ServerCertificate::public_key as GetKey -> PublicKey, python = true);
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerCertificate_New(
     key_id: u32,
     server_key: &PublicKey,
@@ -726,17 +752,17 @@ fn ServerCertificate_New(
     ServerCertificate::new(key_id, *server_key, trust_root, &mut rng)
 }
 
-bridge_deserialize!(SenderCertificate::deserialize);
-bridge_get!(SenderCertificate::serialized -> &[u8]);
+bridge_deserialize!(SenderCertificate::deserialize, python = true);
+bridge_get!(SenderCertificate::serialized -> &[u8], python = true);
 bridge_get!(SenderCertificate::certificate -> &[u8]);
 bridge_get!(SenderCertificate::signature -> &[u8]);
-bridge_get!(SenderCertificate::sender_uuid -> &str);
-bridge_get!(SenderCertificate::sender_e164 -> Option<&str>);
-bridge_get!(SenderCertificate::expiration -> Timestamp);
-bridge_get!(SenderCertificate::sender_device_id as GetDeviceId -> u32);
-bridge_get!(SenderCertificate::key -> PublicKey);
+bridge_get!(SenderCertificate::sender_uuid -> &str, python = true);
+bridge_get!(SenderCertificate::sender_e164 -> Option<&str>, python = true);
+bridge_get!(SenderCertificate::expiration -> Timestamp, python = true);
+bridge_get!(SenderCertificate::sender_device_id as GetDeviceId -> u32, python = true);
+bridge_get!(SenderCertificate::key -> PublicKey, python = true);
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn SenderCertificate_Validate(
     cert: &SenderCertificate,
     key: &PublicKey,
@@ -745,12 +771,12 @@ fn SenderCertificate_Validate(
     cert.validate(key, time)
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn SenderCertificate_GetServerCertificate(cert: &SenderCertificate) -> Result<ServerCertificate> {
     Ok(cert.signer()?.clone())
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn SenderCertificate_New(
     sender_uuid: String,
     sender_e164: Option<String>,
@@ -766,7 +792,7 @@ fn SenderCertificate_New(
         sender_uuid,
         sender_e164,
         *sender_key,
-        sender_device_id.into(),
+        sender_device_id.try_into()?,
         expiration,
         signer_cert.clone(),
         signer_key,
@@ -1223,6 +1249,105 @@ async fn SealedSessionCipher_DecryptToUsmc(
     sealed_sender_decrypt_to_usmc(ctext, identity_store).await
 }
 
+/// Returns the major version of a serialized sealed sender message: 1 or 2.
+#[bridge_fn(jni = "SealedSessionCipher_1MessageVersion")]
+fn SealedSender_MessageVersion(ctext: &[u8]) -> Result<u8> {
+    Ok(match sealed_sender_message_version(ctext)? {
+        SealedSenderMessageVersion::V1 => 1,
+        SealedSenderMessageVersion::V2 => 2,
+    })
+}
+
+#[bridge_fn]
+fn Envelope_Deserialize(data: &[u8]) -> Result<Envelope> {
+    Envelope::try_from(data).map_err(|e| match e {
+        EnvelopeError::InvalidProtobufEncoding => SignalProtocolError::InvalidProtobufEncoding,
+        EnvelopeError::InvalidContent(_, e) => e,
+        e => SignalProtocolError::InvalidArgument(e.to_string()),
+    })
+}
+
+#[bridge_fn]
+fn Envelope_GetSourceServiceId(envelope: &Envelope) -> Option<String> {
+    envelope
+        .source
+        .map(|source| source.service_id.service_id_string())
+}
+
+#[bridge_fn]
+fn Envelope_GetSourceDeviceId(envelope: &Envelope) -> Option<u32> {
+    envelope.source.map(|source| source.device_id.into())
+}
+
+#[bridge_fn]
+fn Envelope_GetDestinationServiceId(envelope: &Envelope) -> Option<String> {
+    envelope.destination.map(|id| id.service_id_string())
+}
+
+#[bridge_fn]
+fn Envelope_GetTimestamp(envelope: &Envelope) -> Timestamp {
+    envelope.timestamp
+}
+
+/// Returns 0 if the server didn't record when it received the message.
+#[bridge_fn]
+fn Envelope_GetServerTimestamp(envelope: &Envelope) -> Timestamp {
+    envelope
+        .server_timestamp
+        .unwrap_or(Timestamp::from_epoch_millis(0))
+}
+
+#[bridge_fn]
+fn Envelope_GetServerGuid(envelope: &Envelope) -> Option<String> {
+    envelope.server_guid.map(|guid| guid.to_string())
+}
+
+/// Returns the server's `Envelope.Type` for the content.
+#[bridge_fn]
+fn Envelope_GetType(envelope: &Envelope) -> u8 {
+    envelope.content.envelope_type()
+}
+
+#[bridge_fn]
+fn Envelope_GetContent(envelope: &Envelope) -> &[u8] {
+    envelope.content.serialized()
+}
+
+#[bridge_fn]
+fn Envelope_GetUrgent(envelope: &Envelope) -> bool {
+    envelope.urgent
+}
+
+#[bridge_fn]
+fn Envelope_GetStory(envelope: &Envelope) -> bool {
+    envelope.story
+}
+
+#[bridge_fn]
+fn Envelope_GetEphemeral(envelope: &Envelope) -> bool {
+    envelope.ephemeral
+}
+
+#[bridge_fn]
+fn Envelope_GetUpdatedPni(envelope: &Envelope) -> Option<String> {
+    envelope.updated_pni.map(|pni| pni.service_id_string())
+}
+
+/// Returns an empty buffer if the envelope has no spam reporting token.
+#[bridge_fn]
+fn Envelope_GetReportSpamToken(envelope: &Envelope) -> &[u8] {
+    envelope.report_spam_token.as_deref().unwrap_or_default()
+}
+
+#[bridge_fn(node = "SealedSender_RewrapV1AsV2")]
+async fn SealedSessionCipher_RewrapV1AsV2(
+    ctext: &[u8],
+    identity_store: &mut dyn IdentityKeyStore,
+) -> Result<Vec<u8>> {
+    let mut rng = rand::rngs::OsRng;
+    sealed_sender_rewrap_v1_as_v2(ctext, identity_store, &mut rng).await
+}
+
 #[allow(clippy::too_many_arguments)]
 #[bridge_fn(ffi = false, jni = false)]
 async fn SealedSender_DecryptMessage(
@@ -1244,7 +1369,7 @@ async fn SealedSender_DecryptMessage(
         timestamp,
         local_e164,
         local_uuid,
-        local_device_id.into(),
+        local_device_id.try_into()?,
         identity_store,
         session_store,
         prekey_store,
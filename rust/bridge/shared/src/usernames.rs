@@ -14,17 +14,17 @@ use libsignal_bridge_macros::*;
 use crate::support::*;
 use crate::*;
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 pub fn Username_Hash(username: String) -> Result<[u8; 32], UsernameError> {
     Username::new(&username).map(|un| un.hash())
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 pub fn Username_Proof(username: String, randomness: &[u8]) -> Result<Vec<u8>, UsernameError> {
     Username::new(&username)?.proof(randomness)
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 pub fn Username_Verify(
     proof: &[u8],
     hash: &[u8],
@@ -37,7 +37,7 @@ pub fn Username_Verify(
     Username::verify_proof(proof, arr)
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 pub fn Username_CandidatesFrom(
     nickname: String,
     min_len: u32,
@@ -48,7 +48,7 @@ pub fn Username_CandidatesFrom(
     Username::candidates_from(&mut rng, &nickname, limits).map(Vec::into_boxed_slice)
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 pub fn Username_HashFromParts(
     nickname: String,
     discriminator: String,
@@ -59,7 +59,7 @@ pub fn Username_HashFromParts(
     Username::from_parts(&nickname, &discriminator, limits).map(|un| un.hash())
 }
 
-#[bridge_fn(ffi = false)]
+#[bridge_fn(ffi = false, wasm = true)]
 pub fn UsernameLink_Create(
     username: String,
     entropy: Option<&[u8]>,
@@ -96,7 +96,7 @@ pub fn UsernameLink_CreateAllowingEmptyEntropy(
     Ok(buffer)
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 pub fn UsernameLink_DecryptUsername(
     entropy: &[u8],
     encrypted_username: &[u8],
@@ -28,8 +28,8 @@ bridge_fixed_length_serializable_fns!(ExpiringProfileKeyCredential);
 bridge_fixed_length_serializable_fns!(ExpiringProfileKeyCredentialResponse);
 bridge_fixed_length_serializable_fns!(GroupMasterKey);
 bridge_fixed_length_serializable_fns!(GroupPublicParams);
-bridge_fixed_length_serializable_fns!(GroupSecretParams);
-bridge_fixed_length_serializable_fns!(ProfileKey);
+bridge_fixed_length_serializable_fns!(GroupSecretParams, wasm = true);
+bridge_fixed_length_serializable_fns!(ProfileKey, wasm = true);
 bridge_fixed_length_serializable_fns!(ProfileKeyCiphertext);
 bridge_fixed_length_serializable_fns!(ProfileKeyCommitment);
 bridge_fixed_length_serializable_fns!(ProfileKeyCredentialRequest);
@@ -41,10 +41,10 @@ bridge_fixed_length_serializable_fns!(ReceiptCredentialRequestContext);
 bridge_fixed_length_serializable_fns!(ReceiptCredentialResponse);
 bridge_fixed_length_serializable_fns!(UuidCiphertext);
 
-bridge_serializable_handle_fns!(ServerPublicParams);
-bridge_serializable_handle_fns!(ServerSecretParams);
+bridge_serializable_handle_fns!(ServerPublicParams, python = true, wasm = true);
+bridge_serializable_handle_fns!(ServerSecretParams, python = true);
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn ProfileKey_GetCommitment(
     profile_key: Serialized<ProfileKey>,
     user_id: Aci,
@@ -52,7 +52,7 @@ fn ProfileKey_GetCommitment(
     profile_key.get_commitment(user_id).into()
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn ProfileKey_GetProfileKeyVersion(
     profile_key: Serialized<ProfileKey>,
     user_id: Aci,
@@ -61,19 +61,19 @@ fn ProfileKey_GetProfileKeyVersion(
     serialized.try_into().expect("right length")
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn ProfileKey_DeriveAccessKey(profile_key: Serialized<ProfileKey>) -> [u8; ACCESS_KEY_LEN] {
     profile_key.derive_access_key()
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_GenerateDeterministic(
     randomness: &[u8; RANDOMNESS_LEN],
 ) -> Serialized<GroupSecretParams> {
     GroupSecretParams::generate(*randomness).into()
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_DeriveFromMasterKey(
     master_key: Serialized<GroupMasterKey>,
 ) -> Serialized<GroupSecretParams> {
@@ -81,7 +81,7 @@ fn GroupSecretParams_DeriveFromMasterKey(
 }
 
 // FIXME: Could be bridge_get! if we provide ArgTypeInfo for &GroupSecretParams.
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_GetMasterKey(
     params: Serialized<GroupSecretParams>,
 ) -> Serialized<GroupMasterKey> {
@@ -89,14 +89,14 @@ fn GroupSecretParams_GetMasterKey(
 }
 
 // FIXME: Could be bridge_get! if we provide ArgTypeInfo for &GroupSecretParams.
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_GetPublicParams(
     params: Serialized<GroupSecretParams>,
 ) -> Serialized<GroupPublicParams> {
     params.get_public_params().into()
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_EncryptServiceId(
     params: Serialized<GroupSecretParams>,
     service_id: ServiceId,
@@ -104,7 +104,7 @@ fn GroupSecretParams_EncryptServiceId(
     params.encrypt_service_id(service_id).into()
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_DecryptServiceId(
     params: Serialized<GroupSecretParams>,
     ciphertext: Serialized<UuidCiphertext>,
@@ -112,7 +112,7 @@ fn GroupSecretParams_DecryptServiceId(
     params.decrypt_service_id(ciphertext.into_inner())
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_EncryptProfileKey(
     params: Serialized<GroupSecretParams>,
     profile_key: Serialized<ProfileKey>,
@@ -123,7 +123,7 @@ fn GroupSecretParams_EncryptProfileKey(
         .into()
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_DecryptProfileKey(
     params: Serialized<GroupSecretParams>,
     profile_key: Serialized<ProfileKeyCiphertext>,
@@ -134,7 +134,7 @@ fn GroupSecretParams_DecryptProfileKey(
         .into())
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_EncryptBlobWithPaddingDeterministic(
     params: Serialized<GroupSecretParams>,
     randomness: &[u8; RANDOMNESS_LEN],
@@ -144,7 +144,7 @@ fn GroupSecretParams_EncryptBlobWithPaddingDeterministic(
     params.encrypt_blob_with_padding(*randomness, plaintext, padding_len)
 }
 
-#[bridge_fn]
+#[bridge_fn(wasm = true)]
 fn GroupSecretParams_DecryptBlobWithPadding(
     params: Serialized<GroupSecretParams>,
     ciphertext: &[u8],
@@ -152,7 +152,7 @@ fn GroupSecretParams_DecryptBlobWithPadding(
     params.decrypt_blob_with_padding(ciphertext)
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_GenerateDeterministic(
     randomness: &[u8; RANDOMNESS_LEN],
 ) -> ServerSecretParams {
@@ -160,12 +160,12 @@ fn ServerSecretParams_GenerateDeterministic(
 }
 
 // FIXME: Could be bridge_get!
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_GetPublicParams(params: &ServerSecretParams) -> ServerPublicParams {
     params.get_public_params()
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_SignDeterministic(
     params: &ServerSecretParams,
     randomness: &[u8; RANDOMNESS_LEN],
@@ -311,7 +311,7 @@ fn ServerSecretParams_IssueAuthCredentialWithPniAsServiceIdDeterministic(
     )
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_IssueAuthCredentialWithPniZkcDeterministic(
     server_secret_params: &ServerSecretParams,
     randomness: &[u8; RANDOMNESS_LEN],
@@ -342,7 +342,7 @@ fn AuthCredentialWithPniResponse_CheckValidContents(
     AuthCredentialWithPniResponse::new(bytes).map(|_| ())
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_VerifyAuthCredentialPresentation(
     server_secret_params: &ServerSecretParams,
     group_public_params: Serialized<GroupPublicParams>,
@@ -358,7 +358,7 @@ fn ServerSecretParams_VerifyAuthCredentialPresentation(
     )
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_IssueExpiringProfileKeyCredentialDeterministic(
     server_secret_params: &ServerSecretParams,
     randomness: &[u8; RANDOMNESS_LEN],
@@ -378,7 +378,7 @@ fn ServerSecretParams_IssueExpiringProfileKeyCredentialDeterministic(
         .into())
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_VerifyProfileKeyCredentialPresentation(
     server_secret_params: &ServerSecretParams,
     group_public_params: Serialized<GroupPublicParams>,
@@ -394,7 +394,7 @@ fn ServerSecretParams_VerifyProfileKeyCredentialPresentation(
     )
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_IssueReceiptCredentialDeterministic(
     server_secret_params: &ServerSecretParams,
     randomness: &[u8; RANDOMNESS_LEN],
@@ -412,7 +412,7 @@ fn ServerSecretParams_IssueReceiptCredentialDeterministic(
         .into()
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ServerSecretParams_VerifyReceiptCredentialPresentation(
     server_secret_params: &ServerSecretParams,
     presentation: Serialized<ReceiptCredentialPresentation>,
@@ -428,7 +428,7 @@ fn GroupPublicParams_GetGroupIdentifier(
     group_public_params.get_group_identifier()
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true, wasm = true)]
 fn ServerPublicParams_VerifySignature(
     server_public_params: &ServerPublicParams,
     message: &[u8],
@@ -437,7 +437,7 @@ fn ServerPublicParams_VerifySignature(
     server_public_params.verify_signature(message, *notary_signature)
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn AuthCredentialPresentation_CheckValidContents(
     presentation_bytes: &[u8],
 ) -> Result<(), ZkGroupDeserializationFailure> {
@@ -496,7 +496,7 @@ fn ExpiringProfileKeyCredential_GetExpirationTime(
     credential.get_expiration_time()
 }
 
-#[bridge_fn]
+#[bridge_fn(python = true)]
 fn ProfileKeyCredentialPresentation_CheckValidContents(
     presentation_bytes: &[u8],
 ) -> Result<(), ZkGroupDeserializationFailure> {
@@ -152,6 +152,7 @@ make_error_testing_enum! {
         ServiceInactive => ServiceInactive,
         ServiceUnavailable => ServiceUnavailable,
         ServiceIntentionallyDisconnected => ServiceIntentionallyDisconnected,
+        RetryLater => RetryAfter42Seconds,
     }
 }
 
@@ -189,6 +190,11 @@ fn TESTING_ChatServiceErrorConvert(
         TestingChatServiceError::ServiceIntentionallyDisconnected => {
             ChatServiceError::ServiceIntentionallyDisconnected
         }
+        TestingChatServiceError::RetryAfter42Seconds => {
+            ChatServiceError::RetryLater(libsignal_net::infra::errors::RetryLater {
+                retry_after: std::time::Duration::from_secs(42),
+            })
+        }
     })
 }
 
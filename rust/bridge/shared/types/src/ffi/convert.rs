@@ -560,7 +560,9 @@ impl<'a, T: BridgeHandle> ArgTypeInfo<'a> for &'a [&'a T] {
 impl<T: BridgeHandle> ResultTypeInfo for T {
     type ResultType = *mut T;
     fn convert_into(self) -> SignalFfiResult<Self::ResultType> {
-        Ok(Box::into_raw(Box::new(self)))
+        let handle = Box::into_raw(Box::new(self));
+        crate::support::handle_accounting::track(handle);
+        Ok(handle)
     }
 }
 
@@ -19,7 +19,7 @@ use usernames::{UsernameError, UsernameLinkError};
 use zkgroup::{ZkGroupDeserializationFailure, ZkGroupVerificationFailure};
 
 use super::{FutureCancelled, NullPointerError, UnexpectedPanic};
-use crate::support::describe_panic;
+use crate::support::{ErrorDetails, ProvideErrorDetails};
 
 #[derive(Debug)]
 #[repr(C)]
@@ -122,21 +122,36 @@ pub trait FfiError: UpcastAsAny + fmt::Debug + Send + 'static {
     fn describe(&self) -> String;
     fn code(&self) -> SignalErrorCode;
 
+    /// Provides the details backing `provide_address`, `provide_retry_after_seconds`,
+    /// `provide_tries_remaining`, and `provide_http_status`.
+    fn provide_details(&self) -> ErrorDetails {
+        ErrorDetails::default()
+    }
+
     fn provide_address(&self) -> Result<ProtocolAddress, WrongErrorKind> {
-        Err(WrongErrorKind)
+        self.provide_details().address.ok_or(WrongErrorKind)
     }
     fn provide_uuid(&self) -> Result<uuid::Uuid, WrongErrorKind> {
         Err(WrongErrorKind)
     }
     fn provide_retry_after_seconds(&self) -> Result<u32, WrongErrorKind> {
-        Err(WrongErrorKind)
+        self.provide_details()
+            .retry_after_seconds
+            .ok_or(WrongErrorKind)
     }
     fn provide_tries_remaining(&self) -> Result<u32, WrongErrorKind> {
-        Err(WrongErrorKind)
+        self.provide_details().tries_remaining.ok_or(WrongErrorKind)
+    }
+    fn provide_http_status(&self) -> Result<u16, WrongErrorKind> {
+        self.provide_details().http_status.ok_or(WrongErrorKind)
     }
     fn provide_unknown_fields(&self) -> Result<Vec<String>, WrongErrorKind> {
         Err(WrongErrorKind)
     }
+    /// The Rust backtrace captured when an unexpected panic occurred, if available.
+    fn provide_backtrace(&self) -> Result<String, WrongErrorKind> {
+        Err(WrongErrorKind)
+    }
 }
 
 /// The top-level error type (opaquely) returned to C clients when something goes wrong.
@@ -207,7 +222,7 @@ impl FfiError for SignalProtocolError {
     }
 
     fn code(&self) -> SignalErrorCode {
-        match self {
+        match self.underlying_error() {
             Self::InvalidArgument(_) => SignalErrorCode::InvalidArgument,
             Self::InvalidState(_, _) => SignalErrorCode::InvalidState,
             Self::InvalidProtobufEncoding => SignalErrorCode::ProtobufError,
@@ -244,18 +259,16 @@ impl FfiError for SignalProtocolError {
             Self::FfiBindingError(_) => SignalErrorCode::InternalError,
             Self::ApplicationCallbackError(_, _) => SignalErrorCode::CallbackError,
             Self::SealedSenderSelfSend => SignalErrorCode::SealedSenderSelfSend,
+            Self::WithContext { .. } => unreachable!("context was removed"),
         }
     }
 
-    fn provide_address(&self) -> Result<ProtocolAddress, WrongErrorKind> {
-        match self {
-            Self::InvalidRegistrationId(address, _id) => Ok(address.clone()),
-            _ => Err(WrongErrorKind),
-        }
+    fn provide_details(&self) -> ErrorDetails {
+        self.error_details()
     }
 
     fn provide_uuid(&self) -> Result<uuid::Uuid, WrongErrorKind> {
-        match self {
+        match self.underlying_error() {
             Self::InvalidSenderKeySession { distribution_id } => Ok(*distribution_id),
             _ => Err(WrongErrorKind),
         }
@@ -270,6 +283,7 @@ impl FfiError for DeviceTransferError {
     fn code(&self) -> SignalErrorCode {
         match self {
             Self::KeyDecodingFailed => SignalErrorCode::InvalidKey,
+            Self::CertificateDecodingFailed => SignalErrorCode::InvalidArgument,
             Self::InternalError(_) => SignalErrorCode::InternalError,
         }
     }
@@ -316,9 +330,10 @@ impl FfiError for PinError {
 
     fn code(&self) -> SignalErrorCode {
         match self {
-            Self::Argon2Error(_) | Self::DecodingError(_) | Self::MrenclaveLookupError => {
-                SignalErrorCode::InvalidArgument
-            }
+            Self::Argon2Error(_)
+            | Self::DecodingError(_)
+            | Self::MrenclaveLookupError
+            | Self::MasterKeyDecryptionFailed => SignalErrorCode::InvalidArgument,
         }
     }
 }
@@ -436,9 +451,11 @@ impl FfiError for IoError {
 impl FfiError for libsignal_net::cdsi::LookupError {
     fn describe(&self) -> String {
         match self {
-            Self::Protocol | Self::InvalidResponse | Self::ParseError | Self::Server { .. } => {
-                format!("Protocol error: {self}")
-            }
+            Self::Protocol
+            | Self::InvalidResponse
+            | Self::ParseError
+            | Self::Server { .. }
+            | Self::UnsupportedByServer { .. } => format!("Protocol error: {self}"),
             Self::AttestationError(e) => e.describe(),
             Self::RateLimited {
                 retry_after_seconds,
@@ -453,9 +470,11 @@ impl FfiError for libsignal_net::cdsi::LookupError {
 
     fn code(&self) -> SignalErrorCode {
         match self {
-            Self::Protocol | Self::InvalidResponse | Self::ParseError | Self::Server { .. } => {
-                SignalErrorCode::NetworkProtocol
-            }
+            Self::Protocol
+            | Self::InvalidResponse
+            | Self::ParseError
+            | Self::Server { .. }
+            | Self::UnsupportedByServer { .. } => SignalErrorCode::NetworkProtocol,
             Self::AttestationError(e) => e.code(),
             Self::RateLimited { .. } => SignalErrorCode::RateLimited,
             Self::InvalidToken => SignalErrorCode::CdsiInvalidToken,
@@ -466,13 +485,8 @@ impl FfiError for libsignal_net::cdsi::LookupError {
         }
     }
 
-    fn provide_retry_after_seconds(&self) -> Result<u32, WrongErrorKind> {
-        match self {
-            Self::RateLimited {
-                retry_after_seconds,
-            } => Ok(*retry_after_seconds),
-            _ => Err(WrongErrorKind),
-        }
+    fn provide_details(&self) -> ErrorDetails {
+        self.error_details()
     }
 }
 
@@ -520,11 +534,8 @@ impl FfiError for Svr3Error {
         }
     }
 
-    fn provide_tries_remaining(&self) -> Result<u32, WrongErrorKind> {
-        match self {
-            Self::RestoreFailed(tries_remaining) => Ok(*tries_remaining),
-            _ => Err(WrongErrorKind),
-        }
+    fn provide_details(&self) -> ErrorDetails {
+        self.error_details()
     }
 }
 
@@ -550,6 +561,10 @@ impl FfiError for ChatServiceError {
             Self::ServiceIntentionallyDisconnected => {
                 "Chat service explicitly disconnected".to_owned()
             }
+            Self::RetryLater(retry_later) => format!(
+                "Rate limited; try again after {}s",
+                retry_later.retry_after.as_secs()
+            ),
         }
     }
 
@@ -574,8 +589,13 @@ impl FfiError for ChatServiceError {
             Self::ServiceIntentionallyDisconnected => {
                 SignalErrorCode::ChatServiceIntentionallyDisconnected
             }
+            Self::RetryLater(_) => SignalErrorCode::RateLimited,
         }
     }
+
+    fn provide_details(&self) -> ErrorDetails {
+        self.error_details()
+    }
 }
 
 impl FfiError for http::uri::InvalidUri {
@@ -645,7 +665,7 @@ impl FfiError for signal_media::sanitize::webp::Error {
 
 impl FfiError for libsignal_message_backup::ReadError {
     fn describe(&self) -> String {
-        self.to_string()
+        self.scrubbed().to_string()
     }
 
     fn code(&self) -> SignalErrorCode {
@@ -673,12 +693,16 @@ impl FfiError for NullPointerError {
 
 impl FfiError for UnexpectedPanic {
     fn describe(&self) -> String {
-        format!("unexpected panic: {}", describe_panic(&self.0))
+        format!("unexpected panic: {}", self.0)
     }
 
     fn code(&self) -> SignalErrorCode {
         SignalErrorCode::InternalError
     }
+
+    fn provide_backtrace(&self) -> Result<String, WrongErrorKind> {
+        self.0.backtrace.clone().ok_or(WrongErrorKind)
+    }
 }
 
 impl FfiError for std::str::Utf8Error {
@@ -80,7 +80,7 @@ impl<T: ResultTypeInfo + std::panic::UnwindSafe> ResultReporter for FutureResult
 
         let result = result.and_then(|result| {
             std::panic::catch_unwind(|| result.convert_into())
-                .unwrap_or_else(|panic| Err(UnexpectedPanic(panic).into()))
+                .unwrap_or_else(|panic| Err(UnexpectedPanic::from(panic).into()))
         });
 
         match result {
@@ -137,5 +137,5 @@ pub fn catch_unwind<T>(
 ) -> impl Future<Output = SignalFfiResult<T>> + Send + std::panic::UnwindSafe + 'static {
     future
         .catch_unwind()
-        .unwrap_or_else(|panic| Err(UnexpectedPanic(panic).into()))
+        .unwrap_or_else(|panic| Err(UnexpectedPanic::from(panic).into()))
 }
@@ -27,7 +27,7 @@ pub use io::*;
 mod storage;
 pub use storage::*;
 
-use crate::support::describe_panic;
+use crate::support::panics::{self, PanicReport};
 
 #[derive(Debug)]
 pub struct NullPointerError;
@@ -191,13 +191,12 @@ pub struct FfiResponseAndDebugInfo {
     debug_info: FfiChatServiceDebugInfo,
 }
 
-struct UnexpectedPanic(Box<dyn std::any::Any + Send>);
+#[derive(Debug)]
+struct UnexpectedPanic(PanicReport);
 
-impl std::fmt::Debug for UnexpectedPanic {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("UnexpectedPanic")
-            .field(&describe_panic(&self.0))
-            .finish()
+impl From<Box<dyn std::any::Any + Send>> for UnexpectedPanic {
+    fn from(payload: Box<dyn std::any::Any + Send>) -> Self {
+        Self(PanicReport::for_payload(&payload))
     }
 }
 
@@ -205,10 +204,11 @@ impl std::fmt::Debug for UnexpectedPanic {
 pub fn run_ffi_safe<F: FnOnce() -> Result<(), SignalFfiError> + std::panic::UnwindSafe>(
     f: F,
 ) -> *mut SignalFfiError {
+    panics::install_panic_hook();
     let result = match std::panic::catch_unwind(f) {
         Ok(Ok(())) => Ok(()),
         Ok(Err(e)) => Err(e),
-        Err(r) => Err(UnexpectedPanic(r).into()),
+        Err(r) => Err(UnexpectedPanic::from(r).into()),
     };
 
     // When ThinBox is stabilized, we can return that instead of double-boxing.
@@ -271,6 +271,7 @@ macro_rules! ffi_bridge_handle_destroy {
                 let p = std::panic::AssertUnwindSafe(p);
                 ffi::run_ffi_safe(|| {
                     if !p.is_null() {
+                        $crate::support::handle_accounting::untrack(*p);
                         drop(Box::from_raw(*p));
                     }
                     Ok(())
@@ -65,6 +65,7 @@ impl IdentityKeyStore for &FfiIdentityKeyStoreStruct {
             ));
         }
 
+        crate::support::handle_accounting::untrack(key);
         let priv_key = unsafe { Box::from_raw(key) };
         let pub_key = priv_key.public_key()?;
 
@@ -139,6 +140,7 @@ impl IdentityKeyStore for &FfiIdentityKeyStoreStruct {
             return Ok(None);
         }
 
+        crate::support::handle_accounting::untrack(key);
         let pk = unsafe { Box::from_raw(key) };
 
         Ok(Some(IdentityKey::new(*pk)))
@@ -174,6 +176,7 @@ impl PreKeyStore for &FfiPreKeyStoreStruct {
             return Err(SignalProtocolError::InvalidPreKeyId);
         }
 
+        crate::support::handle_accounting::untrack(record);
         let record = unsafe { Box::from_raw(record) };
         Ok(*record)
     }
@@ -229,6 +232,7 @@ impl SignedPreKeyStore for &FfiSignedPreKeyStoreStruct {
             return Err(SignalProtocolError::InvalidSignedPreKeyId);
         }
 
+        crate::support::handle_accounting::untrack(record);
         let record = unsafe { Box::from_raw(record) };
 
         Ok(*record)
@@ -281,6 +285,7 @@ impl KyberPreKeyStore for &FfiKyberPreKeyStoreStruct {
             return Err(SignalProtocolError::InvalidKyberPreKeyId);
         }
 
+        crate::support::handle_accounting::untrack(record);
         let record = unsafe { Box::from_raw(record) };
 
         Ok(*record)
@@ -346,6 +351,7 @@ impl SessionStore for &FfiSessionStoreStruct {
             return Ok(None);
         }
 
+        crate::support::handle_accounting::untrack(record);
         let record = unsafe { Box::from_raw(record) };
 
         Ok(Some(*record))
@@ -417,6 +423,7 @@ impl SenderKeyStore for &FfiSenderKeyStoreStruct {
             return Ok(None);
         }
 
+        crate::support::handle_accounting::untrack(record);
         let record = unsafe { Box::from_raw(record) };
 
         Ok(Some(*record))
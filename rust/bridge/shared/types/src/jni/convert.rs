@@ -836,7 +836,9 @@ impl<'storage, 'param: 'storage, 'context: 'param, T: BridgeHandle>
 impl<T: BridgeHandle> ResultTypeInfo<'_> for T {
     type ResultType = ObjectHandle;
     fn convert_into(self, _env: &mut JNIEnv) -> Result<Self::ResultType, BridgeLayerError> {
-        Ok(Box::into_raw(Box::new(self)) as ObjectHandle)
+        let handle = Box::into_raw(Box::new(self));
+        crate::support::handle_accounting::track(handle);
+        Ok(handle as ObjectHandle)
     }
 }
 
@@ -21,7 +21,7 @@ use zkgroup::{ZkGroupDeserializationFailure, ZkGroupVerificationFailure};
 
 use super::*;
 use crate::net::cdsi::CdsiError;
-use crate::support::describe_panic;
+use crate::support::panics::PanicReport;
 
 /// The top-level error type for when something goes wrong.
 #[derive(Debug, thiserror::Error)]
@@ -48,6 +48,7 @@ pub enum SignalJniError {
     ChatService(ChatServiceError),
     InvalidUri(InvalidUri),
     ConnectTimedOut,
+    Cancelled,
     BackupValidation(#[from] libsignal_message_backup::ReadError),
     Bridge(BridgeLayerError),
     TestingError {
@@ -69,7 +70,14 @@ pub enum BridgeLayerError {
     IntegerOverflow(String),
     IncorrectArrayLength { expected: usize, actual: usize },
     CallbackException(&'static str, ThrownException),
-    UnexpectedPanic(std::boxed::Box<dyn std::any::Any + std::marker::Send>),
+    UnexpectedPanic(PanicReport),
+}
+
+impl BridgeLayerError {
+    /// Wraps a caught panic, along with any details recorded by the panic hook.
+    pub fn unexpected_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        Self::UnexpectedPanic(PanicReport::for_payload(&payload))
+    }
 }
 
 impl fmt::Display for SignalJniError {
@@ -96,7 +104,8 @@ impl fmt::Display for SignalJniError {
             SignalJniError::InvalidUri(e) => write!(f, "{}", e),
             SignalJniError::WebSocket(e) => write!(f, "{e}"),
             SignalJniError::ConnectTimedOut => write!(f, "connect timed out"),
-            SignalJniError::BackupValidation(e) => write!(f, "{}", e),
+            SignalJniError::Cancelled => write!(f, "cancelled"),
+            SignalJniError::BackupValidation(e) => write!(f, "{}", e.scrubbed()),
             SignalJniError::Svr3(e) => write!(f, "{}", e),
             SignalJniError::Bridge(e) => write!(f, "{}", e),
             SignalJniError::TestingError { exception_class } => {
@@ -132,8 +141,8 @@ impl fmt::Display for BridgeLayerError {
             Self::CallbackException(callback_name, exception) => {
                 write!(f, "exception in method call '{callback_name}': {exception}")
             }
-            Self::UnexpectedPanic(e) => {
-                write!(f, "unexpected panic: {}", describe_panic(e))
+            Self::UnexpectedPanic(report) => {
+                write!(f, "unexpected panic: {report}")
             }
         }
     }
@@ -259,7 +268,7 @@ impl From<libsignal_net::cdsi::LookupError> for SignalJniError {
                 ))
             }
             LookupError::InvalidResponse => CdsiError::InvalidResponse,
-            LookupError::Protocol => CdsiError::Protocol,
+            LookupError::Protocol | LookupError::UnsupportedByServer { .. } => CdsiError::Protocol,
             LookupError::RateLimited {
                 retry_after_seconds,
             } => CdsiError::RateLimited {
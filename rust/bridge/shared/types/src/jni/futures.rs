@@ -8,7 +8,7 @@ use std::future::Future;
 use futures_util::{FutureExt, TryFutureExt};
 
 use super::*;
-use crate::support::{AsyncRuntime, ResultReporter};
+use crate::support::{AsyncRuntime, CancellationId, ResultReporter};
 
 /// Used to complete a Java CompletableFuture from any thread.
 pub struct FutureCompleter<T> {
@@ -95,7 +95,7 @@ impl<T: for<'a> ResultTypeInfo<'a> + std::panic::UnwindSafe, U> ResultReporter
                     })
                     .map_err(Into::into)
             })
-            .unwrap_or_else(|panic| Err(BridgeLayerError::UnexpectedPanic(panic).into()))
+            .unwrap_or_else(|panic| Err(BridgeLayerError::unexpected_panic(panic).into()))
         });
 
         // From this point on we can't catch panics, because SignalJniError isn't UnwindSafe. This
@@ -168,7 +168,17 @@ where
         jni_args!(() -> void),
     )?;
     let completer = FutureCompleter::new(env, &java_future)?;
-    runtime.run_future(future, completer);
+    let cancellation_id = runtime.run_future(future, completer);
+    if cancellation_id != CancellationId::NotSupported {
+        // Java longs are signed, but the ID is only ever compared for equality.
+        let raw_cancellation_id = u64::from(cancellation_id) as i64;
+        call_method_checked(
+            env,
+            &java_future,
+            "setCancellationId",
+            jni_args!((raw_cancellation_id => long) -> void),
+        )?;
+    }
     Ok(java_future.into())
 }
 
@@ -178,5 +188,5 @@ pub fn catch_unwind<'a, O>(
 ) -> impl Future<Output = SignalJniResult<O>> + Send + std::panic::UnwindSafe + 'a {
     future
         .catch_unwind()
-        .unwrap_or_else(|panic| Err(BridgeLayerError::UnexpectedPanic(panic).into()))
+        .unwrap_or_else(|panic| Err(BridgeLayerError::unexpected_panic(panic).into()))
 }
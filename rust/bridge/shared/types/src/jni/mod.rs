@@ -16,6 +16,7 @@ use jni::objects::{GlobalRef, JThrowable, JValue, JValueOwned};
 pub use jni::sys::{jboolean, jint, jlong};
 pub use jni::JNIEnv;
 use jni::JavaVM;
+use libsignal_net::infra::errors::RetryLater;
 use libsignal_net::infra::ws::WebSocketServiceError;
 use libsignal_net::svr3::Error as Svr3Error;
 use libsignal_protocol::*;
@@ -24,6 +25,7 @@ use signal_pin::Error as PinError;
 use usernames::{UsernameError, UsernameLinkError};
 
 use crate::net::cdsi::CdsiError;
+use crate::support::ProvideErrorDetails;
 
 #[macro_use]
 mod args;
@@ -124,6 +126,26 @@ impl<'env> ConsumableException<'env> {
                 .check_exceptions(env, "ConsumableException::new")
         }
 
+        // Passed to the exception constructor as an extra argument, for the network exceptions
+        // that accept one.
+        let http_status = match &error {
+            SignalJniError::WebSocket(e)
+            | SignalJniError::ChatService(ChatServiceError::WebSocket(e)) => {
+                e.error_details().http_status
+            }
+            _ => None,
+        };
+
+        // The exception type depends on the underlying protocol error; any context only goes into
+        // the message.
+        let (error, context) = match error {
+            SignalJniError::Protocol(e) => {
+                let context = e.context().cloned();
+                (SignalJniError::Protocol(e.without_context()), context)
+            }
+            error => (error, None),
+        };
+
         let (exception_type, error) = match error {
             SignalJniError::Bridge(BridgeLayerError::CallbackException(callback, exception)) => {
                 let throwable = env
@@ -292,7 +314,10 @@ impl<'env> ConsumableException<'env> {
                 };
             }
 
-            SignalJniError::Cdsi(CdsiError::RateLimited { retry_after }) => {
+            SignalJniError::Cdsi(CdsiError::RateLimited { retry_after })
+            | SignalJniError::ChatService(ChatServiceError::RetryLater(RetryLater {
+                retry_after,
+            })) => {
                 let retry_after_seconds = retry_after
                     .as_secs()
                     .try_into()
@@ -309,8 +334,39 @@ impl<'env> ConsumableException<'env> {
                 };
             }
 
-            SignalJniError::Bridge(BridgeLayerError::UnexpectedPanic(_))
-            | SignalJniError::Bridge(BridgeLayerError::BadJniParameter(_))
+            SignalJniError::Bridge(BridgeLayerError::UnexpectedPanic(ref report)) => {
+                let throwable = (|| {
+                    let message = to_java_string(env, error.to_string())?;
+                    let location = report
+                        .location
+                        .as_deref()
+                        .map(|location| to_java_string(env, location))
+                        .transpose()?
+                        .map_or_else(JObject::null, JObject::from);
+                    let backtrace = report
+                        .backtrace
+                        .as_deref()
+                        .map(|backtrace| to_java_string(env, backtrace))
+                        .transpose()?
+                        .map_or_else(JObject::null, JObject::from);
+                    new_instance(
+                        env,
+                        ClassName("org.signal.libsignal.protocol.logging.RustPanicError"),
+                        jni_args!((
+                            message => java.lang.String,
+                            location => java.lang.String,
+                            backtrace => java.lang.String,
+                        ) -> void),
+                    )
+                })();
+
+                return ConsumableException {
+                    throwable: throwable.map(Into::into),
+                    error: error.into(),
+                };
+            }
+
+            SignalJniError::Bridge(BridgeLayerError::BadJniParameter(_))
             | SignalJniError::Bridge(BridgeLayerError::UnexpectedJniResultType(_, _)) => {
                 // java.lang.AssertionError has a slightly different signature.
                 let throwable = to_java_string(env, error.to_string()).and_then(|message| {
@@ -335,7 +391,7 @@ impl<'env> ConsumableException<'env> {
                         found_unknown_fields,
                     } = err;
 
-                    let message = error.to_string().convert_into(env)?;
+                    let message = error.scrubbed().to_string().convert_into(env)?;
                     let found_unknown_fields = found_unknown_fields
                         .iter()
                         .map(|field| field.to_string())
@@ -376,7 +432,8 @@ impl<'env> ConsumableException<'env> {
             | SignalJniError::Protocol(SignalProtocolError::ApplicationCallbackError(_, _))
             | SignalJniError::Protocol(SignalProtocolError::FfiBindingError(_))
             | SignalJniError::DeviceTransfer(DeviceTransferError::InternalError(_))
-            | SignalJniError::DeviceTransfer(DeviceTransferError::KeyDecodingFailed) => {
+            | SignalJniError::DeviceTransfer(DeviceTransferError::KeyDecodingFailed)
+            | SignalJniError::DeviceTransfer(DeviceTransferError::CertificateDecodingFailed) => {
                 (ClassName("java.lang.RuntimeException"), error)
             }
 
@@ -441,6 +498,9 @@ impl<'env> ConsumableException<'env> {
                 ClassName("org.signal.libsignal.protocol.fingerprint.FingerprintParsingException"),
                 error,
             ),
+            SignalJniError::Protocol(SignalProtocolError::WithContext { .. }) => {
+                unreachable!("context was removed above")
+            }
 
             SignalJniError::HsmEnclave(HsmEnclaveError::HSMHandshakeError(_))
             | SignalJniError::HsmEnclave(HsmEnclaveError::HSMCommunicationError(_)) => (
@@ -478,7 +538,8 @@ impl<'env> ConsumableException<'env> {
 
             SignalJniError::Pin(PinError::Argon2Error(_))
             | SignalJniError::Pin(PinError::DecodingError(_))
-            | SignalJniError::Pin(PinError::MrenclaveLookupError) => {
+            | SignalJniError::Pin(PinError::MrenclaveLookupError)
+            | SignalJniError::Pin(PinError::MasterKeyDecryptionFailed) => {
                 (ClassName("java.lang.IllegalArgumentException"), error)
             }
 
@@ -608,6 +669,11 @@ impl<'env> ConsumableException<'env> {
                 error,
             ),
 
+            SignalJniError::Cancelled => (
+                ClassName("java.util.concurrent.CancellationException"),
+                error,
+            ),
+
             SignalJniError::WebSocket(_) | SignalJniError::ConnectTimedOut => (
                 ClassName("org.signal.libsignal.net.NetworkException"),
                 error,
@@ -660,12 +726,21 @@ impl<'env> ConsumableException<'env> {
             SignalJniError::TestingError { exception_class } => (exception_class, error),
         };
 
-        let throwable = to_java_string(env, error.to_string()).and_then(|message| {
-            new_instance(
+        let message = match context {
+            Some(context) => format!("{context}: {error}"),
+            None => error.to_string(),
+        };
+        let throwable = to_java_string(env, message).and_then(|message| match http_status {
+            Some(http_status) => new_instance(
+                env,
+                exception_type,
+                jni_args!((message => java.lang.String, http_status.into() => int) -> void),
+            ),
+            None => new_instance(
                 env,
                 exception_type,
                 jni_args!((message => java.lang.String) -> void),
-            )
+            ),
         });
         ConsumableException {
             throwable: throwable.map(Into::into),
@@ -728,6 +803,7 @@ where
     // If we get a panic downstream, it is entirely possible the Java environment won't be usable anymore.
     // But if that's the case, we've got bigger problems!
     // So if we want to catch panics, we have to allow this.
+    crate::support::panics::install_panic_hook();
     match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(env))) {
         Ok(Ok(r)) => r,
         Ok(Err(e)) => {
@@ -735,7 +811,7 @@ where
             R::default()
         }
         Err(r) => {
-            throw_error(env, BridgeLayerError::UnexpectedPanic(r).into());
+            throw_error(env, BridgeLayerError::unexpected_panic(r).into());
             R::default()
         }
     }
@@ -1051,7 +1127,9 @@ macro_rules! jni_bridge_handle_destroy {
                 handle: $crate::jni::ObjectHandle,
             ) {
                 if handle != 0 {
-                    let _boxed_value = Box::from_raw(handle as *mut $typ);
+                    let handle = handle as *mut $typ;
+                    $crate::support::handle_accounting::untrack(handle);
+                    let _boxed_value = Box::from_raw(handle);
                 }
             }
         }
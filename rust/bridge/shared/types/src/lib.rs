@@ -18,16 +18,29 @@ pub mod jni;
 #[macro_use]
 pub mod node;
 
+#[cfg(feature = "python")]
+#[macro_use]
+pub mod python;
+
+#[cfg(feature = "wasm")]
+#[macro_use]
+pub mod wasm;
+
 #[macro_use]
 pub mod support;
 
 pub use support::{describe_panic, AsyncRuntime, ResultReporter};
 
+// These depend on BoringSSL and tokio's multi-threaded runtime, which can't be built for wasm.
+#[cfg(not(target_arch = "wasm32"))]
 pub mod cds2;
 pub mod crypto;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod hsm_enclave;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod net;
 pub mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod sgx_session;
 pub mod zkgroup;
 
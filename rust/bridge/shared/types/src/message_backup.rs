@@ -6,6 +6,7 @@
 use libsignal_message_backup::frame::ValidationError as FrameValidationError;
 use libsignal_message_backup::key::{BackupKey, MessageBackupKey as MessageBackupKeyInner};
 use libsignal_message_backup::parse::ParseError;
+use libsignal_message_backup::scrub::Scrubbed;
 use libsignal_message_backup::{Error, FoundUnknownField};
 use libsignal_protocol::Aci;
 
@@ -21,7 +22,7 @@ impl MessageBackupKey {
     }
 }
 
-bridge_as_handle!(MessageBackupKey);
+bridge_as_handle!(MessageBackupKey, python = true);
 
 #[derive(Debug)]
 pub enum MessageBackupValidationError {
@@ -29,16 +30,21 @@ pub enum MessageBackupValidationError {
     String(String),
 }
 
+/// Error messages are [scrubbed](libsignal_message_backup::scrub) of user content before they
+/// leave the bridge.
 impl From<Error> for MessageBackupValidationError {
     fn from(value: Error) -> Self {
         match value {
-            Error::BackupValidation(e) => Self::String(e.to_string()),
-            Error::BackupCompletion(e) => Self::String(e.to_string()),
             Error::Parse(ParseError::Io(e)) => Self::Io(e),
-            e @ Error::NoFrames
-            | e @ Error::InvalidProtobuf(_)
-            | e @ Error::HmacMismatch(_)
-            | e @ Error::Parse(ParseError::Decode(_)) => Self::String(e.to_string()),
+            e @ (Error::BackupValidation(_)
+            | Error::BackupCompletion(_)
+            | Error::NoFrames
+            | Error::InvalidProtobuf(_)
+            | Error::HmacMismatch(_)
+            | Error::Parse(ParseError::Decode(_))
+            | Error::Parse(ParseError::FrameTooLarge { .. })) => {
+                Self::String(e.scrubbed().to_string())
+            }
         }
     }
 }
@@ -48,7 +54,7 @@ impl From<FrameValidationError> for MessageBackupValidationError {
         match value {
             FrameValidationError::Io(e) => Self::Io(e),
             e @ (FrameValidationError::TooShort | FrameValidationError::InvalidHmac(_)) => {
-                Self::String(e.to_string())
+                Self::String(Scrubbed(e).to_string())
             }
         }
     }
@@ -58,7 +64,12 @@ pub struct MessageBackupValidationOutcome {
     pub error_message: Option<String>,
     pub found_unknown_fields: Vec<FoundUnknownField>,
 }
-bridge_as_handle!(MessageBackupValidationOutcome, jni = false, node = false);
+bridge_as_handle!(
+    MessageBackupValidationOutcome,
+    jni = false,
+    node = false,
+    python = true
+);
 
 pub struct ComparableBackup {
     pub backup: libsignal_message_backup::backup::serialize::Backup,
@@ -3,26 +3,32 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::num::{NonZeroU16, NonZeroU32};
 use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use aes_gcm_siv::aead::rand_core::CryptoRngCore;
 use async_trait::async_trait;
 use futures_util::future::join3;
 use libsignal_net::auth::Auth;
+use libsignal_net::clock::{Clock as _, OffsetClock};
+use libsignal_net::enclave::events::{EnclaveEventSink, EnclaveInteraction};
 use libsignal_net::enclave::{
     Cdsi, EnclaveEndpoint, EnclaveEndpointConnection, EnclaveKind, Nitro, PpssSetup, Sgx, Tpm2Snp,
 };
-use libsignal_net::env::{add_user_agent_header, Env, Svr3Env};
+use libsignal_net::env::{add_user_agent_header, DomainConfig, Env, Svr3Env};
 use libsignal_net::infra::connection_manager::MultiRouteConnectionManager;
 use libsignal_net::infra::dns::DnsResolver;
 use libsignal_net::infra::host::Host;
 use libsignal_net::infra::tcp_ssl::proxy::tls::TlsProxyConnector as TcpSslProxyConnector;
 use libsignal_net::infra::tcp_ssl::{
-    DirectConnector as TcpSslDirectConnector, TcpSslConnector, TcpSslConnectorStream,
+    DirectConnector as TcpSslDirectConnector, TcpSslConnector, TcpSslConnectorStream, TlsPolicy,
 };
-use libsignal_net::infra::EndpointConnection;
+use libsignal_net::infra::{ConnectionParams, EndpointConnection};
+use libsignal_net::route_discovery::{RouteDocument, RouteService};
 use libsignal_net::svr::SvrConnection;
 use libsignal_net::svr3::traits::*;
 use libsignal_net::svr3::{Error, OpaqueMaskedShareSet};
@@ -55,7 +61,7 @@ impl Environment {
     }
 }
 
-pub struct ConnectionManager {
+struct Endpoints {
     chat: EndpointConnection<MultiRouteConnectionManager>,
     cdsi: EnclaveEndpointConnection<Cdsi, MultiRouteConnectionManager>,
     svr3: (
@@ -63,8 +69,27 @@ pub struct ConnectionManager {
         EnclaveEndpointConnection<Nitro, MultiRouteConnectionManager>,
         EnclaveEndpointConnection<Tpm2Snp, MultiRouteConnectionManager>,
     ),
+    /// When the route document applied to each service was issued.
+    route_documents_issued_at: HashMap<RouteService, SystemTime>,
+}
+
+pub struct ConnectionManager {
+    environment: Environment,
+    user_agent: String,
+    /// Replaced as a whole when routes are discovered, so that connections that are already being
+    /// established keep using the routes they started with.
+    endpoints: std::sync::Mutex<Arc<Endpoints>>,
     transport_connector: std::sync::Mutex<TcpSslConnector>,
+    /// Applied to every transport connector, including ones created when the proxy changes.
+    ///
+    /// Only locked while `transport_connector` is held.
+    tls_policy: std::sync::Mutex<TlsPolicy>,
     network_change_event: ObservableEvent,
+    /// Used to check enclave attestations and route documents; apps can correct it if the device's
+    /// clock is wrong.
+    clock: Arc<OffsetClock>,
+    /// Told about each request sent to an SVR3 enclave.
+    svr3_event_sink: std::sync::Mutex<Arc<dyn EnclaveEventSink>>,
 }
 
 impl RefUnwindSafe for ConnectionManager {}
@@ -79,40 +104,150 @@ impl ConnectionManager {
         );
         let transport_connector =
             std::sync::Mutex::new(TcpSslDirectConnector::new(dns_resolver).into());
+        let clock = Arc::new(OffsetClock::default());
         let chat = libsignal_net::chat::endpoint_connection(
             &environment.env().chat_domain_config,
             &user_agent,
             &network_change_event,
         );
-        Self {
+        let endpoints = Endpoints {
             chat,
             cdsi: Self::endpoint_connection(
                 &environment.env().cdsi,
                 &user_agent,
                 &network_change_event,
+                &clock,
             ),
             svr3: (
                 Self::endpoint_connection(
                     environment.env().svr3.sgx(),
                     &user_agent,
                     &network_change_event,
+                    &clock,
                 ),
                 Self::endpoint_connection(
                     environment.env().svr3.nitro(),
                     &user_agent,
                     &network_change_event,
+                    &clock,
                 ),
                 Self::endpoint_connection(
                     environment.env().svr3.tpm2snp(),
                     &user_agent,
                     &network_change_event,
+                    &clock,
                 ),
             ),
+            route_documents_issued_at: HashMap::new(),
+        };
+        Self {
+            environment,
+            user_agent,
+            endpoints: std::sync::Mutex::new(Arc::new(endpoints)),
             transport_connector,
+            tls_policy: Default::default(),
             network_change_event,
+            clock,
+            svr3_event_sink: std::sync::Mutex::new(Arc::new(LogEnclaveEvents)),
         }
     }
 
+    fn endpoints(&self) -> Arc<Endpoints> {
+        Arc::clone(&self.endpoints.lock().expect("not poisoned"))
+    }
+
+    /// Verifies a [`RouteDocument`] signed with `key` and tries its routes after the first
+    /// configured route for the document's service, until the document expires.
+    ///
+    /// Replaces the routes from any previously applied document for the same service, but only if
+    /// this document was issued after that one.
+    pub fn apply_route_document(
+        &self,
+        signed_document: &[u8],
+        key: &libsignal_protocol::PublicKey,
+    ) -> Result<(), std::io::Error> {
+        let now = self.clock.now();
+        let document = RouteDocument::verify(signed_document, key, now)?;
+        let valid_for = document.time_remaining(now);
+        let env = self.environment.env();
+
+        let mut guard = self.endpoints.lock().expect("not poisoned");
+        document.check_newer_than(
+            guard
+                .route_documents_issued_at
+                .get(&document.service)
+                .copied(),
+        )?;
+        let Endpoints {
+            chat,
+            cdsi,
+            svr3: (sgx, nitro, tpm2snp),
+            route_documents_issued_at,
+        } = &**guard;
+        let mut route_documents_issued_at = route_documents_issued_at.clone();
+        route_documents_issued_at.insert(document.service, document.issued_at);
+        let endpoints = match document.service {
+            RouteService::Chat => Endpoints {
+                chat: chat.with_discovered_routes(
+                    self.discovered_params(&document, &env.chat_domain_config),
+                    valid_for,
+                    ONE_ROUTE_CONNECTION_TIMEOUT,
+                    &self.network_change_event,
+                ),
+                cdsi: cdsi.clone(),
+                svr3: (sgx.clone(), nitro.clone(), tpm2snp.clone()),
+                route_documents_issued_at,
+            },
+            RouteService::Cdsi => Endpoints {
+                chat: chat.clone(),
+                cdsi: self.with_discovered_routes(cdsi, &document, &env.cdsi, valid_for),
+                svr3: (sgx.clone(), nitro.clone(), tpm2snp.clone()),
+                route_documents_issued_at,
+            },
+            RouteService::Svr3 => Endpoints {
+                chat: chat.clone(),
+                cdsi: cdsi.clone(),
+                svr3: (
+                    self.with_discovered_routes(sgx, &document, env.svr3.sgx(), valid_for),
+                    self.with_discovered_routes(nitro, &document, env.svr3.nitro(), valid_for),
+                    self.with_discovered_routes(tpm2snp, &document, env.svr3.tpm2snp(), valid_for),
+                ),
+                route_documents_issued_at,
+            },
+        };
+        log::info!(
+            "applied route document for {:?} with {} routes, valid for {:?}",
+            document.service,
+            document.routes.len(),
+            valid_for
+        );
+        *guard = Arc::new(endpoints);
+        Ok(())
+    }
+
+    fn discovered_params(
+        &self,
+        document: &RouteDocument,
+        domain_config: &DomainConfig,
+    ) -> Vec<ConnectionParams> {
+        add_user_agent_header(document.connection_params(domain_config), &self.user_agent)
+    }
+
+    fn with_discovered_routes<E: EnclaveKind>(
+        &self,
+        connection: &EnclaveEndpointConnection<E, MultiRouteConnectionManager>,
+        document: &RouteDocument,
+        endpoint: &EnclaveEndpoint<'static, E>,
+        valid_for: Duration,
+    ) -> EnclaveEndpointConnection<E, MultiRouteConnectionManager> {
+        connection.with_discovered_routes(
+            self.discovered_params(document, &endpoint.domain_config),
+            valid_for,
+            ONE_ROUTE_CONNECTION_TIMEOUT,
+            &self.network_change_event,
+        )
+    }
+
     pub fn set_proxy(&self, host: &str, port: Option<NonZeroU16>) -> Result<(), std::io::Error> {
         let host = Host::parse_as_ip_or_domain(host);
 
@@ -129,6 +264,7 @@ impl ConnectionManager {
                         *guard = TcpSslProxyConnector::new(dns_resolver.clone(), proxy_addr).into()
                     }
                 };
+                self.apply_tls_policy(&mut guard);
                 Ok(())
             }
             None => {
@@ -153,6 +289,18 @@ impl ConnectionManager {
                 *guard = TcpSslDirectConnector::new(dns_resolver.clone()).into()
             }
         };
+        self.apply_tls_policy(&mut guard);
+    }
+
+    /// Applies `tls_policy` to future connections, whatever proxy is used.
+    pub fn set_tls_policy(&self, tls_policy: TlsPolicy) {
+        let mut guard = self.transport_connector.lock().expect("not poisoned");
+        *self.tls_policy.lock().expect("not poisoned") = tls_policy;
+        self.apply_tls_policy(&mut guard);
+    }
+
+    fn apply_tls_policy(&self, transport_connector: &mut TcpSslConnector) {
+        transport_connector.set_tls_policy(self.tls_policy.lock().expect("not poisoned").clone());
     }
 
     pub fn set_ipv6_enabled(&self, ipv6_enabled: bool) {
@@ -164,6 +312,7 @@ impl ConnectionManager {
         endpoint: &EnclaveEndpoint<'static, E>,
         user_agent: &str,
         network_change_event: &ObservableEvent,
+        clock: &Arc<OffsetClock>,
     ) -> EnclaveEndpointConnection<E, MultiRouteConnectionManager> {
         let params = endpoint.domain_config.connection_params_with_fallback();
         let params = add_user_agent_header(params, user_agent);
@@ -173,29 +322,67 @@ impl ConnectionManager {
             ONE_ROUTE_CONNECTION_TIMEOUT,
             network_change_event,
         )
+        .with_clock(clock.clone())
+    }
+
+    /// Corrects the time used to check enclave attestations and route documents by
+    /// `offset_seconds`, for devices whose clocks are known to be wrong.
+    pub fn set_clock_offset(&self, offset_seconds: i32) {
+        self.clock
+            .set_offset_millis(i64::from(offset_seconds) * 1000);
     }
 
     pub fn on_network_change(&self) {
         self.network_change_event.fire()
     }
+
+    /// Reports each request sent to an SVR3 enclave to `sink` instead of logging it.
+    ///
+    /// Operations that are already in progress keep reporting to the previous sink.
+    pub fn set_svr3_event_sink(&self, sink: Arc<dyn EnclaveEventSink>) {
+        *self.svr3_event_sink.lock().expect("not poisoned") = sink;
+    }
 }
 
 bridge_as_handle!(ConnectionManager);
 
+/// Logs each enclave interaction, so that an enclave that slows down or fails SVR3 operations shows
+/// up in the app's logs.
+struct LogEnclaveEvents;
+
+impl EnclaveEventSink for LogEnclaveEvents {
+    fn on_interaction(&self, interaction: &EnclaveInteraction<'_>) {
+        let EnclaveInteraction {
+            operation,
+            enclave,
+            elapsed,
+            outcome,
+        } = interaction;
+        log::info!("SVR3 {operation} with {enclave}: {outcome:?} after {elapsed:?}");
+    }
+}
+
 pub enum PreviousVersion {}
 pub enum CurrentVersion {}
 
 pub struct Svr3Client<'a, Kind> {
     connection_manager: &'a ConnectionManager,
     auth: Auth,
+    event_sink: Arc<dyn EnclaveEventSink>,
     kind: PhantomData<Kind>,
 }
 
 impl<'a, Kind> Svr3Client<'a, Kind> {
     fn new(connection_manager: &'a ConnectionManager, auth: Auth) -> Self {
+        let event_sink = connection_manager
+            .svr3_event_sink
+            .lock()
+            .expect("not poisoned")
+            .clone();
         Self {
             connection_manager,
             auth,
+            event_sink,
             kind: PhantomData,
         }
     }
@@ -212,7 +399,10 @@ impl<'a> Svr3Clients<'a> {
         username: String,
         password: String,
     ) -> Self {
-        let auth = Auth { username, password };
+        let auth = Auth {
+            username,
+            password: password.into(),
+        };
         Self {
             previous: Svr3Client::new(connection_manager, auth.clone()),
             current: Svr3Client::new(connection_manager, auth),
@@ -226,12 +416,14 @@ impl<'a> Svr3Connect for Svr3Client<'a, CurrentVersion> {
     type Env = Svr3Env<'static>;
 
     async fn connect(&self) -> <Self::Env as PpssSetup<Self::Stream>>::ConnectionResults {
-        let ConnectionManager {
-            svr3: (sgx, nitro, tpm2snp),
-            transport_connector,
-            ..
-        } = &self.connection_manager;
-        let transport_connector = transport_connector.lock().expect("not poisoned").clone();
+        let endpoints = self.connection_manager.endpoints();
+        let (sgx, nitro, tpm2snp) = &endpoints.svr3;
+        let transport_connector = self
+            .connection_manager
+            .transport_connector
+            .lock()
+            .expect("not poisoned")
+            .clone();
         let (sgx, nitro, tpm2snp) = join3(
             SvrConnection::connect(self.auth.clone(), sgx, transport_connector.clone()),
             SvrConnection::connect(self.auth.clone(), nitro, transport_connector.clone()),
@@ -240,6 +432,10 @@ impl<'a> Svr3Connect for Svr3Client<'a, CurrentVersion> {
         .await;
         (sgx, nitro, tpm2snp)
     }
+
+    fn event_sink(&self) -> &dyn EnclaveEventSink {
+        &*self.event_sink
+    }
 }
 
 #[async_trait]
@@ -319,7 +515,10 @@ mod empty_env {
 
 #[cfg(test)]
 mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
     use assert_matches::assert_matches;
+    use libsignal_net::enclave::events::InteractionOutcome;
     use test_case::test_case;
 
     use super::*;
@@ -330,6 +529,103 @@ mod test {
         let _ = ConnectionManager::new(env, "test-user-agent".to_string());
     }
 
+    #[test]
+    fn svr3_clients_report_to_event_sink() {
+        #[derive(Default)]
+        struct CountingSink(AtomicUsize);
+
+        impl EnclaveEventSink for CountingSink {
+            fn on_interaction(&self, _interaction: &EnclaveInteraction<'_>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let manager = ConnectionManager::new(Environment::Staging, "test-user-agent".to_owned());
+        let sink = Arc::new(CountingSink::default());
+        manager.set_svr3_event_sink(sink.clone());
+
+        let clients = Svr3Clients::new(&manager, "username".to_owned(), "password".to_owned());
+        clients
+            .current
+            .event_sink()
+            .on_interaction(&EnclaveInteraction {
+                operation: "query",
+                enclave: &Host::Domain("enclave.example".into()),
+                elapsed: Duration::from_millis(10),
+                outcome: InteractionOutcome::Response,
+            });
+        assert_eq!(sink.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn route_documents_only_replace_older_ones_for_the_same_service() {
+        use base64::prelude::{Engine as _, BASE64_STANDARD};
+        use libsignal_protocol::KeyPair;
+        use rand::rngs::OsRng;
+
+        let key_pair = KeyPair::generate(&mut OsRng);
+        let sign = |service: &str, issued_at: u64| {
+            let document = serde_json::json!({
+                "version": 1,
+                "service": service,
+                "issuedAt": issued_at,
+                "expiresAt": 4_000_000_000u64,
+                "routes": [{"type": "tlsProxy", "host": "proxy.example"}],
+            })
+            .to_string();
+            let signature = key_pair
+                .calculate_signature(document.as_bytes(), &mut OsRng)
+                .expect("can sign");
+            serde_json::json!({
+                "document": BASE64_STANDARD.encode(document),
+                "signature": BASE64_STANDARD.encode(signature),
+            })
+            .to_string()
+        };
+        let issued_at = |manager: &ConnectionManager, service| {
+            manager
+                .endpoints()
+                .route_documents_issued_at
+                .get(&service)
+                .copied()
+        };
+        let seconds = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        let manager = ConnectionManager::new(Environment::Staging, "test-user-agent".to_owned());
+        let apply = |service, issued_at| {
+            manager.apply_route_document(sign(service, issued_at).as_bytes(), &key_pair.public_key)
+        };
+
+        apply("chat", 1_000).expect("first document");
+        assert_eq!(
+            issued_at(&manager, RouteService::Chat),
+            Some(seconds(1_000))
+        );
+        assert_eq!(issued_at(&manager, RouteService::Cdsi), None);
+
+        for stale in [1_000, 999] {
+            let error = apply("chat", stale).expect_err("not newer");
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+        assert_eq!(
+            issued_at(&manager, RouteService::Chat),
+            Some(seconds(1_000))
+        );
+
+        apply("cdsi", 500).expect("other service");
+        assert_eq!(issued_at(&manager, RouteService::Cdsi), Some(seconds(500)));
+        assert_eq!(
+            issued_at(&manager, RouteService::Chat),
+            Some(seconds(1_000))
+        );
+
+        apply("chat", 1_001).expect("newer document");
+        assert_eq!(
+            issued_at(&manager, RouteService::Chat),
+            Some(seconds(1_001))
+        );
+    }
+
     #[test]
     fn connection_manager_invalid_after_invalid_host_port() {
         let manager = ConnectionManager::new(Environment::Staging, "test-user-agent".to_owned());
@@ -338,4 +634,44 @@ mod test {
         let transport_connector = manager.transport_connector.lock().expect("not poisoned");
         assert_matches!(&*transport_connector, TcpSslConnector::Invalid(_))
     }
+
+    #[test]
+    fn connection_manager_keeps_tls_policy_across_proxy_changes() {
+        let manager = ConnectionManager::new(Environment::Staging, "test-user-agent".to_owned());
+        manager.set_tls_policy(TlsPolicy::tls1_3_only());
+
+        let assert_policy = |manager: &ConnectionManager| {
+            let transport_connector = manager.transport_connector.lock().expect("not poisoned");
+            match &*transport_connector {
+                TcpSslConnector::Direct(direct) => {
+                    assert_eq!(direct.tls_policy, TlsPolicy::tls1_3_only())
+                }
+                TcpSslConnector::Proxied(proxied) => {
+                    assert_eq!(proxied.tls_policy, TlsPolicy::tls1_3_only())
+                }
+                TcpSslConnector::Invalid(_) => panic!("expected a valid connector"),
+            }
+        };
+        assert_policy(&manager);
+
+        manager
+            .set_proxy("proxy.host", NonZeroU16::new(443))
+            .expect("valid proxy");
+        assert_policy(&manager);
+
+        // Going through an invalid proxy loses the connector, but not the policy.
+        manager
+            .set_proxy("proxy.host", None)
+            .expect_err("invalid port");
+        manager
+            .set_proxy("proxy.host", NonZeroU16::new(443))
+            .expect("valid proxy");
+        assert_policy(&manager);
+
+        manager
+            .set_proxy("proxy.host", None)
+            .expect_err("invalid port");
+        manager.clear_proxy();
+        assert_policy(&manager);
+    }
 }
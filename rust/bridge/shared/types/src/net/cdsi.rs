@@ -41,6 +41,46 @@ impl LookupRequest {
 
 bridge_as_handle!(LookupRequest);
 
+impl ConnectionManager {
+    /// Performs a complete CDSI lookup, returning the token along with the results.
+    pub async fn cdsi_lookup(
+        &self,
+        auth: Auth,
+        request: cdsi::LookupRequest,
+    ) -> Result<(Token, cdsi::LookupResponse), cdsi::LookupError> {
+        let transport_connector = self
+            .transport_connector
+            .lock()
+            .expect("not poisoned")
+            .clone();
+        cdsi::cdsi_lookup(&self.endpoints().cdsi, transport_connector, auth, request).await
+    }
+}
+
+/// The outcome of [`ConnectionManager::cdsi_lookup`], held so the token and the response can be
+/// read out separately.
+pub struct CdsiLookupResult {
+    pub token: Token,
+    response: std::sync::Mutex<Option<cdsi::LookupResponse>>,
+}
+
+impl CdsiLookupResult {
+    pub fn take_response(&self) -> Option<cdsi::LookupResponse> {
+        self.response.lock().expect("not poisoned").take()
+    }
+}
+
+impl From<(Token, cdsi::LookupResponse)> for CdsiLookupResult {
+    fn from((token, response): (Token, cdsi::LookupResponse)) -> Self {
+        Self {
+            token,
+            response: std::sync::Mutex::new(Some(response)),
+        }
+    }
+}
+
+bridge_as_handle!(CdsiLookupResult);
+
 pub struct CdsiLookup {
     pub token: Token,
     remaining: std::sync::Mutex<Option<ClientResponseCollector<TcpSslConnectorStream>>>,
@@ -57,8 +97,8 @@ impl CdsiLookup {
             .lock()
             .expect("not poisoned")
             .clone();
-        let connected =
-            CdsiConnection::connect(&connection_manager.cdsi, transport_connector, auth).await?;
+        let endpoints = connection_manager.endpoints();
+        let connected = CdsiConnection::connect(&endpoints.cdsi, transport_connector, auth).await?;
         let (token, remaining_response) = connected.send_request(request).await?;
 
         Ok(CdsiLookup {
@@ -80,7 +80,7 @@ impl Chat {
 
         Chat {
             service: chat::chat_service(
-                &connection_manager.chat,
+                &connection_manager.endpoints().chat,
                 connection_manager
                     .transport_connector
                     .lock()
@@ -144,9 +144,13 @@ impl Chat {
         // We're not using run_future here because we aren't trying to run a single task; we're
         // starting a run-loop. We *do* want that run-loop to be async so it goes to sleep when
         // there are no messages.
-        let handle = runtime
-            .rt
-            .spawn(listener.start_listening(request_stream_future, cancel_rx));
+        let run_loop = listener.start_listening(request_stream_future, cancel_rx);
+        #[cfg(feature = "tracing")]
+        let run_loop = tracing::Instrument::instrument(
+            run_loop,
+            tracing::info_span!(parent: None, "chat_listener"),
+        );
+        let handle = runtime.rt.spawn(run_loop);
 
         *guard = ChatListenerState::Active {
             handle,
@@ -309,7 +313,13 @@ impl dyn ChatListener {
             // properly async tasks in the mean time. (And because of this, we have to move
             // `listener` out and back into this task.)
             let mut listener_for_blocking_task = listener.take().expect("have listener");
+            // Carry the span over to the blocking thread, so anything the app's callback
+            // logs through `tracing` is attributed to this listener.
+            #[cfg(feature = "tracing")]
+            let span = tracing::Span::current();
             let blocking_task = runtime.spawn_blocking(move || {
+                #[cfg(feature = "tracing")]
+                let _entered = span.enter();
                 listener_for_blocking_task.received_server_request(next);
                 listener_for_blocking_task
             });
@@ -5,35 +5,190 @@
 
 use std::collections::HashMap;
 use std::future::Future;
+use std::num::NonZeroUsize;
 use std::sync::atomic::AtomicU64;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
 use futures_util::future::BoxFuture;
 use futures_util::FutureExt as _;
 
 use crate::support::*;
 use crate::*;
+
+/// Settings for the runtime behind a [`TokioAsyncContext`].
+#[derive(Clone, Debug)]
+pub struct TokioAsyncContextConfig {
+    /// The number of worker threads, or `None` to use one per CPU core.
+    ///
+    /// Ignored if `use_current_thread` is set.
+    pub worker_threads: Option<NonZeroUsize>,
+    /// The name given to the runtime's threads.
+    pub thread_name: String,
+    /// Whether to run all tasks on a single background thread, using tokio's current-thread
+    /// scheduler, instead of on a pool of worker threads.
+    pub use_current_thread: bool,
+}
+
+impl Default for TokioAsyncContextConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: None,
+            thread_name: "libsignal-tokio-worker".to_owned(),
+            use_current_thread: false,
+        }
+    }
+}
+
 pub struct TokioAsyncContext {
-    pub(crate) rt: tokio::runtime::Runtime,
+    pub(crate) rt: tokio::runtime::Handle,
+    /// The owner of the runtime's threads, or `None` once [`TokioAsyncContext::shutdown`] has been
+    /// called.
+    runtime: Mutex<Option<RuntimeOwner>>,
     tasks: Arc<Mutex<HashMap<CancellationId, tokio::sync::oneshot::Sender<()>>>>,
+    running_tasks: Arc<RunningTaskCount>,
     next_raw_cancellation_id: AtomicU64,
 }
 
+enum RuntimeOwner {
+    MultiThread(tokio::runtime::Runtime),
+    /// A current-thread runtime, driven by `thread` until a shutdown timeout is sent over `stop`.
+    CurrentThread {
+        stop: tokio::sync::oneshot::Sender<Duration>,
+        thread: std::thread::JoinHandle<()>,
+    },
+}
+
 impl TokioAsyncContext {
     // This is an expensive operation, so we don't want to just use Default.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        Self {
-            rt: tokio::runtime::Builder::new_multi_thread()
+        Self::with_config(&TokioAsyncContextConfig::default()).expect("failed to create runtime")
+    }
+
+    pub fn with_config(config: &TokioAsyncContextConfig) -> std::io::Result<Self> {
+        if config.use_current_thread {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_io()
+                .enable_time()
+                .thread_name(&config.thread_name)
+                .build()?;
+            let handle = runtime.handle().clone();
+            let (stop, stop_rx) = tokio::sync::oneshot::channel();
+            let thread = std::thread::Builder::new()
+                .name(config.thread_name.clone())
+                .spawn(move || match runtime.block_on(stop_rx) {
+                    Ok(timeout) => runtime.shutdown_timeout(timeout),
+                    // The context was dropped without being shut down; wait for any
+                    // in-progress reporting like a multi-threaded runtime would.
+                    Err(_) => drop(runtime),
+                })?;
+            Ok(Self::from_parts(
+                handle,
+                RuntimeOwner::CurrentThread { stop, thread },
+            ))
+        } else {
+            let mut builder = tokio::runtime::Builder::new_multi_thread();
+            if let Some(worker_threads) = config.worker_threads {
+                builder.worker_threads(worker_threads.get());
+            }
+            let runtime = builder
                 .enable_io()
                 .enable_time()
-                .thread_name("libsignal-tokio-worker")
-                .build()
-                .expect("failed to create runtime"),
+                .thread_name(&config.thread_name)
+                .build()?;
+            Ok(Self::from_parts(
+                runtime.handle().clone(),
+                RuntimeOwner::MultiThread(runtime),
+            ))
+        }
+    }
+
+    fn from_parts(rt: tokio::runtime::Handle, owner: RuntimeOwner) -> Self {
+        Self {
+            rt,
+            runtime: Mutex::new(Some(owner)),
             tasks: Default::default(),
+            running_tasks: Default::default(),
             next_raw_cancellation_id: AtomicU64::new(1),
         }
     }
+
+    /// Cancels all outstanding tasks and stops the runtime's threads.
+    ///
+    /// Cancelled tasks are given up to `timeout` to report their results; any that are still
+    /// running after that are dropped without reporting. Tasks started after shutdown are
+    /// cancelled immediately. Calling this more than once has no further effect.
+    ///
+    /// This blocks, so it must not be called from within an async task.
+    pub fn shutdown(&self, timeout: Duration) {
+        let Some(owner) = self.runtime.lock().expect("not poisoned").take() else {
+            log::debug!("async runtime already shut down");
+            return;
+        };
+        let start = Instant::now();
+
+        let cancel_txs = std::mem::take(&mut *self.tasks.lock().expect("task map isn't poisoned"));
+        log::info!(
+            "shutting down async runtime, cancelling {} tasks",
+            cancel_txs.len()
+        );
+        // Dropping the cancellation Senders cancels the tasks.
+        drop(cancel_txs);
+        if !self.running_tasks.wait_until_idle(timeout) {
+            log::warn!("timed out waiting for cancelled tasks to finish");
+        }
+
+        let remaining = timeout.saturating_sub(start.elapsed());
+        match owner {
+            RuntimeOwner::MultiThread(runtime) => runtime.shutdown_timeout(remaining),
+            RuntimeOwner::CurrentThread { stop, thread } => {
+                // If the thread has already exited, there's nothing to stop.
+                _ = stop.send(remaining);
+                if thread.join().is_err() {
+                    log::error!("async runtime thread panicked");
+                }
+            }
+        }
+        log::info!("async runtime shut down");
+    }
+}
+
+/// Counts spawned tasks that haven't yet handed off their results for reporting.
+#[derive(Default)]
+struct RunningTaskCount {
+    count: Mutex<usize>,
+    became_idle: Condvar,
+}
+
+impl RunningTaskCount {
+    fn start(self: &Arc<Self>) -> RunningTaskGuard {
+        *self.count.lock().expect("not poisoned") += 1;
+        RunningTaskGuard(self.clone())
+    }
+
+    /// Returns `false` if there were still tasks running after `timeout`.
+    fn wait_until_idle(&self, timeout: Duration) -> bool {
+        let guard = self.count.lock().expect("not poisoned");
+        let (_guard, result) = self
+            .became_idle
+            .wait_timeout_while(guard, timeout, |count| *count != 0)
+            .expect("not poisoned");
+        !result.timed_out()
+    }
+}
+
+/// Marks a task as finished when dropped, even if the task is dropped by the runtime.
+struct RunningTaskGuard(Arc<RunningTaskCount>);
+
+impl Drop for RunningTaskGuard {
+    fn drop(&mut self) {
+        let mut count = self.0.count.lock().expect("not poisoned");
+        *count -= 1;
+        if *count == 0 {
+            self.0.became_idle.notify_all();
+        }
+    }
 }
 
 /// Assert [`TokioAsyncContext`] is unwind-safe.
@@ -133,8 +288,28 @@ impl TokioAsyncContext {
             dyn 's + FnOnce(TokioContextCancellation) -> BoxFuture<'static, ReportResultBoxed>,
         >,
     ) -> CancellationId {
+        // Hold the lock until the task is in the task map, so that shutdown can't miss it.
+        let runtime_guard = self.runtime.lock().expect("not poisoned");
         let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel();
 
+        if runtime_guard.is_none() {
+            log::warn!("async runtime has been shut down; cancelling new task");
+            drop(cancel_tx);
+            let future = make_future(TokioContextCancellation(cancel_rx));
+            // There's no runtime left to run the task on, so give it a temporary one just long
+            // enough for it to notice it's been cancelled.
+            let _: std::thread::JoinHandle<()> = std::thread::spawn(move || {
+                let report_fn = tokio::runtime::Builder::new_current_thread()
+                    .enable_io()
+                    .enable_time()
+                    .build()
+                    .expect("failed to create runtime")
+                    .block_on(future);
+                report_fn()
+            });
+            return CancellationId::NotSupported;
+        }
+
         let cancellation_id = CancellationId::from(
             self.next_raw_cancellation_id
                 .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
@@ -149,16 +324,19 @@ impl TokioAsyncContext {
             previous_cancel_tx.is_none(),
             "shouldn't reuse cancellation IDs"
         );
+        drop(runtime_guard);
 
         let future = make_future(TokioContextCancellation(cancel_rx));
 
-        let handle = self.rt.handle().clone();
+        let handle = self.rt.clone();
         let task_map_weak = Arc::downgrade(&self.tasks);
+        let running_task = self.running_tasks.start();
 
         #[allow(clippy::let_underscore_future)]
         let _: tokio::task::JoinHandle<()> = self.rt.spawn(async move {
             let report_fn = future.await;
             let _: tokio::task::JoinHandle<()> = handle.spawn_blocking(report_fn);
+            drop(running_task);
             // What happens if we don't get here? We leak an entry in the task map. Also, we
             // probably have bigger problems, because in practice all the `bridge_io` futures are
             // supposed to be catching panics.
@@ -182,6 +360,7 @@ mod test {
     use std::sync::{Arc, Mutex};
 
     use assert_matches::assert_matches;
+    use test_case::test_case;
     use tokio::sync::{mpsc, oneshot};
 
     use super::*;
@@ -249,11 +428,10 @@ mod test {
         let (sum_tx, mut sum_rx, sum_future) = sum_task();
         runtime.spawn(sum_future);
 
-        let async_context = TokioAsyncContext {
-            rt: runtime,
-            tasks: Default::default(),
-            next_raw_cancellation_id: AtomicU64::new(1),
-        };
+        let async_context = TokioAsyncContext::from_parts(
+            runtime.handle().clone(),
+            RuntimeOwner::MultiThread(runtime),
+        );
 
         let (send_to_task, task_output, when_reporting) = {
             let (sender, receiver) = oneshot::channel();
@@ -313,11 +491,10 @@ mod test {
         runtime_builder.worker_threads(1);
         let runtime = runtime_builder.build().expect("valid runtime");
 
-        let async_context = TokioAsyncContext {
-            rt: runtime,
-            tasks: Default::default(),
-            next_raw_cancellation_id: AtomicU64::new(1),
-        };
+        let async_context = TokioAsyncContext::from_parts(
+            runtime.handle().clone(),
+            RuntimeOwner::MultiThread(runtime),
+        );
 
         let (on_start_reporting1, mut when_reporting1) = oneshot::channel();
         let cancellation_id1 = async_context.run_future(
@@ -362,4 +539,49 @@ mod test {
         async_context.cancel(cancellation_id1);
         when_reporting1.blocking_recv().expect("completed");
     }
+
+    #[test_case(false; "multi-threaded")]
+    #[test_case(true; "current-thread")]
+    fn shutdown_cancels_tasks(use_current_thread: bool) {
+        let async_context = TokioAsyncContext::with_config(&TokioAsyncContextConfig {
+            worker_threads: NonZeroUsize::new(1),
+            thread_name: "test-worker".to_owned(),
+            use_current_thread,
+        })
+        .expect("valid runtime");
+
+        let (on_start_reporting, when_reporting) = oneshot::channel();
+        let cancellation_id = async_context.run_future(
+            |cancel| async move {
+                cancel.await;
+                NotifyingReporter {
+                    on_start_reporting,
+                    reporter: DiscardingReporter,
+                }
+            },
+            (),
+        );
+        assert_ne!(cancellation_id, CancellationId::NotSupported);
+
+        async_context.shutdown(Duration::from_secs(10));
+        when_reporting.blocking_recv().expect("completed");
+
+        // Tasks started after shutdown are cancelled without ever running.
+        let (on_start_reporting, when_reporting) = oneshot::channel();
+        let cancellation_id = async_context.run_future(
+            |cancel| async move {
+                cancel.await;
+                NotifyingReporter {
+                    on_start_reporting,
+                    reporter: DiscardingReporter,
+                }
+            },
+            (),
+        );
+        assert_eq!(cancellation_id, CancellationId::NotSupported);
+        when_reporting.blocking_recv().expect("completed");
+
+        // Shutting down again does nothing.
+        async_context.shutdown(Duration::ZERO);
+    }
 }
@@ -11,6 +11,7 @@ use signal_media::sanitize::mp4::{Error as Mp4Error, ParseError as Mp4ParseError
 use signal_media::sanitize::webp::{Error as WebpError, ParseError as WebpParseError};
 
 use super::*;
+use crate::support::{ErrorDetails, ProvideErrorDetails};
 
 const ERRORS_PROPERTY_NAME: &str = "Errors";
 const ERROR_CLASS_NAME: &str = "LibSignalErrorBase";
@@ -28,12 +29,25 @@ fn no_extra_properties<'a>(cx: &mut impl Context<'a>) -> JsResult<'a, JsValue> {
     Ok(cx.undefined().upcast())
 }
 
-fn optional_extra_properties<'a, C: Context<'a>, F: FnOnce(&mut C) -> JsResult<'a, JsValue>>(
-    f: Option<F>,
+/// Sets a property for each of the fields present in `details`, other than the address.
+fn error_details_properties<'a, C: Context<'a>>(
+    details: ErrorDetails,
 ) -> impl FnOnce(&mut C) -> JsResult<'a, JsValue> {
-    |cx| match f {
-        None => no_extra_properties(cx),
-        Some(f) => f(cx),
+    move |cx| {
+        let props = cx.empty_object();
+        if let Some(retry_after_seconds) = details.retry_after_seconds {
+            let retry_after = cx.number(retry_after_seconds);
+            props.set(cx, "retryAfterSecs", retry_after)?;
+        }
+        if let Some(tries_remaining) = details.tries_remaining {
+            let tries_remaining = cx.number(tries_remaining);
+            props.set(cx, "triesRemaining", tries_remaining)?;
+        }
+        if let Some(http_status) = details.http_status {
+            let http_status = cx.number(http_status);
+            props.set(cx, "httpStatus", http_status)?;
+        }
+        Ok(props.upcast())
     }
 }
 
@@ -157,7 +171,7 @@ impl SignalNodeError for SignalProtocolError {
         operation_name: &str,
     ) -> Handle<'a, JsError> {
         let message = self.to_string();
-        match self {
+        match self.without_context() {
             SignalProtocolError::DuplicatedMessage(..) => new_js_error(
                 cx,
                 module,
@@ -415,6 +429,7 @@ impl SignalNodeError for libsignal_net::chat::ChatServiceError {
             ChatServiceError::ServiceInactive => Some("ChatServiceInactive"),
             ChatServiceError::AppExpired => Some("AppExpired"),
             ChatServiceError::DeviceDeregistered => Some("DeviceDelinked"),
+            ChatServiceError::RetryLater(_) => Some(RATE_LIMITED_ERROR),
             // TODO: Distinguish retryable errors from proper failures?
             _ => Some(IO_ERROR),
         };
@@ -425,7 +440,7 @@ impl SignalNodeError for libsignal_net::chat::ChatServiceError {
             name,
             &message,
             operation_name,
-            no_extra_properties,
+            error_details_properties(self.error_details()),
         )
     }
 }
@@ -457,28 +472,19 @@ impl SignalNodeError for libsignal_net::cdsi::LookupError {
         module: Handle<'a, JsObject>,
         operation_name: &str,
     ) -> Handle<'a, JsError> {
-        let (name, make_extra_props) = match self {
-            Self::RateLimited {
-                retry_after_seconds,
-            } => (
-                Some(RATE_LIMITED_ERROR),
-                Some(move |cx: &mut C| {
-                    let props = cx.empty_object();
-                    let retry_after = retry_after_seconds.convert_into(cx)?;
-                    props.set(cx, "retryAfterSecs", retry_after)?;
-                    Ok(props.upcast())
-                }),
-            ),
+        let name = match self {
+            Self::RateLimited { .. } => Some(RATE_LIMITED_ERROR),
             Self::AttestationError(e) => return e.into_throwable(cx, module, operation_name),
-            Self::InvalidArgument { server_reason: _ } => (None, None),
-            Self::InvalidToken => (Some("CdsiInvalidToken"), None),
+            Self::InvalidArgument { server_reason: _ } => None,
+            Self::InvalidToken => Some("CdsiInvalidToken"),
             Self::ConnectionTimedOut
             | Self::ConnectTransport(_)
             | Self::WebSocket(_)
             | Self::Protocol
             | Self::InvalidResponse
             | Self::ParseError
-            | Self::Server { reason: _ } => (Some(IO_ERROR), None),
+            | Self::Server { reason: _ }
+            | Self::UnsupportedByServer { .. } => Some(IO_ERROR),
         };
         let message = self.to_string();
         new_js_error(
@@ -487,7 +493,7 @@ impl SignalNodeError for libsignal_net::cdsi::LookupError {
             name,
             &message,
             operation_name,
-            optional_extra_properties(make_extra_props),
+            error_details_properties(self.error_details()),
         )
     }
 }
@@ -499,26 +505,18 @@ impl SignalNodeError for libsignal_net::svr3::Error {
         module: Handle<'a, JsObject>,
         operation_name: &str,
     ) -> Handle<'a, JsError> {
-        let (name, make_props) = match self {
+        let name = match self {
             Svr3Error::Service(_) | Svr3Error::ConnectionTimedOut | Svr3Error::Connect(_) => {
-                (Some(IO_ERROR), None)
+                Some(IO_ERROR)
             }
             Svr3Error::AttestationError(inner) => {
                 return inner.into_throwable(cx, module, operation_name);
             }
-            Svr3Error::RequestFailed(_) => (Some(SVR3_REQUEST_FAILED), None),
-            Svr3Error::RestoreFailed(tries_remaining) => (
-                Some(SVR3_RESTORE_FAILED),
-                Some(move |cx: &mut C| {
-                    let props = cx.empty_object();
-                    let tries_remaining = tries_remaining.convert_into(cx)?;
-                    props.set(cx, "triesRemaining", tries_remaining)?;
-                    Ok(props.upcast())
-                }),
-            ),
-            Svr3Error::DataMissing => (Some(SVR3_DATA_MISSING), None),
-            Svr3Error::Protocol(_) => (None, None),
-            Svr3Error::RotationMachineTooManySteps => (Some(SVR3_ROTATION_MACHINE_STEPS), None),
+            Svr3Error::RequestFailed(_) => Some(SVR3_REQUEST_FAILED),
+            Svr3Error::RestoreFailed(_) => Some(SVR3_RESTORE_FAILED),
+            Svr3Error::DataMissing => Some(SVR3_DATA_MISSING),
+            Svr3Error::Protocol(_) => None,
+            Svr3Error::RotationMachineTooManySteps => Some(SVR3_ROTATION_MACHINE_STEPS),
         };
 
         let message = self.to_string();
@@ -528,7 +526,7 @@ impl SignalNodeError for libsignal_net::svr3::Error {
             name,
             &message,
             operation_name,
-            optional_extra_properties(make_props),
+            error_details_properties(self.error_details()),
         )
     }
 }
@@ -563,7 +561,7 @@ impl SignalNodeError for libsignal_message_backup::ReadError {
             error,
             found_unknown_fields,
         } = self;
-        let message = error.to_string();
+        let message = error.scrubbed().to_string();
         let make_props = |cx: &mut C| {
             let props = cx.empty_object();
             let unknown_field_messages = found_unknown_fields.convert_into(cx)?;
@@ -11,9 +11,8 @@ use neon::types::{Deferred, JsBigInt};
 use signal_neon_futures::ChannelEx;
 
 use super::*;
-use crate::support::{
-    describe_panic, AsyncRuntime, AsyncRuntimeBase, CancellationId, ResultReporter,
-};
+use crate::support::panics::PanicReport;
+use crate::support::{AsyncRuntime, AsyncRuntimeBase, CancellationId, ResultReporter};
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 /// Promise cancelled
@@ -140,11 +139,21 @@ where
                 });
 
             settled_result.unwrap_or_else(|panic| {
-                cx.throw_error(format!(
+                // Panics in the future itself were already turned into reports by catch_unwind,
+                // on the thread where they happened.
+                let report = panic
+                    .downcast::<PanicReport>()
+                    .map(|report| *report)
+                    .unwrap_or_else(|panic| PanicReport::for_payload(&panic));
+                let error = cx.error(format!(
                     "unexpected panic completing {}: {}",
-                    node_function_name,
-                    describe_panic(&panic)
-                ))
+                    node_function_name, report
+                ))?;
+                if let Some(backtrace) = report.backtrace {
+                    let backtrace = cx.string(backtrace);
+                    error.set(&mut cx, "rustBacktrace", backtrace)?;
+                }
+                cx.throw(error)
             })
         });
     }
@@ -206,13 +215,20 @@ where
 
 /// Wraps [`FutureExt::catch_unwind`].
 ///
-/// Only here for consistency with the other bridges, which can treat panic errors uniformly with
-/// their other error types.
-pub fn catch_unwind<F>(future: F) -> futures_util::future::CatchUnwind<F>
+/// Unlike the other bridges, panics can't be converted to errors right away, because that needs a
+/// JavaScript context. Instead, the panic payload is replaced by a boxed [`PanicReport`], captured
+/// on the thread where the panic happened.
+pub fn catch_unwind<F>(future: F) -> impl Future<Output = std::thread::Result<F::Output>>
 where
     F: Future + std::panic::UnwindSafe,
 {
-    future.catch_unwind()
+    crate::support::panics::install_panic_hook();
+    future.catch_unwind().map(|result| {
+        result.map_err(|panic| {
+            let report: Box<dyn std::any::Any + Send> = Box::new(PanicReport::for_payload(&panic));
+            report
+        })
+    })
 }
 
 /// Used to "send" a task from a thread to itself through a multi-threaded interface.
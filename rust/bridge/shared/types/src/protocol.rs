@@ -25,14 +25,26 @@ bridge_as_handle!(PlaintextContent);
 bridge_as_handle!(PreKeyBundle);
 bridge_as_handle!(PreKeyRecord);
 bridge_as_handle!(PreKeySignalMessage);
-bridge_as_handle!(PrivateKey, ffi = privatekey, jni = ECPrivateKey);
+bridge_as_handle!(
+    PrivateKey,
+    ffi = privatekey,
+    jni = ECPrivateKey,
+    python = true,
+    wasm = true
+);
 bridge_as_handle!(ProtocolAddress, ffi = address);
-bridge_as_handle!(PublicKey, ffi = publickey, jni = ECPublicKey);
-bridge_as_handle!(SenderCertificate);
+bridge_as_handle!(
+    PublicKey,
+    ffi = publickey,
+    jni = ECPublicKey,
+    python = true,
+    wasm = true
+);
+bridge_as_handle!(SenderCertificate, python = true);
 bridge_as_handle!(SenderKeyDistributionMessage);
 bridge_as_handle!(SenderKeyMessage);
 bridge_as_handle!(SenderKeyRecord);
-bridge_as_handle!(ServerCertificate);
+bridge_as_handle!(ServerCertificate, python = true);
 bridge_as_handle!(SessionRecord, mut = true);
 bridge_as_handle!(SignalMessage, ffi = message);
 bridge_as_handle!(SignedPreKeyRecord);
@@ -42,6 +54,7 @@ bridge_as_handle!(SealedSenderDecryptionResult, ffi = false, jni = false);
 bridge_as_handle!(KyberKeyPair);
 bridge_as_handle!(KyberPublicKey);
 bridge_as_handle!(KyberSecretKey);
+bridge_as_handle!(Envelope);
 
 pub use libsignal_protocol::Timestamp;
 
@@ -0,0 +1,354 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use libsignal_protocol::{Aci, Pni, ServiceId};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::pybacked::PyBackedBytes;
+use pyo3::types::PyNone;
+
+use super::*;
+use crate::support::{Array, AsType, FixedLengthBincodeSerializable, Serialized};
+
+/// Converts arguments from their Python form to a Rust type.
+///
+/// `ArgTypeInfo` has two required methods: `borrow` and `load_from`. The use site looks like this:
+///
+/// ```no_run
+/// # use libsignal_bridge_types::python::*;
+/// # struct Foo;
+/// # impl SimpleArgTypeInfo for Foo {
+/// #     fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+/// #         Ok(Foo)
+/// #     }
+/// # }
+/// # fn test(py_arg: &Bound<'_, PyAny>) -> PyResult<()> {
+/// let mut py_arg_borrowed = Foo::borrow(py_arg)?;
+/// let rust_arg = Foo::load_from(&mut py_arg_borrowed);
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// The `'storage` lifetime allows for borrowed types to depend on the lifetime of the stored
+/// value, so that a `&[u8]` can refer directly to the contents of a Python `bytes` object.
+///
+/// Simple Rust types that are converted by value should implement [`SimpleArgTypeInfo`] instead.
+pub trait ArgTypeInfo<'storage>: Sized {
+    /// Local storage for the argument (ideally borrowed rather than copied).
+    type StoredType: 'storage;
+    /// "Borrows" the data in `foreign`, usually to establish a local lifetime or owning type.
+    fn borrow(foreign: &Bound<'_, PyAny>) -> PyResult<Self::StoredType>;
+    /// Loads the Rust value from the data that's been `stored` by [`borrow()`](Self::borrow()).
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self;
+}
+
+/// A simpler interface for [`ArgTypeInfo`] for Rust types that are converted by value.
+pub trait SimpleArgTypeInfo: Sized + 'static {
+    /// Converts the data in `foreign` to the Rust type.
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self>;
+}
+
+impl<'a, T> ArgTypeInfo<'a> for T
+where
+    T: SimpleArgTypeInfo,
+{
+    type StoredType = Option<Self>;
+    fn borrow(foreign: &Bound<'_, PyAny>) -> PyResult<Self::StoredType> {
+        Ok(Some(Self::convert_from(foreign)?))
+    }
+    fn load_from(stored: &'a mut Self::StoredType) -> Self {
+        stored.take().expect("only called once")
+    }
+}
+
+/// Converts result values from their Rust form to Python objects.
+pub trait ResultTypeInfo: Sized {
+    /// Converts the data in `self` to a Python object.
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject>;
+}
+
+macro_rules! extract_as_simple_arg_type {
+    ($typ:ty) => {
+        impl SimpleArgTypeInfo for $typ {
+            fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+                foreign.extract()
+            }
+        }
+    };
+}
+
+extract_as_simple_arg_type!(bool);
+extract_as_simple_arg_type!(u8);
+extract_as_simple_arg_type!(u32);
+extract_as_simple_arg_type!(u64);
+extract_as_simple_arg_type!(String);
+
+impl SimpleArgTypeInfo for crate::protocol::Timestamp {
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self::from_epoch_millis(foreign.extract()?))
+    }
+}
+
+impl SimpleArgTypeInfo for crate::zkgroup::Timestamp {
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self::from_epoch_seconds(foreign.extract()?))
+    }
+}
+
+impl SimpleArgTypeInfo for ServiceId {
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let bytes = foreign.extract::<PyBackedBytes>()?;
+        bytes
+            .as_ref()
+            .try_into()
+            .ok()
+            .and_then(Self::parse_from_service_id_fixed_width_binary)
+            .ok_or_else(|| PyTypeError::new_err("invalid Service-Id-FixedWidthBinary"))
+    }
+}
+
+impl SimpleArgTypeInfo for Aci {
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        ServiceId::convert_from(foreign)?
+            .try_into()
+            .map_err(|_| PyTypeError::new_err("not an ACI"))
+    }
+}
+
+impl SimpleArgTypeInfo for Pni {
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        ServiceId::convert_from(foreign)?
+            .try_into()
+            .map_err(|_| PyTypeError::new_err("not a PNI"))
+    }
+}
+
+impl<T, P> SimpleArgTypeInfo for AsType<T, P>
+where
+    T: 'static,
+    P: SimpleArgTypeInfo + TryInto<T>,
+    P::Error: Display,
+{
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        match P::convert_from(foreign)?.try_into() {
+            Ok(t) => Ok(AsType::from(t)),
+            Err(e) => Err(PyTypeError::new_err(format!(
+                "invalid {}: {e}",
+                std::any::type_name::<T>()
+            ))),
+        }
+    }
+}
+
+/// Unlike the app bridges, which validate these up front, Python callers can pass arbitrary bytes,
+/// so a bad value is reported as a `ValueError` rather than treated as a bug.
+impl<T> SimpleArgTypeInfo for Serialized<T>
+where
+    T: FixedLengthBincodeSerializable
+        + for<'a> serde::Deserialize<'a>
+        + partial_default::PartialDefault,
+{
+    fn convert_from(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let bytes = foreign.extract::<PyBackedBytes>()?;
+        let invalid = || PyValueError::new_err(format!("invalid {}", std::any::type_name::<T>()));
+        if bytes.len() != T::Array::LEN {
+            return Err(invalid());
+        }
+        let result: T = zkgroup::deserialize(&bytes).map_err(|_| invalid())?;
+        Ok(Serialized::from(result))
+    }
+}
+
+impl<'storage, T> ArgTypeInfo<'storage> for Option<T>
+where
+    T: ArgTypeInfo<'storage>,
+{
+    type StoredType = Option<T::StoredType>;
+    fn borrow(foreign: &Bound<'_, PyAny>) -> PyResult<Self::StoredType> {
+        if foreign.is_none() {
+            Ok(None)
+        } else {
+            T::borrow(foreign).map(Some)
+        }
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored.as_mut().map(T::load_from)
+    }
+}
+
+impl<'storage> ArgTypeInfo<'storage> for &'storage [u8] {
+    type StoredType = PyBackedBytes;
+    fn borrow(foreign: &Bound<'_, PyAny>) -> PyResult<Self::StoredType> {
+        foreign.extract()
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        &stored[..]
+    }
+}
+
+impl<'storage, const LEN: usize> ArgTypeInfo<'storage> for &'storage [u8; LEN] {
+    type StoredType = PyBackedBytes;
+    fn borrow(foreign: &Bound<'_, PyAny>) -> PyResult<Self::StoredType> {
+        let bytes: PyBackedBytes = foreign.extract()?;
+        if bytes.len() != LEN {
+            return Err(PyValueError::new_err(format!(
+                "expected {LEN} bytes, got {}",
+                bytes.len()
+            )));
+        }
+        Ok(bytes)
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored[..].try_into().expect("checked in borrow")
+    }
+}
+
+/// Keeps a [`NativeHandle`] alive while the Rust value inside it is in use.
+pub struct BorrowedHandle<T> {
+    handle: Py<NativeHandle>,
+    value_type: PhantomData<fn() -> T>,
+}
+
+impl<T: BridgeHandle> BorrowedHandle<T> {
+    pub fn new(foreign: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let handle = foreign.downcast::<NativeHandle>()?;
+        if handle.get().downcast_ref::<T>().is_none() {
+            return Err(PyTypeError::new_err(format!(
+                "expected a handle to {}, got {}",
+                std::any::type_name::<T>(),
+                handle.get().type_name,
+            )));
+        }
+        Ok(Self {
+            handle: handle.clone().unbind(),
+            value_type: PhantomData,
+        })
+    }
+
+    pub fn get(&self) -> &T {
+        self.handle
+            .get()
+            .downcast_ref()
+            .expect("checked on construction")
+    }
+}
+
+impl NativeHandle {
+    /// Moves `value` into a new Python object.
+    pub fn wrap<T: BridgeHandle>(py: Python<'_>, value: T) -> PyResult<PyObject> {
+        Ok(Py::new(py, Self::new(value))?.into_any())
+    }
+}
+
+/// Implementation of [`bridge_as_handle`](crate::support::bridge_as_handle) for Python.
+#[macro_export]
+macro_rules! python_bridge_as_handle {
+    ( $typ:ty as false ) => {};
+    ( $typ:ty as true ) => {
+        impl python::BridgeHandle for $typ {}
+
+        impl<'storage> python::ArgTypeInfo<'storage> for &'storage $typ {
+            type StoredType = python::BorrowedHandle<$typ>;
+            fn borrow(
+                foreign: &python::Bound<'_, python::PyAny>,
+            ) -> python::PyResult<Self::StoredType> {
+                python::BorrowedHandle::new(foreign)
+            }
+            fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+                stored.get()
+            }
+        }
+
+        impl python::ResultTypeInfo for $typ {
+            fn convert_into(self, py: python::Python<'_>) -> python::PyResult<python::PyObject> {
+                python::NativeHandle::wrap(py, self)
+            }
+        }
+    };
+}
+
+impl ResultTypeInfo for () {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(PyNone::get_bound(py).to_owned().into_any().unbind())
+    }
+}
+
+macro_rules! to_object_as_result_type {
+    ($typ:ty) => {
+        impl ResultTypeInfo for $typ {
+            fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+                Ok(self.into_py(py))
+            }
+        }
+    };
+}
+
+to_object_as_result_type!(bool);
+to_object_as_result_type!(u32);
+to_object_as_result_type!(u64);
+to_object_as_result_type!(String);
+to_object_as_result_type!(&str);
+
+impl ResultTypeInfo for crate::protocol::Timestamp {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        self.epoch_millis().convert_into(py)
+    }
+}
+
+impl ResultTypeInfo for crate::zkgroup::Timestamp {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        self.epoch_seconds().convert_into(py)
+    }
+}
+
+impl ResultTypeInfo for &[u8] {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(PyBytes::new_bound(py, self).into_any().unbind())
+    }
+}
+
+impl ResultTypeInfo for Vec<u8> {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        self.as_slice().convert_into(py)
+    }
+}
+
+impl<const LEN: usize> ResultTypeInfo for [u8; LEN] {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        self.as_slice().convert_into(py)
+    }
+}
+
+impl ResultTypeInfo for Box<[String]> {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(PyList::new_bound(py, self.iter()).into_any().unbind())
+    }
+}
+
+impl<T> ResultTypeInfo for Serialized<T>
+where
+    T: FixedLengthBincodeSerializable + serde::Serialize,
+{
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        zkgroup::serialize(&*self).convert_into(py)
+    }
+}
+
+impl<T: ResultTypeInfo> ResultTypeInfo for Option<T> {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        match self {
+            Some(value) => value.convert_into(py),
+            None => ().convert_into(py),
+        }
+    }
+}
+
+impl<T: ResultTypeInfo, E: PythonError> ResultTypeInfo for Result<T, E> {
+    fn convert_into(self, py: Python<'_>) -> PyResult<PyObject> {
+        self.map_err(PythonError::into_py_err)?.convert_into(py)
+    }
+}
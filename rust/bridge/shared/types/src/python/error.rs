@@ -0,0 +1,60 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::fmt::Display;
+
+use libsignal_protocol::SignalProtocolError;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use zkgroup::{ZkGroupDeserializationFailure, ZkGroupVerificationFailure};
+
+use super::*;
+
+create_exception!(
+    libsignal_server,
+    LibSignalError,
+    PyException,
+    "Base class for errors raised by libsignal."
+);
+create_exception!(
+    libsignal_server,
+    VerificationFailedError,
+    LibSignalError,
+    "A credential, presentation, or signature failed to verify."
+);
+
+pub(super) fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add("LibSignalError", py.get_type_bound::<LibSignalError>())?;
+    module.add(
+        "VerificationFailedError",
+        py.get_type_bound::<VerificationFailedError>(),
+    )?;
+    Ok(())
+}
+
+/// Errors that can be raised as Python exceptions.
+pub trait PythonError: Display + Sized {
+    /// Converts the error to an exception, [`LibSignalError`] unless overridden.
+    fn into_py_err(self) -> PyErr {
+        LibSignalError::new_err(self.to_string())
+    }
+}
+
+impl PythonError for SignalProtocolError {}
+
+impl PythonError for ZkGroupDeserializationFailure {}
+
+impl PythonError for ZkGroupVerificationFailure {
+    fn into_py_err(self) -> PyErr {
+        VerificationFailedError::new_err(self.to_string())
+    }
+}
+
+impl PythonError for std::io::Error {
+    fn into_py_err(self) -> PyErr {
+        self.into()
+    }
+}
@@ -0,0 +1,60 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::cell::Cell;
+use std::io::{ErrorKind as IoErrorKind, Result as IoResult};
+
+use async_trait::async_trait;
+use pyo3::pybacked::PyBackedBytes;
+
+use super::*;
+use crate::io::{InputStream, InputStreamRead};
+
+/// An [`InputStream`] over the contents of a Python `bytes` object.
+///
+/// Python callers pass the whole input up front, so every read completes immediately.
+pub struct PythonBytesInputStream {
+    buffer: PyBackedBytes,
+    pos: Cell<usize>,
+}
+
+impl PythonBytesInputStream {
+    fn new(buffer: PyBackedBytes) -> Self {
+        Self {
+            buffer,
+            pos: Default::default(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl InputStream for PythonBytesInputStream {
+    fn read<'out, 'a: 'out>(&'a self, buf: &mut [u8]) -> IoResult<InputStreamRead<'out>> {
+        let buffer_remaining = &self.buffer[self.pos.get()..];
+        let amount_read = buffer_remaining.len().min(buf.len());
+        buf[..amount_read].copy_from_slice(&buffer_remaining[..amount_read]);
+        self.pos.set(self.pos.get() + amount_read);
+        Ok(InputStreamRead::Ready { amount_read })
+    }
+
+    async fn skip(&self, amount: u64) -> IoResult<()> {
+        let buffer_remaining = self.buffer[self.pos.get()..].len();
+        if (buffer_remaining as u64) < amount {
+            return Err(IoErrorKind::UnexpectedEof.into());
+        }
+        self.pos.set(self.pos.get() + amount as usize);
+        Ok(())
+    }
+}
+
+impl<'storage> ArgTypeInfo<'storage> for &'storage mut dyn InputStream {
+    type StoredType = PythonBytesInputStream;
+    fn borrow(foreign: &Bound<'_, PyAny>) -> PyResult<Self::StoredType> {
+        Ok(PythonBytesInputStream::new(foreign.extract()?))
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored
+    }
+}
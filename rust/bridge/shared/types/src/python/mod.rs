@@ -0,0 +1,93 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::any::Any;
+
+pub use pyo3::prelude::*;
+pub use pyo3::types::{PyBytes, PyList, PyString, PyTuple};
+
+#[macro_use]
+mod convert;
+pub use convert::*;
+
+mod error;
+pub use error::*;
+
+mod io;
+pub use io::*;
+
+/// A function pointer referring to a `bridge_fn`-generated Python entry point.
+#[doc(hidden)]
+pub type PyFn = for<'py> fn(Python<'py>, &Bound<'py, PyTuple>) -> PyResult<PyObject>;
+
+#[doc(hidden)]
+#[linkme::distributed_slice]
+pub static LIBSIGNAL_FNS: [(&'static str, PyFn)] = [..];
+
+/// Adds all `bridge_fn`-generated entry points to `module`, along with the types they rely on.
+pub fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add_class::<NativeHandle>()?;
+    error::register(module)?;
+    for &(name, f) in LIBSIGNAL_FNS {
+        let function = pyo3::types::PyCFunction::new_closure_bound(
+            py,
+            Some(name),
+            None,
+            move |args, _kwargs| f(args.py(), args),
+        )?;
+        module.add(name, function)?;
+    }
+    Ok(())
+}
+
+/// Checks that a generated entry point was called with the number of arguments it expects.
+#[doc(hidden)]
+pub fn check_arg_count(name: &str, args: &Bound<'_, PyTuple>, expected: usize) -> PyResult<()> {
+    let actual = args.len();
+    if actual != expected {
+        return Err(pyo3::exceptions::PyTypeError::new_err(format!(
+            "{name}() takes {expected} positional arguments but {actual} were given"
+        )));
+    }
+    Ok(())
+}
+
+/// An opaque Python object owning a Rust value declared with
+/// [`bridge_as_handle`](crate::support::bridge_as_handle).
+///
+/// Python code never looks inside these; the wrapper classes in the `libsignal_server` package hold
+/// on to them and pass them back to the entry points that take the corresponding Rust type.
+#[pyclass(module = "libsignal_server._native", frozen)]
+pub struct NativeHandle {
+    value: Box<dyn Any + Send + Sync>,
+    type_name: &'static str,
+}
+
+impl NativeHandle {
+    fn new<T: BridgeHandle>(value: T) -> Self {
+        Self {
+            value: Box::new(value),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    fn downcast_ref<T: BridgeHandle>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+}
+
+#[pymethods]
+impl NativeHandle {
+    fn __repr__(&self) -> String {
+        format!("<NativeHandle {}>", self.type_name)
+    }
+}
+
+/// A marker trait for Rust objects exposed to Python through a [`NativeHandle`].
+///
+/// Implemented by [`bridge_as_handle`](crate::support::bridge_as_handle) for types declared with
+/// `python = true`.
+pub trait BridgeHandle: Any + Send + Sync {}
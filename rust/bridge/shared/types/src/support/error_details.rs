@@ -0,0 +1,206 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use libsignal_net::chat::ChatServiceError;
+use libsignal_net::infra::errors::RetryLater;
+use libsignal_net::infra::ws::{WebSocketConnectError, WebSocketServiceError};
+use libsignal_protocol::{ProtocolAddress, SignalProtocolError};
+
+/// Machine-readable details about an error, beyond its kind and message.
+///
+/// Each bridge passes along whichever fields are present in its own idiom, so that apps don't have
+/// to parse error messages to act on them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorDetails {
+    /// How long to wait before trying again, for rate-limiting errors.
+    pub retry_after_seconds: Option<u32>,
+    /// How many attempts are left, for operations that limit them.
+    pub tries_remaining: Option<u32>,
+    /// The address of the device the error is about.
+    pub address: Option<ProtocolAddress>,
+    /// The status code of an HTTP response that rejected a request.
+    pub http_status: Option<u16>,
+}
+
+/// Errors that can describe themselves with [`ErrorDetails`].
+pub trait ProvideErrorDetails {
+    fn error_details(&self) -> ErrorDetails;
+}
+
+impl ProvideErrorDetails for SignalProtocolError {
+    fn error_details(&self) -> ErrorDetails {
+        match self.underlying_error() {
+            Self::InvalidRegistrationId(address, _)
+            | Self::SessionNotFound(address)
+            | Self::UntrustedIdentity(address) => ErrorDetails {
+                address: Some(address.clone()),
+                ..Default::default()
+            },
+            _ => ErrorDetails::default(),
+        }
+    }
+}
+
+impl ProvideErrorDetails for WebSocketServiceError {
+    fn error_details(&self) -> ErrorDetails {
+        match self {
+            Self::Http(response) => ErrorDetails {
+                http_status: Some(response.status().as_u16()),
+                ..Default::default()
+            },
+            _ => ErrorDetails::default(),
+        }
+    }
+}
+
+impl ProvideErrorDetails for WebSocketConnectError {
+    fn error_details(&self) -> ErrorDetails {
+        match self {
+            Self::RejectedByServer(response) => ErrorDetails {
+                http_status: Some(response.status().as_u16()),
+                ..Default::default()
+            },
+            _ => ErrorDetails::default(),
+        }
+    }
+}
+
+impl ProvideErrorDetails for ChatServiceError {
+    fn error_details(&self) -> ErrorDetails {
+        // These are the statuses the server uses to reject the connection; see the conversion
+        // from WebSocketConnectError.
+        let http_status = match self {
+            Self::WebSocket(e) => return e.error_details(),
+            Self::AppExpired => 499,
+            Self::DeviceDeregistered => 403,
+            // This can come from either a 429 or a 503, so there's no single status to report.
+            Self::RetryLater(retry_later) => {
+                return ErrorDetails {
+                    retry_after_seconds: Some(retry_after_seconds(retry_later)),
+                    ..Default::default()
+                }
+            }
+            _ => return ErrorDetails::default(),
+        };
+        ErrorDetails {
+            http_status: Some(http_status),
+            ..Default::default()
+        }
+    }
+}
+
+fn retry_after_seconds(retry_later: &RetryLater) -> u32 {
+    retry_later
+        .retry_after
+        .as_secs()
+        .try_into()
+        .unwrap_or(u32::MAX)
+}
+
+impl ProvideErrorDetails for libsignal_net::cdsi::LookupError {
+    fn error_details(&self) -> ErrorDetails {
+        match self {
+            Self::RateLimited {
+                retry_after_seconds,
+            } => ErrorDetails {
+                retry_after_seconds: Some(*retry_after_seconds),
+                ..Default::default()
+            },
+            Self::WebSocket(e) => e.error_details(),
+            _ => ErrorDetails::default(),
+        }
+    }
+}
+
+impl ProvideErrorDetails for libsignal_net::svr3::Error {
+    fn error_details(&self) -> ErrorDetails {
+        match self {
+            Self::RestoreFailed(tries_remaining) => ErrorDetails {
+                tries_remaining: Some(*tries_remaining),
+                ..Default::default()
+            },
+            Self::Connect(e) => e.error_details(),
+            Self::Service(e) => e.error_details(),
+            _ => ErrorDetails::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use libsignal_protocol::DeviceId;
+
+    use super::*;
+
+    fn http_response(status: u16) -> http::Response<Option<Vec<u8>>> {
+        http::Response::builder()
+            .status(status)
+            .body(None)
+            .expect("valid response")
+    }
+
+    #[test]
+    fn http_status_from_rejected_connection() {
+        let error =
+            ChatServiceError::from(WebSocketConnectError::RejectedByServer(http_response(429)));
+        assert_eq!(error.error_details().http_status, Some(429));
+
+        let rate_limited = http::Response::builder()
+            .status(429)
+            .header("retry-after", "30")
+            .body(None)
+            .expect("valid response");
+        let error = ChatServiceError::from(WebSocketConnectError::RejectedByServer(rate_limited));
+        assert!(matches!(error, ChatServiceError::RetryLater(_)));
+        assert_eq!(error.error_details().retry_after_seconds, Some(30));
+        assert_eq!(error.error_details().http_status, None);
+
+        let unavailable = http::Response::builder()
+            .status(503)
+            .header("retry-after", "30")
+            .body(None)
+            .expect("valid response");
+        let error = ChatServiceError::from(WebSocketConnectError::RejectedByServer(unavailable));
+        assert!(matches!(error, ChatServiceError::RetryLater(_)));
+        assert_eq!(error.error_details().retry_after_seconds, Some(30));
+
+        let conflict = http::Response::builder()
+            .status(409)
+            .header("retry-after", "30")
+            .body(None)
+            .expect("valid response");
+        let error = ChatServiceError::from(WebSocketConnectError::RejectedByServer(conflict));
+        assert!(!matches!(error, ChatServiceError::RetryLater(_)));
+        assert_eq!(error.error_details().retry_after_seconds, None);
+
+        let error =
+            ChatServiceError::from(WebSocketConnectError::RejectedByServer(http_response(499)));
+        assert!(matches!(error, ChatServiceError::AppExpired));
+        assert_eq!(error.error_details().http_status, Some(499));
+
+        let error =
+            libsignal_net::svr3::Error::Service(WebSocketServiceError::Http(http_response(500)));
+        assert_eq!(error.error_details().http_status, Some(500));
+    }
+
+    #[test]
+    fn per_domain_fields() {
+        let error = libsignal_net::cdsi::LookupError::RateLimited {
+            retry_after_seconds: 30,
+        };
+        assert_eq!(error.error_details().retry_after_seconds, Some(30));
+
+        let error = libsignal_net::svr3::Error::RestoreFailed(2);
+        assert_eq!(error.error_details().tries_remaining, Some(2));
+
+        let address = ProtocolAddress::new("alice".to_owned(), DeviceId::new(2).expect("valid"));
+        let error = SignalProtocolError::SessionNotFound(address.clone());
+        assert_eq!(error.error_details().address, Some(address));
+
+        let details = SignalProtocolError::InvalidPreKeyId.error_details();
+        assert_eq!(details.address, None);
+        assert_eq!(details.http_status, None);
+    }
+}
@@ -0,0 +1,255 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Opt-in accounting of Rust objects handed to the app as bridge handles.
+//!
+//! When enabled with [`set_tracking_enabled`], the FFI and JNI bridges record every handle they
+//! give to the app and forget it when the app destroys it, so that [`snapshot`] can report how many
+//! handles of each type are alive. (Node handles are owned by the JavaScript garbage collector and
+//! are not tracked.)
+//!
+//! Sizes are approximate: they only count the object itself (`size_of`), not any heap allocations
+//! it owns.
+//!
+//! With a threshold set by [`set_leak_threshold`], handles that stay alive longer than the
+//! threshold are logged (once each) as possible leaks.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+
+static TRACKING: AtomicBool = AtomicBool::new(false);
+static HANDLES: Lazy<Mutex<HandleTable>> = Lazy::new(Default::default);
+
+/// How often creating a handle checks for long-lived handles, when leak detection is on.
+const LEAK_SCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct HandleTable {
+    live: HashMap<usize, LiveHandle>,
+    leak_threshold: Option<Duration>,
+    last_leak_scan: Option<Instant>,
+}
+
+struct LiveHandle {
+    type_name: &'static str,
+    size: usize,
+    created_at: Instant,
+    reported_as_leak: bool,
+}
+
+/// Turns tracking on or off.
+///
+/// Only handles created while tracking is on are counted. Turning tracking off forgets all tracked
+/// handles.
+pub fn set_tracking_enabled(enabled: bool) {
+    TRACKING.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        let mut handles = HANDLES.lock().expect("not poisoned");
+        handles.live.clear();
+        handles.last_leak_scan = None;
+    }
+}
+
+/// Logs tracked handles that stay alive longer than `threshold`, or stops doing so if `None`.
+pub fn set_leak_threshold(threshold: Option<Duration>) {
+    HANDLES.lock().expect("not poisoned").leak_threshold = threshold;
+}
+
+/// Records that a handle has been given to the app.
+///
+/// Zero-sized values are never tracked, since they don't have unique addresses.
+pub fn track<T>(handle: *const T) {
+    if !TRACKING.load(Ordering::Relaxed) || std::mem::size_of::<T>() == 0 {
+        return;
+    }
+    let now = Instant::now();
+    let mut handles = HANDLES.lock().expect("not poisoned");
+    handles.live.insert(
+        handle as usize,
+        LiveHandle {
+            type_name: short_type_name(std::any::type_name::<T>()),
+            size: std::mem::size_of::<T>(),
+            created_at: now,
+            reported_as_leak: false,
+        },
+    );
+    let scan_due = handles.last_leak_scan.map_or(true, |last_scan| {
+        now.saturating_duration_since(last_scan) >= LEAK_SCAN_INTERVAL
+    });
+    if scan_due {
+        let leaks = handles.find_new_leaks(now);
+        drop(handles);
+        log_leaks(leaks);
+    }
+}
+
+/// Records that the app has given up a handle, whether or not it was tracked.
+pub fn untrack<T>(handle: *const T) {
+    if !TRACKING.load(Ordering::Relaxed) {
+        return;
+    }
+    HANDLES
+        .lock()
+        .expect("not poisoned")
+        .live
+        .remove(&(handle as usize));
+}
+
+/// Aggregate information about the live handles of a single type.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HandleStats {
+    pub count: u64,
+    pub approximate_bytes: u64,
+    pub oldest_age: Duration,
+}
+
+/// Returns the live tracked handles, grouped by type name.
+///
+/// Also logs any handles that have newly passed the leak threshold.
+pub fn snapshot() -> BTreeMap<&'static str, HandleStats> {
+    let now = Instant::now();
+    let mut handles = HANDLES.lock().expect("not poisoned");
+    let leaks = handles.find_new_leaks(now);
+
+    let mut result = BTreeMap::<_, HandleStats>::new();
+    for handle in handles.live.values() {
+        let stats = result.entry(handle.type_name).or_default();
+        stats.count += 1;
+        stats.approximate_bytes += handle.size as u64;
+        stats.oldest_age = stats
+            .oldest_age
+            .max(now.saturating_duration_since(handle.created_at));
+    }
+    drop(handles);
+
+    log_leaks(leaks);
+    result
+}
+
+impl HandleTable {
+    /// Returns the type name and age of each handle that has passed the leak threshold since the
+    /// last scan, and marks them as reported.
+    ///
+    /// The caller should release [`HANDLES`] before passing the result to [`log_leaks`], since
+    /// logging can call back into the app.
+    fn find_new_leaks(&mut self, now: Instant) -> Vec<(&'static str, Duration)> {
+        self.last_leak_scan = Some(now);
+        let Some(threshold) = self.leak_threshold else {
+            return Vec::new();
+        };
+        let mut leaks = Vec::new();
+        for handle in self.live.values_mut() {
+            let age = now.saturating_duration_since(handle.created_at);
+            if age >= threshold && !handle.reported_as_leak {
+                handle.reported_as_leak = true;
+                leaks.push((handle.type_name, age));
+            }
+        }
+        leaks
+    }
+}
+
+fn log_leaks(leaks: Vec<(&'static str, Duration)>) {
+    for (type_name, age) in leaks {
+        log::warn!(
+            "{} handle has been alive for {}s; possible leak",
+            type_name,
+            age.as_secs()
+        );
+    }
+}
+
+/// Strips the module path from a type name, e.g. `libsignal_protocol::SessionRecord` becomes
+/// `SessionRecord`.
+///
+/// Generic arguments are left as is.
+fn short_type_name(full_name: &'static str) -> &'static str {
+    let base_name_end = full_name.find('<').unwrap_or(full_name.len());
+    let start = full_name[..base_name_end]
+        .rfind("::")
+        .map_or(0, |index| index + 2);
+    &full_name[start..]
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("SessionRecord" => "SessionRecord")]
+    #[test_case("libsignal_protocol::state::SessionRecord" => "SessionRecord")]
+    #[test_case("alloc::vec::Vec<core::primitive::u8>" => "Vec<core::primitive::u8>")]
+    fn short_type_name(full_name: &'static str) -> &'static str {
+        super::short_type_name(full_name)
+    }
+
+    /// Takes a snapshot whenever a leak is logged, which would deadlock if leaks were logged while
+    /// [`HANDLES`] is locked.
+    struct SnapshottingLogger {
+        leaks_logged: AtomicUsize,
+    }
+
+    impl log::Log for SnapshottingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            if record.args().to_string().ends_with("possible leak") {
+                let _ = snapshot();
+                self.leaks_logged.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: SnapshottingLogger = SnapshottingLogger {
+        leaks_logged: AtomicUsize::new(0),
+    };
+
+    #[test]
+    fn track_and_untrack() {
+        // No other tests in this crate turn on tracking, so it's safe to toggle it here.
+        struct Tracked([u8; 5]);
+
+        let first = Box::into_raw(Box::new(Tracked([0; 5])));
+        let second = Box::into_raw(Box::new(Tracked([1; 5])));
+        track(first);
+
+        set_tracking_enabled(true);
+        track(first);
+        track(second);
+        let stats = snapshot().remove("Tracked").expect("tracked");
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.approximate_bytes, 10);
+
+        untrack(first);
+        assert_eq!(snapshot().remove("Tracked").expect("tracked").count, 1);
+
+        log::set_logger(&LOGGER).expect("no other tests in this crate set a logger");
+        log::set_max_level(log::LevelFilter::Warn);
+        set_leak_threshold(Some(Duration::ZERO));
+        assert_eq!(snapshot().remove("Tracked").expect("tracked").count, 1);
+        assert_eq!(LOGGER.leaks_logged.load(Ordering::Relaxed), 1);
+        set_leak_threshold(None);
+
+        set_tracking_enabled(false);
+        assert_eq!(snapshot().remove("Tracked"), None);
+
+        // SAFETY: these were created with Box::into_raw above.
+        unsafe {
+            drop(Box::from_raw(first));
+            drop(Box::from_raw(second));
+        }
+    }
+}
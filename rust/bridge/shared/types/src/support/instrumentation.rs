@@ -0,0 +1,200 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Opt-in timing of bridged calls.
+//!
+//! When enabled with [`set_enabled`], every `bridge_fn` and `bridge_io` entry point records how
+//! long it ran, and asynchronous entry points additionally record how long they waited between
+//! being called and starting to run ("queue latency"). Timings are aggregated per operation into
+//! [`Histogram`]s, which apps can fetch with [`snapshot`] to find slow or blocked operations.
+//!
+//! When disabled (the default), the cost of instrumentation is a single relaxed atomic load per
+//! call.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static OPERATIONS: Mutex<BTreeMap<&'static str, OperationTimings>> = Mutex::new(BTreeMap::new());
+
+/// Turns recording on or off.
+///
+/// Turning recording off does not discard timings collected so far; use [`reset`] for that.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Discards all recorded timings.
+pub fn reset() {
+    OPERATIONS.lock().expect("not poisoned").clear();
+}
+
+/// Returns the timings recorded so far, keyed by the name of the bridged function.
+pub fn snapshot() -> BTreeMap<&'static str, OperationTimings> {
+    OPERATIONS.lock().expect("not poisoned").clone()
+}
+
+fn record(name: &'static str, update: impl FnOnce(&mut OperationTimings)) {
+    let mut operations = OPERATIONS.lock().expect("not poisoned");
+    update(operations.entry(name).or_default());
+}
+
+/// The timings recorded for a single bridged function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperationTimings {
+    /// How long each call took to run, once started.
+    pub duration: Histogram,
+    /// For asynchronous calls, how long each call waited before it started to run.
+    ///
+    /// Empty for synchronous calls.
+    pub queue_latency: Histogram,
+}
+
+/// A histogram of durations, with exponentially-sized buckets.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Histogram {
+    pub count: u64,
+    pub total_micros: u64,
+    pub max_micros: u64,
+    /// `buckets[0]` counts durations under 1µs; `buckets[i]` counts durations in
+    /// [2<sup>i-1</sup>µs, 2<sup>i</sup>µs). The last bucket also counts anything longer.
+    pub buckets: [u64; Histogram::BUCKET_COUNT],
+}
+
+impl Histogram {
+    /// Enough buckets that only durations over about 4 seconds end up in the last one.
+    pub const BUCKET_COUNT: usize = 24;
+
+    pub fn record(&mut self, duration: Duration) {
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        self.count += 1;
+        self.total_micros = self.total_micros.saturating_add(micros);
+        self.max_micros = self.max_micros.max(micros);
+        self.buckets[Self::bucket_index(micros)] += 1;
+    }
+
+    fn bucket_index(micros: u64) -> usize {
+        let index = (u64::BITS - micros.leading_zeros()) as usize;
+        index.min(Self::BUCKET_COUNT - 1)
+    }
+}
+
+/// Records the duration of a call when dropped.
+///
+/// Created by [`CallTimer::start`] or [`QueuedCall::start`].
+#[must_use]
+pub struct CallTimer {
+    name: &'static str,
+    started_at: Instant,
+}
+
+impl CallTimer {
+    /// Starts timing a synchronous call, if instrumentation is enabled.
+    #[inline]
+    pub fn start(name: &'static str) -> Option<Self> {
+        is_enabled().then(|| Self {
+            name,
+            started_at: Instant::now(),
+        })
+    }
+}
+
+impl Drop for CallTimer {
+    fn drop(&mut self) {
+        let elapsed = self.started_at.elapsed();
+        record(self.name, |timings| timings.duration.record(elapsed));
+    }
+}
+
+/// Tracks an asynchronous call between being scheduled and starting to run.
+#[must_use]
+pub struct QueuedCall {
+    name: &'static str,
+    queued_at: Instant,
+}
+
+impl QueuedCall {
+    /// Notes that an asynchronous call has been scheduled, if instrumentation is enabled.
+    #[inline]
+    pub fn enqueue(name: &'static str) -> Option<Self> {
+        is_enabled().then(|| Self {
+            name,
+            queued_at: Instant::now(),
+        })
+    }
+
+    /// Records the queue latency of the call, and starts timing its execution.
+    pub fn start(self) -> CallTimer {
+        let started_at = Instant::now();
+        let latency = started_at.saturating_duration_since(self.queued_at);
+        record(self.name, |timings| timings.queue_latency.record(latency));
+        CallTimer {
+            name: self.name,
+            started_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(0 => 0)]
+    #[test_case(1 => 1)]
+    #[test_case(2 => 2)]
+    #[test_case(3 => 2)]
+    #[test_case(4 => 3)]
+    #[test_case(1_000_000 => 20)]
+    #[test_case(1 << 22 => 23)]
+    #[test_case(u64::MAX => 23)]
+    fn bucket_index(micros: u64) -> usize {
+        Histogram::bucket_index(micros)
+    }
+
+    #[test]
+    fn histogram_record() {
+        let mut histogram = Histogram::default();
+        histogram.record(Duration::from_micros(3));
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_nanos(10));
+
+        assert_eq!(histogram.count, 3);
+        assert_eq!(histogram.total_micros, 5003);
+        assert_eq!(histogram.max_micros, 5000);
+        assert_eq!(histogram.buckets.iter().sum::<u64>(), 3);
+        assert_eq!(histogram.buckets[0], 1);
+        assert_eq!(histogram.buckets[2], 1);
+        assert_eq!(histogram.buckets[13], 1);
+    }
+
+    #[test]
+    fn timers_record_only_when_enabled() {
+        // Other tests in this crate don't enable instrumentation, so it's safe to toggle it here.
+        const NAME: &str = "timers_record_only_when_enabled";
+
+        assert!(CallTimer::start(NAME).is_none());
+        assert!(QueuedCall::enqueue(NAME).is_none());
+
+        set_enabled(true);
+        drop(CallTimer::start(NAME).expect("enabled"));
+        let queued = QueuedCall::enqueue(NAME).expect("enabled");
+        set_enabled(false);
+        // Calls that started while enabled are still recorded.
+        drop(queued.start());
+
+        let timings = snapshot().remove(NAME).expect("recorded");
+        assert_eq!(timings.duration.count, 2);
+        assert_eq!(timings.queue_latency.count, 1);
+    }
+}
@@ -7,17 +7,30 @@ use std::future::Future;
 use std::num::NonZeroU64;
 
 mod as_type;
+#[cfg(not(target_arch = "wasm32"))]
+mod error_details;
 mod sequences;
 mod serialized;
 pub use as_type::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use error_details::*;
 pub use sequences::*;
 pub use serialized::*;
 
 mod transform_helper;
 pub use transform_helper::*;
 
+pub mod handle_accounting;
+pub mod instrumentation;
+pub mod panics;
+
 // See https://github.com/rust-lang/rfcs/issues/1389
 pub fn describe_panic(any: &Box<dyn std::any::Any + Send>) -> String {
+    describe_panic_payload(&**any)
+}
+
+/// Like [`describe_panic`], but for a payload that isn't boxed, as in a panic hook.
+pub fn describe_panic_payload(any: &(dyn std::any::Any + Send)) -> String {
     if let Some(msg) = any.downcast_ref::<&str>() {
         msg.to_string()
     } else if let Some(msg) = any.downcast_ref::<String>() {
@@ -43,7 +56,7 @@ pub(crate) unsafe fn extend_lifetime<'a, 'b: 'a, T: ?Sized>(some_ref: &'a T) ->
 /// ```ignore
 /// # struct Foo;
 /// # #[cfg(ignore_even_when_running_all_tests)]
-/// bridge_as_handle!(Foo, mut = true, ffi = foo, jni = Foo, node = Foo);
+/// bridge_as_handle!(Foo, mut = true, ffi = foo, jni = Foo, node = Foo, python = true);
 /// # #[cfg(ignore_even_when_running_all_tests)]
 /// bridge_handle_fns!(Foo, ffi = foo, jni = Foo, node = Foo);
 /// ```
@@ -89,17 +102,35 @@ pub(crate) unsafe fn extend_lifetime<'a, 'b: 'a, T: ?Sized>(some_ref: &'a T) ->
 ///   For TypeScript's benefit, each boxed type gets its own unique `interface Foo`, and the
 ///   arguments are of the form `Wrapper<Foo>`.
 ///
+/// - Python: only types declared with `python = true` are exposed. Boxed values are opaque
+///   [`NativeHandle`] objects managed by Python's garbage collector, which the wrapper classes on
+///   the Python side hold on to.
+///
+/// - wasm: only types declared with `wasm = true` are exposed. Boxed values are opaque
+///   [`wasm::NativeHandle`] objects exported through wasm-bindgen. Arguments are borrowed from the
+///   JavaScript object for the duration of the call, so JavaScript keeps ownership of the handle.
+///
 /// [`JsBox`]: https://docs.rs/neon/0.7.1-napi/neon/types/struct.JsBox.html
 /// [`node::AsyncArgTypeInfo`]: crate::node::AsyncArgTypeInfo
+/// [`NativeHandle`]: crate::python::NativeHandle
+/// [`wasm::NativeHandle`]: crate::wasm::NativeHandle
 #[macro_export]
 macro_rules! bridge_as_handle {
-    ($typ:ty $(, mut = $_mut:tt)? $(, ffi = $ffi_name:ident)? $(, jni = $jni_name:ident)? $(, node = $node_name:ident)?) => {
+    ($typ:ty $(, mut = $_mut:tt)? $(, ffi = $ffi_name:ident)? $(, jni = $jni_name:ident)? $(, node = $node_name:ident)? $(, python = $python:tt)? $(, wasm = $wasm:tt)?) => {
         #[cfg(feature = "ffi")]
         $crate::ffi_bridge_as_handle!($typ $(as $ffi_name)?);
         #[cfg(feature = "jni")]
         $crate::jni_bridge_as_handle!($typ $(as $jni_name)?);
         #[cfg(feature = "node")]
         $crate::node_bridge_as_handle!($typ $(as $node_name)? $(, mut = $_mut)?);
+        $(
+            #[cfg(feature = "python")]
+            $crate::python_bridge_as_handle!($typ as $python);
+        )?
+        $(
+            #[cfg(feature = "wasm")]
+            $crate::wasm_bridge_as_handle!($typ as $wasm);
+        )?
     };
 }
 
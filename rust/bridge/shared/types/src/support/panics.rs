@@ -0,0 +1,178 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Capturing details about panics in bridged calls.
+//!
+//! Once [`install_panic_hook`] has been called, every panic records a [`PanicReport`] with its
+//! message, location, and (when the platform supports it) a symbolized backtrace. The bridges
+//! catch panics at their entry points and use [`PanicReport::for_payload`] to attach the report
+//! to the error they return, and the app can register a [`PanicReporter`] to be told about every
+//! panic as it happens, e.g. to forward it to a crash-reporting service.
+
+use std::any::Any;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::{Once, RwLock};
+
+use super::{describe_panic, describe_panic_payload};
+
+/// Information about a single panic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanicReport {
+    /// The panic message, or a placeholder if the payload wasn't a string.
+    pub message: String,
+    /// The source location of the panic, as `file:line:column`.
+    pub location: Option<String>,
+    /// A symbolized backtrace from the point of the panic, if one could be captured.
+    pub backtrace: Option<String>,
+}
+
+impl PanicReport {
+    /// Returns the report recorded for the most recent panic on this thread, if it matches
+    /// `payload`, or a report with just the message otherwise.
+    ///
+    /// Should be called on the same thread that caught the panic, right after catching it.
+    pub fn for_payload(payload: &Box<dyn Any + Send>) -> Self {
+        let message = describe_panic(payload);
+        LAST_PANIC
+            .with(|last| last.borrow_mut().take())
+            .filter(|report| report.message == message)
+            .unwrap_or(Self {
+                message,
+                location: None,
+                backtrace: None,
+            })
+    }
+}
+
+impl fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " (at {location})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives reports for every panic, whether or not it is eventually caught.
+///
+/// Reporters run inside the panic hook, so they must not panic themselves (that would abort the
+/// process), and should return quickly.
+pub trait PanicReporter: Send + Sync {
+    fn report(&self, report: &PanicReport);
+}
+
+static REPORTER: RwLock<Option<Box<dyn PanicReporter>>> = RwLock::new(None);
+
+thread_local! {
+    static LAST_PANIC: RefCell<Option<PanicReport>> = const { RefCell::new(None) };
+}
+
+/// Sets (or with `None`, clears) the reporter that is told about every panic.
+///
+/// Also installs the panic hook if it hasn't been already.
+pub fn set_panic_reporter(reporter: Option<Box<dyn PanicReporter>>) {
+    install_panic_hook();
+    *REPORTER.write().expect("not poisoned") = reporter;
+}
+
+/// Installs a panic hook that records a [`PanicReport`] for each panic.
+///
+/// The hook runs before any previously-installed hook, so it can be combined with hooks that log or
+/// abort. Calling this more than once has no further effect.
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let backtrace = Backtrace::force_capture();
+            let report = PanicReport {
+                message: describe_panic_payload(info.payload()),
+                location: info.location().map(ToString::to_string),
+                backtrace: (backtrace.status() == BacktraceStatus::Captured)
+                    .then(|| backtrace.to_string()),
+            };
+
+            // Don't block on a reporter being swapped out; it's more important to finish
+            // panicking.
+            if let Ok(reporter) = REPORTER.try_read() {
+                if let Some(reporter) = reporter.as_deref() {
+                    reporter.report(&report);
+                }
+            }
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(report));
+
+            previous_hook(info)
+        }));
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn report_from_hook() {
+        install_panic_hook();
+        let payload = std::panic::catch_unwind(|| panic!("at the disco")).expect_err("panicked");
+        let report = PanicReport::for_payload(&payload);
+        assert_eq!(report.message, "at the disco");
+        assert!(
+            report
+                .location
+                .as_deref()
+                .is_some_and(|location| location.contains("panics.rs")),
+            "{report:?}"
+        );
+
+        // The report is consumed.
+        let report = PanicReport::for_payload(&payload);
+        assert_eq!(report.location, None);
+        assert_eq!(report.backtrace, None);
+    }
+
+    #[test]
+    fn mismatched_report_is_ignored() {
+        install_panic_hook();
+        let _ = std::panic::catch_unwind(|| panic!("first"));
+        let payload: Box<dyn Any + Send> = Box::new("second");
+        assert_eq!(
+            PanicReport::for_payload(&payload),
+            PanicReport {
+                message: "second".to_owned(),
+                location: None,
+                backtrace: None,
+            }
+        );
+    }
+
+    #[test]
+    fn reporter_sees_panics() {
+        // No other tests in this crate set a reporter, so it's safe to set one here.
+        struct Collector(Mutex<Vec<String>>);
+        impl PanicReporter for &'static Collector {
+            fn report(&self, report: &PanicReport) {
+                self.0
+                    .lock()
+                    .expect("not poisoned")
+                    .push(report.message.clone());
+            }
+        }
+        static COLLECTOR: Collector = Collector(Mutex::new(Vec::new()));
+
+        set_panic_reporter(Some(Box::new(&COLLECTOR)));
+        let _ = std::panic::catch_unwind(|| panic!("reported"));
+        set_panic_reporter(None);
+        let _ = std::panic::catch_unwind(|| panic!("not reported"));
+
+        let messages = COLLECTOR.0.lock().expect("not poisoned");
+        assert!(messages.iter().any(|message| message == "reported"));
+        assert!(!messages.iter().any(|message| message == "not reported"));
+    }
+}
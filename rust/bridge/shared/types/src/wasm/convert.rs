@@ -0,0 +1,340 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::fmt::Display;
+
+use js_sys::{Array, TypeError, Uint8Array};
+use libsignal_protocol::{Aci, Pni, ServiceId};
+use wasm_bindgen::convert::{FromWasmAbi, RefFromWasmAbi};
+
+use super::*;
+use crate::support::{Array as _, AsType, FixedLengthBincodeSerializable, Serialized};
+
+/// The type an argument is passed as across the wasm-bindgen boundary.
+///
+/// This is separate from [`ArgTypeInfo`] so that generated entry points can name it without
+/// having to name a `'storage` lifetime.
+pub trait WasmArgType {
+    /// A type wasm-bindgen knows how to receive from JavaScript.
+    type ArgType: FromWasmAbi;
+}
+
+/// Converts arguments from their wasm-bindgen form to a Rust type.
+///
+/// `ArgTypeInfo` has two required methods: `borrow` and `load_from`. The use site looks like this:
+///
+/// ```no_run
+/// # use libsignal_bridge_types::wasm::*;
+/// # struct Foo;
+/// # impl SimpleArgTypeInfo for Foo {
+/// #     type ArgType = u32;
+/// #     fn convert_from(foreign: u32) -> Result<Self, JsValue> {
+/// #         Ok(Foo)
+/// #     }
+/// # }
+/// # fn test(js_arg: u32) -> Result<(), JsValue> {
+/// let mut js_arg_borrowed = Foo::borrow(js_arg)?;
+/// let rust_arg = Foo::load_from(&mut js_arg_borrowed);
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// The `'storage` lifetime allows for borrowed types to depend on the lifetime of the stored
+/// value, so that an `Option<&[u8]>` can refer to a buffer copied out of JavaScript.
+///
+/// Shared references that appear directly as arguments use [`RefArgTypeInfo`] instead, and simple
+/// Rust types that are converted by value should implement [`SimpleArgTypeInfo`].
+pub trait ArgTypeInfo<'storage>: WasmArgType + Sized {
+    /// Local storage for the argument.
+    type StoredType: 'storage;
+    /// "Borrows" the data in `foreign`, usually to establish a local lifetime or owning type.
+    fn borrow(foreign: Self::ArgType) -> Result<Self::StoredType, JsValue>;
+    /// Loads the Rust value from the data that's been `stored` by [`borrow()`](Self::borrow()).
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self;
+}
+
+/// A simpler interface for [`ArgTypeInfo`] for Rust types that are converted by value.
+pub trait SimpleArgTypeInfo: Sized + 'static {
+    /// A type wasm-bindgen knows how to receive from JavaScript.
+    type ArgType: FromWasmAbi;
+    /// Converts the data in `foreign` to the Rust type.
+    fn convert_from(foreign: Self::ArgType) -> Result<Self, JsValue>;
+}
+
+impl<T> WasmArgType for T
+where
+    T: SimpleArgTypeInfo,
+{
+    type ArgType = <T as SimpleArgTypeInfo>::ArgType;
+}
+
+impl<'a, T> ArgTypeInfo<'a> for T
+where
+    T: SimpleArgTypeInfo,
+{
+    type StoredType = Option<Self>;
+    fn borrow(foreign: Self::ArgType) -> Result<Self::StoredType, JsValue> {
+        Ok(Some(Self::convert_from(foreign)?))
+    }
+    fn load_from(stored: &'a mut Self::StoredType) -> Self {
+        stored.take().expect("only called once")
+    }
+}
+
+/// Converts arguments passed by reference, like `&[u8]` or handles.
+///
+/// wasm-bindgen lends the generated entry point a `&Self::ArgType` for the duration of the call,
+/// which is then viewed as a `&Self`. Handles in particular are never moved out of JavaScript.
+pub trait RefArgTypeInfo {
+    /// A type wasm-bindgen knows how to lend from JavaScript.
+    type ArgType: RefFromWasmAbi + ?Sized;
+    /// Views the data in `foreign` as the Rust type.
+    fn load_from(foreign: &Self::ArgType) -> Result<&Self, JsValue>;
+}
+
+/// Converts result values from their Rust form to JavaScript values.
+pub trait ResultTypeInfo: Sized {
+    /// Converts the data in `self` to a JavaScript value.
+    fn convert_into(self) -> Result<JsValue, JsValue>;
+}
+
+macro_rules! primitive_as_simple_arg_type {
+    ($typ:ty) => {
+        impl SimpleArgTypeInfo for $typ {
+            type ArgType = Self;
+            fn convert_from(foreign: Self) -> Result<Self, JsValue> {
+                Ok(foreign)
+            }
+        }
+    };
+}
+
+primitive_as_simple_arg_type!(bool);
+primitive_as_simple_arg_type!(u8);
+primitive_as_simple_arg_type!(u32);
+primitive_as_simple_arg_type!(String);
+
+impl SimpleArgTypeInfo for ServiceId {
+    type ArgType = Box<[u8]>;
+    fn convert_from(foreign: Box<[u8]>) -> Result<Self, JsValue> {
+        foreign[..]
+            .try_into()
+            .ok()
+            .and_then(Self::parse_from_service_id_fixed_width_binary)
+            .ok_or_else(|| TypeError::new("invalid Service-Id-FixedWidthBinary").into())
+    }
+}
+
+impl SimpleArgTypeInfo for Aci {
+    type ArgType = Box<[u8]>;
+    fn convert_from(foreign: Box<[u8]>) -> Result<Self, JsValue> {
+        ServiceId::convert_from(foreign)?
+            .try_into()
+            .map_err(|_| TypeError::new("not an ACI").into())
+    }
+}
+
+impl SimpleArgTypeInfo for Pni {
+    type ArgType = Box<[u8]>;
+    fn convert_from(foreign: Box<[u8]>) -> Result<Self, JsValue> {
+        ServiceId::convert_from(foreign)?
+            .try_into()
+            .map_err(|_| TypeError::new("not a PNI").into())
+    }
+}
+
+impl<T, P> SimpleArgTypeInfo for AsType<T, P>
+where
+    T: 'static,
+    P: SimpleArgTypeInfo + TryInto<T>,
+    P::Error: Display,
+{
+    type ArgType = P::ArgType;
+    fn convert_from(foreign: P::ArgType) -> Result<Self, JsValue> {
+        match P::convert_from(foreign)?.try_into() {
+            Ok(t) => Ok(AsType::from(t)),
+            Err(e) => {
+                Err(TypeError::new(&format!("invalid {}: {e}", std::any::type_name::<T>())).into())
+            }
+        }
+    }
+}
+
+/// Unlike the Node bridge, which relies on the TypeScript wrappers to validate these up front,
+/// the wasm bridge checks them here and throws a `TypeError` for a bad value.
+impl<T> SimpleArgTypeInfo for Serialized<T>
+where
+    T: FixedLengthBincodeSerializable
+        + for<'a> serde::Deserialize<'a>
+        + partial_default::PartialDefault
+        + 'static,
+{
+    type ArgType = Box<[u8]>;
+    fn convert_from(foreign: Box<[u8]>) -> Result<Self, JsValue> {
+        let invalid = || TypeError::new(&format!("invalid {}", std::any::type_name::<T>()));
+        if foreign.len() != T::Array::LEN {
+            return Err(invalid().into());
+        }
+        let result: T = zkgroup::deserialize(&foreign).map_err(|_| invalid())?;
+        Ok(Serialized::from(result))
+    }
+}
+
+impl WasmArgType for &[u8] {
+    type ArgType = Box<[u8]>;
+}
+
+impl<'storage> ArgTypeInfo<'storage> for &'storage [u8] {
+    type StoredType = Box<[u8]>;
+    fn borrow(foreign: Box<[u8]>) -> Result<Self::StoredType, JsValue> {
+        Ok(foreign)
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored
+    }
+}
+
+impl<T> WasmArgType for Option<T>
+where
+    T: WasmArgType,
+    Option<T::ArgType>: FromWasmAbi,
+{
+    type ArgType = Option<T::ArgType>;
+}
+
+impl<'storage, T> ArgTypeInfo<'storage> for Option<T>
+where
+    T: ArgTypeInfo<'storage>,
+    Option<T::ArgType>: FromWasmAbi,
+{
+    type StoredType = Option<T::StoredType>;
+    fn borrow(foreign: Self::ArgType) -> Result<Self::StoredType, JsValue> {
+        foreign.map(T::borrow).transpose()
+    }
+    fn load_from(stored: &'storage mut Self::StoredType) -> Self {
+        stored.as_mut().map(T::load_from)
+    }
+}
+
+impl RefArgTypeInfo for [u8] {
+    type ArgType = [u8];
+    fn load_from(foreign: &[u8]) -> Result<&Self, JsValue> {
+        Ok(foreign)
+    }
+}
+
+impl RefArgTypeInfo for str {
+    type ArgType = str;
+    fn load_from(foreign: &str) -> Result<&Self, JsValue> {
+        Ok(foreign)
+    }
+}
+
+impl<const LEN: usize> RefArgTypeInfo for [u8; LEN] {
+    type ArgType = [u8];
+    fn load_from(foreign: &[u8]) -> Result<&Self, JsValue> {
+        foreign.try_into().map_err(|_| {
+            TypeError::new(&format!("expected {LEN} bytes, got {}", foreign.len())).into()
+        })
+    }
+}
+
+/// Implementation of [`bridge_as_handle`](crate::support::bridge_as_handle) for wasm.
+#[macro_export]
+macro_rules! wasm_bridge_as_handle {
+    ( $typ:ty as false ) => {};
+    ( $typ:ty as true ) => {
+        impl wasm::BridgeHandle for $typ {}
+
+        impl wasm::RefArgTypeInfo for $typ {
+            type ArgType = wasm::NativeHandle;
+            fn load_from(foreign: &wasm::NativeHandle) -> Result<&Self, wasm::JsValue> {
+                foreign.downcast_ref()
+            }
+        }
+
+        impl wasm::ResultTypeInfo for $typ {
+            fn convert_into(self) -> Result<wasm::JsValue, wasm::JsValue> {
+                Ok(wasm::NativeHandle::wrap(self))
+            }
+        }
+    };
+}
+
+impl ResultTypeInfo for () {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        Ok(JsValue::UNDEFINED)
+    }
+}
+
+macro_rules! into_js_value_as_result_type {
+    ($typ:ty) => {
+        impl ResultTypeInfo for $typ {
+            fn convert_into(self) -> Result<JsValue, JsValue> {
+                Ok(self.into())
+            }
+        }
+    };
+}
+
+into_js_value_as_result_type!(bool);
+into_js_value_as_result_type!(i32);
+into_js_value_as_result_type!(u32);
+into_js_value_as_result_type!(String);
+into_js_value_as_result_type!(&str);
+
+impl ResultTypeInfo for &[u8] {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        Ok(Uint8Array::from(self).into())
+    }
+}
+
+impl ResultTypeInfo for Vec<u8> {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        self.as_slice().convert_into()
+    }
+}
+
+impl<const LEN: usize> ResultTypeInfo for [u8; LEN] {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        self.as_slice().convert_into()
+    }
+}
+
+impl ResultTypeInfo for Box<[String]> {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        Ok(self.iter().map(JsValue::from).collect::<Array>().into())
+    }
+}
+
+impl ResultTypeInfo for ServiceId {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        self.service_id_fixed_width_binary().convert_into()
+    }
+}
+
+impl<T> ResultTypeInfo for Serialized<T>
+where
+    T: FixedLengthBincodeSerializable + serde::Serialize,
+{
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        zkgroup::serialize(&*self).convert_into()
+    }
+}
+
+impl<T: ResultTypeInfo> ResultTypeInfo for Option<T> {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        match self {
+            Some(value) => value.convert_into(),
+            None => Ok(JsValue::NULL),
+        }
+    }
+}
+
+impl<T: ResultTypeInfo, E: WasmError> ResultTypeInfo for Result<T, E> {
+    fn convert_into(self) -> Result<JsValue, JsValue> {
+        self.map_err(WasmError::into_js_error)?.convert_into()
+    }
+}
@@ -0,0 +1,94 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::fmt::Display;
+
+use libsignal_protocol::SignalProtocolError;
+use usernames::{ProofVerificationFailure, UsernameError, UsernameLinkError};
+use zkgroup::{ZkGroupDeserializationFailure, ZkGroupVerificationFailure};
+
+use super::*;
+
+/// Errors that can be thrown into JavaScript.
+///
+/// The thrown `Error` has the same `name` the Node bridge would give it, so it can be checked
+/// against the `ErrorCode`s in `node/ts/Errors.ts`.
+pub trait WasmError: Display + Sized {
+    /// The `ErrorCode` name for this error, or `None` for a generic `LibSignalError`.
+    fn code(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Converts the error to a JavaScript `Error`.
+    fn into_js_error(self) -> JsValue {
+        let error = js_sys::Error::new(&self.to_string());
+        error.set_name(self.code().unwrap_or("LibSignalError"));
+        error.into()
+    }
+}
+
+/// Records which entry point failed on a thrown `Error`, like the Node bridge's `operation`.
+///
+/// Values that aren't `Error`s are passed through unchanged.
+#[doc(hidden)]
+pub fn with_operation(error: JsValue, operation: &str) -> JsValue {
+    if error.is_instance_of::<js_sys::Error>() {
+        // Setting a property on an Error object can only fail if it's been frozen.
+        let _ = js_sys::Reflect::set(&error, &"operation".into(), &operation.into());
+    }
+    error
+}
+
+impl WasmError for SignalProtocolError {
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::DuplicatedMessage(..) => Some("DuplicatedMessage"),
+            Self::SealedSenderSelfSend => Some("SealedSenderSelfSend"),
+            Self::UntrustedIdentity(_) => Some("UntrustedIdentity"),
+            Self::InvalidRegistrationId(..) => Some("InvalidRegistrationId"),
+            Self::InvalidSessionStructure(_) => Some("InvalidSession"),
+            Self::InvalidSenderKeySession { .. } => Some("InvalidSenderKeySession"),
+            _ => None,
+        }
+    }
+}
+
+impl WasmError for ZkGroupDeserializationFailure {}
+
+impl WasmError for ZkGroupVerificationFailure {}
+
+impl WasmError for UsernameError {
+    fn code(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::BadNicknameCharacter => "BadNicknameCharacter",
+            Self::NicknameTooShort => "NicknameTooShort",
+            Self::NicknameTooLong => "NicknameTooLong",
+            Self::NicknameCannotBeEmpty => "NicknameCannotBeEmpty",
+            Self::NicknameCannotStartWithDigit => "CannotStartWithDigit",
+            Self::MissingSeparator => "MissingSeparator",
+            Self::DiscriminatorCannotBeEmpty => "DiscriminatorCannotBeEmpty",
+            Self::DiscriminatorCannotBeZero => "DiscriminatorCannotBeZero",
+            Self::DiscriminatorCannotBeSingleDigit => "DiscriminatorCannotBeSingleDigit",
+            Self::DiscriminatorCannotHaveLeadingZeros => "DiscriminatorCannotHaveLeadingZeros",
+            Self::BadDiscriminatorCharacter => "BadDiscriminatorCharacter",
+            Self::DiscriminatorTooLarge => "DiscriminatorTooLarge",
+        })
+    }
+}
+
+impl WasmError for ProofVerificationFailure {}
+
+impl WasmError for UsernameLinkError {
+    fn code(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::InputDataTooLong => "InputDataTooLong",
+            Self::InvalidEntropyDataLength => "InvalidEntropyDataLength",
+            Self::UsernameLinkDataTooShort
+            | Self::HmacMismatch
+            | Self::BadCiphertext
+            | Self::InvalidDecryptedDataStructure => "InvalidUsernameLinkEncryptedData",
+        })
+    }
+}
@@ -0,0 +1,60 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::any::Any;
+
+pub use wasm_bindgen::prelude::*;
+pub use {js_sys, wasm_bindgen};
+
+#[macro_use]
+mod convert;
+pub use convert::*;
+
+mod error;
+pub use error::*;
+
+/// An opaque JavaScript object owning a Rust value declared with
+/// [`bridge_as_handle`](crate::support::bridge_as_handle).
+///
+/// JavaScript code never looks inside these; the TypeScript wrapper classes hold on to them and
+/// pass them back to the entry points that take the corresponding Rust type, just like the boxed
+/// handles of the Node bridge.
+#[wasm_bindgen]
+pub struct NativeHandle {
+    value: Box<dyn Any>,
+    type_name: &'static str,
+}
+
+impl NativeHandle {
+    fn new<T: BridgeHandle>(value: T) -> Self {
+        Self {
+            value: Box::new(value),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    /// Moves `value` into a new JavaScript object.
+    pub fn wrap<T: BridgeHandle>(value: T) -> JsValue {
+        Self::new(value).into()
+    }
+
+    /// Returns the Rust value inside this handle, or a `TypeError` if it has a different type.
+    pub fn downcast_ref<T: BridgeHandle>(&self) -> Result<&T, JsValue> {
+        self.value.downcast_ref().ok_or_else(|| {
+            js_sys::TypeError::new(&format!(
+                "expected a handle to {}, got {}",
+                std::any::type_name::<T>(),
+                self.type_name,
+            ))
+            .into()
+        })
+    }
+}
+
+/// A marker trait for Rust objects exposed to JavaScript through a [`NativeHandle`].
+///
+/// Implemented by [`bridge_as_handle`](crate::support::bridge_as_handle) for types declared with
+/// `wasm = true`.
+pub trait BridgeHandle: Any {}
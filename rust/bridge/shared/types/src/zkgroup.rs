@@ -44,13 +44,15 @@ macro_rules! bridge_as_fixed_length_serializable {
 
 /// Defines functions for types that implement [`FixedLengthBincodeSerializable`].
 ///
-/// `
+/// `bridge_fixed_length_serializable_fns!(FooBar)` generates
 /// `#[bridge_fn] fn FooBar_CheckValidContents`, which checks that the type can be deserialized.
+///
+/// Any additional arguments are forwarded to the `bridge_fn`.
 #[macro_export]
 macro_rules! bridge_fixed_length_serializable_fns {
-    ($typ:ident) => {
+    ($typ:ident $(, $param:ident = $val:tt)*) => {
         ::paste::paste! {
-            #[bridge_fn]
+            #[bridge_fn($($param = $val),*)]
             fn [<$typ _CheckValidContents>](
                 buffer: &[u8]
             ) -> Result<(), ZkGroupDeserializationFailure> {
@@ -68,18 +70,20 @@ macro_rules! bridge_fixed_length_serializable_fns {
 /// `bridge_serializable_handle_fns!(FooBar)` generates
 /// - `#[bridge_fn] fn FooBar_Deserialize` for deserializing into a `FooBar`, and
 /// - `#[bridge_fn] fn FooBar_Serialize` for serializing a `FooBar` again.
+///
+/// Any additional arguments are forwarded to both `bridge_fn`s.
 #[macro_export]
 macro_rules! bridge_serializable_handle_fns {
-    ($typ:ident) => {
+    ($typ:ident $(, $param:ident = $val:tt)*) => {
         $crate::bridge_handle_fns!($typ, clone = false);
         ::paste::paste! {
-            #[bridge_fn]
+            #[bridge_fn($($param = $val),*)]
             fn [<$typ _Deserialize>](
                 buffer: &[u8]
             ) -> Result<$typ, ZkGroupDeserializationFailure> {
                 zkgroup::deserialize(buffer)
             }
-            #[bridge_fn]
+            #[bridge_fn($($param = $val),*)]
             fn [<$typ _Serialize>](
                 handle: & $typ,
             ) -> Vec<u8> {
@@ -106,5 +110,5 @@ bridge_as_fixed_length_serializable!(ReceiptCredentialRequestContext);
 bridge_as_fixed_length_serializable!(ReceiptCredentialResponse);
 bridge_as_fixed_length_serializable!(UuidCiphertext);
 
-bridge_as_handle!(ServerPublicParams);
-bridge_as_handle!(ServerSecretParams);
+bridge_as_handle!(ServerPublicParams, python = true, wasm = true);
+bridge_as_handle!(ServerSecretParams, python = true);
@@ -0,0 +1,37 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! WebAssembly entry points for libsignal, for JavaScript environments that can't load the Node
+//! addon.
+//!
+//! The entry points themselves are generated in libsignal-bridge by `#[bridge_fn(wasm = true)]`,
+//! with the same names and argument orders as in `node/Native.d.ts`, so that the TypeScript
+//! wrappers can be shared between the two. This crate only links them into a single module, and
+//! adds the few functions that the Node bridge writes by hand.
+
+#![warn(clippy::unwrap_used)]
+
+use libsignal_bridge::wasm::{self, WasmError as _};
+use libsignal_protocol::IdentityKeyPair;
+use wasm_bindgen::prelude::*;
+
+/// Returns `{ publicKey: PublicKey, privateKey: PrivateKey }`, like the Node bridge.
+#[wasm_bindgen(js_name = "IdentityKeyPair_Deserialize")]
+pub fn identity_key_pair_deserialize(buffer: &[u8]) -> Result<js_sys::Object, JsValue> {
+    let identity_key_pair = IdentityKeyPair::try_from(buffer)
+        .map_err(|e| wasm::with_operation(e.into_js_error(), "IdentityKeyPair_Deserialize"))?;
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(
+        &result,
+        &"publicKey".into(),
+        &wasm::NativeHandle::wrap(*identity_key_pair.public_key()),
+    )?;
+    js_sys::Reflect::set(
+        &result,
+        &"privateKey".into(),
+        &wasm::NativeHandle::wrap(*identity_key_pair.private_key()),
+    )?;
+    Ok(result)
+}
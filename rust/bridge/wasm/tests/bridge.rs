@@ -0,0 +1,145 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Run with `wasm-pack test --node rust/bridge/wasm`.
+
+use js_sys::{Array, Reflect, Uint8Array};
+use libsignal_bridge::wasm::wasm_bindgen::convert::TryFromJsValue as _;
+use libsignal_bridge::wasm::{JsCast as _, JsValue, NativeHandle};
+use libsignal_bridge::{protocol, usernames, zkgroup};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn handle(value: Result<JsValue, JsValue>) -> NativeHandle {
+    NativeHandle::try_from_js_value(value.expect("success")).expect("a handle")
+}
+
+fn bytes(value: Result<JsValue, JsValue>) -> Vec<u8> {
+    Uint8Array::new(&value.expect("success")).to_vec()
+}
+
+fn thrown(value: Result<JsValue, JsValue>) -> js_sys::Error {
+    value.expect_err("failure").dyn_into().expect("an Error")
+}
+
+fn operation(error: &js_sys::Error) -> JsValue {
+    Reflect::get(error, &"operation".into()).expect("can read properties")
+}
+
+#[wasm_bindgen_test]
+fn private_key_round_trip() {
+    let private_key = handle(protocol::wasm_PrivateKey_Generate());
+    let serialized = bytes(protocol::wasm_PrivateKey_Serialize(&private_key));
+    assert_eq!(serialized.len(), 32);
+
+    let deserialized = handle(protocol::wasm_PrivateKey_Deserialize(&serialized));
+    assert_eq!(
+        bytes(protocol::wasm_PrivateKey_Serialize(&deserialized)),
+        serialized
+    );
+
+    let public_key = handle(protocol::wasm_PrivateKey_GetPublicKey(&private_key));
+    let message = b"message";
+    let signature = bytes(protocol::wasm_PrivateKey_Sign(&private_key, message));
+    assert_eq!(
+        protocol::wasm_PublicKey_Verify(&public_key, message, &signature),
+        Ok(JsValue::TRUE)
+    );
+    assert_eq!(
+        protocol::wasm_PublicKey_Verify(&public_key, b"other message", &signature),
+        Ok(JsValue::FALSE)
+    );
+}
+
+#[wasm_bindgen_test]
+fn identity_key_pair_round_trip() {
+    let private_key = handle(protocol::wasm_PrivateKey_Generate());
+    let public_key = handle(protocol::wasm_PrivateKey_GetPublicKey(&private_key));
+    let serialized = bytes(protocol::wasm_IdentityKeyPair_Serialize(
+        &public_key,
+        &private_key,
+    ));
+
+    let result = signal_wasm::identity_key_pair_deserialize(&serialized).expect("valid");
+    let get = |name: &str| {
+        NativeHandle::try_from_js_value(Reflect::get(&result, &name.into()).expect("present"))
+            .expect("a handle")
+    };
+    assert_eq!(
+        bytes(protocol::wasm_PublicKey_Serialize(&get("publicKey"))),
+        bytes(protocol::wasm_PublicKey_Serialize(&public_key))
+    );
+    assert_eq!(
+        bytes(protocol::wasm_PrivateKey_Serialize(&get("privateKey"))),
+        bytes(protocol::wasm_PrivateKey_Serialize(&private_key))
+    );
+}
+
+#[wasm_bindgen_test]
+fn handle_of_wrong_type() {
+    let private_key = handle(protocol::wasm_PrivateKey_Generate());
+    let error = thrown(protocol::wasm_PublicKey_Serialize(&private_key));
+    assert!(error.is_instance_of::<js_sys::TypeError>());
+}
+
+#[wasm_bindgen_test]
+fn errors_have_name_and_operation() {
+    let error = thrown(protocol::wasm_PrivateKey_Deserialize(&[1, 2, 3]));
+    assert_eq!(error.name(), "LibSignalError");
+    assert_eq!(operation(&error), "PrivateKey_Deserialize");
+
+    let error = thrown(usernames::wasm_Username_Hash("no_separator".to_owned()));
+    assert_eq!(error.name(), "MissingSeparator");
+    assert_eq!(operation(&error), "Username_Hash");
+}
+
+#[wasm_bindgen_test]
+fn username_candidates() {
+    let candidates = usernames::wasm_Username_CandidatesFrom("signal".to_owned(), 3, 32)
+        .expect("valid")
+        .dyn_into::<Array>()
+        .expect("an Array");
+    assert!(candidates.length() > 0);
+    for candidate in candidates.iter() {
+        let candidate = candidate.as_string().expect("a string");
+        assert!(candidate.starts_with("signal."), "{candidate}");
+        assert_eq!(bytes(usernames::wasm_Username_Hash(candidate)).len(), 32);
+    }
+}
+
+#[wasm_bindgen_test]
+fn group_secret_params_service_id_round_trip() {
+    let params = bytes(zkgroup::wasm_GroupSecretParams_GenerateDeterministic(
+        &[0x42; 32],
+    ));
+    zkgroup::wasm_GroupSecretParams_CheckValidContents(&params).expect("valid");
+
+    let mut aci = [0x11; 17];
+    aci[0] = 0x00;
+    let ciphertext = bytes(zkgroup::wasm_GroupSecretParams_EncryptServiceId(
+        params.clone().into(),
+        aci.into(),
+    ));
+    let decrypted = bytes(zkgroup::wasm_GroupSecretParams_DecryptServiceId(
+        params.into(),
+        ciphertext.into(),
+    ));
+    assert_eq!(decrypted, aci);
+}
+
+#[wasm_bindgen_test]
+fn invalid_arguments_are_type_errors() {
+    let error = thrown(zkgroup::wasm_GroupSecretParams_GenerateDeterministic(
+        &[0x42; 31],
+    ));
+    assert!(error.is_instance_of::<js_sys::TypeError>());
+
+    let error = thrown(zkgroup::wasm_GroupSecretParams_GetMasterKey(
+        vec![0; 3].into(),
+    ));
+    assert!(error.is_instance_of::<js_sys::TypeError>());
+
+    let error = thrown(zkgroup::wasm_ProfileKey_DeriveAccessKey(vec![0; 31].into()));
+    assert!(error.is_instance_of::<js_sys::TypeError>());
+}
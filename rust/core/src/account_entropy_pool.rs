@@ -0,0 +1,195 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+#![warn(missing_docs)]
+
+//! The account entropy pool, the root secret for an account's recoverable keys.
+//!
+//! The pool is shown to the user as a "recovery key", so its canonical form is a string of
+//! [`AccountEntropyPool::LENGTH`] characters from a restricted alphabet, and the keys derived from
+//! it are derived from that string rather than from some underlying binary value. Every client has
+//! to agree on this byte-for-byte.
+//!
+//! ```text
+//! account entropy pool
+//! ├── SVR key (see AccountEntropyPool::derive_svr_key)
+//! └── backup key (see AccountEntropyPool::derive_backup_key)
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use hkdf::Hkdf;
+use rand::{CryptoRng, Rng};
+use sha2::Sha256;
+
+/// A randomly-generated string used as the root of an account's recoverable keys.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AccountEntropyPool {
+    entropy_pool: [u8; Self::LENGTH],
+}
+
+/// The error returned when a string is not a valid [`AccountEntropyPool`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidAccountEntropyPool {
+    /// The input had the wrong number of characters.
+    WrongLength(usize),
+    /// The input contained a character outside the pool's alphabet.
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for InvalidAccountEntropyPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongLength(length) => write!(
+                f,
+                "account entropy pool must be {} characters, but was {length}",
+                AccountEntropyPool::LENGTH
+            ),
+            Self::InvalidCharacter(c) => {
+                write!(f, "account entropy pool contains invalid character {c:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidAccountEntropyPool {}
+
+impl AccountEntropyPool {
+    /// The number of characters in a pool.
+    pub const LENGTH: usize = 64;
+
+    /// The characters a pool is made from.
+    ///
+    /// Uppercase letters are not accepted; the encoding must be canonical so that every client
+    /// derives the same keys.
+    pub const ALPHABET: &'static [u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    /// The length of keys derived from the pool.
+    pub const DERIVED_KEY_LEN: usize = 32;
+
+    /// Generates a new pool, with each character chosen uniformly at random from
+    /// [`Self::ALPHABET`].
+    pub fn generate<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        let entropy_pool =
+            std::array::from_fn(|_| Self::ALPHABET[rng.gen_range(0..Self::ALPHABET.len())]);
+        Self { entropy_pool }
+    }
+
+    /// The canonical string form of the pool.
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.entropy_pool).expect("validated on construction")
+    }
+
+    /// Derives the key used to store the account's master secrets in SVR.
+    pub fn derive_svr_key(&self) -> [u8; Self::DERIVED_KEY_LEN] {
+        self.derive(b"20240801_SIGNAL_SVR_MASTER_KEY")
+    }
+
+    /// Derives the root key for message backups.
+    pub fn derive_backup_key(&self) -> [u8; Self::DERIVED_KEY_LEN] {
+        self.derive(b"20240801_SIGNAL_BACKUP_KEY")
+    }
+
+    fn derive(&self, info: &[u8]) -> [u8; Self::DERIVED_KEY_LEN] {
+        let mut key = [0; Self::DERIVED_KEY_LEN];
+        Hkdf::<Sha256>::new(
+            None, // Empty salt
+            &self.entropy_pool,
+        )
+        .expand(info, &mut key)
+        .expect("valid length");
+        key
+    }
+}
+
+impl FromStr for AccountEntropyPool {
+    type Err = InvalidAccountEntropyPool;
+
+    /// Validates a pool in its canonical string form.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(c) = s
+            .chars()
+            .find(|c| !u8::try_from(*c).is_ok_and(|b| Self::ALPHABET.contains(&b)))
+        {
+            return Err(InvalidAccountEntropyPool::InvalidCharacter(c));
+        }
+        let entropy_pool = s
+            .as_bytes()
+            .try_into()
+            .map_err(|_| InvalidAccountEntropyPool::WrongLength(s.len()))?;
+        Ok(Self { entropy_pool })
+    }
+}
+
+impl fmt::Display for AccountEntropyPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for AccountEntropyPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't put the pool in logs.
+        f.write_str("AccountEntropyPool(_)")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    const POOL: &str = "dtjs858asj6tv0jzsqrsmj0ubp335pisj98e9ssnss8myoc08drhtcktyawvx45l";
+
+    #[test]
+    fn known_derivations() {
+        let pool: AccountEntropyPool = POOL.parse().expect("valid");
+        assert_eq!(
+            pool.derive_svr_key(),
+            hex!("cdfecb856b148ca1c7f7557904f1ec698d0ccc4d4d68ed4c58c74a21e5c1c6c1")
+        );
+        assert_eq!(
+            pool.derive_backup_key(),
+            hex!("ea26a2ddb5dba5ef9e34e1b8dea1f5ae7f255306a6d2d883e542306eaa9fe985")
+        );
+    }
+
+    #[test]
+    fn generated_pools_are_valid() {
+        for _ in 0..100 {
+            let pool = AccountEntropyPool::generate(&mut OsRng);
+            assert_eq!(pool.as_str().parse(), Ok(pool));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_pools() {
+        assert_eq!(
+            POOL[1..].parse::<AccountEntropyPool>(),
+            Err(InvalidAccountEntropyPool::WrongLength(63))
+        );
+        assert_eq!(
+            format!("{POOL}a").parse::<AccountEntropyPool>(),
+            Err(InvalidAccountEntropyPool::WrongLength(65))
+        );
+        assert_eq!(
+            POOL.to_uppercase().parse::<AccountEntropyPool>(),
+            Err(InvalidAccountEntropyPool::InvalidCharacter('D'))
+        );
+        assert_eq!(
+            POOL.replacen('d', "é", 1).parse::<AccountEntropyPool>(),
+            Err(InvalidAccountEntropyPool::InvalidCharacter('é'))
+        );
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let pool: AccountEntropyPool = POOL.parse().expect("valid");
+        assert!(!format!("{pool:?}").contains(POOL));
+    }
+}
@@ -8,11 +8,22 @@
 //! Types for identifying an individual Signal client instance.
 
 use std::fmt;
+use std::num::NonZeroU8;
 
 use uuid::Uuid;
 
 /// Known types of [ServiceId].
-#[derive(Clone, Copy, Hash, PartialEq, Eq, num_enum::IntoPrimitive, num_enum::TryFromPrimitive)]
+#[derive(
+    Clone,
+    Copy,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    num_enum::IntoPrimitive,
+    num_enum::TryFromPrimitive,
+)]
 #[repr(u8)]
 pub enum ServiceIdKind {
     /// An [Aci].
@@ -46,6 +57,44 @@ pub struct WrongKindOfServiceIdError {
     pub actual: ServiceIdKind,
 }
 
+impl fmt::Display for WrongKindOfServiceIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected service ID of kind {}, but got {}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for WrongKindOfServiceIdError {}
+
+/// The error returned by the `parse_*` helpers on [`ServiceId`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceIdParseError {
+    /// The input was not a service ID in any accepted format.
+    InvalidFormat,
+    /// The input was a valid service ID, but not of the expected kind.
+    WrongKind(WrongKindOfServiceIdError),
+}
+
+impl fmt::Display for ServiceIdParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceIdParseError::InvalidFormat => f.write_str("invalid service ID"),
+            ServiceIdParseError::WrongKind(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ServiceIdParseError {}
+
+impl From<WrongKindOfServiceIdError> for ServiceIdParseError {
+    fn from(value: WrongKindOfServiceIdError) -> Self {
+        Self::WrongKind(value)
+    }
+}
+
 /// A service ID with a known type.
 ///
 /// `RAW_KIND` is a raw [ServiceIdKind] (eventually Rust will allow enums as generic parameters).
@@ -65,6 +114,15 @@ impl<const KIND: u8> SpecificServiceId<KIND> {
     const fn from_uuid(uuid: Uuid) -> Self {
         Self(uuid)
     }
+
+    /// The UUID inside this service ID as raw bytes, with no kind marker.
+    ///
+    /// This is the fixed-size form used by protocols that already know which kind of service ID
+    /// they're dealing with, such as CDSI.
+    #[inline]
+    pub const fn raw_uuid_bytes(&self) -> &uuid::Bytes {
+        self.0.as_bytes()
+    }
 }
 
 impl<const KIND: u8> SpecificServiceId<KIND>
@@ -271,6 +329,61 @@ impl ServiceId {
             ServiceId::Pni(pni) => pni.into(),
         }
     }
+
+    /// Parses a service ID of any kind from its standard string representation.
+    ///
+    /// Equivalent to [`parse_from_service_id_string`](Self::parse_from_service_id_string), but with
+    /// an error instead of `None`.
+    pub fn parse_any(input: &str) -> Result<Self, ServiceIdParseError> {
+        Self::parse_from_service_id_string(input).ok_or(ServiceIdParseError::InvalidFormat)
+    }
+
+    /// Parses a service ID of any kind from a binary representation.
+    ///
+    /// Unlike [`parse_from_service_id_binary`](Self::parse_from_service_id_binary), this also
+    /// accepts the fixed-width form of an ACI.
+    pub fn parse_any_binary(bytes: &[u8]) -> Result<Self, ServiceIdParseError> {
+        match bytes.len() {
+            17 => Self::parse_from_service_id_fixed_width_binary(
+                bytes.try_into().expect("already measured"),
+            ),
+            _ => Self::parse_from_service_id_binary(bytes),
+        }
+        .ok_or(ServiceIdParseError::InvalidFormat)
+    }
+
+    /// Parses an ACI from its standard string representation, which is a bare UUID.
+    ///
+    /// A valid service ID of another kind produces [`ServiceIdParseError::WrongKind`].
+    pub fn parse_as_aci(input: &str) -> Result<Aci, ServiceIdParseError> {
+        Ok(Self::parse_any(input)?.try_into()?)
+    }
+
+    /// Parses a PNI from its standard string representation (`PNI:<uuid>`), or from a bare UUID,
+    /// the legacy representation for PNIs.
+    pub fn parse_as_pni(input: &str) -> Result<Pni, ServiceIdParseError> {
+        match Self::parse_any(input)? {
+            // A bare UUID parses as an ACI, but here we know it's a PNI.
+            ServiceId::Aci(legacy_pni) => Ok(Uuid::from(legacy_pni).into()),
+            ServiceId::Pni(pni) => Ok(pni),
+        }
+    }
+
+    /// Parses an ACI from any binary representation, including the raw UUID bytes.
+    ///
+    /// A valid service ID of another kind produces [`ServiceIdParseError::WrongKind`].
+    pub fn parse_binary_as_aci(bytes: &[u8]) -> Result<Aci, ServiceIdParseError> {
+        Ok(Self::parse_any_binary(bytes)?.try_into()?)
+    }
+
+    /// Parses a PNI from any binary representation, or from raw UUID bytes, the legacy
+    /// representation for PNIs.
+    pub fn parse_binary_as_pni(bytes: &[u8]) -> Result<Pni, ServiceIdParseError> {
+        match bytes.len() {
+            16 => Ok(Uuid::from_slice(bytes).expect("already measured").into()),
+            _ => Ok(Self::parse_any_binary(bytes)?.try_into()?),
+        }
+    }
 }
 
 impl fmt::Debug for ServiceId {
@@ -329,6 +442,44 @@ where
     }
 }
 
+/// Service IDs are serialized using their standard string representation.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ServiceId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.service_id_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ServiceId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::parse_any(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const KIND: u8> serde::Serialize for SpecificServiceId<KIND>
+where
+    ServiceId: From<Self>,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ServiceId::from(*self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const KIND: u8> serde::Deserialize<'de> for SpecificServiceId<KIND>
+where
+    Self: TryFrom<ServiceId, Error = WrongKindOfServiceIdError>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ServiceId::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod service_id_tests {
     use std::borrow::Borrow;
@@ -610,6 +761,83 @@ mod service_id_tests {
         assert!(ServiceId::parse_from_service_id_string("ACI:{uuid}").is_none());
     }
 
+    #[test]
+    fn parse_with_expected_kind() {
+        let uuid = uuid::uuid!("8c78cd2a-16ff-427d-83dc-1a5e36ce713d");
+        let aci = Aci::from(uuid);
+        let pni = Pni::from(uuid);
+
+        assert_eq!(ServiceId::parse_any(&uuid.to_string()), Ok(aci.into()));
+        assert_eq!(ServiceId::parse_any(&format!("PNI:{uuid}")), Ok(pni.into()));
+        assert_eq!(
+            ServiceId::parse_any("PNI:"),
+            Err(ServiceIdParseError::InvalidFormat)
+        );
+
+        assert_eq!(ServiceId::parse_as_aci(&uuid.to_string()), Ok(aci));
+        assert_eq!(
+            ServiceId::parse_as_aci(&format!("PNI:{uuid}")),
+            Err(ServiceIdParseError::WrongKind(WrongKindOfServiceIdError {
+                expected: ServiceIdKind::Aci,
+                actual: ServiceIdKind::Pni,
+            }))
+        );
+
+        assert_eq!(ServiceId::parse_as_pni(&format!("PNI:{uuid}")), Ok(pni));
+        // Legacy PNIs are bare UUIDs.
+        assert_eq!(ServiceId::parse_as_pni(&uuid.to_string()), Ok(pni));
+
+        assert_eq!(
+            ServiceId::parse_any_binary(&array_prepend(0x00, uuid.as_bytes())),
+            Ok(aci.into())
+        );
+        assert_eq!(ServiceId::parse_binary_as_aci(uuid.as_bytes()), Ok(aci));
+        assert_eq!(
+            ServiceId::parse_binary_as_aci(&pni.service_id_binary()),
+            Err(ServiceIdParseError::WrongKind(WrongKindOfServiceIdError {
+                expected: ServiceIdKind::Aci,
+                actual: ServiceIdKind::Pni,
+            }))
+        );
+        assert_eq!(
+            ServiceId::parse_binary_as_pni(&pni.service_id_binary()),
+            Ok(pni)
+        );
+        assert_eq!(ServiceId::parse_binary_as_pni(uuid.as_bytes()), Ok(pni));
+        assert_eq!(
+            ServiceId::parse_binary_as_pni(&[0; 15]),
+            Err(ServiceIdParseError::InvalidFormat)
+        );
+
+        assert_eq!(aci.raw_uuid_bytes(), uuid.as_bytes());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_uses_service_id_string() {
+        let uuid = uuid::uuid!("8c78cd2a-16ff-427d-83dc-1a5e36ce713d");
+        let aci = Aci::from(uuid);
+        let pni = Pni::from(uuid);
+
+        let json = serde_json::to_string(&[ServiceId::from(aci), ServiceId::from(pni)])
+            .expect("can serialize");
+        assert_eq!(json, format!(r#"["{uuid}","PNI:{uuid}"]"#));
+        assert_eq!(
+            serde_json::from_str::<[ServiceId; 2]>(&json).expect("can deserialize"),
+            [ServiceId::from(aci), ServiceId::from(pni)]
+        );
+
+        assert_eq!(
+            serde_json::to_string(&pni).expect("can serialize"),
+            format!(r#""PNI:{uuid}""#)
+        );
+        assert_eq!(
+            serde_json::from_str::<Aci>(&format!(r#""{uuid}""#)).expect("can deserialize"),
+            aci
+        );
+        assert!(serde_json::from_str::<Aci>(&format!(r#""PNI:{uuid}""#)).is_err());
+    }
+
     #[test]
     fn ordering() {
         let test_uuid = uuid::uuid!("8c78cd2a-16ff-427d-83dc-1a5e36ce713d");
@@ -661,24 +889,74 @@ mod service_id_tests {
             }
         })
     }
+
+    #[test]
+    fn device_id_range() {
+        assert_eq!(DeviceId::new(0), Err(InvalidDeviceId(0)));
+        assert_eq!(u32::from(DeviceId::new(1).expect("valid")), 1);
+        assert_eq!(u8::from(DeviceId::new(127).expect("valid")), 127);
+        assert_eq!(DeviceId::new(128), Err(InvalidDeviceId(128)));
+
+        assert_eq!(DeviceId::try_from(42u32), DeviceId::new(42));
+        assert_eq!(DeviceId::try_from(0x101u32), Err(InvalidDeviceId(0x101)));
+        assert_eq!(
+            InvalidDeviceId(300).to_string(),
+            "invalid device ID 300 (must be between 1 and 127)"
+        );
+    }
 }
 
 /// The type used in memory to represent a *device*, i.e. a particular Signal client instance which
 /// represents some user.
 ///
+/// Valid device IDs are in the range `1..=127`; use [`DeviceId::new`] or `TryFrom` to check a raw
+/// value.
+///
 /// Used in [ProtocolAddress].
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
-pub struct DeviceId(u32);
+pub struct DeviceId(NonZeroU8);
+
+impl DeviceId {
+    /// The largest valid device ID.
+    pub const MAX: u8 = 127;
+
+    /// Checks that `id` is a valid device ID.
+    pub const fn new(id: u8) -> Result<Self, InvalidDeviceId> {
+        match NonZeroU8::new(id) {
+            Some(id) if id.get() <= Self::MAX => Ok(Self(id)),
+            _ => Err(InvalidDeviceId(id as u32)),
+        }
+    }
+}
+
+impl TryFrom<u8> for DeviceId {
+    type Error = InvalidDeviceId;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl TryFrom<u32> for DeviceId {
+    type Error = InvalidDeviceId;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        u8::try_from(value)
+            .ok()
+            .and_then(|id| Self::new(id).ok())
+            .ok_or(InvalidDeviceId(value))
+    }
+}
 
-impl From<u32> for DeviceId {
-    fn from(value: u32) -> Self {
-        Self(value)
+impl From<DeviceId> for u8 {
+    fn from(value: DeviceId) -> Self {
+        value.0.get()
     }
 }
 
 impl From<DeviceId> for u32 {
     fn from(value: DeviceId) -> Self {
-        value.0
+        value.0.get().into()
     }
 }
 
@@ -688,6 +966,23 @@ impl fmt::Display for DeviceId {
     }
 }
 
+/// The error returned when converting a raw value that is out of range to a [`DeviceId`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidDeviceId(pub u32);
+
+impl fmt::Display for InvalidDeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid device ID {} (must be between 1 and {})",
+            self.0,
+            DeviceId::MAX
+        )
+    }
+}
+
+impl std::error::Error for InvalidDeviceId {}
+
 /// Represents a unique Signal client instance as `(<user ID>, <device ID>)` pair.
 #[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct ProtocolAddress {
@@ -709,7 +1004,7 @@ impl ProtocolAddress {
     /// // This is a unique id for some user, typically a UUID.
     /// let user_id: String = "04899A85-4C9E-44CC-8428-A02AB69335F1".to_string();
     /// // Each client instance representing that user has a unique device id.
-    /// let device_id: DeviceId = 2_u32.into();
+    /// let device_id = DeviceId::new(2).expect("valid");
     /// let address = ProtocolAddress::new(user_id.clone(), device_id);
     ///
     /// assert!(address.name() == &user_id);
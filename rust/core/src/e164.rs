@@ -0,0 +1,105 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+#![warn(missing_docs)]
+
+//! Phone numbers in E.164 format.
+
+use std::fmt;
+use std::num::{NonZeroU64, ParseIntError};
+use std::str::FromStr;
+
+#[cfg(feature = "e164-formatting")]
+mod format;
+
+/// A phone number in [E.164] format, stored as its digits (including the country code).
+///
+/// No validation is done beyond requiring a positive number; in particular, the ordering is
+/// numeric and should not be presented to users as meaningful.
+///
+/// [E.164]: https://en.wikipedia.org/wiki/E.164
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct E164(NonZeroU64);
+
+impl E164 {
+    /// The size of the fixed-width big-endian encoding produced by [`Self::to_be_bytes`].
+    pub const SERIALIZED_LEN: usize = 8;
+
+    /// Wraps the digits of a phone number.
+    pub const fn new(number: NonZeroU64) -> Self {
+        Self(number)
+    }
+
+    /// Decodes a big-endian number, returning `None` if it is zero.
+    pub const fn from_be_bytes(bytes: [u8; Self::SERIALIZED_LEN]) -> Option<Self> {
+        match NonZeroU64::new(u64::from_be_bytes(bytes)) {
+            Some(number) => Some(Self(number)),
+            None => None,
+        }
+    }
+
+    /// Encodes the number as big-endian bytes.
+    pub const fn to_be_bytes(self) -> [u8; Self::SERIALIZED_LEN] {
+        self.0.get().to_be_bytes()
+    }
+}
+
+impl From<E164> for NonZeroU64 {
+    fn from(value: E164) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for E164 {
+    type Err = ParseIntError;
+
+    /// Parses a number with or without a leading `+`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('+').unwrap_or(s);
+        NonZeroU64::from_str(s).map(Self)
+    }
+}
+
+impl fmt::Display for E164 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{}", self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for E164 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for E164 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn string_round_trip() {
+        let e164: E164 = "+18005550100".parse().expect("valid");
+        assert_eq!(e164, "18005550100".parse().expect("valid"));
+        assert_eq!(e164.to_string(), "+18005550100");
+        assert!("+0".parse::<E164>().is_err());
+        assert!("+1-800".parse::<E164>().is_err());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let e164 = E164::new(NonZeroU64::new(18005550100).expect("nonzero"));
+        assert_eq!(E164::from_be_bytes(e164.to_be_bytes()), Some(e164));
+        assert_eq!(E164::from_be_bytes([0; 8]), None);
+    }
+}
@@ -0,0 +1,157 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Display formatting for [`E164`] numbers.
+//!
+//! This is deliberately simple: it knows how to find the country calling code at the start of a
+//! number, and how numbers are grouped in the North American Numbering Plan (country code 1), but
+//! otherwise shows the national number as a single run of digits. That's enough for showing a
+//! number back to a user without pulling in a full phone number metadata library.
+
+use super::E164;
+
+/// The North American Numbering Plan, shared by the US, Canada, and several other countries.
+const NANP_COUNTRY_CODE: u16 = 1;
+
+/// Two-digit country calling codes.
+///
+/// Country codes are prefix-free: every code starting with `1` or `7` is a single digit, and
+/// every code that isn't one or two digits is three.
+const TWO_DIGIT_COUNTRY_CODES: &[u16] = &[
+    20, 27, 30, 31, 32, 33, 34, 36, 39, 40, 41, 43, 44, 45, 46, 47, 48, 49, 51, 52, 53, 54, 55, 56,
+    57, 58, 60, 61, 62, 63, 64, 65, 66, 81, 82, 84, 86, 90, 91, 92, 93, 94, 95, 98,
+];
+
+impl E164 {
+    /// Returns the country calling code at the start of the number, such as `1` for
+    /// `+18005550100` or `44` for `+442079460000`.
+    ///
+    /// Returns `None` if the number isn't long enough to hold anything after the country code.
+    /// Three-digit codes aren't checked against the list of codes actually in use.
+    pub fn country_code(&self) -> Option<u16> {
+        split_country_code(&self.0.to_string()).map(|(code, _)| code)
+    }
+
+    /// Returns the digits after the country calling code.
+    ///
+    /// Returns `None` in the same cases as [`Self::country_code`].
+    pub fn national_number(&self) -> Option<String> {
+        split_country_code(&self.0.to_string()).map(|(_, national)| national.to_owned())
+    }
+
+    /// Formats the number for display to someone in any country, such as `+1 800-555-0100` or
+    /// `+44 2079460000`.
+    pub fn format_international(&self) -> String {
+        let digits = self.0.to_string();
+        match split_country_code(&digits) {
+            Some((NANP_COUNTRY_CODE, national)) if national.len() == 10 => {
+                format!(
+                    "+1 {}-{}-{}",
+                    &national[..3],
+                    &national[3..6],
+                    &national[6..]
+                )
+            }
+            Some((code, national)) => format!("+{code} {national}"),
+            None => self.to_string(),
+        }
+    }
+
+    /// Formats the number for display within its own country, such as `(800) 555-0100` or
+    /// `2079460000`.
+    ///
+    /// Trunk prefixes (like the leading `0` used within the UK) aren't added, since they differ
+    /// from country to country.
+    pub fn format_national(&self) -> String {
+        let digits = self.0.to_string();
+        match split_country_code(&digits) {
+            Some((NANP_COUNTRY_CODE, national)) if national.len() == 10 => {
+                format!(
+                    "({}) {}-{}",
+                    &national[..3],
+                    &national[3..6],
+                    &national[6..]
+                )
+            }
+            Some((_, national)) => national.to_owned(),
+            None => self.to_string(),
+        }
+    }
+
+    /// Formats the number for display to someone whose own number has the country code
+    /// `local_country_code`.
+    ///
+    /// Numbers from the same country are shown in national format, and all others in
+    /// international format.
+    pub fn format_for_region(&self, local_country_code: u16) -> String {
+        if self.country_code() == Some(local_country_code) {
+            self.format_national()
+        } else {
+            self.format_international()
+        }
+    }
+}
+
+/// Splits `digits` into a country calling code and the remaining national number.
+fn split_country_code(digits: &str) -> Option<(u16, &str)> {
+    let code_len = match digits.as_bytes().first()? {
+        b'1' | b'7' => 1,
+        _ => {
+            let two_digits = digits.get(..2)?.parse().ok()?;
+            if TWO_DIGIT_COUNTRY_CODES.contains(&two_digits) {
+                2
+            } else {
+                3
+            }
+        }
+    };
+    if digits.len() <= code_len {
+        return None;
+    }
+    let (code, national) = digits.split_at(code_len);
+    Some((code.parse().ok()?, national))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn e164(s: &str) -> E164 {
+        s.parse().expect("valid")
+    }
+
+    #[test]
+    fn country_codes() {
+        assert_eq!(e164("+18005550100").country_code(), Some(1));
+        assert_eq!(e164("+74951234567").country_code(), Some(7));
+        assert_eq!(e164("+442079460000").country_code(), Some(44));
+        assert_eq!(e164("+353212345678").country_code(), Some(353));
+        assert_eq!(e164("+8613800138000").country_code(), Some(86));
+        assert_eq!(
+            e164("+442079460000").national_number().as_deref(),
+            Some("2079460000")
+        );
+        assert_eq!(e164("+44").country_code(), None);
+        assert_eq!(e164("+1").national_number(), None);
+    }
+
+    #[test]
+    fn formatting() {
+        let nanp = e164("+18005550100");
+        assert_eq!(nanp.format_international(), "+1 800-555-0100");
+        assert_eq!(nanp.format_national(), "(800) 555-0100");
+
+        let uk = e164("+442079460000");
+        assert_eq!(uk.format_international(), "+44 2079460000");
+        assert_eq!(uk.format_national(), "2079460000");
+
+        assert_eq!(nanp.format_for_region(1), "(800) 555-0100");
+        assert_eq!(nanp.format_for_region(44), "+1 800-555-0100");
+        assert_eq!(uk.format_for_region(44), "2079460000");
+
+        // Too short to split, so shown as-is.
+        assert_eq!(e164("+44").format_national(), "+44");
+    }
+}
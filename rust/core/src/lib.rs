@@ -3,11 +3,15 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+mod account_entropy_pool;
 mod address;
+mod e164;
 mod version;
 
+pub use account_entropy_pool::{AccountEntropyPool, InvalidAccountEntropyPool};
 pub use address::{
-    Aci, DeviceId, Pni, ProtocolAddress, ServiceId, ServiceIdFixedWidthBinaryBytes, ServiceIdKind,
-    WrongKindOfServiceIdError,
+    Aci, DeviceId, InvalidDeviceId, Pni, ProtocolAddress, ServiceId,
+    ServiceIdFixedWidthBinaryBytes, ServiceIdKind, ServiceIdParseError, WrongKindOfServiceIdError,
 };
+pub use e164::E164;
 pub use version::VERSION;
@@ -0,0 +1,184 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{Aes256GcmDecryption, Aes256GcmEncryption, Error, Result};
+
+/// The size of the authentication tag produced by every [`Aead`].
+pub const AEAD_TAG_SIZE: usize = 16;
+
+/// An authenticated encryption algorithm with associated data.
+///
+/// The one-shot methods produce and accept the ciphertext followed by the
+/// tag. For large inputs, see [`crate::StreamEncryptor`].
+pub trait Aead {
+    /// A human-readable name for the algorithm.
+    const NAME: &'static str;
+    const KEY_SIZE: usize;
+    const NONCE_SIZE: usize;
+
+    /// Encrypts `buf` in place, returning the authentication tag.
+    fn seal_in_place(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        buf: &mut [u8],
+    ) -> Result<[u8; AEAD_TAG_SIZE]>;
+
+    /// Checks `tag` and decrypts `buf` in place.
+    ///
+    /// If the tag doesn't match, the contents of `buf` are unspecified.
+    fn open_in_place(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        buf: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()>;
+
+    fn encrypt(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(plaintext.len() + AEAD_TAG_SIZE);
+        buf.extend_from_slice(plaintext);
+        let tag = Self::seal_in_place(key, nonce, associated_data, &mut buf)?;
+        buf.extend_from_slice(&tag);
+        Ok(buf)
+    }
+
+    fn decrypt(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>> {
+        let tag_start = ciphertext
+            .len()
+            .checked_sub(AEAD_TAG_SIZE)
+            .ok_or(Error::InvalidInputSize)?;
+        let (ciphertext, tag) = ciphertext.split_at(tag_start);
+        let mut buf = ciphertext.to_vec();
+        Self::open_in_place(key, nonce, associated_data, &mut buf, tag)?;
+        Ok(buf)
+    }
+}
+
+/// AES-256-GCM, as implemented by [`Aes256GcmEncryption`] and
+/// [`Aes256GcmDecryption`].
+pub enum Aes256Gcm {}
+
+impl Aead for Aes256Gcm {
+    const NAME: &'static str = "AES-256-GCM";
+    const KEY_SIZE: usize = 32;
+    const NONCE_SIZE: usize = Aes256GcmEncryption::NONCE_SIZE;
+
+    fn seal_in_place(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        buf: &mut [u8],
+    ) -> Result<[u8; AEAD_TAG_SIZE]> {
+        let mut gcm = Aes256GcmEncryption::new(key, nonce, associated_data)?;
+        gcm.encrypt(buf);
+        Ok(gcm.compute_tag())
+    }
+
+    fn open_in_place(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        buf: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        let mut gcm = Aes256GcmDecryption::new(key, nonce, associated_data)?;
+        gcm.decrypt(buf);
+        gcm.verify_tag(tag)
+    }
+}
+
+/// XChaCha20-Poly1305, which doesn't rely on AES hardware support and has
+/// nonces large enough to be chosen at random.
+pub enum XChaCha20Poly1305 {}
+
+impl XChaCha20Poly1305 {
+    /// Encrypts `plaintext` with a random nonce.
+    ///
+    /// The result is the nonce, followed by the ciphertext, followed by the
+    /// tag.
+    pub fn encrypt_with_random_nonce(
+        key: &[u8],
+        associated_data: &[u8],
+        plaintext: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Vec<u8>> {
+        let mut nonce = [0; Self::NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+
+        let mut buf = Vec::with_capacity(Self::NONCE_SIZE + plaintext.len() + AEAD_TAG_SIZE);
+        buf.extend_from_slice(&nonce);
+        buf.extend_from_slice(plaintext);
+        let tag = Self::seal_in_place(key, &nonce, associated_data, &mut buf[Self::NONCE_SIZE..])?;
+        buf.extend_from_slice(&tag);
+        Ok(buf)
+    }
+
+    /// Decrypts the output of [`Self::encrypt_with_random_nonce`].
+    pub fn decrypt_with_nonce_prefix(
+        key: &[u8],
+        associated_data: &[u8],
+        input: &[u8],
+    ) -> Result<Vec<u8>> {
+        if input.len() < Self::NONCE_SIZE {
+            return Err(Error::InvalidInputSize);
+        }
+        let (nonce, ciphertext) = input.split_at(Self::NONCE_SIZE);
+        Self::decrypt(key, nonce, associated_data, ciphertext)
+    }
+
+    fn cipher(key: &[u8], nonce: &[u8]) -> Result<chacha20poly1305::XChaCha20Poly1305> {
+        if nonce.len() != Self::NONCE_SIZE {
+            return Err(Error::InvalidNonceSize);
+        }
+        chacha20poly1305::XChaCha20Poly1305::new_from_slice(key).map_err(|_| Error::InvalidKeySize)
+    }
+}
+
+impl Aead for XChaCha20Poly1305 {
+    const NAME: &'static str = "XChaCha20-Poly1305";
+    const KEY_SIZE: usize = 32;
+    const NONCE_SIZE: usize = 24;
+
+    fn seal_in_place(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        buf: &mut [u8],
+    ) -> Result<[u8; AEAD_TAG_SIZE]> {
+        let tag = Self::cipher(key, nonce)?
+            .encrypt_in_place_detached(nonce.into(), associated_data, buf)
+            .map_err(|_| Error::InvalidInputSize)?;
+        Ok(tag.into())
+    }
+
+    fn open_in_place(
+        key: &[u8],
+        nonce: &[u8],
+        associated_data: &[u8],
+        buf: &mut [u8],
+        tag: &[u8],
+    ) -> Result<()> {
+        if tag.len() != AEAD_TAG_SIZE {
+            return Err(Error::InvalidTag);
+        }
+        Self::cipher(key, nonce)?
+            .decrypt_in_place_detached(nonce.into(), associated_data, buf, tag.into())
+            .map_err(|_| Error::InvalidTag)
+    }
+}
@@ -0,0 +1,791 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Chunked [`Aead`] encryption for large files.
+//!
+//! The encrypted stream starts with a random salt, which is combined with the
+//! caller's key to derive a key used only for this stream. The plaintext is
+//! then split into chunks of [`STREAM_CHUNK_SIZE`] bytes (the last one may be
+//! shorter, or even empty), and each chunk is sealed separately. Each chunk's
+//! nonce is built from its position in the stream and whether it is the last
+//! chunk, so chunks can't be reordered, dropped, or truncated without the
+//! decryptor noticing. Callers never provide a nonce.
+//!
+//! AES-256-GCM is used unless another algorithm is chosen with the
+//! `with_aead` constructors.
+//!
+//! Plaintext is only released by the decryptors after the chunk it came from
+//! has been authenticated. Note that a stream that was cut short at a chunk
+//! boundary is only detected at the end, so a caller that acts on plaintext
+//! before reaching the end of the stream may see a prefix of the original.
+//!
+//! A [`StreamEncryptor`] can be paused at a chunk boundary with
+//! [`StreamEncryptor::pause`] and picked up again later, even in another
+//! process, with [`StreamEncryptor::resume`]. Chunks past the pause may
+//! already have been written under the old key, so a resumed stream never
+//! uses that key again: it writes a rekeying record, made up of a marker
+//! derived from the old salt and the chunk position followed by a fresh salt,
+//! and seals the rest of the stream under a key derived from the new salt.
+//! This holds even when the same checkpoint is resumed more than once.
+
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_util::{AsyncRead, AsyncWrite};
+use hmac::{Hmac, Mac as _};
+use rand_core::{CryptoRng, RngCore};
+use sha2::Sha256;
+use subtle::ConstantTimeEq as _;
+
+use crate::aead::{Aead, Aes256Gcm, AEAD_TAG_SIZE};
+use crate::{Error, Result};
+
+/// The number of plaintext bytes in each chunk except the last.
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+/// The size of the random salt at the start of every stream.
+pub const STREAM_SALT_SIZE: usize = 32;
+/// The size of the key passed to the encryptor and decryptor, whichever
+/// algorithm is used.
+pub const STREAM_KEY_SIZE: usize = 32;
+
+const SEALED_CHUNK_SIZE: usize = STREAM_CHUNK_SIZE + AEAD_TAG_SIZE;
+const KEY_DERIVATION_LABEL: &[u8] = b"Signal_ChunkedAead_20240601_";
+const REKEY_MARKER_LABEL: &[u8] = b"Signal_ChunkedAead_Rekey_20241016_";
+const REKEY_MARKER_SIZE: usize = 16;
+const REKEY_RECORD_SIZE: usize = REKEY_MARKER_SIZE + STREAM_SALT_SIZE;
+const MAX_NONCE_SIZE: usize = 24;
+
+/// Returns the size of the encrypted stream for a plaintext of `len` bytes.
+///
+/// Each time the stream was resumed adds a rekeying record to this.
+pub fn encrypted_len(len: u64) -> u64 {
+    // A chunk is only sealed once more plaintext follows it, so there's always
+    // at least one chunk and never an empty one after a full chunk.
+    let chunks = ((len + STREAM_CHUNK_SIZE as u64 - 1) / STREAM_CHUNK_SIZE as u64).max(1);
+    STREAM_SALT_SIZE as u64 + len + chunks * AEAD_TAG_SIZE as u64
+}
+
+fn check_key(key: &[u8]) -> Result<[u8; STREAM_KEY_SIZE]> {
+    key.try_into().map_err(|_| Error::InvalidKeySize)
+}
+
+fn derive_stream_key<A: Aead>(key: &[u8; STREAM_KEY_SIZE], salt: &[u8]) -> [u8; STREAM_KEY_SIZE] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key size");
+    mac.update(KEY_DERIVATION_LABEL);
+    mac.update(A::NAME.as_bytes());
+    mac.update(salt);
+    mac.finalize().into_bytes().into()
+}
+
+/// Marks the switch to a new salt before the chunk at `index`.
+///
+/// Ciphertext can't be made to look like a marker without the key, so a
+/// decryptor can check for one at every chunk boundary.
+fn rekey_marker<A: Aead>(
+    key: &[u8; STREAM_KEY_SIZE],
+    salt: &[u8],
+    index: u64,
+) -> [u8; REKEY_MARKER_SIZE] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key size");
+    mac.update(REKEY_MARKER_LABEL);
+    mac.update(A::NAME.as_bytes());
+    mac.update(salt);
+    mac.update(&index.to_be_bytes());
+    mac.finalize().into_bytes()[..REKEY_MARKER_SIZE]
+        .try_into()
+        .expect("correct length")
+}
+
+/// Writes the nonce for a chunk into the first `A::NONCE_SIZE` bytes of the
+/// result.
+fn chunk_nonce<A: Aead>(index: u64, is_last: bool) -> [u8; MAX_NONCE_SIZE] {
+    let len = A::NONCE_SIZE;
+    let mut nonce = [0; MAX_NONCE_SIZE];
+    nonce[len - 9..len - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[len - 1] = is_last.into();
+    nonce
+}
+
+/// Where to pick up a paused [`StreamEncryptor`].
+///
+/// Contains no secrets, but must be kept alongside the partially written
+/// output: resuming with the wrong checkpoint produces a stream that fails to
+/// decrypt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamCheckpoint {
+    /// The salt the most recent chunks were sealed with.
+    salt: [u8; STREAM_SALT_SIZE],
+    chunks_sealed: u64,
+    /// The number of rekeying records written so far.
+    resumptions: u64,
+}
+
+impl StreamCheckpoint {
+    /// The size of the serialized form of a checkpoint.
+    pub const SERIALIZED_LEN: usize = STREAM_SALT_SIZE + 8 + 8;
+
+    /// The number of plaintext bytes that were encrypted before pausing.
+    ///
+    /// Resuming continues with the plaintext byte at this offset.
+    pub fn plaintext_offset(&self) -> u64 {
+        self.chunks_sealed * STREAM_CHUNK_SIZE as u64
+    }
+
+    /// The number of bytes of output that were written before pausing.
+    ///
+    /// Anything in the output after this offset must be discarded before
+    /// resuming.
+    pub fn ciphertext_offset(&self) -> u64 {
+        STREAM_SALT_SIZE as u64
+            + self.chunks_sealed * SEALED_CHUNK_SIZE as u64
+            + self.resumptions * REKEY_RECORD_SIZE as u64
+    }
+
+    pub fn serialize(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut result = [0; Self::SERIALIZED_LEN];
+        let (salt, counts) = result.split_at_mut(STREAM_SALT_SIZE);
+        salt.copy_from_slice(&self.salt);
+        counts[..8].copy_from_slice(&self.chunks_sealed.to_be_bytes());
+        counts[8..].copy_from_slice(&self.resumptions.to_be_bytes());
+        result
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != Self::SERIALIZED_LEN {
+            return Err(Error::InvalidInputSize);
+        }
+        let (salt, counts) = bytes.split_at(STREAM_SALT_SIZE);
+        let (chunks_sealed, resumptions) = counts.split_at(8);
+        Ok(Self {
+            salt: salt.try_into().expect("correct length"),
+            chunks_sealed: u64::from_be_bytes(chunks_sealed.try_into().expect("correct length")),
+            resumptions: u64::from_be_bytes(resumptions.try_into().expect("correct length")),
+        })
+    }
+}
+
+/// Incremental encryption state, independent of the kind of I/O being done.
+struct Sealer<A> {
+    salt: [u8; STREAM_SALT_SIZE],
+    stream_key: [u8; STREAM_KEY_SIZE],
+    next_index: u64,
+    resumptions: u64,
+    /// Plaintext that hasn't been sealed yet. Never more than one byte longer
+    /// than a chunk, since a full chunk can't be sealed until it's known
+    /// whether it's the last one.
+    plaintext: Vec<u8>,
+    /// Sealed output that hasn't been written yet, starting at `written`.
+    output: Vec<u8>,
+    written: usize,
+    finished: bool,
+    aead: PhantomData<fn() -> A>,
+}
+
+impl<A: Aead> Sealer<A> {
+    fn new(key: &[u8], rng: &mut (impl RngCore + CryptoRng)) -> Result<Self> {
+        let key = check_key(key)?;
+        let mut salt = [0; STREAM_SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+
+        Ok(Self {
+            salt,
+            stream_key: derive_stream_key::<A>(&key, &salt),
+            next_index: 0,
+            resumptions: 0,
+            plaintext: Vec::with_capacity(STREAM_CHUNK_SIZE + 1),
+            output: salt.to_vec(),
+            written: 0,
+            finished: false,
+            aead: PhantomData,
+        })
+    }
+
+    /// Continues a stream whose output so far ends at
+    /// [`StreamCheckpoint::ciphertext_offset`], switching to a fresh salt.
+    fn resume(
+        key: &[u8],
+        checkpoint: &StreamCheckpoint,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        let key = check_key(key)?;
+        let resumptions = checkpoint
+            .resumptions
+            .checked_add(1)
+            .ok_or(Error::InvalidInputSize)?;
+        let mut salt = [0; STREAM_SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+
+        let mut output = Vec::with_capacity(REKEY_RECORD_SIZE);
+        output.extend_from_slice(&rekey_marker::<A>(
+            &key,
+            &checkpoint.salt,
+            checkpoint.chunks_sealed,
+        ));
+        output.extend_from_slice(&salt);
+
+        Ok(Self {
+            salt,
+            stream_key: derive_stream_key::<A>(&key, &salt),
+            next_index: checkpoint.chunks_sealed,
+            resumptions,
+            plaintext: Vec::with_capacity(STREAM_CHUNK_SIZE + 1),
+            output,
+            written: 0,
+            finished: false,
+            aead: PhantomData,
+        })
+    }
+
+    /// Describes the chunks sealed so far.
+    ///
+    /// Buffered plaintext that hasn't been sealed yet isn't covered; it has
+    /// to be provided again after resuming.
+    fn checkpoint(&self) -> StreamCheckpoint {
+        assert!(!self.finished, "paused after finishing");
+        StreamCheckpoint {
+            salt: self.salt,
+            chunks_sealed: self.next_index,
+            resumptions: self.resumptions,
+        }
+    }
+
+    /// Takes as much of `data` as can be buffered, returning the number of
+    /// bytes taken.
+    ///
+    /// Always takes at least one byte if `data` isn't empty.
+    fn push(&mut self, data: &[u8]) -> usize {
+        assert!(!self.finished, "data written after finishing");
+        let taken = data.len().min(STREAM_CHUNK_SIZE + 1 - self.plaintext.len());
+        self.plaintext.extend_from_slice(&data[..taken]);
+        if self.plaintext.len() > STREAM_CHUNK_SIZE {
+            self.seal_chunk(STREAM_CHUNK_SIZE, false);
+        }
+        taken
+    }
+
+    fn finish(&mut self) {
+        if !self.finished {
+            self.seal_chunk(self.plaintext.len(), true);
+            self.finished = true;
+        }
+    }
+
+    fn seal_chunk(&mut self, len: usize, is_last: bool) {
+        let nonce = chunk_nonce::<A>(self.next_index, is_last);
+        self.next_index = self
+            .next_index
+            .checked_add(1)
+            .expect("stream has too many chunks");
+
+        let start = self.output.len();
+        self.output.extend_from_slice(&self.plaintext[..len]);
+        let tag = A::seal_in_place(
+            &self.stream_key,
+            &nonce[..A::NONCE_SIZE],
+            &[],
+            &mut self.output[start..],
+        )
+        .expect("key and nonce are the right size");
+        self.output.extend_from_slice(&tag);
+        self.plaintext.drain(..len);
+    }
+
+    fn pending_output(&self) -> &[u8] {
+        &self.output[self.written..]
+    }
+
+    fn consume_output(&mut self, len: usize) {
+        self.written += len;
+        if self.written == self.output.len() {
+            self.output.clear();
+            self.written = 0;
+        }
+    }
+}
+
+enum OpenerState {
+    ReadingSalt {
+        key: [u8; STREAM_KEY_SIZE],
+    },
+    ReadingChunks {
+        key: [u8; STREAM_KEY_SIZE],
+        salt: [u8; STREAM_SALT_SIZE],
+        stream_key: [u8; STREAM_KEY_SIZE],
+        next_index: u64,
+        /// Whether the input buffer starts at a chunk boundary that hasn't
+        /// been checked for a rekeying record yet.
+        may_rekey: bool,
+    },
+    Finished,
+    Failed(Error),
+}
+
+/// Incremental decryption state, independent of the kind of I/O being done.
+struct Opener<A> {
+    state: OpenerState,
+    /// Input that hasn't been processed yet. Holds one byte more than a
+    /// sealed chunk so that a full chunk can be checked for being the last
+    /// one before opening it.
+    input: Box<[u8]>,
+    filled: usize,
+    /// Authenticated plaintext that hasn't been returned yet, starting at
+    /// `returned`.
+    plaintext: Vec<u8>,
+    returned: usize,
+    aead: PhantomData<fn() -> A>,
+}
+
+impl<A: Aead> Opener<A> {
+    fn new(key: &[u8]) -> Result<Self> {
+        Ok(Self {
+            state: OpenerState::ReadingSalt {
+                key: check_key(key)?,
+            },
+            input: vec![0; SEALED_CHUNK_SIZE + 1].into_boxed_slice(),
+            filled: 0,
+            plaintext: Vec::with_capacity(STREAM_CHUNK_SIZE),
+            returned: 0,
+            aead: PhantomData,
+        })
+    }
+
+    /// The part of the input buffer that should be read into next.
+    ///
+    /// Empty once the stream is finished.
+    fn unfilled(&mut self) -> &mut [u8] {
+        let target = match self.state {
+            OpenerState::ReadingSalt { .. } => STREAM_SALT_SIZE,
+            OpenerState::ReadingChunks { .. } => SEALED_CHUNK_SIZE + 1,
+            OpenerState::Finished | OpenerState::Failed(_) => self.filled,
+        };
+        &mut self.input[self.filled..target]
+    }
+
+    /// Records that `len` bytes were read into [`Self::unfilled`].
+    fn advance(&mut self, len: usize) -> Result<()> {
+        self.filled += len;
+        match &self.state {
+            OpenerState::ReadingSalt { key } if self.filled == STREAM_SALT_SIZE => {
+                let salt = self.input[..STREAM_SALT_SIZE]
+                    .try_into()
+                    .expect("correct length");
+                self.state = OpenerState::ReadingChunks {
+                    key: *key,
+                    salt,
+                    stream_key: derive_stream_key::<A>(key, &salt),
+                    next_index: 0,
+                    may_rekey: true,
+                };
+                self.filled = 0;
+            }
+            OpenerState::ReadingChunks { .. } => {
+                self.check_for_rekey();
+                if self.filled == SEALED_CHUNK_SIZE + 1 {
+                    // There's more input after this chunk, so it isn't the last.
+                    self.open_chunk(SEALED_CHUNK_SIZE, false)?;
+                    self.input[0] = self.input[SEALED_CHUNK_SIZE];
+                    self.filled = 1;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Switches to a new salt if the input starts with a rekeying record.
+    fn check_for_rekey(&mut self) {
+        let OpenerState::ReadingChunks {
+            key,
+            salt,
+            stream_key,
+            next_index,
+            may_rekey,
+        } = &mut self.state
+        else {
+            return;
+        };
+        // Resuming the same checkpoint more than once leaves only the last
+        // rekeying record, but a stream paused again before sealing any more
+        // chunks has several in a row.
+        while *may_rekey && self.filled >= REKEY_RECORD_SIZE {
+            let marker = rekey_marker::<A>(key, salt, *next_index);
+            if !bool::from(marker.ct_eq(&self.input[..REKEY_MARKER_SIZE])) {
+                *may_rekey = false;
+                break;
+            }
+            salt.copy_from_slice(&self.input[REKEY_MARKER_SIZE..REKEY_RECORD_SIZE]);
+            *stream_key = derive_stream_key::<A>(key, salt);
+            self.input.copy_within(REKEY_RECORD_SIZE..self.filled, 0);
+            self.filled -= REKEY_RECORD_SIZE;
+        }
+    }
+
+    /// Processes the remaining input as the last chunk.
+    fn end_of_input(&mut self) -> Result<()> {
+        match self.state {
+            OpenerState::ReadingSalt { .. } => self.fail(Error::InvalidInputSize),
+            OpenerState::ReadingChunks { .. } => {
+                self.check_for_rekey();
+                if self.filled < AEAD_TAG_SIZE {
+                    return self.fail(Error::InvalidInputSize);
+                }
+                self.open_chunk(self.filled, true)?;
+                self.filled = 0;
+                self.state = OpenerState::Finished;
+                Ok(())
+            }
+            OpenerState::Finished | OpenerState::Failed(_) => self.check_failed(),
+        }
+    }
+
+    fn open_chunk(&mut self, len: usize, is_last: bool) -> Result<()> {
+        let OpenerState::ReadingChunks {
+            stream_key,
+            next_index,
+            may_rekey,
+            ..
+        } = &mut self.state
+        else {
+            unreachable!("only called while reading chunks");
+        };
+        *may_rekey = true;
+        let nonce = chunk_nonce::<A>(*next_index, is_last);
+        *next_index = next_index
+            .checked_add(1)
+            .expect("stream has too many chunks");
+
+        let (ciphertext, tag) = self.input[..len].split_at(len - AEAD_TAG_SIZE);
+        self.plaintext.clear();
+        self.plaintext.extend_from_slice(ciphertext);
+        self.returned = 0;
+
+        if let Err(e) = A::open_in_place(
+            stream_key.as_slice(),
+            &nonce[..A::NONCE_SIZE],
+            &[],
+            &mut self.plaintext,
+            tag,
+        ) {
+            self.plaintext.clear();
+            return self.fail(e);
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, error: Error) -> Result<()> {
+        self.state = OpenerState::Failed(error);
+        self.check_failed()
+    }
+
+    fn check_failed(&self) -> Result<()> {
+        match &self.state {
+            OpenerState::Failed(Error::InvalidTag) => Err(Error::InvalidTag),
+            OpenerState::Failed(_) => Err(Error::InvalidInputSize),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.state, OpenerState::Finished)
+    }
+
+    /// Copies authenticated plaintext into `buf`, returning the number of
+    /// bytes copied.
+    fn read_plaintext(&mut self, buf: &mut [u8]) -> usize {
+        let available = &self.plaintext[self.returned..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.returned += count;
+        count
+    }
+
+    fn has_plaintext(&self) -> bool {
+        self.returned < self.plaintext.len()
+    }
+}
+
+fn io_error(error: Error) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
+
+/// [`Write`]r that encrypts everything written to it before passing it on.
+///
+/// [`StreamEncryptor::finish`] must be called once all the plaintext has been
+/// written; otherwise the output will be rejected as truncated.
+pub struct StreamEncryptor<W, A = Aes256Gcm> {
+    writer: W,
+    sealer: Sealer<A>,
+}
+
+impl<W: Write> StreamEncryptor<W> {
+    /// Creates an AES-256-GCM encryptor that writes to `writer` with a fresh
+    /// random salt.
+    pub fn new(key: &[u8], writer: W, rng: &mut (impl RngCore + CryptoRng)) -> Result<Self> {
+        Self::with_aead(key, writer, rng)
+    }
+
+    /// Continues an AES-256-GCM stream paused with [`StreamEncryptor::pause`].
+    ///
+    /// `writer` must be positioned at [`StreamCheckpoint::ciphertext_offset`]
+    /// of the earlier output, with anything after that discarded, and the next
+    /// plaintext written must start at [`StreamCheckpoint::plaintext_offset`].
+    /// The rest of the stream is sealed under a key derived from a fresh salt.
+    pub fn resume(
+        key: &[u8],
+        checkpoint: &StreamCheckpoint,
+        writer: W,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        Self::resume_with_aead(key, checkpoint, writer, rng)
+    }
+}
+
+impl<W: Write, A: Aead> StreamEncryptor<W, A> {
+    /// Like [`StreamEncryptor::new`], but uses the algorithm `A`.
+    pub fn with_aead(key: &[u8], writer: W, rng: &mut (impl RngCore + CryptoRng)) -> Result<Self> {
+        Ok(Self {
+            writer,
+            sealer: Sealer::new(key, rng)?,
+        })
+    }
+
+    /// Like [`StreamEncryptor::resume`], but uses the algorithm `A`.
+    pub fn resume_with_aead(
+        key: &[u8],
+        checkpoint: &StreamCheckpoint,
+        writer: W,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self> {
+        Ok(Self {
+            writer,
+            sealer: Sealer::resume(key, checkpoint, rng)?,
+        })
+    }
+
+    /// Writes out every complete chunk and stops, returning where to resume
+    /// and the inner writer.
+    ///
+    /// Plaintext after [`StreamCheckpoint::plaintext_offset`] has been
+    /// dropped and must be written again after resuming.
+    pub fn pause(mut self) -> std::io::Result<(StreamCheckpoint, W)> {
+        self.write_pending()?;
+        self.writer.flush()?;
+        Ok((self.sealer.checkpoint(), self.writer))
+    }
+
+    /// Seals the last chunk, flushes all output, and returns the inner writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.sealer.finish();
+        self.write_pending()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_pending(&mut self) -> std::io::Result<()> {
+        while !self.sealer.pending_output().is_empty() {
+            let written = self.writer.write(self.sealer.pending_output())?;
+            if written == 0 {
+                return Err(std::io::ErrorKind::WriteZero.into());
+            }
+            self.sealer.consume_output(written);
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write, A: Aead> Write for StreamEncryptor<W, A> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_pending()?;
+        Ok(self.sealer.push(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.write_pending()?;
+        self.writer.flush()
+    }
+}
+
+/// [`Read`]er that decrypts the output of a [`StreamEncryptor`].
+///
+/// Reads fail with [`std::io::ErrorKind::InvalidData`] if the stream has been
+/// modified or truncated.
+pub struct StreamDecryptor<R, A = Aes256Gcm> {
+    reader: R,
+    opener: Opener<A>,
+}
+
+impl<R: Read> StreamDecryptor<R> {
+    /// Creates an AES-256-GCM decryptor that reads from `reader`.
+    pub fn new(key: &[u8], reader: R) -> Result<Self> {
+        Self::with_aead(key, reader)
+    }
+}
+
+impl<R: Read, A: Aead> StreamDecryptor<R, A> {
+    /// Like [`StreamDecryptor::new`], but uses the algorithm `A`.
+    pub fn with_aead(key: &[u8], reader: R) -> Result<Self> {
+        Ok(Self {
+            reader,
+            opener: Opener::new(key)?,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read, A: Aead> Read for StreamDecryptor<R, A> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if buf.is_empty() || self.opener.has_plaintext() {
+                return Ok(self.opener.read_plaintext(buf));
+            }
+            if self.opener.is_finished() {
+                return Ok(0);
+            }
+            self.opener.check_failed().map_err(io_error)?;
+
+            let read = match self.reader.read(self.opener.unfilled()) {
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if read == 0 {
+                self.opener.end_of_input().map_err(io_error)?;
+            } else {
+                self.opener.advance(read).map_err(io_error)?;
+            }
+        }
+    }
+}
+
+/// [`AsyncWrite`]r that encrypts everything written to it before passing it
+/// on.
+///
+/// The last chunk is sealed when the writer is closed; a stream that isn't
+/// closed will be rejected as truncated.
+pub struct AsyncStreamEncryptor<W, A = Aes256Gcm> {
+    writer: W,
+    sealer: Sealer<A>,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncStreamEncryptor<W> {
+    /// Creates an AES-256-GCM encryptor that writes to `writer` with a fresh
+    /// random salt.
+    pub fn new(key: &[u8], writer: W, rng: &mut (impl RngCore + CryptoRng)) -> Result<Self> {
+        Self::with_aead(key, writer, rng)
+    }
+}
+
+impl<W: AsyncWrite + Unpin, A: Aead> AsyncStreamEncryptor<W, A> {
+    /// Like [`AsyncStreamEncryptor::new`], but uses the algorithm `A`.
+    pub fn with_aead(key: &[u8], writer: W, rng: &mut (impl RngCore + CryptoRng)) -> Result<Self> {
+        Ok(Self {
+            writer,
+            sealer: Sealer::new(key, rng)?,
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while !self.sealer.pending_output().is_empty() {
+            let written =
+                ready!(Pin::new(&mut self.writer).poll_write(cx, self.sealer.pending_output()))?;
+            if written == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.sealer.consume_output(written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin, A: Aead> AsyncWrite for AsyncStreamEncryptor<W, A> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Poll::Ready(Ok(this.sealer.push(buf)))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        this.sealer.finish();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.writer).poll_close(cx)
+    }
+}
+
+/// [`AsyncRead`]er that decrypts the output of a [`StreamEncryptor`] or
+/// [`AsyncStreamEncryptor`].
+///
+/// Reads fail with [`std::io::ErrorKind::InvalidData`] if the stream has been
+/// modified or truncated.
+pub struct AsyncStreamDecryptor<R, A = Aes256Gcm> {
+    reader: R,
+    opener: Opener<A>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncStreamDecryptor<R> {
+    /// Creates an AES-256-GCM decryptor that reads from `reader`.
+    pub fn new(key: &[u8], reader: R) -> Result<Self> {
+        Self::with_aead(key, reader)
+    }
+}
+
+impl<R: AsyncRead + Unpin, A: Aead> AsyncStreamDecryptor<R, A> {
+    /// Like [`AsyncStreamDecryptor::new`], but uses the algorithm `A`.
+    pub fn with_aead(key: &[u8], reader: R) -> Result<Self> {
+        Ok(Self {
+            reader,
+            opener: Opener::new(key)?,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin, A: Aead> AsyncRead for AsyncStreamDecryptor<R, A> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let Self { reader, opener } = self.get_mut();
+        loop {
+            if buf.is_empty() || opener.has_plaintext() {
+                return Poll::Ready(Ok(opener.read_plaintext(buf)));
+            }
+            if opener.is_finished() {
+                return Poll::Ready(Ok(0));
+            }
+            opener.check_failed().map_err(io_error)?;
+
+            let read = ready!(Pin::new(&mut *reader).poll_read(cx, opener.unfilled()))?;
+            if read == 0 {
+                opener.end_of_input().map_err(io_error)?;
+            } else {
+                opener.advance(read).map_err(io_error)?;
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Encrypted exports of a local database.
+//!
+//! When rotating the key of its local database, a client first writes out a
+//! plaintext export and then re-imports it under the new key. The export is
+//! encrypted as a chunked [`StreamEncryptor`] stream, under a key derived from
+//! the account's backup key or master key, so that it is never stored in the
+//! clear and any modification of the file is detected on import.
+//!
+//! Exports of large databases can be interrupted and continued with
+//! [`resume_database_export`], using the [`StreamCheckpoint`] returned by
+//! [`StreamEncryptor::pause`]. Decrypt the result with a
+//! [`StreamDecryptor`](crate::StreamDecryptor).
+//!
+//! If the plaintext has to be written to disk, for example when the database
+//! library can only export to a file, [`export_database_file`] encrypts it and
+//! then removes it with [`secure_delete_file`].
+
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use rand_core::{CryptoRng, RngCore};
+use sha2::Sha256;
+
+use crate::aead_stream::{StreamCheckpoint, StreamEncryptor, STREAM_KEY_SIZE};
+use crate::secure_delete::secure_delete_file;
+use crate::{Error, Result};
+
+const KEY_DERIVATION_INFO: &[u8] = b"20241016_SIGNAL_LOCAL_DATABASE_EXPORT";
+
+/// Derives the key for encrypting database exports from a 32-byte backup key
+/// or master key.
+pub fn derive_database_export_key(root_key: &[u8]) -> Result<[u8; STREAM_KEY_SIZE]> {
+    if root_key.len() != 32 {
+        return Err(Error::InvalidKeySize);
+    }
+    let mut key = [0; STREAM_KEY_SIZE];
+    hkdf::Hkdf::<Sha256>::new(None, root_key)
+        .expand(KEY_DERIVATION_INFO, &mut key)
+        .expect("valid output length");
+    Ok(key)
+}
+
+/// Encrypts the plaintext export at `plaintext_path` into `output`, then
+/// removes the plaintext with [`secure_delete_file`].
+///
+/// `output` is flushed before the plaintext is removed. If anything fails
+/// before then, the plaintext is left in place so the export can be retried.
+pub fn export_database_file<W: Write>(
+    key: &[u8],
+    plaintext_path: impl AsRef<Path>,
+    output: W,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> std::io::Result<W> {
+    let plaintext_path = plaintext_path.as_ref();
+    let mut input = File::open(plaintext_path)?;
+    let mut encryptor = StreamEncryptor::new(key, output, rng)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    std::io::copy(&mut input, &mut encryptor)?;
+    let mut output = encryptor.finish()?;
+    output.flush()?;
+    drop(input);
+
+    secure_delete_file(plaintext_path, rng)?;
+    Ok(output)
+}
+
+/// Output that can be cut short, like [`std::fs::File::set_len`].
+pub trait SetLen {
+    fn set_len(&mut self, len: u64) -> std::io::Result<()>;
+}
+
+impl SetLen for std::fs::File {
+    fn set_len(&mut self, len: u64) -> std::io::Result<()> {
+        std::fs::File::set_len(self, len)
+    }
+}
+
+impl SetLen for &std::fs::File {
+    fn set_len(&mut self, len: u64) -> std::io::Result<()> {
+        std::fs::File::set_len(self, len)
+    }
+}
+
+impl SetLen for Cursor<Vec<u8>> {
+    fn set_len(&mut self, len: u64) -> std::io::Result<()> {
+        let len = usize::try_from(len).map_err(|_| std::io::ErrorKind::InvalidInput)?;
+        self.get_mut().resize(len, 0);
+        Ok(())
+    }
+}
+
+/// Continues a paused export.
+///
+/// Discards anything in `output` past the end of the paused stream, which may
+/// have been sealed under a key that must not be used again, and positions
+/// `output` there and `input` at the first plaintext byte that still needs to
+/// be written to the returned encryptor. The caller then copies the rest of
+/// `input` and calls [`StreamEncryptor::finish`] as usual.
+pub fn resume_database_export<R: Read + Seek, W: Write + Seek + SetLen>(
+    key: &[u8],
+    checkpoint: &StreamCheckpoint,
+    input: &mut R,
+    mut output: W,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> std::io::Result<StreamEncryptor<W>> {
+    let ciphertext_offset = checkpoint.ciphertext_offset();
+    if output.seek(SeekFrom::End(0))? < ciphertext_offset {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "output is shorter than the checkpoint",
+        ));
+    }
+    output.set_len(ciphertext_offset)?;
+    output.seek(SeekFrom::Start(ciphertext_offset))?;
+    input.seek(SeekFrom::Start(checkpoint.plaintext_offset()))?;
+    StreamEncryptor::resume(key, checkpoint, output, rng)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+}
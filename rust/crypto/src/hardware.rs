@@ -0,0 +1,100 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Reporting on hardware acceleration for the primitives used here.
+//!
+//! The `aes`, `ghash`, and `polyval` crates check the CPU at runtime and use
+//! hardware instructions when they're available, falling back to portable
+//! constant-time implementations otherwise. This applies to AES-256-GCM here
+//! and to AES-256-GCM-SIV in the crates that use `aes-gcm-siv`. The functions
+//! in this module make the same checks, so callers can find out which
+//! implementation is being used, and [`preferred_aead`] picks an algorithm
+//! that will be fast on the current device.
+//!
+//! On 64-bit ARM, the accelerated implementations are only compiled in when
+//! building with `--cfg aes_armv8 --cfg polyval_armv8`, as the Android and iOS
+//! builds do.
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(cpuid_aes, "aes");
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+cpufeatures::new!(cpuid_clmul, "pclmulqdq");
+
+// On ARMv8, "aes" covers both the AES and the PMULL instructions.
+#[cfg(target_arch = "aarch64")]
+cpufeatures::new!(cpuid_aes, "aes");
+
+/// Which implementation of a primitive is in use.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Implementation {
+    /// Uses dedicated CPU instructions (AES-NI, CLMUL, or ARMv8 AES/PMULL).
+    Hardware,
+    /// Uses portable, constant-time software.
+    Portable,
+}
+
+/// The implementations in use for the building blocks of the AES-based AEADs.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ActiveImplementations {
+    /// The AES block cipher.
+    pub aes: Implementation,
+    /// Carry-less multiplication, used by GHASH (for AES-GCM) and POLYVAL
+    /// (for AES-GCM-SIV).
+    pub polynomial_hash: Implementation,
+}
+
+/// An AEAD that can be selected based on hardware support.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AeadAlgorithm {
+    /// [`crate::Aes256Gcm`].
+    Aes256Gcm,
+    /// [`crate::XChaCha20Poly1305`].
+    XChaCha20Poly1305,
+}
+
+fn implementation(available: bool) -> Implementation {
+    if available {
+        Implementation::Hardware
+    } else {
+        Implementation::Portable
+    }
+}
+
+/// Returns the implementations selected for the current CPU.
+///
+/// The CPU is only checked once; later calls are cheap.
+pub fn active_implementations() -> ActiveImplementations {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    let (aes, clmul) = (cpuid_aes::get(), cpuid_clmul::get());
+
+    #[cfg(target_arch = "aarch64")]
+    let (aes, clmul) = (
+        cfg!(aes_armv8) && cpuid_aes::get(),
+        cfg!(polyval_armv8) && cpuid_aes::get(),
+    );
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    let (aes, clmul) = (false, false);
+
+    ActiveImplementations {
+        aes: implementation(aes),
+        polynomial_hash: implementation(clmul),
+    }
+}
+
+/// Returns the AEAD expected to be fastest on the current CPU.
+///
+/// AES-256-GCM is preferred whenever both AES and carry-less multiplication
+/// are accelerated; otherwise XChaCha20-Poly1305 is both faster and avoids
+/// relying on the slower portable AES.
+pub fn preferred_aead() -> AeadAlgorithm {
+    match active_implementations() {
+        ActiveImplementations {
+            aes: Implementation::Hardware,
+            polynomial_hash: Implementation::Hardware,
+        } => AeadAlgorithm::Aes256Gcm,
+        _ => AeadAlgorithm::XChaCha20Poly1305,
+    }
+}
@@ -6,14 +6,33 @@
 #![deny(clippy::unwrap_used)]
 
 mod error;
+mod hardware;
 mod hash;
 
+mod aead;
+mod aead_stream;
 mod aes_cbc;
 mod aes_ctr;
 mod aes_gcm;
+mod database_export;
+mod secure_delete;
+mod sticker;
 
+pub use aead::{Aead, Aes256Gcm, XChaCha20Poly1305, AEAD_TAG_SIZE};
+pub use aead_stream::{
+    encrypted_len, AsyncStreamDecryptor, AsyncStreamEncryptor, StreamCheckpoint, StreamDecryptor,
+    StreamEncryptor, STREAM_CHUNK_SIZE, STREAM_KEY_SIZE, STREAM_SALT_SIZE,
+};
 pub use aes_cbc::{aes_256_cbc_decrypt, aes_256_cbc_encrypt, DecryptionError, EncryptionError};
 pub use aes_ctr::Aes256Ctr32;
 pub use aes_gcm::{Aes256GcmDecryption, Aes256GcmEncryption};
+pub use database_export::{
+    derive_database_export_key, export_database_file, resume_database_export, SetLen,
+};
 pub use error::{Error, Result};
+pub use hardware::{
+    active_implementations, preferred_aead, ActiveImplementations, AeadAlgorithm, Implementation,
+};
 pub use hash::{CryptographicHash, CryptographicMac};
+pub use secure_delete::{overwrite_file, secure_delete_file};
+pub use sticker::{StickerPackKeys, STICKER_PACK_KEY_SIZE};
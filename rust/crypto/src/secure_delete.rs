@@ -0,0 +1,117 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Best-effort secure deletion of files containing key material.
+//!
+//! Exported keys and plaintext database exports sometimes have to pass
+//! through the filesystem. [`secure_delete_file`] overwrites such a file
+//! before removing it, so that its contents don't linger in blocks the
+//! filesystem has merely marked as free.
+//!
+//! # Guarantees
+//!
+//! The file's contents are overwritten in place with random bytes, flushed to
+//! the device with [`File::sync_all`], truncated, and then unlinked. That
+//! prevents recovery with ordinary undelete tools on filesystems that update
+//! blocks in place. It does **not** make the old contents unrecoverable when:
+//!
+//! - the filesystem is copy-on-write or log-structured (APFS, Btrfs, ZFS,
+//!   F2FS), so the overwrite lands in new blocks;
+//! - the storage is flash with wear leveling, as on practically every phone
+//!   and SSD;
+//! - the file has other hard links, has been snapshotted or backed up, or is
+//!   still open in another process.
+//!
+//! On such storage the only reliable protection is not to write the plaintext
+//! at all: keep it encrypted under a key held elsewhere, such as the platform
+//! keystore, and destroy that key instead. Random bytes are used rather than
+//! zeros so that filesystems that compress or deduplicate blocks still write
+//! the full length.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek as _, SeekFrom, Write as _};
+use std::path::Path;
+
+use rand_core::{CryptoRng, RngCore};
+
+const OVERWRITE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Overwrites the entire contents of `file` with random bytes and flushes
+/// them to the device.
+///
+/// The file's length is unchanged and its position is left at the end. See
+/// the [module documentation](self) for what this does and does not
+/// guarantee.
+pub fn overwrite_file(file: &mut File, rng: &mut (impl RngCore + CryptoRng)) -> io::Result<()> {
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(0))?;
+
+    let mut chunk = vec![0; OVERWRITE_CHUNK_SIZE];
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk_len = usize::try_from(remaining).map_or(chunk.len(), |r| r.min(chunk.len()));
+        rng.fill_bytes(&mut chunk[..chunk_len]);
+        file.write_all(&chunk[..chunk_len])?;
+        remaining -= chunk_len as u64;
+    }
+    file.sync_all()
+}
+
+/// Overwrites, truncates, and removes the regular file at `path`.
+///
+/// Symbolic links and other non-regular files are rejected with
+/// [`io::ErrorKind::InvalidInput`] rather than followed. On Unix the file is
+/// opened with `O_NOFOLLOW` and the opened handle is checked, so swapping in a
+/// symbolic link after the check has no effect. See the [module
+/// documentation](self) for what this does and does not guarantee.
+pub fn secure_delete_file(
+    path: impl AsRef<Path>,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> io::Result<()> {
+    let path = path.as_ref();
+    let not_a_regular_file = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only regular files can be securely deleted",
+        )
+    };
+    if !std::fs::symlink_metadata(path)?.file_type().is_file() {
+        return Err(not_a_regular_file());
+    }
+
+    let mut file = open_without_following_links(path).map_err(|e| {
+        #[cfg(unix)]
+        if e.raw_os_error() == Some(libc::ELOOP) {
+            return not_a_regular_file();
+        }
+        e
+    })?;
+    if !file.metadata()?.file_type().is_file() {
+        return Err(not_a_regular_file());
+    }
+
+    overwrite_file(&mut file, rng)?;
+    file.set_len(0)?;
+    file.sync_all()?;
+    drop(file);
+
+    std::fs::remove_file(path)
+}
+
+#[cfg(unix)]
+fn open_without_following_links(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt as _;
+    // O_NONBLOCK keeps a FIFO swapped in after the check from blocking the
+    // open; it has no effect on regular files.
+    OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn open_without_following_links(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).open(path)
+}
@@ -0,0 +1,153 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Encryption for sticker packs.
+//!
+//! Every sticker pack has a random 32-byte pack key, shared in the pack's URL.
+//! An AES-256 key and an HMAC-SHA256 key are derived from it with HKDF, and
+//! both the pack manifest and each individual sticker are encrypted with them
+//! in the same format as attachments:
+//!
+//! ```text
+//! IV (16 bytes) || AES-256-CBC ciphertext (PKCS#7 padded) || HMAC-SHA256 (32 bytes)
+//! ```
+//!
+//! where the MAC covers the IV and the ciphertext.
+
+use hmac::{Hmac, Mac as _};
+use rand_core::{CryptoRng, RngCore};
+use sha2::Sha256;
+use subtle::ConstantTimeEq as _;
+
+use crate::{aes_256_cbc_decrypt, aes_256_cbc_encrypt, Error, Result};
+
+/// The size of a sticker pack key.
+pub const STICKER_PACK_KEY_SIZE: usize = 32;
+
+const KEY_DERIVATION_INFO: &[u8] = b"Sticker Pack";
+const IV_SIZE: usize = 16;
+const MAC_SIZE: usize = 32;
+const AES_BLOCK_SIZE: usize = 16;
+
+/// The keys used to encrypt everything in one sticker pack.
+#[derive(Clone)]
+pub struct StickerPackKeys {
+    aes_key: [u8; 32],
+    mac_key: [u8; 32],
+}
+
+impl StickerPackKeys {
+    /// Derives the encryption keys from a pack key.
+    pub fn derive(pack_key: &[u8]) -> Result<Self> {
+        if pack_key.len() != STICKER_PACK_KEY_SIZE {
+            return Err(Error::InvalidKeySize);
+        }
+        let mut derived = [0; 64];
+        hkdf::Hkdf::<Sha256>::new(None, pack_key)
+            .expand(KEY_DERIVATION_INFO, &mut derived)
+            .expect("valid output length");
+        let (aes_key, mac_key) = derived.split_at(32);
+        Ok(Self {
+            aes_key: aes_key.try_into().expect("correct length"),
+            mac_key: mac_key.try_into().expect("correct length"),
+        })
+    }
+
+    /// Encrypts a serialized pack manifest.
+    pub fn encrypt_manifest<R: RngCore + CryptoRng>(
+        &self,
+        manifest: &[u8],
+        rng: &mut R,
+    ) -> Vec<u8> {
+        self.encrypt(manifest, rng)
+    }
+
+    /// Decrypts a pack manifest, returning the serialized manifest.
+    pub fn decrypt_manifest(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt(ciphertext)
+    }
+
+    /// Encrypts the image data for a single sticker.
+    pub fn encrypt_sticker<R: RngCore + CryptoRng>(&self, sticker: &[u8], rng: &mut R) -> Vec<u8> {
+        self.encrypt(sticker, rng)
+    }
+
+    /// Decrypts the image data for a single sticker.
+    pub fn decrypt_sticker(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt(ciphertext)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(&self, plaintext: &[u8], rng: &mut R) -> Vec<u8> {
+        let mut iv = [0; IV_SIZE];
+        rng.fill_bytes(&mut iv);
+        self.encrypt_with_iv(plaintext, &iv)
+    }
+
+    fn encrypt_with_iv(&self, plaintext: &[u8], iv: &[u8; IV_SIZE]) -> Vec<u8> {
+        let ciphertext =
+            aes_256_cbc_encrypt(plaintext, &self.aes_key, iv).expect("key and IV are valid");
+        let mut result = Vec::with_capacity(IV_SIZE + ciphertext.len() + MAC_SIZE);
+        result.extend_from_slice(iv);
+        result.extend_from_slice(&ciphertext);
+        let mac = self.mac(&result);
+        result.extend_from_slice(&mac);
+        result
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < IV_SIZE + AES_BLOCK_SIZE + MAC_SIZE {
+            return Err(Error::InvalidInputSize);
+        }
+        let (iv_and_ciphertext, their_mac) = ciphertext.split_at(ciphertext.len() - MAC_SIZE);
+        if !bool::from(self.mac(iv_and_ciphertext).ct_eq(their_mac)) {
+            return Err(Error::InvalidTag);
+        }
+        let (iv, ciphertext) = iv_and_ciphertext.split_at(IV_SIZE);
+        aes_256_cbc_decrypt(ciphertext, &self.aes_key, iv).map_err(|_| Error::InvalidInputSize)
+    }
+
+    fn mac(&self, data: &[u8]) -> [u8; MAC_SIZE] {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.mac_key).expect("HMAC accepts any key size");
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+
+    use super::*;
+
+    const PACK_KEY: [u8; 32] =
+        hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+
+    #[test]
+    fn derivation_matches_reference() {
+        let keys = StickerPackKeys::derive(&PACK_KEY).expect("valid key");
+        assert_eq!(
+            keys.aes_key,
+            hex!("64f46fd204462c27c05dd6f5db863edd9f286af100e5e9704efdd36ce89583ff")
+        );
+        assert_eq!(
+            keys.mac_key,
+            hex!("c483e5dfa58b86e9a7a2245b775677f5f6864b94f2302dcec6636f713de6de65")
+        );
+    }
+
+    #[test]
+    fn encryption_matches_reference() {
+        let keys = StickerPackKeys::derive(&PACK_KEY).expect("valid key");
+        let encrypted = keys.encrypt_with_iv(
+            &hex!("0a0c537469636b6572207465737412065369676e616c"),
+            &hex!("a0a1a2a3a4a5a6a7a8a9aaabacadaeaf"),
+        );
+        assert_eq!(
+            hex::encode(encrypted),
+            "a0a1a2a3a4a5a6a7a8a9aaabacadaeafe59e8a6ec85155a7409842921faedae3e838b39ea9d90fd6f7f4bea2c7886b8008ca36567159bb217b42b804a3cc6d3bff13dde621f6311227abc8b814187d9b"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::io::{Read, Write};
+
+use futures::executor::block_on;
+use futures::{AsyncReadExt, AsyncWriteExt};
+use rand::rngs::OsRng;
+use rand::Rng;
+use signal_crypto::{
+    encrypted_len, AsyncStreamDecryptor, AsyncStreamEncryptor, StreamDecryptor, StreamEncryptor,
+    STREAM_CHUNK_SIZE, STREAM_SALT_SIZE,
+};
+
+const KEY: [u8; 32] = [0x42; 32];
+const TAG_SIZE: usize = 16;
+
+fn encrypt(key: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    let mut encryptor = StreamEncryptor::new(key, Vec::new(), &mut OsRng).expect("valid key");
+    // Write in uneven pieces to exercise buffering.
+    for piece in plaintext.chunks(1000) {
+        encryptor.write_all(piece).expect("can write");
+    }
+    encryptor.finish().expect("can finish")
+}
+
+fn decrypt(key: &[u8], ciphertext: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decryptor = StreamDecryptor::new(key, ciphertext).expect("valid key");
+    let mut plaintext = Vec::new();
+    decryptor.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+fn random_plaintext(len: usize) -> Vec<u8> {
+    let mut plaintext = vec![0; len];
+    rand::thread_rng().fill(&mut plaintext[..]);
+    plaintext
+}
+
+#[test]
+fn aead_stream_round_trip() {
+    for len in [
+        0,
+        1,
+        STREAM_CHUNK_SIZE - 1,
+        STREAM_CHUNK_SIZE,
+        STREAM_CHUNK_SIZE + 1,
+        3 * STREAM_CHUNK_SIZE + 17,
+    ] {
+        let plaintext = random_plaintext(len);
+        let ciphertext = encrypt(&KEY, &plaintext);
+        assert_eq!(ciphertext.len() as u64, encrypted_len(len as u64), "{len}");
+        assert_eq!(
+            decrypt(&KEY, &ciphertext).expect("valid"),
+            plaintext,
+            "{len}"
+        );
+    }
+}
+
+#[test]
+fn aead_stream_uses_fresh_salt() {
+    let plaintext = random_plaintext(100);
+    assert_ne!(encrypt(&KEY, &plaintext), encrypt(&KEY, &plaintext));
+}
+
+#[test]
+fn aead_stream_rejects_wrong_key() {
+    let ciphertext = encrypt(&KEY, b"plaintext");
+    let err = decrypt(&[0x43; 32], &ciphertext).expect_err("wrong key");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    assert!(matches!(
+        StreamDecryptor::new(&KEY[..16], &ciphertext[..]),
+        Err(signal_crypto::Error::InvalidKeySize)
+    ));
+}
+
+#[test]
+fn aead_stream_rejects_modification() {
+    let plaintext = random_plaintext(2 * STREAM_CHUNK_SIZE + 5);
+    let ciphertext = encrypt(&KEY, &plaintext);
+
+    for position in [
+        0,
+        STREAM_SALT_SIZE,
+        STREAM_SALT_SIZE + STREAM_CHUNK_SIZE + 1,
+    ] {
+        let mut modified = ciphertext.clone();
+        modified[position] ^= 1;
+        let err = decrypt(&KEY, &modified).expect_err("modified");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{position}");
+    }
+}
+
+#[test]
+fn aead_stream_rejects_truncation() {
+    let plaintext = random_plaintext(2 * STREAM_CHUNK_SIZE + 5);
+    let ciphertext = encrypt(&KEY, &plaintext);
+    let sealed_chunk_size = STREAM_CHUNK_SIZE + TAG_SIZE;
+
+    for len in [
+        0,
+        STREAM_SALT_SIZE - 1,
+        STREAM_SALT_SIZE,
+        // Cut at a chunk boundary, so the last remaining chunk isn't marked
+        // as the last one.
+        STREAM_SALT_SIZE + sealed_chunk_size,
+        STREAM_SALT_SIZE + 2 * sealed_chunk_size,
+        ciphertext.len() - 1,
+    ] {
+        let err = decrypt(&KEY, &ciphertext[..len]).expect_err("truncated");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{len}");
+    }
+}
+
+#[test]
+fn aead_stream_rejects_unfinished_stream() {
+    let mut ciphertext = Vec::new();
+    let mut encryptor = StreamEncryptor::new(&KEY, &mut ciphertext, &mut OsRng).expect("valid key");
+    encryptor
+        .write_all(&random_plaintext(STREAM_CHUNK_SIZE + 1))
+        .expect("can write");
+    encryptor.flush().expect("can flush");
+    drop(encryptor);
+
+    let err = decrypt(&KEY, &ciphertext).expect_err("unfinished");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn aead_stream_async_round_trip() {
+    let plaintext = random_plaintext(2 * STREAM_CHUNK_SIZE + 123);
+
+    let ciphertext = block_on(async {
+        let mut encryptor =
+            AsyncStreamEncryptor::new(&KEY, futures::io::Cursor::new(Vec::new()), &mut OsRng)
+                .expect("valid key");
+        encryptor.write_all(&plaintext).await.expect("can write");
+        encryptor.close().await.expect("can close");
+        encryptor.into_inner().into_inner()
+    });
+
+    // The sync and async formats are the same.
+    assert_eq!(decrypt(&KEY, &ciphertext).expect("valid"), plaintext);
+
+    let decrypted = block_on(async {
+        let mut decryptor = AsyncStreamDecryptor::new(&KEY, &ciphertext[..]).expect("valid key");
+        let mut decrypted = Vec::new();
+        decryptor.read_to_end(&mut decrypted).await.expect("valid");
+        decrypted
+    });
+    assert_eq!(decrypted, plaintext);
+}
@@ -168,3 +168,19 @@ fn aes_gcm_smoke_test() -> Result<(), signal_crypto::Error> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn aes_gcm_hardware_detection_matches_std() {
+    use signal_crypto::Implementation;
+
+    let active = signal_crypto::active_implementations();
+    assert_eq!(
+        active.aes == Implementation::Hardware,
+        std::arch::is_x86_feature_detected!("aes")
+    );
+    assert_eq!(
+        active.polynomial_hash == Implementation::Hardware,
+        std::arch::is_x86_feature_detected!("pclmulqdq")
+    );
+}
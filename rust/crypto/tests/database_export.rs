@@ -0,0 +1,201 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::io::{Cursor, Read, Write};
+
+use rand::rngs::OsRng;
+use rand::Rng;
+use signal_crypto::{
+    derive_database_export_key, export_database_file, resume_database_export, StreamCheckpoint,
+    StreamDecryptor, StreamEncryptor, STREAM_CHUNK_SIZE,
+};
+
+fn random_plaintext(len: usize) -> Vec<u8> {
+    let mut plaintext = vec![0; len];
+    rand::thread_rng().fill(&mut plaintext[..]);
+    plaintext
+}
+
+fn decrypt(key: &[u8], ciphertext: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decryptor = StreamDecryptor::new(key, ciphertext).expect("valid key");
+    let mut plaintext = Vec::new();
+    decryptor.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+#[test]
+fn database_export_key_derivation() {
+    let key = derive_database_export_key(&[0x42; 32]).expect("valid key");
+    assert_eq!(
+        key,
+        derive_database_export_key(&[0x42; 32]).expect("valid key")
+    );
+    assert_ne!(
+        key,
+        derive_database_export_key(&[0x43; 32]).expect("valid key")
+    );
+    assert!(matches!(
+        derive_database_export_key(&[0x42; 16]),
+        Err(signal_crypto::Error::InvalidKeySize)
+    ));
+}
+
+#[test]
+fn database_export_pause_and_resume() {
+    let key = derive_database_export_key(&[0x42; 32]).expect("valid key");
+    let database = random_plaintext(3 * STREAM_CHUNK_SIZE + 100);
+    let mut input = Cursor::new(&database[..]);
+
+    // Write part of the way into the second chunk before pausing.
+    let mut encryptor =
+        StreamEncryptor::new(&key, Cursor::new(Vec::new()), &mut OsRng).expect("valid key");
+    let mut partial = vec![0; STREAM_CHUNK_SIZE + STREAM_CHUNK_SIZE / 2];
+    input.read_exact(&mut partial).expect("can read");
+    encryptor.write_all(&partial).expect("can write");
+    let (checkpoint, output) = encryptor.pause().expect("can pause");
+    assert_eq!(checkpoint.plaintext_offset(), STREAM_CHUNK_SIZE as u64);
+    assert_eq!(
+        output.get_ref().len() as u64,
+        checkpoint.ciphertext_offset()
+    );
+
+    // The checkpoint is stored separately from the output.
+    let checkpoint =
+        StreamCheckpoint::deserialize(&checkpoint.serialize()).expect("valid checkpoint");
+
+    let mut encryptor = resume_database_export(&key, &checkpoint, &mut input, output, &mut OsRng)
+        .expect("can resume");
+    std::io::copy(&mut input, &mut encryptor).expect("can copy");
+    let ciphertext = encryptor.finish().expect("can finish").into_inner();
+
+    assert_eq!(decrypt(&key, &ciphertext).expect("valid"), database);
+}
+
+#[test]
+fn database_export_resume_twice_from_one_checkpoint() {
+    let key = derive_database_export_key(&[0x42; 32]).expect("valid key");
+    let database = random_plaintext(3 * STREAM_CHUNK_SIZE + 100);
+
+    let mut encryptor =
+        StreamEncryptor::new(&key, Cursor::new(Vec::new()), &mut OsRng).expect("valid key");
+    encryptor
+        .write_all(&database[..STREAM_CHUNK_SIZE + 1])
+        .expect("can write");
+    let (checkpoint, output) = encryptor.pause().expect("can pause");
+    let checkpoint_len = output.get_ref().len();
+
+    // The first attempt gets another chunk out before it is interrupted.
+    let mut input = Cursor::new(&database[..]);
+    let mut encryptor = resume_database_export(&key, &checkpoint, &mut input, output, &mut OsRng)
+        .expect("can resume");
+    let mut chunk = vec![0; STREAM_CHUNK_SIZE + 1];
+    input.read_exact(&mut chunk).expect("can read");
+    encryptor.write_all(&chunk).expect("can write");
+    let (_, output) = encryptor.pause().expect("can pause");
+    let first_attempt = output.get_ref()[checkpoint_len..].to_vec();
+
+    let mut encryptor = resume_database_export(&key, &checkpoint, &mut input, output, &mut OsRng)
+        .expect("can resume");
+    std::io::copy(&mut input, &mut encryptor).expect("can copy");
+    let ciphertext = encryptor.finish().expect("can finish").into_inner();
+
+    // Both attempts start with the same 16-byte rekeying marker, but pick
+    // different salts, so nothing after it is sealed under the same key twice.
+    let second_attempt = &ciphertext[checkpoint_len..][..first_attempt.len()];
+    assert_eq!(first_attempt[..16], second_attempt[..16]);
+    assert_ne!(first_attempt[16..48], second_attempt[16..48]);
+    assert_eq!(decrypt(&key, &ciphertext).expect("valid"), database);
+}
+
+#[test]
+fn database_export_rejects_short_output() {
+    let key = derive_database_export_key(&[0x42; 32]).expect("valid key");
+    let database = random_plaintext(STREAM_CHUNK_SIZE + 1);
+
+    let mut encryptor =
+        StreamEncryptor::new(&key, Cursor::new(Vec::new()), &mut OsRng).expect("valid key");
+    encryptor.write_all(&database).expect("can write");
+    let (checkpoint, mut output) = encryptor.pause().expect("can pause");
+    output.get_mut().pop();
+
+    let err = resume_database_export(
+        &key,
+        &checkpoint,
+        &mut Cursor::new(&database[..]),
+        output,
+        &mut OsRng,
+    )
+    .err()
+    .expect("output was cut short");
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn database_export_rejects_wrong_checkpoint() {
+    let key = derive_database_export_key(&[0x42; 32]).expect("valid key");
+    let database = random_plaintext(2 * STREAM_CHUNK_SIZE + 100);
+
+    let mut encryptor =
+        StreamEncryptor::new(&key, Cursor::new(Vec::new()), &mut OsRng).expect("valid key");
+    encryptor
+        .write_all(&database[..STREAM_CHUNK_SIZE + 1])
+        .expect("can write");
+    let (_checkpoint, output) = encryptor.pause().expect("can pause");
+
+    // A checkpoint from a different export has a different salt.
+    let (other_checkpoint, _) = {
+        let mut other = StreamEncryptor::new(&key, Vec::new(), &mut OsRng).expect("valid key");
+        other
+            .write_all(&database[..STREAM_CHUNK_SIZE + 1])
+            .expect("can write");
+        other.pause().expect("can pause")
+    };
+
+    let mut input = Cursor::new(&database[..]);
+    let mut encryptor =
+        resume_database_export(&key, &other_checkpoint, &mut input, output, &mut OsRng)
+            .expect("can resume");
+    std::io::copy(&mut input, &mut encryptor).expect("can copy");
+    let ciphertext = encryptor.finish().expect("can finish").into_inner();
+
+    let err = decrypt(&key, &ciphertext).expect_err("mismatched checkpoint");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    assert!(matches!(
+        StreamCheckpoint::deserialize(&[0; 12]),
+        Err(signal_crypto::Error::InvalidInputSize)
+    ));
+}
+
+#[test]
+fn database_export_from_file_removes_plaintext() {
+    let key = derive_database_export_key(&[0x42; 32]).expect("valid key");
+    let database = random_plaintext(2 * STREAM_CHUNK_SIZE + 100);
+    let path = std::env::temp_dir().join(format!(
+        "signal-crypto-database-export-{:016x}",
+        OsRng.gen::<u64>()
+    ));
+    std::fs::write(&path, &database).expect("can write");
+
+    let ciphertext = export_database_file(&key, &path, Vec::new(), &mut OsRng).expect("can export");
+    assert_eq!(decrypt(&key, &ciphertext).expect("valid"), database);
+    assert!(!path.exists());
+}
+
+#[test]
+fn database_export_from_file_keeps_plaintext_on_failure() {
+    let database = random_plaintext(100);
+    let path = std::env::temp_dir().join(format!(
+        "signal-crypto-database-export-{:016x}",
+        OsRng.gen::<u64>()
+    ));
+    std::fs::write(&path, &database).expect("can write");
+
+    let err =
+        export_database_file(&[0x42; 16], &path, Vec::new(), &mut OsRng).expect_err("invalid key");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(std::fs::read(&path).expect("still present"), database);
+    std::fs::remove_file(&path).expect("can remove");
+}
@@ -0,0 +1,85 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::io::Read as _;
+use std::path::PathBuf;
+
+use rand::rngs::OsRng;
+use rand::Rng as _;
+use signal_crypto::{overwrite_file, secure_delete_file};
+
+fn temp_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "signal-crypto-secure-delete-{:016x}",
+        OsRng.gen::<u64>()
+    ))
+}
+
+#[test]
+fn overwrite_keeps_length_and_replaces_contents() {
+    let path = temp_path();
+    let original = vec![0x42; 100_000];
+    std::fs::write(&path, &original).expect("can write");
+
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .expect("can open");
+    overwrite_file(&mut file, &mut OsRng).expect("can overwrite");
+    drop(file);
+
+    let mut contents = Vec::new();
+    std::fs::File::open(&path)
+        .expect("can open")
+        .read_to_end(&mut contents)
+        .expect("can read");
+    assert_eq!(contents.len(), original.len());
+    assert_ne!(contents, original);
+
+    secure_delete_file(&path, &mut OsRng).expect("can delete");
+    assert!(!path.exists());
+}
+
+#[test]
+fn rejects_directories_and_missing_files() {
+    let path = temp_path();
+    assert_eq!(
+        secure_delete_file(&path, &mut OsRng)
+            .expect_err("missing")
+            .kind(),
+        std::io::ErrorKind::NotFound
+    );
+
+    std::fs::create_dir(&path).expect("can create");
+    assert_eq!(
+        secure_delete_file(&path, &mut OsRng)
+            .expect_err("directory")
+            .kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    std::fs::remove_dir(&path).expect("can remove");
+}
+
+#[cfg(unix)]
+#[test]
+fn rejects_symlinks_without_touching_the_target() {
+    let target = temp_path();
+    let link = temp_path();
+    let original = vec![0x42; 1000];
+    std::fs::write(&target, &original).expect("can write");
+    std::os::unix::fs::symlink(&target, &link).expect("can link");
+
+    assert_eq!(
+        secure_delete_file(&link, &mut OsRng)
+            .expect_err("symlink")
+            .kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    assert_eq!(std::fs::read(&target).expect("can read"), original);
+
+    std::fs::remove_file(&link).expect("can remove");
+    std::fs::remove_file(&target).expect("can remove");
+}
@@ -0,0 +1,59 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use hex_literal::hex;
+use signal_crypto::{Error, StickerPackKeys};
+
+const PACK_KEY: [u8; 32] = hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+const MANIFEST: [u8; 22] = hex!("0a0c537469636b6572207465737412065369676e616c");
+const ENCRYPTED_MANIFEST: [u8; 80] = hex!(
+    "a0a1a2a3a4a5a6a7a8a9aaabacadaeaf"
+    "e59e8a6ec85155a7409842921faedae3e838b39ea9d90fd6f7f4bea2c7886b80"
+    "08ca36567159bb217b42b804a3cc6d3bff13dde621f6311227abc8b814187d9b"
+);
+
+#[test]
+fn decrypt_manifest_vector() -> Result<(), Error> {
+    let keys = StickerPackKeys::derive(&PACK_KEY)?;
+    assert_eq!(keys.decrypt_manifest(&ENCRYPTED_MANIFEST)?, MANIFEST);
+    Ok(())
+}
+
+#[test]
+fn sticker_round_trip() -> Result<(), Error> {
+    let keys = StickerPackKeys::derive(&PACK_KEY)?;
+    let sticker = b"RIFF\0\0\0\0WEBPVP8 not really an image";
+    let encrypted = keys.encrypt_sticker(sticker, &mut rand::thread_rng());
+    assert_eq!(keys.decrypt_sticker(&encrypted)?, sticker);
+    Ok(())
+}
+
+#[test]
+fn rejects_tampering_and_wrong_keys() -> Result<(), Error> {
+    let keys = StickerPackKeys::derive(&PACK_KEY)?;
+
+    let mut tampered = ENCRYPTED_MANIFEST;
+    tampered[20] ^= 1;
+    assert!(matches!(
+        keys.decrypt_manifest(&tampered),
+        Err(Error::InvalidTag)
+    ));
+
+    let other_keys = StickerPackKeys::derive(&[0x55; 32])?;
+    assert!(matches!(
+        other_keys.decrypt_manifest(&ENCRYPTED_MANIFEST),
+        Err(Error::InvalidTag)
+    ));
+
+    assert!(matches!(
+        keys.decrypt_sticker(&ENCRYPTED_MANIFEST[..40]),
+        Err(Error::InvalidInputSize)
+    ));
+    assert!(matches!(
+        StickerPackKeys::derive(&PACK_KEY[..16]),
+        Err(Error::InvalidKeySize)
+    ));
+    Ok(())
+}
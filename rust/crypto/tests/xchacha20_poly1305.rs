@@ -0,0 +1,101 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::io::{Read, Write};
+
+use hex_literal::hex;
+use rand::rngs::OsRng;
+use signal_crypto::{Aead, StreamDecryptor, StreamEncryptor, XChaCha20Poly1305};
+
+#[test]
+fn xchacha20_poly1305_kat() -> Result<(), signal_crypto::Error> {
+    // From draft-irtf-cfrg-xchacha-03, appendix A.3.1.
+    let key = hex!("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+    let nonce = hex!("404142434445464748494a4b4c4d4e4f5051525354555657");
+    let aad = hex!("50515253c0c1c2c3c4c5c6c7");
+    let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+    let ciphertext = hex!("bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b4522f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff921f9664c97637da9768812f615c68b13b52e");
+    let tag = hex!("c0875924c1c7987947deafd8780acf49");
+
+    let sealed = XChaCha20Poly1305::encrypt(&key, &nonce, &aad, plaintext)?;
+    assert_eq!(
+        hex::encode(&sealed),
+        hex::encode([&ciphertext[..], &tag].concat())
+    );
+
+    let opened = XChaCha20Poly1305::decrypt(&key, &nonce, &aad, &sealed)?;
+    assert_eq!(opened, plaintext);
+
+    let mut modified = sealed.clone();
+    modified[0] ^= 1;
+    assert!(matches!(
+        XChaCha20Poly1305::decrypt(&key, &nonce, &aad, &modified),
+        Err(signal_crypto::Error::InvalidTag)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn xchacha20_poly1305_checks_sizes() {
+    assert!(matches!(
+        XChaCha20Poly1305::encrypt(&[0; 16], &[0; 24], &[], &[]),
+        Err(signal_crypto::Error::InvalidKeySize)
+    ));
+    assert!(matches!(
+        XChaCha20Poly1305::encrypt(&[0; 32], &[0; 12], &[], &[]),
+        Err(signal_crypto::Error::InvalidNonceSize)
+    ));
+    assert!(matches!(
+        XChaCha20Poly1305::decrypt(&[0; 32], &[0; 24], &[], &[0; 15]),
+        Err(signal_crypto::Error::InvalidInputSize)
+    ));
+}
+
+#[test]
+fn xchacha20_poly1305_random_nonce() -> Result<(), signal_crypto::Error> {
+    let key = [0x42; 32];
+    let first = XChaCha20Poly1305::encrypt_with_random_nonce(&key, b"ad", b"hello", &mut OsRng)?;
+    let second = XChaCha20Poly1305::encrypt_with_random_nonce(&key, b"ad", b"hello", &mut OsRng)?;
+    assert_ne!(first, second);
+    assert_eq!(first.len(), XChaCha20Poly1305::NONCE_SIZE + 5 + 16);
+
+    assert_eq!(
+        XChaCha20Poly1305::decrypt_with_nonce_prefix(&key, b"ad", &first)?,
+        b"hello"
+    );
+    assert!(matches!(
+        XChaCha20Poly1305::decrypt_with_nonce_prefix(&key, b"other ad", &first),
+        Err(signal_crypto::Error::InvalidTag)
+    ));
+    Ok(())
+}
+
+#[test]
+fn xchacha20_poly1305_stream() {
+    let key = [0x42; 32];
+    let plaintext = vec![0x5a; 100_000];
+
+    let mut encryptor =
+        StreamEncryptor::<_, XChaCha20Poly1305>::with_aead(&key, Vec::new(), &mut OsRng)
+            .expect("valid key");
+    encryptor.write_all(&plaintext).expect("can write");
+    let ciphertext = encryptor.finish().expect("can finish");
+
+    let mut decrypted = Vec::new();
+    StreamDecryptor::<_, XChaCha20Poly1305>::with_aead(&key, &ciphertext[..])
+        .expect("valid key")
+        .read_to_end(&mut decrypted)
+        .expect("valid");
+    assert_eq!(decrypted, plaintext);
+
+    // The algorithm is bound into the stream key, so an AES-GCM decryptor
+    // can't read the stream.
+    let err = StreamDecryptor::new(&key, &ciphertext[..])
+        .expect("valid key")
+        .read_to_end(&mut Vec::new())
+        .expect_err("wrong algorithm");
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,226 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Exporting verified search results so they can be audited offline.
+//!
+//! A [`SearchEvidence`] bundles a search request with the response that was
+//! verified for it: the VRF proof, the search and inclusion proofs, the
+//! commitment opening, and the signed tree head. Anyone with the log's
+//! [`PublicConfig`] can check the bundle again with [`SearchEvidence::verify`],
+//! with no access to the service and no monitoring state.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use prost::Message as _;
+
+use crate::store::{LogStore as _, MemoryLogStore};
+use crate::verify::{verify_search_internal, Error};
+use crate::{wire, PublicConfig, SearchRequest, SearchResponse};
+
+/// A search result that has been verified, in a form that can be serialized
+/// and verified again later.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchEvidence {
+    request: SearchRequest,
+    response: SearchResponse,
+    verified_at: SystemTime,
+}
+
+/// The claims established by verifying a [`SearchEvidence`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifiedSearch {
+    pub search_key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub tree_size: u64,
+    /// The tree head timestamp, in milliseconds since the epoch.
+    pub timestamp: i64,
+    /// The root hash of the tree the value was found in.
+    pub root: [u8; 32],
+}
+
+impl SearchEvidence {
+    /// Bundles a search result that was verified at `verified_at`.
+    ///
+    /// Any consistency proofs in the response are removed, since they relate
+    /// the tree head to state only the original client had.
+    pub fn new(
+        request: SearchRequest,
+        mut response: SearchResponse,
+        verified_at: SystemTime,
+    ) -> Self {
+        if let Some(full_tree_head) = &mut response.tree_head {
+            full_tree_head.last.clear();
+            full_tree_head.distinguished.clear();
+        }
+        Self {
+            request,
+            response,
+            verified_at,
+        }
+    }
+
+    /// Fails with [`Error::TimestampOutOfRange`] if the verification time is
+    /// before the epoch or too far after it to be encoded.
+    pub fn serialize(&self) -> Result<Vec<u8>, Error> {
+        let verified_at = self
+            .verified_at
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .and_then(|since_epoch| i64::try_from(since_epoch.as_millis()).ok())
+            .ok_or(Error::TimestampOutOfRange)?;
+        Ok(wire::SearchEvidence {
+            request: Some(self.request.clone()),
+            response: Some(self.response.clone()),
+            verified_at,
+        }
+        .encode_to_vec())
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        let wire::SearchEvidence {
+            request,
+            response,
+            verified_at,
+        } = wire::SearchEvidence::decode(bytes).map_err(|_| Error::InvalidProofElement)?;
+        let verified_at = u64::try_from(verified_at)
+            .map(|millis| UNIX_EPOCH + Duration::from_millis(millis))
+            .map_err(|_| Error::InvalidProofElement)?;
+        Ok(Self {
+            request: request.ok_or(Error::RequiredFieldMissing)?,
+            response: response.ok_or(Error::RequiredFieldMissing)?,
+            verified_at,
+        })
+    }
+
+    pub fn verified_at(&self) -> SystemTime {
+        self.verified_at
+    }
+
+    /// Verifies the bundle against `config`, as of the time it was originally
+    /// verified.
+    pub fn verify(&self, config: &PublicConfig) -> Result<VerifiedSearch, Error> {
+        let mut store = MemoryLogStore::default();
+        verify_search_internal(
+            config,
+            &mut store,
+            &self.request,
+            &self.response,
+            false,
+            self.verified_at,
+        )?;
+
+        let (tree_head, root) = store
+            .get_last_tree_head()?
+            .expect("verification stores the tree head");
+        let value = self
+            .response
+            .value
+            .as_ref()
+            .ok_or(Error::RequiredFieldMissing)?;
+        Ok(VerifiedSearch {
+            search_key: self.request.search_key.clone(),
+            value: value.value.clone(),
+            tree_size: tree_head.tree_size,
+            timestamp: tree_head.timestamp,
+            root,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use ed25519_dalek::VerifyingKey;
+    use hex_literal::hex;
+
+    use super::*;
+    use crate::{vrf, DeploymentMode};
+
+    fn config() -> PublicConfig {
+        PublicConfig {
+            mode: DeploymentMode::ContactMonitoring,
+            signature_key: VerifyingKey::from_bytes(&hex!(
+                "61eae8fe6373577e6473c5bb65a43b4d86190d78b2e5dc48fa6f253253438fb9"
+            ))
+            .unwrap(),
+            vrf_key: vrf::PublicKey::try_from(hex!(
+                "ee964d1552c57c4b8dd9b3f409e6cfd7fb3691966014dbe89f652de7d0a67ca2"
+            ))
+            .unwrap(),
+        }
+    }
+
+    fn evidence() -> SearchEvidence {
+        let request =
+            SearchRequest::decode(hex!("0a10b81b5b821e8d4eec8ec5e6513834a9f31a020804").as_slice())
+                .unwrap();
+        let response =
+            SearchResponse::decode(include_bytes!("../res/kt-search-response.dat").as_slice())
+                .unwrap();
+        let verified_at = UNIX_EPOCH + Duration::from_secs(1724796478);
+        SearchEvidence::new(request, response, verified_at)
+    }
+
+    #[test]
+    fn round_trips_and_verifies() {
+        let evidence = evidence();
+        let restored = SearchEvidence::deserialize(&evidence.serialize().unwrap()).unwrap();
+        assert_eq!(restored, evidence);
+
+        let verified = restored.verify(&config()).unwrap();
+        assert_eq!(verified.search_key, evidence.request.search_key);
+        assert_eq!(
+            Some(&verified.value),
+            evidence.response.value.as_ref().map(|v| &v.value)
+        );
+        let tree_head = evidence
+            .response
+            .tree_head
+            .as_ref()
+            .and_then(|fth| fth.tree_head.as_ref())
+            .unwrap();
+        assert_eq!(verified.tree_size, tree_head.tree_size);
+        assert_eq!(verified.timestamp, tree_head.timestamp);
+    }
+
+    #[test]
+    fn rejects_tampered_evidence() {
+        let mut evidence = evidence();
+        evidence.response.value.as_mut().unwrap().value.push(0);
+        assert_matches!(
+            evidence.verify(&config()),
+            Err(Error::VerificationFailed(_))
+        );
+    }
+
+    #[test]
+    fn rejects_evidence_verified_long_after_the_tree_head() {
+        let mut evidence = evidence();
+        evidence.verified_at += Duration::from_secs(7 * 24 * 60 * 60);
+        assert_matches!(
+            evidence.verify(&config()),
+            Err(Error::VerificationFailed(_))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_bundles() {
+        assert_matches!(
+            SearchEvidence::deserialize(&[]),
+            Err(Error::RequiredFieldMissing)
+        );
+        assert_matches!(
+            SearchEvidence::deserialize(&[0xff]),
+            Err(Error::InvalidProofElement)
+        );
+    }
+
+    #[test]
+    fn rejects_unrepresentable_verification_times() {
+        let mut evidence = evidence();
+        evidence.verified_at = UNIX_EPOCH - Duration::from_secs(1);
+        assert_matches!(evidence.serialize(), Err(Error::TimestampOutOfRange));
+    }
+}
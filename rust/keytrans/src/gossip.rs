@@ -0,0 +1,416 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Cross-checking tree heads obtained out-of-band.
+//!
+//! A log that shows different clients different contents (a "split view")
+//! can only be caught by comparing what those clients have seen. Tree heads
+//! from other users or a gossip endpoint can be checked against the local
+//! state with [`KeyTransparency::merge_gossiped_tree_head`]. Two signed tree
+//! heads of the same size with different roots are proof that the log has
+//! misbehaved.
+//!
+//! A gossiped head that can't be checked right away is kept in the
+//! [`LogStore`] until a tree head of the same size has been verified, whether
+//! that's the log's own tree head, the distinguished head of a request, or
+//! another gossiped head that comes with a consistency proof.
+
+use crate::log::verify_consistency_proof;
+use crate::store::LogStore;
+use crate::verify::{get_hash_proof, verify_tree_head_signature, Error};
+use crate::{KeyTransparency, TreeHead};
+
+/// The number of unchecked gossiped tree heads kept in the store. Once there
+/// are more, the oldest is dropped.
+const MAX_GOSSIPED_TREE_HEADS: usize = 16;
+
+/// A tree head along with the root hash it was signed over.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedTreeHead {
+    pub tree_head: TreeHead,
+    pub root: [u8; 32],
+}
+
+/// The result of [`KeyTransparency::merge_gossiped_tree_head`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GossipOutcome {
+    /// The gossiped head is the same size as the local one and has the same
+    /// root.
+    Matches,
+    /// The gossiped head is consistent with the local one. If it was newer,
+    /// it is now the local head.
+    Consistent,
+    /// The gossiped head is validly signed, but couldn't be compared with the
+    /// local state without a consistency proof. It has been kept in the store
+    /// to be checked later, and can be used as a distinguished tree head for
+    /// the next request to the log.
+    Unchecked,
+}
+
+impl KeyTransparency {
+    /// Checks a tree head obtained out-of-band against the last tree head in
+    /// `store`.
+    ///
+    /// `consistency` is a proof of consistency between the smaller and the
+    /// larger of the two heads, if one was provided along with the gossiped
+    /// head; it may be empty.
+    ///
+    /// Returns [`Error::SplitView`] if the log signed two different roots for
+    /// the same tree size, including a gossiped head kept from an earlier
+    /// call.
+    pub fn merge_gossiped_tree_head(
+        &self,
+        store: &mut dyn LogStore,
+        gossiped: &SignedTreeHead,
+        consistency: &[Vec<u8>],
+    ) -> Result<GossipOutcome, Error> {
+        let SignedTreeHead { tree_head, root } = gossiped;
+        verify_tree_head_signature(&self.config, tree_head, root, &self.config.signature_key)?;
+
+        let Some((last, last_root)) = store.get_last_tree_head()? else {
+            remember_gossiped_tree_head(store, gossiped)?;
+            return Ok(GossipOutcome::Unchecked);
+        };
+
+        if tree_head.tree_size == last.tree_size {
+            return if *root == last_root {
+                Ok(GossipOutcome::Matches)
+            } else {
+                Err(Error::SplitView {
+                    tree_size: last.tree_size,
+                })
+            };
+        }
+
+        if consistency.is_empty() {
+            remember_gossiped_tree_head(store, gossiped)?;
+            return Ok(GossipOutcome::Unchecked);
+        }
+        let proof = get_hash_proof(consistency)?;
+        if tree_head.tree_size < last.tree_size {
+            verify_consistency_proof(
+                tree_head.tree_size,
+                last.tree_size,
+                &proof,
+                *root,
+                last_root,
+            )?;
+        } else {
+            verify_consistency_proof(
+                last.tree_size,
+                tree_head.tree_size,
+                &proof,
+                last_root,
+                *root,
+            )?;
+            store.set_last_tree_head(tree_head.clone(), *root)?;
+        }
+        check_gossiped_tree_heads(store, tree_head.tree_size, *root)?;
+        Ok(GossipOutcome::Consistent)
+    }
+}
+
+/// Keeps a gossiped tree head that couldn't be checked yet.
+///
+/// It is compared straight away with any head of the same size that was
+/// gossiped earlier.
+fn remember_gossiped_tree_head(
+    store: &mut dyn LogStore,
+    gossiped: &SignedTreeHead,
+) -> Result<(), Error> {
+    let SignedTreeHead { tree_head, root } = gossiped;
+    let mut heads = store.get_gossiped_tree_heads()?;
+    if let Some((_, kept_root)) = heads
+        .iter()
+        .find(|(head, _)| head.tree_size == tree_head.tree_size)
+    {
+        return if kept_root == root {
+            Ok(())
+        } else {
+            Err(Error::SplitView {
+                tree_size: tree_head.tree_size,
+            })
+        };
+    }
+
+    heads.push((tree_head.clone(), *root));
+    if heads.len() > MAX_GOSSIPED_TREE_HEADS {
+        heads.remove(0);
+    }
+    Ok(store.set_gossiped_tree_heads(heads)?)
+}
+
+/// Checks a kept gossiped tree head of size `tree_size`, if there is one,
+/// against a verified `root` of the same size, and drops it from the store.
+pub(crate) fn check_gossiped_tree_heads(
+    store: &mut dyn LogStore,
+    tree_size: u64,
+    root: [u8; 32],
+) -> Result<(), Error> {
+    let mut heads = store.get_gossiped_tree_heads()?;
+    let Some(index) = heads
+        .iter()
+        .position(|(head, _)| head.tree_size == tree_size)
+    else {
+        return Ok(());
+    };
+    let (_, gossiped_root) = heads.remove(index);
+    if gossiped_root != root {
+        return Err(Error::SplitView { tree_size });
+    }
+    Ok(store.set_gossiped_tree_heads(heads)?)
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use ed25519_dalek::{Signer, SigningKey};
+    use hex_literal::hex;
+
+    use super::*;
+    use crate::store::MemoryLogStore;
+    use crate::verify::marshal_tree_head_tbs;
+    use crate::{vrf, DeploymentMode, PublicConfig};
+
+    const SMALL_SIZE: u64 = 1078;
+    const SMALL_ROOT: [u8; 32] =
+        hex!("47cffc2f3d88213d58d25ec12a2284cc94dd7736a5a2f99b5e49543f6d324409");
+    const LARGE_SIZE: u64 = 2000;
+    const LARGE_ROOT: [u8; 32] =
+        hex!("7b830576af52cb15e47f51bf0859c7918858881a2ae1945889e15e89f0b6b654");
+
+    fn consistency_proof() -> Vec<Vec<u8>> {
+        [
+            hex!("817b7723f0c429cc053f1690cdd9ef6357cf544c90b2b898f2b17647379a55f0"),
+            hex!("a3d4fac233766d3f546ce7d21683bcfd442db3da1fd8f672b04223cd7e26e1d4"),
+            hex!("02632c875f214195b9c116a13a105b7a5a891d3bf19d77e1c807380d918623d5"),
+            hex!("cb0c07deb12feceeca301453fdc65fb15a1bada91dc69f5b045a3fb647a216ba"),
+            hex!("f23e0ed32b4481c6619e6175105f6a555f55a7b6d98d4f297f4a292bfeedebb1"),
+            hex!("7343774893f3b7b4dac9d1a5cb4e88d5c57b71dba95aa377f88da043af030df2"),
+            hex!("bc593d72ffdfda9b8cbbc758e10a8bd07e8aed332f8c9168cbf834e8d1d80012"),
+            hex!("32ef158c2def8c641f5c5392b6d248508b7d0fc1ea5ccda1deaf866d38e93ca4"),
+            hex!("74e16d5b930d68f3228396f35717df5a2f6b58382c8d82a14d1c1f5190900f14"),
+            hex!("db84e6de2d857cf6753b7321f5c3e6c7e66aaf8ec2c7b94b7959c462a4fc8162"),
+            hex!("6263b4af228c862edcc8b63ca33d4e67d1d278000e1f7c3eb8cd56039b9613b3"),
+        ]
+        .map(Vec::from)
+        .to_vec()
+    }
+
+    struct TestLog {
+        signing_key: SigningKey,
+        kt: KeyTransparency,
+    }
+
+    impl TestLog {
+        fn new() -> Self {
+            let signing_key = SigningKey::from_bytes(&[7; 32]);
+            let config = PublicConfig {
+                mode: DeploymentMode::ContactMonitoring,
+                signature_key: signing_key.verifying_key(),
+                vrf_key: vrf::PublicKey::try_from(hex!(
+                    "ee964d1552c57c4b8dd9b3f409e6cfd7fb3691966014dbe89f652de7d0a67ca2"
+                ))
+                .unwrap(),
+            };
+            Self {
+                signing_key,
+                kt: KeyTransparency { config },
+            }
+        }
+
+        fn sign(&self, tree_size: u64, root: [u8; 32]) -> SignedTreeHead {
+            let timestamp = 1724796478000;
+            let tbs = marshal_tree_head_tbs(tree_size, timestamp, &root, &self.kt.config).unwrap();
+            SignedTreeHead {
+                tree_head: TreeHead {
+                    tree_size,
+                    timestamp,
+                    signature: self.signing_key.sign(&tbs).to_bytes().to_vec(),
+                },
+                root,
+            }
+        }
+
+        fn store_with(&self, head: &SignedTreeHead) -> MemoryLogStore {
+            let mut store = MemoryLogStore::default();
+            store
+                .set_last_tree_head(head.tree_head.clone(), head.root)
+                .unwrap();
+            store
+        }
+    }
+
+    fn last_tree_size(store: &MemoryLogStore) -> u64 {
+        store.get_last_tree_head().unwrap().unwrap().0.tree_size
+    }
+
+    fn gossiped_tree_sizes(store: &MemoryLogStore) -> Vec<u64> {
+        store
+            .get_gossiped_tree_heads()
+            .unwrap()
+            .iter()
+            .map(|(head, _)| head.tree_size)
+            .collect()
+    }
+
+    #[test]
+    fn same_size_heads_are_compared() {
+        let log = TestLog::new();
+        let head = log.sign(LARGE_SIZE, LARGE_ROOT);
+        let mut store = log.store_with(&head);
+
+        assert_matches!(
+            log.kt.merge_gossiped_tree_head(&mut store, &head, &[]),
+            Ok(GossipOutcome::Matches)
+        );
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut store, &log.sign(LARGE_SIZE, SMALL_ROOT), &[]),
+            Err(Error::SplitView {
+                tree_size: LARGE_SIZE
+            })
+        );
+    }
+
+    #[test]
+    fn newer_consistent_head_is_merged() {
+        let log = TestLog::new();
+        let small = log.sign(SMALL_SIZE, SMALL_ROOT);
+        let large = log.sign(LARGE_SIZE, LARGE_ROOT);
+
+        let mut store = log.store_with(&small);
+        assert_matches!(
+            log.kt.merge_gossiped_tree_head(&mut store, &large, &[]),
+            Ok(GossipOutcome::Unchecked)
+        );
+        assert_eq!(last_tree_size(&store), SMALL_SIZE);
+        assert_eq!(gossiped_tree_sizes(&store), [LARGE_SIZE]);
+
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut store, &large, &consistency_proof()),
+            Ok(GossipOutcome::Consistent)
+        );
+        assert_eq!(last_tree_size(&store), LARGE_SIZE);
+        assert_eq!(gossiped_tree_sizes(&store), [] as [u64; 0]);
+
+        // An older head is checked but doesn't replace the newer one.
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut store, &small, &consistency_proof()),
+            Ok(GossipOutcome::Consistent)
+        );
+        assert_eq!(last_tree_size(&store), LARGE_SIZE);
+        assert_eq!(gossiped_tree_sizes(&store), [] as [u64; 0]);
+    }
+
+    #[test]
+    fn inconsistent_or_unsigned_heads_are_rejected() {
+        let log = TestLog::new();
+        let mut store = log.store_with(&log.sign(SMALL_SIZE, SMALL_ROOT));
+
+        assert_matches!(
+            log.kt.merge_gossiped_tree_head(
+                &mut store,
+                &log.sign(LARGE_SIZE, SMALL_ROOT),
+                &consistency_proof()
+            ),
+            Err(Error::VerificationFailed(_))
+        );
+
+        let mut forged = log.sign(LARGE_SIZE, LARGE_ROOT);
+        forged.tree_head.timestamp += 1;
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut store, &forged, &consistency_proof()),
+            Err(Error::VerificationFailed(_))
+        );
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut MemoryLogStore::default(), &forged, &[]),
+            Err(Error::VerificationFailed(_))
+        );
+    }
+
+    #[test]
+    fn unchecked_heads_are_kept_until_checked() {
+        let log = TestLog::new();
+        let small = log.sign(SMALL_SIZE, SMALL_ROOT);
+        let large = log.sign(LARGE_SIZE, LARGE_ROOT);
+
+        let mut store = MemoryLogStore::default();
+        assert_matches!(
+            log.kt.merge_gossiped_tree_head(&mut store, &large, &[]),
+            Ok(GossipOutcome::Unchecked)
+        );
+        assert_matches!(
+            log.kt.merge_gossiped_tree_head(&mut store, &large, &[]),
+            Ok(GossipOutcome::Unchecked)
+        );
+        assert_eq!(gossiped_tree_sizes(&store), [LARGE_SIZE]);
+
+        // A different root for a head that's already kept is caught even
+        // before it can be checked against the local state.
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut store, &log.sign(LARGE_SIZE, SMALL_ROOT), &[]),
+            Err(Error::SplitView {
+                tree_size: LARGE_SIZE
+            })
+        );
+
+        // Once the local state reaches the same size, the kept head is
+        // checked and dropped.
+        store
+            .set_last_tree_head(small.tree_head.clone(), small.root)
+            .unwrap();
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut store, &large, &consistency_proof()),
+            Ok(GossipOutcome::Consistent)
+        );
+        assert_eq!(gossiped_tree_sizes(&store), [] as [u64; 0]);
+    }
+
+    #[test]
+    fn kept_heads_must_match_verified_heads() {
+        let log = TestLog::new();
+        let mut store = log.store_with(&log.sign(SMALL_SIZE, SMALL_ROOT));
+        assert_matches!(
+            log.kt
+                .merge_gossiped_tree_head(&mut store, &log.sign(LARGE_SIZE, LARGE_ROOT), &[]),
+            Ok(GossipOutcome::Unchecked)
+        );
+
+        assert_matches!(
+            check_gossiped_tree_heads(&mut store, LARGE_SIZE, SMALL_ROOT),
+            Err(Error::SplitView {
+                tree_size: LARGE_SIZE
+            })
+        );
+        assert_matches!(
+            check_gossiped_tree_heads(&mut store, LARGE_SIZE, LARGE_ROOT),
+            Ok(())
+        );
+        assert_eq!(gossiped_tree_sizes(&store), [] as [u64; 0]);
+    }
+
+    #[test]
+    fn oldest_unchecked_head_is_dropped() {
+        let log = TestLog::new();
+        let mut store = MemoryLogStore::default();
+        for tree_size in 1..=(MAX_GOSSIPED_TREE_HEADS as u64 + 1) {
+            assert_matches!(
+                log.kt
+                    .merge_gossiped_tree_head(&mut store, &log.sign(tree_size, SMALL_ROOT), &[]),
+                Ok(GossipOutcome::Unchecked)
+            );
+        }
+        let sizes = gossiped_tree_sizes(&store);
+        assert_eq!(sizes.len(), MAX_GOSSIPED_TREE_HEADS);
+        assert_eq!(sizes.first(), Some(&2));
+    }
+}
@@ -6,6 +6,8 @@
 #![cfg_attr(not(test), warn(clippy::unwrap_used))]
 
 mod commitments;
+mod evidence;
+mod gossip;
 mod guide;
 mod implicit;
 mod left_balanced;
@@ -19,10 +21,14 @@ mod wire;
 use std::collections::HashMap;
 
 use ed25519_dalek::VerifyingKey as SigPublicKey;
-pub use store::LogStore;
+pub use evidence::{SearchEvidence, VerifiedSearch};
+pub use gossip::{GossipOutcome, SignedTreeHead};
+pub use store::{LogStore, LogStoreError, MemoryLogStore};
+pub use verify::Error;
 use verify::{
     truncate_search_response, verify_distinguished, verify_monitor, verify_search, verify_update,
 };
+pub use vrf::PublicKey as VrfPublicKey;
 pub use wire::{
     Consistency, FullTreeHead, MonitorKey, MonitorRequest, MonitorResponse, SearchRequest,
     SearchResponse, TreeHead, UpdateRequest, UpdateResponse, UpdateValue,
@@ -64,11 +70,13 @@ pub struct PublicConfig {
 }
 
 /// Key transparency main API entrypoint
+///
+/// Verified state is kept in the [`LogStore`] passed to each operation, so the
+/// same store must be used for every request to a given log.
+#[derive(Clone)]
 pub struct KeyTransparency {
     /// Key transparency system configuration
     pub config: PublicConfig,
-    /// Abstract key transparency data store
-    pub store: dyn LogStore,
 }
 
 impl KeyTransparency {
@@ -76,30 +84,26 @@ impl KeyTransparency {
     /// client's stored data. `res.value.value` may only be consumed by the
     /// application if this function returns successfully.
     pub fn verify_search(
-        &mut self,
+        &self,
+        store: &mut dyn LogStore,
         request: &SearchRequest,
         response: &SearchResponse,
         force_monitor: bool,
     ) -> Result<(), verify::Error> {
-        verify_search(
-            &self.config,
-            &mut self.store,
-            request,
-            response,
-            force_monitor,
-        )
+        verify_search(&self.config, store, request, response, force_monitor)
     }
 
     /// Checks that the provided FullTreeHead has a valid consistency proof relative
     /// to the provided distinguished head.
     pub fn verify_distinguished(
-        &mut self,
+        &self,
+        store: &mut dyn LogStore,
         full_tree_head: &FullTreeHead,
         distinguished_size: u64,
         distinguished_root: [u8; 32],
     ) -> Result<(), verify::Error> {
         verify_distinguished(
-            &mut self.store,
+            store,
             full_tree_head,
             distinguished_size,
             distinguished_root,
@@ -111,7 +115,7 @@ impl KeyTransparency {
     ///
     /// Most validation is skipped so the SearchResponse MUST already be verified.
     pub fn truncate_search_response(
-        &mut self,
+        &self,
         request: &SearchRequest,
         response: &SearchResponse,
     ) -> Result<(u64, [u8; 32]), verify::Error> {
@@ -121,26 +125,29 @@ impl KeyTransparency {
     /// Checks that the output of a Monitor operation is valid and updates the
     /// client's stored data.
     pub fn verify_monitor(
-        &mut self,
+        &self,
+        store: &mut dyn LogStore,
         request: &MonitorRequest,
         response: &MonitorResponse,
     ) -> Result<(), verify::Error> {
-        verify_monitor(&self.config, &mut self.store, request, response)
+        verify_monitor(&self.config, store, request, response)
     }
 
     /// Checks that the output of an Update operation is valid and updates the
     /// client's stored data.
     pub fn verify_update(
-        &mut self,
+        &self,
+        store: &mut dyn LogStore,
         request: &UpdateRequest,
         response: &UpdateResponse,
     ) -> Result<(), verify::Error> {
-        verify_update(&self.config, &mut self.store, request, response)
+        verify_update(&self.config, store, request, response)
     }
 }
 
 /// MonitoringData is the structure retained for each key in the KT server being
 /// monitored.
+#[derive(Clone)]
 pub struct MonitoringData {
     /// The VRF output on the search key.
     pub index: [u8; 32],
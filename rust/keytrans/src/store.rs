@@ -2,6 +2,8 @@
 // Copyright 2024 Signal Messenger, LLC.
 // SPDX-License-Identifier: AGPL-3.0-only
 //
+use std::collections::HashMap;
+
 use prost::{DecodeError, Message};
 
 use crate::{wire, MonitoringData, TreeHead};
@@ -10,6 +12,12 @@ use crate::{wire, MonitoringData, TreeHead};
 #[derive(Debug, displaydoc::Display)]
 pub struct LogStoreError(String);
 
+impl LogStoreError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
 impl From<DecodeError> for LogStoreError {
     fn from(err: DecodeError) -> Self {
         Self(err.to_string())
@@ -22,16 +30,72 @@ pub trait LogStore {
     fn get_last_tree_head(&self) -> Result<Option<(TreeHead, [u8; 32])>, LogStoreError>;
     fn set_last_tree_head(&mut self, head: TreeHead, root: [u8; 32]) -> Result<(), LogStoreError>;
 
+    /// Gossiped tree heads that couldn't be checked against the last tree head
+    /// yet, see [`KeyTransparency::merge_gossiped_tree_head`].
+    ///
+    /// [`KeyTransparency::merge_gossiped_tree_head`]: crate::KeyTransparency::merge_gossiped_tree_head
+    fn get_gossiped_tree_heads(&self) -> Result<Vec<(TreeHead, [u8; 32])>, LogStoreError>;
+    fn set_gossiped_tree_heads(
+        &mut self,
+        heads: Vec<(TreeHead, [u8; 32])>,
+    ) -> Result<(), LogStoreError>;
+
     fn get_data(&self, key: &[u8]) -> Result<Option<MonitoringData>, LogStoreError>;
     fn set_data(&mut self, key: &[u8], data: MonitoringData) -> Result<(), LogStoreError>;
 }
 
+/// A [`LogStore`] that keeps everything in memory.
+///
+/// Useful for checking a single response, where nothing needs to outlive the
+/// check, and in tests.
+#[derive(Default)]
+pub struct MemoryLogStore {
+    last_tree_head: Option<(TreeHead, [u8; 32])>,
+    gossiped_tree_heads: Vec<(TreeHead, [u8; 32])>,
+    data: HashMap<Vec<u8>, MonitoringData>,
+}
+
+impl LogStore for MemoryLogStore {
+    fn get_last_tree_head(&self) -> Result<Option<(TreeHead, [u8; 32])>, LogStoreError> {
+        Ok(self.last_tree_head.clone())
+    }
+
+    fn set_last_tree_head(&mut self, head: TreeHead, root: [u8; 32]) -> Result<(), LogStoreError> {
+        self.last_tree_head = Some((head, root));
+        Ok(())
+    }
+
+    fn get_gossiped_tree_heads(&self) -> Result<Vec<(TreeHead, [u8; 32])>, LogStoreError> {
+        Ok(self.gossiped_tree_heads.clone())
+    }
+
+    fn set_gossiped_tree_heads(
+        &mut self,
+        heads: Vec<(TreeHead, [u8; 32])>,
+    ) -> Result<(), LogStoreError> {
+        self.gossiped_tree_heads = heads;
+        Ok(())
+    }
+
+    fn get_data(&self, key: &[u8]) -> Result<Option<MonitoringData>, LogStoreError> {
+        Ok(self.data.get(key).cloned())
+    }
+
+    fn set_data(&mut self, key: &[u8], data: MonitoringData) -> Result<(), LogStoreError> {
+        self.data.insert(key.to_vec(), data);
+        Ok(())
+    }
+}
+
 /// SimplifiedLogStore is a simpler version of the LogStore trait that clients
 /// can implement to avoid needing to deal with serialization themselves.
 trait SimplifiedLogStore {
     fn get_raw_tree_head(&self) -> Result<Option<Vec<u8>>, LogStoreError>;
     fn set_raw_tree_head(&mut self, data: &[u8]) -> Result<(), LogStoreError>;
 
+    fn get_raw_gossiped_tree_heads(&self) -> Result<Option<Vec<u8>>, LogStoreError>;
+    fn set_raw_gossiped_tree_heads(&mut self, data: &[u8]) -> Result<(), LogStoreError>;
+
     fn get_raw_data(&self, key: &[u8]) -> Result<Option<Vec<u8>>, LogStoreError>;
     fn set_raw_data(
         &mut self,
@@ -67,6 +131,43 @@ impl<T: SimplifiedLogStore + ?Sized> LogStore for T {
         self.set_raw_tree_head(&raw)
     }
 
+    fn get_gossiped_tree_heads(&self) -> Result<Vec<(TreeHead, [u8; 32])>, LogStoreError> {
+        let Some(data) = self.get_raw_gossiped_tree_heads()? else {
+            return Ok(vec![]);
+        };
+        wire::StoredGossipedTreeHeads::decode(data.as_slice())?
+            .heads
+            .into_iter()
+            .map(|stored| {
+                let tree_head = stored
+                    .tree_head
+                    .ok_or_else(|| LogStoreError("malformed tree head found".to_string()))?;
+                let root = stored
+                    .root
+                    .try_into()
+                    .map_err(|_| LogStoreError("malformed root found".to_string()))?;
+                Ok((tree_head, root))
+            })
+            .collect()
+    }
+
+    fn set_gossiped_tree_heads(
+        &mut self,
+        heads: Vec<(TreeHead, [u8; 32])>,
+    ) -> Result<(), LogStoreError> {
+        let raw = wire::StoredGossipedTreeHeads {
+            heads: heads
+                .into_iter()
+                .map(|(head, root)| wire::StoredTreeHead {
+                    tree_head: Some(head),
+                    root: root.to_vec(),
+                })
+                .collect(),
+        }
+        .encode_to_vec();
+        self.set_raw_gossiped_tree_heads(&raw)
+    }
+
     fn get_data(&self, key: &[u8]) -> Result<Option<MonitoringData>, LogStoreError> {
         self.get_raw_data(key)?
             .map(|data| {
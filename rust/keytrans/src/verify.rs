@@ -9,6 +9,7 @@ use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use sha2::{Digest, Sha256};
 
 use crate::commitments::verify as verify_commitment;
+use crate::gossip::check_gossiped_tree_heads;
 use crate::guide::{InvalidState, ProofGuide};
 use crate::implicit::{full_monitoring_path, monitoring_path};
 use crate::log::{evaluate_batch_proof, truncate_batch_proof, verify_consistency_proof};
@@ -43,8 +44,14 @@ pub enum Error {
     VerificationFailed(String),
     /// Storage operation failed: {0}
     StorageFailure(String),
+    /// Split view detected: the log signed different roots for tree size {tree_size}
+    SplitView { tree_size: u64 },
+    /// Timestamp can't be represented in milliseconds since the epoch
+    TimestampOutOfRange,
 }
 
+impl std::error::Error for Error {}
+
 impl From<log::Error> for Error {
     fn from(err: log::Error) -> Self {
         Self::VerificationFailed(err.to_string())
@@ -81,7 +88,7 @@ fn get_proto_field<T>(field: &Option<T>) -> Result<&T> {
     field.as_ref().ok_or(Error::RequiredFieldMissing)
 }
 
-fn get_hash_proof(proof: &[Vec<u8>]) -> Result<Vec<[u8; 32]>> {
+pub(crate) fn get_hash_proof(proof: &[Vec<u8>]) -> Result<Vec<[u8; 32]>> {
     proof
         .iter()
         .map(|elem| <&[u8] as TryInto<[u8; 32]>>::try_into(elem))
@@ -96,7 +103,7 @@ fn serialize_key(buffer: &mut Vec<u8>, key_material: &[u8], key_kind: &str) {
     buffer.extend_from_slice(key_material);
 }
 
-fn marshal_tree_head_tbs(
+pub(crate) fn marshal_tree_head_tbs(
     tree_size: u64,
     timestamp: i64,
     root: &[u8; 32],
@@ -141,7 +148,7 @@ fn leaf_hash(prefix_root: &[u8; 32], commitment: &[u8; 32]) -> [u8; 32] {
 }
 
 /// Checks the signature on the provided transparency tree head using the given key
-fn verify_tree_head_signature(
+pub(crate) fn verify_tree_head_signature(
     config: &PublicConfig,
     head: &TreeHead,
     root: &[u8; 32],
@@ -277,7 +284,11 @@ fn verify_full_tree_head(
         }
     }
 
-    Ok(storage.set_last_tree_head(tree_head, root)?)
+    let tree_size = tree_head.tree_size;
+    storage.set_last_tree_head(tree_head, root)?;
+
+    // 5. Check any gossiped tree heads of the same size against the new one.
+    check_gossiped_tree_heads(storage, tree_size, root)
 }
 
 /// The range of allowed timestamp values relative to "now".
@@ -338,23 +349,24 @@ pub fn verify_distinguished(
 
     // Handle special case when tree_size == distinguished_size.
     if tree_size == distinguished_size {
-        let result = if root == distinguished_root {
-            Ok(())
-        } else {
-            Err(Error::VerificationFailed(
+        if root != distinguished_root {
+            return Err(Error::VerificationFailed(
                 "root hash does not match expected value".to_string(),
-            ))
-        };
-        return result;
+            ));
+        }
+    } else {
+        verify_consistency_proof(
+            distinguished_size,
+            tree_size,
+            &get_hash_proof(&fth.distinguished)?,
+            distinguished_root,
+            root,
+        )?;
     }
 
-    Ok(verify_consistency_proof(
-        distinguished_size,
-        tree_size,
-        &get_hash_proof(&fth.distinguished)?,
-        distinguished_root,
-        root,
-    )?)
+    // A gossiped tree head used as the distinguished head has now been
+    // checked.
+    check_gossiped_tree_heads(storage, distinguished_size, distinguished_root)
 }
 
 fn evaluate_vrf_proof(
@@ -367,7 +379,7 @@ fn evaluate_vrf_proof(
 }
 
 /// The shared implementation of verify_search and verify_update.
-fn verify_search_internal(
+pub(crate) fn verify_search_internal(
     config: &PublicConfig,
     storage: &mut dyn LogStore,
     req: &SearchRequest,
@@ -960,6 +972,19 @@ mod test {
             Ok(())
         }
 
+        fn get_gossiped_tree_heads(
+            &self,
+        ) -> Result<Vec<(wire::TreeHead, [u8; 32])>, LogStoreError> {
+            Ok(vec![])
+        }
+
+        fn set_gossiped_tree_heads(
+            &mut self,
+            _heads: Vec<(wire::TreeHead, [u8; 32])>,
+        ) -> Result<(), LogStoreError> {
+            Ok(())
+        }
+
         fn get_data(&self, _key: &[u8]) -> Result<Option<MonitoringData>, LogStoreError> {
             Ok(None)
         }
@@ -4,10 +4,17 @@
 //
 
 mod error;
+mod seek;
+mod strip;
 
+pub mod gif;
+pub mod jpeg;
 #[cfg(feature = "mp4san")]
 pub mod mp4;
+pub mod png;
 #[cfg(feature = "webpsan")]
 pub mod webp;
 
 pub use mediasan_common::{AsyncSkip, InputSpan, Skip};
+pub use seek::{AsyncSeekInput, SeekInput};
+pub use strip::MetadataAllowlist;
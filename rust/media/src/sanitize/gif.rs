@@ -0,0 +1,342 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Validation of GIF images.
+//!
+//! The block structure of the whole file is checked, including that every frame lies within the
+//! logical screen, but the compressed image data itself is not decoded.
+
+use std::io::{self, Read};
+
+/// Error type returned by [`sanitize`].
+pub type Error = super::error::SanitizerError<ParseError>;
+
+/// A decomposed and stringified GIF parse error.
+pub type ParseErrorReport = super::error::ParseErrorReport<ParseError>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// The input is not a GIF, or violates the GIF format.
+    #[error("invalid input")]
+    InvalidInput,
+    /// A block is laid out incorrectly, such as a frame extending outside the logical screen.
+    #[error("invalid block layout")]
+    InvalidBlockLayout,
+    /// The input ended in the middle of a block, or before the trailer.
+    #[error("truncated block")]
+    TruncatedBlock,
+    /// The input uses a version other than 87a or 89a.
+    #[error("unsupported version")]
+    UnsupportedVersion,
+}
+
+/// Normalized details about a sanitized GIF.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GifInfo {
+    pub canvas_width: u16,
+    pub canvas_height: u16,
+    pub frame_count: u32,
+    /// How many times an animation should be played, where 0 means forever, or `None` if the file
+    /// doesn't say, in which case it should be played once.
+    pub loop_count: Option<u16>,
+}
+
+const SIGNATURE: &[u8; 3] = b"GIF";
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_SEPARATOR: u8 = 0x2c;
+const TRAILER: u8 = 0x3b;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xf9;
+const APPLICATION_LABEL: u8 = 0xff;
+const COLOR_TABLE_FLAG: u8 = 0x80;
+const NETSCAPE_APPLICATION: &[u8] = b"NETSCAPE2.0";
+const MAX_LZW_MINIMUM_CODE_SIZE: u8 = 8;
+
+fn parse_error(kind: ParseError, report: &str) -> Error {
+    Error::Parse(ParseErrorReport {
+        kind,
+        report: report.to_owned(),
+    })
+}
+
+fn read_exact<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<(), Error> {
+    input.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            parse_error(ParseError::TruncatedBlock, "input ended early")
+        }
+        _ => Error::Io(e),
+    })
+}
+
+fn read_u8<R: Read>(input: &mut R) -> Result<u8, Error> {
+    let mut byte = [0];
+    read_exact(input, &mut byte)?;
+    Ok(byte[0])
+}
+
+fn skip_color_table<R: Read>(input: &mut R, flags: u8) -> Result<(), Error> {
+    if flags & COLOR_TABLE_FLAG != 0 {
+        let mut table = vec![0; 3 << ((flags & 0x07) + 1)];
+        read_exact(input, &mut table)?;
+    }
+    Ok(())
+}
+
+/// Reads a sequence of data sub-blocks, returning the first `keep` of them.
+fn read_sub_blocks<R: Read>(input: &mut R, keep: usize) -> Result<Vec<Vec<u8>>, Error> {
+    let mut kept = vec![];
+    let mut block = [0; u8::MAX as usize];
+    loop {
+        let len = usize::from(read_u8(input)?);
+        if len == 0 {
+            return Ok(kept);
+        }
+        read_exact(input, &mut block[..len])?;
+        if kept.len() < keep {
+            kept.push(block[..len].to_vec());
+        }
+    }
+}
+
+/// Sanitize a GIF input.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an `Error` is returned.
+pub fn sanitize<R: Read>(mut input: R) -> Result<GifInfo, Error> {
+    let mut header = [0; 13];
+    read_exact(&mut input, &mut header)?;
+    if &header[..3] != SIGNATURE {
+        return Err(parse_error(
+            ParseError::InvalidInput,
+            "missing GIF signature",
+        ));
+    }
+    if &header[3..6] != b"87a" && &header[3..6] != b"89a" {
+        return Err(parse_error(
+            ParseError::UnsupportedVersion,
+            "unknown version",
+        ));
+    }
+    let canvas_width = u16::from_le_bytes([header[6], header[7]]);
+    let canvas_height = u16::from_le_bytes([header[8], header[9]]);
+    if canvas_width == 0 || canvas_height == 0 {
+        return Err(parse_error(
+            ParseError::InvalidInput,
+            "empty logical screen",
+        ));
+    }
+    skip_color_table(&mut input, header[10])?;
+
+    let mut frame_count = 0;
+    let mut loop_count = None;
+    loop {
+        match read_u8(&mut input)? {
+            IMAGE_SEPARATOR => {
+                let mut descriptor = [0; 9];
+                read_exact(&mut input, &mut descriptor)?;
+                let field =
+                    |i: usize| u32::from(u16::from_le_bytes([descriptor[i], descriptor[i + 1]]));
+                let (left, top, width, height) = (field(0), field(2), field(4), field(6));
+                if width == 0
+                    || height == 0
+                    || left + width > canvas_width.into()
+                    || top + height > canvas_height.into()
+                {
+                    return Err(parse_error(
+                        ParseError::InvalidBlockLayout,
+                        "frame extends outside the logical screen",
+                    ));
+                }
+                skip_color_table(&mut input, descriptor[8])?;
+                let lzw_minimum_code_size = read_u8(&mut input)?;
+                if !(2..=MAX_LZW_MINIMUM_CODE_SIZE).contains(&lzw_minimum_code_size) {
+                    return Err(parse_error(
+                        ParseError::InvalidInput,
+                        "invalid LZW minimum code size",
+                    ));
+                }
+                read_sub_blocks(&mut input, 0)?;
+                frame_count += 1;
+            }
+            EXTENSION_INTRODUCER => {
+                let label = read_u8(&mut input)?;
+                let blocks = read_sub_blocks(&mut input, 2)?;
+                match (label, blocks.as_slice()) {
+                    (GRAPHIC_CONTROL_LABEL, [control]) if control.len() == 4 => {}
+                    (GRAPHIC_CONTROL_LABEL, _) => {
+                        return Err(parse_error(
+                            ParseError::InvalidBlockLayout,
+                            "graphic control extension has the wrong size",
+                        ));
+                    }
+                    (APPLICATION_LABEL, [identifier, data, ..])
+                        if identifier == NETSCAPE_APPLICATION =>
+                    {
+                        // Sub-block 1 of the NETSCAPE2.0 extension holds the loop count.
+                        if let [1, low, high] = data[..] {
+                            loop_count = Some(u16::from_le_bytes([low, high]));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            TRAILER => break,
+            _ => {
+                return Err(parse_error(
+                    ParseError::InvalidInput,
+                    "unknown block introducer",
+                ))
+            }
+        }
+    }
+
+    if frame_count == 0 {
+        return Err(parse_error(ParseError::InvalidInput, "no frames"));
+    }
+    Ok(GifInfo {
+        canvas_width,
+        canvas_height,
+        frame_count,
+        loop_count,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn header(width: u16, height: u16) -> Vec<u8> {
+        [
+            &b"GIF89a"[..],
+            &width.to_le_bytes(),
+            &height.to_le_bytes(),
+            // A 2-entry global color table.
+            &[COLOR_TABLE_FLAG, 0, 0],
+            &[0; 6],
+        ]
+        .concat()
+    }
+
+    fn frame(left: u16, top: u16, width: u16, height: u16) -> Vec<u8> {
+        [
+            &[IMAGE_SEPARATOR][..],
+            &left.to_le_bytes(),
+            &top.to_le_bytes(),
+            &width.to_le_bytes(),
+            &height.to_le_bytes(),
+            &[0],
+            // LZW minimum code size, one data sub-block, and the terminator.
+            &[2, 2, 0x4c, 0x01, 0],
+        ]
+        .concat()
+    }
+
+    fn graphic_control() -> Vec<u8> {
+        vec![
+            EXTENSION_INTRODUCER,
+            GRAPHIC_CONTROL_LABEL,
+            4,
+            0,
+            10,
+            0,
+            0,
+            0,
+        ]
+    }
+
+    fn netscape_loop(count: u16) -> Vec<u8> {
+        let [low, high] = count.to_le_bytes();
+        [
+            &[EXTENSION_INTRODUCER, APPLICATION_LABEL, 11][..],
+            NETSCAPE_APPLICATION,
+            &[3, 1, low, high, 0],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn reads_animation_info() {
+        let input = [
+            header(10, 20),
+            netscape_loop(0),
+            graphic_control(),
+            frame(0, 0, 10, 20),
+            graphic_control(),
+            frame(5, 5, 5, 15),
+            vec![TRAILER],
+        ]
+        .concat();
+        assert_eq!(
+            sanitize(&input[..]).expect("valid"),
+            GifInfo {
+                canvas_width: 10,
+                canvas_height: 20,
+                frame_count: 2,
+                loop_count: Some(0),
+            }
+        );
+    }
+
+    #[test]
+    fn reads_still_image_info() {
+        let input = [header(10, 20), frame(0, 0, 10, 20), vec![TRAILER]].concat();
+        assert_eq!(
+            sanitize(&input[..]).expect("valid"),
+            GifInfo {
+                canvas_width: 10,
+                canvas_height: 20,
+                frame_count: 1,
+                loop_count: None,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_frame_outside_canvas() {
+        let input = [header(10, 20), frame(6, 0, 5, 20), vec![TRAILER]].concat();
+        assert_matches!(
+            sanitize(&input[..]),
+            Err(Error::Parse(ParseErrorReport {
+                kind: ParseError::InvalidBlockLayout,
+                ..
+            }))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let input = [header(10, 20), frame(0, 0, 10, 20), vec![TRAILER]].concat();
+        for len in 0..input.len() {
+            assert_matches!(
+                sanitize(&input[..len]),
+                Err(Error::Parse(ParseErrorReport {
+                    kind: ParseError::TruncatedBlock,
+                    ..
+                })),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert_matches!(
+            sanitize(&b"\x89PNG\r\n\x1a\n\0\0\0\0\0"[..]),
+            Err(Error::Parse(ParseErrorReport {
+                kind: ParseError::InvalidInput,
+                ..
+            }))
+        );
+        assert_matches!(
+            sanitize(&b"GIF90a\0\0\0\0\0\0\0"[..]),
+            Err(Error::Parse(ParseErrorReport {
+                kind: ParseError::UnsupportedVersion,
+                ..
+            }))
+        );
+    }
+}
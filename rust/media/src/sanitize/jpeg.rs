@@ -0,0 +1,349 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Removing metadata from JPEG images.
+//!
+//! The marker segment structure of the image is validated as it is copied, but the entropy-coded
+//! image data is passed through without being decoded. Application segments other than JFIF and
+//! Adobe color transform segments are removed unless allowed, as are comments and any data after
+//! the end of the image.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use super::MetadataAllowlist;
+
+/// Error type returned by [`strip_metadata`].
+pub type Error = super::error::SanitizerError<ParseError>;
+
+/// A decomposed and stringified JPEG parse error.
+pub type ParseErrorReport = super::error::ParseErrorReport<ParseError>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// The input is not a JPEG, or violates the JPEG format.
+    #[error("invalid input")]
+    InvalidInput,
+    /// A segment is in the wrong place or has an invalid length.
+    #[error("invalid segment layout")]
+    InvalidSegmentLayout,
+    /// The image has no frame header or no scan.
+    #[error("missing required segment")]
+    MissingRequiredSegment,
+    /// The input ended in the middle of a segment, or before the end of the image.
+    #[error("truncated segment")]
+    TruncatedSegment,
+}
+
+const SOI: u8 = 0xd8;
+const EOI: u8 = 0xd9;
+const SOS: u8 = 0xda;
+const DHT: u8 = 0xc4;
+const JPG: u8 = 0xc8;
+const DAC: u8 = 0xcc;
+const APP0: u8 = 0xe0;
+const APP1: u8 = 0xe1;
+const APP2: u8 = 0xe2;
+const APP14: u8 = 0xee;
+const APP15: u8 = 0xef;
+const COM: u8 = 0xfe;
+const TEM: u8 = 0x01;
+const RST0: u8 = 0xd0;
+const RST7: u8 = 0xd7;
+
+const EXIF_IDENTIFIER: &[u8] = b"Exif\0\0";
+const XMP_IDENTIFIER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const EXTENDED_XMP_IDENTIFIER: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+const ICC_IDENTIFIER: &[u8] = b"ICC_PROFILE\0";
+
+fn parse_error(kind: ParseError, report: &str) -> Error {
+    Error::Parse(ParseErrorReport {
+        kind,
+        report: report.to_owned(),
+    })
+}
+
+fn map_read_error(e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            parse_error(ParseError::TruncatedSegment, "input ended early")
+        }
+        _ => Error::Io(e),
+    }
+}
+
+fn read_u8<R: Read>(input: &mut R) -> Result<u8, Error> {
+    let mut byte = [0];
+    input.read_exact(&mut byte).map_err(map_read_error)?;
+    Ok(byte[0])
+}
+
+/// Reads the next marker, skipping any fill bytes before it.
+fn read_marker<R: Read>(input: &mut R) -> Result<u8, Error> {
+    if read_u8(input)? != 0xff {
+        return Err(parse_error(
+            ParseError::InvalidSegmentLayout,
+            "expected a marker",
+        ));
+    }
+    loop {
+        match read_u8(input)? {
+            0xff => continue,
+            0x00 => {
+                return Err(parse_error(
+                    ParseError::InvalidSegmentLayout,
+                    "stuffed byte outside of entropy-coded data",
+                ))
+            }
+            marker => return Ok(marker),
+        }
+    }
+}
+
+/// Decides whether to keep a segment, given as much of the start of its payload as fits in
+/// `prefix`.
+fn keep_segment(marker: u8, prefix: &[u8], allow: &MetadataAllowlist) -> bool {
+    match marker {
+        APP0 | APP14 => true,
+        APP1 if prefix.starts_with(EXIF_IDENTIFIER) => allow.exif,
+        APP1 if prefix.starts_with(XMP_IDENTIFIER)
+            || prefix.starts_with(EXTENDED_XMP_IDENTIFIER) =>
+        {
+            allow.xmp
+        }
+        APP2 if prefix.starts_with(ICC_IDENTIFIER) => allow.icc,
+        APP0..=APP15 | COM => false,
+        _ => true,
+    }
+}
+
+fn is_frame_header(marker: u8) -> bool {
+    matches!(marker, 0xc0..=0xcf) && !matches!(marker, DHT | JPG | DAC)
+}
+
+/// Copies entropy-coded data up to the next marker, which is returned.
+fn copy_entropy_coded_data<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+) -> Result<u8, Error> {
+    loop {
+        let buf = input.fill_buf().map_err(map_read_error)?;
+        if buf.is_empty() {
+            return Err(map_read_error(io::ErrorKind::UnexpectedEof.into()));
+        }
+        let len = buf.iter().position(|&b| b == 0xff).unwrap_or(buf.len());
+        output.write_all(&buf[..len]).map_err(Error::Io)?;
+        input.consume(len);
+        if len == 0 {
+            input.consume(1);
+            let mut next = read_u8(input)?;
+            while next == 0xff {
+                next = read_u8(input)?;
+            }
+            match next {
+                0x00 | RST0..=RST7 => output.write_all(&[0xff, next]).map_err(Error::Io)?,
+                marker => return Ok(marker),
+            }
+        }
+    }
+}
+
+/// Copies a JPEG image from `input` to `output`, removing metadata not in `allow`.
+///
+/// Data is streamed rather than buffered, so `output` should be discarded if an error is
+/// returned.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs reading the input or writing the output,
+/// an `Error` is returned.
+pub fn strip_metadata<R: Read, W: Write>(
+    input: R,
+    mut output: W,
+    allow: &MetadataAllowlist,
+) -> Result<(), Error> {
+    let mut input = BufReader::new(input);
+    if read_marker(&mut input).ok() != Some(SOI) {
+        return Err(parse_error(ParseError::InvalidInput, "missing SOI marker"));
+    }
+    output.write_all(&[0xff, SOI]).map_err(Error::Io)?;
+
+    let mut seen_frame_header = false;
+    let mut seen_scan = false;
+    let mut next_marker = None;
+    loop {
+        let marker = match next_marker.take() {
+            Some(marker) => marker,
+            None => read_marker(&mut input)?,
+        };
+        match marker {
+            EOI => {
+                if !seen_scan {
+                    return Err(parse_error(
+                        ParseError::MissingRequiredSegment,
+                        "no scans before EOI",
+                    ));
+                }
+                output.write_all(&[0xff, EOI]).map_err(Error::Io)?;
+                return output.flush().map_err(Error::Io);
+            }
+            TEM => output.write_all(&[0xff, TEM]).map_err(Error::Io)?,
+            SOI | RST0..=RST7 => {
+                return Err(parse_error(
+                    ParseError::InvalidSegmentLayout,
+                    "unexpected standalone marker",
+                ))
+            }
+            _ => {
+                let mut len = [0; 2];
+                input.read_exact(&mut len).map_err(map_read_error)?;
+                let payload_len = u16::from_be_bytes(len).checked_sub(2).ok_or_else(|| {
+                    parse_error(ParseError::InvalidSegmentLayout, "segment length too short")
+                })?;
+
+                let mut prefix =
+                    vec![0; usize::from(payload_len).min(EXTENDED_XMP_IDENTIFIER.len())];
+                input.read_exact(&mut prefix).map_err(map_read_error)?;
+                let rest = u64::from(payload_len) - prefix.len() as u64;
+
+                if keep_segment(marker, &prefix, allow) {
+                    output.write_all(&[0xff, marker]).map_err(Error::Io)?;
+                    output.write_all(&len).map_err(Error::Io)?;
+                    output.write_all(&prefix).map_err(Error::Io)?;
+                    copy_exact(&mut input, &mut output, rest)?;
+                } else {
+                    copy_exact(&mut input, &mut io::sink(), rest)?;
+                }
+
+                if is_frame_header(marker) {
+                    seen_frame_header = true;
+                } else if marker == SOS {
+                    if !seen_frame_header {
+                        return Err(parse_error(
+                            ParseError::MissingRequiredSegment,
+                            "scan before frame header",
+                        ));
+                    }
+                    seen_scan = true;
+                    next_marker = Some(copy_entropy_coded_data(&mut input, &mut output)?);
+                }
+            }
+        }
+    }
+}
+
+fn copy_exact<R: Read, W: Write>(input: &mut R, output: &mut W, len: u64) -> Result<(), Error> {
+    let copied = io::copy(&mut input.take(len), output).map_err(Error::Io)?;
+    if copied != len {
+        return Err(map_read_error(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let len = u16::try_from(payload.len() + 2).unwrap();
+        [&[0xff, marker][..], &len.to_be_bytes(), payload].concat()
+    }
+
+    fn image(metadata: &[Vec<u8>]) -> Vec<u8> {
+        [
+            vec![0xff, SOI],
+            segment(APP0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0"),
+            metadata.concat(),
+            segment(0xc0, &[8, 0, 1, 0, 1, 1, 1, 0x11, 0]),
+            segment(SOS, &[1, 1, 0, 0, 0x3f, 0]),
+            // Entropy-coded data with a stuffed byte and a restart marker.
+            vec![0x12, 0xff, 0x00, 0x34, 0xff, RST0, 0x56],
+            vec![0xff, EOI],
+        ]
+        .concat()
+    }
+
+    fn strip(input: &[u8], allow: MetadataAllowlist) -> Result<Vec<u8>, Error> {
+        let mut output = vec![];
+        strip_metadata(input, &mut output, &allow)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn removes_metadata() {
+        let exif = segment(APP1, &[EXIF_IDENTIFIER, b"MM\0*"].concat());
+        let xmp = segment(APP1, &[XMP_IDENTIFIER, b"<x:xmpmeta/>"].concat());
+        let icc = segment(APP2, &[ICC_IDENTIFIER, &[1, 1], b"profile"].concat());
+        let comment = segment(COM, b"hello");
+        let input = image(&[exif.clone(), xmp.clone(), icc.clone(), comment]);
+
+        assert_eq!(
+            strip(&input, MetadataAllowlist::default()).expect("valid"),
+            image(&[])
+        );
+        assert_eq!(
+            strip(
+                &input,
+                MetadataAllowlist {
+                    icc: true,
+                    ..Default::default()
+                }
+            )
+            .expect("valid"),
+            image(&[icc.clone()])
+        );
+        assert_eq!(
+            strip(
+                &input,
+                MetadataAllowlist {
+                    exif: true,
+                    xmp: true,
+                    icc: true,
+                }
+            )
+            .expect("valid"),
+            image(&[exif, xmp, icc])
+        );
+    }
+
+    #[test]
+    fn drops_trailing_data() {
+        let input = [image(&[]), b"trailing video".to_vec()].concat();
+        assert_eq!(
+            strip(&input, MetadataAllowlist::default()).expect("valid"),
+            image(&[])
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let input = image(&[]);
+        for len in 0..input.len() {
+            assert_matches!(
+                strip(&input[..len], MetadataAllowlist::default()),
+                Err(Error::Parse(_)),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_scan_without_frame_header() {
+        let input = [
+            vec![0xff, SOI],
+            segment(SOS, &[1, 1, 0, 0, 0x3f, 0]),
+            vec![0x12, 0xff, EOI],
+        ]
+        .concat();
+        assert_matches!(
+            strip(&input, MetadataAllowlist::default()),
+            Err(Error::Parse(ParseErrorReport {
+                kind: ParseError::MissingRequiredSegment,
+                ..
+            }))
+        );
+    }
+}
@@ -3,12 +3,28 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use futures_util::AsyncRead;
+use std::borrow::Cow;
+use std::io::{self, SeekFrom};
+
+use futures_util::{
+    AsyncRead, AsyncReadExt as _, AsyncSeek, AsyncSeekExt as _, AsyncWrite, AsyncWriteExt as _,
+};
 use mediasan_common::AsyncSkip;
 pub use mp4san::parse::ParseError;
 use mp4san::{sanitize_async_with_config, Config};
 pub use mp4san::{InputSpan, SanitizedMetadata};
 
+use super::AsyncSeekInput;
+
+mod audio;
+pub use audio::{sanitize_audio_seekable, AudioInfo};
+
+mod info;
+pub use info::Mp4Info;
+
+mod push;
+pub use push::PushSanitizer;
+
 /// Error type returned by [`sanitize_mp4`].
 pub type Error = super::error::SanitizerError<ParseError>;
 
@@ -31,5 +47,287 @@ pub async fn sanitize<R: AsyncRead + AsyncSkip>(input: R) -> Result<SanitizedMet
     Ok(metadata)
 }
 
+/// Sanitize an MP4 input that supports seeking, such as a file.
+///
+/// See [`sanitize`]; to sanitize an input while it is still arriving, use [`PushSanitizer`].
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an `Error` is returned.
+pub async fn sanitize_seekable<R: AsyncRead + AsyncSeek + Unpin>(
+    input: R,
+) -> Result<SanitizedMetadata, Error> {
+    sanitize(AsyncSeekInput::new(input)).await
+}
+
+/// Sanitize an MP4 input that supports seeking, also returning details about it for display.
+///
+/// See [`sanitize_seekable`] and [`Mp4Info`]. The details are read from the same `moov` box that
+/// was sanitized, so no separate pass over the media data is needed.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an `Error` is returned.
+pub async fn sanitize_seekable_with_info<R: AsyncRead + AsyncSeek + Unpin>(
+    mut input: R,
+) -> Result<(SanitizedMetadata, Mp4Info), Error> {
+    let sanitized = sanitize(AsyncSeekInput::new(&mut input)).await?;
+    let info = Mp4Info::from_metadata(
+        &*sanitized_moov(&sanitized, &mut input).await?,
+        sanitized.data.len,
+    );
+    Ok((sanitized, info))
+}
+
+/// Returns the metadata containing the sanitized `moov` box for an input.
+async fn sanitized_moov<'a, R: AsyncRead + AsyncSeek + Unpin>(
+    sanitized: &'a SanitizedMetadata,
+    input: &mut R,
+) -> Result<Cow<'a, [u8]>, Error> {
+    match &sanitized.metadata {
+        Some(metadata) => Ok(Cow::Borrowed(metadata)),
+        // The input's own metadata was already fine, so it wasn't copied; read it back.
+        None => Ok(Cow::Owned(read_moov(input).await.map_err(Error::Io)?)),
+    }
+}
+
+/// Sanitize an MP4 input that supports seeking, writing a faststart copy of it to `output`.
+///
+/// The copy has its metadata ahead of the media data, so it can be played while it is still being
+/// downloaded. The media data is streamed from `input` to `output` rather than buffered in memory.
+/// An input that is already sanitized and faststart is copied through unchanged.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs reading the input or writing the output,
+/// an `Error` is returned.
+pub async fn sanitize_seekable_to_writer<R, W>(
+    mut input: R,
+    output: &mut W,
+) -> Result<SanitizedMetadata, Error>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let sanitized = sanitize(AsyncSeekInput::new(&mut input)).await?;
+    write_sanitized(
+        sanitized.metadata.as_deref(),
+        sanitized.data.offset,
+        sanitized.data.len,
+        &mut input,
+        output,
+    )
+    .await
+    .map_err(Error::Io)?;
+    Ok(sanitized)
+}
+
+/// Writes `metadata` followed by the `data_len` bytes of `input` at `data_offset`.
+///
+/// Without `metadata`, the input's own metadata is kept, so all of `input` is copied; it must still
+/// contain the media data.
+async fn write_sanitized<R, W>(
+    metadata: Option<&[u8]>,
+    data_offset: u64,
+    data_len: u64,
+    input: &mut R,
+    output: &mut W,
+) -> io::Result<()>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (copied, expected) = match metadata {
+        Some(metadata) => {
+            output.write_all(metadata).await?;
+            input.seek(SeekFrom::Start(data_offset)).await?;
+            let copied = futures_util::io::copy(input.take(data_len), output).await?;
+            (copied, data_len)
+        }
+        None => {
+            input.seek(SeekFrom::Start(0)).await?;
+            let copied = futures_util::io::copy(input, output).await?;
+            (copied, data_offset.saturating_add(data_len))
+        }
+    };
+    if copied < expected {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    output.flush().await
+}
+
+/// Reads the `moov` box, header included, from an input that has already been sanitized.
+async fn read_moov<R: AsyncRead + AsyncSeek + Unpin>(input: &mut R) -> io::Result<Vec<u8>> {
+    let mut position = input.seek(SeekFrom::Start(0)).await?;
+    loop {
+        let mut header = [0; 16];
+        input.read_exact(&mut header[..8]).await?;
+        let size = u32::from_be_bytes(header[..4].try_into().expect("4 bytes"));
+        let (header_len, size) = match size {
+            1 => {
+                input.read_exact(&mut header[8..]).await?;
+                let size = u64::from_be_bytes(header[8..].try_into().expect("8 bytes"));
+                (16, size)
+            }
+            // A box extending to the end of the input; leave the details out rather than read an
+            // unbounded amount.
+            0 => return Ok(vec![]),
+            size => (8, size.into()),
+        };
+        if &header[4..8] != b"moov" {
+            let next = position
+                .checked_add(size)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "box size overflows"))?;
+            position = input.seek(SeekFrom::Start(next)).await?;
+            continue;
+        }
+        if size > MAX_METADATA_SIZE || size < header_len as u64 {
+            return Ok(vec![]);
+        }
+        let mut moov = header[..header_len].to_vec();
+        moov.resize(size as usize, 0);
+        input.read_exact(&mut moov[header_len..]).await?;
+        return Ok(moov);
+    }
+}
+
 /// The maximum size of metadata to support, setting an upper bound on memory consumption in the parser.
 const MAX_METADATA_SIZE: u64 = 300 * 1024 * 1024;
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use futures::executor::block_on;
+    use futures_util::io::Cursor;
+
+    use super::*;
+
+    fn mp4_box(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let size = u32::try_from(8 + body.len()).unwrap();
+        [&size.to_be_bytes()[..], name, body].concat()
+    }
+
+    fn full_box(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        mp4_box(name, &[&[0; 4][..], body].concat())
+    }
+
+    const MEDIA_DATA: &[u8] = b"one chunk of media data";
+
+    /// A complete MP4 with a single audio sample, with its `moov` box before or after the `mdat`
+    /// box.
+    fn test_mp4(faststart: bool) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let ftyp = mp4_box(b"ftyp", b"isom\0\0\x02\0isommp41");
+        let mdat = mp4_box(b"mdat", MEDIA_DATA);
+        let moov = |chunk_offset: u32| {
+            let one = 1u32.to_be_bytes();
+            let sample_size = u32::try_from(MEDIA_DATA.len()).unwrap().to_be_bytes();
+            let mvhd = full_box(
+                b"mvhd",
+                &[&[0; 8][..], &1000u32.to_be_bytes(), &[0; 84]].concat(),
+            );
+            let tkhd = full_box(b"tkhd", &[0; 80]);
+            let mdhd = full_box(
+                b"mdhd",
+                &[&[0; 8][..], &1000u32.to_be_bytes(), &[0; 8]].concat(),
+            );
+            let hdlr = full_box(b"hdlr", &[&[0; 4][..], b"soun", &[0; 13]].concat());
+            let stsd = full_box(b"stsd", &[&one[..], &mp4_box(b"mp4a", &[0; 28])].concat());
+            let stts = full_box(b"stts", &[&one[..], &one, &1000u32.to_be_bytes()].concat());
+            let stsc = full_box(b"stsc", &[&one[..], &one, &one, &one].concat());
+            let stsz = full_box(b"stsz", &[&[0; 4][..], &one, &sample_size].concat());
+            let stco = full_box(b"stco", &[&one[..], &chunk_offset.to_be_bytes()].concat());
+            let stbl = mp4_box(b"stbl", &[stsd, stts, stsc, stsz, stco].concat());
+            let minf = mp4_box(b"minf", &stbl);
+            let mdia = mp4_box(b"mdia", &[mdhd, hdlr, minf].concat());
+            let trak = mp4_box(b"trak", &[tkhd, mdia].concat());
+            mp4_box(b"moov", &[mvhd, trak].concat())
+        };
+        let moov_len = moov(0).len();
+
+        let file = if faststart {
+            let chunk_offset = ftyp.len() + moov_len + 8;
+            [&ftyp[..], &moov(chunk_offset.try_into().unwrap()), &mdat].concat()
+        } else {
+            let chunk_offset = ftyp.len() + 8;
+            [&ftyp[..], &mdat, &moov(chunk_offset.try_into().unwrap())].concat()
+        };
+        (file, ftyp, mdat)
+    }
+
+    #[test]
+    fn sanitize_to_writer_copies_faststart_input_unchanged() {
+        let (input, _, _) = test_mp4(true);
+        let mut output = vec![];
+        let sanitized = block_on(sanitize_seekable_to_writer(
+            Cursor::new(&input),
+            &mut output,
+        ))
+        .expect("valid");
+        assert!(sanitized.metadata.is_none());
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn sanitize_to_writer_moves_metadata_first() {
+        let (input, ftyp, mdat) = test_mp4(false);
+        let mut output = vec![];
+        let sanitized = block_on(sanitize_seekable_to_writer(
+            Cursor::new(&input),
+            &mut output,
+        ))
+        .expect("valid");
+        let metadata = sanitized.metadata.expect("moov was moved");
+        assert!(metadata.starts_with(&ftyp));
+        assert_eq!(output, [metadata, mdat].concat());
+    }
+
+    #[test]
+    fn read_moov_rejects_overflowing_box_size() {
+        let mut input = Cursor::new(
+            [
+                &mp4_box(b"ftyp", b"isom\0\0\x02\0")[..],
+                &1u32.to_be_bytes(),
+                b"free",
+                &u64::MAX.to_be_bytes(),
+            ]
+            .concat(),
+        );
+        assert_matches!(
+            block_on(read_moov(&mut input)),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn write_sanitized_puts_metadata_before_data() {
+        let mut input = Cursor::new(b"\0\0mdat-contents-moov".to_vec());
+        let mut output = vec![];
+        block_on(write_sanitized(
+            Some(&b"ftyp-moov-"[..]),
+            2,
+            13,
+            &mut input,
+            &mut output,
+        ))
+        .expect("can write");
+        assert_eq!(output, b"ftyp-moov-mdat-contents");
+    }
+
+    #[test]
+    fn write_sanitized_keeps_input_without_new_metadata() {
+        let mut input = Cursor::new(b"ftyp-moov-mdat-contents".to_vec());
+        let mut output = vec![];
+        block_on(write_sanitized(None, 10, 13, &mut input, &mut output)).expect("can write");
+        assert_eq!(output, b"ftyp-moov-mdat-contents");
+    }
+
+    #[test]
+    fn write_sanitized_rejects_short_input() {
+        let mut input = Cursor::new(b"mdat".to_vec());
+        let mut output = vec![];
+        assert_matches!(
+            block_on(write_sanitized(None, 0, 10, &mut input, &mut output)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::time::Duration;
+
+use futures_util::{AsyncRead, AsyncSeek};
+
+use super::info::track_handler_types;
+use super::{
+    sanitize, sanitized_moov, AsyncSeekInput, Error, Mp4Info, ParseError, ParseErrorReport,
+    SanitizedMetadata,
+};
+
+/// Details about a sanitized audio file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AudioInfo {
+    pub duration: Option<Duration>,
+    /// The sample entry type of the audio track, such as `mp4a`.
+    pub codec: Option<String>,
+    /// The average bitrate of the media data, in bits per second.
+    pub bitrate: Option<u64>,
+}
+
+/// Sanitize an audio-only MP4 input, such as an M4A voice note, that supports seeking.
+///
+/// In addition to the checks done by [`sanitize`], the input must have at least one track, and
+/// every track must be an audio track.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, has a track that isn't audio, or an IO error occurs, an `Error`
+/// is returned.
+pub async fn sanitize_audio_seekable<R: AsyncRead + AsyncSeek + Unpin>(
+    mut input: R,
+) -> Result<(SanitizedMetadata, AudioInfo), Error> {
+    let sanitized = sanitize(AsyncSeekInput::new(&mut input)).await?;
+    let info = {
+        let metadata = sanitized_moov(&sanitized, &mut input).await?;
+        check_audio_only(&metadata)?;
+        Mp4Info::from_metadata(&metadata, sanitized.data.len)
+    };
+    Ok((
+        sanitized,
+        AudioInfo {
+            duration: info.duration,
+            codec: info.audio_codec,
+            bitrate: info.bitrate,
+        },
+    ))
+}
+
+fn check_audio_only(metadata: &[u8]) -> Result<(), Error> {
+    let handlers = track_handler_types(metadata);
+    let report = if handlers.is_empty() {
+        "no audio track".to_owned()
+    } else if let Some(handler) = handlers.iter().find(|h| h.as_ref() != Some(b"soun")) {
+        match handler {
+            Some(handler) => format!(
+                "unexpected {} track in audio file",
+                String::from_utf8_lossy(handler)
+            ),
+            None => "track without a handler in audio file".to_owned(),
+        }
+    } else {
+        return Ok(());
+    };
+    Err(Error::Parse(ParseErrorReport {
+        kind: ParseError::InvalidInput,
+        report,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn mp4_box(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let size = u32::try_from(8 + body.len()).unwrap();
+        [&size.to_be_bytes()[..], name, body].concat()
+    }
+
+    fn trak(handler: &[u8; 4]) -> Vec<u8> {
+        let hdlr = mp4_box(b"hdlr", &[&[0; 8][..], handler, &[0; 13]].concat());
+        mp4_box(b"trak", &mp4_box(b"mdia", &hdlr))
+    }
+
+    #[test]
+    fn accepts_only_audio_tracks() {
+        let audio = mp4_box(b"moov", &[trak(b"soun"), trak(b"soun")].concat());
+        assert_matches!(check_audio_only(&audio), Ok(()));
+
+        for moov in [
+            mp4_box(b"moov", &[]),
+            mp4_box(b"moov", &[trak(b"soun"), trak(b"vide")].concat()),
+            mp4_box(b"moov", &mp4_box(b"trak", &[])),
+        ] {
+            assert_matches!(
+                check_audio_only(&moov),
+                Err(Error::Parse(ParseErrorReport {
+                    kind: ParseError::InvalidInput,
+                    ..
+                }))
+            );
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Reading presentation details from the `moov` box of a sanitized MP4.
+//!
+//! This is deliberately lenient: the input has already been validated by the sanitizer, so any
+//! field that can't be found is simply left out of the result.
+
+use std::time::Duration;
+
+/// Details about an MP4 useful for displaying a preview, read from its `moov` box.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Mp4Info {
+    pub duration: Option<Duration>,
+    /// The width of the first video track, in pixels, before rotation.
+    pub width: Option<u32>,
+    /// The height of the first video track, in pixels, before rotation.
+    pub height: Option<u32>,
+    /// The clockwise rotation of the first video track, in degrees: 0, 90, 180, or 270.
+    pub rotation: Option<u16>,
+    /// The sample entry type of the first video track, such as `avc1` or `hvc1`.
+    pub video_codec: Option<String>,
+    /// The sample entry type of the first audio track, such as `mp4a`.
+    pub audio_codec: Option<String>,
+    /// The average bitrate of the media data, in bits per second.
+    pub bitrate: Option<u64>,
+}
+
+const FULL_BOX_HEADER_LEN: usize = 4;
+const MATRIX_LEN: usize = 36;
+
+struct Mp4Box<'a> {
+    name: [u8; 4],
+    body: &'a [u8],
+}
+
+/// Iterates over the boxes in `data`, stopping at the first malformed box.
+fn boxes(mut data: &[u8]) -> impl Iterator<Item = Mp4Box<'_>> {
+    std::iter::from_fn(move || {
+        let size = u32::from_be_bytes(data.get(..4)?.try_into().ok()?);
+        let name: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+        let (header_len, size) = match size {
+            0 => (8, data.len()),
+            1 => (
+                16,
+                usize::try_from(u64::from_be_bytes(data.get(8..16)?.try_into().ok()?)).ok()?,
+            ),
+            size => (8, usize::try_from(size).ok()?),
+        };
+        let body = data.get(header_len..size)?;
+        data = &data[size..];
+        Some(Mp4Box { name, body })
+    })
+}
+
+fn find<'a>(data: &'a [u8], name: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data).find(|b| &b.name == name).map(|b| b.body)
+}
+
+fn find_path<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, name| find(data, name))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+/// Reads the timescale and duration from an `mvhd` or `mdhd` box.
+fn timescale_and_duration(header: &[u8]) -> Option<(u32, u64)> {
+    match header.first()? {
+        0 => Some((read_u32(header, 12)?, read_u32(header, 16)?.into())),
+        1 => Some((read_u32(header, 20)?, read_u64(header, 24)?)),
+        _ => None,
+    }
+}
+
+fn to_duration(timescale: u32, duration: u64) -> Option<Duration> {
+    if timescale == 0 || duration == u64::MAX || duration == u32::MAX.into() {
+        // All ones means the duration is unknown.
+        return None;
+    }
+    let secs = duration / u64::from(timescale);
+    let nanos = (duration % u64::from(timescale)) * 1_000_000_000 / u64::from(timescale);
+    Some(Duration::new(secs, nanos.try_into().ok()?))
+}
+
+struct TrackHeader {
+    width: u32,
+    height: u32,
+    rotation: u16,
+}
+
+fn track_header(tkhd: &[u8]) -> Option<TrackHeader> {
+    let matrix_offset = match tkhd.first()? {
+        0 => FULL_BOX_HEADER_LEN + 20 + 16,
+        1 => FULL_BOX_HEADER_LEN + 32 + 16,
+        _ => return None,
+    };
+    let matrix = tkhd.get(matrix_offset..matrix_offset + MATRIX_LEN)?;
+    let a = read_u32(matrix, 0)? as i32;
+    let b = read_u32(matrix, 4)? as i32;
+    let rotation = match (a.signum(), b.signum()) {
+        (0, 1) => 90,
+        (-1, 0) => 180,
+        (0, -1) => 270,
+        _ => 0,
+    };
+    // Width and height are 16.16 fixed-point.
+    let width = read_u32(tkhd, matrix_offset + MATRIX_LEN)? >> 16;
+    let height = read_u32(tkhd, matrix_offset + MATRIX_LEN + 4)? >> 16;
+    Some(TrackHeader {
+        width,
+        height,
+        rotation,
+    })
+}
+
+fn handler_type(mdia: &[u8]) -> Option<[u8; 4]> {
+    let hdlr = find(mdia, b"hdlr")?;
+    hdlr.get(FULL_BOX_HEADER_LEN + 4..FULL_BOX_HEADER_LEN + 8)?
+        .try_into()
+        .ok()
+}
+
+/// Returns the handler type of each track, such as `vide` or `soun`, or `None` for a track whose
+/// handler couldn't be read.
+pub(super) fn track_handler_types(metadata: &[u8]) -> Vec<Option<[u8; 4]>> {
+    let Some(moov) = find(metadata, b"moov") else {
+        return vec![];
+    };
+    boxes(moov)
+        .filter(|b| &b.name == b"trak")
+        .map(|trak| find(trak.body, b"mdia").and_then(handler_type))
+        .collect()
+}
+
+fn codec(mdia: &[u8]) -> Option<String> {
+    let stsd = find_path(mdia, &[b"minf", b"stbl", b"stsd"])?;
+    // Skip the entry count to get to the first sample entry.
+    let entry = boxes(stsd.get(FULL_BOX_HEADER_LEN + 4..)?).next()?;
+    std::str::from_utf8(&entry.name).ok().map(str::to_owned)
+}
+
+impl Mp4Info {
+    /// Reads details from the metadata of an MP4, which should contain its `moov` box.
+    ///
+    /// `media_data_len` is the size of the media data, such as
+    /// [`SanitizedMetadata::data`](super::SanitizedMetadata::data), and is used to compute the
+    /// bitrate.
+    pub fn from_metadata(metadata: &[u8], media_data_len: u64) -> Self {
+        let mut info = Self::default();
+        let Some(moov) = find(metadata, b"moov") else {
+            return info;
+        };
+
+        info.duration = find(moov, b"mvhd")
+            .and_then(timescale_and_duration)
+            .and_then(|(timescale, duration)| to_duration(timescale, duration));
+
+        for trak in boxes(moov).filter(|b| &b.name == b"trak") {
+            let Some(mdia) = find(trak.body, b"mdia") else {
+                continue;
+            };
+            match &handler_type(mdia).unwrap_or_default() {
+                b"vide" if info.video_codec.is_none() => {
+                    info.video_codec = codec(mdia);
+                    if let Some(header) = find(trak.body, b"tkhd").and_then(track_header) {
+                        info.width = Some(header.width);
+                        info.height = Some(header.height);
+                        info.rotation = Some(header.rotation);
+                    }
+                }
+                b"soun" if info.audio_codec.is_none() => {
+                    info.audio_codec = codec(mdia);
+                }
+                _ => {}
+            }
+        }
+
+        info.bitrate = info
+            .duration
+            .filter(|duration| !duration.is_zero())
+            .map(|duration| {
+                (u128::from(media_data_len) * 8 * 1000 / duration.as_millis().max(1)) as u64
+            });
+        info
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mp4_box(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let size = u32::try_from(8 + body.len()).unwrap();
+        [&size.to_be_bytes()[..], name, body].concat()
+    }
+
+    fn full_box(name: &[u8; 4], version: u8, body: &[u8]) -> Vec<u8> {
+        mp4_box(name, &[&[version, 0, 0, 0][..], body].concat())
+    }
+
+    fn mvhd(timescale: u32, duration: u32) -> Vec<u8> {
+        let body = [
+            &[0; 8][..],
+            &timescale.to_be_bytes(),
+            &duration.to_be_bytes(),
+            &[0; 80],
+        ]
+        .concat();
+        full_box(b"mvhd", 0, &body)
+    }
+
+    fn tkhd(matrix: [i32; 9], width: u32, height: u32) -> Vec<u8> {
+        let matrix: Vec<u8> = matrix.iter().flat_map(|x| x.to_be_bytes()).collect();
+        let body = [
+            &[0; 20][..],
+            &[0; 16],
+            &matrix,
+            &(width << 16).to_be_bytes(),
+            &(height << 16).to_be_bytes(),
+        ]
+        .concat();
+        full_box(b"tkhd", 0, &body)
+    }
+
+    fn mdia(handler: &[u8; 4], codec: &[u8; 4]) -> Vec<u8> {
+        let hdlr = full_box(b"hdlr", 0, &[&[0; 4][..], handler, &[0; 13]].concat());
+        let stsd = full_box(
+            b"stsd",
+            0,
+            &[&1u32.to_be_bytes()[..], &mp4_box(codec, &[0; 8])].concat(),
+        );
+        let stbl = mp4_box(b"stbl", &stsd);
+        let minf = mp4_box(b"minf", &stbl);
+        mp4_box(b"mdia", &[hdlr, minf].concat())
+    }
+
+    const ONE: i32 = 1 << 16;
+    const IDENTITY: [i32; 9] = [ONE, 0, 0, 0, ONE, 0, 0, 0, 1 << 30];
+    const ROTATE_90: [i32; 9] = [0, ONE, 0, -ONE, 0, 0, 0, 0, 1 << 30];
+
+    #[test]
+    fn reads_video_and_audio_tracks() {
+        let video = mp4_box(
+            b"trak",
+            &[tkhd(ROTATE_90, 1920, 1080), mdia(b"vide", b"avc1")].concat(),
+        );
+        let audio = mp4_box(
+            b"trak",
+            &[tkhd(IDENTITY, 0, 0), mdia(b"soun", b"mp4a")].concat(),
+        );
+        let moov = mp4_box(b"moov", &[mvhd(1000, 2500), video, audio].concat());
+        let metadata = [mp4_box(b"ftyp", b"isom\0\0\0\0"), moov].concat();
+
+        assert_eq!(
+            track_handler_types(&metadata),
+            [Some(*b"vide"), Some(*b"soun")]
+        );
+        assert_eq!(
+            Mp4Info::from_metadata(&metadata, 1_000_000),
+            Mp4Info {
+                duration: Some(Duration::from_millis(2500)),
+                width: Some(1920),
+                height: Some(1080),
+                rotation: Some(90),
+                video_codec: Some("avc1".to_owned()),
+                audio_codec: Some("mp4a".to_owned()),
+                bitrate: Some(3_200_000),
+            }
+        );
+    }
+
+    #[test]
+    fn tolerates_missing_boxes() {
+        assert_eq!(Mp4Info::from_metadata(&[], 0), Mp4Info::default());
+
+        let moov = mp4_box(b"moov", &mp4_box(b"trak", &mdia(b"soun", b"Opus")));
+        assert_eq!(
+            Mp4Info::from_metadata(&moov, 100),
+            Mp4Info {
+                audio_codec: Some("Opus".to_owned()),
+                ..Default::default()
+            }
+        );
+
+        let truncated = &mp4_box(b"moov", &mvhd(1000, 2500))[..20];
+        assert_eq!(track_handler_types(truncated), []);
+        assert_eq!(Mp4Info::from_metadata(truncated, 0), Mp4Info::default());
+    }
+}
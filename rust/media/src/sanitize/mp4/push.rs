@@ -0,0 +1,208 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+
+use futures_util::task::noop_waker_ref;
+use futures_util::AsyncRead;
+use mediasan_common::AsyncSkip;
+
+use super::{sanitize, Error, SanitizedMetadata};
+
+type SanitizeFuture = Pin<Box<dyn Future<Output = Result<SanitizedMetadata, Error>> + Send>>;
+
+/// Sanitizes an MP4 input as it arrives, such as while it is being downloaded.
+///
+/// Data is provided with [`push`](Self::push) in order, and the result is retrieved with
+/// [`finish`](Self::finish). Only data the parser hasn't consumed yet is buffered; data it skips
+/// over, like the contents of the `mdat` box, is discarded as it arrives.
+pub struct PushSanitizer {
+    pipe: Arc<Mutex<Pipe>>,
+    state: PushState,
+}
+
+enum PushState {
+    Running(SanitizeFuture),
+    Done(Result<SanitizedMetadata, Error>),
+}
+
+#[derive(Default)]
+struct Pipe {
+    buffer: VecDeque<u8>,
+    position: u64,
+    skip_remaining: Option<u64>,
+    closed: bool,
+}
+
+/// The parser's end of the [`Pipe`].
+struct PipeInput {
+    pipe: Arc<Mutex<Pipe>>,
+    len: u64,
+}
+
+impl PushSanitizer {
+    /// Starts sanitizing an input of `len` bytes.
+    pub fn new(len: u64) -> Self {
+        let pipe = Arc::new(Mutex::new(Pipe::default()));
+        let input = PipeInput {
+            pipe: Arc::clone(&pipe),
+            len,
+        };
+        let mut sanitizer = Self {
+            pipe,
+            state: PushState::Running(Box::pin(sanitize(input))),
+        };
+        sanitizer.poll();
+        sanitizer
+    }
+
+    /// Provides the next part of the input.
+    ///
+    /// # Errors
+    ///
+    /// If the input seen so far can't be parsed, the error is returned here rather than waiting
+    /// for [`finish`](Self::finish), so the rest of the download can be abandoned.
+    pub fn push(&mut self, data: &[u8]) -> Result<(), Error> {
+        if let PushState::Running(_) = self.state {
+            self.pipe
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .buffer
+                .extend(data);
+            self.poll();
+        }
+        match &self.state {
+            PushState::Done(Err(e)) => Err(clone_error(e)),
+            PushState::Running(_) | PushState::Done(Ok(_)) => Ok(()),
+        }
+    }
+
+    /// Marks the end of the input and returns the result of sanitizing it.
+    ///
+    /// # Errors
+    ///
+    /// If the input cannot be parsed, or ended early, an `Error` is returned.
+    pub fn finish(mut self) -> Result<SanitizedMetadata, Error> {
+        self.pipe
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .closed = true;
+        self.poll();
+        match self.state {
+            PushState::Done(result) => result,
+            PushState::Running(_) => Err(Error::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "sanitizer did not finish at the end of the input",
+            ))),
+        }
+    }
+
+    fn poll(&mut self) {
+        let PushState::Running(future) = &mut self.state else {
+            return;
+        };
+        // Every poll is driven by new data arriving, so there's nothing for a waker to do.
+        let mut cx = Context::from_waker(noop_waker_ref());
+        if let Poll::Ready(result) = future.as_mut().poll(&mut cx) {
+            self.state = PushState::Done(result);
+        }
+    }
+}
+
+fn clone_error(error: &Error) -> Error {
+    match error {
+        Error::Io(e) => Error::Io(io::Error::new(e.kind(), e.to_string())),
+        Error::Parse(report) => Error::Parse(report.clone()),
+    }
+}
+
+impl PipeInput {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Pipe> {
+        self.pipe.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl AsyncRead for PipeInput {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.lock();
+        if pipe.buffer.is_empty() {
+            return if pipe.closed {
+                Poll::Ready(Ok(0))
+            } else {
+                Poll::Pending
+            };
+        }
+        let amount = buf.len().min(pipe.buffer.len());
+        for (dst, src) in buf.iter_mut().zip(pipe.buffer.drain(..amount)) {
+            *dst = src;
+        }
+        pipe.position += amount as u64;
+        Poll::Ready(Ok(amount))
+    }
+}
+
+impl AsyncSkip for PipeInput {
+    fn poll_skip(self: Pin<&mut Self>, _cx: &mut Context<'_>, amount: u64) -> Poll<io::Result<()>> {
+        let mut pipe = self.lock();
+        let remaining = *pipe.skip_remaining.get_or_insert(amount);
+        let available = usize::try_from(remaining)
+            .unwrap_or(usize::MAX)
+            .min(pipe.buffer.len());
+        pipe.buffer.drain(..available);
+        pipe.position += available as u64;
+
+        let remaining = remaining - available as u64;
+        if remaining == 0 {
+            pipe.skip_remaining = None;
+            Poll::Ready(Ok(()))
+        } else if pipe.closed {
+            pipe.skip_remaining = None;
+            Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()))
+        } else {
+            pipe.skip_remaining = Some(remaining);
+            Poll::Pending
+        }
+    }
+
+    fn poll_stream_position(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.lock().position))
+    }
+
+    fn poll_stream_len(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.len))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_input() {
+        let input = b"\0\0\0\x10not an mp4 file";
+        let mut sanitizer = PushSanitizer::new(input.len() as u64);
+        // The error may be found as soon as the data arrives, or only at the end.
+        let _ = sanitizer.push(input);
+        assert_matches!(sanitizer.finish(), Err(_));
+    }
+
+    #[test]
+    fn reports_truncated_input() {
+        let mut sanitizer = PushSanitizer::new(1024);
+        sanitizer.push(&[0, 0]).expect("not enough to fail yet");
+        assert_matches!(sanitizer.finish(), Err(_));
+    }
+}
@@ -0,0 +1,323 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Removing metadata from PNG images.
+//!
+//! The chunk structure and checksums of the image are validated as it is copied, but the image
+//! data is passed through without being decompressed. Ancillary chunks that don't affect how the
+//! image is displayed are removed unless allowed, as is any data after the end of the image.
+
+use std::io::{self, Read, Write};
+
+use super::MetadataAllowlist;
+
+/// Error type returned by [`strip_metadata`].
+pub type Error = super::error::SanitizerError<ParseError>;
+
+/// A decomposed and stringified PNG parse error.
+pub type ParseErrorReport = super::error::ParseErrorReport<ParseError>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// The input is not a PNG, or violates the PNG format.
+    #[error("invalid input")]
+    InvalidInput,
+    /// A chunk is in the wrong place or has an invalid length or type.
+    #[error("invalid chunk layout")]
+    InvalidChunkLayout,
+    /// A chunk's CRC doesn't match its contents.
+    #[error("invalid chunk checksum")]
+    InvalidChecksum,
+    /// The image has no header or no image data.
+    #[error("missing required chunk")]
+    MissingRequiredChunk,
+    /// The input ended in the middle of a chunk, or before the end of the image.
+    #[error("truncated chunk")]
+    TruncatedChunk,
+    /// The image has a critical chunk that isn't part of the PNG specification.
+    #[error("unsupported chunk")]
+    UnsupportedChunk,
+}
+
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+const IHDR_LEN: u32 = 13;
+const MAX_CHUNK_LEN: u32 = (1 << 31) - 1;
+const XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp\0";
+
+/// Ancillary chunks that affect how the image is displayed, including the APNG animation chunks,
+/// which are always kept.
+const DISPLAY_CHUNKS: &[&[u8; 4]] = &[
+    b"acTL", b"bKGD", b"cHRM", b"cICP", b"cLLI", b"fcTL", b"fdAT", b"gAMA", b"hIST", b"mDCV",
+    b"pHYs", b"sBIT", b"sPLT", b"sRGB", b"tRNS",
+];
+
+fn parse_error(kind: ParseError, report: &str) -> Error {
+    Error::Parse(ParseErrorReport {
+        kind,
+        report: report.to_owned(),
+    })
+}
+
+fn read_exact<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<(), Error> {
+    input.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            parse_error(ParseError::TruncatedChunk, "input ended early")
+        }
+        _ => Error::Io(e),
+    })
+}
+
+fn is_critical(chunk_type: &[u8; 4]) -> bool {
+    chunk_type[0].is_ascii_uppercase()
+}
+
+/// Decides whether to keep an ancillary chunk, given as much of the start of its data as fits in
+/// `prefix`.
+fn keep_ancillary_chunk(chunk_type: &[u8; 4], prefix: &[u8], allow: &MetadataAllowlist) -> bool {
+    match chunk_type {
+        b"eXIf" => allow.exif,
+        b"iCCP" => allow.icc,
+        b"iTXt" if prefix.starts_with(XMP_KEYWORD) => allow.xmp,
+        _ => DISPLAY_CHUNKS.contains(&chunk_type),
+    }
+}
+
+/// Copies a PNG image from `input` to `output`, removing metadata not in `allow`.
+///
+/// Text chunks and timestamps are always removed. Data is streamed rather than buffered, so
+/// `output` should be discarded if an error is returned.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs reading the input or writing the output,
+/// an `Error` is returned.
+pub fn strip_metadata<R: Read, W: Write>(
+    mut input: R,
+    mut output: W,
+    allow: &MetadataAllowlist,
+) -> Result<(), Error> {
+    let mut signature = [0; SIGNATURE.len()];
+    read_exact(&mut input, &mut signature)?;
+    if &signature != SIGNATURE {
+        return Err(parse_error(
+            ParseError::InvalidInput,
+            "missing PNG signature",
+        ));
+    }
+    output.write_all(SIGNATURE).map_err(Error::Io)?;
+
+    let mut seen_header = false;
+    let mut seen_data = false;
+    loop {
+        let mut header = [0; 8];
+        read_exact(&mut input, &mut header)?;
+        let len = u32::from_be_bytes(header[..4].try_into().expect("4 bytes"));
+        let chunk_type: [u8; 4] = header[4..].try_into().expect("4 bytes");
+        if len > MAX_CHUNK_LEN || !chunk_type.iter().all(u8::is_ascii_alphabetic) {
+            return Err(parse_error(
+                ParseError::InvalidChunkLayout,
+                "invalid chunk header",
+            ));
+        }
+
+        match &chunk_type {
+            b"IHDR" if !seen_header && len == IHDR_LEN => seen_header = true,
+            _ if !seen_header => {
+                return Err(parse_error(
+                    ParseError::MissingRequiredChunk,
+                    "first chunk is not IHDR",
+                ))
+            }
+            b"IHDR" => {
+                return Err(parse_error(
+                    ParseError::InvalidChunkLayout,
+                    "unexpected IHDR chunk",
+                ))
+            }
+            b"IDAT" => seen_data = true,
+            b"IEND" if !seen_data => {
+                return Err(parse_error(
+                    ParseError::MissingRequiredChunk,
+                    "no IDAT chunk before IEND",
+                ))
+            }
+            b"PLTE" | b"IEND" => {}
+            chunk_type if is_critical(chunk_type) => {
+                return Err(parse_error(
+                    ParseError::UnsupportedChunk,
+                    "unknown critical chunk",
+                ))
+            }
+            _ => {}
+        }
+
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(&chunk_type);
+        let mut prefix = vec![0; len.min(XMP_KEYWORD.len() as u32) as usize];
+        read_exact(&mut input, &mut prefix)?;
+        crc.update(&prefix);
+
+        let keep = is_critical(&chunk_type) || keep_ancillary_chunk(&chunk_type, &prefix, allow);
+        if keep {
+            output.write_all(&header).map_err(Error::Io)?;
+            output.write_all(&prefix).map_err(Error::Io)?;
+        }
+        let rest = u64::from(len) - prefix.len() as u64;
+        let mut checked_input = CrcReader {
+            inner: (&mut input).take(rest),
+            crc: &mut crc,
+        };
+        let copied = if keep {
+            io::copy(&mut checked_input, &mut output)
+        } else {
+            io::copy(&mut checked_input, &mut io::sink())
+        }
+        .map_err(Error::Io)?;
+        if copied != rest {
+            return Err(parse_error(ParseError::TruncatedChunk, "input ended early"));
+        }
+
+        let mut expected_crc = [0; 4];
+        read_exact(&mut input, &mut expected_crc)?;
+        if crc.finalize() != u32::from_be_bytes(expected_crc) {
+            return Err(parse_error(ParseError::InvalidChecksum, "CRC mismatch"));
+        }
+        if keep {
+            output.write_all(&expected_crc).map_err(Error::Io)?;
+        }
+
+        if &chunk_type == b"IEND" {
+            return output.flush().map_err(Error::Io);
+        }
+    }
+}
+
+/// Updates a CRC with everything read through it.
+struct CrcReader<'a, R> {
+    inner: R,
+    crc: &'a mut crc32fast::Hasher,
+}
+
+impl<R: Read> Read for CrcReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.crc.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(chunk_type);
+        crc.update(data);
+        [
+            &u32::try_from(data.len()).unwrap().to_be_bytes()[..],
+            chunk_type,
+            data,
+            &crc.finalize().to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    fn image(metadata: &[Vec<u8>]) -> Vec<u8> {
+        [
+            SIGNATURE.to_vec(),
+            chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0]),
+            chunk(b"gAMA", &45455u32.to_be_bytes()),
+            metadata.concat(),
+            chunk(b"IDAT", &[0x78, 0x9c, 0x63, 0x60, 0, 0, 0, 2, 0, 1]),
+            chunk(b"IEND", &[]),
+        ]
+        .concat()
+    }
+
+    fn strip(input: &[u8], allow: MetadataAllowlist) -> Result<Vec<u8>, Error> {
+        let mut output = vec![];
+        strip_metadata(input, &mut output, &allow)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn removes_metadata() {
+        let exif = chunk(b"eXIf", b"MM\0*");
+        let xmp = chunk(b"iTXt", &[XMP_KEYWORD, b"\0\0\0\0<x:xmpmeta/>"].concat());
+        let icc = chunk(b"iCCP", b"profile\0\0compressed");
+        let text = chunk(b"tEXt", b"Comment\0hello");
+        let time = chunk(b"tIME", &[7, 232, 1, 1, 0, 0, 0]);
+        let input = image(&[exif.clone(), xmp.clone(), icc.clone(), text, time]);
+
+        assert_eq!(
+            strip(&input, MetadataAllowlist::default()).expect("valid"),
+            image(&[])
+        );
+        assert_eq!(
+            strip(
+                &input,
+                MetadataAllowlist {
+                    icc: true,
+                    ..Default::default()
+                }
+            )
+            .expect("valid"),
+            image(&[icc.clone()])
+        );
+        assert_eq!(
+            strip(
+                &input,
+                MetadataAllowlist {
+                    exif: true,
+                    xmp: true,
+                    icc: true,
+                }
+            )
+            .expect("valid"),
+            image(&[exif, xmp, icc])
+        );
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut input = image(&[]);
+        let last = input.len() - 1;
+        input[last] ^= 1;
+        assert_matches!(
+            strip(&input, MetadataAllowlist::default()),
+            Err(Error::Parse(ParseErrorReport {
+                kind: ParseError::InvalidChecksum,
+                ..
+            }))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let input = image(&[]);
+        for len in 0..input.len() {
+            assert_matches!(
+                strip(&input[..len], MetadataAllowlist::default()),
+                Err(Error::Parse(_)),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_critical_chunk() {
+        let input = image(&[chunk(b"ABCD", &[])]);
+        assert_matches!(
+            strip(&input, MetadataAllowlist::default()),
+            Err(Error::Parse(ParseErrorReport {
+                kind: ParseError::UnsupportedChunk,
+                ..
+            }))
+        );
+    }
+}
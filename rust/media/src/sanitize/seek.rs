@@ -0,0 +1,191 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_util::{AsyncRead, AsyncSeek};
+use mediasan_common::{AsyncSkip, Skip};
+
+/// Adapts a [`Read`] + [`Seek`] input, such as a file, for the synchronous sanitizers, which only
+/// need to skip forward.
+pub struct SeekInput<R> {
+    inner: R,
+}
+
+impl<R> SeekInput<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for SeekInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Skip for SeekInput<R> {
+    fn skip(&mut self, amount: u64) -> io::Result<()> {
+        let amount = i64::try_from(amount)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "skip amount too large"))?;
+        self.inner.seek(SeekFrom::Current(amount))?;
+        Ok(())
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        self.inner.stream_position()
+    }
+
+    fn stream_len(&mut self) -> io::Result<u64> {
+        let position = self.inner.stream_position()?;
+        let len = self.inner.seek(SeekFrom::End(0))?;
+        self.inner.seek(SeekFrom::Start(position))?;
+        Ok(len)
+    }
+}
+
+/// Adapts an [`AsyncRead`] + [`AsyncSeek`] input, such as a file, for the sanitizers, which only
+/// need to skip forward.
+pub struct AsyncSeekInput<R> {
+    inner: R,
+    len: Option<u64>,
+    /// The position to return to while seeking to the end to find the length.
+    position_before_len: Option<u64>,
+}
+
+impl<R> AsyncSeekInput<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            len: None,
+            position_before_len: None,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncSeekInput<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<R: AsyncSeek + Unpin> AsyncSkip for AsyncSeekInput<R> {
+    fn poll_skip(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        amount: u64,
+    ) -> Poll<io::Result<()>> {
+        let amount = i64::try_from(amount)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "skip amount too large"))?;
+        ready!(Pin::new(&mut self.inner).poll_seek(cx, SeekFrom::Current(amount)))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_stream_position(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_seek(cx, SeekFrom::Current(0))
+    }
+
+    fn poll_stream_len(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        if let (Some(len), None) = (self.len, self.position_before_len) {
+            return Poll::Ready(Ok(len));
+        }
+
+        let position = match self.position_before_len {
+            Some(position) => position,
+            None => {
+                let position =
+                    ready!(Pin::new(&mut self.inner).poll_seek(cx, SeekFrom::Current(0)))?;
+                self.position_before_len = Some(position);
+                position
+            }
+        };
+        let len = match self.len {
+            Some(len) => len,
+            None => {
+                let len = ready!(Pin::new(&mut self.inner).poll_seek(cx, SeekFrom::End(0)))?;
+                self.len = Some(len);
+                len
+            }
+        };
+        ready!(Pin::new(&mut self.inner).poll_seek(cx, SeekFrom::Start(position)))?;
+        self.position_before_len = None;
+        Poll::Ready(Ok(len))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::io::Cursor;
+    use futures_util::{future, AsyncReadExt as _};
+
+    use super::*;
+
+    fn skip(input: &mut AsyncSeekInput<Cursor<Vec<u8>>>, amount: u64) -> io::Result<()> {
+        futures::executor::block_on(future::poll_fn(|cx| {
+            Pin::new(&mut *input).poll_skip(cx, amount)
+        }))
+    }
+
+    fn position(input: &mut AsyncSeekInput<Cursor<Vec<u8>>>) -> io::Result<u64> {
+        futures::executor::block_on(future::poll_fn(|cx| {
+            Pin::new(&mut *input).poll_stream_position(cx)
+        }))
+    }
+
+    fn len(input: &mut AsyncSeekInput<Cursor<Vec<u8>>>) -> io::Result<u64> {
+        futures::executor::block_on(future::poll_fn(|cx| {
+            Pin::new(&mut *input).poll_stream_len(cx)
+        }))
+    }
+
+    #[test]
+    fn skips_and_reports_length() {
+        let mut input = AsyncSeekInput::new(Cursor::new((0..10).collect::<Vec<u8>>()));
+        let mut buf = [0; 2];
+
+        futures::executor::block_on(input.read_exact(&mut buf)).unwrap();
+        assert_eq!(buf, [0, 1]);
+        skip(&mut input, 3).unwrap();
+        assert_eq!(position(&mut input).unwrap(), 5);
+        assert_eq!(len(&mut input).unwrap(), 10);
+        assert_eq!(position(&mut input).unwrap(), 5);
+
+        futures::executor::block_on(input.read_exact(&mut buf)).unwrap();
+        assert_eq!(buf, [5, 6]);
+    }
+
+    #[test]
+    fn sync_skips_and_reports_length() {
+        let mut input = SeekInput::new(std::io::Cursor::new((0..10).collect::<Vec<u8>>()));
+        let mut buf = [0; 2];
+
+        input.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0, 1]);
+        input.skip(3).unwrap();
+        assert_eq!(Skip::stream_position(&mut input).unwrap(), 5);
+        assert_eq!(input.stream_len().unwrap(), 10);
+        assert_eq!(Skip::stream_position(&mut input).unwrap(), 5);
+
+        input.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [5, 6]);
+    }
+}
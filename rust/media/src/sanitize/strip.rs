@@ -0,0 +1,19 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+/// Which kinds of metadata to keep when stripping an image with [`jpeg::strip_metadata`] or
+/// [`png::strip_metadata`].
+///
+/// By default, everything is removed. Keeping the ICC profile is usually safe and preserves the
+/// image's colors; EXIF and XMP can contain the location where the image was taken.
+///
+/// [`jpeg::strip_metadata`]: super::jpeg::strip_metadata
+/// [`png::strip_metadata`]: super::png::strip_metadata
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MetadataAllowlist {
+    pub exif: bool,
+    pub xmp: bool,
+    pub icc: bool,
+}
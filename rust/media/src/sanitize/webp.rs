@@ -3,11 +3,266 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::io::{self, Read, Seek, SeekFrom};
+
 pub use webpsan::parse::ParseError;
 pub use webpsan::sanitize;
 
+use super::SeekInput;
+
 /// Error type returned by [`sanitize_webp`].
 pub type Error = super::error::SanitizerError<ParseError>;
 
 /// A decomposed and stringified [`error_stack::Report<ParseError>`](mediasan_common::Error::Parse).
 pub type ParseErrorReport = super::error::ParseErrorReport<ParseError>;
+
+/// Normalized details about a sanitized WebP image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebpInfo {
+    pub canvas_width: u32,
+    pub canvas_height: u32,
+    /// The number of frames; 1 for a still image.
+    pub frame_count: u32,
+    /// How many times an animation should be played, where 0 means forever, or `None` for a still
+    /// image.
+    pub loop_count: Option<u16>,
+}
+
+/// Sanitize a WebP input that supports seeking, returning details about it.
+///
+/// In addition to the checks done by [`sanitize`], every frame of an animated image must lie
+/// within the canvas.
+///
+/// # Errors
+///
+/// If the input cannot be parsed, or an IO error occurs, an `Error` is returned.
+pub fn sanitize_with_info<R: Read + Seek>(mut input: R) -> Result<WebpInfo, Error> {
+    sanitize(SeekInput::new(&mut input))?;
+    input.seek(SeekFrom::Start(0)).map_err(Error::Io)?;
+    read_info(&mut input)
+}
+
+const RIFF_HEADER_LEN: usize = 12;
+const CHUNK_HEADER_LEN: usize = 8;
+const VP8X_LEN: usize = 10;
+const ANIM_LEN: usize = 6;
+const ANMF_HEADER_LEN: usize = 16;
+const VP8_HEADER_LEN: usize = 10;
+const VP8L_HEADER_LEN: usize = 5;
+const VP8X_ANIMATION_FLAG: u8 = 0x02;
+
+fn invalid(report: &str) -> Error {
+    Error::Parse(ParseErrorReport {
+        kind: ParseError::InvalidInput,
+        report: report.to_owned(),
+    })
+}
+
+fn read_u24(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])
+}
+
+/// Reads the chunk headers of an already-sanitized WebP, reading only the parts of chunk payloads
+/// needed for [`WebpInfo`].
+fn read_info<R: Read + Seek>(input: &mut R) -> Result<WebpInfo, Error> {
+    input
+        .seek(SeekFrom::Start(RIFF_HEADER_LEN as u64))
+        .map_err(Error::Io)?;
+
+    let mut canvas = None;
+    let mut animated = false;
+    let mut loop_count = None;
+    let mut frame_count = 0;
+
+    while let Some((name, len)) = read_chunk_header(input)? {
+        let padded_len = u64::from(len) + u64::from(len % 2);
+        let read_len = match &name {
+            b"VP8X" => VP8X_LEN,
+            b"ANIM" => ANIM_LEN,
+            b"ANMF" => ANMF_HEADER_LEN,
+            b"VP8 " if canvas.is_none() => VP8_HEADER_LEN,
+            b"VP8L" if canvas.is_none() => VP8L_HEADER_LEN,
+            _ => 0,
+        };
+        if u64::from(len) < read_len as u64 {
+            return Err(invalid("chunk too short"));
+        }
+        let mut payload = vec![0; read_len];
+        input.read_exact(&mut payload).map_err(Error::Io)?;
+
+        match &name {
+            b"VP8X" => {
+                animated = payload[0] & VP8X_ANIMATION_FLAG != 0;
+                canvas = Some((read_u24(&payload[4..]) + 1, read_u24(&payload[7..]) + 1));
+            }
+            b"ANIM" => loop_count = Some(u16::from_le_bytes([payload[4], payload[5]])),
+            b"ANMF" => {
+                let (canvas_width, canvas_height) =
+                    canvas.ok_or_else(|| invalid("ANMF chunk without VP8X chunk"))?;
+                let x = u64::from(read_u24(&payload[0..])) * 2;
+                let y = u64::from(read_u24(&payload[3..])) * 2;
+                let width = u64::from(read_u24(&payload[6..])) + 1;
+                let height = u64::from(read_u24(&payload[9..])) + 1;
+                if x + width > canvas_width.into() || y + height > canvas_height.into() {
+                    return Err(invalid("animation frame extends outside the canvas"));
+                }
+                frame_count += 1;
+            }
+            b"VP8 " if read_len != 0 => {
+                // A 3-byte frame tag and 3-byte start code precede the 14-bit dimensions.
+                let width = u16::from_le_bytes([payload[6], payload[7]]) & 0x3fff;
+                let height = u16::from_le_bytes([payload[8], payload[9]]) & 0x3fff;
+                canvas = Some((width.into(), height.into()));
+            }
+            b"VP8L" if read_len != 0 => {
+                // A 1-byte signature precedes the 14-bit dimensions, each stored minus one.
+                let bits = u32::from_le_bytes(payload[1..5].try_into().expect("4 bytes"));
+                canvas = Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1));
+            }
+            _ => {}
+        }
+
+        let remaining = i64::try_from(padded_len - read_len as u64).expect("chunk len fits");
+        input
+            .seek(SeekFrom::Current(remaining))
+            .map_err(Error::Io)?;
+    }
+
+    let (canvas_width, canvas_height) = canvas.ok_or_else(|| invalid("no image data"))?;
+    if animated {
+        if frame_count == 0 {
+            return Err(invalid("animation has no frames"));
+        }
+        Ok(WebpInfo {
+            canvas_width,
+            canvas_height,
+            frame_count,
+            loop_count: Some(loop_count.ok_or_else(|| invalid("animation has no ANIM chunk"))?),
+        })
+    } else {
+        Ok(WebpInfo {
+            canvas_width,
+            canvas_height,
+            frame_count: 1,
+            loop_count: None,
+        })
+    }
+}
+
+fn read_chunk_header<R: Read>(input: &mut R) -> Result<Option<([u8; 4], u32)>, Error> {
+    let mut header = [0; CHUNK_HEADER_LEN];
+    match input.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(Error::Io(e)),
+    }
+    let name = header[..4].try_into().expect("4 bytes");
+    let len = u32::from_le_bytes(header[4..].try_into().expect("4 bytes"));
+    Ok(Some((name, len)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn chunk(name: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = [
+            &name[..],
+            &(payload.len() as u32).to_le_bytes()[..],
+            payload,
+        ]
+        .concat();
+        if payload.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn riff(chunks: &[Vec<u8>]) -> Cursor<Vec<u8>> {
+        let body = chunks.concat();
+        let len = (body.len() + 4) as u32;
+        Cursor::new([&b"RIFF"[..], &len.to_le_bytes(), b"WEBP", &body].concat())
+    }
+
+    fn u24(value: u32) -> [u8; 3] {
+        let [a, b, c, _] = value.to_le_bytes();
+        [a, b, c]
+    }
+
+    fn vp8x(flags: u8, width: u32, height: u32) -> Vec<u8> {
+        chunk(
+            b"VP8X",
+            &[&[flags, 0, 0, 0][..], &u24(width - 1), &u24(height - 1)].concat(),
+        )
+    }
+
+    fn anmf(x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+        let header = [
+            &u24(x / 2)[..],
+            &u24(y / 2),
+            &u24(width - 1),
+            &u24(height - 1),
+            &u24(100),
+            &[0],
+        ]
+        .concat();
+        chunk(b"ANMF", &[header, chunk(b"VP8L", &[0; 5])].concat())
+    }
+
+    #[test]
+    fn reads_animation_info() {
+        let mut input = riff(&[
+            vp8x(VP8X_ANIMATION_FLAG, 100, 50),
+            chunk(b"ANIM", &[0, 0, 0, 0, 3, 0]),
+            anmf(0, 0, 100, 50),
+            anmf(10, 20, 90, 30),
+        ]);
+        assert_eq!(
+            read_info(&mut input).expect("valid"),
+            WebpInfo {
+                canvas_width: 100,
+                canvas_height: 50,
+                frame_count: 2,
+                loop_count: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn reads_still_image_info() {
+        let bits: u32 = (320 - 1) | ((240 - 1) << 14);
+        let mut input = riff(&[chunk(
+            b"VP8L",
+            &[&[0x2f][..], &bits.to_le_bytes(), &[0; 3]].concat(),
+        )]);
+        assert_eq!(
+            read_info(&mut input).expect("valid"),
+            WebpInfo {
+                canvas_width: 320,
+                canvas_height: 240,
+                frame_count: 1,
+                loop_count: None,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_frame_outside_canvas() {
+        let mut input = riff(&[
+            vp8x(VP8X_ANIMATION_FLAG, 100, 50),
+            chunk(b"ANIM", &[0; 6]),
+            anmf(20, 0, 90, 50),
+        ]);
+        assert_matches!(
+            read_info(&mut input),
+            Err(Error::Parse(ParseErrorReport {
+                kind: ParseError::InvalidInput,
+                ..
+            }))
+        );
+    }
+}
@@ -28,6 +28,8 @@ use crate::proto::backup::frame::Item as FrameItem;
 mod account_data;
 mod call;
 mod chat;
+#[cfg(feature = "json")]
+pub mod compare;
 mod file;
 mod frame;
 pub(crate) mod method;
@@ -573,6 +575,9 @@ impl From<crate::parse::ParseError> for ConvertJsonError {
         match value {
             crate::parse::ParseError::Decode(e) => e.into(),
             crate::parse::ParseError::Io(e) => e.into(),
+            e @ crate::parse::ParseError::FrameTooLarge { .. } => {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()).into()
+            }
         }
     }
 }
@@ -0,0 +1,401 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Semantic comparison of backup contents.
+//!
+//! Backups are compared in their canonical [`serialize::Backup`] form, so
+//! differences that don't affect the meaning of a backup (frame order, the
+//! backup timestamp, etc.) are ignored. This is intended for round-trip
+//! testing of exporters: import a backup, export it again, and check that
+//! nothing was lost or changed along the way.
+//!
+//! [`compare_backups`] reads and compares two backup files; [`compare`] works
+//! on contents that have already been loaded.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::hash::{Hash as _, Hasher};
+
+use futures::AsyncRead;
+use serde_json::Value;
+
+use crate::backup::serialize;
+use crate::frame::VerifyHmac;
+use crate::BackupReader;
+
+/// Source of backup contents that can be compared.
+///
+/// This is implemented for [`serialize::Backup`]; clients can implement it for
+/// their own stores to compare live data against a backup, as long as they
+/// produce the same canonical JSON representation.
+pub trait ComparableContents {
+    fn to_canonical_json(&self) -> Value;
+}
+
+impl ComparableContents for serialize::Backup {
+    fn to_canonical_json(&self) -> Value {
+        serde_json::to_value(self).expect("can't fail serialization")
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+/// The broad area of a backup a [`Difference`] was found in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, strum::Display)]
+pub enum DifferenceArea {
+    Meta,
+    AccountData,
+    Recipients,
+    Chats,
+    ChatItems,
+    PinnedChats,
+    AdHocCalls,
+    StickerPacks,
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DifferenceKind {
+    /// The value is present in the left backup but not the right.
+    OnlyInLeft(Value),
+    /// The value is present in the right backup but not the left.
+    OnlyInRight(Value),
+    /// The value is present in both but differs.
+    Mismatch { left: Value, right: Value },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub path: Vec<PathSegment>,
+    pub kind: DifferenceKind,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum CompareError {
+    /// left backup: {0}
+    Left(crate::Error),
+    /// right backup: {0}
+    Right(crate::Error),
+}
+
+/// Reads two backups in full and compares their contents.
+///
+/// Both backups must be valid; an error reading either one is reported
+/// instead of a list of differences.
+pub async fn compare_backups(
+    left: BackupReader<impl AsyncRead + Unpin + VerifyHmac>,
+    right: BackupReader<impl AsyncRead + Unpin + VerifyHmac>,
+) -> Result<Vec<Difference>, CompareError> {
+    let left = left.read_all().await.result.map_err(CompareError::Left)?;
+    let right = right.read_all().await.result.map_err(CompareError::Right)?;
+    Ok(compare(
+        &serialize::Backup::from(left),
+        &serialize::Backup::from(right),
+    ))
+}
+
+/// Compares two backups, returning every difference found.
+///
+/// An empty result means the two are semantically identical.
+pub fn compare(left: &impl ComparableContents, right: &impl ComparableContents) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_values(
+        &mut Vec::new(),
+        &left.to_canonical_json(),
+        &right.to_canonical_json(),
+        &mut differences,
+    );
+    differences
+}
+
+fn diff_values(
+    path: &mut Vec<PathSegment>,
+    left: &Value,
+    right: &Value,
+    out: &mut Vec<Difference>,
+) {
+    if left == right {
+        return;
+    }
+
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, left_value) in left {
+                path.push(PathSegment::Field(key.clone()));
+                match right.get(key) {
+                    Some(right_value) => diff_values(path, left_value, right_value, out),
+                    None => out.push(Difference {
+                        path: path.clone(),
+                        kind: DifferenceKind::OnlyInLeft(left_value.clone()),
+                    }),
+                }
+                path.pop();
+            }
+            for (key, right_value) in right {
+                if left.contains_key(key) {
+                    continue;
+                }
+                path.push(PathSegment::Field(key.clone()));
+                out.push(Difference {
+                    path: path.clone(),
+                    kind: DifferenceKind::OnlyInRight(right_value.clone()),
+                });
+                path.pop();
+            }
+        }
+        (Value::Array(left), Value::Array(right)) if left.len() == right.len() => {
+            // Canonical lists are sorted, so same-length lists are compared
+            // pairwise to report the specific fields that differ.
+            for (i, (left, right)) in left.iter().zip(right).enumerate() {
+                path.push(PathSegment::Index(i));
+                diff_values(path, left, right, out);
+                path.pop();
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            // When the lengths differ, a pairwise comparison would report
+            // every element after the first missing one. Instead, report the
+            // elements that don't have an exact match on the other side.
+            // Elements are bucketed by hash so this takes linear time.
+            let mut unmatched_right: Vec<Option<&Value>> = right.iter().map(Some).collect();
+            let mut right_by_hash = HashMap::<u64, VecDeque<usize>>::new();
+            for (i, right_value) in right.iter().enumerate() {
+                right_by_hash
+                    .entry(hash_value(right_value))
+                    .or_default()
+                    .push_back(i);
+            }
+
+            for (i, left_value) in left.iter().enumerate() {
+                let matched =
+                    right_by_hash
+                        .get_mut(&hash_value(left_value))
+                        .and_then(|candidates| {
+                            let position = candidates
+                                .iter()
+                                .position(|&candidate| &right[candidate] == left_value)?;
+                            candidates.remove(position)
+                        });
+                match matched {
+                    Some(index) => unmatched_right[index] = None,
+                    None => {
+                        path.push(PathSegment::Index(i));
+                        out.push(Difference {
+                            path: path.clone(),
+                            kind: DifferenceKind::OnlyInLeft(left_value.clone()),
+                        });
+                        path.pop();
+                    }
+                }
+            }
+            for (i, right_value) in unmatched_right.into_iter().enumerate() {
+                let Some(right_value) = right_value else {
+                    continue;
+                };
+                path.push(PathSegment::Index(i));
+                out.push(Difference {
+                    path: path.clone(),
+                    kind: DifferenceKind::OnlyInRight(right_value.clone()),
+                });
+                path.pop();
+            }
+        }
+        (left, right) => out.push(Difference {
+            path: path.clone(),
+            kind: DifferenceKind::Mismatch {
+                left: left.clone(),
+                right: right.clone(),
+            },
+        }),
+    }
+}
+
+/// Hashes `value` consistently with its `PartialEq`, which ignores the order
+/// of object keys.
+fn hash_value(value: &Value) -> u64 {
+    fn hash_into(value: &Value, state: &mut impl Hasher) {
+        std::mem::discriminant(value).hash(state);
+        match value {
+            Value::Null => {}
+            Value::Bool(b) => b.hash(state),
+            Value::Number(n) => n.to_string().hash(state),
+            Value::String(s) => s.hash(state),
+            Value::Array(items) => {
+                items.len().hash(state);
+                for item in items {
+                    hash_into(item, state);
+                }
+            }
+            Value::Object(fields) => {
+                fields.len().hash(state);
+                let mut fields: Vec<_> = fields.iter().collect();
+                fields.sort_unstable_by_key(|(key, _)| *key);
+                for (key, field) in fields {
+                    key.hash(state);
+                    hash_into(field, state);
+                }
+            }
+        }
+    }
+
+    let mut state = DefaultHasher::new();
+    hash_into(value, &mut state);
+    state.finish()
+}
+
+impl Difference {
+    /// Classifies the difference by where in the backup it was found.
+    pub fn area(&self) -> DifferenceArea {
+        let field = |i| match self.path.get(i) {
+            Some(PathSegment::Field(name)) => Some(name.as_str()),
+            _ => None,
+        };
+        match field(0) {
+            Some("meta") => DifferenceArea::Meta,
+            Some("account_data") => DifferenceArea::AccountData,
+            Some("recipients") => DifferenceArea::Recipients,
+            Some("chats") if field(2) == Some("items") => DifferenceArea::ChatItems,
+            Some("chats") => DifferenceArea::Chats,
+            Some("pinned_chats") => DifferenceArea::PinnedChats,
+            Some("ad_hoc_calls") => DifferenceArea::AdHocCalls,
+            Some("sticker_packs") => DifferenceArea::StickerPacks,
+            _ => DifferenceArea::Other,
+        }
+    }
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { path, kind } = self;
+        write!(f, "{}: ", self.area())?;
+        if path.is_empty() {
+            write!(f, "<root>")?;
+        }
+        for (i, segment) in path.iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) if i == 0 => write!(f, "{name}")?,
+                PathSegment::Field(name) => write!(f, ".{name}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        match kind {
+            DifferenceKind::OnlyInLeft(value) => write!(f, " only in left: {value}"),
+            DifferenceKind::OnlyInRight(value) => write!(f, " only in right: {value}"),
+            DifferenceKind::Mismatch { left, right } => write!(f, " {left} != {right}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    impl ComparableContents for Value {
+        fn to_canonical_json(&self) -> Value {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn identical_has_no_differences() {
+        let backup = json!({"meta": {"version": 1}, "chats": [{"items": [1, 2]}]});
+        assert_eq!(compare(&backup, &backup), vec![]);
+    }
+
+    #[test]
+    fn mismatched_field() {
+        let left = json!({"chats": [{"items": [{"sent": 1}]}]});
+        let right = json!({"chats": [{"items": [{"sent": 2}]}]});
+
+        let differences = compare(&left, &right);
+        assert_eq!(
+            differences,
+            vec![Difference {
+                path: vec![
+                    PathSegment::Field("chats".into()),
+                    PathSegment::Index(0),
+                    PathSegment::Field("items".into()),
+                    PathSegment::Index(0),
+                    PathSegment::Field("sent".into()),
+                ],
+                kind: DifferenceKind::Mismatch {
+                    left: json!(1),
+                    right: json!(2)
+                },
+            }]
+        );
+        assert_eq!(differences[0].area(), DifferenceArea::ChatItems);
+        assert_eq!(
+            differences[0].to_string(),
+            "ChatItems: chats[0].items[0].sent 1 != 2"
+        );
+    }
+
+    #[test]
+    fn missing_list_element_reported_once() {
+        let left = json!({"recipients": ["a", "b", "c", "d"]});
+        let right = json!({"recipients": ["a", "c", "d"]});
+
+        assert_eq!(
+            compare(&left, &right),
+            vec![Difference {
+                path: vec![
+                    PathSegment::Field("recipients".into()),
+                    PathSegment::Index(1)
+                ],
+                kind: DifferenceKind::OnlyInLeft(json!("b")),
+            }]
+        );
+    }
+
+    #[test]
+    fn duplicate_list_elements_are_matched_individually() {
+        let left = json!({"chats": [{"id": 1}, {"id": 1}, {"id": 2}]});
+        let right = json!({"chats": [{"id": 2}, {"id": 1}]});
+
+        assert_eq!(
+            compare(&left, &right),
+            vec![Difference {
+                path: vec![PathSegment::Field("chats".into()), PathSegment::Index(1)],
+                kind: DifferenceKind::OnlyInLeft(json!({"id": 1})),
+            }]
+        );
+    }
+
+    #[test]
+    fn object_key_order_does_not_affect_matching() {
+        let left: Value = serde_json::from_str(r#"[{"a": 1, "b": 2}, {"c": 3}]"#).unwrap();
+        let right: Value = serde_json::from_str(r#"[{"b": 2, "a": 1}]"#).unwrap();
+
+        assert_eq!(
+            compare(&left, &right),
+            vec![Difference {
+                path: vec![PathSegment::Index(1)],
+                kind: DifferenceKind::OnlyInLeft(json!({"c": 3})),
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_field_on_either_side() {
+        let left = json!({"account_data": {"username": "abc.123"}});
+        let right = json!({"account_data": {"given_name": "Abc"}});
+
+        let differences = compare(&left, &right);
+        assert_eq!(differences.len(), 2);
+        assert_matches::assert_matches!(&differences[0].kind, DifferenceKind::OnlyInLeft(_));
+        assert_matches::assert_matches!(&differences[1].kind, DifferenceKind::OnlyInRight(_));
+        assert!(differences
+            .iter()
+            .all(|d| d.area() == DifferenceArea::AccountData));
+    }
+}
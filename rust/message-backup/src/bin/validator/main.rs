@@ -16,7 +16,9 @@ use libsignal_message_backup::frame::{
     VerifyHmac,
 };
 use libsignal_message_backup::key::{BackupKey, MessageBackupKey};
-use libsignal_message_backup::{BackupReader, Error, FoundUnknownField, ReadResult};
+use libsignal_message_backup::{
+    unknown_field_warnings, BackupReader, Error, FoundUnknownField, ReadResult,
+};
 use mediasan_common::SeekSkipAdapter;
 
 use crate::args::ParseVerbosity;
@@ -249,8 +251,8 @@ fn print_unknown_fields(found_unknown_fields: Vec<FoundUnknownField>) {
     }
 
     eprintln!("not all proto values were recognized; found the following unknown values:");
-    for field in found_unknown_fields {
-        eprintln!("{field}");
+    for warning in unknown_field_warnings(found_unknown_fields) {
+        eprintln!("{warning}");
     }
 }
 
@@ -16,20 +16,24 @@ use mediasan_common::{AsyncSkip, AsyncSkipExt as _};
 use sha2::Sha256;
 use subtle::ConstantTimeEq as _;
 
-use crate::frame::aes_read::{Aes256CbcReader, AES_IV_SIZE};
+use crate::frame::aes_read::Aes256CbcReader;
 use crate::frame::mac_read::MacReader;
 use crate::key::MessageBackupKey;
 
 mod aes_read;
+mod aes_write;
 mod block_stream;
 mod cbc;
 mod mac_read;
 mod reader_factory;
 mod unpad;
 
+pub(crate) use aes_read::AES_IV_SIZE;
+pub(crate) use aes_write::Aes256CbcHmacEncryptor;
 pub use reader_factory::{CursorFactory, FileReaderFactory, LimitedReaderFactory, ReaderFactory};
 
-const HMAC_LEN: usize = <<Hmac<Sha256> as OutputSizeUser>::OutputSize as Unsigned>::USIZE;
+pub(crate) const HMAC_LEN: usize =
+    <<Hmac<Sha256> as OutputSizeUser>::OutputSize as Unsigned>::USIZE;
 
 #[derive(Debug)]
 pub struct FramesReader<R: AsyncRead + Unpin> {
@@ -0,0 +1,142 @@
+//
+// Copyright (C) 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::collections::VecDeque;
+
+use aes::Aes256;
+use cbc::cipher::{BlockEncryptMut, BlockSizeUser, KeyIvInit, KeySizeUser, Unsigned};
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use crate::frame::{AES_IV_SIZE, HMAC_LEN};
+
+const AES_BLOCK_SIZE: usize = <<Aes256 as BlockSizeUser>::BlockSize as Unsigned>::USIZE;
+const AES_KEY_SIZE: usize = <<Aes256 as KeySizeUser>::KeySize as Unsigned>::USIZE;
+
+/// Incremental encryptor for the `IV || ciphertext || HMAC` layout.
+///
+/// The ciphertext is AES-256-CBC with PKCS7 padding, and the HMAC is
+/// HMAC-SHA256 over the IV and ciphertext. This is the layout that backup
+/// files and media tier objects share, so both are written through here.
+pub(crate) struct Aes256CbcHmacEncryptor {
+    encryptor: cbc::Encryptor<Aes256>,
+    mac: Option<Hmac<Sha256>>,
+    /// Plaintext bytes that don't yet fill a block.
+    partial_block: Vec<u8>,
+}
+
+impl Aes256CbcHmacEncryptor {
+    /// Starts encrypting, appending the IV to `output`.
+    pub(crate) fn new(
+        aes_key: &[u8; AES_KEY_SIZE],
+        hmac_key: &[u8],
+        iv: &[u8; AES_IV_SIZE],
+        output: &mut VecDeque<u8>,
+    ) -> Self {
+        let mut mac = Hmac::<Sha256>::new_from_slice(hmac_key)
+            .expect("HMAC-SHA256 should accept any size key");
+        mac.update(iv);
+        output.extend(iv);
+
+        Self {
+            encryptor: cbc::Encryptor::new(aes_key.into(), iv.into()),
+            mac: Some(mac),
+            partial_block: Vec::with_capacity(AES_BLOCK_SIZE),
+        }
+    }
+
+    /// Whether [`finalize`](Self::finalize) has been called.
+    pub(crate) fn is_finalized(&self) -> bool {
+        self.mac.is_none()
+    }
+
+    /// Encrypts all complete blocks to `output`, holding back any remainder.
+    pub(crate) fn update(&mut self, plaintext: &[u8], output: &mut VecDeque<u8>) {
+        let Self {
+            encryptor,
+            mac,
+            partial_block,
+        } = self;
+        let mac = mac.as_mut().expect("not finalized");
+
+        partial_block.extend_from_slice(plaintext);
+        let full_len = partial_block.len() - partial_block.len() % AES_BLOCK_SIZE;
+        for chunk in partial_block[..full_len].chunks_exact(AES_BLOCK_SIZE) {
+            let mut block = *<&[u8; AES_BLOCK_SIZE]>::try_from(chunk).expect("exact chunk");
+            encryptor.encrypt_block_mut((&mut block).into());
+            mac.update(&block);
+            output.extend(block);
+        }
+        partial_block.drain(..full_len);
+    }
+
+    /// Pads and encrypts the final block, then appends the HMAC to `output`.
+    ///
+    /// Returns the HMAC.
+    pub(crate) fn finalize(&mut self, output: &mut VecDeque<u8>) -> [u8; HMAC_LEN] {
+        let Self {
+            encryptor,
+            mac,
+            partial_block,
+        } = self;
+        let mut mac = mac.take().expect("only finalized once");
+
+        let pad_len = AES_BLOCK_SIZE - partial_block.len();
+        let mut block = [pad_len as u8; AES_BLOCK_SIZE];
+        block[..partial_block.len()].copy_from_slice(partial_block);
+        partial_block.clear();
+
+        encryptor.encrypt_block_mut((&mut block).into());
+        mac.update(&block);
+        output.extend(block);
+
+        let hmac: [u8; HMAC_LEN] = mac.finalize().into_bytes().into();
+        output.extend(hmac);
+        hmac
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::*;
+
+    const AES_KEY: [u8; AES_KEY_SIZE] = [0xaa; AES_KEY_SIZE];
+    const HMAC_KEY: [u8; 32] = [0xbb; 32];
+    const IV: [u8; AES_IV_SIZE] = [0xcc; AES_IV_SIZE];
+
+    #[test_case(0, 1)]
+    #[test_case(15, 4)]
+    #[test_case(16, 16)]
+    #[test_case(100, 7)]
+    fn matches_one_shot_encryption(len: usize, update_size: usize) {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+
+        let mut output = VecDeque::new();
+        let mut encryptor = Aes256CbcHmacEncryptor::new(&AES_KEY, &HMAC_KEY, &IV, &mut output);
+        for chunk in plaintext.chunks(update_size) {
+            encryptor.update(chunk, &mut output);
+        }
+        assert!(!encryptor.is_finalized());
+        let hmac = encryptor.finalize(&mut output);
+        assert!(encryptor.is_finalized());
+
+        let ciphertext =
+            signal_crypto::aes_256_cbc_encrypt(&plaintext, &AES_KEY, &IV).expect("valid");
+        let expected_hmac: [u8; HMAC_LEN] = Hmac::<Sha256>::new_from_slice(&HMAC_KEY)
+            .unwrap()
+            .chain_update(IV)
+            .chain_update(&ciphertext)
+            .finalize()
+            .into_bytes()
+            .into();
+        assert_eq!(hmac, expected_hmac);
+        assert_eq!(
+            Vec::from(output),
+            [IV.as_slice(), &ciphertext, &expected_hmac].concat()
+        );
+    }
+}
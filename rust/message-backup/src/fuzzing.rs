@@ -0,0 +1,151 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Support for structured fuzzing of backup validation.
+//!
+//! Random bytes rarely make it past protobuf parsing, so [`ArbitraryFrame`]
+//! and [`ArbitraryBackup`] build well-formed frames with arbitrary contents
+//! instead. Ids are drawn from a small range so that frames often refer to
+//! each other. [`validate_plaintext_backup`] is the entry point for fuzz
+//! targets, here and downstream.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use futures::FutureExt as _;
+use protobuf::Message as _;
+
+use crate::backup::Purpose;
+use crate::proto::backup as proto;
+use crate::{BackupReader, ReadResult};
+
+const MAX_ID: u64 = 15;
+const MAX_FRAMES: usize = 32;
+
+/// A serialized `Frame` holding account data, a recipient, or a chat.
+#[derive(Debug)]
+pub struct ArbitraryFrame(pub Vec<u8>);
+
+/// A plaintext backup: a `BackupInfo` header followed by arbitrary frames,
+/// each preceded by its length as a varint.
+#[derive(Debug)]
+pub struct ArbitraryBackup(pub Vec<u8>);
+
+impl<'a> Arbitrary<'a> for ArbitraryFrame {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_frame(u)?
+            .write_to_bytes()
+            .map(Self)
+            .map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryBackup {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut contents = Vec::new();
+        let info = proto::BackupInfo {
+            version: 1,
+            backupTimeMs: u.arbitrary()?,
+            ..Default::default()
+        };
+        info.write_length_delimited_to_vec(&mut contents)
+            .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        for _ in 0..u.int_in_range(0..=MAX_FRAMES)? {
+            arbitrary_frame(u)?
+                .write_length_delimited_to_vec(&mut contents)
+                .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        }
+        Ok(Self(contents))
+    }
+}
+
+fn arbitrary_frame(u: &mut Unstructured<'_>) -> Result<proto::Frame> {
+    let item: proto::frame::Item = match u.int_in_range(0..=2)? {
+        0 => proto::AccountData {
+            profileKey: u.arbitrary::<[u8; 32]>()?.to_vec(),
+            username: u.arbitrary()?,
+            givenName: u.arbitrary()?,
+            familyName: u.arbitrary()?,
+            accountSettings: Some(Default::default()).into(),
+            ..Default::default()
+        }
+        .into(),
+        1 => proto::Recipient {
+            id: u.int_in_range(0..=MAX_ID)?,
+            destination: Some(arbitrary_destination(u)?),
+            ..Default::default()
+        }
+        .into(),
+        _ => proto::Chat {
+            id: u.int_in_range(0..=MAX_ID)?,
+            recipientId: u.int_in_range(0..=MAX_ID)?,
+            archived: u.arbitrary()?,
+            pinnedOrder: u.arbitrary()?,
+            expirationTimerMs: u.arbitrary()?,
+            muteUntilMs: u.arbitrary()?,
+            markedUnread: u.arbitrary()?,
+            ..Default::default()
+        }
+        .into(),
+    };
+    Ok(proto::Frame {
+        item: Some(item),
+        ..Default::default()
+    })
+}
+
+fn arbitrary_destination(u: &mut Unstructured<'_>) -> Result<proto::recipient::Destination> {
+    use proto::recipient::Destination;
+
+    Ok(match u.int_in_range(0..=3)? {
+        0 => Destination::Self_(Default::default()),
+        1 => Destination::ReleaseNotes(Default::default()),
+        2 => Destination::Group(proto::Group {
+            masterKey: u.arbitrary::<[u8; 32]>()?.to_vec(),
+            whitelisted: u.arbitrary()?,
+            ..Default::default()
+        }),
+        _ => Destination::Contact(proto::Contact {
+            aci: u.arbitrary::<Option<[u8; 16]>>()?.map(Vec::from),
+            pni: u.arbitrary::<Option<[u8; 16]>>()?.map(Vec::from),
+            e164: u.arbitrary()?,
+            username: u.arbitrary()?,
+            blocked: u.arbitrary()?,
+            registration: Some(if u.arbitrary()? {
+                proto::contact::Registration::Registered(Default::default())
+            } else {
+                proto::contact::Registration::NotRegistered(proto::contact::NotRegistered {
+                    unregisteredTimestamp: u.arbitrary()?,
+                    ..Default::default()
+                })
+            }),
+            ..Default::default()
+        }),
+    })
+}
+
+/// Reads and validates a plaintext backup, ignoring the result.
+pub fn validate_plaintext_backup(data: &[u8]) {
+    let reader = BackupReader::new_unencrypted(data, Purpose::RemoteBackup);
+    let _: ReadResult<()> = reader
+        .validate_all()
+        .now_or_never()
+        .expect("reading from memory doesn't block");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arbitrary_backups_can_be_validated() {
+        let data = (0..=255).cycle().take(4096).collect::<Vec<u8>>();
+        let mut u = Unstructured::new(&data);
+
+        let ArbitraryFrame(frame) = u.arbitrary().expect("enough data");
+        proto::Frame::parse_from_bytes(&frame).expect("valid frame");
+
+        let ArbitraryBackup(backup) = u.arbitrary().expect("enough data");
+        validate_plaintext_backup(&backup);
+    }
+}
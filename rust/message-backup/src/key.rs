@@ -9,9 +9,23 @@
 //! will then derive a [`BackupId`] from this key and their [`Aci`]. This
 //! ensures that the `BackupKey` is reconstructible using only state stored in
 //! SVR, so that a restorer can reconstruct the `BackupId`.
+//!
+//! The full hierarchy is:
+//!
+//! ```text
+//! master key or account entropy pool
+//! └── BackupKey
+//!     ├── BackupId (with the Aci)
+//!     │   └── MessageBackupKey (with the BackupKey and, optionally, a
+//!     │       BackupForwardSecrecyToken)
+//!     └── MediaRootKey (the BackupKey itself)
+//!         └── MediaId (with the media name)
+//!             └── MediaEncryptionKey (for full-size media or thumbnails)
+//! ```
 
+use aes::cipher::crypto_common::rand_core::{CryptoRng, RngCore};
 use hkdf::Hkdf;
-use libsignal_core::Aci;
+use libsignal_core::{AccountEntropyPool, Aci};
 use sha2::Sha256;
 
 /// Primary key for backups that is used to derive other keys.
@@ -23,6 +37,11 @@ impl BackupKey {
     pub const LEN: usize = 32;
     pub const MASTER_KEY_LEN: usize = 32;
 
+    /// Derive a `BackupKey` from the account entropy pool.
+    pub fn derive_from_account_entropy_pool(entropy_pool: &AccountEntropyPool) -> Self {
+        Self(entropy_pool.derive_backup_key())
+    }
+
     /// Derive a `BackupKey` from the provided master key.
     pub fn derive_from_master_key(master_key: &[u8; Self::MASTER_KEY_LEN]) -> Self {
         const INFO: &[u8] = b"20231003_Signal_Backups_GenerateBackupKey";
@@ -50,6 +69,19 @@ impl BackupKey {
 
         BackupId(bytes)
     }
+
+    /// Get the [`MediaRootKey`] used for all media stored with backups.
+    ///
+    /// Signal's clients derive media IDs and keys directly from the
+    /// `BackupKey`, so this is the same key material viewed as a
+    /// `MediaRootKey`.
+    pub fn derive_media_root_key(&self) -> MediaRootKey {
+        MediaRootKey(self.0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; Self::LEN] {
+        &self.0
+    }
 }
 
 /// The per-account key used to store backups.
@@ -61,6 +93,112 @@ pub struct BackupId([u8; BackupId::LEN]);
 
 impl BackupId {
     pub const LEN: usize = 16;
+
+    pub fn as_bytes(&self) -> &[u8; Self::LEN] {
+        &self.0
+    }
+}
+
+/// Root of the keys used for media stored alongside backups.
+///
+/// This is kept separate from the keys for the message backup itself so that
+/// media keys can be handed out without granting access to messages.
+#[derive(Debug)]
+#[cfg_attr(test, derive(Eq, PartialEq))]
+pub struct MediaRootKey([u8; MediaRootKey::LEN]);
+
+/// Identifier for a single media object, derived from its name.
+///
+/// This is what the server knows the media by; it doesn't reveal the name.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MediaId([u8; MediaId::LEN]);
+
+/// Keys used to encrypt a single media object.
+#[derive(Debug)]
+#[cfg_attr(test, derive(Eq, PartialEq))]
+pub struct MediaEncryptionKey {
+    pub hmac_key: [u8; MediaEncryptionKey::HMAC_KEY_LEN],
+    pub aes_key: [u8; MediaEncryptionKey::AES_KEY_LEN],
+}
+
+impl MediaRootKey {
+    pub const LEN: usize = 32;
+
+    /// Derive the [`MediaId`] for the media with the given name.
+    pub fn derive_media_id(&self, media_name: &str) -> MediaId {
+        const INFO_PREFIX: &[u8] = b"20241007_SIGNAL_BACKUP_MEDIA_ID:";
+        let mut bytes = [0; MediaId::LEN];
+
+        Hkdf::<Sha256>::new(None, &self.0)
+            .expand_multi_info(&[INFO_PREFIX, media_name.as_bytes()], &mut bytes)
+            .expect("valid length");
+
+        MediaId(bytes)
+    }
+
+    /// Derive the key for encrypting the full-size media with ID `media_id`.
+    pub fn derive_media_encryption_key(&self, media_id: &MediaId) -> MediaEncryptionKey {
+        const INFO: &[u8] = b"20241007_SIGNAL_BACKUP_ENCRYPT_MEDIA:";
+        MediaEncryptionKey::derive(self, media_id, INFO)
+    }
+
+    /// Derive the key for encrypting the thumbnail for the media with ID `media_id`.
+    ///
+    /// This differs from the key for the full-size media so that thumbnails
+    /// can't be substituted for the original.
+    pub fn derive_thumbnail_encryption_key(&self, media_id: &MediaId) -> MediaEncryptionKey {
+        const INFO: &[u8] = b"20241030_SIGNAL_BACKUP_ENCRYPT_THUMBNAIL:";
+        MediaEncryptionKey::derive(self, media_id, INFO)
+    }
+}
+
+impl MediaId {
+    pub const LEN: usize = 15;
+
+    pub fn as_bytes(&self) -> &[u8; Self::LEN] {
+        &self.0
+    }
+}
+
+impl MediaEncryptionKey {
+    pub const HMAC_KEY_LEN: usize = 32;
+    pub const AES_KEY_LEN: usize = 32;
+
+    pub const LEN: usize = Self::HMAC_KEY_LEN + Self::AES_KEY_LEN;
+
+    fn derive(root_key: &MediaRootKey, media_id: &MediaId, info_prefix: &[u8]) -> Self {
+        let mut full_bytes = [0; Self::LEN];
+
+        Hkdf::<Sha256>::new(None, &root_key.0)
+            .expand_multi_info(&[info_prefix, &media_id.0], &mut full_bytes)
+            .expect("valid length");
+
+        let (hmac_key, aes_key) = full_bytes.split_at(Self::HMAC_KEY_LEN);
+
+        Self {
+            hmac_key: hmac_key.try_into().expect("correct length"),
+            aes_key: aes_key.try_into().expect("correct length"),
+        }
+    }
+}
+
+/// Random secret generated for each backup and mixed into its
+/// [`MessageBackupKey`].
+///
+/// Clients store the token somewhere that forgets old values, so that once a
+/// backup's token is gone the backup can't be decrypted even with the
+/// [`BackupKey`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BackupForwardSecrecyToken(pub [u8; BackupForwardSecrecyToken::LEN]);
+
+impl BackupForwardSecrecyToken {
+    pub const LEN: usize = 32;
+
+    pub fn generate(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut bytes = [0; Self::LEN];
+        rng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
 }
 
 #[derive(Debug)]
@@ -90,6 +228,28 @@ impl MessageBackupKey {
             .expand(INFO, &mut full_bytes)
             .expect("valid length");
 
+        Self::from_derived_bytes(&full_bytes)
+    }
+
+    /// Derives a `MessageBackupKey` for a single backup from a user's
+    /// [`BackupKey`] and [`BackupId`] along with the backup's
+    /// [`BackupForwardSecrecyToken`].
+    pub fn derive_with_forward_secrecy_token(
+        backup_key: &BackupKey,
+        backup_id: &BackupId,
+        forward_secrecy_token: &BackupForwardSecrecyToken,
+    ) -> Self {
+        const INFO: &[u8] = b"20241007_SIGNAL_BACKUP_ENCRYPT_MESSAGE_BACKUP:";
+        let mut full_bytes = [0; MessageBackupKey::LEN];
+
+        Hkdf::<Sha256>::new(Some(&forward_secrecy_token.0), &backup_key.0)
+            .expand_multi_info(&[INFO, &backup_id.0], &mut full_bytes)
+            .expect("valid length");
+
+        Self::from_derived_bytes(&full_bytes)
+    }
+
+    fn from_derived_bytes(full_bytes: &[u8; Self::LEN]) -> Self {
         // TODO split into arrays instead of slices when the API for that is
         // stabilized. See https://github.com/rust-lang/rust/issues/90091
         let (hmac_key, aes_key) = full_bytes.split_at(Self::HMAC_KEY_LEN);
@@ -130,6 +290,19 @@ pub(crate) mod test {
         assert_eq!(b, BackupKey(EXPECTED_KEY_BYTES), "got {b:02x?}");
     }
 
+    #[test]
+    fn backup_key_from_account_entropy_pool_known() {
+        const ENTROPY_POOL: &str =
+            "dtjs858asj6tv0jzsqrsmj0ubp335pisj98e9ssnss8myoc08drhtcktyawvx45l";
+        let b =
+            BackupKey::derive_from_account_entropy_pool(&ENTROPY_POOL.parse().expect("valid pool"));
+
+        const EXPECTED_KEY_BYTES: [u8; BackupKey::LEN] =
+            hex!("ea26a2ddb5dba5ef9e34e1b8dea1f5ae7f255306a6d2d883e542306eaa9fe985");
+
+        assert_eq!(b, BackupKey(EXPECTED_KEY_BYTES), "got {b:02x?}");
+    }
+
     #[test]
     fn backup_id_known() {
         let key = BackupKey::derive_from_master_key(&FAKE_MASTER_KEY);
@@ -150,4 +323,51 @@ pub(crate) mod test {
             "got {message_backup_key:02x?}"
         );
     }
+
+    #[test]
+    fn message_backup_key_with_forward_secrecy_token_known() {
+        let key = BackupKey::derive_from_master_key(&FAKE_MASTER_KEY);
+        let id = key.derive_backup_id(&FAKE_ACI);
+        let token = BackupForwardSecrecyToken([0x42; BackupForwardSecrecyToken::LEN]);
+        let message_backup_key =
+            MessageBackupKey::derive_with_forward_secrecy_token(&key, &id, &token);
+
+        assert_eq!(
+            message_backup_key,
+            MessageBackupKey {
+                hmac_key: hex!("dac1c7ff26f5f55596dd5970bf617494f9e4f35d2cc9c48744f530e73c18e2eb"),
+                aes_key: hex!("1835ecefc5c567273bf809eeed3443782b55408c26114f8c3b482853d18944c8"),
+            },
+            "got {message_backup_key:02x?}"
+        );
+    }
+
+    #[test]
+    fn media_keys_known() {
+        let backup_key = BackupKey::derive_from_master_key(&FAKE_MASTER_KEY);
+        let root_key = backup_key.derive_media_root_key();
+        assert_eq!(root_key, MediaRootKey(backup_key.0), "got {root_key:02x?}");
+
+        let media_id = root_key.derive_media_id("example_media_name");
+        assert_eq!(
+            media_id,
+            MediaId(hex!("b25dec081a3a4ffbe9d2be1a7880a7")),
+            "got {media_id:02x?}"
+        );
+
+        assert_eq!(
+            root_key.derive_media_encryption_key(&media_id),
+            MediaEncryptionKey {
+                hmac_key: hex!("32229077c6753ee4150dbb438535f54b4b50ed41625a43b128c7f622f872795c"),
+                aes_key: hex!("fc1d21adb20a09378e9eb068fa7cc9a43e36bd28dd28f27d725758c6ad4469cc"),
+            }
+        );
+        assert_eq!(
+            root_key.derive_thumbnail_encryption_key(&media_id),
+            MediaEncryptionKey {
+                hmac_key: hex!("2b715bad38d2cb982626f5b8d1ae241cac708826edc11e45cf956fac31a1c27a"),
+                aes_key: hex!("593a4b869f8414a8b582f851531d580d32ad4477b2e25245027ef3b3cead9379"),
+            }
+        );
+    }
 }
@@ -5,7 +5,9 @@
 
 //! Signal remote message backup utilities.
 //!
-//! Contains code to read and validate message backup files.
+//! Contains code to read, validate, and write message backup files.
+
+use std::num::NonZeroUsize;
 
 use futures::AsyncRead;
 use mediasan_common::AsyncSkip;
@@ -18,14 +20,23 @@ use crate::frame::{
 };
 use crate::key::MessageBackupKey;
 use crate::parse::VarintDelimitedReader;
+use crate::restore::{FrameCategory, RestoreCursor, RestoreOptions};
 use crate::unknown::{FormatPath, PathPart, UnknownValue, VisitUnknownFieldsExt as _};
 
 pub mod args;
 pub mod backup;
 pub mod frame;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
 pub mod key;
+pub mod media;
 pub mod parse;
+pub mod restore;
+pub mod scrub;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
 pub mod unknown;
+pub mod writer;
 
 pub(crate) mod proto;
 
@@ -33,6 +44,22 @@ pub struct BackupReader<R> {
     purpose: Purpose,
     reader: VarintDelimitedReader<R>,
     pub visitor: fn(&dyn std::fmt::Debug),
+    progress: Option<ProgressReporter>,
+}
+
+/// How far a [`BackupReader`] has gotten through its input.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReadProgress {
+    /// The number of decrypted and decompressed bytes consumed so far.
+    pub bytes_processed: u64,
+    /// The number of frames, including the leading `BackupInfo`, validated so far.
+    pub frames_validated: usize,
+}
+
+/// Periodically hands [`ReadProgress`] updates to a caller-provided callback.
+struct ProgressReporter {
+    every_n_frames: NonZeroUsize,
+    callback: Box<dyn FnMut(ReadProgress) + Send>,
 }
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
@@ -51,12 +78,31 @@ pub enum Error {
     HmacMismatch(#[from] HmacMismatchError),
 }
 
+impl Error {
+    /// Returns a view of this error that is safe to include in bug reports.
+    ///
+    /// See [`scrub::Scrubbed`] for what is redacted.
+    pub fn scrubbed(&self) -> scrub::Scrubbed<&Self> {
+        scrub::Scrubbed(self)
+    }
+}
+
 #[must_use]
 pub struct ReadResult<B> {
     pub result: Result<B, Error>,
     pub found_unknown_fields: Vec<FoundUnknownField>,
 }
 
+/// The outcome of [`BackupReader::restore`].
+#[must_use]
+pub struct RestoreResult<B> {
+    pub read: ReadResult<B>,
+    /// The point from which an interrupted restore can be resumed.
+    ///
+    /// If the restore succeeded, this points past the last frame.
+    pub cursor: RestoreCursor,
+}
+
 #[derive(Debug, thiserror::Error)]
 #[must_use]
 pub struct ReadError {
@@ -64,6 +110,15 @@ pub struct ReadError {
     pub found_unknown_fields: Vec<FoundUnknownField>,
 }
 
+impl ReadError {
+    /// Returns a view of this error that is safe to include in bug reports.
+    ///
+    /// See [`scrub::Scrubbed`] for what is redacted.
+    pub fn scrubbed(&self) -> scrub::Scrubbed<&Self> {
+        scrub::Scrubbed(self)
+    }
+}
+
 impl std::fmt::Display for ReadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self {
@@ -84,6 +139,8 @@ impl std::fmt::Display for ReadError {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct FoundUnknownField {
     pub frame_index: usize,
+    /// The kind of frame the field was found in, or `None` for the `BackupInfo` header.
+    pub frame_category: Option<FrameCategory>,
     pub path: Vec<PathPart>,
     pub value: UnknownValue,
 }
@@ -92,19 +149,86 @@ impl std::fmt::Display for FoundUnknownField {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self {
             frame_index,
+            frame_category,
             path,
             value,
         } = self;
-        write!(
-            f,
-            "in frame {frame_index}, {} has unknown {}",
-            FormatPath(path.as_slice()),
-            value
-        )
+        write!(f, "in frame {frame_index}")?;
+        if let Some(category) = frame_category {
+            write!(f, " ({category})")?;
+        }
+        write!(f, ", {} has unknown {}", FormatPath(path.as_slice()), value)
     }
 }
 
+/// Unknown fields found in a single frame.
+///
+/// Unknown fields usually indicate that the backup was produced by a newer
+/// client, and that the data they hold was validly skipped rather than being
+/// corrupt. They are reported as warnings so callers can surface them without
+/// failing the read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownFieldsWarning {
+    pub frame_index: usize,
+    pub frame_category: Option<FrameCategory>,
+    pub fields: Vec<(Vec<PathPart>, UnknownValue)>,
+}
+
+impl std::fmt::Display for UnknownFieldsWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            frame_index,
+            frame_category,
+            fields,
+        } = self;
+        write!(f, "frame {frame_index}")?;
+        if let Some(category) = frame_category {
+            write!(f, " ({category})")?;
+        }
+        write!(f, " has {} unknown value(s): ", fields.len())?;
+        for (i, (path, value)) in fields.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{value} at {}", FormatPath(path.as_slice()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Groups unknown fields by the frame they were found in.
+///
+/// The input is expected to be in frame order, as produced by
+/// [`BackupReader`]; the output has one entry per frame with unknown fields.
+pub fn unknown_field_warnings(
+    found_unknown_fields: impl IntoIterator<Item = FoundUnknownField>,
+) -> Vec<UnknownFieldsWarning> {
+    let mut warnings = Vec::<UnknownFieldsWarning>::new();
+    for FoundUnknownField {
+        frame_index,
+        frame_category,
+        path,
+        value,
+    } in found_unknown_fields
+    {
+        match warnings.last_mut() {
+            Some(last) if last.frame_index == frame_index => last.fields.push((path, value)),
+            _ => warnings.push(UnknownFieldsWarning {
+                frame_index,
+                frame_category,
+                fields: vec![(path, value)],
+            }),
+        }
+    }
+    warnings
+}
+
 impl<R> ReadResult<R> {
+    /// Returns the unknown fields found while reading, grouped by frame.
+    pub fn unknown_field_warnings(&self) -> Vec<UnknownFieldsWarning> {
+        unknown_field_warnings(self.found_unknown_fields.iter().cloned())
+    }
+
     fn and_then<T>(self, f: impl FnOnce(R) -> Result<T, Error>) -> ReadResult<T> {
         let Self {
             result,
@@ -117,6 +241,35 @@ impl<R> ReadResult<R> {
     }
 }
 
+impl<R> BackupReader<R> {
+    /// Invokes `callback` after every `every_n_frames` frames are validated.
+    ///
+    /// If the last frame doesn't land on an interval boundary, the callback is
+    /// invoked once more after it is read, so callers always see the final
+    /// totals for a successful read.
+    pub fn with_progress_callback(
+        mut self,
+        every_n_frames: NonZeroUsize,
+        callback: impl FnMut(ReadProgress) + Send + 'static,
+    ) -> Self {
+        self.progress = Some(ProgressReporter {
+            every_n_frames,
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Limits the size of any single frame read from the backup.
+    ///
+    /// Frames are held in memory while they are validated, so this bounds
+    /// the amount of memory used for each one. Defaults to
+    /// [`parse::DEFAULT_MAX_FRAME_LENGTH`].
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.reader.set_max_frame_length(max_frame_length);
+        self
+    }
+}
+
 impl<R: AsyncRead + Unpin + VerifyHmac> BackupReader<R> {
     pub async fn read_all(self) -> ReadResult<backup::CompletedBackup<Store>> {
         self.collect_all()
@@ -131,21 +284,60 @@ impl<R: AsyncRead + Unpin + VerifyHmac> BackupReader<R> {
         })
     }
 
+    /// Reads and validates the frames selected by `options`.
+    ///
+    /// Unlike [`Self::read_all`], this reports a [`RestoreCursor`] even on
+    /// failure, which can be passed back in [`RestoreOptions::resume_from`]
+    /// to pick up where the failed restore left off.
+    pub async fn restore(
+        self,
+        options: RestoreOptions,
+    ) -> RestoreResult<backup::CompletedBackup<Store>> {
+        let (read, cursor) = self.collect_with_options(&options).await;
+        RestoreResult {
+            read: read.and_then(|r| Ok(CompletedBackup::try_from(r)?)),
+            cursor,
+        }
+    }
+
     pub async fn collect_all<M: backup::method::Method + backup::ReferencedTypes>(
         self,
     ) -> ReadResult<backup::PartialBackup<M>> {
+        self.collect_with_options(&RestoreOptions::default())
+            .await
+            .0
+    }
+
+    async fn collect_with_options<M: backup::method::Method + backup::ReferencedTypes>(
+        self,
+        options: &RestoreOptions,
+    ) -> (ReadResult<backup::PartialBackup<M>>, RestoreCursor) {
         let Self {
             reader,
             visitor,
             purpose,
+            progress,
         } = self;
 
         let mut found_unknown_fields = Vec::new();
-        let result = read_all_frames(purpose, reader, visitor, &mut found_unknown_fields).await;
-        ReadResult {
-            found_unknown_fields,
-            result,
-        }
+        let mut cursor = options.resume_from.unwrap_or_default();
+        let result = read_all_frames(
+            purpose,
+            reader,
+            visitor,
+            progress,
+            options,
+            &mut cursor,
+            &mut found_unknown_fields,
+        )
+        .await;
+        (
+            ReadResult {
+                found_unknown_fields,
+                result,
+            },
+            cursor,
+        )
     }
 }
 
@@ -156,6 +348,7 @@ impl<R: AsyncRead + Unpin> BackupReader<UnvalidatedHmacReader<R>> {
             reader,
             purpose,
             visitor: |_| (),
+            progress: None,
         }
     }
 }
@@ -171,6 +364,7 @@ impl<R: AsyncRead + AsyncSkip + Unpin> BackupReader<frame::FramesReader<R>> {
             reader: VarintDelimitedReader::new(reader),
             purpose,
             visitor: |_| (),
+            progress: None,
         })
     }
 }
@@ -179,13 +373,17 @@ async fn read_all_frames<M: backup::method::Method + backup::ReferencedTypes>(
     purpose: Purpose,
     mut reader: VarintDelimitedReader<impl AsyncRead + Unpin + VerifyHmac>,
     mut visitor: impl FnMut(&dyn std::fmt::Debug),
+    mut progress: Option<ProgressReporter>,
+    options: &RestoreOptions,
+    cursor: &mut RestoreCursor,
     unknown_fields: &mut impl Extend<FoundUnknownField>,
 ) -> Result<backup::PartialBackup<M>, Error> {
-    let mut add_found_unknown = |found_unknown: Vec<_>, index| {
+    let mut add_found_unknown = |found_unknown: Vec<_>, index, frame_category| {
         let iter = found_unknown
             .into_iter()
             .map(|(path, value)| FoundUnknownField {
                 frame_index: index,
+                frame_category,
                 path,
                 value,
             });
@@ -196,20 +394,52 @@ async fn read_all_frames<M: backup::method::Method + backup::ReferencedTypes>(
     let backup_info = proto::backup::BackupInfo::parse_from_bytes(&first)?;
 
     visitor(&backup_info);
-    add_found_unknown(backup_info.collect_unknown_fields(), 0);
+    add_found_unknown(backup_info.collect_unknown_fields(), 0, None);
 
     let mut backup = backup::PartialBackup::new(backup_info, purpose);
     let mut frame_index = 1;
+    cursor.frame_index = cursor.frame_index.max(frame_index);
+
+    let mut report_progress = |frames_validated, bytes_processed, is_final| {
+        let Some(ProgressReporter {
+            every_n_frames,
+            callback,
+        }) = &mut progress
+        else {
+            return;
+        };
+        let on_interval = frames_validated % every_n_frames.get() == 0;
+        // The final report is skipped if it would repeat the previous one.
+        if on_interval != is_final {
+            callback(ReadProgress {
+                bytes_processed,
+                frames_validated,
+            })
+        }
+    };
+
+    report_progress(frame_index, reader.bytes_consumed(), false);
 
     while let Some(frame) = reader.read_next().await? {
         let frame_proto = proto::backup::Frame::parse_from_bytes(&frame)?;
-        visitor(&frame_proto);
-        add_found_unknown(frame_proto.collect_unknown_fields(), frame_index);
+        let category = frame_proto.item.as_ref().map(FrameCategory::of);
+        let should_process = category.map_or(true, |category| {
+            options.should_process(frame_index, category)
+        });
+
+        if should_process {
+            visitor(&frame_proto);
+            add_found_unknown(frame_proto.collect_unknown_fields(), frame_index, category);
+            backup.add_frame(frame_proto)?;
+        }
         frame_index += 1;
+        cursor.frame_index = cursor.frame_index.max(frame_index);
 
-        backup.add_frame(frame_proto)?
+        report_progress(frame_index, reader.bytes_consumed(), false);
     }
 
+    report_progress(frame_index, reader.bytes_consumed(), true);
+
     // Before reporting success, check that the HMAC still matches. This
     // prevents TOC/TOU issues.
     reader.into_inner().verify_hmac().await?;
@@ -225,3 +455,46 @@ impl From<VerifyHmacError> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn unknown_field(frame_index: usize, tag: u32) -> FoundUnknownField {
+        FoundUnknownField {
+            frame_index,
+            frame_category: (frame_index != 0).then_some(FrameCategory::ChatItem),
+            path: vec![PathPart::Field {
+                field_name: "standardMessage".to_owned(),
+            }],
+            value: UnknownValue::Field { tag },
+        }
+    }
+
+    #[test]
+    fn unknown_field_warnings_grouped_by_frame() {
+        let warnings = unknown_field_warnings([
+            unknown_field(0, 10),
+            unknown_field(3, 11),
+            unknown_field(3, 12),
+            unknown_field(5, 13),
+        ]);
+
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|w| (w.frame_index, w.fields.len()))
+                .collect::<Vec<_>>(),
+            [(0, 1), (3, 2), (5, 1)]
+        );
+        assert_eq!(
+            warnings[1].to_string(),
+            "frame 3 (ChatItem) has 2 unknown value(s): \
+             field with tag 11 at standardMessage, field with tag 12 at standardMessage"
+        );
+        assert_eq!(
+            unknown_field(3, 11).to_string(),
+            "in frame 3 (ChatItem), standardMessage has unknown field with tag 11"
+        );
+    }
+}
@@ -0,0 +1,519 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Encryption of attachments for storage in the backup media tier.
+//!
+//! Attachments uploaded to the media tier are already encrypted with their
+//! own attachment keys. Before upload they are wrapped in an outer layer of
+//! encryption using a [`MediaEncryptionKey`] derived from the account's
+//! [`MediaRootKey`], so that the server can't correlate media-tier objects
+//! with attachments sent in messages.
+//!
+//! The outer layer has the same shape as a regular attachment: a random IV,
+//! AES-256-CBC ciphertext with PKCS7 padding, and an HMAC-SHA256 over the IV
+//! and ciphertext. All of the helpers here are [`AsyncRead`] adapters so that
+//! they can be composed without holding whole files in memory.
+//!
+//! Objects already in the media tier can be decrypted with
+//! [`decrypt_from_media_tier`], or moved to a new [`MediaRootKey`] with
+//! [`reencrypt_for_media_tier`].
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::Poll;
+
+use aes::cipher::crypto_common::rand_core::{CryptoRng, RngCore};
+use aes::Aes256;
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use futures::{ready, AsyncRead};
+use hmac::{Hmac, Mac as _};
+use sha2::{Digest as _, Sha256};
+use subtle::ConstantTimeEq as _;
+
+use crate::frame::{Aes256CbcHmacEncryptor, AES_IV_SIZE, HMAC_LEN};
+use crate::key::{MediaEncryptionKey, MediaId, MediaRootKey};
+
+const AES_BLOCK_SIZE: usize = 16;
+const READ_CHUNK_SIZE: usize = 4096;
+
+/// Returns the media name for an attachment.
+///
+/// The name combines the hash of the attachment plaintext with the key the
+/// attachment was encrypted with, so it's unique to a particular upload of a
+/// particular file.
+pub fn media_name(plaintext_sha256: &[u8; 32], attachment_key: &[u8]) -> String {
+    let mut name = hex::encode(plaintext_sha256);
+    name.push_str(&hex::encode(attachment_key));
+    name
+}
+
+/// Wraps an already-encrypted attachment in the media tier's outer layer.
+///
+/// Returns the [`MediaId`] the object should be uploaded as, along with a
+/// reader that produces the bytes to upload.
+pub fn encrypt_for_media_tier<R: AsyncRead + Unpin>(
+    root_key: &MediaRootKey,
+    media_name: &str,
+    encrypted_attachment: R,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> (MediaId, EncryptingReader<R>) {
+    let media_id = root_key.derive_media_id(media_name);
+    let key = root_key.derive_media_encryption_key(&media_id);
+    (
+        media_id,
+        EncryptingReader::new(&key, encrypted_attachment, rng),
+    )
+}
+
+/// Like [`encrypt_for_media_tier`], but for an attachment's thumbnail.
+pub fn encrypt_thumbnail_for_media_tier<R: AsyncRead + Unpin>(
+    root_key: &MediaRootKey,
+    media_name: &str,
+    encrypted_thumbnail: R,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> (MediaId, EncryptingReader<R>) {
+    let media_id = root_key.derive_media_id(media_name);
+    let key = root_key.derive_thumbnail_encryption_key(&media_id);
+    (
+        media_id,
+        EncryptingReader::new(&key, encrypted_thumbnail, rng),
+    )
+}
+
+/// Removes the media tier's outer layer from an object encrypted with
+/// [`encrypt_for_media_tier`].
+///
+/// See [`DecryptingReader`] for how authentication failures are reported.
+pub fn decrypt_from_media_tier<R: AsyncRead + Unpin>(
+    root_key: &MediaRootKey,
+    media_name: &str,
+    media_tier_object: R,
+) -> DecryptingReader<R> {
+    let media_id = root_key.derive_media_id(media_name);
+    let key = root_key.derive_media_encryption_key(&media_id);
+    DecryptingReader::new(&key, media_tier_object)
+}
+
+/// Like [`decrypt_from_media_tier`], but for an attachment's thumbnail.
+pub fn decrypt_thumbnail_from_media_tier<R: AsyncRead + Unpin>(
+    root_key: &MediaRootKey,
+    media_name: &str,
+    media_tier_object: R,
+) -> DecryptingReader<R> {
+    let media_id = root_key.derive_media_id(media_name);
+    let key = root_key.derive_thumbnail_encryption_key(&media_id);
+    DecryptingReader::new(&key, media_tier_object)
+}
+
+/// Re-encrypts an object in the media tier from `old_root_key` to
+/// `new_root_key`.
+///
+/// Returns the [`MediaId`] the object should be uploaded as under the new
+/// key, along with a reader that produces the bytes to upload. The old layer
+/// is only authenticated once all of it has been read, so if the reader
+/// returns an error the output produced so far must be discarded.
+pub fn reencrypt_for_media_tier<R: AsyncRead + Unpin>(
+    old_root_key: &MediaRootKey,
+    new_root_key: &MediaRootKey,
+    media_name: &str,
+    media_tier_object: R,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> (MediaId, EncryptingReader<DecryptingReader<R>>) {
+    let decrypted = decrypt_from_media_tier(old_root_key, media_name, media_tier_object);
+    encrypt_for_media_tier(new_root_key, media_name, decrypted, rng)
+}
+
+/// Like [`reencrypt_for_media_tier`], but for an attachment's thumbnail.
+pub fn reencrypt_thumbnail_for_media_tier<R: AsyncRead + Unpin>(
+    old_root_key: &MediaRootKey,
+    new_root_key: &MediaRootKey,
+    media_name: &str,
+    media_tier_object: R,
+    rng: &mut (impl RngCore + CryptoRng),
+) -> (MediaId, EncryptingReader<DecryptingReader<R>>) {
+    let decrypted = decrypt_thumbnail_from_media_tier(old_root_key, media_name, media_tier_object);
+    encrypt_thumbnail_for_media_tier(new_root_key, media_name, decrypted, rng)
+}
+
+/// [`AsyncRead`]er that encrypts the contents of another reader.
+///
+/// Produces the IV, then the AES-256-CBC ciphertext, then an HMAC-SHA256 of
+/// everything before it.
+pub struct EncryptingReader<R> {
+    reader: R,
+    encryptor: Aes256CbcHmacEncryptor,
+    /// Bytes that are ready to be returned to the caller.
+    output: VecDeque<u8>,
+}
+
+impl<R> EncryptingReader<R> {
+    pub fn new(key: &MediaEncryptionKey, reader: R, rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut iv = [0; AES_IV_SIZE];
+        rng.fill_bytes(&mut iv);
+
+        let mut output = VecDeque::new();
+        let encryptor = Aes256CbcHmacEncryptor::new(&key.aes_key, &key.hmac_key, &iv, &mut output);
+
+        Self {
+            reader,
+            encryptor,
+            output,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for EncryptingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<futures::io::Result<usize>> {
+        let this = self.get_mut();
+
+        while this.output.is_empty() && !this.encryptor.is_finalized() {
+            let mut chunk = [0; READ_CHUNK_SIZE];
+            let read = ready!(Pin::new(&mut this.reader).poll_read(cx, &mut chunk))?;
+            if read == 0 {
+                this.encryptor.finalize(&mut this.output);
+            } else {
+                this.encryptor.update(&chunk[..read], &mut this.output);
+            }
+        }
+
+        let count = buf.len().min(this.output.len());
+        for (dest, src) in buf.iter_mut().zip(this.output.drain(..count)) {
+            *dest = src;
+        }
+        Poll::Ready(Ok(count))
+    }
+}
+
+/// [`AsyncRead`]er that decrypts the output of an [`EncryptingReader`].
+///
+/// Plaintext is produced as soon as it's decrypted, but the HMAC can only be
+/// checked once the whole input has been read. If it doesn't match, or the
+/// input is malformed, the final read fails with
+/// [`std::io::ErrorKind::InvalidData`] and everything produced before it must
+/// be discarded.
+pub struct DecryptingReader<R> {
+    reader: R,
+    aes_key: [u8; MediaEncryptionKey::AES_KEY_LEN],
+    decryptor: Option<cbc::Decryptor<Aes256>>,
+    mac: Option<Hmac<Sha256>>,
+    /// Input bytes that might still turn out to be the HMAC.
+    held: VecDeque<u8>,
+    /// The most recently decrypted block, kept back until it's known whether
+    /// it holds the padding.
+    last_block: Option<[u8; AES_BLOCK_SIZE]>,
+    /// Bytes that are ready to be returned to the caller.
+    output: VecDeque<u8>,
+}
+
+impl<R> DecryptingReader<R> {
+    pub fn new(key: &MediaEncryptionKey, reader: R) -> Self {
+        Self {
+            reader,
+            aes_key: key.aes_key,
+            decryptor: None,
+            mac: Some(
+                Hmac::<Sha256>::new_from_slice(&key.hmac_key)
+                    .expect("HMAC-SHA256 should accept any size key"),
+            ),
+            held: VecDeque::new(),
+            last_block: None,
+            output: VecDeque::new(),
+        }
+    }
+
+    /// Decrypts every block that can't be part of the trailing HMAC.
+    fn decrypt_available(&mut self, ciphertext: &[u8]) {
+        let Self {
+            aes_key,
+            decryptor,
+            mac,
+            held,
+            last_block,
+            output,
+            reader: _,
+        } = self;
+        let mac = mac.as_mut().expect("not finished");
+        held.extend(ciphertext);
+
+        if decryptor.is_none() {
+            if held.len() < AES_IV_SIZE {
+                return;
+            }
+            let iv: [u8; AES_IV_SIZE] =
+                std::array::from_fn(|_| held.pop_front().expect("long enough"));
+            mac.update(&iv);
+            *decryptor = Some(cbc::Decryptor::new(&(*aes_key).into(), &iv.into()));
+        }
+        let decryptor = decryptor.as_mut().expect("initialized above");
+
+        while held.len() >= HMAC_LEN + AES_BLOCK_SIZE {
+            let mut block: [u8; AES_BLOCK_SIZE] =
+                std::array::from_fn(|_| held.pop_front().expect("long enough"));
+            mac.update(&block);
+            decryptor.decrypt_block_mut((&mut block).into());
+            if let Some(previous) = last_block.replace(block) {
+                output.extend(previous);
+            }
+        }
+    }
+
+    /// Checks the HMAC and removes the padding from the final block.
+    fn finish(&mut self) -> std::io::Result<()> {
+        let invalid = |message| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let mac = self.mac.take().expect("only finished once");
+
+        if self.held.len() != HMAC_LEN {
+            return Err(invalid("not a whole number of blocks"));
+        }
+        let expected: [u8; HMAC_LEN] = mac.finalize().into_bytes().into();
+        let found: [u8; HMAC_LEN] = std::array::from_fn(|i| self.held[i]);
+        if expected.ct_ne(&found).into() {
+            return Err(invalid("HMAC doesn't match"));
+        }
+
+        let last_block = self
+            .last_block
+            .take()
+            .ok_or_else(|| invalid("no ciphertext"))?;
+        let pad_len = usize::from(last_block[AES_BLOCK_SIZE - 1]);
+        if !(1..=AES_BLOCK_SIZE).contains(&pad_len)
+            || last_block[AES_BLOCK_SIZE - pad_len..]
+                .iter()
+                .any(|&b| usize::from(b) != pad_len)
+        {
+            return Err(invalid("invalid padding"));
+        }
+        self.output.extend(&last_block[..AES_BLOCK_SIZE - pad_len]);
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DecryptingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<futures::io::Result<usize>> {
+        let this = self.get_mut();
+
+        while this.output.is_empty() && this.mac.is_some() {
+            let mut chunk = [0; READ_CHUNK_SIZE];
+            let read = ready!(Pin::new(&mut this.reader).poll_read(cx, &mut chunk))?;
+            if read == 0 {
+                this.finish()?;
+            } else {
+                this.decrypt_available(&chunk[..read]);
+            }
+        }
+
+        let count = buf.len().min(this.output.len());
+        for (dest, src) in buf.iter_mut().zip(this.output.drain(..count)) {
+            *dest = src;
+        }
+        Poll::Ready(Ok(count))
+    }
+}
+
+/// [`AsyncRead`]er that computes a SHA-256 digest of the produced contents.
+///
+/// Useful for computing the plaintext hash needed for [`media_name`] while
+/// the plaintext is being encrypted.
+pub struct Sha256Reader<R> {
+    reader: R,
+    hasher: Sha256,
+}
+
+impl<R> Sha256Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Returns the digest of all the bytes read so far.
+    pub fn finalize(self) -> [u8; 32] {
+        self.hasher.finalize().into()
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Sha256Reader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<futures::io::Result<usize>> {
+        let Self { reader, hasher } = self.get_mut();
+        let read = ready!(Pin::new(reader).poll_read(cx, buf))?;
+        hasher.update(&buf[..read]);
+        Poll::Ready(Ok(read))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aes::cipher::crypto_common::rand_core::OsRng;
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+    use futures::AsyncReadExt as _;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::key::BackupKey;
+
+    fn test_root_key() -> MediaRootKey {
+        BackupKey::derive_from_master_key(&[0x11; 32]).derive_media_root_key()
+    }
+
+    #[test_case(0)]
+    #[test_case(1)]
+    #[test_case(AES_BLOCK_SIZE)]
+    #[test_case(READ_CHUNK_SIZE + 3)]
+    fn encrypts_and_authenticates(len: usize) {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let root_key = test_root_key();
+
+        let (media_id, mut reader) =
+            encrypt_for_media_tier(&root_key, "name", Cursor::new(&plaintext), &mut OsRng);
+        assert_eq!(media_id, root_key.derive_media_id("name"));
+
+        let mut encrypted = Vec::new();
+        block_on(reader.read_to_end(&mut encrypted)).expect("can read");
+
+        let padded_len = (len / AES_BLOCK_SIZE + 1) * AES_BLOCK_SIZE;
+        assert_eq!(encrypted.len(), AES_IV_SIZE + padded_len + HMAC_LEN);
+
+        let key = root_key.derive_media_encryption_key(&media_id);
+        let (authenticated, hmac) = encrypted.split_at(encrypted.len() - HMAC_LEN);
+        Hmac::<Sha256>::new_from_slice(&key.hmac_key)
+            .unwrap()
+            .chain_update(authenticated)
+            .verify_slice(hmac)
+            .expect("valid HMAC");
+
+        let (iv, ciphertext) = authenticated.split_at(AES_IV_SIZE);
+        let decrypted =
+            signal_crypto::aes_256_cbc_decrypt(ciphertext, &key.aes_key, iv).expect("can decrypt");
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test_case(0)]
+    #[test_case(1)]
+    #[test_case(AES_BLOCK_SIZE)]
+    #[test_case(READ_CHUNK_SIZE + 3)]
+    fn decrypts_what_it_encrypts(len: usize) {
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let root_key = test_root_key();
+
+        let (_, mut reader) =
+            encrypt_for_media_tier(&root_key, "name", Cursor::new(&plaintext), &mut OsRng);
+        let mut encrypted = Vec::new();
+        block_on(reader.read_to_end(&mut encrypted)).expect("can read");
+
+        let mut decrypted = Vec::new();
+        block_on(
+            decrypt_from_media_tier(&root_key, "name", Cursor::new(encrypted))
+                .read_to_end(&mut decrypted),
+        )
+        .expect("can decrypt");
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn reencrypts_for_new_root_key() {
+        let plaintext = vec![0x42; READ_CHUNK_SIZE * 2 + 5];
+        let old_root_key = test_root_key();
+        let new_root_key = BackupKey::derive_from_master_key(&[0x22; 32]).derive_media_root_key();
+
+        let (_, mut reader) = encrypt_thumbnail_for_media_tier(
+            &old_root_key,
+            "name",
+            Cursor::new(&plaintext),
+            &mut OsRng,
+        );
+        let mut old_object = Vec::new();
+        block_on(reader.read_to_end(&mut old_object)).expect("can read");
+
+        let (media_id, mut reader) = reencrypt_thumbnail_for_media_tier(
+            &old_root_key,
+            &new_root_key,
+            "name",
+            Cursor::new(old_object),
+            &mut OsRng,
+        );
+        assert_eq!(media_id, new_root_key.derive_media_id("name"));
+        let mut new_object = Vec::new();
+        block_on(reader.read_to_end(&mut new_object)).expect("can read");
+
+        let mut decrypted = Vec::new();
+        block_on(
+            decrypt_thumbnail_from_media_tier(&new_root_key, "name", Cursor::new(&new_object))
+                .read_to_end(&mut decrypted),
+        )
+        .expect("can decrypt");
+        assert_eq!(decrypted, plaintext);
+
+        let mut decrypted = Vec::new();
+        block_on(
+            decrypt_thumbnail_from_media_tier(&old_root_key, "name", Cursor::new(&new_object))
+                .read_to_end(&mut decrypted),
+        )
+        .expect_err("old key no longer works");
+    }
+
+    #[test_case(|object| *object.last_mut().unwrap() ^= 1; "tampered HMAC")]
+    #[test_case(|object| object[AES_IV_SIZE] ^= 1; "tampered ciphertext")]
+    #[test_case(|object| { object.pop(); }; "truncated")]
+    #[test_case(|object| object.truncate(AES_IV_SIZE + HMAC_LEN); "no ciphertext")]
+    #[test_case(|object| object.clear(); "empty")]
+    fn rejects_invalid_objects(modify: fn(&mut Vec<u8>)) {
+        let root_key = test_root_key();
+        let (_, mut reader) =
+            encrypt_for_media_tier(&root_key, "name", Cursor::new([1, 2, 3]), &mut OsRng);
+        let mut encrypted = Vec::new();
+        block_on(reader.read_to_end(&mut encrypted)).expect("can read");
+        modify(&mut encrypted);
+
+        let error = block_on(
+            decrypt_from_media_tier(&root_key, "name", Cursor::new(encrypted))
+                .read_to_end(&mut Vec::new()),
+        )
+        .expect_err("invalid");
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn thumbnail_uses_different_key() {
+        let root_key = test_root_key();
+        let (media_id, _) =
+            encrypt_thumbnail_for_media_tier(&root_key, "name", Cursor::new([]), &mut OsRng);
+        assert_ne!(
+            root_key.derive_media_encryption_key(&media_id),
+            root_key.derive_thumbnail_encryption_key(&media_id)
+        );
+    }
+
+    #[test]
+    fn sha256_reader_matches_digest() {
+        let mut reader = Sha256Reader::new(Cursor::new(b"this was a triumph"));
+        block_on(futures::io::copy(&mut reader, &mut futures::io::sink())).expect("can read");
+        assert_eq!(
+            reader.finalize(),
+            <[u8; 32]>::from(Sha256::digest(b"this was a triumph"))
+        );
+    }
+
+    #[test]
+    fn media_name_is_hex() {
+        assert_eq!(
+            media_name(&[0xab; 32], &[0x01, 0x02]),
+            format!("{}0102", "ab".repeat(32))
+        );
+    }
+}
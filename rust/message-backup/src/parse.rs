@@ -14,13 +14,37 @@ pub enum ParseError {
     Io(#[from] std::io::Error),
     /// proto decode error: {0}
     Decode(#[from] protobuf::Error),
+    /// frame of {length} bytes exceeds the maximum of {max} bytes
+    FrameTooLarge { length: usize, max: usize },
 }
 
 const VARINT_MAX_LENGTH: usize = 10;
 
+/// The largest single frame that will be read into memory by default.
+///
+/// Real backup frames are far smaller than this; the limit exists so that a
+/// corrupt or malicious length prefix can't cause an arbitrarily large
+/// allocation.
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 16 * 1024 * 1024;
+
 pub(crate) struct VarintDelimitedReader<R> {
     reader: R,
     buffer: ArrayVec<u8, VARINT_MAX_LENGTH>,
+    max_frame_length: usize,
+    bytes_consumed: u64,
+}
+
+impl<R> VarintDelimitedReader<R> {
+    /// Sets the length above which [`Self::read_next`] will refuse to read a frame.
+    pub(crate) fn set_max_frame_length(&mut self, max_frame_length: usize) {
+        self.max_frame_length = max_frame_length;
+    }
+
+    /// The number of bytes, including length prefixes, consumed by the frames
+    /// returned so far.
+    pub(crate) fn bytes_consumed(&self) -> u64 {
+        self.bytes_consumed
+    }
 }
 
 impl<R: AsyncRead + Unpin> VarintDelimitedReader<R> {
@@ -28,6 +52,8 @@ impl<R: AsyncRead + Unpin> VarintDelimitedReader<R> {
         Self {
             reader,
             buffer: ArrayVec::new(),
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            bytes_consumed: 0,
         }
     }
 
@@ -37,7 +63,19 @@ impl<R: AsyncRead + Unpin> VarintDelimitedReader<R> {
             Some(length) => length,
         };
 
-        let Self { reader, buffer } = self;
+        let Self {
+            reader,
+            buffer,
+            max_frame_length,
+            bytes_consumed,
+        } = self;
+
+        if length > *max_frame_length {
+            return Err(ParseError::FrameTooLarge {
+                length,
+                max: *max_frame_length,
+            });
+        }
 
         // Read `length` bytes, first from the buffer, then from the reader.
         let mut buf = Vec::with_capacity(length);
@@ -51,6 +89,8 @@ impl<R: AsyncRead + Unpin> VarintDelimitedReader<R> {
             reader.read_exact(&mut buf[buffered_byte_count..]).await?;
         }
 
+        *bytes_consumed += u64::try_from(length).expect("usize fits in u64");
+
         Ok(Some(buf.into_boxed_slice()))
     }
 
@@ -60,7 +100,12 @@ impl<R: AsyncRead + Unpin> VarintDelimitedReader<R> {
     }
 
     async fn read_next_varint(&mut self) -> Result<Option<usize>, ParseError> {
-        let Self { buffer, reader } = self;
+        let Self {
+            buffer,
+            reader,
+            max_frame_length: _,
+            bytes_consumed,
+        } = self;
 
         fill_buffer_from_reader(reader, buffer).await?;
 
@@ -82,6 +127,7 @@ impl<R: AsyncRead + Unpin> VarintDelimitedReader<R> {
         drop(proto_reader);
 
         buffer.drain(..consumed_byte_count);
+        *bytes_consumed += u64::try_from(consumed_byte_count).expect("usize fits in u64");
 
         Ok(Some(length.try_into().expect("u32::MAX < usize::MAX")))
     }
@@ -158,6 +204,41 @@ mod test {
             Err(ParseError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
         );
     }
+
+    #[test]
+    fn read_length_delimited_too_large() {
+        const MESSAGE: MessageAndLen<1, 9> = MessageAndLen::new([9], *b"123456789");
+        assert_valid(&MESSAGE);
+
+        let mut reader = VarintDelimitedReader::new(MESSAGE.into_reader());
+        reader.set_max_frame_length(8);
+        pin_mut!(reader);
+
+        assert_matches!(
+            block_on(reader.read_next()),
+            Err(ParseError::FrameTooLarge { length: 9, max: 8 })
+        );
+    }
+
+    #[test]
+    fn read_length_delimited_counts_consumed_bytes() {
+        const FIRST: MessageAndLen<1, 5> = MessageAndLen::new([5], *b"12345");
+        const SECOND: MessageAndLen<2, 256> = MessageAndLen::new([0x80, 0x02], [0xab; 256]);
+        assert_valid(&FIRST);
+        assert_valid(&SECOND);
+
+        let reader = VarintDelimitedReader::new(FIRST.into_reader().chain(SECOND.into_reader()));
+        pin_mut!(reader);
+
+        assert_eq!(reader.bytes_consumed(), 0);
+        let _ = block_on(reader.read_next()).expect("can read");
+        assert_eq!(reader.bytes_consumed(), 1 + 5);
+        let _ = block_on(reader.read_next()).expect("can read");
+        assert_eq!(reader.bytes_consumed(), 1 + 5 + 2 + 256);
+        assert_matches!(block_on(reader.read_next()), Ok(None));
+        assert_eq!(reader.bytes_consumed(), 1 + 5 + 2 + 256);
+    }
+
     struct MessageAndLen<const L: usize, const M: usize> {
         varint: [u8; L],
         message: [u8; M],
@@ -0,0 +1,193 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Options for restoring part of a backup.
+//!
+//! A restore can be limited to a subset of [`FrameCategory`]s, and can resume
+//! from the [`RestoreCursor`] reported by a previous, interrupted restore.
+//! Frames that are needed to resolve references from selected frames (for
+//! example, the recipient for a chat) are always processed, even if they
+//! weren't selected or come before the cursor.
+
+use crate::proto::backup as proto;
+
+/// The kinds of frame that can appear in a backup after the `BackupInfo`.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, strum::Display, strum::EnumIter)]
+pub enum FrameCategory {
+    AccountData,
+    Recipient,
+    Chat,
+    ChatItem,
+    StickerPack,
+    AdHocCall,
+}
+
+/// A set of [`FrameCategory`]s.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FrameSelection(u8);
+
+/// Position within a backup from which a restore can be resumed.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct RestoreCursor {
+    /// The index of the first frame that hasn't been restored.
+    ///
+    /// The `BackupInfo` header is frame 0.
+    pub frame_index: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RestoreOptions {
+    /// The categories of frames to restore.
+    pub selection: FrameSelection,
+    /// If set, frames before this point are only processed if they are
+    /// needed to resolve references from later frames.
+    pub resume_from: Option<RestoreCursor>,
+}
+
+impl FrameCategory {
+    pub(crate) fn of(item: &proto::frame::Item) -> Self {
+        match item {
+            proto::frame::Item::Account(_) => Self::AccountData,
+            proto::frame::Item::Recipient(_) => Self::Recipient,
+            proto::frame::Item::Chat(_) => Self::Chat,
+            proto::frame::Item::ChatItem(_) => Self::ChatItem,
+            proto::frame::Item::StickerPack(_) => Self::StickerPack,
+            proto::frame::Item::AdHocCall(_) => Self::AdHocCall,
+        }
+    }
+
+    /// The categories that frames of this category can refer to.
+    fn references(self) -> FrameSelection {
+        use FrameCategory::*;
+        match self {
+            AccountData | StickerPack => FrameSelection::NONE,
+            // Distribution lists refer to other recipients.
+            Recipient => FrameSelection::NONE.with(Recipient),
+            // Chat styles can refer to custom colors in the account settings.
+            Chat => FrameSelection::NONE.with(Recipient).with(AccountData),
+            ChatItem => Chat.references().with(Chat),
+            AdHocCall => FrameSelection::NONE.with(Recipient),
+        }
+    }
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl FrameSelection {
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self(
+        FrameCategory::AccountData.bit()
+            | FrameCategory::Recipient.bit()
+            | FrameCategory::Chat.bit()
+            | FrameCategory::ChatItem.bit()
+            | FrameCategory::StickerPack.bit()
+            | FrameCategory::AdHocCall.bit(),
+    );
+
+    #[must_use]
+    pub const fn with(self, category: FrameCategory) -> Self {
+        Self(self.0 | category.bit())
+    }
+
+    pub const fn contains(self, category: FrameCategory) -> bool {
+        self.0 & category.bit() != 0
+    }
+
+    /// The categories whose frames are needed to resolve references from
+    /// frames in this selection.
+    fn referenced(self) -> Self {
+        <FrameCategory as strum::IntoEnumIterator>::iter()
+            .filter(|c| self.contains(*c))
+            .fold(Self::NONE, |acc, c| Self(acc.0 | c.references().0))
+    }
+}
+
+impl Default for FrameSelection {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl FromIterator<FrameCategory> for FrameSelection {
+    fn from_iter<T: IntoIterator<Item = FrameCategory>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::NONE, Self::with)
+    }
+}
+
+impl RestoreOptions {
+    /// Decides whether the frame at `frame_index` should be processed.
+    ///
+    /// Account data is always processed since a backup isn't complete
+    /// without it.
+    pub(crate) fn should_process(&self, frame_index: usize, category: FrameCategory) -> bool {
+        let Self {
+            selection,
+            resume_from,
+        } = self;
+        let referenced = selection.referenced().with(FrameCategory::AccountData);
+
+        if referenced.contains(category) {
+            return true;
+        }
+        let before_cursor = resume_from.is_some_and(|cursor| frame_index < cursor.frame_index);
+        selection.contains(category) && !before_cursor
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::*;
+    use crate::restore::FrameCategory::*;
+
+    #[test]
+    fn all_contains_every_category() {
+        for category in <FrameCategory as strum::IntoEnumIterator>::iter() {
+            assert!(FrameSelection::ALL.contains(category), "{category:?}");
+        }
+        assert_eq!(
+            FrameSelection::ALL,
+            <FrameCategory as strum::IntoEnumIterator>::iter().collect()
+        );
+    }
+
+    #[test_case(&[Recipient], Recipient, true)]
+    #[test_case(&[Recipient], Chat, false)]
+    #[test_case(&[Recipient], AccountData, true; "account data always processed")]
+    #[test_case(&[ChatItem], Chat, true; "chat items need chats")]
+    #[test_case(&[ChatItem], Recipient, true; "chat items need recipients")]
+    #[test_case(&[AdHocCall], Chat, false)]
+    #[test_case(&[StickerPack], Recipient, false)]
+    fn selection_includes_references(
+        selected: &[FrameCategory],
+        category: FrameCategory,
+        expected: bool,
+    ) {
+        let options = RestoreOptions {
+            selection: selected.iter().copied().collect(),
+            resume_from: None,
+        };
+        assert_eq!(options.should_process(10, category), expected);
+    }
+
+    #[test_case(4, ChatItem, false; "skipped before cursor")]
+    #[test_case(5, ChatItem, true; "processed at cursor")]
+    #[test_case(4, Chat, true; "referenced before cursor")]
+    #[test_case(4, StickerPack, false)]
+    fn resume_skips_unreferenced_frames(
+        frame_index: usize,
+        category: FrameCategory,
+        expected: bool,
+    ) {
+        let options = RestoreOptions {
+            selection: FrameSelection::ALL,
+            resume_from: Some(RestoreCursor { frame_index: 5 }),
+        };
+        assert_eq!(options.should_process(frame_index, category), expected);
+    }
+}
@@ -0,0 +1,142 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Redaction of user content from error messages.
+//!
+//! Validation errors are written to be useful to developers, which means they
+//! sometimes include values from the backup being validated. Wrapping an error
+//! in [`Scrubbed`] produces text that is safe to include in a bug report:
+//! quoted strings, UUIDs, phone-number-length digit sequences, and long hex
+//! strings are redacted, while the structure of the message (including frame
+//! indices and field paths) is kept.
+
+use std::fmt::{Debug, Display};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// The shortest run of digits that is treated as potentially sensitive.
+///
+/// This is long enough to catch phone numbers while keeping frame indices,
+/// counts, and other small numbers.
+const MIN_SENSITIVE_DIGITS: usize = 7;
+
+/// The shortest run of hex digits that is treated as potentially sensitive.
+const MIN_SENSITIVE_HEX_DIGITS: usize = 16;
+
+/// Wrapper whose [`Display`] and [`Debug`] impls redact user content.
+#[derive(Copy, Clone)]
+pub struct Scrubbed<T>(pub T);
+
+impl<T: Display> Display for Scrubbed<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&scrub(&self.0.to_string()))
+    }
+}
+
+impl<T: Debug> Debug for Scrubbed<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&scrub(&format!("{:?}", self.0)))
+    }
+}
+
+/// Redacts potentially sensitive content from `input`.
+pub fn scrub(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c == '"' {
+            // Skip to the closing quote, respecting escapes.
+            let mut escaped = false;
+            for (_, c) in chars.by_ref() {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => break,
+                    _ => (),
+                }
+            }
+            output.push('"');
+            output.push_str(REDACTED);
+            output.push('"');
+            continue;
+        }
+
+        if !is_word_char(c) {
+            output.push(c);
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+            end = i + c.len_utf8();
+        }
+        let word = &input[start..end];
+        output.push_str(if is_sensitive_word(word) {
+            REDACTED
+        } else {
+            word
+        });
+    }
+
+    output
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-'
+}
+
+fn is_sensitive_word(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    let is_uuid = bytes.len() == 36
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => *b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        });
+    let is_long_number =
+        bytes.len() >= MIN_SENSITIVE_DIGITS && bytes.iter().all(u8::is_ascii_digit);
+    let is_long_hex =
+        bytes.len() >= MIN_SENSITIVE_HEX_DIGITS && bytes.iter().all(u8::is_ascii_hexdigit);
+
+    is_uuid || is_long_number || is_long_hex
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case("no content", "no content"; "plain text")]
+    #[test_case(
+        "chat frame ChatId(3) error: chat item: quote: has unknown author RecipientId(9)",
+        "chat frame ChatId(3) error: chat item: quote: has unknown author RecipientId(9)";
+        "structure is kept"
+    )]
+    #[test_case("text was \"hello, world\"", "text was \"[REDACTED]\""; "quoted string")]
+    #[test_case(r#"text "with \"escaped\" quotes" ok"#, r#"text "[REDACTED]" ok"#; "escaped quotes")]
+    #[test_case("e164 +15555550100", "e164 +[REDACTED]"; "phone number")]
+    #[test_case(
+        "aci 55555555-5555-5555-5555-555555555555 invalid",
+        "aci [REDACTED] invalid";
+        "uuid"
+    )]
+    #[test_case(
+        "key 7624d47e91d7f4de5eae5f00a1662984",
+        "key [REDACTED]";
+        "hex"
+    )]
+    #[test_case("in frame 1234, standardMessage.text", "in frame 1234, standardMessage.text"; "frame index and path")]
+    fn scrubs(input: &str, expected: &str) {
+        assert_eq!(scrub(input), expected);
+    }
+
+    #[test]
+    fn scrubbed_wrapper_covers_display_and_debug() {
+        let value = "+15555550100".to_owned();
+        assert_eq!(Scrubbed(&value).to_string(), "+[REDACTED]");
+        assert_eq!(format!("{:?}", Scrubbed(&value)), "\"[REDACTED]\"");
+    }
+}
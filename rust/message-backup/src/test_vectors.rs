@@ -0,0 +1,135 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Deterministic generation of encrypted backup files.
+//!
+//! The keys, forward secrecy token, and IV for a backup are derived from a
+//! 32-byte seed, so the same
+//! seed and frames always produce the same file. Test suites for the
+//! app-facing bridges and independent implementations can use this to
+//! regenerate encrypted fixtures from their plaintext frames instead of
+//! checking in the encrypted files, so the output for a given seed must not
+//! change; the tests pin a hash of it.
+
+use libsignal_core::Aci;
+use rand_chacha::rand_core::{RngCore as _, SeedableRng as _};
+use rand_chacha::ChaCha20Rng;
+
+use crate::backup::Purpose;
+use crate::key::{BackupKey, MessageBackupKey};
+use crate::writer::{BackupWriter, WriteError, WrittenBackup};
+
+/// An encrypted backup file along with what's needed to decrypt it.
+pub struct BackupFileVector {
+    pub master_key: [u8; BackupKey::MASTER_KEY_LEN],
+    pub aci: Aci,
+    /// The key derived from `master_key`, `aci`, and the backup's forward
+    /// secrecy token.
+    pub key: MessageBackupKey,
+    pub backup: WrittenBackup<Vec<u8>>,
+}
+
+/// Writes a remote backup from a serialized `BackupInfo` header and frames,
+/// encrypting it with keys derived from `seed`.
+///
+/// The frames are validated just as [`BackupWriter::add_frame`] does.
+pub async fn encrypted_backup<'a>(
+    seed: [u8; 32],
+    backup_info: &[u8],
+    frames: impl IntoIterator<Item = &'a [u8]>,
+) -> Result<BackupFileVector, WriteError> {
+    let mut rng = ChaCha20Rng::from_seed(seed);
+
+    let mut master_key = [0; BackupKey::MASTER_KEY_LEN];
+    rng.fill_bytes(&mut master_key);
+    let mut aci_bytes = [0; 16];
+    rng.fill_bytes(&mut aci_bytes);
+    let aci = Aci::from_uuid_bytes(aci_bytes);
+
+    let backup_key = BackupKey::derive_from_master_key(&master_key);
+    let backup_id = backup_key.derive_backup_id(&aci);
+
+    let mut writer = BackupWriter::new_encrypted(
+        backup_info,
+        Purpose::RemoteBackup,
+        &backup_key,
+        &backup_id,
+        Vec::new(),
+        &mut rng,
+    )
+    .await?;
+    for frame in frames {
+        writer.add_frame(frame).await?;
+    }
+    let backup = writer.finish_encrypted().await?;
+    let key = MessageBackupKey::derive_with_forward_secrecy_token(
+        &backup_key,
+        &backup_id,
+        &backup.metadata.forward_secrecy_token,
+    );
+
+    Ok(BackupFileVector {
+        master_key,
+        aci,
+        key,
+        backup,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use futures::executor::block_on;
+    use protobuf::Message as _;
+    use sha2::{Digest as _, Sha256};
+
+    use super::*;
+    use crate::frame::CursorFactory;
+    use crate::proto::backup as proto;
+    use crate::BackupReader;
+
+    fn generate(seed: [u8; 32]) -> BackupFileVector {
+        let info = proto::BackupInfo {
+            version: 1,
+            backupTimeMs: 1715636551000,
+            ..Default::default()
+        }
+        .write_to_bytes()
+        .expect("can serialize");
+        let account_data = proto::Frame {
+            item: Some(proto::AccountData::test_data().into()),
+            ..Default::default()
+        }
+        .write_to_bytes()
+        .expect("can serialize");
+
+        block_on(encrypted_backup(seed, &info, [&account_data[..]])).expect("valid backup")
+    }
+
+    #[test]
+    fn encrypted_backup_is_deterministic_and_readable() {
+        let vector = generate([1; 32]);
+        assert_eq!(vector.backup.output, generate([1; 32]).backup.output);
+        assert_ne!(vector.backup.output, generate([2; 32]).backup.output);
+
+        let reader = block_on(BackupReader::new_encrypted_compressed(
+            &vector.key,
+            CursorFactory::new(&vector.backup.output),
+            Purpose::RemoteBackup,
+        ))
+        .expect("valid HMAC");
+        block_on(reader.validate_all())
+            .result
+            .expect("valid backup");
+    }
+
+    #[test]
+    fn encrypted_backup_is_pinned() {
+        let vector = generate([1; 32]);
+        assert_eq!(
+            hex::encode(Sha256::digest(&vector.backup.output)),
+            "6bcf41eaa270699d8c9c87f01796afff245ac24d6ca06e2a33206381e0eb0238"
+        );
+    }
+}
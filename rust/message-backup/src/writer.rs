@@ -0,0 +1,536 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Creation of message backup files.
+//!
+//! A [`BackupWriter`] accepts serialized frames one at a time, validating each
+//! against the frames that came before it using the same rules as
+//! [`BackupReader`](crate::BackupReader). Frames are written to the output as
+//! soon as they're accepted, either as a plaintext varint-delimited sequence
+//! or compressed, encrypted, and authenticated as a complete backup file, so
+//! the backup is never held in memory all at once.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use aes::cipher::crypto_common::rand_core::{CryptoRng, RngCore};
+use async_compression::futures::write::GzipEncoder;
+use futures::{ready, AsyncWrite, AsyncWriteExt as _};
+use protobuf::Message as _;
+
+use crate::backup::method::ValidateOnly;
+use crate::backup::{CompletedBackup, CompletionError, PartialBackup, Purpose, ValidationError};
+use crate::frame::{Aes256CbcHmacEncryptor, AES_IV_SIZE, HMAC_LEN};
+use crate::key::{BackupForwardSecrecyToken, BackupId, BackupKey, MessageBackupKey};
+use crate::proto::backup as proto;
+
+/// Incrementally writes a backup file from individually serialized frames.
+pub struct BackupWriter<W> {
+    backup: PartialBackup<ValidateOnly>,
+    output: W,
+    frame_count: usize,
+    plaintext_len: u64,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum WriteError {
+    /// invalid protobuf: {0}
+    InvalidProtobuf(#[from] protobuf::Error),
+    /// the first frame after BackupInfo must be AccountData
+    AccountDataNotFirst,
+    /// frame of {0} bytes is too large to be written
+    FrameTooLarge(usize),
+    /// frame {index}: {error}
+    InvalidFrame {
+        index: usize,
+        error: ValidationError,
+    },
+    /// {0}
+    Incomplete(#[from] CompletionError),
+    /// io error: {0}
+    Io(#[from] std::io::Error),
+}
+
+/// A finished, encrypted backup file along with information about its contents.
+#[derive(Debug)]
+pub struct WrittenBackup<W> {
+    /// The output the backup file was written to: IV, ciphertext, then HMAC.
+    pub output: W,
+    pub metadata: WrittenBackupMetadata,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WrittenBackupMetadata {
+    /// The number of frames written, including the leading `BackupInfo`.
+    pub frame_count: usize,
+    /// The length of the varint-delimited plaintext before compression.
+    pub plaintext_len: u64,
+    /// The IV used to encrypt the compressed contents.
+    pub iv: [u8; AES_IV_SIZE],
+    /// The HMAC over the IV and ciphertext, also found at the end of the output.
+    pub hmac: [u8; HMAC_LEN],
+    /// The token mixed into the key this backup was encrypted with.
+    ///
+    /// The backup can only be decrypted with a key from
+    /// [`MessageBackupKey::derive_with_forward_secrecy_token`] using this
+    /// token.
+    pub forward_secrecy_token: BackupForwardSecrecyToken,
+}
+
+impl<W: AsyncWrite + Unpin> BackupWriter<W> {
+    /// Starts a new unencrypted backup with the provided serialized
+    /// `BackupInfo` header.
+    ///
+    /// Frames are written to `output` as varint-delimited plaintext.
+    pub async fn new(backup_info: &[u8], purpose: Purpose, output: W) -> Result<Self, WriteError> {
+        let info = proto::BackupInfo::parse_from_bytes(backup_info)?;
+
+        let mut writer = Self {
+            backup: PartialBackup::new_validator(info, purpose),
+            output,
+            frame_count: 0,
+            plaintext_len: 0,
+        };
+        writer.append_delimited(backup_info).await?;
+        Ok(writer)
+    }
+
+    /// Validates and appends a single serialized `Frame`.
+    ///
+    /// The frame is checked against everything written so far, so frames
+    /// must be added in an order where every reference (to a recipient, chat,
+    /// custom color, etc.) points at something that has already been written.
+    /// If the frame is rejected, nothing is written and the writer can
+    /// continue to be used. After an [`WriteError::Io`] the output is
+    /// incomplete and should be discarded.
+    pub async fn add_frame(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let frame_proto = proto::Frame::parse_from_bytes(frame)?;
+
+        let is_account_data = matches!(frame_proto.item, Some(proto::frame::Item::Account(_)));
+        // The BackupInfo header is frame 0, so AccountData must be frame 1.
+        if is_account_data != (self.frame_count == 1) {
+            return Err(WriteError::AccountDataNotFirst);
+        }
+
+        self.backup
+            .add_frame(frame_proto)
+            .map_err(|error| WriteError::InvalidFrame {
+                index: self.frame_count,
+                error,
+            })?;
+
+        self.append_delimited(frame).await
+    }
+
+    /// Checks that the backup is complete and flushes the output.
+    ///
+    /// If the backup is incomplete, the frames written so far don't form a
+    /// valid backup and the output should be discarded.
+    pub async fn finish(self) -> Result<W, WriteError> {
+        let Self {
+            backup,
+            mut output,
+            frame_count: _,
+            plaintext_len: _,
+        } = self;
+        let _: CompletedBackup<ValidateOnly> = backup.try_into()?;
+
+        output.flush().await?;
+        Ok(output)
+    }
+
+    async fn append_delimited(&mut self, bytes: &[u8]) -> Result<(), WriteError> {
+        let len = u32::try_from(bytes.len()).map_err(|_| WriteError::FrameTooLarge(bytes.len()))?;
+
+        let mut header = Vec::new();
+        let mut header_output = protobuf::CodedOutputStream::vec(&mut header);
+        header_output.write_raw_varint32(len)?;
+        header_output.flush()?;
+        drop(header_output);
+
+        self.output.write_all(&header).await?;
+        self.output.write_all(bytes).await?;
+
+        self.frame_count += 1;
+        self.plaintext_len += (header.len() + bytes.len()) as u64;
+        Ok(())
+    }
+}
+
+impl<W: AsyncWrite + Unpin> BackupWriter<EncryptingWriter<W>> {
+    /// Starts a new backup that is compressed and encrypted as it's written.
+    ///
+    /// A fresh [`BackupForwardSecrecyToken`] is generated for the backup and
+    /// mixed into the key it's encrypted with. The token is returned by
+    /// [`finish_encrypted`](Self::finish_encrypted) and must be stored
+    /// alongside the backup for it to be readable.
+    pub async fn new_encrypted(
+        backup_info: &[u8],
+        purpose: Purpose,
+        backup_key: &BackupKey,
+        backup_id: &BackupId,
+        output: W,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self, WriteError> {
+        let forward_secrecy_token = BackupForwardSecrecyToken::generate(rng);
+        let key = MessageBackupKey::derive_with_forward_secrecy_token(
+            backup_key,
+            backup_id,
+            &forward_secrecy_token,
+        );
+        let output = EncryptingWriter::new(&key, forward_secrecy_token, output, rng);
+        Self::new(backup_info, purpose, output).await
+    }
+
+    /// Finishes the backup, writing the padding, final block, and HMAC.
+    ///
+    /// The compressed contents are padded to a size bucket before encryption
+    /// to avoid leaking the exact size of the backup.
+    pub async fn finish_encrypted(self) -> Result<WrittenBackup<W>, WriteError> {
+        let frame_count = self.frame_count;
+        let plaintext_len = self.plaintext_len;
+        let EncryptingWriter {
+            mut compressor,
+            forward_secrecy_token,
+        } = self.finish().await?;
+
+        compressor.close().await?;
+        let mut encryptor = compressor.into_inner();
+        // Closing the compressor closes the encryptor too, but make sure.
+        encryptor.close().await?;
+
+        let PaddingEncryptor {
+            output, iv, hmac, ..
+        } = encryptor;
+        Ok(WrittenBackup {
+            output,
+            metadata: WrittenBackupMetadata {
+                frame_count,
+                plaintext_len,
+                iv,
+                hmac: hmac.expect("closed"),
+                forward_secrecy_token,
+            },
+        })
+    }
+}
+
+/// [`AsyncWrite`]r that compresses, pads, and encrypts a backup.
+///
+/// Created by [`BackupWriter::new_encrypted`].
+pub struct EncryptingWriter<W> {
+    compressor: GzipEncoder<PaddingEncryptor<W>>,
+    forward_secrecy_token: BackupForwardSecrecyToken,
+}
+
+impl<W: AsyncWrite + Unpin> EncryptingWriter<W> {
+    fn new(
+        key: &MessageBackupKey,
+        forward_secrecy_token: BackupForwardSecrecyToken,
+        output: W,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        Self {
+            compressor: GzipEncoder::new(PaddingEncryptor::new(key, output, rng)),
+            forward_secrecy_token,
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for EncryptingWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().compressor).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().compressor).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().compressor).poll_close(cx)
+    }
+}
+
+/// [`AsyncWrite`]r that encrypts compressed contents into a backup file.
+///
+/// Writes the IV, then the AES-256-CBC ciphertext, then an HMAC-SHA256 of
+/// everything before it. On close, the contents are padded with zeros up to
+/// the next size bucket before the final block is encrypted.
+struct PaddingEncryptor<W> {
+    output: W,
+    encryptor: Aes256CbcHmacEncryptor,
+    iv: [u8; AES_IV_SIZE],
+    hmac: Option<[u8; HMAC_LEN]>,
+    /// The number of compressed bytes written so far.
+    written_len: u64,
+    /// Zeros still to be written before the final block, once closing.
+    padding_remaining: Option<u64>,
+    /// Bytes that are ready to be written to `output`.
+    pending: VecDeque<u8>,
+}
+
+impl<W: AsyncWrite + Unpin> PaddingEncryptor<W> {
+    fn new(key: &MessageBackupKey, output: W, rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut iv = [0; AES_IV_SIZE];
+        rng.fill_bytes(&mut iv);
+
+        let mut pending = VecDeque::new();
+        let encryptor = Aes256CbcHmacEncryptor::new(&key.aes_key, &key.hmac_key, &iv, &mut pending);
+
+        Self {
+            output,
+            encryptor,
+            iv,
+            hmac: None,
+            written_len: 0,
+            padding_remaining: None,
+            pending,
+        }
+    }
+
+    /// Writes out everything in `pending`.
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while !self.pending.is_empty() {
+            let (front, _) = self.pending.as_slices();
+            let written = ready!(Pin::new(&mut self.output).poll_write(cx, front))?;
+            if written == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for PaddingEncryptor<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        assert!(this.padding_remaining.is_none(), "written after close");
+
+        ready!(this.poll_write_pending(cx))?;
+        this.written_len += buf.len() as u64;
+        this.encryptor.update(buf, &mut this.pending);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.output).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        const ZEROS: [u8; 4096] = [0; 4096];
+
+        let this = self.get_mut();
+        if this.padding_remaining.is_none() {
+            this.padding_remaining = Some(padded_len(this.written_len) - this.written_len);
+        }
+
+        loop {
+            ready!(this.poll_write_pending(cx))?;
+
+            let padding_remaining = this.padding_remaining.as_mut().expect("set above");
+            if *padding_remaining > 0 {
+                let count = (*padding_remaining).min(ZEROS.len() as u64);
+                *padding_remaining -= count;
+                this.encryptor
+                    .update(&ZEROS[..count as usize], &mut this.pending);
+            } else if !this.encryptor.is_finalized() {
+                this.hmac = Some(this.encryptor.finalize(&mut this.pending));
+            } else {
+                break;
+            }
+        }
+
+        Pin::new(&mut this.output).poll_flush(cx)
+    }
+}
+
+/// Returns the size bucket that gzip output of `len` bytes is padded up to.
+///
+/// Buckets start at 541 bytes and each is 5% larger than the last, so the
+/// padding overhead is bounded at ~5%. Trailing zeros after the gzip stream
+/// are ignored by readers.
+fn padded_len(len: u64) -> u64 {
+    const MIN_LEN: u64 = 541;
+
+    let mut bucket = MIN_LEN;
+    while bucket < len {
+        bucket += bucket / 20;
+    }
+    bucket
+}
+
+#[cfg(test)]
+mod test {
+    use aes::cipher::crypto_common::rand_core::OsRng;
+    use assert_matches::assert_matches;
+    use futures::executor::block_on;
+    use test_case::test_case;
+
+    use super::*;
+    use crate::frame::CursorFactory;
+    use crate::BackupReader;
+
+    fn serialize_frame(item: impl Into<proto::frame::Item>) -> Vec<u8> {
+        proto::Frame {
+            item: Some(item.into()),
+            ..Default::default()
+        }
+        .write_to_bytes()
+        .expect("can serialize")
+    }
+
+    fn serialized_info() -> Vec<u8> {
+        proto::BackupInfo {
+            version: 1,
+            backupTimeMs: 1715636551000,
+            ..Default::default()
+        }
+        .write_to_bytes()
+        .expect("can serialize")
+    }
+
+    fn new_writer() -> BackupWriter<Vec<u8>> {
+        block_on(BackupWriter::new(
+            &serialized_info(),
+            Purpose::RemoteBackup,
+            Vec::new(),
+        ))
+        .expect("valid header")
+    }
+
+    #[test]
+    fn round_trip_through_reader() {
+        let backup_key = BackupKey::derive_from_master_key(&[0x11; BackupKey::MASTER_KEY_LEN]);
+        let backup_id =
+            backup_key.derive_backup_id(&libsignal_core::Aci::from_uuid_bytes([0x22; 16]));
+
+        let mut writer = block_on(BackupWriter::new_encrypted(
+            &serialized_info(),
+            Purpose::RemoteBackup,
+            &backup_key,
+            &backup_id,
+            Vec::new(),
+            &mut OsRng,
+        ))
+        .expect("valid header");
+        for frame in [
+            serialize_frame(proto::AccountData::test_data()),
+            serialize_frame(proto::Recipient::test_data()),
+            serialize_frame(proto::Chat::test_data()),
+        ] {
+            block_on(writer.add_frame(&frame)).expect("valid frame");
+        }
+
+        let WrittenBackup { output, metadata } =
+            block_on(writer.finish_encrypted()).expect("can finish");
+        assert_eq!(metadata.frame_count, 4);
+        assert_eq!(output[..AES_IV_SIZE], metadata.iv);
+        assert_eq!(output[output.len() - HMAC_LEN..], metadata.hmac);
+
+        let key = MessageBackupKey::derive_with_forward_secrecy_token(
+            &backup_key,
+            &backup_id,
+            &metadata.forward_secrecy_token,
+        );
+        let reader = block_on(BackupReader::new_encrypted_compressed(
+            &key,
+            CursorFactory::new(&output),
+            Purpose::RemoteBackup,
+        ))
+        .expect("valid HMAC");
+        block_on(reader.validate_all())
+            .result
+            .expect("valid backup");
+
+        // Without the forward secrecy token, the backup can't be read.
+        let key_without_token = MessageBackupKey::derive(&backup_key, &backup_id);
+        assert!(matches!(
+            block_on(BackupReader::new_encrypted_compressed(
+                &key_without_token,
+                CursorFactory::new(&output),
+                Purpose::RemoteBackup,
+            )),
+            Err(crate::frame::ValidationError::InvalidHmac(_))
+        ));
+    }
+
+    #[test]
+    fn requires_account_data_first() {
+        let mut writer = new_writer();
+        assert_matches!(
+            block_on(writer.add_frame(&serialize_frame(proto::Recipient::test_data()))),
+            Err(WriteError::AccountDataNotFirst)
+        );
+
+        block_on(writer.add_frame(&serialize_frame(proto::AccountData::test_data())))
+            .expect("valid frame");
+        assert_matches!(
+            block_on(writer.add_frame(&serialize_frame(proto::AccountData::test_data()))),
+            Err(WriteError::AccountDataNotFirst)
+        );
+    }
+
+    #[test]
+    fn rejects_dangling_reference_and_continues() {
+        let mut writer = new_writer();
+        block_on(writer.add_frame(&serialize_frame(proto::AccountData::test_data())))
+            .expect("valid frame");
+
+        // The chat refers to a recipient that hasn't been written yet.
+        assert_matches!(
+            block_on(writer.add_frame(&serialize_frame(proto::Chat::test_data()))),
+            Err(WriteError::InvalidFrame { index: 2, .. })
+        );
+
+        block_on(writer.add_frame(&serialize_frame(proto::Recipient::test_data())))
+            .expect("valid frame");
+        block_on(writer.add_frame(&serialize_frame(proto::Chat::test_data())))
+            .expect("valid frame");
+
+        let plaintext = block_on(writer.finish()).expect("complete");
+        let reader = BackupReader::new_unencrypted(plaintext.as_slice(), Purpose::RemoteBackup);
+        block_on(reader.validate_all())
+            .result
+            .expect("valid backup");
+    }
+
+    #[test]
+    fn empty_backup_is_incomplete() {
+        assert_matches!(
+            block_on(new_writer().finish()),
+            Err(WriteError::Incomplete(CompletionError::MissingAccountData))
+        );
+    }
+
+    #[test_case(0 => 541)]
+    #[test_case(541 => 541)]
+    #[test_case(542 => 568)]
+    #[test_case(568 => 568)]
+    #[test_case(569 => 596)]
+    fn padded_len_buckets(len: u64) -> u64 {
+        padded_len(len)
+    }
+
+    #[test]
+    fn padded_len_overhead_is_bounded() {
+        for len in (0..30).map(|i| 1u64 << i) {
+            let padded = padded_len(len);
+            assert!(padded >= len, "{len}");
+            assert!(padded <= (len * 21 / 20).max(541) + 1, "{len} -> {padded}");
+        }
+    }
+}
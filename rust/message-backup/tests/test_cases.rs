@@ -3,7 +3,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::cell::RefCell;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use assert_cmd::Command;
 use assert_matches::assert_matches;
@@ -11,10 +14,15 @@ use dir_test::{dir_test, Fixture};
 use futures::io::Cursor;
 use futures::AsyncRead;
 use libsignal_core::Aci;
+use libsignal_message_backup::backup::compare::{compare_backups, CompareError, DifferenceArea};
 use libsignal_message_backup::backup::Purpose;
 use libsignal_message_backup::frame::{FileReaderFactory, VerifyHmac};
 use libsignal_message_backup::key::{BackupKey, MessageBackupKey};
-use libsignal_message_backup::{BackupReader, ReadResult};
+use libsignal_message_backup::parse::ParseError;
+use libsignal_message_backup::restore::{
+    FrameCategory, FrameSelection, RestoreCursor, RestoreOptions,
+};
+use libsignal_message_backup::{BackupReader, Error, ReadResult, RestoreResult};
 
 const BACKUP_PURPOSE: Purpose = Purpose::RemoteBackup;
 
@@ -75,6 +83,184 @@ fn serialized_account_settings_is_valid() {
     pretty_assertions::assert_str_eq!(canonical_repr, expected_canonical_str)
 }
 
+#[test]
+fn compares_backup_files() {
+    let binproto = include_bytes!("res/canonical-backup.binproto");
+    let reader = || BackupReader::new_unencrypted(Cursor::new(binproto), BACKUP_PURPOSE);
+    let differences =
+        futures::executor::block_on(compare_backups(reader(), reader())).expect("valid backups");
+    assert_eq!(differences, vec![]);
+
+    let from_json = |contents: &str| {
+        let json_contents = json5::from_str(contents).expect("invalid JSON");
+        let json_array =
+            assert_matches!(json_contents, serde_json::Value::Array(contents) => contents);
+        libsignal_message_backup::backup::convert_from_json(json_array).expect("failed to convert")
+    };
+    let account_only = from_json(include_str!("res/test-cases/valid/account-data.jsonproto"));
+    let with_contact = from_json(include_str!(
+        "res/test-cases/valid/registered-blocked-contact.jsonproto"
+    ));
+    let differences = futures::executor::block_on(compare_backups(
+        BackupReader::new_unencrypted(Cursor::new(&account_only), BACKUP_PURPOSE),
+        BackupReader::new_unencrypted(Cursor::new(&with_contact), BACKUP_PURPOSE),
+    ))
+    .expect("valid backups");
+    assert!(
+        differences
+            .iter()
+            .any(|d| d.area() == DifferenceArea::Recipients),
+        "{differences:#?}"
+    );
+
+    assert_matches!(
+        futures::executor::block_on(compare_backups(
+            reader(),
+            BackupReader::new_unencrypted(Cursor::new(&[][..]), BACKUP_PURPOSE),
+        )),
+        Err(CompareError::Right(Error::NoFrames))
+    );
+}
+
+#[test]
+fn reports_progress_through_final_frame() {
+    let binproto = include_bytes!("res/canonical-backup.binproto");
+
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    // The callback must not keep the reader from being sent to another thread.
+    let reader = assert_send(
+        BackupReader::new_unencrypted(Cursor::new(binproto), BACKUP_PURPOSE)
+            .with_progress_callback(NonZeroUsize::new(2).unwrap(), {
+                let reports = reports.clone();
+                move |progress| reports.lock().expect("not poisoned").push(progress)
+            }),
+    );
+    futures::executor::block_on(reader.validate_all())
+        .result
+        .expect("valid backup");
+
+    let reports = std::mem::take(&mut *reports.lock().expect("not poisoned"));
+    assert!(reports.len() > 1, "{reports:?}");
+    assert!(
+        reports
+            .windows(2)
+            .all(|w| w[0].frames_validated < w[1].frames_validated
+                && w[0].bytes_processed < w[1].bytes_processed),
+        "{reports:?}"
+    );
+    let last = reports.last().unwrap();
+    assert_eq!(last.bytes_processed, binproto.len() as u64);
+}
+
+#[test]
+fn rejects_frames_over_max_length() {
+    let binproto = include_bytes!("res/canonical-backup.binproto");
+
+    let reader = BackupReader::new_unencrypted(Cursor::new(binproto), BACKUP_PURPOSE)
+        .with_max_frame_length(1);
+    assert_matches!(
+        futures::executor::block_on(reader.validate_all()).result,
+        Err(Error::Parse(ParseError::FrameTooLarge { max: 1, .. }))
+    );
+}
+
+#[test]
+fn restores_selected_categories() {
+    let binproto = include_bytes!("res/canonical-backup.binproto");
+    let frame_count = futures::executor::block_on(
+        libsignal_message_backup::backup::convert_to_json(Cursor::new(binproto)),
+    )
+    .expect("valid binproto")
+    .len();
+
+    let reader = BackupReader::new_unencrypted(Cursor::new(binproto), BACKUP_PURPOSE);
+    let RestoreResult { read, cursor } = futures::executor::block_on(
+        reader.restore(RestoreOptions {
+            selection: [FrameCategory::AccountData, FrameCategory::Recipient]
+                .into_iter()
+                .collect(),
+            resume_from: None,
+        }),
+    );
+    read.result.expect("valid backup");
+    assert_eq!(
+        cursor,
+        RestoreCursor {
+            frame_index: frame_count
+        }
+    );
+}
+
+/// Restores `binproto`, returning the `Debug` output of each frame that was
+/// processed along with the final cursor.
+fn restore_recording_frames(
+    binproto: &[u8],
+    options: RestoreOptions,
+) -> (Vec<String>, RestoreCursor) {
+    thread_local! {
+        static PROCESSED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    let mut reader = BackupReader::new_unencrypted(Cursor::new(binproto), BACKUP_PURPOSE);
+    reader.visitor = |frame| PROCESSED.with_borrow_mut(|frames| frames.push(format!("{frame:?}")));
+    let RestoreResult { read, cursor } = futures::executor::block_on(reader.restore(options));
+    read.result.expect("valid backup");
+    (PROCESSED.take(), cursor)
+}
+
+#[test]
+fn resuming_skips_frames_before_cursor() {
+    let json_contents = json5::from_str(include_str!(
+        "res/test-cases/valid/simple-chat-update-message.jsonproto"
+    ))
+    .expect("invalid JSON");
+    let json_array = assert_matches!(json_contents, serde_json::Value::Array(contents) => contents);
+
+    // The BackupInfo, then the account data, 5 recipients, 2 chats, and 20
+    // chat items.
+    let items: Vec<_> = json_array[1..]
+        .iter()
+        .map(|frame| {
+            let frame = frame.as_object().expect("frame is an object");
+            frame.keys().next().expect("frame has an item").clone()
+        })
+        .collect();
+    let expected_items = [
+        ["account"].as_slice(),
+        &["recipient"; 5],
+        &["chat"; 2],
+        &["chatItem"; 20],
+    ]
+    .concat();
+    assert_eq!(items, expected_items);
+
+    let binproto =
+        libsignal_message_backup::backup::convert_from_json(json_array).expect("valid backup");
+    let (all_frames, _) = restore_recording_frames(&binproto, RestoreOptions::default());
+    assert_eq!(all_frames.len(), 29);
+
+    let (resumed_frames, cursor) = restore_recording_frames(
+        &binproto,
+        RestoreOptions {
+            selection: FrameSelection::ALL,
+            resume_from: Some(RestoreCursor { frame_index: 20 }),
+        },
+    );
+    assert_eq!(cursor, RestoreCursor { frame_index: 29 });
+
+    // Everything chat items can refer to is read again, but the 11 chat items
+    // before the cursor aren't delivered a second time.
+    assert_eq!(resumed_frames.len(), 18);
+    assert_eq!(
+        resumed_frames,
+        [&all_frames[..9], &all_frames[20..]].concat()
+    );
+}
+
 const ENCRYPTED_SOURCE_SUFFIX: &str = ".source.jsonproto";
 #[dir_test(
         dir: "$CARGO_MANIFEST_DIR/tests/res/test-cases",
@@ -4,8 +4,23 @@
 //
 
 fn main() {
-    let protos = ["src/proto/chat_websocket.proto", "src/proto/cds2.proto"];
-    prost_build::compile_protos(&protos, &["src"]).expect("Protobufs in src are valid");
+    let protos = [
+        "src/proto/chat_websocket.proto",
+        "src/proto/cds2.proto",
+        "src/proto/device_sync.proto",
+        "src/proto/groups.proto",
+        "src/proto/provisioning.proto",
+        "src/proto/receipts.proto",
+        "src/proto/storage.proto",
+        "src/proto/svr2.proto",
+    ];
+    prost_build::Config::new()
+        .type_attribute(
+            "org.signal.cdsi.ClientResponse",
+            "#[cfg_attr(feature = \"arbitrary\", derive(arbitrary::Arbitrary))]",
+        )
+        .compile_protos(&protos, &["src"])
+        .expect("Protobufs in src are valid");
     for proto in &protos {
         println!("cargo:rerun-if-changed={}", proto);
     }
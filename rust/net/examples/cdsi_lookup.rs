@@ -7,33 +7,13 @@
 use std::time::Duration;
 
 use libsignal_net::auth::Auth;
-use libsignal_net::cdsi::{CdsiConnection, LookupError, LookupRequest, LookupResponse};
-use libsignal_net::enclave::{Cdsi, EnclaveEndpointConnection};
-use libsignal_net::infra::connection_manager::ConnectionManager;
+use libsignal_net::cdsi::{cdsi_lookup, LookupError, LookupRequest};
+use libsignal_net::enclave::EnclaveEndpointConnection;
 use libsignal_net::infra::dns::DnsResolver;
 use libsignal_net::infra::tcp_ssl::DirectConnector as TcpSslTransportConnector;
-use libsignal_net::infra::TransportConnector;
 use libsignal_net::utils::ObservableEvent;
 use tokio::io::AsyncBufReadExt as _;
 
-async fn cdsi_lookup(
-    auth: Auth,
-    endpoint: &EnclaveEndpointConnection<Cdsi, impl ConnectionManager>,
-    transport_connector: impl TransportConnector,
-    request: LookupRequest,
-    timeout: Duration,
-) -> Result<LookupResponse, LookupError> {
-    let connected = CdsiConnection::connect(endpoint, transport_connector, auth).await?;
-    let (_token, remaining_response) = libsignal_net::utils::timeout(
-        timeout,
-        LookupError::ConnectionTimedOut,
-        connected.send_request(request),
-    )
-    .await?;
-
-    remaining_response.collect().await
-}
-
 #[tokio::main]
 async fn main() {
     env_logger::init();
@@ -59,12 +39,18 @@ async fn main() {
         EnclaveEndpointConnection::new(&env.cdsi, Duration::from_secs(10), &network_change_event);
     let transport_connection =
         TcpSslTransportConnector::new(DnsResolver::new(&network_change_event));
-    let cdsi_response = cdsi_lookup(
-        Auth { username, password },
-        &endpoint_connection,
-        transport_connection,
-        request,
+    let (_token, cdsi_response) = libsignal_net::utils::timeout(
         Duration::from_secs(10),
+        LookupError::ConnectionTimedOut,
+        cdsi_lookup(
+            &endpoint_connection,
+            transport_connection,
+            Auth {
+                username,
+                password: password.into(),
+            },
+            request,
+        ),
     )
     .await
     .unwrap();
@@ -15,7 +15,7 @@ use libsignal_net::infra::dns::dns_transport_doh::DohTransport;
 use libsignal_net::infra::dns::dns_transport_udp::UdpTransport;
 use libsignal_net::infra::host::Host;
 use libsignal_net::infra::{
-    ConnectionParams, HttpRequestDecoratorSeq, RouteType, TransportConnectionParams,
+    ConnectionParams, HttpRequestDecoratorSeq, RouteType, TrafficClass, TransportConnectionParams,
 };
 use libsignal_net::utils::ObservableEvent;
 use nonzero_ext::nonzero;
@@ -66,6 +66,8 @@ async fn main() {
                     tcp_host: Host::Ip(ip_addr!("1.1.1.1")),
                     port: nonzero!(443u16),
                     certs: RootCertificates::Native,
+                    proxy: None,
+                    traffic_class: TrafficClass::default(),
                 },
                 http_host: host,
             };
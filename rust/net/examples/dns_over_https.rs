@@ -14,7 +14,7 @@ use libsignal_net::infra::dns::dns_lookup::DnsLookupRequest;
 use libsignal_net::infra::dns::dns_transport_doh::DohTransport;
 use libsignal_net::infra::host::Host;
 use libsignal_net::infra::{
-    ConnectionParams, HttpRequestDecoratorSeq, RouteType, TransportConnectionParams,
+    ConnectionParams, HttpRequestDecoratorSeq, RouteType, TrafficClass, TransportConnectionParams,
 };
 
 #[derive(Parser, Debug)]
@@ -54,6 +54,8 @@ async fn main() {
             tcp_host: args.ns_address,
             port: NonZeroU16::try_from(args.ns_port).expect("valid port value"),
             certs: RootCertificates::Native,
+            proxy: None,
+            traffic_class: TrafficClass::default(),
         },
         http_host: host,
         connection_confirmation_header: None,
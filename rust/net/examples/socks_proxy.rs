@@ -15,7 +15,9 @@ use libsignal_net::infra::certs::RootCertificates;
 use libsignal_net::infra::dns::DnsResolver;
 use libsignal_net::infra::host::Host;
 use libsignal_net::infra::tcp_ssl::proxy::socks::{Protocol, SocksConnector};
-use libsignal_net::infra::{Alpn, StreamAndInfo, TransportConnectionParams, TransportConnector};
+use libsignal_net::infra::{
+    Alpn, StreamAndInfo, TrafficClass, TransportConnectionParams, TransportConnector,
+};
 use url::Url;
 
 #[derive(Clone, Debug, Parser)]
@@ -92,6 +94,8 @@ async fn main() {
         tcp_host: host,
         port,
         certs: RootCertificates::Native,
+        proxy: None,
+        traffic_class: TrafficClass::default(),
     };
     let StreamAndInfo(mut connection, info) = connector
         .connect(&connection_params, Alpn::Http1_1)
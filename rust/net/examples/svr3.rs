@@ -15,6 +15,7 @@ use base64::prelude::{Engine, BASE64_STANDARD};
 use clap::Parser;
 use colored::Colorize as _;
 use libsignal_net::auth::Auth;
+use libsignal_net::clock::SystemClock;
 use libsignal_net::enclave::PpssSetup;
 use libsignal_net::env::Svr3Env;
 use libsignal_net::infra::tcp_ssl::DirectConnector;
@@ -74,7 +75,7 @@ async fn main() {
 
     let client = {
         let env = libsignal_net::env::STAGING.svr3;
-        let auth = Auth::from_uid_and_secret(uid, enclave_secret);
+        let auth = Auth::from_uid_and_secret(uid, enclave_secret, &SystemClock);
         Svr3Client { env, auth }
     };
 
@@ -18,6 +18,7 @@ use base64::prelude::{Engine, BASE64_STANDARD};
 use clap::Parser;
 use hex_literal::hex;
 use libsignal_net::auth::Auth;
+use libsignal_net::clock::SystemClock;
 use libsignal_net::enclave::{
     self, EnclaveEndpoint, EnclaveEndpointConnection, EndpointParams, MrEnclave, PpssSetup, Sgx,
     Svr3Flavor,
@@ -156,7 +157,7 @@ async fn main() {
             bytes
         };
 
-        let make_auth = |uid: [u8; 16]| Auth::from_uid_and_secret(uid, auth_secret);
+        let make_auth = |uid: [u8; 16]| Auth::from_uid_and_secret(uid, auth_secret, &SystemClock);
 
         Client {
             env: two_sgx_env,
@@ -9,6 +9,7 @@ use std::time::Duration;
 use assert_matches::assert_matches;
 use async_trait::async_trait;
 use libsignal_net::auth::Auth;
+use libsignal_net::clock::SystemClock;
 use libsignal_net::enclave::PpssSetup;
 use libsignal_net::env::Svr3Env;
 use libsignal_net::infra::ws::DefaultStream;
@@ -321,7 +322,7 @@ struct Client<'a> {
 
 impl<'a> Client<'a> {
     fn new(uid: Uid, storage: &'a Svr3Storage) -> Self {
-        let auth = Auth::from_uid_and_secret(uid, storage.enclave_secret);
+        let auth = Auth::from_uid_and_secret(uid, storage.enclave_secret, &SystemClock);
         Self {
             auth,
             env: &storage.env,
@@ -0,0 +1,432 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Transfers of attachments to and from the CDN.
+//!
+//! Both directions can be resumed after an interruption. Uploads use the tus
+//! protocol (see [`TusUpload`]), and downloads are made in chunks using HTTP
+//! range requests, so a download can continue from the last byte received.
+//! Progress is reported after every chunk, and transfers can be capped at a
+//! maximum speed so that they don't crowd out other traffic.
+
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::str::FromStr;
+use std::time::Duration;
+
+use bytes::Bytes;
+use hmac::Mac;
+use http::response::Parts;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use libsignal_protocol::incremental_mac::Validating;
+use nonzero_ext::nonzero;
+use tokio::time::Instant;
+
+use crate::cdn::{check_status, HttpEndpoint, RequestError, TusUpload};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::TransportConnector;
+
+#[derive(Clone, Debug)]
+pub struct TransferOptions {
+    /// The most bytes to send or receive in a single request.
+    pub chunk_size: NonZeroUsize,
+    /// The largest response accepted when downloading.
+    ///
+    /// A server that ignores range requests sends the whole object in one
+    /// response, so this bounds the size of objects that can be downloaded from
+    /// such a server. Responses of up to `chunk_size` are always accepted.
+    pub max_response_size: NonZeroUsize,
+    /// If set, transfers are slowed down to at most this many bytes per second.
+    pub max_bytes_per_second: Option<NonZeroU64>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TransferProgress {
+    /// The number of bytes transferred so far, including any transferred
+    /// before the transfer was resumed.
+    pub transferred: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum TransferError {
+    /// {0}
+    Request(#[from] RequestError),
+    /// server response did not match the requested range
+    InvalidRange,
+    /// contents did not match the length of the upload
+    LengthMismatch,
+    /// downloaded contents failed integrity verification
+    IntegrityCheckFailed,
+}
+
+/// Receives downloaded contents in order.
+///
+/// The caller keeps ownership of a sink across interrupted downloads, so any
+/// state it holds (a partially-decrypted block, a running digest) carries over
+/// when the download is resumed.
+pub trait DownloadSink {
+    fn write(&mut self, chunk: &[u8]) -> Result<(), TransferError>;
+
+    /// Called once the entire object has been received.
+    fn finish(&mut self) -> Result<(), TransferError> {
+        Ok(())
+    }
+}
+
+impl DownloadSink for Vec<u8> {
+    fn write(&mut self, chunk: &[u8]) -> Result<(), TransferError> {
+        self.extend_from_slice(chunk);
+        Ok(())
+    }
+}
+
+/// [`DownloadSink`] that checks contents against incremental MACs before
+/// passing them on.
+///
+/// The download fails as soon as a MAC doesn't match. Contents of the chunk
+/// that failed are not passed on, but earlier chunks already have been, so the
+/// inner sink should be discarded on failure.
+pub struct VerifyingSink<S, M: Mac + Clone> {
+    inner: S,
+    validating: Option<Validating<M>>,
+}
+
+impl<S: DownloadSink, M: Mac + Clone> VerifyingSink<S, M> {
+    pub fn new(inner: S, validating: Validating<M>) -> Self {
+        Self {
+            inner,
+            validating: Some(validating),
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: DownloadSink, M: Mac + Clone> DownloadSink for VerifyingSink<S, M> {
+    fn write(&mut self, chunk: &[u8]) -> Result<(), TransferError> {
+        self.validating
+            .as_mut()
+            .ok_or(TransferError::IntegrityCheckFailed)?
+            .update(chunk)
+            .map_err(|_| TransferError::IntegrityCheckFailed)?;
+        self.inner.write(chunk)
+    }
+
+    fn finish(&mut self) -> Result<(), TransferError> {
+        self.validating
+            .take()
+            .ok_or(TransferError::IntegrityCheckFailed)?
+            .finalize()
+            .map_err(|_| TransferError::IntegrityCheckFailed)?;
+        self.inner.finish()
+    }
+}
+
+/// Client for downloading attachments from a single CDN.
+pub struct AttachmentTransferClient<C, T> {
+    endpoint: HttpEndpoint<C, T>,
+    options: TransferOptions,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> AttachmentTransferClient<C, T> {
+    /// Creates a client; `connection_manager` should connect to the CDN host,
+    /// using [`download_connection_params`](crate::cdn::download_connection_params).
+    pub fn new(connection_manager: C, transport_connector: T, options: TransferOptions) -> Self {
+        Self {
+            endpoint: HttpEndpoint::new(
+                connection_manager,
+                transport_connector,
+                options.max_response_size.max(options.chunk_size).get(),
+            ),
+            options,
+        }
+    }
+
+    /// Downloads the object at `path`, starting from byte `offset`.
+    ///
+    /// Returns the total length of the object. If the download is interrupted,
+    /// the last `transferred` count passed to `on_progress` is the offset to
+    /// resume from.
+    pub async fn download(
+        &self,
+        path: PathAndQuery,
+        mut offset: u64,
+        sink: &mut impl DownloadSink,
+        mut on_progress: impl FnMut(TransferProgress),
+    ) -> Result<u64, TransferError> {
+        let chunk_size = u64::try_from(self.options.chunk_size.get()).expect("usize fits in u64");
+        let mut throttle = Throttle::new(self.options.max_bytes_per_second);
+
+        loop {
+            let range = format!("bytes={}-{}", offset, offset + chunk_size - 1);
+            let headers = HeaderMap::from_iter([(
+                http::header::RANGE,
+                HeaderValue::try_from(range).expect("valid header value"),
+            )]);
+            let (parts, body) = self
+                .endpoint
+                .send_unchecked(Method::GET, path.clone(), headers, Bytes::new())
+                .await?;
+            if parts.status != StatusCode::RANGE_NOT_SATISFIABLE {
+                check_status(&parts)?;
+            }
+            let received = u64::try_from(body.len()).expect("usize fits in u64");
+            let total = check_received_range(&parts, offset, received)?;
+
+            sink.write(&body)?;
+            offset += received;
+            on_progress(TransferProgress {
+                transferred: offset,
+                total,
+            });
+
+            if offset == total {
+                sink.finish()?;
+                return Ok(total);
+            }
+            throttle.record(received).await;
+        }
+    }
+}
+
+/// Uploads the rest of `contents`, starting from the offset the server has
+/// acknowledged.
+///
+/// `contents` is the entire object being uploaded.
+pub async fn upload<C: ConnectionManager, T: TransportConnector>(
+    upload: &mut TusUpload<C, T>,
+    contents: &Bytes,
+    options: &TransferOptions,
+    mut on_progress: impl FnMut(TransferProgress),
+) -> Result<(), TransferError> {
+    if u64::try_from(contents.len()).ok() != Some(upload.length()) {
+        return Err(TransferError::LengthMismatch);
+    }
+    let mut throttle = Throttle::new(options.max_bytes_per_second);
+
+    while !upload.is_complete() {
+        let start = usize::try_from(upload.offset()).expect("offset is within contents");
+        let end = contents
+            .len()
+            .min(start.saturating_add(options.chunk_size.get()));
+        let previous_offset = upload.offset();
+        // The server must make progress with every chunk, so this can't loop
+        // forever.
+        let offset = upload.upload_chunk(contents.slice(start..end)).await?;
+        on_progress(TransferProgress {
+            transferred: offset,
+            total: upload.length(),
+        });
+        throttle.record(offset - previous_offset).await;
+    }
+    Ok(())
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: nonzero!(1_048_576_usize),
+            max_response_size: nonzero!(100 * 1_048_576_usize),
+            max_bytes_per_second: None,
+        }
+    }
+}
+
+/// Checks that a download response holds the `received` bytes starting at
+/// `offset`, and returns the total length of the object.
+fn check_received_range(parts: &Parts, offset: u64, received: u64) -> Result<u64, TransferError> {
+    let content_range = parts.headers.get(http::header::CONTENT_RANGE);
+    match parts.status {
+        StatusCode::PARTIAL_CONTENT => {
+            let (start, end, total) = content_range
+                .and_then(parse_content_range)
+                .ok_or(TransferError::InvalidRange)?;
+            if start != offset || end - start + 1 != received {
+                return Err(TransferError::InvalidRange);
+            }
+            Ok(total)
+        }
+        // The server is allowed to ignore the range and send everything.
+        StatusCode::OK if offset == 0 => Ok(received),
+        // Nothing is left past `offset`, as when downloading an empty object.
+        StatusCode::RANGE_NOT_SATISFIABLE
+            if content_range.and_then(parse_unsatisfied_range) == Some(offset) =>
+        {
+            Ok(offset)
+        }
+        _ => Err(TransferError::InvalidRange),
+    }
+}
+
+/// Parses a `Content-Range` header of the form `bytes */total`, sent with a
+/// range that can't be satisfied.
+fn parse_unsatisfied_range(value: &HeaderValue) -> Option<u64> {
+    u64::from_str(value.to_str().ok()?.strip_prefix("bytes */")?).ok()
+}
+
+/// Parses a `Content-Range` header of the form `bytes start-end/total`.
+fn parse_content_range(value: &HeaderValue) -> Option<(u64, u64, u64)> {
+    let (range, total) = value
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let start = u64::from_str(start).ok()?;
+    let end = u64::from_str(end).ok()?;
+    let total = u64::from_str(total).ok()?;
+    (start <= end && end < total).then_some((start, end, total))
+}
+
+/// Paces a transfer to stay under a maximum speed.
+struct Throttle {
+    max_bytes_per_second: Option<NonZeroU64>,
+    start: Instant,
+    transferred: u64,
+}
+
+impl Throttle {
+    fn new(max_bytes_per_second: Option<NonZeroU64>) -> Self {
+        Self {
+            max_bytes_per_second,
+            start: Instant::now(),
+            transferred: 0,
+        }
+    }
+
+    /// Waits until `bytes` more can be considered transferred without
+    /// exceeding the limit.
+    async fn record(&mut self, bytes: u64) {
+        self.transferred += bytes;
+        let Some(max_bytes_per_second) = self.max_bytes_per_second else {
+            return;
+        };
+        let elapsed =
+            Duration::from_secs_f64(self.transferred as f64 / max_bytes_per_second.get() as f64);
+        tokio::time::sleep_until(self.start + elapsed).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use hmac::Hmac;
+    use libsignal_protocol::incremental_mac::Incremental;
+    use sha2::Sha256;
+    use test_case::test_case;
+
+    use super::*;
+
+    const CHUNK_SIZE: usize = 32;
+
+    #[test_case("bytes 0-99/1000" => Some((0, 99, 1000)))]
+    #[test_case("bytes 900-999/1000" => Some((900, 999, 1000)))]
+    #[test_case("bytes 0-999/1000" => Some((0, 999, 1000)))]
+    #[test_case("bytes 0-1000/1000" => None; "end past total")]
+    #[test_case("bytes 10-5/1000" => None; "end before start")]
+    #[test_case("bytes 0-99/*" => None; "unknown total")]
+    #[test_case("items 0-99/1000" => None; "wrong unit")]
+    fn content_range(value: &'static str) -> Option<(u64, u64, u64)> {
+        parse_content_range(&HeaderValue::from_static(value))
+    }
+
+    fn response_parts(status: StatusCode, content_range: Option<&'static str>) -> Parts {
+        let mut builder = http::Response::builder().status(status);
+        if let Some(content_range) = content_range {
+            builder = builder.header(http::header::CONTENT_RANGE, content_range);
+        }
+        builder.body(()).expect("valid response").into_parts().0
+    }
+
+    #[test_case(StatusCode::PARTIAL_CONTENT, Some("bytes 10-19/100"), 10, 10 => matches Ok(100))]
+    #[test_case(StatusCode::PARTIAL_CONTENT, Some("bytes 10-19/100"), 0, 10 => matches Err(TransferError::InvalidRange); "wrong start")]
+    #[test_case(StatusCode::PARTIAL_CONTENT, Some("bytes 10-19/100"), 10, 5 => matches Err(TransferError::InvalidRange); "short body")]
+    #[test_case(StatusCode::PARTIAL_CONTENT, None, 10, 10 => matches Err(TransferError::InvalidRange); "missing range")]
+    #[test_case(StatusCode::OK, None, 0, 100 => matches Ok(100); "whole object")]
+    #[test_case(StatusCode::OK, None, 10, 90 => matches Err(TransferError::InvalidRange); "whole object when resuming")]
+    #[test_case(StatusCode::OK, None, 0, 0 => matches Ok(0); "empty object")]
+    #[test_case(StatusCode::RANGE_NOT_SATISFIABLE, Some("bytes */0"), 0, 0 => matches Ok(0); "empty object without range")]
+    #[test_case(StatusCode::RANGE_NOT_SATISFIABLE, Some("bytes */100"), 100, 0 => matches Ok(100); "already complete")]
+    #[test_case(StatusCode::RANGE_NOT_SATISFIABLE, Some("bytes */100"), 0, 0 => matches Err(TransferError::InvalidRange); "unsatisfiable")]
+    fn received_range(
+        status: StatusCode,
+        content_range: Option<&'static str>,
+        offset: u64,
+        received: u64,
+    ) -> Result<u64, TransferError> {
+        check_received_range(&response_parts(status, content_range), offset, received)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_limits_speed() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(Some(nonzero!(100_u64)));
+        throttle.record(50).await;
+        throttle.record(150).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_throttle_does_not_wait() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(None);
+        throttle.record(u64::MAX / 2).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    fn new_incremental() -> Incremental<Hmac<Sha256>> {
+        Incremental::new(
+            Hmac::<Sha256>::new_from_slice(b"attachment mac key").expect("any size key"),
+            CHUNK_SIZE,
+        )
+    }
+
+    fn verifying_sink(contents: &[u8]) -> VerifyingSink<Vec<u8>, Hmac<Sha256>> {
+        let mut incremental = new_incremental();
+        let mut macs: Vec<_> = incremental.update(contents).collect();
+        macs.push(incremental.finalize());
+        VerifyingSink::new(Vec::new(), new_incremental().validating(macs))
+    }
+
+    #[test]
+    fn verifying_sink_accepts_valid_contents() {
+        let contents: Vec<u8> = (0..100).collect();
+        let mut sink = verifying_sink(&contents);
+
+        for chunk in contents.chunks(7) {
+            sink.write(chunk).expect("valid");
+        }
+        sink.finish().expect("valid");
+        assert_eq!(sink.into_inner(), contents);
+    }
+
+    #[test]
+    fn verifying_sink_rejects_modified_contents() {
+        let contents: Vec<u8> = (0..100).collect();
+        let mut sink = verifying_sink(&contents);
+
+        let mut modified = contents.clone();
+        modified[40] ^= 1;
+        sink.write(&modified[..CHUNK_SIZE])
+            .expect("first chunk is intact");
+        assert_matches!(
+            sink.write(&modified[CHUNK_SIZE..]),
+            Err(TransferError::IntegrityCheckFailed)
+        );
+    }
+
+    #[test]
+    fn verifying_sink_rejects_truncated_contents() {
+        let contents: Vec<u8> = (0..100).collect();
+        let mut sink = verifying_sink(&contents);
+
+        sink.write(&contents[..90])
+            .expect("whole chunks are intact");
+        assert_matches!(sink.finish(), Err(TransferError::IntegrityCheckFailed));
+    }
+}
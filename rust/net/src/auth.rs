@@ -2,13 +2,17 @@
 // Copyright 2024 Signal Messenger, LLC.
 // SPDX-License-Identifier: AGPL-3.0-only
 //
+use std::fmt;
 use std::time::SystemTime;
 
 use hmac::{Hmac, Mac};
+use serde::{Deserialize, Deserializer};
 use sha2::Sha256;
+use subtle::ConstantTimeEq as _;
 
+use crate::clock::Clock;
 use crate::infra::HttpRequestDecorator;
-use crate::utils::basic_authorization;
+use crate::utils::{basic_authorization, Redacted};
 
 pub trait HttpBasicAuth {
     fn username(&self) -> &str;
@@ -24,6 +28,52 @@ impl<T: HttpBasicAuth> From<T> for HttpRequestDecorator {
     }
 }
 
+/// A secret value, such as a password or access key.
+///
+/// Equality is checked in constant time, and the value is left out of
+/// [`Debug`](fmt::Debug) output unless the `unredacted-logs` feature is
+/// enabled. Use [`SecretBytes::expose`] to get at the contents.
+#[derive(Clone, Default)]
+pub struct SecretBytes<T>(T);
+
+impl<T> SecretBytes<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> From<T> for SecretBytes<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: AsRef<[u8]>> PartialEq for SecretBytes<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_ref().ct_eq(other.0.as_ref()).into()
+    }
+}
+
+impl<T: AsRef<[u8]>> Eq for SecretBytes<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for SecretBytes<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SecretBytes")
+            .field(&Redacted(&self.0))
+            .finish()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SecretBytes<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
 /// username and password as returned by the chat server's /auth endpoints.
 /// - username is a "hex(uid)"
 /// - password is a "timestamp:hex(otp(uid, timestamp, secret))"
@@ -31,14 +81,17 @@ impl<T: HttpBasicAuth> From<T> for HttpRequestDecorator {
 #[cfg_attr(feature = "test-support", derive(Default))]
 pub struct Auth {
     pub username: String,
-    pub password: String,
+    pub password: SecretBytes<String>,
 }
 
 impl Auth {
-    pub fn from_uid_and_secret(uid: [u8; 16], secret: [u8; 32]) -> Self {
+    pub fn from_uid_and_secret(uid: [u8; 16], secret: [u8; 32], clock: &dyn Clock) -> Self {
         let username = hex::encode(uid);
-        let password = Self::otp(&username, &secret, SystemTime::now());
-        Self { username, password }
+        let password = Self::otp(&username, &secret, clock.now());
+        Self {
+            username,
+            password: password.into(),
+        }
     }
 
     const OTP_LEN: usize = 20;
@@ -60,12 +113,58 @@ impl Auth {
     }
 }
 
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auth")
+            .field("username", &Redacted(&self.username))
+            .field("password", &self.password)
+            .finish()
+    }
+}
+
 impl HttpBasicAuth for Auth {
     fn username(&self) -> &str {
         &self.username
     }
 
     fn password(&self) -> &str {
-        &self.password
+        self.password.expose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(not(feature = "unredacted-logs"))]
+    fn secret_bytes_hides_contents() {
+        let secret = SecretBytes::new("hunter2".to_owned());
+        assert_eq!(format!("{secret:?}"), "SecretBytes(<redacted>)");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    #[cfg(not(feature = "unredacted-logs"))]
+    fn auth_debug_is_redacted() {
+        let auth = Auth {
+            username: "0123456789abcdef".to_owned(),
+            password: "1700000000:abcdef".to_owned().into(),
+        };
+        assert_eq!(
+            format!("{auth:?}"),
+            r#"Auth { username: <redacted>, password: SecretBytes(<redacted>) }"#
+        );
+    }
+
+    #[test]
+    fn secret_bytes_equality() {
+        let key = SecretBytes::new([1u8; 16]);
+        assert_eq!(key, SecretBytes::new([1u8; 16]));
+        assert_ne!(key, SecretBytes::new([2u8; 16]));
+        assert_ne!(
+            SecretBytes::new(b"short".to_vec()),
+            SecretBytes::new(b"longer".to_vec())
+        );
     }
 }
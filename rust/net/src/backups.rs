@@ -0,0 +1,246 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for the backup storage endpoints on the chat server.
+//!
+//! Requests are authenticated with a zero-knowledge presentation of a backup
+//! auth credential, signed with the backup's private key, so the server can
+//! check access to a backup without learning which account it belongs to.
+//! Backup files and media are uploaded directly to the CDN described by an
+//! [`UploadForm`]; see [`crate::cdn::TusUpload`].
+
+use std::str::FromStr;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bytes::Bytes;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+
+use crate::cdn::{HttpEndpoint, RequestError, UploadForm, DEFAULT_MAX_RESPONSE_SIZE};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::TransportConnector;
+
+const UPLOAD_FORM_PATH: &str = "/v1/archives/upload/form";
+const MEDIA_UPLOAD_FORM_PATH: &str = "/v1/archives/media/upload/form";
+const MEDIA_PATH: &str = "/v1/archives/media";
+const DELETE_MEDIA_PATH: &str = "/v1/archives/media/delete";
+
+const ZK_AUTH_HEADER: HeaderName = HeaderName::from_static("x-signal-zk-auth");
+const ZK_AUTH_SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-signal-zk-auth-signature");
+
+/// Credentials for accessing a backup.
+#[derive(Clone)]
+pub struct BackupAuth {
+    /// A serialized `BackupAuthCredentialPresentation`.
+    pub presentation: Vec<u8>,
+    /// A signature over `presentation` made with the backup's private key.
+    pub signature: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredMediaObject {
+    pub cdn: u32,
+    /// The URL-safe base64 encoding of the media ID.
+    pub media_id: String,
+    pub object_length: u64,
+}
+
+/// Identifies a media object to delete.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaObjectRef {
+    pub cdn: u32,
+    /// The URL-safe base64 encoding of the media ID.
+    pub media_id: String,
+}
+
+/// One page of the media stored for a backup.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ListMediaResponse {
+    pub stored_media_objects: Vec<StoredMediaObject>,
+    pub backup_dir: String,
+    pub media_dir: String,
+    /// If present, can be passed to [`BackupsClient::list_media`] to fetch
+    /// the next page.
+    pub cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteMediaRequest<'a> {
+    media_to_delete: &'a [MediaObjectRef],
+}
+
+impl From<&StoredMediaObject> for MediaObjectRef {
+    fn from(value: &StoredMediaObject) -> Self {
+        let StoredMediaObject {
+            cdn,
+            media_id,
+            object_length: _,
+        } = value;
+        Self {
+            cdn: *cdn,
+            media_id: media_id.clone(),
+        }
+    }
+}
+
+impl BackupAuth {
+    fn headers(&self) -> HeaderMap {
+        let Self {
+            presentation,
+            signature,
+        } = self;
+        [
+            (ZK_AUTH_HEADER, presentation),
+            (ZK_AUTH_SIGNATURE_HEADER, signature),
+        ]
+        .into_iter()
+        .map(|(name, value)| {
+            let value = HeaderValue::try_from(BASE64_STANDARD.encode(value))
+                .expect("base64 is a valid header value");
+            (name, value)
+        })
+        .collect()
+    }
+}
+
+/// Client for the backup endpoints on the chat server.
+pub struct BackupsClient<C, T> {
+    endpoint: HttpEndpoint<C, T>,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> BackupsClient<C, T> {
+    pub fn new(connection_manager: C, transport_connector: T) -> Self {
+        Self {
+            endpoint: HttpEndpoint::new(
+                connection_manager,
+                transport_connector,
+                DEFAULT_MAX_RESPONSE_SIZE,
+            ),
+        }
+    }
+
+    /// Requests a form for uploading a new backup file.
+    pub async fn get_upload_form(&self, auth: &BackupAuth) -> Result<UploadForm, RequestError> {
+        self.get_json(auth, PathAndQuery::from_static(UPLOAD_FORM_PATH))
+            .await
+    }
+
+    /// Requests a form for uploading a media object to the backup media tier.
+    pub async fn get_media_upload_form(
+        &self,
+        auth: &BackupAuth,
+    ) -> Result<UploadForm, RequestError> {
+        self.get_json(auth, PathAndQuery::from_static(MEDIA_UPLOAD_FORM_PATH))
+            .await
+    }
+
+    /// Lists up to `limit` stored media objects, starting at `cursor`.
+    pub async fn list_media(
+        &self,
+        auth: &BackupAuth,
+        cursor: Option<&str>,
+        limit: u32,
+    ) -> Result<ListMediaResponse, RequestError> {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("limit", &limit.to_string());
+        if let Some(cursor) = cursor {
+            query.append_pair("cursor", cursor);
+        }
+        let path_and_query = PathAndQuery::from_str(&format!("{MEDIA_PATH}?{}", query.finish()))
+            .expect("query is encoded");
+
+        self.get_json(auth, path_and_query).await
+    }
+
+    /// Deletes media objects from the backup media tier.
+    pub async fn delete_media(
+        &self,
+        auth: &BackupAuth,
+        media: &[MediaObjectRef],
+    ) -> Result<(), RequestError> {
+        let body = serde_json::to_vec(&DeleteMediaRequest {
+            media_to_delete: media,
+        })
+        .expect("can serialize");
+
+        let mut headers = auth.headers();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        self.endpoint
+            .send(
+                Method::POST,
+                PathAndQuery::from_static(DELETE_MEDIA_PATH),
+                headers,
+                Bytes::from(body),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_json<R: for<'de> Deserialize<'de>>(
+        &self,
+        auth: &BackupAuth,
+        path_and_query: PathAndQuery,
+    ) -> Result<R, RequestError> {
+        let (_parts, body) = self
+            .endpoint
+            .send(Method::GET, path_and_query, auth.headers(), Bytes::new())
+            .await?;
+        serde_json::from_slice(&body).map_err(|_| RequestError::InvalidResponse)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn auth_headers_are_base64() {
+        let auth = BackupAuth {
+            presentation: vec![1, 2, 3],
+            signature: vec![4, 5, 6],
+        };
+        let headers = auth.headers();
+        assert_eq!(headers[ZK_AUTH_HEADER], "AQID");
+        assert_eq!(headers[ZK_AUTH_SIGNATURE_HEADER], "BAUG");
+    }
+
+    #[test]
+    fn parses_list_media_response() {
+        let response: ListMediaResponse = serde_json::from_str(
+            r#"{
+                "storedMediaObjects": [{"cdn": 3, "mediaId": "AAEC", "objectLength": 100}],
+                "backupDir": "backups",
+                "mediaDir": "media",
+                "cursor": null
+            }"#,
+        )
+        .expect("valid JSON");
+
+        assert_eq!(
+            response.stored_media_objects,
+            [StoredMediaObject {
+                cdn: 3,
+                media_id: "AAEC".to_owned(),
+                object_length: 100
+            }]
+        );
+        assert_eq!(response.cursor, None);
+        assert_eq!(
+            MediaObjectRef::from(&response.stored_media_objects[0]),
+            MediaObjectRef {
+                cdn: 3,
+                media_id: "AAEC".to_owned()
+            }
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Requests to Signal's CDNs and the servers that hand out access to them.
+//!
+//! Objects are uploaded to the CDN described by an [`UploadForm`] using the
+//! resumable [tus](https://tus.io) protocol.
+
+use std::collections::HashMap;
+use std::num::{NonZeroU16, NonZeroUsize};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bytes::Bytes;
+use http::response::Parts;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use nonzero_ext::nonzero;
+use serde::Deserialize;
+
+use crate::infra::certs::RootCertificates;
+use crate::infra::connection_manager::{ConnectionAttemptOutcome, ConnectionManager};
+use crate::infra::errors::RetryLater;
+use crate::infra::host::Host;
+use crate::infra::http_client::{http2_client, AggregatingHttp2Client, HttpError};
+use crate::infra::{
+    ConnectionParams, HttpRequestDecoratorSeq, RouteType, TrafficClass, TransportConnectionParams,
+    TransportConnector,
+};
+
+/// The largest response accepted by default, for JSON and other small responses.
+pub(crate) const DEFAULT_MAX_RESPONSE_SIZE: usize = 1024 * 1024;
+
+const TUS_RESUMABLE_HEADER: HeaderName = HeaderName::from_static("tus-resumable");
+const TUS_VERSION: HeaderValue = HeaderValue::from_static("1.0.0");
+const UPLOAD_LENGTH_HEADER: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET_HEADER: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_METADATA_HEADER: HeaderName = HeaderName::from_static("upload-metadata");
+const OFFSET_OCTET_STREAM: HeaderValue =
+    HeaderValue::from_static("application/offset+octet-stream");
+
+/// How many chunks in a row the server may accept only part of before an
+/// upload is abandoned.
+const MAX_PARTIAL_CHUNKS: u32 = 3;
+
+/// Describes where and how to upload an attachment, backup file, or media object.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadForm {
+    /// The number of the CDN the object will be stored on.
+    pub cdn: u32,
+    /// The key the object will be stored under.
+    pub key: String,
+    /// Headers that must be included with every upload request.
+    pub headers: HashMap<String, String>,
+    /// The URL at which to create the upload.
+    pub signed_upload_location: String,
+}
+
+/// Anything that can go wrong during a request to the CDN or chat server.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum RequestError {
+    /// HTTP error: {0}
+    Http(HttpError),
+    /// connect attempt timed out
+    ConnectionTimedOut,
+    /// credentials were rejected
+    Unauthorized,
+    /// the requested object does not exist
+    NotFound,
+    /// {0}
+    RetryLater(RetryLater),
+    /// rate limited without a retry delay
+    RateLimited,
+    /// server returned unexpected status {0}
+    UnexpectedStatus(StatusCode),
+    /// invalid response received from the server
+    InvalidResponse,
+    /// upload form was invalid
+    InvalidUploadForm,
+}
+
+/// Maps non-success HTTP statuses to errors.
+pub(crate) fn check_status(parts: &Parts) -> Result<(), RequestError> {
+    match parts.status {
+        status if status.is_success() => Ok(()),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(RequestError::Unauthorized),
+        StatusCode::NOT_FOUND => Err(RequestError::NotFound),
+        StatusCode::TOO_MANY_REQUESTS => {
+            Err(RetryLater::from_response(parts.status, &parts.headers)
+                .map_or(RequestError::RateLimited, RequestError::RetryLater))
+        }
+        status => Err(RetryLater::from_response(status, &parts.headers).map_or(
+            RequestError::UnexpectedStatus(status),
+            RequestError::RetryLater,
+        )),
+    }
+}
+
+/// An HTTP/2 server reached through a [`ConnectionManager`].
+///
+/// The connection is established on first use and reused for later requests
+/// until a request fails.
+pub(crate) struct HttpEndpoint<C, T> {
+    manager: C,
+    max_response_size: usize,
+    transport_connector: T,
+    client: Mutex<Option<AggregatingHttp2Client>>,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> HttpEndpoint<C, T> {
+    pub(crate) fn new(manager: C, transport_connector: T, max_response_size: usize) -> Self {
+        Self {
+            manager,
+            max_response_size,
+            transport_connector,
+            client: Mutex::new(None),
+        }
+    }
+
+    async fn client(&self) -> Result<AggregatingHttp2Client, RequestError> {
+        if let Some(client) = self.client.lock().expect("not poisoned").clone() {
+            return Ok(client);
+        }
+
+        let client = match self
+            .manager
+            .connect_or_wait(|params| {
+                http2_client(
+                    &self.transport_connector,
+                    params.clone(),
+                    self.max_response_size,
+                )
+            })
+            .await
+        {
+            ConnectionAttemptOutcome::Attempted(result) => result.map_err(RequestError::Http),
+            ConnectionAttemptOutcome::TimedOut | ConnectionAttemptOutcome::WaitUntil(_) => {
+                Err(RequestError::ConnectionTimedOut)
+            }
+        }?;
+
+        *self.client.lock().expect("not poisoned") = Some(client.clone());
+        Ok(client)
+    }
+
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        path_and_query: PathAndQuery,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<(Parts, Bytes), RequestError> {
+        let (parts, body) = self
+            .send_unchecked(method, path_and_query, headers, body)
+            .await?;
+        check_status(&parts)?;
+        Ok((parts, body))
+    }
+
+    /// Like [`Self::send`], but returns the response whatever its status, for
+    /// endpoints whose error responses carry information.
+    pub(crate) async fn send_unchecked(
+        &self,
+        method: Method,
+        path_and_query: PathAndQuery,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<(Parts, Bytes), RequestError> {
+        let client = self.client().await?;
+        let (parts, body) = client
+            .send_request_aggregate_response(path_and_query, method, headers, body)
+            .await
+            .map_err(|e| {
+                // Reconnect for the next request in case the connection was lost.
+                *self.client.lock().expect("not poisoned") = None;
+                RequestError::Http(e)
+            })?;
+        Ok((parts, body))
+    }
+}
+
+/// Connection parameters for the CDN host named in an [`UploadForm`].
+///
+/// These can be used to build the connection manager for a [`TusUpload`].
+pub fn upload_connection_params(form: &UploadForm) -> Result<ConnectionParams, RequestError> {
+    let uri = http::Uri::from_str(&form.signed_upload_location)
+        .map_err(|_| RequestError::InvalidUploadForm)?;
+    if uri.scheme() != Some(&http::uri::Scheme::HTTPS) {
+        return Err(RequestError::InvalidUploadForm);
+    }
+    let host: Arc<str> = uri.host().ok_or(RequestError::InvalidUploadForm)?.into();
+    let port =
+        NonZeroU16::new(uri.port_u16().unwrap_or(443)).ok_or(RequestError::InvalidUploadForm)?;
+
+    Ok(bulk_transfer_params(host, port))
+}
+
+/// Connection parameters for downloading attachments, backup files, and
+/// media from the CDN at `host`.
+///
+/// These can be used to build the connection manager for an
+/// [`AttachmentTransferClient`](crate::attachments::AttachmentTransferClient).
+pub fn download_connection_params(host: &str) -> ConnectionParams {
+    bulk_transfer_params(host.into(), nonzero!(443u16))
+}
+
+fn bulk_transfer_params(host: Arc<str>, port: NonZeroU16) -> ConnectionParams {
+    ConnectionParams {
+        route_type: RouteType::Direct,
+        transport: TransportConnectionParams {
+            sni: Arc::clone(&host),
+            tcp_host: Host::Domain(Arc::clone(&host)),
+            port,
+            certs: RootCertificates::Native,
+            proxy: None,
+            traffic_class: TrafficClass::BULK,
+        },
+        http_host: host,
+        http_request_decorator: HttpRequestDecoratorSeq::default(),
+        connection_confirmation_header: None,
+    }
+}
+
+/// The path to create an upload at, from the upload location in the form.
+fn tus_create_path(form: &UploadForm) -> Result<PathAndQuery, RequestError> {
+    let uri = http::Uri::from_str(&form.signed_upload_location)
+        .map_err(|_| RequestError::InvalidUploadForm)?;
+    Ok(uri
+        .path_and_query()
+        .cloned()
+        .unwrap_or_else(|| PathAndQuery::from_static("/")))
+}
+
+/// The path of a created upload, from the `Location` the server gave for it.
+///
+/// The location may be an absolute path, or an absolute URL on the same host
+/// as the form's upload location.
+fn tus_upload_path(form: &UploadForm, location: &str) -> Result<PathAndQuery, RequestError> {
+    let uri = http::Uri::from_str(location).map_err(|_| RequestError::InvalidResponse)?;
+    if uri.scheme().is_some() || uri.authority().is_some() {
+        let form_uri = http::Uri::from_str(&form.signed_upload_location)
+            .map_err(|_| RequestError::InvalidUploadForm)?;
+        let same_host = uri.scheme() == Some(&http::uri::Scheme::HTTPS)
+            && uri.host() == form_uri.host()
+            && uri.port_u16().unwrap_or(443) == form_uri.port_u16().unwrap_or(443);
+        if !same_host {
+            return Err(RequestError::InvalidResponse);
+        }
+    }
+    uri.path_and_query()
+        .filter(|path| path.path().starts_with('/'))
+        .cloned()
+        .ok_or(RequestError::InvalidResponse)
+}
+
+fn tus_form_headers(form: &UploadForm) -> Result<HeaderMap, RequestError> {
+    form.headers
+        .iter()
+        .map(|(name, value)| {
+            Ok((
+                HeaderName::from_str(name).map_err(|_| RequestError::InvalidUploadForm)?,
+                HeaderValue::from_str(value).map_err(|_| RequestError::InvalidUploadForm)?,
+            ))
+        })
+        .collect()
+}
+
+/// An upload to the CDN using the tus resumable upload protocol.
+///
+/// Contents are sent in chunks, and the server acknowledges how much it has
+/// received after each one. If an upload is interrupted, [`TusUpload::resume`]
+/// with the same form and [`TusUpload::upload_location`] picks up where the
+/// server left off.
+pub struct TusUpload<C, T> {
+    endpoint: HttpEndpoint<C, T>,
+    form_headers: HeaderMap,
+    upload_path: PathAndQuery,
+    length: u64,
+    offset: u64,
+    partial_chunks: u32,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> TusUpload<C, T> {
+    /// Creates a new upload of `length` bytes on the server.
+    pub async fn create(
+        connection_manager: C,
+        transport_connector: T,
+        form: &UploadForm,
+        length: u64,
+    ) -> Result<Self, RequestError> {
+        let endpoint = HttpEndpoint::new(
+            connection_manager,
+            transport_connector,
+            DEFAULT_MAX_RESPONSE_SIZE,
+        );
+        let form_headers = tus_form_headers(form)?;
+
+        let mut headers = form_headers.clone();
+        headers.insert(TUS_RESUMABLE_HEADER, TUS_VERSION);
+        headers.insert(UPLOAD_LENGTH_HEADER, length.into());
+        headers.insert(
+            UPLOAD_METADATA_HEADER,
+            HeaderValue::try_from(format!("filename {}", BASE64_STANDARD.encode(&form.key)))
+                .expect("base64 is a valid header value"),
+        );
+        let (parts, _body) = endpoint
+            .send(Method::POST, tus_create_path(form)?, headers, Bytes::new())
+            .await?;
+        let location = parts
+            .headers
+            .get(http::header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(RequestError::InvalidResponse)?;
+
+        Ok(Self {
+            endpoint,
+            form_headers,
+            upload_path: tus_upload_path(form, location)?,
+            length,
+            offset: 0,
+            partial_chunks: 0,
+        })
+    }
+
+    /// Resumes an upload previously started with [`TusUpload::create`].
+    ///
+    /// `upload_location` is the [`TusUpload::upload_location`] of that upload.
+    /// The server is asked how many bytes it has already received.
+    pub async fn resume(
+        connection_manager: C,
+        transport_connector: T,
+        form: &UploadForm,
+        upload_location: &str,
+        length: u64,
+    ) -> Result<Self, RequestError> {
+        let mut upload = Self {
+            endpoint: HttpEndpoint::new(
+                connection_manager,
+                transport_connector,
+                DEFAULT_MAX_RESPONSE_SIZE,
+            ),
+            form_headers: tus_form_headers(form)?,
+            upload_path: tus_upload_path(form, upload_location)?,
+            length,
+            offset: 0,
+            partial_chunks: 0,
+        };
+
+        let (parts, _body) = upload
+            .endpoint
+            .send(
+                Method::HEAD,
+                upload.upload_path.clone(),
+                upload.tus_headers(),
+                Bytes::new(),
+            )
+            .await?;
+        upload.offset = parse_upload_offset(&parts, 0..=length)?;
+
+        Ok(upload)
+    }
+
+    /// Where the server put the upload, to be saved for [`TusUpload::resume`].
+    pub fn upload_location(&self) -> &str {
+        self.upload_path.as_str()
+    }
+
+    /// The number of bytes the server has acknowledged.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The total length of the upload.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.offset == self.length
+    }
+
+    /// Sends the next chunk of contents, starting at [`Self::offset`].
+    ///
+    /// Returns the new offset, which may be less than the end of the chunk if
+    /// the server didn't accept all of it. The server must accept at least one
+    /// byte, and may only accept part of a few chunks in a row.
+    pub async fn upload_chunk(&mut self, chunk: Bytes) -> Result<u64, RequestError> {
+        let chunk_end = self.offset + u64::try_from(chunk.len()).expect("usize fits in u64");
+        let mut headers = self.tus_headers();
+        headers.insert(UPLOAD_OFFSET_HEADER, self.offset.into());
+        headers.insert(http::header::CONTENT_TYPE, OFFSET_OCTET_STREAM);
+
+        let (parts, _body) = self
+            .endpoint
+            .send(Method::PATCH, self.upload_path.clone(), headers, chunk)
+            .await?;
+        self.offset = parse_upload_offset(&parts, self.offset + 1..=self.length)?;
+
+        if self.offset < chunk_end {
+            self.partial_chunks += 1;
+            if self.partial_chunks > MAX_PARTIAL_CHUNKS {
+                return Err(RequestError::InvalidResponse);
+            }
+        } else {
+            self.partial_chunks = 0;
+        }
+        Ok(self.offset)
+    }
+
+    /// Sends the rest of `contents` in chunks of at most `chunk_size` bytes.
+    ///
+    /// `contents` is the entire object being uploaded; whatever the server has
+    /// already acknowledged is skipped.
+    pub async fn upload_remaining(
+        &mut self,
+        contents: &Bytes,
+        chunk_size: NonZeroUsize,
+    ) -> Result<(), RequestError> {
+        if u64::try_from(contents.len()).ok() != Some(self.length) {
+            return Err(RequestError::InvalidUploadForm);
+        }
+        while !self.is_complete() {
+            let start = usize::try_from(self.offset).expect("offset is within contents");
+            let end = contents.len().min(start.saturating_add(chunk_size.get()));
+            self.upload_chunk(contents.slice(start..end)).await?;
+        }
+        Ok(())
+    }
+
+    fn tus_headers(&self) -> HeaderMap {
+        let mut headers = self.form_headers.clone();
+        headers.insert(TUS_RESUMABLE_HEADER, TUS_VERSION);
+        headers
+    }
+}
+
+/// Reads the server's acknowledged offset, which must be within `valid`.
+fn parse_upload_offset(parts: &Parts, valid: RangeInclusive<u64>) -> Result<u64, RequestError> {
+    parts
+        .headers
+        .get(UPLOAD_OFFSET_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| u64::from_str(value).ok())
+        .filter(|offset| valid.contains(offset))
+        .ok_or(RequestError::InvalidResponse)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use assert_matches::assert_matches;
+    use test_case::test_case;
+
+    use super::*;
+
+    fn response_parts(status: StatusCode, headers: &[(&'static str, &'static str)]) -> Parts {
+        let mut builder = http::Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).expect("valid response").into_parts().0
+    }
+
+    fn test_form() -> UploadForm {
+        serde_json::from_str(
+            r#"{
+                "cdn": 3,
+                "key": "abc123",
+                "headers": {"authorization": "Basic dXNlcjpwYXNz"},
+                "signedUploadLocation": "https://cdn3.example.org/upload/"
+            }"#,
+        )
+        .expect("valid JSON")
+    }
+
+    #[test_case(StatusCode::OK, &[] => matches Ok(()))]
+    #[test_case(StatusCode::NO_CONTENT, &[] => matches Ok(()))]
+    #[test_case(StatusCode::UNAUTHORIZED, &[] => matches Err(RequestError::Unauthorized))]
+    #[test_case(StatusCode::NOT_FOUND, &[] => matches Err(RequestError::NotFound))]
+    #[test_case(StatusCode::TOO_MANY_REQUESTS, &[("retry-after", "30")] => matches Err(RequestError::RetryLater(RetryLater { retry_after })) if retry_after == Duration::from_secs(30))]
+    #[test_case(StatusCode::TOO_MANY_REQUESTS, &[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")] => matches Err(RequestError::RateLimited))]
+    #[test_case(StatusCode::TOO_MANY_REQUESTS, &[] => matches Err(RequestError::RateLimited))]
+    #[test_case(StatusCode::SERVICE_UNAVAILABLE, &[("retry-after", "30")] => matches Err(RequestError::RetryLater(RetryLater { retry_after })) if retry_after == Duration::from_secs(30))]
+    #[test_case(StatusCode::SERVICE_UNAVAILABLE, &[] => matches Err(RequestError::UnexpectedStatus(StatusCode::SERVICE_UNAVAILABLE)))]
+    #[test_case(StatusCode::BAD_GATEWAY, &[] => matches Err(RequestError::UnexpectedStatus(StatusCode::BAD_GATEWAY)))]
+    #[test_case(StatusCode::BAD_GATEWAY, &[("retry-after", "30")] => matches Err(RequestError::UnexpectedStatus(StatusCode::BAD_GATEWAY)))]
+    #[test_case(StatusCode::CONFLICT, &[("retry-after", "30")] => matches Err(RequestError::UnexpectedStatus(StatusCode::CONFLICT)))]
+    fn status_mapping(
+        status: StatusCode,
+        headers: &[(&'static str, &'static str)],
+    ) -> Result<(), RequestError> {
+        check_status(&response_parts(status, headers))
+    }
+
+    #[test_case("0", 0..=10 => matches Ok(0))]
+    #[test_case("10", 0..=10 => matches Ok(10))]
+    #[test_case("5", 6..=10 => matches Err(RequestError::InvalidResponse); "went backwards")]
+    #[test_case("6", 7..=10 => matches Err(RequestError::InvalidResponse); "stalled")]
+    #[test_case("11", 7..=10 => matches Err(RequestError::InvalidResponse); "past the end")]
+    #[test_case("six", 0..=10 => matches Err(RequestError::InvalidResponse); "not a number")]
+    fn upload_offset(value: &'static str, valid: RangeInclusive<u64>) -> Result<u64, RequestError> {
+        parse_upload_offset(
+            &response_parts(StatusCode::NO_CONTENT, &[("upload-offset", value)]),
+            valid,
+        )
+    }
+
+    #[test]
+    fn upload_location_params() {
+        let params = upload_connection_params(&test_form()).expect("valid form");
+        assert_eq!(&*params.http_host, "cdn3.example.org");
+        assert_eq!(params.transport.port.get(), 443);
+        assert_eq!(params.transport.traffic_class, TrafficClass::BULK);
+
+        let create_path = tus_create_path(&test_form()).expect("valid form");
+        assert_eq!(create_path.as_str(), "/upload/");
+    }
+
+    #[test_case("/upload/xyz789" => matches Ok(path) if path == "/upload/xyz789")]
+    #[test_case("https://cdn3.example.org/upload/xyz789?a=b" => matches Ok(path) if path == "/upload/xyz789?a=b")]
+    #[test_case("https://cdn3.example.org:443/upload/xyz789" => matches Ok(path) if path == "/upload/xyz789")]
+    #[test_case("https://elsewhere.example.org/upload/xyz789" => matches Err(RequestError::InvalidResponse); "other host")]
+    #[test_case("http://cdn3.example.org/upload/xyz789" => matches Err(RequestError::InvalidResponse); "insecure")]
+    #[test_case("xyz789" => matches Err(RequestError::InvalidResponse); "relative")]
+    fn upload_location(location: &str) -> Result<PathAndQuery, RequestError> {
+        tus_upload_path(&test_form(), location)
+    }
+
+    #[test]
+    fn download_params() {
+        let params = download_connection_params("cdn3.example.org");
+        assert_eq!(&*params.http_host, "cdn3.example.org");
+        assert_eq!(params.transport.port.get(), 443);
+        assert_eq!(params.transport.traffic_class, TrafficClass::BULK);
+    }
+
+    #[test]
+    fn rejects_insecure_upload_location() {
+        let form = UploadForm {
+            signed_upload_location: "http://cdn3.example.org/upload".to_owned(),
+            ..test_form()
+        };
+        assert_matches!(
+            upload_connection_params(&form),
+            Err(RequestError::InvalidUploadForm)
+        );
+    }
+}
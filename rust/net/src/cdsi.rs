@@ -4,12 +4,12 @@
 //
 
 use std::default::Default;
-use std::fmt::Display;
-use std::num::{NonZeroU64, ParseIntError};
+use std::fmt;
 use std::str::FromStr;
 
 use http::StatusCode;
-use libsignal_core::{Aci, Pni};
+pub use libsignal_core::E164;
+use libsignal_core::{Aci, Pni, ServiceId, ServiceIdParseError};
 use prost::Message as _;
 use thiserror::Error;
 use tokio::net::TcpStream;
@@ -17,17 +17,25 @@ use tokio_boring_signal::SslStream;
 use tungstenite::protocol::frame::coding::CloseCode;
 use tungstenite::protocol::CloseFrame;
 use uuid::Uuid;
+pub use zkgroup::profiles::AccessKey;
 
 use crate::auth::HttpBasicAuth;
-use crate::enclave::{Cdsi, EnclaveEndpointConnection};
+use crate::enclave::{Cdsi, EnclaveEndpointConnection, ProtocolVersion};
 use crate::infra::connection_manager::ConnectionManager;
 use crate::infra::errors::TransportConnectError;
+use crate::infra::ws::close::{error_for_close, CloseFrameError, CommonClose};
+use crate::infra::ws::error::ResponseLimitError;
 use crate::infra::ws::{
-    AttestedConnection, AttestedConnectionError, NextOrClose, WebSocketConnectError,
-    WebSocketServiceError,
+    AttestedConnection, AttestedConnectionError, NextOrClose, ResponseLimiter,
+    WebSocketConnectError, WebSocketServiceError,
 };
 use crate::infra::{AsyncDuplexStream, TransportConnector};
 use crate::proto::cds2::{ClientRequest, ClientResponse};
+use crate::proto::limits::{DecodeError, DecodeLimits};
+use crate::utils::Redacted;
+
+mod delta;
+pub use delta::{DeltaLookup, LookupTokenStore, PreviousLookup};
 
 trait FixedLengthSerializable {
     const SERIALIZED_LEN: usize;
@@ -52,44 +60,11 @@ impl<It: ExactSizeIterator<Item = T>, T: FixedLengthSerializable> CollectSeriali
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct E164(NonZeroU64);
-
-impl E164 {
-    pub const fn new(number: NonZeroU64) -> Self {
-        Self(number)
-    }
-
-    fn from_serialized(bytes: [u8; E164::SERIALIZED_LEN]) -> Option<Self> {
-        NonZeroU64::new(u64::from_be_bytes(bytes)).map(Self)
-    }
-}
-
-impl From<E164> for NonZeroU64 {
-    fn from(value: E164) -> Self {
-        value.0
-    }
-}
-
-impl FromStr for E164 {
-    type Err = ParseIntError;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.strip_prefix('+').unwrap_or(s);
-        NonZeroU64::from_str(s).map(Self)
-    }
-}
-
-impl Display for E164 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "+{}", self.0)
-    }
-}
-
 impl FixedLengthSerializable for E164 {
-    const SERIALIZED_LEN: usize = 8;
+    const SERIALIZED_LEN: usize = E164::SERIALIZED_LEN;
 
     fn serialize_into(&self, target: &mut [u8]) {
-        target.copy_from_slice(&self.0.get().to_be_bytes())
+        target.copy_from_slice(&self.to_be_bytes())
     }
 }
 
@@ -102,7 +77,16 @@ impl FixedLengthSerializable for Uuid {
 
 pub struct AciAndAccessKey {
     pub aci: Aci,
-    pub access_key: [u8; 16],
+    pub access_key: AccessKey,
+}
+
+impl fmt::Debug for AciAndAccessKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AciAndAccessKey")
+            .field("aci", &Redacted(&self.aci))
+            .field("access_key", &self.access_key)
+            .finish()
+    }
 }
 
 impl FixedLengthSerializable for AciAndAccessKey {
@@ -111,25 +95,82 @@ impl FixedLengthSerializable for AciAndAccessKey {
     fn serialize_into(&self, target: &mut [u8]) {
         let (aci_bytes, access_key_bytes) = target.split_at_mut(Uuid::SERIALIZED_LEN);
 
-        Uuid::from(self.aci).serialize_into(aci_bytes);
-        access_key_bytes.copy_from_slice(&self.access_key)
+        aci_bytes.copy_from_slice(self.aci.raw_uuid_bytes());
+        access_key_bytes.copy_from_slice(self.access_key.as_bytes())
     }
 }
 
+/// The first CDSI protocol version that accepts [`LookupRequest::discard_e164s`].
+pub const PROTOCOL_VERSION_DISCARD_E164S: ProtocolVersion = ProtocolVersion(1);
+
 #[derive(Default)]
 pub struct LookupRequest {
     pub new_e164s: Vec<E164>,
     pub prev_e164s: Vec<E164>,
+    /// Numbers from a previous request that should no longer count against
+    /// the token.
+    ///
+    /// Only servers that negotiate at least [`PROTOCOL_VERSION_DISCARD_E164S`]
+    /// accept these; sending any to an older server fails with
+    /// [`LookupError::UnsupportedByServer`].
+    pub discard_e164s: Vec<E164>,
     pub acis_and_access_keys: Vec<AciAndAccessKey>,
     pub return_acis_without_uaks: bool,
     pub token: Box<[u8]>,
 }
 
+impl fmt::Debug for LookupRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            new_e164s,
+            prev_e164s,
+            discard_e164s,
+            acis_and_access_keys,
+            return_acis_without_uaks,
+            token,
+        } = self;
+        f.debug_struct("LookupRequest")
+            .field("new_e164s", &Redacted(new_e164s))
+            .field("prev_e164s", &Redacted(prev_e164s))
+            .field("discard_e164s", &Redacted(discard_e164s))
+            .field("acis_and_access_keys", acis_and_access_keys)
+            .field("return_acis_without_uaks", return_acis_without_uaks)
+            .field("token", &Redacted(token))
+            .finish()
+    }
+}
+
+/// Limits on the response that carries the token, which is a few dozen bytes.
+const TOKEN_RESPONSE_LIMITS: DecodeLimits = DecodeLimits {
+    max_message_size: 64 << 10,
+    max_repeated_field_len: 0,
+};
+
+/// Room in a lookup response for everything besides the triples, including
+/// the per-frame framing when it arrives in pieces.
+const LOOKUP_RESPONSE_OVERHEAD: usize = 64 << 10;
+
 impl LookupRequest {
-    fn into_client_request(self) -> ClientRequest {
+    /// Limits on the lookup results, which contain one fixed-size triple for
+    /// each number in the request.
+    fn response_limits(&self) -> DecodeLimits {
+        let numbers = self.new_e164s.len().saturating_add(self.prev_e164s.len());
+        DecodeLimits {
+            max_message_size: numbers
+                .saturating_mul(LookupResponseEntry::SERIALIZED_LEN)
+                .saturating_add(LOOKUP_RESPONSE_OVERHEAD),
+            max_repeated_field_len: 0,
+        }
+    }
+
+    fn into_client_request(
+        self,
+        protocol_version: ProtocolVersion,
+    ) -> Result<ClientRequest, LookupError> {
         let Self {
             new_e164s,
             prev_e164s,
+            discard_e164s,
             acis_and_access_keys,
             return_acis_without_uaks,
             token,
@@ -138,32 +179,56 @@ impl LookupRequest {
         let aci_uak_pairs = acis_and_access_keys.into_iter().collect_serialized();
         let new_e164s = new_e164s.into_iter().collect_serialized();
         let prev_e164s = prev_e164s.into_iter().collect_serialized();
+        if !discard_e164s.is_empty() && protocol_version < PROTOCOL_VERSION_DISCARD_E164S {
+            return Err(LookupError::UnsupportedByServer {
+                feature: "discard_e164s",
+                protocol_version,
+            });
+        }
+        let discard_e164s = discard_e164s.into_iter().collect_serialized();
 
-        ClientRequest {
+        Ok(ClientRequest {
             aci_uak_pairs,
             new_e164s,
             prev_e164s,
             return_acis_without_uaks,
             token: token.into_vec(),
             token_ack: false,
-            // TODO: use these for supporting non-desktop client requirements.
-            discard_e164s: Vec::new(),
-        }
+            discard_e164s,
+        })
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Token(pub Box<[u8]>);
 
-#[derive(Debug)]
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Token").field(&Redacted(&self.0)).finish()
+    }
+}
+
 #[cfg_attr(test, derive(PartialEq))]
 pub struct LookupResponse {
     pub records: Vec<LookupResponseEntry>,
     pub debug_permits_used: i32,
 }
 
-#[derive(Clone, Debug)]
+impl fmt::Debug for LookupResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            records,
+            debug_permits_used,
+        } = self;
+        f.debug_struct("LookupResponse")
+            .field("records", records)
+            .field("debug_permits_used", debug_permits_used)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct LookupResponseEntry {
     pub e164: E164,
@@ -171,6 +236,17 @@ pub struct LookupResponseEntry {
     pub pni: Option<Pni>,
 }
 
+impl fmt::Debug for LookupResponseEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { e164, aci, pni } = self;
+        f.debug_struct("LookupResponseEntry")
+            .field("e164", &Redacted(e164))
+            .field("aci", &Redacted(aci))
+            .field("pni", &Redacted(pni))
+            .finish()
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum LookupResponseParseError {
     InvalidNumberOfBytes { actual_length: usize },
@@ -218,20 +294,26 @@ impl TryFrom<ClientResponse> for LookupResponse {
 
 impl LookupResponseEntry {
     fn try_parse_from(record: &[u8; Self::SERIALIZED_LEN]) -> Option<Self> {
-        fn non_nil_uuid<T: From<Uuid>>(bytes: &uuid::Bytes) -> Option<T> {
-            let uuid = Uuid::from_bytes(*bytes);
-            (!uuid.is_nil()).then(|| uuid.into())
+        fn non_nil<T>(
+            bytes: &[u8],
+            parse: fn(&[u8]) -> Result<T, ServiceIdParseError>,
+        ) -> Option<T> {
+            // An all-zero (nil) UUID means there was no match.
+            if bytes.iter().all(|b| *b == 0) {
+                return None;
+            }
+            parse(bytes).ok()
         }
 
         // TODO(https://github.com/rust-lang/rust/issues/90091): use split_array
         // instead of expect() on the output.
         let (e164_bytes, record) = record.split_at(E164::SERIALIZED_LEN);
         let e164_bytes = <&[u8; E164::SERIALIZED_LEN]>::try_from(e164_bytes).expect("split at len");
-        let e164 = E164::from_serialized(*e164_bytes)?;
+        let e164 = E164::from_be_bytes(*e164_bytes)?;
         let (pni_bytes, aci_bytes) = record.split_at(Uuid::SERIALIZED_LEN);
 
-        let pni = non_nil_uuid(pni_bytes.try_into().expect("split at len"));
-        let aci = non_nil_uuid(aci_bytes.try_into().expect("split at len"));
+        let pni = non_nil(pni_bytes, ServiceId::parse_binary_as_pni);
+        let aci = non_nil(aci_bytes, ServiceId::parse_binary_as_aci);
 
         Some(Self { e164, aci, pni })
     }
@@ -247,19 +329,29 @@ impl FixedLengthSerializable for LookupResponseEntry {
         e164.serialize_into(e164_bytes);
 
         let (pni_bytes, aci_bytes) = target.split_at_mut(Uuid::SERIALIZED_LEN);
-        pni.map(Uuid::from)
-            .unwrap_or(Uuid::nil())
-            .serialize_into(pni_bytes);
-
-        aci.map(Uuid::from)
-            .unwrap_or(Uuid::nil())
-            .serialize_into(aci_bytes);
+        pni_bytes.copy_from_slice(
+            pni.as_ref()
+                .map_or(Uuid::nil().as_bytes(), Pni::raw_uuid_bytes),
+        );
+        aci_bytes.copy_from_slice(
+            aci.as_ref()
+                .map_or(Uuid::nil().as_bytes(), Aci::raw_uuid_bytes),
+        );
     }
 }
 
 #[cfg_attr(test, derive(Debug))]
 pub struct CdsiConnection<S>(AttestedConnection<S>);
 
+/// Lets tests run lookups over a connection to a
+/// [`LoopbackEnclave`](crate::enclave::loopback::LoopbackEnclave).
+#[cfg(feature = "test-support")]
+impl<S> From<AttestedConnection<S>> for CdsiConnection<S> {
+    fn from(connection: AttestedConnection<S>) -> Self {
+        Self(connection)
+    }
+}
+
 impl<S> AsMut<AttestedConnection<S>> for CdsiConnection<S> {
     fn as_mut(&mut self) -> &mut AttestedConnection<S> {
         &mut self.0
@@ -291,6 +383,11 @@ pub enum LookupError {
     InvalidArgument { server_reason: String },
     /// server error: {reason}
     Server { reason: &'static str },
+    /// server does not support {feature} (negotiated protocol {protocol_version})
+    UnsupportedByServer {
+        feature: &'static str,
+        protocol_version: ProtocolVersion,
+    },
 }
 
 impl From<AttestedConnectionError> for LookupError {
@@ -304,6 +401,12 @@ impl From<AttestedConnectionError> for LookupError {
     }
 }
 
+impl From<ResponseLimitError> for LookupError {
+    fn from(value: ResponseLimitError) -> Self {
+        Self::WebSocket(WebSocketServiceError::ResponseLimit(value))
+    }
+}
+
 impl From<crate::enclave::Error> for LookupError {
     fn from(value: crate::enclave::Error) -> Self {
         use crate::enclave::Error;
@@ -342,14 +445,17 @@ impl From<prost::DecodeError> for LookupError {
     }
 }
 
-#[derive(serde::Deserialize)]
-#[cfg_attr(test, derive(serde::Serialize))]
-struct RateLimitExceededResponse {
-    retry_after_seconds: u32,
+impl From<DecodeError> for LookupError {
+    fn from(value: DecodeError) -> Self {
+        match value {
+            DecodeError::Limit(e) => e.into(),
+            DecodeError::Invalid(e) => e.into(),
+        }
+    }
 }
 
 #[cfg_attr(test, derive(Debug))]
-pub struct ClientResponseCollector<S = SslStream<TcpStream>>(CdsiConnection<S>);
+pub struct ClientResponseCollector<S = SslStream<TcpStream>>(CdsiConnection<S>, DecodeLimits);
 
 impl<S: AsyncDuplexStream> CdsiConnection<S> {
     /// Connect to remote host and verify remote attestation.
@@ -370,12 +476,18 @@ impl<S: AsyncDuplexStream> CdsiConnection<S> {
         mut self,
         request: LookupRequest,
     ) -> Result<(Token, ClientResponseCollector<S>), LookupError> {
-        self.0.send(request.into_client_request()).await?;
-        let token_response: ClientResponse = self.0.receive().await?.next_or_else(|close| {
-            close
-                .and_then(err_for_close)
-                .unwrap_or(LookupError::Protocol)
-        })?;
+        let protocol_version = self.0.protocol_version();
+        let response_limits = request.response_limits();
+        self.0
+            .send(request.into_client_request(protocol_version)?)
+            .await?;
+        let token_response: ClientResponse = TOKEN_RESPONSE_LIMITS.decode(
+            &self
+                .0
+                .receive_bytes()
+                .await?
+                .next_or_else(error_for_close)?,
+        )?;
 
         if token_response.token.is_empty() {
             return Err(LookupError::Protocol);
@@ -383,14 +495,14 @@ impl<S: AsyncDuplexStream> CdsiConnection<S> {
 
         Ok((
             Token(token_response.token.into_boxed_slice()),
-            ClientResponseCollector(self),
+            ClientResponseCollector(self, response_limits),
         ))
     }
 }
 
 impl<S: AsyncDuplexStream> ClientResponseCollector<S> {
     pub async fn collect(self) -> Result<LookupResponse, LookupError> {
-        let Self(mut connection) = self;
+        let Self(mut connection, decode_limits) = self;
 
         let token_ack = ClientRequest {
             token_ack: true,
@@ -398,14 +510,21 @@ impl<S: AsyncDuplexStream> ClientResponseCollector<S> {
         };
 
         connection.0.send(token_ack).await?;
-        let mut response: ClientResponse = connection.0.receive().await?.next_or_else(|close| {
-            close
-                .and_then(err_for_close)
-                .unwrap_or(LookupError::Protocol)
-        })?;
+        let mut limiter = ResponseLimiter::new(connection.0.response_limits());
+        let first = connection
+            .0
+            .receive_bytes()
+            .await?
+            .next_or_else(error_for_close)?;
+        limiter.record_frame(first.len())?;
+        let mut received_size = first.len();
+        let mut response: ClientResponse = decode_limits.decode(&first)?;
         loop {
             match connection.0.receive_bytes().await? {
                 NextOrClose::Next(decoded) => {
+                    limiter.record_frame(decoded.len())?;
+                    received_size = received_size.saturating_add(decoded.len());
+                    decode_limits.check_size(received_size)?;
                     response
                         .merge(decoded.as_ref())
                         .map_err(LookupError::from)?;
@@ -417,59 +536,65 @@ impl<S: AsyncDuplexStream> ClientResponseCollector<S> {
                         reason: _,
                     }),
                 ) => break,
-                NextOrClose::Close(Some(close)) => {
-                    return Err(err_for_close(close).unwrap_or(LookupError::Protocol))
-                }
+                NextOrClose::Close(close) => return Err(error_for_close(close)),
             }
         }
         Ok(response.try_into()?)
     }
 }
 
-/// Numeric code set by the server on the websocket close frame.
-#[repr(u16)]
-#[derive(Copy, Clone, num_enum::TryFromPrimitive, strum::IntoStaticStr)]
-enum CdsiCloseCode {
-    InvalidArgument = 4003,
-    RateLimitExceeded = 4008,
-    ServerInternalError = 4013,
-    ServerUnavailable = 4014,
-    InvalidToken = 4101,
+/// Performs a complete lookup on a new connection.
+///
+/// This connects to `endpoint`, verifies attestation, sends `request`,
+/// acknowledges the token, and collects the response. Use [`CdsiConnection`]
+/// and [`ClientResponseCollector`] directly to do something between receiving
+/// the token and collecting the results.
+pub async fn cdsi_lookup<C, T>(
+    endpoint: &EnclaveEndpointConnection<Cdsi, C>,
+    transport_connector: T,
+    auth: impl HttpBasicAuth,
+    request: LookupRequest,
+) -> Result<(Token, LookupResponse), LookupError>
+where
+    C: ConnectionManager,
+    T: TransportConnector,
+{
+    let connection = CdsiConnection::connect(endpoint, transport_connector, auth).await?;
+    let (token, collector) = connection.send_request(request).await?;
+    let response = collector.collect().await?;
+    Ok((token, response))
 }
 
-/// Produces a [`LookupError`] for the provided [`CloseFrame`].
-///
-/// Returns `Some(err)` if there is a relevant `LookupError` value for the
-/// provided close frame. Otherwise returns `None`.
-fn err_for_close(CloseFrame { code, reason }: CloseFrame<'_>) -> Option<LookupError> {
-    let Ok(code) = CdsiCloseCode::try_from(u16::from(code)) else {
-        log::warn!("got unexpected websocket error code: {code}",);
-        return None;
-    };
+/// Close code for a request with a token the server doesn't recognize.
+const INVALID_TOKEN_CLOSE_CODE: u16 = 4101;
+
+impl CloseFrameError for LookupError {
+    fn from_service_close(code: u16, _reason: &str) -> Option<Self> {
+        (code == INVALID_TOKEN_CLOSE_CODE).then_some(Self::InvalidToken)
+    }
 
-    match code {
-        CdsiCloseCode::InvalidArgument => Some(LookupError::InvalidArgument {
-            server_reason: reason.into_owned(),
-        }),
-        CdsiCloseCode::InvalidToken => Some(LookupError::InvalidToken),
-        CdsiCloseCode::RateLimitExceeded => {
-            let RateLimitExceededResponse {
+    fn from_common_close(close: CommonClose) -> Self {
+        match close {
+            CommonClose::InvalidArgument { server_reason } => {
+                Self::InvalidArgument { server_reason }
+            }
+            CommonClose::RateLimited {
                 retry_after_seconds,
-            } = serde_json::from_str(&reason).ok()?;
-            Some(LookupError::RateLimited {
+            } => Self::RateLimited {
                 retry_after_seconds,
-            })
-        }
-        CdsiCloseCode::ServerInternalError | CdsiCloseCode::ServerUnavailable => {
-            Some(LookupError::Server {
-                reason: code.into(),
-            })
+            },
+            CommonClose::Server { reason } => Self::Server { reason },
         }
     }
+
+    fn unexpected_close() -> Self {
+        Self::Protocol
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::num::NonZeroU64;
     use std::time::Duration;
 
     use assert_matches::assert_matches;
@@ -482,7 +607,9 @@ mod test {
 
     use super::*;
     use crate::auth::Auth;
+    use crate::enclave::loopback::{LoopbackEnclave, LoopbackReply};
     use crate::infra::test::shared::InMemoryWarpConnector;
+    use crate::infra::ws::close::RateLimitExceededResponse;
     use crate::infra::ws::testutil::{
         fake_websocket, mock_connection_info, run_attested_server, AttestedServerOutput,
         FAKE_ATTESTATION,
@@ -490,6 +617,57 @@ mod test {
     use crate::infra::ws::WebSocketClient;
     use crate::utils::ObservableEvent;
 
+    #[test]
+    fn discard_e164s_rejected_unless_negotiated() {
+        let e164: E164 = "+18005551001".parse().unwrap();
+        let request = || LookupRequest {
+            discard_e164s: vec![e164],
+            ..Default::default()
+        };
+
+        assert_matches!(
+            request().into_client_request(ProtocolVersion::BASELINE),
+            Err(LookupError::UnsupportedByServer {
+                feature: "discard_e164s",
+                protocol_version: ProtocolVersion::BASELINE,
+            })
+        );
+
+        let negotiated = request()
+            .into_client_request(PROTOCOL_VERSION_DISCARD_E164S)
+            .expect("supported");
+        assert_eq!(negotiated.discard_e164s, e164.to_be_bytes());
+
+        // Requests that don't discard anything work with any server.
+        assert_matches!(
+            LookupRequest::default().into_client_request(ProtocolVersion::BASELINE),
+            Ok(_)
+        );
+    }
+
+    #[test]
+    fn lookup_response_limit_scales_with_request() {
+        let e164: E164 = "+18005551001".parse().unwrap();
+        let request = LookupRequest {
+            new_e164s: vec![e164; 3],
+            prev_e164s: vec![e164; 2],
+            discard_e164s: vec![e164; 100],
+            ..Default::default()
+        };
+        let limits = request.response_limits();
+        assert_eq!(
+            limits.max_message_size,
+            5 * LookupResponseEntry::SERIALIZED_LEN + LOOKUP_RESPONSE_OVERHEAD
+        );
+
+        assert_matches!(
+            LookupError::from(limits.check_size(limits.max_message_size + 1).unwrap_err()),
+            LookupError::WebSocket(WebSocketServiceError::ResponseLimit(
+                ResponseLimitError::ResponseTooLarge { .. }
+            ))
+        );
+    }
+
     #[test]
     fn parse_lookup_response_entries() {
         const ACI_BYTES: [u8; 16] = hex!("0102030405060708a1a2a3a4a5a6a7a8");
@@ -531,11 +709,29 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(not(feature = "unredacted-logs"))]
+    fn lookup_response_debug_is_redacted() {
+        let response = LookupResponse {
+            records: vec![LookupResponseEntry {
+                e164: "+18005551001".parse().unwrap(),
+                aci: Some(Aci::from(Uuid::from_bytes([0x11; 16]))),
+                pni: None,
+            }],
+            debug_permits_used: 42,
+        };
+        assert_eq!(
+            format!("{response:?}"),
+            "LookupResponse { records: [LookupResponseEntry { e164: <redacted>, aci: <redacted>, \
+             pni: <redacted> }], debug_permits_used: 42 }"
+        );
+    }
+
     #[test]
     fn serialize_e164s() {
         let e164s: Vec<E164> = (18005551001..)
             .take(5)
-            .map(|n| E164(NonZeroU64::new(n).unwrap()))
+            .map(|n| E164::new(NonZeroU64::new(n).unwrap()))
             .collect();
         let serialized = e164s.into_iter().collect_serialized();
 
@@ -554,7 +750,7 @@ mod test {
     #[test]
     fn serialize_acis_and_access_keys() {
         let pairs = [1, 2, 3, 4, 5].map(|i| AciAndAccessKey {
-            access_key: [i; 16],
+            access_key: AccessKey::from_bytes([i; 16]),
             aci: Aci::from_uuid_bytes([i | 0x80; 16]),
         });
         let serialized = pairs.into_iter().collect_serialized();
@@ -588,7 +784,7 @@ mod test {
         const RESPONSE_RECORD: LookupResponseEntry = LookupResponseEntry {
             aci: Some(Aci::from_uuid_bytes([b'a'; 16])),
             pni: Some(Pni::from_uuid_bytes([b'p'; 16])),
-            e164: E164(nonzero!(18005550101u64)),
+            e164: E164::new(nonzero!(18005550101u64)),
         };
 
         fn receive_frame(&mut self, frame: &[u8]) -> AttestedServerOutput {
@@ -704,6 +900,42 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn lookup_through_loopback_enclave() {
+        let mut fake_server = FakeServerState::default();
+        let connection = LoopbackEnclave::new(&mut rand::rngs::OsRng)
+            .connect_in_memory(move |frame| {
+                let AttestedServerOutput {
+                    message,
+                    close_after,
+                } = fake_server.receive_frame(&frame);
+                LoopbackReply {
+                    message,
+                    close_after,
+                }
+            })
+            .await
+            .expect("handshake succeeds");
+
+        let (token, collector) = CdsiConnection(connection)
+            .send_request(LookupRequest {
+                new_e164s: vec![FakeServerState::RESPONSE_RECORD.e164],
+                ..Default::default()
+            })
+            .await
+            .expect("request accepted");
+        assert_eq!(&*token.0, FakeServerState::RESPONSE_TOKEN);
+
+        let response = collector.collect().await.expect("successful request");
+        assert_eq!(
+            response,
+            LookupResponse {
+                debug_permits_used: 1,
+                records: vec![FakeServerState::RESPONSE_RECORD],
+            }
+        );
+    }
+
     const RETRY_AFTER_SECS: u32 = 12345;
 
     #[tokio::test]
@@ -821,7 +1053,7 @@ mod test {
         );
         let auth = Auth {
             username: "username".to_string(),
-            password: "password".to_string(),
+            password: "password".to_string().into(),
         };
 
         let result = CdsiConnection::connect(&endpoint_connection, connector, auth).await;
@@ -833,6 +1065,42 @@ mod test {
         )
     }
 
+    #[tokio::test]
+    async fn single_call_lookup_reports_connection_errors() {
+        let h2_server = warp::get().then(|| async move {
+            warp::reply::with_status(
+                warp::reply::with_header("(ignored body)", "Retry-After", "100"),
+                warp::http::StatusCode::TOO_MANY_REQUESTS,
+            )
+        });
+        let connector = InMemoryWarpConnector::new(h2_server);
+
+        let env = crate::env::PROD;
+        let endpoint_connection = EnclaveEndpointConnection::new(
+            &env.cdsi,
+            Duration::from_secs(10),
+            &ObservableEvent::default(),
+        );
+        let auth = Auth {
+            username: "username".to_string(),
+            password: "password".to_string().into(),
+        };
+
+        let result = cdsi_lookup(
+            &endpoint_connection,
+            connector,
+            auth,
+            LookupRequest::default(),
+        )
+        .await;
+        assert_matches!(
+            result,
+            Err(LookupError::RateLimited {
+                retry_after_seconds: 100
+            })
+        )
+    }
+
     #[tokio::test]
     async fn websocket_invalid_token_close() {
         let (server, client) = fake_websocket().await;
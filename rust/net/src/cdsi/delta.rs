@@ -0,0 +1,261 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::{cdsi_lookup, LookupError, LookupRequest, LookupResponse, Token, E164};
+use crate::auth::HttpBasicAuth;
+use crate::enclave::{Cdsi, EnclaveEndpointConnection};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::TransportConnector;
+use crate::utils::Redacted;
+
+/// The numbers looked up by the last successful lookup, and the token the
+/// server issued for them.
+#[derive(Clone)]
+pub struct PreviousLookup {
+    pub e164s: BTreeSet<E164>,
+    pub token: Token,
+}
+
+impl fmt::Debug for PreviousLookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreviousLookup")
+            .field("e164s", &Redacted(&self.e164s))
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+/// Persistent storage for the state carried between incremental lookups.
+pub trait LookupTokenStore {
+    /// Returns the state saved by the last successful lookup, if any.
+    fn load(&self) -> Option<PreviousLookup>;
+    /// Replaces the saved state after a successful lookup.
+    fn save(&mut self, previous: PreviousLookup);
+    /// Forgets the saved state, so that the next lookup starts from scratch.
+    fn clear(&mut self);
+}
+
+/// In-memory storage, for clients that don't persist lookup state.
+impl LookupTokenStore for Option<PreviousLookup> {
+    fn load(&self) -> Option<PreviousLookup> {
+        self.clone()
+    }
+
+    fn save(&mut self, previous: PreviousLookup) {
+        *self = Some(previous);
+    }
+
+    fn clear(&mut self) {
+        *self = None;
+    }
+}
+
+/// Issues the smallest CDSI request for a contact list, given the previous one.
+///
+/// A token lets the server discount numbers that were already looked up, but
+/// only if the client sends exactly those numbers as `prev_e164s`, sends
+/// everything else as `new_e164s`, and saves the new token together with the
+/// full set of numbers it covers. `DeltaLookup` takes care of all of that.
+pub struct DeltaLookup<S> {
+    store: S,
+}
+
+impl<S: LookupTokenStore> DeltaLookup<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Fills in the numbers and token of `request` for looking up `current`.
+    ///
+    /// Numbers that were looked up before are sent as `prev_e164s`, ones that
+    /// weren't as `new_e164s`, and ones that are no longer in the list as
+    /// `discard_e164s`. Without saved state, everything is new and no token is
+    /// sent.
+    pub fn prepare_request(
+        &self,
+        current: &BTreeSet<E164>,
+        request: LookupRequest,
+    ) -> LookupRequest {
+        let Some(PreviousLookup {
+            e164s: previous,
+            token,
+        }) = self.store.load()
+        else {
+            return LookupRequest {
+                new_e164s: current.iter().copied().collect(),
+                prev_e164s: Vec::new(),
+                discard_e164s: Vec::new(),
+                token: Default::default(),
+                ..request
+            };
+        };
+
+        LookupRequest {
+            new_e164s: current.difference(&previous).copied().collect(),
+            prev_e164s: current.intersection(&previous).copied().collect(),
+            discard_e164s: previous.difference(current).copied().collect(),
+            token: token.0,
+            ..request
+        }
+    }
+
+    /// Looks up `current`, reusing and then replacing the saved token.
+    ///
+    /// `request` supplies everything other than the numbers and token, which
+    /// are filled in by [`Self::prepare_request`]. The new token is only saved
+    /// once the full response has been received. If the server rejects the
+    /// saved token, the saved state is cleared so the next lookup starts from
+    /// scratch.
+    pub async fn lookup<C, T>(
+        &mut self,
+        endpoint: &EnclaveEndpointConnection<Cdsi, C>,
+        transport_connector: T,
+        auth: impl HttpBasicAuth,
+        current: BTreeSet<E164>,
+        request: LookupRequest,
+    ) -> Result<LookupResponse, LookupError>
+    where
+        C: ConnectionManager,
+        T: TransportConnector,
+    {
+        let request = self.prepare_request(&current, request);
+        let result = cdsi_lookup(endpoint, transport_connector, auth, request).await;
+        self.record_result(current, result)
+    }
+
+    fn record_result(
+        &mut self,
+        current: BTreeSet<E164>,
+        result: Result<(Token, LookupResponse), LookupError>,
+    ) -> Result<LookupResponse, LookupError> {
+        match result {
+            Ok((token, response)) => {
+                self.store.save(PreviousLookup {
+                    e164s: current,
+                    token,
+                });
+                Ok(response)
+            }
+            Err(LookupError::InvalidToken) => {
+                self.store.clear();
+                Err(LookupError::InvalidToken)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn e164s(numbers: &[&str]) -> BTreeSet<E164> {
+        numbers.iter().map(|n| n.parse().unwrap()).collect()
+    }
+
+    fn empty_response() -> LookupResponse {
+        LookupResponse {
+            records: vec![],
+            debug_permits_used: 0,
+        }
+    }
+
+    #[test]
+    fn first_lookup_sends_everything_as_new() {
+        let delta = DeltaLookup::new(None);
+        let current = e164s(&["+18005550101", "+18005550102"]);
+
+        let request = delta.prepare_request(&current, LookupRequest::default());
+
+        assert_eq!(request.new_e164s, Vec::from_iter(current));
+        assert!(request.prev_e164s.is_empty());
+        assert!(request.discard_e164s.is_empty());
+        assert!(request.token.is_empty());
+    }
+
+    #[test]
+    fn later_lookup_sends_diffs_and_token() {
+        let delta = DeltaLookup::new(Some(PreviousLookup {
+            e164s: e164s(&["+18005550101", "+18005550102"]),
+            token: Token(b"token".as_slice().into()),
+        }));
+        let current = e164s(&["+18005550102", "+18005550103"]);
+
+        let request = delta.prepare_request(
+            &current,
+            LookupRequest {
+                return_acis_without_uaks: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(request.new_e164s, Vec::from_iter(e164s(&["+18005550103"])));
+        assert_eq!(request.prev_e164s, Vec::from_iter(e164s(&["+18005550102"])));
+        assert_eq!(
+            request.discard_e164s,
+            Vec::from_iter(e164s(&["+18005550101"]))
+        );
+        assert_eq!(&*request.token, b"token");
+        assert!(request.return_acis_without_uaks);
+    }
+
+    #[test]
+    fn success_saves_new_token_and_full_set() {
+        let mut delta = DeltaLookup::new(Some(PreviousLookup {
+            e164s: e164s(&["+18005550101"]),
+            token: Token(b"old".as_slice().into()),
+        }));
+        let current = e164s(&["+18005550101", "+18005550102"]);
+
+        delta
+            .record_result(
+                current.clone(),
+                Ok((Token(b"new".as_slice().into()), empty_response())),
+            )
+            .expect("success");
+
+        let saved = delta.into_store().expect("saved");
+        assert_eq!(saved.e164s, current);
+        assert_eq!(&*saved.token.0, b"new");
+    }
+
+    #[test]
+    fn invalid_token_clears_saved_state() {
+        let mut delta = DeltaLookup::new(Some(PreviousLookup {
+            e164s: e164s(&["+18005550101"]),
+            token: Token(b"old".as_slice().into()),
+        }));
+
+        assert_matches!(
+            delta.record_result(e164s(&["+18005550101"]), Err(LookupError::InvalidToken)),
+            Err(LookupError::InvalidToken)
+        );
+        assert!(delta.into_store().is_none());
+    }
+
+    #[test]
+    fn other_failures_keep_saved_state() {
+        let mut delta = DeltaLookup::new(Some(PreviousLookup {
+            e164s: e164s(&["+18005550101"]),
+            token: Token(b"old".as_slice().into()),
+        }));
+
+        assert_matches!(
+            delta.record_result(e164s(&["+18005550102"]), Err(LookupError::Protocol)),
+            Err(LookupError::Protocol)
+        );
+        let saved = delta.into_store().expect("kept");
+        assert_eq!(saved.e164s, e164s(&["+18005550101"]));
+    }
+}
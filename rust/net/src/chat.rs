@@ -16,7 +16,7 @@ use crate::infra::connection_manager::MultiRouteConnectionManager;
 use crate::infra::service::{Service, ServiceConnectorWithDecorator};
 use crate::infra::ws::WebSocketClientConnector;
 use crate::infra::{
-    make_ws_config, ConnectionInfo, EndpointConnection, HttpRequestDecorator, IpType,
+    make_ws_config, ConnectionInfo, EndpointConnection, HttpRequestDecorator, IpType, TrafficClass,
     TransportConnector,
 };
 use crate::proto;
@@ -398,7 +398,7 @@ fn build_authorized_chat_service(
     let mut header_map = HeaderMap::new();
     header_map.insert(
         http::header::AUTHORIZATION,
-        basic_authorization(&auth.username, &auth.password),
+        basic_authorization(&auth.username, auth.password.expose()),
     );
     header_map.insert(
         HeaderName::from_static(RECEIVE_STORIES_HEADER_NAME),
@@ -482,8 +482,26 @@ pub fn endpoint_connection(
     user_agent: &str,
     network_change_event: &crate::utils::ObservableEvent,
 ) -> EndpointConnection<MultiRouteConnectionManager> {
-    let chat_endpoint = PathAndQuery::from_static(crate::env::constants::WEB_SOCKET_PATH);
-    let chat_connection_params = chat_domain_config.connection_params_with_fallback();
+    endpoint_connection_for_path(
+        crate::env::constants::WEB_SOCKET_PATH,
+        chat_domain_config,
+        user_agent,
+        network_change_event,
+    )
+}
+
+pub(crate) fn endpoint_connection_for_path(
+    path: &'static str,
+    chat_domain_config: &DomainConfig,
+    user_agent: &str,
+    network_change_event: &crate::utils::ObservableEvent,
+) -> EndpointConnection<MultiRouteConnectionManager> {
+    let chat_endpoint = PathAndQuery::from_static(path);
+    let chat_connection_params = chat_domain_config
+        .connection_params_with_fallback()
+        .into_iter()
+        .map(|params| params.with_traffic_class(TrafficClass::INTERACTIVE))
+        .collect();
     let chat_connection_params = add_user_agent_header(chat_connection_params, user_agent);
     let chat_ws_config = make_ws_config(chat_endpoint, ONE_ROUTE_CONNECTION_TIMEOUT);
     EndpointConnection::new_multi(
@@ -550,6 +568,93 @@ pub mod test_support {
     }
 }
 
+#[cfg(test)]
+pub(crate) mod testutil {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use http::{HeaderMap, StatusCode};
+
+    use crate::chat::{ChatService, ChatServiceError, Request, Response};
+
+    /// A [`ChatService`] that records every request and answers it with a
+    /// caller-provided function.
+    pub(crate) struct FakeChat {
+        respond: Box<dyn Fn(&Request) -> Response + Send + Sync>,
+        requests: Mutex<Vec<Request>>,
+    }
+
+    impl FakeChat {
+        pub(crate) fn new(respond: impl Fn(&Request) -> Response + Send + Sync + 'static) -> Self {
+            Self {
+                respond: Box::new(respond),
+                requests: Mutex::default(),
+            }
+        }
+
+        /// Answers every request with `response`.
+        pub(crate) fn responding(response: Response) -> Self {
+            Self::new(move |_| response.clone())
+        }
+
+        /// Answers requests with `responses` in order, panicking if it runs
+        /// out.
+        pub(crate) fn responding_in_order(responses: impl IntoIterator<Item = Response>) -> Self {
+            let responses = Mutex::new(VecDeque::from_iter(responses));
+            Self::new(move |_| {
+                responses
+                    .lock()
+                    .expect("not poisoned")
+                    .pop_front()
+                    .expect("enough responses")
+            })
+        }
+
+        /// The requests sent so far, oldest first.
+        pub(crate) fn requests(&self) -> Vec<Request> {
+            self.requests.lock().expect("not poisoned").clone()
+        }
+
+        pub(crate) fn request_paths(&self) -> Vec<String> {
+            self.requests()
+                .iter()
+                .map(|request| request.path.to_string())
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl ChatService for FakeChat {
+        async fn send(
+            &self,
+            msg: Request,
+            _timeout: Duration,
+        ) -> Result<Response, ChatServiceError> {
+            let response = (self.respond)(&msg);
+            self.requests.lock().expect("not poisoned").push(msg);
+            Ok(response)
+        }
+
+        async fn connect(&self) -> Result<(), ChatServiceError> {
+            Ok(())
+        }
+
+        async fn disconnect(&self) {}
+    }
+
+    /// A response with `body` serialized as JSON.
+    pub(crate) fn json_response(status: StatusCode, body: &serde_json::Value) -> Response {
+        Response {
+            status,
+            message: None,
+            body: Some(body.to_string().into_bytes().into_boxed_slice()),
+            headers: HeaderMap::new(),
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     use assert_matches::assert_matches;
@@ -573,7 +678,7 @@ pub(crate) mod test {
         use crate::infra::host::Host;
         use crate::infra::service::{ServiceConnector, ServiceState};
         use crate::infra::test::shared::{NoReconnectService, TIMEOUT_DURATION};
-        use crate::infra::{ConnectionParams, RouteType, TransportConnectionParams};
+        use crate::infra::{ConnectionParams, RouteType, TrafficClass, TransportConnectionParams};
         use crate::utils::ObservableEvent;
 
         #[async_trait]
@@ -628,6 +733,8 @@ pub(crate) mod test {
                         tcp_host: host,
                         port: nonzero!(443u16),
                         certs: RootCertificates::Signal,
+                        proxy: None,
+                        traffic_class: TrafficClass::default(),
                     },
                     http_host: hostname,
                     http_request_decorator: Default::default(),
@@ -4,7 +4,7 @@
 //
 
 use crate::infra::connection_manager::{ErrorClass, ErrorClassifier};
-use crate::infra::errors::{LogSafeDisplay, TransportConnectError};
+use crate::infra::errors::{LogSafeDisplay, RetryLater, TransportConnectError};
 use crate::infra::service;
 use crate::infra::ws::{WebSocketConnectError, WebSocketServiceError};
 
@@ -38,6 +38,8 @@ pub enum ChatServiceError {
     ServiceUnavailable,
     /// Service was disconnected by an intentional local call
     ServiceIntentionallyDisconnected,
+    /// {0}
+    RetryLater(RetryLater),
 }
 
 impl LogSafeDisplay for ChatServiceError {}
@@ -50,6 +52,9 @@ impl From<WebSocketServiceError> for ChatServiceError {
 
 impl From<WebSocketConnectError> for ChatServiceError {
     fn from(e: WebSocketConnectError) -> Self {
+        if let Some(retry_later) = e.retry_later() {
+            return Self::RetryLater(retry_later);
+        }
         if !matches!(e.classify(), ErrorClass::Fatal) {
             log::warn!(
                 "intermittent WebSocketConnectError should be retried, not returned as a ChatServiceError ({e})"
@@ -105,6 +110,7 @@ impl<E: LogSafeDisplay + Into<ChatServiceError>> From<service::ConnectError<E>>
                 Self::AllConnectionRoutesFailed { attempts }
             }
             service::ConnectError::RejectedByServer(e) => e.into(),
+            service::ConnectError::RetryLater(retry_later) => Self::RetryLater(retry_later),
         }
     }
 }
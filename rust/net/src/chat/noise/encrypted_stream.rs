@@ -10,7 +10,6 @@ use std::task::{ready, Context, Poll};
 use futures_util::FutureExt;
 use libsignal_core::Aci;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
-use uuid::Uuid;
 use zerocopy::{AsBytes, FromBytes, FromZeroes};
 
 use super::handshake::{HandshakeAuth, Handshaker, EPHEMERAL_KEY_LEN, STATIC_KEY_LEN};
@@ -131,17 +130,14 @@ impl<S: Transport + Unpin> AsyncRead for EncryptedStream<S> {
 #[repr(C)]
 #[derive(Debug, PartialEq, AsBytes, FromZeroes, FromBytes)]
 struct InitialPayloadAuth {
-    uuid_high: zerocopy::big_endian::U64,
-    uuid_low: zerocopy::big_endian::U64,
+    aci: uuid::Bytes,
     device_id: u8,
 }
 
 impl InitialPayloadAuth {
     fn new(aci: Aci, device_id: u8) -> Self {
-        let (uuid_high, uuid_low) = Uuid::from(aci).as_u64_pair();
         Self {
-            uuid_high: uuid_high.into(),
-            uuid_low: uuid_low.into(),
+            aci: *aci.raw_uuid_bytes(),
             device_id,
         }
     }
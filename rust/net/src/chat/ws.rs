@@ -28,6 +28,7 @@ use crate::infra::ws::{
 };
 use crate::infra::{AsyncDuplexStream, ConnectionInfo, ConnectionParams, TransportConnector};
 use crate::proto::chat_websocket::web_socket_message::Type;
+use crate::proto::limits::{DecodeError, DecodeLimits};
 
 #[derive(Debug, Default, Eq, Hash, PartialEq, Clone, Copy)]
 struct RequestId {
@@ -161,7 +162,7 @@ impl<T: TransportConnector> ChatOverWebSocketServiceConnector<T> {
 #[async_trait]
 impl<T: TransportConnector> ServiceConnector for ChatOverWebSocketServiceConnector<T> {
     type Service = ChatOverWebSocket<T::Stream>;
-    type Channel = (WebSocketStream<T::Stream>, ConnectionInfo);
+    type Channel = (WebSocketStream<T::Stream>, ConnectionInfo, http::HeaderMap);
     type ConnectError = WebSocketConnectError;
 
     async fn connect_channel(
@@ -179,15 +180,25 @@ impl<T: TransportConnector> ServiceConnector for ChatOverWebSocketServiceConnect
             ws_client_writer,
             ws_client_reader,
             connection_info,
+            upgrade_response_headers: _,
+            response_limits: _,
         } = ws_client;
         let pending_messages: Arc<Mutex<PendingMessagesMap>> = Default::default();
-        tokio::spawn(reader_task(
+        let reader = reader_task(
             ws_client_reader,
             ws_client_writer.clone(),
             self.incoming_tx.clone(),
             pending_messages.clone(),
             service_status.clone(),
-        ));
+        );
+        // Give the connection its own root span, so that server requests aren't
+        // attributed to whichever client request happened to open it.
+        #[cfg(feature = "tracing")]
+        let reader = tracing::Instrument::instrument(
+            reader,
+            tracing::info_span!(parent: None, "chat_connection"),
+        );
+        tokio::spawn(reader);
         (
             ChatOverWebSocket {
                 ws_client_writer,
@@ -323,6 +334,16 @@ impl<S> ChatService for ChatOverWebSocket<S>
 where
     S: AsyncDuplexStream,
 {
+    // The path is left out because it can contain account identifiers.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "chat_request",
+            skip_all,
+            fields(method = %msg.method, id = tracing::field::Empty),
+            err(Display),
+        )
+    )]
     async fn send(&self, msg: Request, timeout: Duration) -> Result<Response, ChatServiceError> {
         // checking if channel has been closed
         if self.service_cancellation.is_cancelled() {
@@ -339,6 +360,8 @@ where
             map.insert(response_tx)
                 .map_err(|_| WebSocketServiceError::ChannelClosed)?
         };
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("id", id.id);
 
         let msg = request_to_websocket_proto(msg, id)
             .map_err(|_| ChatServiceError::RequestHasInvalidHeader)?;
@@ -366,8 +389,23 @@ where
     }
 }
 
+/// Limits on messages from the chat server.
+///
+/// Requests carry envelopes, whose contents the server caps well below this,
+/// and responses carry API results, none of which come close. Neither needs
+/// more than a handful of headers.
+const INCOMING_MESSAGE_LIMITS: DecodeLimits = DecodeLimits {
+    max_message_size: 8 << 20,
+    max_repeated_field_len: 128,
+};
+
 fn decode_and_validate(data: &[u8]) -> Result<ChatMessage, ChatServiceError> {
-    let msg = MessageProto::decode(data).map_err(|_| ChatServiceError::IncomingDataInvalid)?;
+    let msg: MessageProto = INCOMING_MESSAGE_LIMITS.decode(data).map_err(|e| match e {
+        DecodeError::Limit(e) => {
+            ChatServiceError::WebSocket(WebSocketServiceError::ResponseLimit(e))
+        }
+        DecodeError::Invalid(_) => ChatServiceError::IncomingDataInvalid,
+    })?;
     // we want to guarantee that the message is either request or response
     match (
         msg.r#type
@@ -459,6 +497,7 @@ mod test {
             max_connection_time: Duration::from_secs(1),
             keep_alive_interval: Duration::from_secs(5),
             max_idle_time: Duration::from_secs(15),
+            response_limits: Default::default(),
         }
     }
 
@@ -604,6 +643,117 @@ mod test {
         );
     }
 
+    /// Records every span and its fields, ignoring events.
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct RecordedSpans(Arc<std::sync::Mutex<Vec<(&'static str, Vec<(&'static str, String)>)>>>);
+
+    #[cfg(feature = "tracing")]
+    impl RecordedSpans {
+        fn fields_of(&self, name: &str) -> Vec<(&'static str, String)> {
+            self.0
+                .lock()
+                .expect("not poisoned")
+                .iter()
+                .find(|(span_name, _)| *span_name == name)
+                .unwrap_or_else(|| panic!("no {name} span"))
+                .1
+                .clone()
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    struct FieldRecorder<'a>(&'a mut Vec<(&'static str, String)>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for FieldRecorder<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn Debug) {
+            self.0.push((field.name(), format!("{value:?}")));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for RecordedSpans {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut spans = self.0.lock().expect("not poisoned");
+            let mut fields = vec![];
+            span.record(&mut FieldRecorder(&mut fields));
+            spans.push((span.metadata().name(), fields));
+            tracing::span::Id::from_u64(spans.len().try_into().expect("fits"))
+        }
+
+        fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            let mut spans = self.0.lock().expect("not poisoned");
+            let index = usize::try_from(span.into_u64()).expect("fits") - 1;
+            values.record(&mut FieldRecorder(&mut spans[index].1));
+        }
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, _event: &tracing::Event<'_>) {}
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ws_service_spans_leave_out_paths() {
+        let spans = RecordedSpans::default();
+        let _guard = tracing::subscriber::set_default(spans.clone());
+
+        let (ws_server, server_res_rx) = ws_warp_filter(move |websocket| async move {
+            let (mut tx, mut rx) = websocket.split();
+            let msg = rx
+                .next()
+                .await
+                .expect("stream should not be closed")
+                .expect("should be Ok");
+            let request = decode_and_validate(msg.as_bytes()).expect("chat message");
+            let message_proto =
+                response_for_request(&request, StatusCode::OK).expect("not an error");
+            tx.send(warp::ws::Message::binary(message_proto.encode_to_vec()))
+                .await
+                .expect("can send");
+        });
+
+        let (ws_chat, _incoming_rx) = create_ws_chat_service(test_ws_config(), ws_server).await;
+        ws_chat
+            .send(test_request(Method::PUT, "/v1/secret"), TIMEOUT_DURATION)
+            .await
+            .expect("response");
+        validate_server_stopped_successfully(server_res_rx).await;
+
+        let connect_fields = spans.fields_of("connect_websocket");
+        assert!(
+            connect_fields.iter().any(|(name, _)| *name == "host"),
+            "{connect_fields:?}"
+        );
+        let request_fields = spans.fields_of("chat_request");
+        assert!(
+            request_fields.contains(&("method", "PUT".to_owned())),
+            "{request_fields:?}"
+        );
+        assert!(
+            request_fields.iter().any(|(name, _)| *name == "id"),
+            "{request_fields:?}"
+        );
+
+        for (name, fields) in spans.0.lock().expect("not poisoned").iter() {
+            for (field, value) in fields {
+                assert!(
+                    !value.contains("/test") && !value.contains("/v1/secret"),
+                    "{name} span has path in {field}: {value}"
+                );
+            }
+        }
+    }
+
     #[tokio::test(flavor = "current_thread", start_paused = true)]
     async fn ws_service_times_out_on_late_response_from_server() {
         // creating a server that responds to requests with 200
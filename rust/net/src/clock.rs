@@ -0,0 +1,139 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! The source of wall-clock time for expiration and validity checks.
+//!
+//! Certificate and credential expirations, as well as the validity periods
+//! checked during enclave attestation, are compared against the time from a
+//! [`Clock`]. By default that's the [`SystemClock`]; apps that know the
+//! device's clock is wrong can supply an [`OffsetClock`] with an NTP-derived
+//! correction instead, and tests can use a [`ManualClock`] to simulate expiry
+//! and skew.
+//!
+//! Timeouts and retry delays don't use a `Clock`; they're measured with
+//! tokio's monotonic clock, which tests can control with
+//! [`tokio::time::pause`].
+
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The device's own clock.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Returns the [`SystemClock`], shared.
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// The device's clock, corrected by an offset that can be updated at any time.
+#[derive(Debug, Default)]
+pub struct OffsetClock {
+    offset_millis: AtomicI64,
+}
+
+impl OffsetClock {
+    /// Creates a clock that is `offset_millis` ahead of the device's clock, or
+    /// behind it if negative.
+    pub fn new(offset_millis: i64) -> Self {
+        Self {
+            offset_millis: AtomicI64::new(offset_millis),
+        }
+    }
+
+    pub fn set_offset_millis(&self, offset_millis: i64) {
+        self.offset_millis.store(offset_millis, Ordering::Relaxed)
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> SystemTime {
+        let now = SystemTime::now();
+        let offset_millis = self.offset_millis.load(Ordering::Relaxed);
+        let offset = Duration::from_millis(offset_millis.unsigned_abs());
+        if offset_millis >= 0 {
+            now + offset
+        } else {
+            now - offset
+        }
+    }
+}
+
+/// A clock that only moves when told to.
+#[cfg(any(test, feature = "test-support"))]
+#[derive(Debug)]
+pub struct ManualClock {
+    now: std::sync::Mutex<SystemTime>,
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl ManualClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: std::sync::Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().expect("not poisoned") = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().expect("not poisoned") += by;
+    }
+}
+
+#[cfg(any(test, feature = "test-support"))]
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().expect("not poisoned")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn offset_clock_applies_offset() {
+        let clock = OffsetClock::new(HOUR.as_millis() as i64);
+        let ahead = clock
+            .now()
+            .duration_since(SystemTime::now())
+            .expect("ahead");
+        assert!(ahead > HOUR - Duration::from_secs(60), "{ahead:?}");
+
+        clock.set_offset_millis(-(HOUR.as_millis() as i64));
+        let behind = SystemTime::now()
+            .duration_since(clock.now())
+            .expect("behind");
+        assert!(behind >= HOUR, "{behind:?}");
+    }
+
+    #[test]
+    fn manual_clock_moves_when_told() {
+        let start = SystemTime::UNIX_EPOCH + 1000 * HOUR;
+        let clock = ManualClock::new(start);
+        assert_eq!(clock.now(), start);
+        clock.advance(HOUR);
+        assert_eq!(clock.now(), start + HOUR);
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+}
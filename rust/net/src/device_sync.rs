@@ -0,0 +1,583 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Payloads the primary device sends to a device right after linking it.
+//!
+//! The contacts sync is a stream of [`SyncContact`]s, serialized with
+//! [`write_contacts`] and uploaded as an attachment encrypted with
+//! [`SyncAttachmentEncryptor`]. The linked device downloads it through a
+//! [`SyncAttachmentDecryptingSink`] and parses it with [`parse_contacts`]. The
+//! key sync is a single [`SyncKeys`] message.
+
+use std::fmt;
+
+use aes::Aes256;
+use cbc::cipher::generic_array::GenericArray;
+use cbc::cipher::{BlockDecryptMut as _, BlockEncryptMut as _, KeyIvInit as _};
+use hmac::{Hmac, Mac as _};
+use libsignal_core::{Aci, ServiceId, E164};
+use prost::Message as _;
+use rand::{CryptoRng, Rng};
+use sha2::{Digest as _, Sha256};
+use subtle::ConstantTimeEq as _;
+
+use crate::attachments::{DownloadSink, TransferError};
+use crate::proto::device_sync::{contact_details, ContactDetails, Keys};
+use crate::utils::Redacted;
+
+const BLOCK_LEN: usize = 16;
+const MAC_LEN: usize = 32;
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum DeviceSyncError {
+    /// invalid contacts sync: {0}
+    InvalidContacts(&'static str),
+    /// invalid keys sync: {0}
+    InvalidKeys(&'static str),
+}
+
+/// A contact as sent in a contacts sync.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SyncContact {
+    pub aci: Option<Aci>,
+    pub number: Option<E164>,
+    pub name: Option<String>,
+    pub profile_key: Option<[u8; 32]>,
+    pub expire_timer: Option<u32>,
+    pub inbox_position: Option<u32>,
+    pub avatar: Option<SyncAvatar>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyncAvatar {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl fmt::Debug for SyncContact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncContact")
+            .field("aci", &Redacted(&self.aci))
+            .field("number", &Redacted(&self.number))
+            .field("name", &Redacted(&self.name))
+            .field("profile_key", &self.profile_key.map(|_| "<redacted>"))
+            .field("expire_timer", &self.expire_timer)
+            .field("inbox_position", &self.inbox_position)
+            .field("avatar", &self.avatar)
+            .finish()
+    }
+}
+
+impl fmt::Debug for SyncAvatar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncAvatar")
+            .field("content_type", &self.content_type)
+            .field("data", &format_args!("{} bytes", self.data.len()))
+            .finish()
+    }
+}
+
+/// Serializes `contacts` in the contacts sync format.
+pub fn write_contacts<'a>(contacts: impl IntoIterator<Item = &'a SyncContact>) -> Vec<u8> {
+    let mut output = Vec::new();
+    for contact in contacts {
+        let SyncContact {
+            aci,
+            number,
+            name,
+            profile_key,
+            expire_timer,
+            inbox_position,
+            avatar,
+        } = contact;
+        ContactDetails {
+            number: number.map(|number| number.to_string()),
+            name: name.clone(),
+            avatar: avatar.as_ref().map(|avatar| contact_details::Avatar {
+                content_type: Some(avatar.content_type.clone()),
+                length: Some(
+                    avatar
+                        .data
+                        .len()
+                        .try_into()
+                        .expect("avatars are smaller than 4GB"),
+                ),
+            }),
+            profile_key: profile_key.map(Vec::from),
+            expire_timer: *expire_timer,
+            aci: aci.map(|aci| aci.service_id_string()),
+            inbox_position: *inbox_position,
+        }
+        .encode_length_delimited(&mut output)
+        .expect("Vec has unlimited capacity");
+        if let Some(avatar) = avatar {
+            output.extend_from_slice(&avatar.data);
+        }
+    }
+    output
+}
+
+/// Parses a decrypted contacts sync attachment.
+pub fn parse_contacts(mut input: &[u8]) -> Result<Vec<SyncContact>, DeviceSyncError> {
+    use DeviceSyncError::InvalidContacts;
+
+    let mut contacts = Vec::new();
+    while !input.is_empty() {
+        let ContactDetails {
+            number,
+            name,
+            avatar,
+            profile_key,
+            expire_timer,
+            aci,
+            inbox_position,
+        } = ContactDetails::decode_length_delimited(&mut input)
+            .map_err(|_| InvalidContacts("malformed contact details"))?;
+
+        let avatar = match avatar {
+            None => None,
+            Some(contact_details::Avatar {
+                content_type,
+                length,
+            }) => {
+                let length = usize::try_from(length.unwrap_or_default())
+                    .map_err(|_| InvalidContacts("avatar too large"))?;
+                if input.len() < length {
+                    return Err(InvalidContacts("truncated avatar"));
+                }
+                let (data, rest) = input.split_at(length);
+                input = rest;
+                Some(SyncAvatar {
+                    content_type: content_type.unwrap_or_default(),
+                    data: data.to_vec(),
+                })
+            }
+        };
+
+        contacts.push(SyncContact {
+            aci: aci
+                .map(|aci| ServiceId::parse_as_aci(&aci))
+                .transpose()
+                .map_err(|_| InvalidContacts("invalid ACI"))?,
+            number: number
+                .map(|number| number.parse())
+                .transpose()
+                .map_err(|_| InvalidContacts("invalid number"))?,
+            name,
+            profile_key: profile_key
+                .map(|key| key.try_into())
+                .transpose()
+                .map_err(|_| InvalidContacts("invalid profile key"))?,
+            expire_timer,
+            inbox_position,
+            avatar,
+        });
+    }
+    Ok(contacts)
+}
+
+/// The keys sent to a newly-linked device.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SyncKeys {
+    pub storage_service_key: Option<[u8; 32]>,
+    pub master_key: Option<[u8; 32]>,
+}
+
+impl fmt::Debug for SyncKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncKeys")
+            .field(
+                "storage_service_key",
+                &self.storage_service_key.map(|_| "<redacted>"),
+            )
+            .field("master_key", &self.master_key.map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl SyncKeys {
+    pub fn serialize(&self) -> Vec<u8> {
+        Keys {
+            storage_service: self.storage_service_key.map(Vec::from),
+            master: self.master_key.map(Vec::from),
+        }
+        .encode_to_vec()
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, DeviceSyncError> {
+        use DeviceSyncError::InvalidKeys;
+
+        let Keys {
+            storage_service,
+            master,
+        } = Keys::decode(bytes).map_err(|_| InvalidKeys("malformed keys"))?;
+        Ok(Self {
+            storage_service_key: storage_service
+                .map(|key| key.try_into())
+                .transpose()
+                .map_err(|_| InvalidKeys("invalid storage service key"))?,
+            master_key: master
+                .map(|key| key.try_into())
+                .transpose()
+                .map_err(|_| InvalidKeys("invalid master key"))?,
+        })
+    }
+}
+
+/// The keys for a sync attachment, sent alongside its pointer.
+#[derive(Clone)]
+pub struct SyncAttachmentKeys {
+    aes_key: [u8; 32],
+    mac_key: [u8; 32],
+}
+
+impl SyncAttachmentKeys {
+    pub fn generate<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        Self::from_bytes(&rng.gen())
+    }
+
+    pub fn from_bytes(bytes: &[u8; 64]) -> Self {
+        let (aes_key, mac_key) = bytes.split_at(32);
+        Self {
+            aes_key: aes_key.try_into().expect("split in half"),
+            mac_key: mac_key.try_into().expect("split in half"),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&self.aes_key);
+        bytes[32..].copy_from_slice(&self.mac_key);
+        bytes
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::new_from_slice(&self.mac_key).expect("HMAC can take key of any size")
+    }
+}
+
+impl fmt::Debug for SyncAttachmentKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SyncAttachmentKeys { <redacted> }")
+    }
+}
+
+/// Encrypts a sync attachment piece by piece.
+///
+/// The output is the IV, the AES-256-CBC ciphertext, and an HMAC-SHA256 of
+/// both, so the attachment never needs to be in memory all at once.
+pub struct SyncAttachmentEncryptor {
+    encryptor: cbc::Encryptor<Aes256>,
+    mac: Hmac<Sha256>,
+    digest: Sha256,
+    partial_block: Vec<u8>,
+    header: Option<[u8; BLOCK_LEN]>,
+}
+
+impl SyncAttachmentEncryptor {
+    pub fn new<R: Rng + CryptoRng>(keys: &SyncAttachmentKeys, rng: &mut R) -> Self {
+        Self::with_iv(keys, rng.gen())
+    }
+
+    fn with_iv(keys: &SyncAttachmentKeys, iv: [u8; BLOCK_LEN]) -> Self {
+        Self {
+            encryptor: cbc::Encryptor::new(&keys.aes_key.into(), &iv.into()),
+            mac: keys.mac(),
+            digest: Sha256::new(),
+            partial_block: Vec::with_capacity(BLOCK_LEN),
+            header: Some(iv),
+        }
+    }
+
+    /// Encrypts the next piece of plaintext, returning the ciphertext that is
+    /// ready so far.
+    pub fn update(&mut self, plaintext: &[u8]) -> Vec<u8> {
+        let mut output = self.header.take().map(Vec::from).unwrap_or_default();
+        let start = output.len();
+        self.partial_block.extend_from_slice(plaintext);
+        let full_len = self.partial_block.len() - self.partial_block.len() % BLOCK_LEN;
+        output.extend(self.partial_block.drain(..full_len));
+        self.encrypt_blocks(&mut output[start..]);
+        self.authenticate(&output);
+        output
+    }
+
+    /// Pads and encrypts whatever is left, returning the rest of the ciphertext
+    /// and the SHA-256 digest of the full ciphertext.
+    pub fn finish(mut self) -> (Vec<u8>, [u8; 32]) {
+        let mut output = self.update(&[]);
+        let start = output.len();
+        let padding = BLOCK_LEN - self.partial_block.len();
+        output.append(&mut self.partial_block);
+        output.resize(output.len() + padding, padding as u8);
+        self.encrypt_blocks(&mut output[start..]);
+        self.authenticate(&output[start..]);
+
+        let mac = self.mac.finalize().into_bytes();
+        self.digest.update(mac);
+        output.extend_from_slice(&mac);
+        (output, self.digest.finalize().into())
+    }
+
+    fn encrypt_blocks(&mut self, blocks: &mut [u8]) {
+        for block in blocks.chunks_exact_mut(BLOCK_LEN) {
+            self.encryptor
+                .encrypt_block_mut(GenericArray::from_mut_slice(block));
+        }
+    }
+
+    fn authenticate(&mut self, output: &[u8]) {
+        self.mac.update(output);
+        self.digest.update(output);
+    }
+}
+
+/// [`DownloadSink`] that decrypts a sync attachment as it is downloaded.
+///
+/// Plaintext is passed on before the MAC at the end has been checked, so, as
+/// with [`crate::attachments::VerifyingSink`], the inner sink should be
+/// discarded if the download fails.
+pub struct SyncAttachmentDecryptingSink<S> {
+    inner: S,
+    keys: SyncAttachmentKeys,
+    expected_digest: Option<[u8; 32]>,
+    decryptor: Option<cbc::Decryptor<Aes256>>,
+    mac: Hmac<Sha256>,
+    digest: Sha256,
+    buffer: Vec<u8>,
+    last_block: Option<[u8; BLOCK_LEN]>,
+}
+
+impl<S: DownloadSink> SyncAttachmentDecryptingSink<S> {
+    /// Creates a sink; if `expected_digest` is given, the ciphertext must also
+    /// match it.
+    pub fn new(inner: S, keys: SyncAttachmentKeys, expected_digest: Option<[u8; 32]>) -> Self {
+        let mac = keys.mac();
+        Self {
+            inner,
+            keys,
+            expected_digest,
+            decryptor: None,
+            mac,
+            digest: Sha256::new(),
+            buffer: Vec::new(),
+            last_block: None,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: DownloadSink> DownloadSink for SyncAttachmentDecryptingSink<S> {
+    fn write(&mut self, chunk: &[u8]) -> Result<(), TransferError> {
+        self.digest.update(chunk);
+        self.buffer.extend_from_slice(chunk);
+
+        if self.decryptor.is_none() {
+            if self.buffer.len() < BLOCK_LEN {
+                return Ok(());
+            }
+            let iv: [u8; BLOCK_LEN] = self.buffer[..BLOCK_LEN].try_into().expect("correct length");
+            self.mac.update(&iv);
+            self.buffer.drain(..BLOCK_LEN);
+            self.decryptor = Some(cbc::Decryptor::new(&self.keys.aes_key.into(), &iv.into()));
+        }
+        let decryptor = self.decryptor.as_mut().expect("initialized above");
+
+        // Hold back the MAC, which may already be partially in the buffer.
+        let available = self.buffer.len().saturating_sub(MAC_LEN);
+        let mut blocks: Vec<u8> = self
+            .buffer
+            .drain(..available - available % BLOCK_LEN)
+            .collect();
+        self.mac.update(&blocks);
+        for block in blocks.chunks_exact_mut(BLOCK_LEN) {
+            decryptor.decrypt_block_mut(GenericArray::from_mut_slice(block));
+        }
+
+        // Hold back the last block too, since it contains the padding.
+        let Some(last_start) = blocks.len().checked_sub(BLOCK_LEN) else {
+            return Ok(());
+        };
+        let last_block = blocks[last_start..].try_into().expect("correct length");
+        if let Some(previous) = self.last_block.replace(last_block) {
+            self.inner.write(&previous)?;
+        }
+        self.inner.write(&blocks[..last_start])
+    }
+
+    fn finish(&mut self) -> Result<(), TransferError> {
+        let their_mac = std::mem::take(&mut self.buffer);
+        if their_mac.len() != MAC_LEN {
+            return Err(TransferError::IntegrityCheckFailed);
+        }
+        std::mem::replace(&mut self.mac, self.keys.mac())
+            .verify_slice(&their_mac)
+            .map_err(|_| TransferError::IntegrityCheckFailed)?;
+        if let Some(expected_digest) = &self.expected_digest {
+            let digest = std::mem::take(&mut self.digest).finalize();
+            if !bool::from(digest.ct_eq(expected_digest)) {
+                return Err(TransferError::IntegrityCheckFailed);
+            }
+        }
+
+        let last_block = self
+            .last_block
+            .take()
+            .ok_or(TransferError::IntegrityCheckFailed)?;
+        let padding = usize::from(last_block[BLOCK_LEN - 1]);
+        if !(1..=BLOCK_LEN).contains(&padding)
+            || last_block[BLOCK_LEN - padding..]
+                .iter()
+                .any(|&b| usize::from(b) != padding)
+        {
+            return Err(TransferError::IntegrityCheckFailed);
+        }
+        self.inner.write(&last_block[..BLOCK_LEN - padding])?;
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use rand::rngs::OsRng;
+    use test_case::test_case;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn example_contacts() -> Vec<SyncContact> {
+        vec![
+            SyncContact {
+                aci: Some(Aci::from(Uuid::from_bytes([0xaa; 16]))),
+                number: Some("+18005550101".parse().unwrap()),
+                name: Some("Alice".to_owned()),
+                profile_key: Some([1; 32]),
+                expire_timer: Some(3600),
+                inbox_position: Some(2),
+                avatar: Some(SyncAvatar {
+                    content_type: "image/png".to_owned(),
+                    data: vec![0x89, b'P', b'N', b'G'],
+                }),
+            },
+            SyncContact {
+                number: Some("+18005550102".parse().unwrap()),
+                ..Default::default()
+            },
+            SyncContact {
+                aci: Some(Aci::from(Uuid::from_bytes([0xbb; 16]))),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn contacts_round_trip() {
+        let contacts = example_contacts();
+        let serialized = write_contacts(&contacts);
+        assert_eq!(parse_contacts(&serialized).expect("valid"), contacts);
+        assert_eq!(parse_contacts(&[]).expect("valid"), vec![]);
+    }
+
+    #[test]
+    fn truncated_avatar_is_rejected() {
+        let serialized = write_contacts(&example_contacts()[..1]);
+        assert_matches!(
+            parse_contacts(&serialized[..serialized.len() - 1]),
+            Err(DeviceSyncError::InvalidContacts("truncated avatar"))
+        );
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        let keys = SyncKeys {
+            storage_service_key: Some([2; 32]),
+            master_key: Some([3; 32]),
+        };
+        assert_eq!(
+            SyncKeys::deserialize(&keys.serialize()).expect("valid"),
+            keys
+        );
+        assert_matches!(
+            SyncKeys::deserialize(
+                &Keys {
+                    storage_service: None,
+                    master: Some(vec![0; 31]),
+                }
+                .encode_to_vec()
+            ),
+            Err(DeviceSyncError::InvalidKeys("invalid master key"))
+        );
+    }
+
+    fn encrypt_in_pieces(
+        keys: &SyncAttachmentKeys,
+        plaintext: &[u8],
+        piece_len: usize,
+    ) -> (Vec<u8>, [u8; 32]) {
+        let mut encryptor = SyncAttachmentEncryptor::new(keys, &mut OsRng);
+        let mut ciphertext = Vec::new();
+        for piece in plaintext.chunks(piece_len) {
+            ciphertext.extend(encryptor.update(piece));
+        }
+        let (rest, digest) = encryptor.finish();
+        ciphertext.extend(rest);
+        (ciphertext, digest)
+    }
+
+    #[test_case(0, 7; "empty")]
+    #[test_case(15, 7; "less than a block")]
+    #[test_case(16, 16; "exactly one block")]
+    #[test_case(1000, 1; "byte at a time")]
+    #[test_case(1000, 33; "odd pieces")]
+    fn attachment_round_trip(len: usize, piece_len: usize) {
+        let keys = SyncAttachmentKeys::generate(&mut OsRng);
+        let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let (ciphertext, digest) = encrypt_in_pieces(&keys, &plaintext, piece_len);
+
+        assert_eq!(
+            ciphertext.len(),
+            BLOCK_LEN + (len / BLOCK_LEN + 1) * BLOCK_LEN + MAC_LEN
+        );
+        assert_eq!(<[u8; 32]>::from(Sha256::digest(&ciphertext)), digest);
+
+        // The ciphertext matches the one-shot implementation.
+        let (iv, rest) = ciphertext.split_at(BLOCK_LEN);
+        let encrypted = &rest[..rest.len() - MAC_LEN];
+        assert_eq!(
+            signal_crypto::aes_256_cbc_decrypt(encrypted, &keys.aes_key, iv).expect("valid"),
+            plaintext
+        );
+
+        for piece_len in [1, 5, 64, ciphertext.len()] {
+            let mut sink =
+                SyncAttachmentDecryptingSink::new(Vec::new(), keys.clone(), Some(digest));
+            for piece in ciphertext.chunks(piece_len) {
+                sink.write(piece).expect("valid");
+            }
+            sink.finish().expect("valid");
+            assert_eq!(sink.into_inner(), plaintext);
+        }
+    }
+
+    #[test]
+    fn tampered_attachment_is_rejected() {
+        let keys = SyncAttachmentKeys::generate(&mut OsRng);
+        let (mut ciphertext, digest) = encrypt_in_pieces(&keys, &[0x55; 100], 100);
+        ciphertext[20] ^= 1;
+
+        let mut sink = SyncAttachmentDecryptingSink::new(Vec::new(), keys.clone(), None);
+        sink.write(&ciphertext).expect("not checked yet");
+        assert_matches!(sink.finish(), Err(TransferError::IntegrityCheckFailed));
+
+        let (ciphertext, _) = encrypt_in_pieces(&keys, &[0x55; 100], 100);
+        let mut sink = SyncAttachmentDecryptingSink::new(Vec::new(), keys, Some(digest));
+        sink.write(&ciphertext).expect("not checked yet");
+        assert_matches!(sink.finish(), Err(TransferError::IntegrityCheckFailed));
+    }
+}
@@ -4,6 +4,7 @@
 //
 
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use attest::svr2::RaftConfig;
@@ -12,6 +13,7 @@ use derive_where::derive_where;
 use http::uri::PathAndQuery;
 
 use crate::auth::HttpBasicAuth;
+use crate::clock::{system_clock, Clock};
 use crate::env::{DomainConfig, Svr3Env};
 use crate::infra::connection_manager::{
     ConnectionManager, MultiRouteConnectionManager, SingleRouteThrottlingConnectionManager,
@@ -23,11 +25,16 @@ use crate::infra::ws::{
     WebSocketServiceError,
 };
 use crate::infra::{
-    make_ws_config, AsyncDuplexStream, ConnectionParams, EndpointConnection, TransportConnector,
+    make_ws_config, AsyncDuplexStream, ConnectionParams, EndpointConnection, HttpRequestDecorator,
+    TransportConnector,
 };
 use crate::svr::SvrConnection;
 use crate::utils::ObservableEvent;
 
+pub mod events;
+#[cfg(any(test, feature = "test-support"))]
+pub mod loopback;
+
 pub trait AsRaftConfig<'a> {
     fn as_raft_config(&self) -> Option<&'a RaftConfig>;
 }
@@ -46,9 +53,69 @@ impl<'a> AsRaftConfig<'a> for &'a RaftConfig {
 
 pub trait EnclaveKind {
     type RaftConfigType: AsRaftConfig<'static> + Clone + Sync + Send;
+    /// The newest protocol version the client can speak to this enclave.
+    const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::BASELINE;
     fn url_path(enclave: &[u8]) -> PathAndQuery;
 }
 
+/// Header used to negotiate the enclave protocol version.
+///
+/// The client sends the newest version it supports in the upgrade request,
+/// and servers that support negotiation echo back the version they will use.
+/// Headers aren't authenticated, so the outcome is then bound to the attested
+/// handshake; see [`ProtocolVersion::handshake_prologue`].
+pub(crate) const PROTOCOL_VERSION_HEADER: &str = "x-signal-enclave-protocol-version";
+
+/// Version of the request and response messages exchanged with an enclave.
+///
+/// Newer versions can include fields that older enclaves would reject. Servers
+/// that predate negotiation never respond with a version, so they are assumed
+/// to speak [`ProtocolVersion::BASELINE`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion(pub u32);
+
+impl ProtocolVersion {
+    /// The protocol spoken by servers that don't support negotiation.
+    pub const BASELINE: Self = Self(0);
+
+    /// Determines the version selected by the server from its upgrade response.
+    ///
+    /// Returns `None` if the response header is missing or malformed, meaning
+    /// the server didn't negotiate and speaks [`Self::BASELINE`]. A server
+    /// can't select a version newer than `offered`.
+    pub(crate) fn negotiate(offered: Self, response_headers: &http::HeaderMap) -> Option<Self> {
+        let selected = response_headers
+            .get(PROTOCOL_VERSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .map(Self)?;
+        Some(selected.min(offered))
+    }
+
+    /// The Noise prologue that binds a negotiation to the attested handshake.
+    ///
+    /// An enclave that receives an offer mixes the offered and selected
+    /// versions into its handshake the same way, so if either header was
+    /// changed in transit the two sides disagree and the handshake fails.
+    /// Stripping the offer entirely leaves the connection at
+    /// [`Self::BASELINE`], where requests that need a newer version are
+    /// rejected rather than sent without their newer fields.
+    pub(crate) fn handshake_prologue(offered: Self, selected: Self) -> Vec<u8> {
+        [
+            PROTOCOL_VERSION_HEADER.as_bytes(),
+            &offered.0.to_be_bytes(),
+            &selected.0.to_be_bytes(),
+        ]
+        .concat()
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
 pub trait Svr3Flavor: EnclaveKind {}
 
 pub enum Cdsi {}
@@ -63,6 +130,7 @@ pub enum Tpm2Snp {}
 
 impl EnclaveKind for Cdsi {
     type RaftConfigType = ();
+    const PROTOCOL_VERSION: ProtocolVersion = crate::cdsi::PROTOCOL_VERSION_DISCARD_E164S;
     fn url_path(enclave: &[u8]) -> PathAndQuery {
         PathAndQuery::try_from(format!("/v1/{}/discovery", hex::encode(enclave))).unwrap()
     }
@@ -230,17 +298,29 @@ pub struct EnclaveEndpoint<'a, E: EnclaveKind> {
 }
 
 pub trait NewHandshake {
+    /// Starts a handshake, checking the attestation's validity period against `now`.
     fn new_handshake(
         params: &EndpointParams<Self>,
         attestation_message: &[u8],
+        now: SystemTime,
     ) -> enclave::Result<enclave::Handshake>
     where
         Self: EnclaveKind + Sized;
 }
 
+#[derive_where(Clone; C: Clone)]
 pub struct EnclaveEndpointConnection<E: EnclaveKind, C> {
     pub(crate) endpoint_connection: EndpointConnection<C>,
     pub(crate) params: EndpointParams<'static, E>,
+    pub(crate) clock: Arc<dyn Clock>,
+}
+
+impl<E: EnclaveKind, C> EnclaveEndpointConnection<E, C> {
+    /// Uses `clock` instead of the system clock to check attestations.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
 }
 
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
@@ -287,10 +367,17 @@ impl<E: EnclaveKind + NewHandshake, C: ConnectionManager> EnclaveEndpointConnect
             &self.endpoint_connection,
             auth,
             transport_connector,
-            &move |attestation_message| E::new_handshake(&self.params, attestation_message),
+            E::PROTOCOL_VERSION,
+            &move |attestation_message| self.new_handshake(attestation_message),
         )
         .await
     }
+
+    /// Starts a handshake, checking the attestation's validity period against this connection's
+    /// clock.
+    fn new_handshake(&self, attestation_message: &[u8]) -> enclave::Result<enclave::Handshake> {
+        E::new_handshake(&self.params, attestation_message, self.clock.now())
+    }
 }
 
 /// Create an `AttestedConnection`.
@@ -305,13 +392,21 @@ async fn connect_attested<
     endpoint_connection: &EndpointConnection<C>,
     auth: impl HttpBasicAuth,
     transport_connector: T,
+    offered_version: ProtocolVersion,
     do_handshake: &(dyn Sync + Fn(&[u8]) -> enclave::Result<enclave::Handshake>),
 ) -> Result<AttestedConnection<S>, Error> {
     let auth_decorator = auth.into();
+    let version_decorator = HttpRequestDecorator::Header(
+        http::HeaderName::from_static(PROTOCOL_VERSION_HEADER),
+        http::HeaderValue::from(offered_version.0),
+    );
     let connector = ServiceConnectorWithDecorator::new(
-        WebSocketClientConnector::<_, WebSocketServiceError>::new(
-            transport_connector,
-            endpoint_connection.config.clone(),
+        ServiceConnectorWithDecorator::new(
+            WebSocketClientConnector::<_, WebSocketServiceError>::new(
+                transport_connector,
+                endpoint_connection.config.clone(),
+            ),
+            version_decorator,
         ),
         auth_decorator,
     );
@@ -327,7 +422,21 @@ async fn connect_attested<
             unreachable!("can't be returned by the initializer")
         }
     }?;
-    let attested = AttestedConnection::connect(websocket, do_handshake).await?;
+    let selected_version =
+        ProtocolVersion::negotiate(offered_version, &websocket.upgrade_response_headers);
+    let protocol_version = selected_version.unwrap_or(ProtocolVersion::BASELINE);
+    let prologue = selected_version
+        .map(|selected| ProtocolVersion::handshake_prologue(offered_version, selected));
+    log::debug!("negotiated enclave protocol {protocol_version}");
+    let attested = AttestedConnection::connect(websocket, |attestation_message| {
+        let handshake = do_handshake(attestation_message)?;
+        match &prologue {
+            Some(prologue) => handshake.with_prologue(prologue),
+            None => Ok(handshake),
+        }
+    })
+    .await?
+    .with_protocol_version(protocol_version);
     Ok(attested)
 }
 
@@ -350,6 +459,7 @@ impl<E: EnclaveKind> EnclaveEndpointConnection<E, SingleRouteThrottlingConnectio
                 ),
             },
             params: endpoint.params.clone(),
+            clock: system_clock(),
         }
     }
 }
@@ -372,6 +482,29 @@ impl<E: EnclaveKind> EnclaveEndpointConnection<E, MultiRouteConnectionManager> {
                 network_change_event,
             ),
             params: endpoint.params.clone(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Returns a connection that also tries `discovered_params` for the next `valid_for`.
+    ///
+    /// See [`MultiRouteConnectionManager::with_discovered_routes`].
+    pub fn with_discovered_routes(
+        &self,
+        discovered_params: impl IntoIterator<Item = ConnectionParams>,
+        valid_for: Duration,
+        one_route_connect_timeout: Duration,
+        network_change_event: &ObservableEvent,
+    ) -> Self {
+        Self {
+            endpoint_connection: self.endpoint_connection.with_discovered_routes(
+                discovered_params,
+                valid_for,
+                one_route_connect_timeout,
+                network_change_event,
+            ),
+            params: self.params.clone(),
+            clock: Arc::clone(&self.clock),
         }
     }
 }
@@ -380,11 +513,12 @@ impl NewHandshake for SgxPreQuantum {
     fn new_handshake(
         params: &EndpointParams<Self>,
         attestation_message: &[u8],
+        now: SystemTime,
     ) -> enclave::Result<enclave::Handshake> {
         attest::svr2::new_handshake(
             params.mr_enclave.as_ref(),
             attestation_message,
-            SystemTime::now(),
+            now,
             params
                 .raft_config
                 .as_raft_config()
@@ -398,11 +532,12 @@ impl NewHandshake for Sgx {
     fn new_handshake(
         params: &EndpointParams<Self>,
         attestation_message: &[u8],
+        now: SystemTime,
     ) -> enclave::Result<enclave::Handshake> {
         attest::svr2::new_handshake(
             params.mr_enclave.as_ref(),
             attestation_message,
-            SystemTime::now(),
+            now,
             params
                 .raft_config
                 .as_raft_config()
@@ -416,12 +551,9 @@ impl NewHandshake for Cdsi {
     fn new_handshake(
         params: &EndpointParams<Self>,
         attestation_message: &[u8],
+        now: SystemTime,
     ) -> enclave::Result<enclave::Handshake> {
-        cds2::new_handshake(
-            params.mr_enclave.as_ref(),
-            attestation_message,
-            SystemTime::now(),
-        )
+        cds2::new_handshake(params.mr_enclave.as_ref(), attestation_message, now)
     }
 }
 
@@ -429,11 +561,12 @@ impl NewHandshake for Nitro {
     fn new_handshake(
         params: &EndpointParams<Self>,
         attestation_message: &[u8],
+        now: SystemTime,
     ) -> enclave::Result<enclave::Handshake> {
         nitro::new_handshake(
             params.mr_enclave.as_ref(),
             attestation_message,
-            SystemTime::now(),
+            now,
             params
                 .raft_config
                 .as_raft_config()
@@ -446,11 +579,12 @@ impl NewHandshake for Tpm2Snp {
     fn new_handshake(
         params: &EndpointParams<Self>,
         attestation_message: &[u8],
+        now: SystemTime,
     ) -> enclave::Result<enclave::Handshake> {
         tpm2snp::new_handshake(
             params.mr_enclave.as_ref(),
             attestation_message,
-            SystemTime::now(),
+            now,
             params
                 .raft_config
                 .as_raft_config()
@@ -472,11 +606,15 @@ mod test {
 
     use super::*;
     use crate::auth::Auth;
+    use crate::clock::ManualClock;
+    use crate::env::ENDPOINT_PARAMS_SVR2_STAGING;
     use crate::infra::connection_manager::ConnectionAttemptOutcome;
     use crate::infra::errors::TransportConnectError;
     use crate::infra::host::Host;
+    use crate::infra::ws::testutil::FAKE_ATTESTATION;
     use crate::infra::{
-        Alpn, HttpRequestDecoratorSeq, RouteType, StreamAndInfo, TransportConnectionParams,
+        Alpn, HttpRequestDecoratorSeq, RouteType, StreamAndInfo, TrafficClass,
+        TransportConnectionParams,
     };
 
     #[derive(Clone, Debug)]
@@ -510,12 +648,13 @@ mod test {
                 mr_enclave,
                 raft_config: (),
             },
+            clock: system_clock(),
         };
 
         connection
             .connect(
                 Auth {
-                    password: "asdf".to_string(),
+                    password: "asdf".to_string().into(),
                     username: "fdsa".to_string(),
                 },
                 AlwaysFailingConnector,
@@ -523,6 +662,60 @@ mod test {
             .await
     }
 
+    #[test]
+    fn protocol_version_negotiation() {
+        let offered = ProtocolVersion(2);
+        let with_header = |value: &'static str| {
+            let mut headers = http::HeaderMap::new();
+            headers.insert(
+                PROTOCOL_VERSION_HEADER,
+                http::HeaderValue::from_static(value),
+            );
+            headers
+        };
+
+        assert_eq!(
+            ProtocolVersion::negotiate(offered, &http::HeaderMap::new()),
+            None
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(offered, &with_header("1")),
+            Some(ProtocolVersion(1))
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(offered, &with_header("5")),
+            Some(offered)
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(offered, &with_header("not a number")),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn negotiated_protocol_version_is_bound_to_handshake() {
+        let offered = ProtocolVersion(2);
+        let enclave = loopback::LoopbackEnclave::new(&mut rand::rngs::OsRng).with_prologue(
+            &ProtocolVersion::handshake_prologue(offered, ProtocolVersion(1)),
+        );
+
+        enclave
+            .clone()
+            .connect_in_memory(loopback::LoopbackReply::message)
+            .await
+            .expect("same negotiation on both sides");
+
+        // A client that saw a different version than the enclave selected.
+        let downgraded = ProtocolVersion::handshake_prologue(offered, ProtocolVersion::BASELINE);
+        enclave
+            .connect_in_memory_with_handshake(
+                |quote| loopback::new_handshake(quote)?.with_prologue(&downgraded),
+                loopback::LoopbackReply::message,
+            )
+            .await
+            .expect_err("handshake fails");
+    }
+
     fn fake_connection_params() -> ConnectionParams {
         ConnectionParams {
             route_type: RouteType::Direct,
@@ -531,6 +724,8 @@ mod test {
                 tcp_host: Host::Domain("fake".into()),
                 port: nonzero!(1234u16),
                 certs: crate::infra::certs::RootCertificates::Native,
+                proxy: None,
+                traffic_class: TrafficClass::default(),
             },
             http_request_decorator: HttpRequestDecoratorSeq::default(),
             http_host: Arc::from("fake-http"),
@@ -538,6 +733,31 @@ mod test {
         }
     }
 
+    #[test]
+    fn attestation_is_checked_against_clock() {
+        // When the SVR2 attestation in the test data was captured.
+        let attested_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1709245753);
+        let clock = Arc::new(ManualClock::new(attested_at));
+        let connection = EnclaveEndpointConnection {
+            endpoint_connection: EndpointConnection {
+                manager: SingleRouteThrottlingConnectionManager::new(
+                    fake_connection_params(),
+                    CONNECT_TIMEOUT,
+                    &ObservableEvent::default(),
+                ),
+                config: make_ws_config(PathAndQuery::from_static("/endpoint"), CONNECT_TIMEOUT),
+            },
+            params: ENDPOINT_PARAMS_SVR2_STAGING,
+            clock: system_clock(),
+        }
+        .with_clock(clock.clone());
+
+        assert!(connection.new_handshake(FAKE_ATTESTATION).is_ok());
+
+        clock.advance(Duration::from_secs(365 * 24 * 60 * 60));
+        assert!(connection.new_handshake(FAKE_ATTESTATION).is_err());
+    }
+
     #[tokio::test]
     async fn single_route_enclave_connect_failure() {
         let result = enclave_connect(SingleRouteThrottlingConnectionManager::new(
@@ -0,0 +1,52 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Reporting of individual enclave interactions.
+//!
+//! Operations like SVR3's backup and restore send a request to each of several
+//! enclaves and can only finish once all of them have responded. An
+//! [`EnclaveEventSink`] is told how long each enclave took and how the
+//! exchange ended, so that a backend that is slowing down or failing these
+//! operations can be singled out.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::infra::host::Host;
+
+/// Receives reports about interactions with enclaves.
+///
+/// Reports are delivered as each interaction finishes, possibly while others
+/// are still in progress, so implementations should return quickly.
+pub trait EnclaveEventSink: Send + Sync {
+    fn on_interaction(&self, interaction: &EnclaveInteraction<'_>);
+}
+
+/// Discards all reports.
+impl EnclaveEventSink for () {
+    fn on_interaction(&self, _interaction: &EnclaveInteraction<'_>) {}
+}
+
+/// A single request sent to an enclave and the wait for its response.
+#[derive(Clone, Debug)]
+pub struct EnclaveInteraction<'a> {
+    /// The step of the operation the request was for, like `"restore1"`.
+    pub operation: &'static str,
+    /// The address of the enclave.
+    pub enclave: &'a Host<Arc<str>>,
+    /// The time from sending the request to the interaction finishing.
+    pub elapsed: Duration,
+    pub outcome: InteractionOutcome,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteractionOutcome {
+    /// The enclave responded.
+    Response,
+    /// The enclave closed the connection instead of responding.
+    Closed,
+    /// The request couldn't be sent or the response couldn't be received.
+    Failed,
+}
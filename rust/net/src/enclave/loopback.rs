@@ -0,0 +1,372 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! An in-process stand-in for an attested enclave.
+//!
+//! [`LoopbackEnclave`] speaks the same Noise handshake as the real CDSI and SVR
+//! servers, but instead of an SGX quote it presents a test quote signed with
+//! its own Noise key. [`new_handshake`] accepts exactly those quotes, so tests
+//! can exercise the request framing of enclave clients end to end without a
+//! network connection or real hardware.
+
+use std::sync::Arc;
+
+use attest::client_connection::NOISE_PATTERN;
+use attest::enclave;
+use futures_util::{SinkExt as _, StreamExt as _};
+use libsignal_protocol::{KeyPair, PublicKey};
+use rand::{CryptoRng, Rng};
+use tokio::io::DuplexStream;
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::protocol::frame::coding::{Data, OpCode};
+use tungstenite::protocol::frame::Frame;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::Message;
+
+use crate::infra::host::Host;
+use crate::infra::ws::{AttestedConnection, AttestedConnectionError, WebSocketClient};
+use crate::infra::{AsyncDuplexStream, ConnectionInfo, DnsSource, RouteType};
+
+/// Marks a test quote so it can't be confused with a real attestation.
+const TEST_QUOTE_PREFIX: &[u8] = b"libsignal-net loopback enclave test quote v1";
+
+const PUBLIC_KEY_LEN: usize = 32;
+
+/// Overhead added by the Noise transport to each message.
+const NOISE_TAG_LEN: usize = 16;
+
+/// What a [`LoopbackEnclave`] does in response to a message from the client.
+#[derive(Default)]
+pub struct LoopbackReply {
+    /// Sent to the client, encrypted, if present.
+    pub message: Option<Vec<u8>>,
+    /// If present, the connection is closed with this frame after `message`
+    /// is sent.
+    pub close_after: Option<Option<CloseFrame<'static>>>,
+}
+
+impl LoopbackReply {
+    pub fn message(contents: Vec<u8>) -> Self {
+        Self {
+            message: Some(contents),
+            ..Default::default()
+        }
+    }
+
+    pub fn close(frame: Option<CloseFrame<'static>>) -> Self {
+        Self {
+            close_after: Some(frame),
+            ..Default::default()
+        }
+    }
+}
+
+/// A fake enclave with a freshly generated Noise key.
+#[derive(Clone)]
+pub struct LoopbackEnclave {
+    private_key: Arc<[u8]>,
+    test_quote: Arc<[u8]>,
+    prologue: Arc<[u8]>,
+    max_frame_size: Option<usize>,
+}
+
+impl LoopbackEnclave {
+    pub fn new<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        let KeyPair {
+            public_key,
+            private_key,
+        } = KeyPair::generate(rng);
+        let public_key = public_key
+            .public_key_bytes()
+            .expect("generated keys are Djb keys");
+        let signed = [TEST_QUOTE_PREFIX, public_key].concat();
+        let signature = private_key
+            .calculate_signature(&signed, rng)
+            .expect("generated keys can sign");
+
+        Self {
+            private_key: private_key.serialize().into(),
+            test_quote: [signed, signature.into_vec()].concat().into(),
+            prologue: Arc::new([]),
+            max_frame_size: None,
+        }
+    }
+
+    /// Splits every message this enclave sends into websocket continuation
+    /// frames of at most `max_frame_size` bytes, like a proxy with a small
+    /// frame limit would.
+    pub fn with_max_frame_size(self, max_frame_size: usize) -> Self {
+        assert!(max_frame_size > 0, "frames must carry some data");
+        Self {
+            max_frame_size: Some(max_frame_size),
+            ..self
+        }
+    }
+
+    /// Uses `prologue` for the Noise handshake, as a real enclave does after
+    /// negotiating a protocol version.
+    pub fn with_prologue(self, prologue: &[u8]) -> Self {
+        Self {
+            prologue: prologue.into(),
+            ..self
+        }
+    }
+
+    /// The message this enclave sends in place of an attestation.
+    pub fn test_quote(&self) -> &[u8] {
+        &self.test_quote
+    }
+
+    /// Runs the server side of a single connection over `websocket`.
+    ///
+    /// After the handshake completes, `on_message` is called with each
+    /// decrypted message from the client. Returns when the client closes the
+    /// connection or `on_message` asks to close it.
+    pub async fn serve<S: AsyncDuplexStream>(
+        &self,
+        mut websocket: WebSocketStream<S>,
+        mut on_message: impl FnMut(Vec<u8>) -> LoopbackReply,
+    ) -> Result<(), LoopbackError> {
+        let mut handshake = snow::Builder::with_resolver(
+            NOISE_PATTERN.parse().expect("valid"),
+            Box::new(attest::snow_resolver::Resolver),
+        )
+        .local_private_key(&self.private_key[..])
+        .prologue(&self.prologue)
+        .build_responder()?;
+
+        self.send_binary(&mut websocket, self.test_quote.to_vec())
+            .await?;
+
+        let Some(initial_request) = next_binary(&mut websocket).await? else {
+            return Err(LoopbackError::UnexpectedClose);
+        };
+        handshake.read_message(&initial_request, &mut [])?;
+        let mut initial_response = vec![0; 2 * PUBLIC_KEY_LEN];
+        let written = handshake.write_message(&[], &mut initial_response)?;
+        initial_response.truncate(written);
+        self.send_binary(&mut websocket, initial_response).await?;
+
+        let mut transport = handshake.into_transport_mode()?;
+        while let Some(incoming) = next_binary(&mut websocket).await? {
+            let mut request = vec![0; incoming.len()];
+            let read = transport.read_message(&incoming, &mut request)?;
+            request.truncate(read);
+
+            let LoopbackReply {
+                message,
+                close_after,
+            } = on_message(request);
+
+            if let Some(message) = message {
+                let mut outgoing = vec![0; message.len() + NOISE_TAG_LEN];
+                let written = transport.write_message(&message, &mut outgoing)?;
+                outgoing.truncate(written);
+                self.send_binary(&mut websocket, outgoing).await?;
+            }
+            if let Some(frame) = close_after {
+                websocket.close(frame).await?;
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_binary<S: AsyncDuplexStream>(
+        &self,
+        websocket: &mut WebSocketStream<S>,
+        message: Vec<u8>,
+    ) -> Result<(), LoopbackError> {
+        let max_frame_size = match self.max_frame_size {
+            Some(max_frame_size) if message.len() > max_frame_size => max_frame_size,
+            _ => return Ok(websocket.send(Message::Binary(message)).await?),
+        };
+        let mut chunks = message.chunks(max_frame_size).peekable();
+        let mut opcode = OpCode::Data(Data::Binary);
+        while let Some(chunk) = chunks.next() {
+            let is_final = chunks.peek().is_none();
+            websocket
+                .feed(Message::Frame(Frame::message(
+                    chunk.to_vec(),
+                    opcode,
+                    is_final,
+                )))
+                .await?;
+            opcode = OpCode::Data(Data::Continue);
+        }
+        Ok(websocket.flush().await?)
+    }
+
+    /// Starts serving on an in-memory websocket and returns the client side,
+    /// already attested and ready for requests.
+    pub async fn connect_in_memory(
+        self,
+        on_message: impl FnMut(Vec<u8>) -> LoopbackReply + Send + 'static,
+    ) -> Result<AttestedConnection<DuplexStream>, AttestedConnectionError> {
+        let prologue = self.prologue.clone();
+        self.connect_in_memory_with_handshake(
+            |test_quote| new_handshake(test_quote)?.with_prologue(&prologue),
+            on_message,
+        )
+        .await
+    }
+
+    /// Like [`Self::connect_in_memory`], but the client starts its side of
+    /// the handshake with `new_handshake`.
+    pub async fn connect_in_memory_with_handshake(
+        self,
+        new_handshake: impl FnOnce(&[u8]) -> enclave::Result<enclave::Handshake>,
+        on_message: impl FnMut(Vec<u8>) -> LoopbackReply + Send + 'static,
+    ) -> Result<AttestedConnection<DuplexStream>, AttestedConnectionError> {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let url = url::Url::parse("ws://localhost/").expect("valid");
+        let (client, server) = tokio::join!(
+            tokio_tungstenite::client_async(url, client),
+            tokio_tungstenite::accept_async(server),
+        );
+        let (client, _response) = client.expect("in-memory websocket upgrade");
+        let server = server.expect("in-memory websocket upgrade");
+
+        tokio::spawn(async move {
+            if let Err(e) = self.serve(server, on_message).await {
+                log::warn!("loopback enclave stopped: {e}");
+            }
+        });
+
+        let connection_info = ConnectionInfo {
+            route_type: RouteType::Direct,
+            dns_source: DnsSource::Static,
+            address: Host::Domain("localhost".into()),
+        };
+        AttestedConnection::connect(
+            WebSocketClient::new_fake(client, connection_info),
+            new_handshake,
+        )
+        .await
+    }
+}
+
+/// Starts a handshake with a [`LoopbackEnclave`], given its test quote.
+///
+/// Anything other than a correctly self-signed test quote is rejected.
+pub fn new_handshake(test_quote: &[u8]) -> enclave::Result<enclave::Handshake> {
+    let invalid = || enclave::Error::AttestationDataError {
+        reason: "not a loopback enclave test quote".to_string(),
+    };
+
+    let signed_len = TEST_QUOTE_PREFIX.len() + PUBLIC_KEY_LEN;
+    if test_quote.len() < signed_len || !test_quote.starts_with(TEST_QUOTE_PREFIX) {
+        return Err(invalid());
+    }
+    let (signed, signature) = test_quote.split_at(signed_len);
+    let public_key_bytes = &signed[TEST_QUOTE_PREFIX.len()..];
+    let public_key =
+        PublicKey::from_djb_public_key_bytes(public_key_bytes).map_err(|_| invalid())?;
+    if !public_key
+        .verify_signature(signed, signature)
+        .map_err(|_| invalid())?
+    {
+        return Err(invalid());
+    }
+
+    enclave::Handshake::for_testing_with_public_key(public_key_bytes)
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum LoopbackError {
+    /// websocket error: {0}
+    WebSocket(#[from] tungstenite::Error),
+    /// noise error: {0}
+    Noise(#[from] snow::Error),
+    /// client closed the connection during the handshake
+    UnexpectedClose,
+    /// client sent a text frame
+    UnexpectedText,
+}
+
+async fn next_binary<S: AsyncDuplexStream>(
+    websocket: &mut WebSocketStream<S>,
+) -> Result<Option<Vec<u8>>, LoopbackError> {
+    while let Some(message) = websocket.next().await {
+        match message? {
+            Message::Binary(bytes) => return Ok(Some(bytes)),
+            Message::Text(_) => return Err(LoopbackError::UnexpectedText),
+            Message::Close(_) => return Ok(None),
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use rand::rngs::OsRng;
+    use test_case::test_matrix;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn echoes_over_attested_connection() {
+        let enclave = LoopbackEnclave::new(&mut OsRng);
+        let mut connection = enclave
+            .connect_in_memory(LoopbackReply::message)
+            .await
+            .expect("handshake succeeds");
+
+        connection
+            .send_bytes(b"framed request")
+            .await
+            .expect("can send");
+        let response = connection
+            .receive_bytes()
+            .await
+            .expect("can receive")
+            .next_or(())
+            .expect("not closed");
+        assert_eq!(response, b"framed request");
+    }
+
+    #[test_matrix([1, 15, 16, 17, 47, 48, 49, 1000], [0, 1, 16, 17, 4096])]
+    #[tokio::test]
+    async fn reassembles_continuation_frames(max_frame_size: usize, message_len: usize) {
+        // Each transport message carries a 16-byte tag, so these sizes put
+        // frame boundaries at, just before, and just after it.
+        let enclave = LoopbackEnclave::new(&mut OsRng).with_max_frame_size(max_frame_size);
+        let mut connection = enclave
+            .connect_in_memory(LoopbackReply::message)
+            .await
+            .expect("handshake succeeds");
+
+        let message = (0..message_len).map(|i| i as u8).collect::<Vec<_>>();
+        connection.send_bytes(&message).await.expect("can send");
+        let response = connection
+            .receive_bytes()
+            .await
+            .expect("can receive")
+            .next_or(())
+            .expect("not closed");
+        assert_eq!(response, message);
+    }
+
+    #[test]
+    fn rejects_tampered_quotes() {
+        let enclave = LoopbackEnclave::new(&mut OsRng);
+        assert_matches!(new_handshake(enclave.test_quote()), Ok(_));
+
+        let mut tampered = enclave.test_quote().to_vec();
+        let key_byte = TEST_QUOTE_PREFIX.len();
+        tampered[key_byte] ^= 1;
+        assert_matches!(
+            new_handshake(&tampered),
+            Err(enclave::Error::AttestationDataError { .. })
+        );
+
+        assert_matches!(
+            new_handshake(attest::sgx_session::testutil::EVIDENCE_BYTES),
+            Err(enclave::Error::AttestationDataError { .. })
+        );
+    }
+}
@@ -5,7 +5,7 @@
 
 use std::collections::HashMap;
 use std::iter;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::num::NonZeroU16;
 use std::sync::Arc;
 
@@ -22,7 +22,7 @@ use crate::infra::dns::lookup_result::LookupResult;
 use crate::infra::host::Host;
 use crate::infra::{
     ConnectionParams, DnsSource, HttpRequestDecorator, HttpRequestDecoratorSeq, RouteType,
-    TransportConnectionParams,
+    TrafficClass, TransportConnectionParams,
 };
 
 const DEFAULT_HTTPS_PORT: NonZeroU16 = nonzero!(443_u16);
@@ -314,6 +314,8 @@ impl DomainConfig {
                     tcp_host: Host::Domain(Arc::clone(&hostname)),
                     port: self.port,
                     certs: self.cert.clone(),
+                    proxy: None,
+                    traffic_class: TrafficClass::default(),
                 },
                 http_host: hostname,
                 http_request_decorator: HttpRequestDecoratorSeq::default(),
@@ -342,6 +344,35 @@ impl DomainConfig {
         let proxy_params = itertools::interleave(shuffled_g_params, shuffled_f_params);
         iter::once(direct).chain(proxy_params).collect()
     }
+
+    /// Like [`Self::connection_params_with_fallback`], but with a route through the TLS proxy at
+    /// `proxy_addr` that is tried right after the direct route fails.
+    ///
+    /// This is for clients that only want to use a proxy when a direct connection is blocked.
+    pub fn connection_params_with_proxy_fallback(
+        &self,
+        proxy_addr: (Host<Arc<str>>, NonZeroU16),
+    ) -> Vec<ConnectionParams> {
+        let mut params = self.connection_params_with_fallback();
+        let via_proxy = self.direct_connection_params().via_proxy(proxy_addr);
+        params.insert(1, via_proxy);
+        params
+    }
+
+    /// Like [`Self::connection_params_with_fallback`], but first tries direct routes to each of
+    /// `addresses`, in order, instead of looking up the host name.
+    pub fn connection_params_with_address_overrides(
+        &self,
+        addresses: &[(IpAddr, NonZeroU16)],
+    ) -> Vec<ConnectionParams> {
+        let overrides = addresses.iter().map(|&(ip, port)| {
+            self.direct_connection_params()
+                .with_address_override(ip, port)
+        });
+        overrides
+            .chain(self.connection_params_with_fallback())
+            .collect()
+    }
 }
 
 pub fn add_user_agent_header(
@@ -390,6 +421,8 @@ impl ProxyConfig {
                     tcp_host: Host::Domain(sni_and_dns_host),
                     port: nonzero!(443u16),
                     certs: RootCertificates::Native,
+                    proxy: None,
+                    traffic_class: TrafficClass::default(),
                 },
                 http_host: self.http_host.into(),
                 http_request_decorator: HttpRequestDecorator::PathPrefix(proxy_path).into(),
@@ -504,6 +537,7 @@ pub const PROD: Env<'static, Svr3Env> = Env {
 
 pub mod constants {
     pub const WEB_SOCKET_PATH: &str = "/v1/websocket/";
+    pub const PROVISIONING_WEB_SOCKET_PATH: &str = "/v1/websocket/provisioning/";
 }
 
 #[cfg(test)]
@@ -535,6 +569,54 @@ mod test {
         }
     }
 
+    #[test]
+    fn proxy_fallback_comes_after_direct() {
+        let proxy_addr = (Host::Domain("proxy.example".into()), nonzero!(443u16));
+        let params = DOMAIN_CONFIG_CHAT.connection_params_with_proxy_fallback(proxy_addr.clone());
+        let route_types = params
+            .iter()
+            .map(|params| params.route_type)
+            .collect::<Vec<_>>();
+        assert_eq!(route_types[..2], [RouteType::Direct, RouteType::TlsProxy]);
+        assert_eq!(
+            params.len(),
+            DOMAIN_CONFIG_CHAT.connection_params_with_fallback().len() + 1
+        );
+
+        let proxied = &params[1];
+        assert_eq!(proxied.transport.proxy, Some(proxy_addr));
+        assert_eq!(proxied.transport.sni, params[0].transport.sni);
+        assert!(params
+            .iter()
+            .filter(|params| params.route_type != RouteType::TlsProxy)
+            .all(|params| params.transport.proxy.is_none()));
+    }
+
+    #[test]
+    fn address_overrides_come_first_and_keep_host_names() {
+        let addresses = [
+            (ip_addr!(v4, "192.0.2.1").into(), nonzero!(8443u16)),
+            (ip_addr!(v6, "2001:db8::1").into(), nonzero!(443u16)),
+        ];
+        let params = DOMAIN_CONFIG_CHAT.connection_params_with_address_overrides(&addresses);
+        assert_eq!(
+            params.len(),
+            DOMAIN_CONFIG_CHAT.connection_params_with_fallback().len() + addresses.len()
+        );
+
+        for (params, (ip, port)) in params.iter().zip(addresses) {
+            assert_eq!(params.route_type, RouteType::Direct);
+            assert_eq!(params.transport.tcp_host, Host::Ip(ip));
+            assert_eq!(params.transport.port, port);
+            assert_eq!(&*params.transport.sni, DOMAIN_CONFIG_CHAT.hostname);
+            assert_eq!(&*params.http_host, DOMAIN_CONFIG_CHAT.hostname);
+        }
+        assert_eq!(
+            params[addresses.len()].transport.tcp_host,
+            Host::Domain(DOMAIN_CONFIG_CHAT.hostname.into())
+        );
+    }
+
     #[test_matrix([&DOMAIN_CONFIG_CDSI, &DOMAIN_CONFIG_CDSI_STAGING])]
     fn cdsi_has_no_confirmation_header(config: &DomainConfig) {
         assert_eq!(
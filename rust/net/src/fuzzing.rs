@@ -0,0 +1,46 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Entry points for fuzzing the parsing of server responses.
+//!
+//! With the `arbitrary` feature, [`ClientResponse`] implements
+//! [`arbitrary::Arbitrary`], so fuzzers can produce well-formed CDSI responses
+//! with arbitrary contents.
+
+use prost::Message as _;
+
+use crate::cdsi::LookupResponse;
+pub use crate::proto::cds2::ClientResponse;
+
+/// Tries to decode `data` as a CDSI response and extract its lookup results.
+pub fn parse_client_response(data: &[u8]) {
+    if let Ok(response) = ClientResponse::decode(data) {
+        lookup_response(response);
+    }
+}
+
+/// Extracts the lookup results from a CDSI response, as if it had been
+/// received from the server.
+pub fn lookup_response(response: ClientResponse) {
+    let _: Result<_, _> = LookupResponse::try_from(response);
+}
+
+#[cfg(test)]
+mod test {
+    use arbitrary::Unstructured;
+
+    use super::*;
+
+    #[test]
+    fn arbitrary_responses_can_be_parsed() {
+        let data = (0..=255).cycle().take(4096).collect::<Vec<u8>>();
+        let mut u = Unstructured::new(&data);
+        for _ in 0..3 {
+            let response: ClientResponse = u.arbitrary().expect("enough data");
+            parse_client_response(&response.encode_to_vec());
+            lookup_response(response);
+        }
+    }
+}
@@ -0,0 +1,970 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for the groups server.
+//!
+//! Everything the groups server stores is encrypted with the group's
+//! [`GroupSecretParams`], and every request is authorized with a fresh
+//! presentation of the member's auth credential. [`GroupsClient`] takes care
+//! of both: callers pass in the group's secret params and their credential,
+//! and get back decrypted [`DecryptedGroup`]s and [`DecryptedGroupChange`]s.
+//! Changes are described with [`GroupChangeAction`]s and encrypted on the
+//! way out.
+//!
+//! Only a subset of group state is modeled: members, title, description, and
+//! the disappearing messages timer. Changes that touch anything else are
+//! still decrypted, but can't be applied locally. Invite links are described
+//! by [`GroupInviteLink`].
+
+use std::str::FromStr;
+
+use bytes::Bytes;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use libsignal_core::{Aci, ServiceId};
+use prost::Message;
+use rand_core::{CryptoRng, RngCore};
+use zkgroup::auth::AuthCredentialWithPni;
+use zkgroup::groups::{GroupSecretParams, ProfileKeyCiphertext, UuidCiphertext};
+use zkgroup::profiles::{ExpiringProfileKeyCredential, ProfileKey};
+use zkgroup::ServerPublicParams;
+
+use crate::cdn::{HttpEndpoint, RequestError, DEFAULT_MAX_RESPONSE_SIZE};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::TransportConnector;
+use crate::proto::groups::group_attribute_blob::Content;
+use crate::proto::groups::group_change::actions::{
+    AddMemberAction, DeleteMemberAction, ModifyDescriptionAction,
+    ModifyDisappearingMessagesTimerAction, ModifyMemberRoleAction, ModifyTitleAction,
+};
+use crate::proto::groups::group_change::Actions;
+use crate::proto::groups::{
+    member, Group, GroupAttributeBlob, GroupChange, GroupChangeResponse, GroupChanges,
+    GroupResponse, Member,
+};
+use crate::utils::basic_authorization;
+
+mod invite_link;
+pub use invite_link::{
+    GroupInviteLink, InviteLinkError, InviteLinkPassword, INVITE_LINK_PASSWORD_LEN,
+};
+
+const GROUP_PATH: &str = "/v2/groups/";
+const GROUP_LOGS_PATH: &str = "/v2/groups/logs";
+const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The newest change epoch whose actions this client understands.
+const MAX_SUPPORTED_CHANGE_EPOCH: u32 = 5;
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum GroupsError {
+    /// request failed: {0}
+    Request(#[from] RequestError),
+    /// the group has changed since the given version
+    Conflict,
+    /// group data could not be decrypted
+    DecryptionFailed,
+    /// the server signature on a group change is invalid
+    InvalidSignature,
+    /// change to version {change} cannot be applied to version {current}
+    VersionMismatch { current: u32, change: u32 },
+    /// group change contains an action this client does not model (field {0})
+    UnsupportedAction(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemberRole {
+    Default,
+    Administrator,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DecryptedMember {
+    pub aci: Aci,
+    pub role: MemberRole,
+    pub profile_key: ProfileKey,
+    pub joined_at_version: u32,
+}
+
+/// The decrypted state of a group at a particular version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecryptedGroup {
+    pub version: u32,
+    pub title: String,
+    pub description: String,
+    /// The CDN key of the encrypted avatar, or empty if there is none.
+    pub avatar: String,
+    /// In seconds; zero if disappearing messages are off.
+    pub disappearing_messages_timer: u32,
+    pub members: Vec<DecryptedMember>,
+}
+
+/// A decrypted change from one group version to the next.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DecryptedGroupChange {
+    /// Who made the change, if the server reported it.
+    pub editor: Option<ServiceId>,
+    pub version: u32,
+    pub added_members: Vec<DecryptedMember>,
+    pub deleted_members: Vec<Aci>,
+    pub modified_roles: Vec<(Aci, MemberRole)>,
+    pub modified_profile_keys: Vec<(Aci, ProfileKey)>,
+    pub new_title: Option<String>,
+    pub new_description: Option<String>,
+    pub new_disappearing_messages_timer: Option<u32>,
+    /// Field numbers of actions in the change that aren't modeled above.
+    ///
+    /// A change with any of these can't be applied with [`DecryptedGroup::apply_change`], since
+    /// the result wouldn't match the server's state; fetch the whole group instead.
+    pub unsupported_actions: Vec<u32>,
+}
+
+/// A change to make to a group, in plaintext.
+#[derive(Clone)]
+pub enum GroupChangeAction {
+    /// Adds the member whose profile key credential this is.
+    AddMember {
+        credential: ExpiringProfileKeyCredential,
+        role: MemberRole,
+    },
+    DeleteMember(Aci),
+    ModifyMemberRole(Aci, MemberRole),
+    ModifyTitle(String),
+    ModifyDescription(String),
+    /// In seconds; zero turns disappearing messages off.
+    ModifyDisappearingMessagesTimer(u32),
+}
+
+/// One page of a group's change log.
+#[derive(Clone, Debug)]
+pub struct GroupChangePage {
+    pub changes: Vec<DecryptedGroupChange>,
+    /// Whether there are more changes after the last one in this page.
+    pub has_more: bool,
+}
+
+/// The server's response to an accepted change.
+#[derive(Clone, Debug)]
+pub struct AppliedGroupChange {
+    pub change: DecryptedGroupChange,
+    /// The serialized, server-signed `GroupChange`, for sending to the other
+    /// members.
+    pub signed_change: Vec<u8>,
+}
+
+impl From<MemberRole> for member::Role {
+    fn from(value: MemberRole) -> Self {
+        match value {
+            MemberRole::Default => Self::Default,
+            MemberRole::Administrator => Self::Administrator,
+        }
+    }
+}
+
+impl TryFrom<member::Role> for MemberRole {
+    type Error = GroupsError;
+
+    fn try_from(value: member::Role) -> Result<Self, Self::Error> {
+        match value {
+            member::Role::Default => Ok(Self::Default),
+            member::Role::Administrator => Ok(Self::Administrator),
+            member::Role::Unknown => Err(RequestError::InvalidResponse.into()),
+        }
+    }
+}
+
+/// Decrypts group fields with a group's secret params.
+struct GroupCipher<'a>(&'a GroupSecretParams);
+
+impl GroupCipher<'_> {
+    fn decrypt_aci(&self, ciphertext: &[u8]) -> Result<Aci, GroupsError> {
+        self.decrypt_service_id(ciphertext)?
+            .try_into()
+            .map_err(|_| GroupsError::DecryptionFailed)
+    }
+
+    fn decrypt_service_id(&self, ciphertext: &[u8]) -> Result<ServiceId, GroupsError> {
+        let ciphertext: UuidCiphertext =
+            zkgroup::deserialize(ciphertext).map_err(|_| GroupsError::DecryptionFailed)?;
+        self.0
+            .decrypt_service_id(ciphertext)
+            .map_err(|_| GroupsError::DecryptionFailed)
+    }
+
+    fn decrypt_profile_key(&self, ciphertext: &[u8], aci: Aci) -> Result<ProfileKey, GroupsError> {
+        let ciphertext: ProfileKeyCiphertext =
+            zkgroup::deserialize(ciphertext).map_err(|_| GroupsError::DecryptionFailed)?;
+        self.0
+            .decrypt_profile_key(ciphertext, aci)
+            .map_err(|_| GroupsError::DecryptionFailed)
+    }
+
+    fn decrypt_member(&self, member: &Member) -> Result<DecryptedMember, GroupsError> {
+        let aci = self.decrypt_aci(&member.user_id)?;
+        Ok(DecryptedMember {
+            aci,
+            role: member.role().try_into()?,
+            profile_key: self.decrypt_profile_key(&member.profile_key, aci)?,
+            joined_at_version: member.joined_at_version,
+        })
+    }
+
+    /// Decrypts an attribute blob; an empty ciphertext means the attribute is
+    /// unset.
+    fn decrypt_blob(&self, ciphertext: &[u8]) -> Result<Option<Content>, GroupsError> {
+        if ciphertext.is_empty() {
+            return Ok(None);
+        }
+        let plaintext = self
+            .0
+            .decrypt_blob_with_padding(ciphertext)
+            .map_err(|_| GroupsError::DecryptionFailed)?;
+        let blob =
+            GroupAttributeBlob::decode(&*plaintext).map_err(|_| GroupsError::DecryptionFailed)?;
+        Ok(blob.content)
+    }
+
+    fn decrypt_title(&self, ciphertext: &[u8]) -> Result<String, GroupsError> {
+        match self.decrypt_blob(ciphertext)? {
+            Some(Content::Title(title)) => Ok(title),
+            None => Ok(String::new()),
+            Some(_) => Err(GroupsError::DecryptionFailed),
+        }
+    }
+
+    fn decrypt_description(&self, ciphertext: &[u8]) -> Result<String, GroupsError> {
+        match self.decrypt_blob(ciphertext)? {
+            Some(Content::DescriptionText(description)) => Ok(description),
+            None => Ok(String::new()),
+            Some(_) => Err(GroupsError::DecryptionFailed),
+        }
+    }
+
+    fn decrypt_timer(&self, ciphertext: &[u8]) -> Result<u32, GroupsError> {
+        match self.decrypt_blob(ciphertext)? {
+            Some(Content::DisappearingMessagesDuration(duration)) => Ok(duration),
+            None => Ok(0),
+            Some(_) => Err(GroupsError::DecryptionFailed),
+        }
+    }
+
+    fn encrypt_blob(&self, content: Content, rng: &mut (impl RngCore + CryptoRng)) -> Vec<u8> {
+        let plaintext = GroupAttributeBlob {
+            content: Some(content),
+        }
+        .encode_to_vec();
+        self.0
+            .encrypt_blob_with_padding(random_bytes(rng), &plaintext, 0)
+    }
+}
+
+impl DecryptedGroup {
+    fn decrypt(secret_params: &GroupSecretParams, group: &Group) -> Result<Self, GroupsError> {
+        let cipher = GroupCipher(secret_params);
+        Ok(Self {
+            version: group.version,
+            title: cipher.decrypt_title(&group.title)?,
+            description: cipher.decrypt_description(&group.description)?,
+            avatar: group.avatar.clone(),
+            disappearing_messages_timer: cipher
+                .decrypt_timer(&group.disappearing_messages_timer)?,
+            members: group
+                .members
+                .iter()
+                .map(|member| cipher.decrypt_member(member))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Applies `change`, which must be for the next version of the group.
+    ///
+    /// Fails without changing anything if the change contains actions that aren't modeled.
+    pub fn apply_change(&mut self, change: &DecryptedGroupChange) -> Result<(), GroupsError> {
+        if self.version.checked_add(1) != Some(change.version) {
+            return Err(GroupsError::VersionMismatch {
+                current: self.version,
+                change: change.version,
+            });
+        }
+        if let Some(&field) = change.unsupported_actions.first() {
+            return Err(GroupsError::UnsupportedAction(field));
+        }
+
+        let DecryptedGroupChange {
+            editor: _,
+            version,
+            added_members,
+            deleted_members,
+            modified_roles,
+            modified_profile_keys,
+            new_title,
+            new_description,
+            new_disappearing_messages_timer,
+            unsupported_actions: _,
+        } = change;
+
+        self.members
+            .retain(|member| !deleted_members.contains(&member.aci));
+        for added in added_members {
+            self.members.retain(|member| member.aci != added.aci);
+            self.members.push(*added);
+        }
+        for (aci, role) in modified_roles {
+            if let Some(member) = self.member_mut(*aci) {
+                member.role = *role;
+            }
+        }
+        for (aci, profile_key) in modified_profile_keys {
+            if let Some(member) = self.member_mut(*aci) {
+                member.profile_key = *profile_key;
+            }
+        }
+        if let Some(title) = new_title {
+            self.title.clone_from(title);
+        }
+        if let Some(description) = new_description {
+            self.description.clone_from(description);
+        }
+        if let Some(timer) = new_disappearing_messages_timer {
+            self.disappearing_messages_timer = *timer;
+        }
+        self.version = *version;
+        Ok(())
+    }
+
+    fn member_mut(&mut self, aci: Aci) -> Option<&mut DecryptedMember> {
+        self.members.iter_mut().find(|member| member.aci == aci)
+    }
+}
+
+impl DecryptedGroupChange {
+    fn decrypt(secret_params: &GroupSecretParams, actions: &Actions) -> Result<Self, GroupsError> {
+        let cipher = GroupCipher(secret_params);
+        let Actions {
+            source_uuid,
+            version,
+            add_members,
+            delete_members,
+            modify_member_roles,
+            modify_member_profile_keys,
+            modify_title,
+            modify_disappearing_messages_timer,
+            modify_description,
+            group_id: _,
+        } = actions;
+
+        Ok(Self {
+            editor: (!source_uuid.is_empty())
+                .then(|| cipher.decrypt_service_id(source_uuid))
+                .transpose()?,
+            version: *version,
+            added_members: add_members
+                .iter()
+                .map(|action| {
+                    let added = action.added.as_ref().ok_or(RequestError::InvalidResponse)?;
+                    cipher.decrypt_member(added)
+                })
+                .collect::<Result<_, _>>()?,
+            deleted_members: delete_members
+                .iter()
+                .map(|action| cipher.decrypt_aci(&action.deleted_user_id))
+                .collect::<Result<_, _>>()?,
+            modified_roles: modify_member_roles
+                .iter()
+                .map(|action| {
+                    Ok((
+                        cipher.decrypt_aci(&action.user_id)?,
+                        action.role().try_into()?,
+                    ))
+                })
+                .collect::<Result<_, GroupsError>>()?,
+            modified_profile_keys: modify_member_profile_keys
+                .iter()
+                .map(|action| {
+                    let aci = cipher.decrypt_aci(&action.user_id)?;
+                    Ok((aci, cipher.decrypt_profile_key(&action.profile_key, aci)?))
+                })
+                .collect::<Result<_, GroupsError>>()?,
+            new_title: modify_title
+                .as_ref()
+                .map(|action| cipher.decrypt_title(&action.title))
+                .transpose()?,
+            new_description: modify_description
+                .as_ref()
+                .map(|action| cipher.decrypt_description(&action.description))
+                .transpose()?,
+            new_disappearing_messages_timer: modify_disappearing_messages_timer
+                .as_ref()
+                .map(|action| cipher.decrypt_timer(&action.timer))
+                .transpose()?,
+            unsupported_actions: vec![],
+        })
+    }
+}
+
+/// The field numbers of the [`Actions`] that [`DecryptedGroupChange`] models.
+const MODELED_ACTION_FIELDS: &[u32] = &[1, 2, 3, 4, 5, 6, 10, 12, 20, 25];
+
+/// Returns the field numbers in the encoded `actions` that aren't in [`MODELED_ACTION_FIELDS`].
+///
+/// prost drops fields it doesn't know about when decoding, so this looks at the encoding
+/// directly. Returns `None` if `actions` isn't a valid encoding.
+fn unmodeled_action_fields(mut actions: &[u8]) -> Option<Vec<u32>> {
+    fn read_varint(buf: &mut &[u8]) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = buf.split_first()?;
+            *buf = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    let mut fields = vec![];
+    while !actions.is_empty() {
+        let key = read_varint(&mut actions)?;
+        let field = u32::try_from(key >> 3).ok()?;
+        let len = match key & 0x7 {
+            0 => {
+                read_varint(&mut actions)?;
+                0
+            }
+            1 => 8,
+            2 => usize::try_from(read_varint(&mut actions)?).ok()?,
+            5 => 4,
+            // Groups aren't used in the groups protos.
+            _ => return None,
+        };
+        actions = actions.get(len..)?;
+        if !MODELED_ACTION_FIELDS.contains(&field) && !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+    Some(fields)
+}
+
+/// Encrypts `actions` into the form the server expects for version
+/// `version`.
+fn encrypt_actions(
+    server_public_params: &ServerPublicParams,
+    secret_params: &GroupSecretParams,
+    version: u32,
+    actions: &[GroupChangeAction],
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Actions {
+    let cipher = GroupCipher(secret_params);
+    let encrypt_aci = |aci: Aci| zkgroup::serialize(&secret_params.encrypt_service_id(aci.into()));
+
+    let mut result = Actions {
+        version,
+        group_id: secret_params.get_group_identifier().to_vec(),
+        ..Default::default()
+    };
+    for action in actions {
+        match action {
+            GroupChangeAction::AddMember { credential, role } => {
+                let presentation = server_public_params
+                    .create_expiring_profile_key_credential_presentation(
+                        random_bytes(rng),
+                        *secret_params,
+                        *credential,
+                    );
+                result.add_members.push(AddMemberAction {
+                    added: Some(Member {
+                        role: member::Role::from(*role).into(),
+                        presentation: zkgroup::serialize(&presentation),
+                        ..Default::default()
+                    }),
+                    join_from_invite_link: false,
+                })
+            }
+            GroupChangeAction::DeleteMember(aci) => {
+                result.delete_members.push(DeleteMemberAction {
+                    deleted_user_id: encrypt_aci(*aci),
+                })
+            }
+            GroupChangeAction::ModifyMemberRole(aci, role) => {
+                result.modify_member_roles.push(ModifyMemberRoleAction {
+                    user_id: encrypt_aci(*aci),
+                    role: member::Role::from(*role).into(),
+                })
+            }
+            GroupChangeAction::ModifyTitle(title) => {
+                result.modify_title = Some(ModifyTitleAction {
+                    title: cipher.encrypt_blob(Content::Title(title.clone()), rng),
+                })
+            }
+            GroupChangeAction::ModifyDescription(description) => {
+                result.modify_description = Some(ModifyDescriptionAction {
+                    description: cipher
+                        .encrypt_blob(Content::DescriptionText(description.clone()), rng),
+                })
+            }
+            GroupChangeAction::ModifyDisappearingMessagesTimer(duration) => {
+                result.modify_disappearing_messages_timer =
+                    Some(ModifyDisappearingMessagesTimerAction {
+                        timer: cipher
+                            .encrypt_blob(Content::DisappearingMessagesDuration(*duration), rng),
+                    })
+            }
+        }
+    }
+    result
+}
+
+/// Checks the server's signature on a change and decrypts it.
+///
+/// Use this for changes received from other members, which could otherwise
+/// be forged.
+pub fn decrypt_signed_change(
+    server_public_params: &ServerPublicParams,
+    secret_params: &GroupSecretParams,
+    signed_change: &[u8],
+) -> Result<DecryptedGroupChange, GroupsError> {
+    let change = GroupChange::decode(signed_change).map_err(|_| GroupsError::DecryptionFailed)?;
+    let signature = change
+        .server_signature
+        .as_slice()
+        .try_into()
+        .map_err(|_| GroupsError::InvalidSignature)?;
+    server_public_params
+        .verify_signature(&change.actions, signature)
+        .map_err(|_| GroupsError::InvalidSignature)?;
+    decrypt_change(secret_params, &change)
+}
+
+fn decrypt_change(
+    secret_params: &GroupSecretParams,
+    change: &GroupChange,
+) -> Result<DecryptedGroupChange, GroupsError> {
+    let actions = Actions::decode(&*change.actions).map_err(|_| GroupsError::DecryptionFailed)?;
+    let mut decrypted = DecryptedGroupChange::decrypt(secret_params, &actions)?;
+    decrypted.unsupported_actions =
+        unmodeled_action_fields(&change.actions).ok_or(GroupsError::DecryptionFailed)?;
+    Ok(decrypted)
+}
+
+fn random_bytes(rng: &mut (impl RngCore + CryptoRng)) -> zkgroup::RandomnessBytes {
+    let mut randomness = [0; zkgroup::RANDOMNESS_LEN];
+    rng.fill_bytes(&mut randomness);
+    randomness
+}
+
+/// Client for the groups server.
+pub struct GroupsClient<C, T> {
+    endpoint: HttpEndpoint<C, T>,
+    server_public_params: ServerPublicParams,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> GroupsClient<C, T> {
+    pub fn new(
+        connection_manager: C,
+        transport_connector: T,
+        server_public_params: ServerPublicParams,
+    ) -> Self {
+        Self {
+            endpoint: HttpEndpoint::new(
+                connection_manager,
+                transport_connector,
+                DEFAULT_MAX_RESPONSE_SIZE,
+            ),
+            server_public_params,
+        }
+    }
+
+    /// Fetches and decrypts the current state of the group.
+    pub async fn fetch_group(
+        &self,
+        secret_params: &GroupSecretParams,
+        credential: &AuthCredentialWithPni,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<DecryptedGroup, GroupsError> {
+        let headers = self.auth_headers(secret_params, credential, rng);
+        let (_parts, body) = self
+            .endpoint
+            .send(
+                Method::GET,
+                PathAndQuery::from_static(GROUP_PATH),
+                headers,
+                Bytes::new(),
+            )
+            .await?;
+        let group = GroupResponse::decode(body)
+            .ok()
+            .and_then(|response| response.group)
+            .ok_or(RequestError::InvalidResponse)?;
+        DecryptedGroup::decrypt(secret_params, &group)
+    }
+
+    /// Fetches and decrypts the changes starting with the one to
+    /// `from_version`.
+    ///
+    /// The server may split the log into pages; fetch again starting after
+    /// the last change while [`GroupChangePage::has_more`] is set.
+    pub async fn fetch_changes(
+        &self,
+        secret_params: &GroupSecretParams,
+        credential: &AuthCredentialWithPni,
+        from_version: u32,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<GroupChangePage, GroupsError> {
+        let headers = self.auth_headers(secret_params, credential, rng);
+        let path = PathAndQuery::from_str(&format!(
+            "{GROUP_LOGS_PATH}/{from_version}?maxSupportedChangeEpoch={MAX_SUPPORTED_CHANGE_EPOCH}&includeFirstState=false&includeLastState=false"
+        ))
+        .expect("valid path");
+        let (parts, body) = self
+            .endpoint
+            .send(Method::GET, path, headers, Bytes::new())
+            .await?;
+        let changes = GroupChanges::decode(body).map_err(|_| RequestError::InvalidResponse)?;
+
+        // The change log comes straight from the server, so the signatures
+        // don't need to be checked.
+        let changes = changes
+            .group_changes
+            .iter()
+            .filter_map(|state| state.group_change.as_ref())
+            .map(|change| decrypt_change(secret_params, change))
+            .collect::<Result<_, _>>()?;
+        Ok(GroupChangePage {
+            changes,
+            has_more: parts.status == StatusCode::PARTIAL_CONTENT,
+        })
+    }
+
+    /// Encrypts `actions` and submits them as the change to `version`.
+    ///
+    /// Fails with [`GroupsError::Conflict`] if someone else changed the group
+    /// first.
+    pub async fn modify_group(
+        &self,
+        secret_params: &GroupSecretParams,
+        credential: &AuthCredentialWithPni,
+        version: u32,
+        actions: &[GroupChangeAction],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<AppliedGroupChange, GroupsError> {
+        let actions = encrypt_actions(
+            &self.server_public_params,
+            secret_params,
+            version,
+            actions,
+            rng,
+        );
+        let mut headers = self.auth_headers(secret_params, credential, rng);
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static(PROTOBUF_CONTENT_TYPE),
+        );
+
+        let (_parts, body) = self
+            .endpoint
+            .send(
+                Method::PATCH,
+                PathAndQuery::from_static(GROUP_PATH),
+                headers,
+                Bytes::from(actions.encode_to_vec()),
+            )
+            .await
+            .map_err(|e| match e {
+                RequestError::UnexpectedStatus(StatusCode::CONFLICT) => GroupsError::Conflict,
+                e => e.into(),
+            })?;
+        let signed_change = GroupChangeResponse::decode(body)
+            .ok()
+            .and_then(|response| response.group_change)
+            .ok_or(RequestError::InvalidResponse)?
+            .encode_to_vec();
+
+        let change =
+            decrypt_signed_change(&self.server_public_params, secret_params, &signed_change)?;
+        Ok(AppliedGroupChange {
+            change,
+            signed_change,
+        })
+    }
+
+    /// Presents `credential` for the group, as the server requires on every
+    /// request.
+    fn auth_headers(
+        &self,
+        secret_params: &GroupSecretParams,
+        credential: &AuthCredentialWithPni,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> HeaderMap {
+        let presentation = self
+            .server_public_params
+            .create_auth_credential_with_pni_presentation(
+                random_bytes(rng),
+                *secret_params,
+                credential.clone(),
+            );
+        HeaderMap::from_iter([(
+            http::header::AUTHORIZATION,
+            basic_authorization(
+                &hex::encode(zkgroup::serialize(&secret_params.get_public_params())),
+                &hex::encode(zkgroup::serialize(&presentation)),
+            ),
+        )])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use rand::rngs::OsRng;
+    use zkgroup::{ServerSecretParams, Timestamp};
+
+    use super::*;
+
+    const ALICE: Aci = Aci::from_uuid_bytes([0xa1; 16]);
+    const BOB: Aci = Aci::from_uuid_bytes([0xb0; 16]);
+
+    fn member(
+        secret_params: &GroupSecretParams,
+        aci: Aci,
+        role: MemberRole,
+        profile_key: ProfileKey,
+    ) -> Member {
+        Member {
+            user_id: zkgroup::serialize(&secret_params.encrypt_service_id(aci.into())),
+            role: member::Role::from(role).into(),
+            profile_key: zkgroup::serialize(&secret_params.encrypt_profile_key(profile_key, aci)),
+            presentation: vec![],
+            joined_at_version: 0,
+        }
+    }
+
+    #[test]
+    fn decrypt_group_and_apply_changes() {
+        let server_secret_params = ServerSecretParams::generate([1; 32]);
+        let server_public_params = server_secret_params.get_public_params();
+        let secret_params = GroupSecretParams::generate([2; 32]);
+        let cipher = GroupCipher(&secret_params);
+        let alice_key = ProfileKey::create([0xa; 32]);
+        let bob_key = ProfileKey::create([0xb; 32]);
+
+        let group = Group {
+            version: 0,
+            title: cipher.encrypt_blob(Content::Title("Crabs".to_owned()), &mut OsRng),
+            members: vec![member(
+                &secret_params,
+                ALICE,
+                MemberRole::Administrator,
+                alice_key,
+            )],
+            ..Default::default()
+        };
+        let mut decrypted = DecryptedGroup::decrypt(&secret_params, &group).expect("valid");
+        assert_eq!(decrypted.title, "Crabs");
+        assert_eq!(decrypted.description, "");
+        assert_eq!(decrypted.disappearing_messages_timer, 0);
+        assert_eq!(decrypted.members[0].aci, ALICE);
+        assert_eq!(decrypted.members[0].profile_key, alice_key);
+
+        // Build a change the way the client would, then fill in the new member
+        // the way the server would.
+        let mut actions = encrypt_actions(
+            &server_public_params,
+            &secret_params,
+            1,
+            &[
+                GroupChangeAction::ModifyTitle("Lobsters".to_owned()),
+                GroupChangeAction::ModifyDisappearingMessagesTimer(3600),
+                GroupChangeAction::ModifyMemberRole(ALICE, MemberRole::Default),
+            ],
+            &mut OsRng,
+        );
+        assert_eq!(actions.group_id, secret_params.get_group_identifier());
+        actions.source_uuid = zkgroup::serialize(&secret_params.encrypt_service_id(ALICE.into()));
+        actions.add_members.push(AddMemberAction {
+            added: Some(member(&secret_params, BOB, MemberRole::Default, bob_key)),
+            join_from_invite_link: false,
+        });
+        let actions = actions.encode_to_vec();
+        let signed_change = GroupChange {
+            server_signature: server_secret_params.sign([3; 32], &actions).to_vec(),
+            actions,
+            change_epoch: 0,
+        }
+        .encode_to_vec();
+
+        let change = decrypt_signed_change(&server_public_params, &secret_params, &signed_change)
+            .expect("valid");
+        assert_eq!(change.editor, Some(ALICE.into()));
+        assert_eq!(change.new_title.as_deref(), Some("Lobsters"));
+        assert_eq!(change.new_description, None);
+
+        decrypted.apply_change(&change).expect("next version");
+        assert_eq!(decrypted.version, 1);
+        assert_eq!(decrypted.title, "Lobsters");
+        assert_eq!(decrypted.disappearing_messages_timer, 3600);
+        assert_eq!(
+            decrypted
+                .members
+                .iter()
+                .map(|member| (member.aci, member.role))
+                .collect::<Vec<_>>(),
+            [(ALICE, MemberRole::Default), (BOB, MemberRole::Default)]
+        );
+
+        assert_matches!(
+            decrypted.apply_change(&change),
+            Err(GroupsError::VersionMismatch {
+                current: 1,
+                change: 1
+            })
+        );
+    }
+
+    #[test]
+    fn changes_with_unmodeled_actions_are_not_applied() {
+        let server_secret_params = ServerSecretParams::generate([1; 32]);
+        let secret_params = GroupSecretParams::generate([2; 32]);
+        let group = Group {
+            version: 0,
+            ..Default::default()
+        };
+        let mut decrypted = DecryptedGroup::decrypt(&secret_params, &group).expect("valid");
+
+        let mut actions = Actions {
+            version: 1,
+            ..Default::default()
+        }
+        .encode_to_vec();
+        // modifyAvatar (field 11, length-delimited), containing an empty avatar (field 1).
+        actions.extend([0x5a, 2, 0x0a, 0]);
+        let signed_change = GroupChange {
+            server_signature: server_secret_params.sign([3; 32], &actions).to_vec(),
+            actions,
+            change_epoch: 0,
+        }
+        .encode_to_vec();
+
+        let change = decrypt_signed_change(
+            &server_secret_params.get_public_params(),
+            &secret_params,
+            &signed_change,
+        )
+        .expect("valid");
+        assert_eq!(change.unsupported_actions, [11]);
+        assert_matches!(
+            decrypted.apply_change(&change),
+            Err(GroupsError::UnsupportedAction(11))
+        );
+        assert_eq!(decrypted.version, 0);
+    }
+
+    #[test]
+    fn version_does_not_overflow() {
+        let secret_params = GroupSecretParams::generate([2; 32]);
+        let group = Group {
+            version: u32::MAX,
+            ..Default::default()
+        };
+        let mut decrypted = DecryptedGroup::decrypt(&secret_params, &group).expect("valid");
+        let change = DecryptedGroupChange {
+            version: 0,
+            ..Default::default()
+        };
+        assert_matches!(
+            decrypted.apply_change(&change),
+            Err(GroupsError::VersionMismatch {
+                current: u32::MAX,
+                change: 0
+            })
+        );
+    }
+
+    #[test]
+    fn signatures_are_checked() {
+        let server_secret_params = ServerSecretParams::generate([1; 32]);
+        let secret_params = GroupSecretParams::generate([2; 32]);
+        let actions = Actions {
+            version: 1,
+            ..Default::default()
+        }
+        .encode_to_vec();
+        let signed_change = GroupChange {
+            server_signature: ServerSecretParams::generate([4; 32])
+                .sign([3; 32], &actions)
+                .to_vec(),
+            actions,
+            change_epoch: 0,
+        }
+        .encode_to_vec();
+
+        assert_matches!(
+            decrypt_signed_change(
+                &server_secret_params.get_public_params(),
+                &secret_params,
+                &signed_change
+            ),
+            Err(GroupsError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn member_additions_present_credentials() {
+        let server_secret_params = ServerSecretParams::generate([1; 32]);
+        let server_public_params = server_secret_params.get_public_params();
+        let secret_params = GroupSecretParams::generate([2; 32]);
+        let bob_key = ProfileKey::create([0xb; 32]);
+
+        let context = server_public_params
+            .create_profile_key_credential_request_context([5; 32], BOB, bob_key);
+        let expiration = Timestamp::from_epoch_seconds(17 * 24 * 60 * 60);
+        let response = server_secret_params
+            .issue_expiring_profile_key_credential(
+                [6; 32],
+                &context.get_request(),
+                BOB,
+                bob_key.get_commitment(BOB),
+                expiration,
+            )
+            .expect("valid request");
+        let credential = server_public_params
+            .receive_expiring_profile_key_credential(
+                &context,
+                &response,
+                expiration.sub_seconds(24 * 60 * 60),
+            )
+            .expect("valid response");
+
+        let actions = encrypt_actions(
+            &server_public_params,
+            &secret_params,
+            7,
+            &[GroupChangeAction::AddMember {
+                credential,
+                role: MemberRole::Administrator,
+            }],
+            &mut OsRng,
+        );
+        let [AddMemberAction {
+            added: Some(added), ..
+        }] = &actions.add_members[..]
+        else {
+            panic!("expected one added member: {:?}", actions.add_members);
+        };
+        assert_eq!(added.role(), member::Role::Administrator);
+        assert!(added.user_id.is_empty());
+
+        let presentation: zkgroup::profiles::AnyProfileKeyCredentialPresentation =
+            zkgroup::profiles::AnyProfileKeyCredentialPresentation::new(&added.presentation)
+                .expect("valid presentation");
+        server_secret_params
+            .verify_profile_key_credential_presentation(
+                secret_params.get_public_params(),
+                &presentation,
+                expiration.sub_seconds(24 * 60 * 60),
+            )
+            .expect("verifies");
+    }
+}
@@ -0,0 +1,217 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Group invite links.
+//!
+//! An invite link carries the group's master key along with a password that
+//! the groups server checks before letting someone join. The link itself is
+//! `https://signal.group/#` followed by the URL-safe base64 encoding of a
+//! `GroupInviteLink` message.
+
+use base64::prelude::{Engine as _, BASE64_URL_SAFE_NO_PAD};
+use prost::Message as _;
+use rand_core::{CryptoRng, RngCore};
+use zkgroup::groups::GroupMasterKey;
+
+use crate::proto::groups::group_invite_link::{Contents, GroupInviteLinkContentsV1};
+
+pub const INVITE_LINK_PASSWORD_LEN: usize = 16;
+
+const INVITE_LINK_URL_PREFIX: &str = "https://signal.group/#";
+/// Also accepted when parsing, for links handed over by the OS.
+const INVITE_LINK_SGNL_PREFIX: &str = "sgnl://signal.group/#";
+
+#[derive(Debug, thiserror::Error, displaydoc::Display, PartialEq, Eq)]
+pub enum InviteLinkError {
+    /// not a group invite link
+    NotAnInviteLink,
+    /// invite link contents are not valid base64 or protobuf
+    InvalidEncoding,
+    /// invite link is in an unsupported format
+    UnsupportedVersion,
+    /// invite link has an invalid group master key
+    InvalidMasterKey,
+    /// invite link password must be 16 bytes
+    InvalidPassword,
+}
+
+/// The password that must be presented to join a group through its invite
+/// link.
+///
+/// The password is random rather than derived from the master key, so that
+/// resetting the link revokes the old one without changing the group.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct InviteLinkPassword([u8; INVITE_LINK_PASSWORD_LEN]);
+
+impl InviteLinkPassword {
+    pub fn generate(rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut password = [0; INVITE_LINK_PASSWORD_LEN];
+        rng.fill_bytes(&mut password);
+        Self(password)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; INVITE_LINK_PASSWORD_LEN] {
+        &self.0
+    }
+
+    /// The URL-safe base64 encoding used in the groups server's join paths.
+    pub fn to_url_safe_base64(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(self.0)
+    }
+
+    pub fn from_url_safe_base64(encoded: &str) -> Result<Self, InviteLinkError> {
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(encoded)
+            .map_err(|_| InviteLinkError::InvalidPassword)?;
+        Self::try_from(&bytes[..])
+    }
+}
+
+impl TryFrom<&[u8]> for InviteLinkPassword {
+    type Error = InviteLinkError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| InviteLinkError::InvalidPassword)
+    }
+}
+
+impl std::fmt::Debug for InviteLinkPassword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("InviteLinkPassword([REDACTED])")
+    }
+}
+
+/// The contents of a group invite link.
+#[derive(Clone, Copy)]
+pub struct GroupInviteLink {
+    pub master_key: GroupMasterKey,
+    pub password: InviteLinkPassword,
+}
+
+impl GroupInviteLink {
+    /// Creates a link for the group with `master_key` with a new password.
+    pub fn generate(master_key: GroupMasterKey, rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        Self {
+            master_key,
+            password: InviteLinkPassword::generate(rng),
+        }
+    }
+
+    /// The URL-safe base64 encoding of the link's `GroupInviteLink` message.
+    pub fn to_payload(&self) -> String {
+        let link = crate::proto::groups::GroupInviteLink {
+            contents: Some(Contents::ContentsV1(GroupInviteLinkContentsV1 {
+                group_master_key: zkgroup::serialize(&self.master_key),
+                invite_link_password: self.password.0.to_vec(),
+            })),
+        };
+        BASE64_URL_SAFE_NO_PAD.encode(link.encode_to_vec())
+    }
+
+    pub fn from_payload(payload: &str) -> Result<Self, InviteLinkError> {
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| InviteLinkError::InvalidEncoding)?;
+        let link = crate::proto::groups::GroupInviteLink::decode(&*bytes)
+            .map_err(|_| InviteLinkError::InvalidEncoding)?;
+        // Links from before the versioned format, or from a newer one, have
+        // no V1 contents.
+        let Some(Contents::ContentsV1(GroupInviteLinkContentsV1 {
+            group_master_key,
+            invite_link_password,
+        })) = link.contents
+        else {
+            return Err(InviteLinkError::UnsupportedVersion);
+        };
+        let master_key = <[u8; zkgroup::GROUP_MASTER_KEY_LEN]>::try_from(group_master_key)
+            .map(GroupMasterKey::new)
+            .map_err(|_| InviteLinkError::InvalidMasterKey)?;
+        Ok(Self {
+            master_key,
+            password: InviteLinkPassword::try_from(&invite_link_password[..])?,
+        })
+    }
+
+    /// The full `https://signal.group/#...` URL to share.
+    pub fn to_url(&self) -> String {
+        format!("{INVITE_LINK_URL_PREFIX}{}", self.to_payload())
+    }
+
+    pub fn from_url(url: &str) -> Result<Self, InviteLinkError> {
+        let payload = [INVITE_LINK_URL_PREFIX, INVITE_LINK_SGNL_PREFIX]
+            .into_iter()
+            .find_map(|prefix| url.strip_prefix(prefix))
+            .ok_or(InviteLinkError::NotAnInviteLink)?;
+        Self::from_payload(payload)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use rand::rngs::OsRng;
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test]
+    fn url_round_trip() {
+        let link = GroupInviteLink::generate(GroupMasterKey::new([0x42; 32]), &mut OsRng);
+        let url = link.to_url();
+        assert!(url.starts_with(INVITE_LINK_URL_PREFIX), "{url}");
+        assert!(!url.contains(['+', '/', '=']), "{url}");
+
+        let parsed = GroupInviteLink::from_url(&url).expect("valid");
+        assert_eq!(
+            zkgroup::serialize(&parsed.master_key),
+            zkgroup::serialize(&link.master_key)
+        );
+        assert_eq!(parsed.password, link.password);
+
+        let sgnl = url.replace(INVITE_LINK_URL_PREFIX, INVITE_LINK_SGNL_PREFIX);
+        assert_eq!(
+            GroupInviteLink::from_url(&sgnl).expect("valid").password,
+            link.password
+        );
+
+        assert_eq!(
+            InviteLinkPassword::from_url_safe_base64(&link.password.to_url_safe_base64()),
+            Ok(link.password)
+        );
+    }
+
+    #[test_case("https://signal.org/#abc" => InviteLinkError::NotAnInviteLink; "wrong host")]
+    #[test_case("https://signal.group/#!!!" => InviteLinkError::InvalidEncoding; "not base64")]
+    #[test_case("https://signal.group/#" => InviteLinkError::UnsupportedVersion; "no contents")]
+    fn invalid_urls(url: &str) -> InviteLinkError {
+        assert_matches!(GroupInviteLink::from_url(url), Err(e) => e)
+    }
+
+    #[test]
+    fn invalid_contents() {
+        let encode = |group_master_key: Vec<u8>, invite_link_password: Vec<u8>| {
+            BASE64_URL_SAFE_NO_PAD.encode(
+                crate::proto::groups::GroupInviteLink {
+                    contents: Some(Contents::ContentsV1(GroupInviteLinkContentsV1 {
+                        group_master_key,
+                        invite_link_password,
+                    })),
+                }
+                .encode_to_vec(),
+            )
+        };
+        assert_matches!(
+            GroupInviteLink::from_payload(&encode(vec![1; 31], vec![2; 16])),
+            Err(InviteLinkError::InvalidMasterKey)
+        );
+        assert_matches!(
+            GroupInviteLink::from_payload(&encode(vec![1; 32], vec![2; 15])),
+            Err(InviteLinkError::InvalidPassword)
+        );
+    }
+}
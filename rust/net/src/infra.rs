@@ -21,16 +21,17 @@ use crate::infra::connection_manager::{
 };
 use crate::infra::errors::TransportConnectError;
 use crate::infra::host::Host;
-use crate::infra::ws::WebSocketConfig;
+use crate::infra::ws::{ResponseLimits, WebSocketConfig};
 use crate::timeouts::{WS_KEEP_ALIVE_INTERVAL, WS_MAX_IDLE_INTERVAL};
 use crate::utils::ObservableEvent;
 
+pub mod attempt_log;
 pub mod certs;
 pub mod connection_manager;
 pub mod dns;
 pub mod errors;
 pub mod host;
-mod http_client;
+pub(crate) mod http_client;
 pub mod noise;
 pub(crate) mod service;
 pub mod tcp_ssl;
@@ -112,6 +113,33 @@ impl ConnectionParams {
         self.connection_confirmation_header = Some(header);
         self
     }
+
+    /// Makes this route connect through the TLS proxy at `proxy_addr`.
+    ///
+    /// Only [`TcpSslConnector::Direct`](tcp_ssl::TcpSslConnector::Direct) honors per-route
+    /// proxies; a connector that is already proxied keeps using its own proxy for every route.
+    pub fn via_proxy(mut self, proxy_addr: (Host<Arc<str>>, NonZeroU16)) -> Self {
+        self.route_type = RouteType::TlsProxy;
+        self.transport.proxy = Some(proxy_addr);
+        self
+    }
+
+    /// Makes this route connect to `ip` and `port` without a DNS lookup.
+    ///
+    /// The TLS SNI and the HTTP `Host` header are left as they were, so the server still sees
+    /// the usual host name. This is for testing against local servers, and for deployments that
+    /// distribute server addresses out-of-band when DNS is blocked.
+    pub fn with_address_override(mut self, ip: IpAddr, port: NonZeroU16) -> Self {
+        self.transport.tcp_host = Host::Ip(ip);
+        self.transport.port = port;
+        self
+    }
+
+    /// Marks this route's TCP connections with `traffic_class`.
+    pub fn with_traffic_class(mut self, traffic_class: TrafficClass) -> Self {
+        self.transport.traffic_class = traffic_class;
+        self
+    }
 }
 
 /// Contains all information required to establish a TLS connection to a remote endpoint.
@@ -125,6 +153,76 @@ pub struct TransportConnectionParams {
     pub port: NonZeroU16,
     /// Trusted certificates for this connection.
     pub certs: RootCertificates,
+    /// If present, the connection is made through this TLS proxy instead of directly.
+    ///
+    /// This lets a single list of routes mix direct and proxied connections; see
+    /// [`ConnectionParams::via_proxy`].
+    pub proxy: Option<(Host<Arc<str>>, NonZeroU16)>,
+    /// Socket options describing the kind of traffic the connection carries.
+    pub traffic_class: TrafficClass,
+}
+
+/// Socket-level hints about the kind of traffic a connection carries.
+///
+/// These let the OS, and any network equipment that honors DSCP markings,
+/// tell libsignal's latency-sensitive flows apart from its bulk transfers.
+/// They're applied by the TCP connector before connecting, to the socket for
+/// the first hop, which is the proxy for a proxied route. Both are hints:
+/// failing to set them doesn't fail the connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrafficClass {
+    /// The Differentiated Services code point for outgoing packets.
+    ///
+    /// This is set as the IPv4 TOS or IPv6 traffic class on platforms that
+    /// allow it, and ignored elsewhere. `None` leaves the OS default.
+    pub dscp: Option<Dscp>,
+    /// Whether to disable Nagle's algorithm with `TCP_NODELAY`.
+    pub nodelay: bool,
+}
+
+impl TrafficClass {
+    /// Small messages that someone is waiting on, like chat traffic.
+    pub const INTERACTIVE: Self = Self {
+        dscp: Some(Dscp::LOW_LATENCY_DATA),
+        nodelay: true,
+    };
+
+    /// Large transfers like attachment and backup uploads, where throughput
+    /// matters more than latency.
+    pub const BULK: Self = Self {
+        dscp: Some(Dscp::HIGH_THROUGHPUT_DATA),
+        nodelay: false,
+    };
+}
+
+/// A 6-bit Differentiated Services code point, as defined in RFC 2474.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Dscp(u8);
+
+impl Dscp {
+    /// AF21, recommended for low-latency data by RFC 4594.
+    pub const LOW_LATENCY_DATA: Self = Self(18);
+    /// AF11, recommended for high-throughput data by RFC 4594.
+    pub const HIGH_THROUGHPUT_DATA: Self = Self(10);
+
+    /// Returns `None` if `value` doesn't fit in 6 bits.
+    pub const fn new(value: u8) -> Option<Self> {
+        if value < 64 {
+            Some(Self(value))
+        } else {
+            None
+        }
+    }
+
+    pub const fn value(self) -> u8 {
+        self.0
+    }
+
+    /// The value of the IPv4 TOS or IPv6 traffic class byte carrying this code
+    /// point, which also holds the two ECN bits.
+    pub const fn as_traffic_class_byte(self) -> u8 {
+        self.0 << 2
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -178,6 +276,8 @@ pub enum RouteType {
     TlsProxy,
     /// Connection over a SOCKS proxy
     SocksProxy,
+    /// Connection over a domain front provided by the server
+    DiscoveredFront,
     /// Test-only value
     #[cfg(test)]
     Test,
@@ -278,6 +378,7 @@ impl AsRef<[u8]> for Alpn {
     }
 }
 
+#[derive(Clone)]
 pub struct EndpointConnection<C> {
     pub manager: C,
     pub config: WebSocketConfig,
@@ -306,6 +407,34 @@ impl EndpointConnection<MultiRouteConnectionManager> {
             config,
         }
     }
+
+    /// Returns a connection that also tries `discovered_params` for the next `valid_for`.
+    ///
+    /// See [`MultiRouteConnectionManager::with_discovered_routes`].
+    pub fn with_discovered_routes(
+        &self,
+        discovered_params: impl IntoIterator<Item = ConnectionParams>,
+        valid_for: Duration,
+        one_route_connect_timeout: Duration,
+        network_changed_event: &ObservableEvent,
+    ) -> Self {
+        Self {
+            manager: self.manager.with_discovered_routes(
+                discovered_params
+                    .into_iter()
+                    .map(|params| {
+                        SingleRouteThrottlingConnectionManager::new(
+                            params,
+                            one_route_connect_timeout,
+                            network_changed_event,
+                        )
+                    })
+                    .collect(),
+                tokio::time::Instant::now() + valid_for,
+            ),
+            config: self.config.clone(),
+        }
+    }
 }
 
 pub fn make_ws_config(
@@ -318,6 +447,7 @@ pub fn make_ws_config(
         max_connection_time: connect_timeout,
         keep_alive_interval: WS_KEEP_ALIVE_INTERVAL,
         max_idle_time: WS_MAX_IDLE_INTERVAL,
+        response_limits: ResponseLimits::default(),
     }
 }
 
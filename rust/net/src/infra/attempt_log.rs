@@ -0,0 +1,216 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! A record of recent connection attempts, for diagnosing failures after the
+//! fact.
+//!
+//! Every attempt made through a
+//! [`SingleRouteThrottlingConnectionManager`](crate::infra::connection_manager::SingleRouteThrottlingConnectionManager)
+//! is added to a process-wide ring buffer holding the last
+//! [`MAX_RECORDED_ATTEMPTS`] entries. Entries only contain log-safe
+//! information, so apps can attach [`recent_attempts`] to bug reports without
+//! turning on verbose logging.
+
+use std::collections::VecDeque;
+use std::num::NonZeroU16;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::infra::connection_manager::{ConnectionAttemptOutcome, ErrorClassifier};
+use crate::infra::errors::LogSafeDisplay;
+use crate::infra::host::Host;
+use crate::infra::{ConnectionParams, RouteType};
+
+/// How many attempts are kept before the oldest ones are dropped.
+pub const MAX_RECORDED_ATTEMPTS: usize = 64;
+
+/// The step of establishing a connection where an attempt failed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ConnectionStage {
+    Dns,
+    Tcp,
+    Proxy,
+    Tls,
+    /// The HTTP, HTTP/2, or websocket handshake.
+    Http,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttemptResult {
+    Connected,
+    TimedOut,
+    Failed {
+        /// `None` if the error doesn't say how far the attempt got.
+        stage: Option<ConnectionStage>,
+        /// The log-safe description of the error.
+        error: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectionAttempt {
+    pub started_at: SystemTime,
+    pub duration: Duration,
+    pub route_type: RouteType,
+    /// The server's host name or IP address; never the address of a proxy.
+    pub address: Host<Arc<str>>,
+    pub port: NonZeroU16,
+    pub result: AttemptResult,
+}
+
+struct AttemptLog {
+    entries: VecDeque<ConnectionAttempt>,
+    capacity: usize,
+}
+
+impl AttemptLog {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn record(&mut self, attempt: ConnectionAttempt) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(attempt);
+    }
+}
+
+static ATTEMPT_LOG: Mutex<AttemptLog> = Mutex::new(AttemptLog::new(MAX_RECORDED_ATTEMPTS));
+
+/// Returns the recorded connection attempts, oldest first.
+pub fn recent_attempts() -> Vec<ConnectionAttempt> {
+    ATTEMPT_LOG
+        .lock()
+        .expect("not poisoned")
+        .entries
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// Records the outcome of an attempt to connect with `params`.
+///
+/// Nothing is recorded if the attempt was skipped because the route was in
+/// cooldown.
+pub(crate) fn record_attempt<T, E: LogSafeDisplay + ErrorClassifier>(
+    params: &ConnectionParams,
+    started_at: SystemTime,
+    duration: Duration,
+    outcome: &ConnectionAttemptOutcome<T, E>,
+) {
+    let result = match outcome {
+        ConnectionAttemptOutcome::Attempted(Ok(_)) => AttemptResult::Connected,
+        ConnectionAttemptOutcome::Attempted(Err(e)) => AttemptResult::Failed {
+            stage: e.stage_reached(),
+            error: e.to_string(),
+        },
+        ConnectionAttemptOutcome::TimedOut => AttemptResult::TimedOut,
+        ConnectionAttemptOutcome::WaitUntil(_) => return,
+    };
+    ATTEMPT_LOG
+        .lock()
+        .expect("not poisoned")
+        .record(ConnectionAttempt {
+            started_at,
+            duration,
+            route_type: params.route_type,
+            address: params.transport.tcp_host.clone(),
+            port: params.transport.port,
+            result,
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use std::future;
+
+    use nonzero_ext::nonzero;
+
+    use super::*;
+    use crate::infra::certs::RootCertificates;
+    use crate::infra::connection_manager::{
+        ConnectionManager, SingleRouteThrottlingConnectionManager,
+    };
+    use crate::infra::errors::TransportConnectError;
+    use crate::infra::test::shared::TIMEOUT_DURATION;
+    use crate::infra::ws::WebSocketConnectError;
+    use crate::infra::{HttpRequestDecoratorSeq, TrafficClass, TransportConnectionParams};
+    use crate::utils::ObservableEvent;
+
+    fn attempt(port: u16) -> ConnectionAttempt {
+        ConnectionAttempt {
+            started_at: SystemTime::UNIX_EPOCH,
+            duration: Duration::ZERO,
+            route_type: RouteType::Test,
+            address: Host::Domain("chat.signal.org".into()),
+            port: NonZeroU16::new(port).expect("non-zero"),
+            result: AttemptResult::Connected,
+        }
+    }
+
+    #[test]
+    fn oldest_attempts_are_dropped() {
+        let mut log = AttemptLog::new(3);
+        for port in 1..=5 {
+            log.record(attempt(port));
+        }
+        let ports = log
+            .entries
+            .iter()
+            .map(|attempt| attempt.port.get())
+            .collect::<Vec<_>>();
+        assert_eq!(ports, [3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn connection_manager_records_attempts() {
+        // The log is shared by every test, so use a host name no other test does.
+        let host: Arc<str> = "attempt-log.signal.org".into();
+        let manager = SingleRouteThrottlingConnectionManager::new(
+            ConnectionParams {
+                route_type: RouteType::Test,
+                transport: TransportConnectionParams {
+                    sni: Arc::clone(&host),
+                    tcp_host: Host::Domain(Arc::clone(&host)),
+                    port: nonzero!(443u16),
+                    certs: RootCertificates::Signal,
+                    proxy: None,
+                    traffic_class: TrafficClass::default(),
+                },
+                http_host: Arc::clone(&host),
+                http_request_decorator: HttpRequestDecoratorSeq::default(),
+                connection_confirmation_header: None,
+            },
+            TIMEOUT_DURATION,
+            &ObservableEvent::default(),
+        );
+
+        // Go through the trait, like the other connection managers do.
+        let _ = ConnectionManager::connect_or_wait(&manager, |_| {
+            future::ready(Err::<(), _>(WebSocketConnectError::Transport(
+                TransportConnectError::TcpConnectionFailed,
+            )))
+        })
+        .await;
+
+        let recorded = recent_attempts()
+            .into_iter()
+            .filter(|attempt| attempt.address == Host::Domain(Arc::clone(&host)))
+            .map(|attempt| attempt.result)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            recorded,
+            [AttemptResult::Failed {
+                stage: Some(ConnectionStage::Tcp),
+                error: "transport: Failed to establish TCP connection to any of the IPs".to_owned(),
+            }]
+        );
+    }
+}
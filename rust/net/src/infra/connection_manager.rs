@@ -9,13 +9,14 @@ use std::future::Future;
 use std::ops::Add;
 use std::panic::RefUnwindSafe;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 use itertools::Itertools;
 use tokio::sync::Mutex;
 use tokio::time::{timeout_at, Instant};
 
+use crate::infra::attempt_log::{self, ConnectionStage};
 use crate::infra::errors::LogSafeDisplay;
 use crate::infra::ConnectionParams;
 use crate::timeouts::{CONNECTION_ROUTE_COOLDOWN_INTERVALS, CONNECTION_ROUTE_MAX_COOLDOWN};
@@ -186,14 +187,55 @@ pub struct SingleRouteThrottlingConnectionManager<C = ConnectionParams> {
 /// and iterates over them until it can find one that results in a successful connection attempt.
 /// If none did, it will return [ConnectionAttemptOutcome::WaitUntil] with the minimum possible
 /// cooldown time (based on cooldown times returned by all throttling connection managers).
+///
+/// Routes provided by the server (see [`crate::route_discovery`]) are tried right after the first
+/// configured route, until they expire.
 #[derive(Clone)]
 pub struct MultiRouteConnectionManager<M = SingleRouteThrottlingConnectionManager> {
     route_managers: Vec<M>,
+    discovered_route_managers: Vec<M>,
+    discovered_routes_expire_at: Instant,
 }
 
 impl<M> MultiRouteConnectionManager<M> {
     pub fn new(route_managers: Vec<M>) -> Self {
-        Self { route_managers }
+        Self {
+            route_managers,
+            discovered_route_managers: Vec::new(),
+            discovered_routes_expire_at: Instant::now(),
+        }
+    }
+
+    /// Returns a manager that also tries `discovered_route_managers` until `expires_at`, replacing
+    /// any previously discovered routes.
+    ///
+    /// The configured routes are shared with `self`, so they keep their cooldown state.
+    pub fn with_discovered_routes(
+        &self,
+        discovered_route_managers: Vec<M>,
+        expires_at: Instant,
+    ) -> Self
+    where
+        M: Clone,
+    {
+        Self {
+            route_managers: self.route_managers.clone(),
+            discovered_route_managers,
+            discovered_routes_expire_at: expires_at,
+        }
+    }
+
+    fn routes_in_order(&self) -> impl Iterator<Item = &M> {
+        let discovered = if Instant::now() < self.discovered_routes_expire_at {
+            self.discovered_route_managers.as_slice()
+        } else {
+            &[]
+        };
+        let (first, rest) = self
+            .route_managers
+            .split_first()
+            .map_or((None, &[][..]), |(first, rest)| (Some(first), rest));
+        first.into_iter().chain(discovered).chain(rest)
     }
 }
 
@@ -221,7 +263,7 @@ where
         Fut: Future<Output = Result<T, E>> + Send,
     {
         let mut wait_until = None;
-        for route_manager in self.route_managers.iter() {
+        for route_manager in self.routes_in_order() {
             match retry_connect_until_cooldown(route_manager, &connection_fn).await {
                 Ok(t) => return ConnectionAttemptOutcome::Attempted(Ok(t)),
                 Err(RetryError::WaitUntil(i)) => {
@@ -241,8 +283,7 @@ where
     fn describe_for_logging(&self) -> String {
         format!(
             "multi-route: [{}]",
-            self.route_managers
-                .iter()
+            self.routes_in_order()
                 .map(ConnectionManager::describe_for_logging)
                 .join(", ")
         )
@@ -273,6 +314,11 @@ pub enum ErrorClass {
 
 pub trait ErrorClassifier {
     fn classify(&self) -> ErrorClass;
+
+    /// How far the failed connection attempt got, for the [attempt log](crate::infra::attempt_log).
+    fn stage_reached(&self) -> Option<ConnectionStage> {
+        None
+    }
 }
 
 async fn retry_connect_until_cooldown<'a, T, E, Fun, Fut>(
@@ -416,11 +462,20 @@ impl ConnectionManager for SingleRouteThrottlingConnectionManager {
     ) -> ConnectionAttemptOutcome<T, E>
     where
         T: Send,
-        E: Send,
+        E: Send + Debug + LogSafeDisplay + ErrorClassifier,
         Fun: Fn(&'a ConnectionParams) -> Fut + Send + Sync,
         Fut: Future<Output = Result<T, E>> + Send,
     {
-        self.connect_or_wait(connection_fn).await
+        let started_at = SystemTime::now();
+        let start = Instant::now();
+        let outcome = self.connect_or_wait(connection_fn).await;
+        attempt_log::record_attempt(
+            &self.connection_params,
+            started_at,
+            start.elapsed(),
+            &outcome,
+        );
+        outcome
     }
 
     fn describe_for_logging(&self) -> String {
@@ -444,7 +499,9 @@ mod test {
         ClassifiableTestError, TestError, FEW_ATTEMPTS, LONG_CONNECTION_TIME, MANY_ATTEMPTS,
         TIMEOUT_DURATION, TIME_ADVANCE_VALUE,
     };
-    use crate::infra::{HttpRequestDecoratorSeq, RouteType, TransportConnectionParams};
+    use crate::infra::{
+        HttpRequestDecoratorSeq, RouteType, TrafficClass, TransportConnectionParams,
+    };
 
     const ROUTE_THAT_TIMES_OUT: &str = "timeout.signal.org";
 
@@ -739,6 +796,37 @@ mod test {
         validate_expected_route(&multi_route_manager, true, ROUTE_1).await;
     }
 
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn multi_route_manager_tries_discovered_routes_until_they_expire() {
+        let timing_out_route_manager = SingleRouteThrottlingConnectionManager::new(
+            example_connection_params(ROUTE_THAT_TIMES_OUT),
+            TIMEOUT_DURATION,
+            &ObservableEvent::default(),
+        );
+        let manager_1 = SingleRouteThrottlingConnectionManager::new(
+            example_connection_params(ROUTE_1),
+            TIMEOUT_DURATION,
+            &ObservableEvent::default(),
+        );
+        let discovered_manager = SingleRouteThrottlingConnectionManager::new(
+            example_connection_params(ROUTE_2),
+            TIMEOUT_DURATION,
+            &ObservableEvent::default(),
+        );
+        let multi_route_manager =
+            MultiRouteConnectionManager::new(vec![timing_out_route_manager, manager_1])
+                .with_discovered_routes(
+                    vec![discovered_manager],
+                    Instant::now() + Duration::from_secs(3600),
+                );
+
+        time::advance(TIME_ADVANCE_VALUE).await;
+        validate_expected_route(&multi_route_manager, true, ROUTE_2).await;
+
+        time::advance(Duration::from_secs(3600)).await;
+        validate_expected_route(&multi_route_manager, true, ROUTE_1).await;
+    }
+
     #[derive(Clone, Debug)]
     struct CooldownAfterSomeAttempts {
         attempts_until_cooldown: u16,
@@ -988,6 +1076,8 @@ mod test {
                 tcp_host: Host::Domain(Arc::clone(&host)),
                 certs: RootCertificates::Signal,
                 port: nonzero!(443u16),
+                proxy: None,
+                traffic_class: TrafficClass::default(),
             },
             http_host: host,
             http_request_decorator: HttpRequestDecoratorSeq::default(),
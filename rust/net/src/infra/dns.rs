@@ -13,7 +13,7 @@ use nonzero_ext::nonzero;
 use oneshot_broadcast::Sender;
 use tokio::time::Instant;
 
-use super::TransportConnectionParams;
+use super::{TrafficClass, TransportConnectionParams};
 use crate::infra::certs::RootCertificates;
 use crate::infra::dns::custom_resolver::CustomDnsResolver;
 use crate::infra::dns::dns_errors::Error;
@@ -126,6 +126,8 @@ impl DnsResolver {
                 tcp_host: Host::Domain(Arc::clone(&host)),
                 sni: host,
                 certs: RootCertificates::Native,
+                proxy: None,
+                traffic_class: TrafficClass::default(),
             },
             http_request_decorator: HttpRequestDecoratorSeq::default(),
             connection_confirmation_header: None,
@@ -4,13 +4,54 @@
 //
 
 use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
 
 use tokio_boring_signal::HandshakeError;
 
+use crate::infra::attempt_log::ConnectionStage;
 use crate::infra::certs;
 
 pub trait LogSafeDisplay: Display {}
 
+/// The server is rate limiting requests; retry after {retry_after:?}
+///
+/// Produced from the `Retry-After` header of an HTTP 429 or 503 response, wherever the request was
+/// made.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, displaydoc::Display, thiserror::Error)]
+#[ignore_extra_doc_attributes]
+pub struct RetryLater {
+    pub retry_after: Duration,
+}
+
+impl LogSafeDisplay for RetryLater {}
+
+impl RetryLater {
+    /// Reads the delay from the `Retry-After` header of a 429 (Too Many Requests) or 503 (Service
+    /// Unavailable) response.
+    ///
+    /// Only the delay-seconds form is supported, since that's what Signal's servers send. Returns
+    /// `None` for any other status, or if the header is missing or can't be parsed.
+    pub(crate) fn from_response(
+        status: http::StatusCode,
+        headers: &http::HeaderMap,
+    ) -> Option<Self> {
+        if status != http::StatusCode::TOO_MANY_REQUESTS
+            && status != http::StatusCode::SERVICE_UNAVAILABLE
+        {
+            return None;
+        }
+        let seconds = headers
+            .get(http::header::RETRY_AFTER)?
+            .to_str()
+            .ok()
+            .and_then(|value| u32::from_str(value.trim()).ok())?;
+        Some(Self {
+            retry_after: Duration::from_secs(seconds.into()),
+        })
+    }
+}
+
 /// Errors that can occur during transport-level connection establishment.
 #[derive(displaydoc::Display, Debug, thiserror::Error)]
 pub enum TransportConnectError {
@@ -79,6 +120,20 @@ impl Display for FailedHandshakeReason {
     }
 }
 
+impl TransportConnectError {
+    pub(crate) fn stage_reached(&self) -> Option<ConnectionStage> {
+        match self {
+            Self::InvalidConfiguration => None,
+            Self::DnsError => Some(ConnectionStage::Dns),
+            Self::TcpConnectionFailed => Some(ConnectionStage::Tcp),
+            Self::SslError(_) | Self::CertError | Self::SslFailedHandshake(_) => {
+                Some(ConnectionStage::Tls)
+            }
+            Self::ProxyProtocol => Some(ConnectionStage::Proxy),
+        }
+    }
+}
+
 impl From<boring_signal::error::ErrorStack> for TransportConnectError {
     fn from(value: boring_signal::error::ErrorStack) -> Self {
         Self::SslError(SslErrorReasons(value))
@@ -11,6 +11,9 @@ use http_body_util::{BodyExt, Full, Limited};
 use hyper::client::conn::http2;
 use hyper_util::rt::{TokioExecutor, TokioIo};
 
+use crate::infra::attempt_log::ConnectionStage;
+use crate::infra::connection_manager::{ErrorClass, ErrorClassifier};
+use crate::infra::errors::LogSafeDisplay;
 use crate::infra::{Alpn, ConnectionParams, StreamAndInfo, TransportConnector};
 
 #[derive(displaydoc::Display, Debug)]
@@ -33,6 +36,38 @@ pub enum HttpError {
     ResponseTooLarge,
 }
 
+impl LogSafeDisplay for HttpError {}
+
+impl ErrorClassifier for HttpError {
+    fn classify(&self) -> ErrorClass {
+        match self {
+            // Failing to establish a connection over one route doesn't mean others will fail.
+            HttpError::SslHandshakeFailed | HttpError::Http2HandshakeFailed => {
+                ErrorClass::Intermittent
+            }
+            HttpError::FailedToCreateRequest
+            | HttpError::SendRequestError
+            | HttpError::ContentLengthHeaderInvalid
+            | HttpError::FailedToReadContentOfKnownSize
+            | HttpError::FailedToReadContentOfUnknownSize
+            | HttpError::ResponseTooLarge => ErrorClass::Fatal,
+        }
+    }
+
+    fn stage_reached(&self) -> Option<ConnectionStage> {
+        match self {
+            HttpError::SslHandshakeFailed => Some(ConnectionStage::Tls),
+            HttpError::Http2HandshakeFailed => Some(ConnectionStage::Http),
+            HttpError::FailedToCreateRequest
+            | HttpError::SendRequestError
+            | HttpError::ContentLengthHeaderInvalid
+            | HttpError::FailedToReadContentOfKnownSize
+            | HttpError::FailedToReadContentOfUnknownSize
+            | HttpError::ResponseTooLarge => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AggregatingHttp2Client {
     service: http2::SendRequest<Full<Bytes>>,
@@ -165,7 +200,7 @@ mod test {
     use crate::infra::host::Host;
     use crate::infra::tcp_ssl::testutil::{SERVER_CERTIFICATE, SERVER_HOSTNAME};
     use crate::infra::tcp_ssl::DirectConnector;
-    use crate::infra::{HttpRequestDecoratorSeq, TransportConnectionParams};
+    use crate::infra::{HttpRequestDecoratorSeq, TrafficClass, TransportConnectionParams};
 
     const FAKE_RESPONSE: &str = "RESPONSE";
     const FAKE_RESPONSE_HEADER: (HeaderName, HeaderValue) = (
@@ -234,6 +269,8 @@ mod test {
                     certs: crate::infra::certs::RootCertificates::FromDer(Cow::Borrowed(
                         SERVER_CERTIFICATE.cert.der(),
                     )),
+                    proxy: None,
+                    traffic_class: TrafficClass::default(),
                 },
                 http_host: host,
                 http_request_decorator: HttpRequestDecoratorSeq::default(),
@@ -307,6 +344,8 @@ mod test {
                     certs: crate::infra::certs::RootCertificates::FromDer(Cow::Borrowed(
                         SERVER_CERTIFICATE.cert.der(),
                     )),
+                    proxy: None,
+                    traffic_class: TrafficClass::default(),
                 },
                 http_host: host,
                 http_request_decorator: HttpRequestDecoratorSeq::default(),
@@ -17,7 +17,7 @@ use crate::chat::RemoteAddressInfo;
 use crate::infra::connection_manager::{
     ConnectionAttemptOutcome, ConnectionManager, ErrorClass, ErrorClassifier,
 };
-use crate::infra::errors::LogSafeDisplay;
+use crate::infra::errors::{LogSafeDisplay, RetryLater};
 use crate::infra::{ConnectionInfo, ConnectionParams, HttpRequestDecorator};
 
 // A duration where, if this is all that's left on the timeout, we're more likely to fail than not.
@@ -222,6 +222,8 @@ pub(crate) enum ConnectError<E: LogSafeDisplay> {
     AllRoutesFailed { attempts: u16 },
     /// Rejected by server: {0}
     RejectedByServer(E),
+    /// {0}
+    RetryLater(RetryLater),
 }
 
 impl<E: LogSafeDisplay> ErrorClassifier for ConnectError<E> {
@@ -231,6 +233,9 @@ impl<E: LogSafeDisplay> ErrorClassifier for ConnectError<E> {
                 ErrorClass::Intermittent
             }
             ConnectError::RejectedByServer(_) => ErrorClass::Fatal,
+            ConnectError::RetryLater(RetryLater { retry_after }) => {
+                ErrorClass::RetryAt(Instant::now() + *retry_after)
+            }
         }
     }
 }
@@ -366,6 +371,13 @@ where
                         }
                         ErrorClass::RetryAt(next_attempt_time) => {
                             *guard = ServiceState::Cooldown(next_attempt_time);
+                            if next_attempt_time > deadline_for_starting {
+                                // The server asked us to wait longer than we're willing to, so
+                                // let the caller know how long instead of giving up silently.
+                                let retry_after =
+                                    next_attempt_time.saturating_duration_since(Instant::now());
+                                return Err(ConnectError::RetryLater(RetryLater { retry_after }));
+                            }
                             continue;
                         }
                         ErrorClass::Fatal => {
@@ -432,7 +444,8 @@ mod test {
         TIME_ADVANCE_VALUE,
     };
     use crate::infra::{
-        ConnectionParams, HttpRequestDecoratorSeq, RouteType, TransportConnectionParams,
+        ConnectionParams, HttpRequestDecoratorSeq, RouteType, TrafficClass,
+        TransportConnectionParams,
     };
     use crate::timeouts::CONNECTION_ROUTE_MAX_COOLDOWN;
     use crate::utils::{sleep_and_catch_up, ObservableEvent};
@@ -507,6 +520,8 @@ mod test {
                 tcp_host: Host::Domain(Arc::clone(&host)),
                 port: nonzero!(443u16),
                 certs: RootCertificates::Signal,
+                proxy: None,
+                traffic_class: TrafficClass::default(),
             },
             http_host: host,
             http_request_decorator: HttpRequestDecoratorSeq::default(),
@@ -592,6 +607,24 @@ mod test {
             .is_err());
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn retry_after_from_server_is_returned() {
+        let (connector, service) = connector_and_service();
+
+        let retry_at = Instant::now() + Duration::from_secs(60);
+        connector.set_connection_error(Some(ClassifiableTestError(ErrorClass::RetryAt(retry_at))));
+        let connection_result = service.connect().await;
+
+        assert_eq!(connector.attempts_made(), 1);
+        assert_matches!(
+            connection_result,
+            Err(ConnectError::RetryLater(RetryLater { retry_after })) => {
+                assert_eq!(retry_after, retry_at - Instant::now());
+            }
+        );
+        assert_matches!(*service.data.state.lock().await, ServiceState::Cooldown(t) if t == retry_at);
+    }
+
     #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
     async fn multiple_callers_single_attempt() {
         let (connector, service) = connector_and_service();
@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::net::SocketAddr;
 use std::num::NonZeroU16;
 use std::sync::Arc;
 
@@ -10,7 +11,7 @@ use async_trait::async_trait;
 use boring_signal::ssl::{ConnectConfiguration, SslConnector, SslMethod};
 use futures_util::TryFutureExt;
 use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::net::TcpStream;
+use tokio::net::{TcpSocket, TcpStream};
 use tokio_boring_signal::SslStream;
 use tokio_util::either::Either;
 
@@ -20,11 +21,15 @@ use crate::infra::errors::TransportConnectError;
 use crate::infra::host::Host;
 use crate::infra::tcp_ssl::proxy::tls::TlsProxyConnector;
 use crate::infra::{
-    Alpn, ConnectionInfo, RouteType, StreamAndInfo, TransportConnectionParams, TransportConnector,
+    Alpn, ConnectionInfo, Dscp, RouteType, StreamAndInfo, TrafficClass, TransportConnectionParams,
+    TransportConnector,
 };
 use crate::timeouts::TCP_CONNECTION_ATTEMPT_DELAY;
 use crate::utils::first_ok;
 
+mod policy;
+pub use policy::{TlsPolicy, TlsVersion};
+
 pub mod proxy;
 
 #[derive(Clone, Debug)]
@@ -37,6 +42,19 @@ pub enum TcpSslConnector {
 }
 
 impl TcpSslConnector {
+    /// Applies `tls_policy` to future connections.
+    ///
+    /// An [`Invalid`](Self::Invalid) connector can't make connections, so it
+    /// doesn't keep the policy; it has to be applied again to whatever
+    /// connector replaces it.
+    pub fn set_tls_policy(&mut self, tls_policy: TlsPolicy) {
+        match self {
+            TcpSslConnector::Direct(c) => c.tls_policy = tls_policy,
+            TcpSslConnector::Proxied(c) => c.tls_policy = tls_policy,
+            TcpSslConnector::Invalid(_) => {}
+        }
+    }
+
     pub fn set_ipv6_enabled(&mut self, ipv6_enabled: bool) {
         let dns_resolver = match self {
             TcpSslConnector::Direct(c) => &mut c.dns_resolver,
@@ -57,6 +75,7 @@ pub struct TcpSslConnectorStream(
 #[derive(Clone, Debug)]
 pub struct DirectConnector {
     pub dns_resolver: DnsResolver,
+    pub tls_policy: TlsPolicy,
 }
 
 #[async_trait]
@@ -68,15 +87,21 @@ impl TransportConnector for DirectConnector {
         connection_params: &TransportConnectionParams,
         alpn: Alpn,
     ) -> Result<StreamAndInfo<Self::Stream>, TransportConnectError> {
+        if connection_params.proxy.is_some() {
+            // Proxied routes need a different stream type; see TcpSslConnector.
+            return Err(TransportConnectError::InvalidConfiguration);
+        }
+
         let StreamAndInfo(tcp_stream, remote_address) = connect_tcp(
             &self.dns_resolver,
             RouteType::Direct,
             connection_params.tcp_host.as_deref(),
             connection_params.port,
+            connection_params.traffic_class,
         )
         .await?;
 
-        let ssl_stream = connect_tls(tcp_stream, connection_params, alpn).await?;
+        let ssl_stream = connect_tls(tcp_stream, connection_params, alpn, &self.tls_policy).await?;
 
         Ok(StreamAndInfo(ssl_stream, remote_address))
     }
@@ -84,12 +109,20 @@ impl TransportConnector for DirectConnector {
 
 impl DirectConnector {
     pub fn new(dns_resolver: DnsResolver) -> Self {
-        Self { dns_resolver }
+        Self {
+            dns_resolver,
+            tls_policy: TlsPolicy::default(),
+        }
     }
 
     pub fn with_proxy(&self, proxy_addr: (Host<Arc<str>>, NonZeroU16)) -> TlsProxyConnector {
-        let Self { dns_resolver } = self;
-        TlsProxyConnector::new(dns_resolver.clone(), proxy_addr)
+        let Self {
+            dns_resolver,
+            tls_policy,
+        } = self;
+        let mut proxied = TlsProxyConnector::new(dns_resolver.clone(), proxy_addr);
+        proxied.tls_policy = tls_policy.clone();
+        proxied
     }
 }
 
@@ -97,9 +130,11 @@ fn ssl_config(
     certs: &RootCertificates,
     host_name: &str,
     alpn: Option<Alpn>,
+    tls_policy: &TlsPolicy,
 ) -> Result<ConnectConfiguration, TransportConnectError> {
     let mut ssl = SslConnector::builder(SslMethod::tls_client())?;
     certs.apply_to_connector(&mut ssl, host_name)?;
+    tls_policy.apply_to_connector(&mut ssl)?;
     if let Some(alpn) = alpn {
         ssl.set_alpn_protos(alpn.as_ref())?;
     }
@@ -110,8 +145,14 @@ async fn connect_tls<S: AsyncRead + AsyncWrite + Unpin>(
     transport: S,
     connection_params: &TransportConnectionParams,
     alpn: Alpn,
+    tls_policy: &TlsPolicy,
 ) -> Result<SslStream<S>, TransportConnectError> {
-    let ssl_config = ssl_config(&connection_params.certs, &connection_params.sni, Some(alpn))?;
+    let ssl_config = ssl_config(
+        &connection_params.certs,
+        &connection_params.sni,
+        Some(alpn),
+        tls_policy,
+    )?;
 
     Ok(tokio_boring_signal::connect(ssl_config, &connection_params.sni, transport).await?)
 }
@@ -121,6 +162,7 @@ async fn connect_tcp(
     route_type: RouteType,
     host: Host<&str>,
     port: NonZeroU16,
+    traffic_class: TrafficClass,
 ) -> Result<StreamAndInfo<TcpStream>, TransportConnectError> {
     let dns_lookup = match host {
         Host::Ip(ip) => {
@@ -160,7 +202,7 @@ async fn connect_tcp(
             if !delay.is_zero() {
                 tokio::time::sleep(delay).await;
             }
-            TcpStream::connect((ip, port.into()))
+            connect_socket(SocketAddr::new(ip, port.into()), traffic_class)
                 .inspect_err(|e| {
                     log::debug!("failed to connect to IP [{ip}] with an error: {e:?}");
                 })
@@ -184,6 +226,49 @@ async fn connect_tcp(
         .ok_or(TransportConnectError::TcpConnectionFailed)
 }
 
+/// Connects to `addr` from a socket marked with `traffic_class`.
+async fn connect_socket(
+    addr: SocketAddr,
+    traffic_class: TrafficClass,
+) -> std::io::Result<TcpStream> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+
+    let TrafficClass { dscp, nodelay } = traffic_class;
+    if nodelay {
+        if let Err(e) = socket.set_nodelay(true) {
+            log::warn!("failed to set TCP_NODELAY: {e}");
+        }
+    }
+    if let Some(dscp) = dscp {
+        if let Err(e) = set_dscp(&socket, addr.is_ipv6(), dscp) {
+            log::warn!("failed to set DSCP {}: {e}", dscp.value());
+        }
+    }
+
+    socket.connect(addr).await
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_vendor = "apple"))]
+fn set_dscp(socket: &TcpSocket, is_ipv6: bool, dscp: Dscp) -> std::io::Result<()> {
+    let socket = socket2::SockRef::from(socket);
+    let traffic_class = dscp.as_traffic_class_byte().into();
+    if is_ipv6 {
+        socket.set_tclass_v6(traffic_class)
+    } else {
+        socket.set_tos(traffic_class)
+    }
+}
+
+/// Windows only honors DSCP markings set through its QoS APIs, and other
+/// platforms aren't shipped, so the marking is skipped.
+#[cfg(not(any(target_os = "linux", target_os = "android", target_vendor = "apple")))]
+fn set_dscp(_socket: &TcpSocket, _is_ipv6: bool, _dscp: Dscp) -> std::io::Result<()> {
+    Ok(())
+}
+
 impl AsyncRead for TcpSslConnectorStream {
     fn poll_read(
         self: std::pin::Pin<&mut Self>,
@@ -228,10 +313,23 @@ impl TransportConnector for TcpSslConnector {
         alpn: Alpn,
     ) -> Result<StreamAndInfo<Self::Stream>, TransportConnectError> {
         match self {
-            Self::Direct(direct) => direct
-                .connect(connection_params, alpn)
-                .await
-                .map(|s| s.map_stream(Either::Left)),
+            Self::Direct(direct) => match &connection_params.proxy {
+                None => direct
+                    .connect(connection_params, alpn)
+                    .await
+                    .map(|s| s.map_stream(Either::Left)),
+                Some(proxy_addr) => {
+                    let connection_params = TransportConnectionParams {
+                        proxy: None,
+                        ..connection_params.clone()
+                    };
+                    direct
+                        .with_proxy(proxy_addr.clone())
+                        .connect(&connection_params, alpn)
+                        .await
+                        .map(|s| s.map_stream(Either::Right))
+                }
+            },
             Self::Proxied(proxied) => proxied
                 .connect(connection_params, alpn)
                 .await
@@ -317,7 +415,7 @@ pub(crate) mod testutil {
 mod test {
     use std::borrow::Cow;
     use std::collections::HashMap;
-    use std::net::Ipv6Addr;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
     use assert_matches::assert_matches;
     use test_case::test_case;
@@ -326,6 +424,48 @@ mod test {
     use super::*;
     use crate::infra::dns::lookup_result::LookupResult;
     use crate::infra::host::Host;
+    use crate::infra::tcp_ssl::proxy::testutil::{localhost_tcp_proxy, PROXY_HOSTNAME};
+
+    #[test_case(Ipv4Addr::LOCALHOST.into(); "IPv4")]
+    #[test_case(Ipv6Addr::LOCALHOST.into(); "IPv6")]
+    #[tokio::test]
+    async fn connect_tcp_applies_traffic_class(ip: IpAddr) {
+        let listener = tokio::net::TcpListener::bind((ip, 0))
+            .await
+            .expect("can bind");
+        let port = listener.local_addr().expect("bound").port();
+
+        let connect = |traffic_class| {
+            connect_tcp(
+                &DnsResolver::new_from_static_map(HashMap::new()),
+                RouteType::Direct,
+                Host::Ip(ip),
+                port.try_into().expect("bound port"),
+                traffic_class,
+            )
+        };
+
+        let StreamAndInfo(stream, _) = connect(TrafficClass::INTERACTIVE)
+            .await
+            .expect("can connect");
+        assert!(stream.nodelay().expect("can read option"));
+        #[cfg(any(target_os = "linux", target_os = "android", target_vendor = "apple"))]
+        {
+            let socket = socket2::SockRef::from(&stream);
+            let traffic_class = if ip.is_ipv6() {
+                socket.tclass_v6()
+            } else {
+                socket.tos()
+            };
+            assert_eq!(
+                traffic_class.expect("can read option"),
+                u32::from(Dscp::LOW_LATENCY_DATA.as_traffic_class_byte())
+            );
+        }
+
+        let StreamAndInfo(stream, _) = connect(TrafficClass::default()).await.expect("can connect");
+        assert!(!stream.nodelay().expect("can read option"));
+    }
 
     #[test_case(true; "resolved hostname")]
     #[test_case(false; "by IP")]
@@ -346,6 +486,8 @@ mod test {
             },
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            proxy: None,
+            traffic_class: TrafficClass::default(),
         };
 
         let StreamAndInfo(stream, info) = connector
@@ -378,6 +520,8 @@ mod test {
             tcp_host: Host::Ip(addr.ip()),
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            proxy: None,
+            traffic_class: TrafficClass::default(),
         };
 
         match connector.connect(&connection_params, Alpn::Http1_1).await {
@@ -390,4 +534,38 @@ mod test {
             }
         }
     }
+
+    #[tokio::test]
+    async fn connect_direct_through_per_route_proxy() {
+        let (addr, server) = localhost_http_server();
+        let _server_handle = tokio::spawn(server);
+
+        let (proxy_addr, proxy) = localhost_tcp_proxy(addr);
+        let _proxy_handle = tokio::spawn(proxy);
+
+        let connector =
+            TcpSslConnector::Direct(DirectConnector::new(DnsResolver::new_from_static_map(
+                HashMap::from([(PROXY_HOSTNAME, LookupResult::localhost())]),
+            )));
+        let connection_params = TransportConnectionParams {
+            sni: SERVER_HOSTNAME.into(),
+            tcp_host: Host::Domain("localhost".into()),
+            port: addr.port().try_into().expect("bound port"),
+            certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            proxy: Some((
+                Host::Domain(format!("UNENCRYPTED_FOR_TESTING@{PROXY_HOSTNAME}").into()),
+                proxy_addr.port().try_into().expect("bound port"),
+            )),
+            traffic_class: TrafficClass::default(),
+        };
+
+        let StreamAndInfo(stream, info) = connector
+            .connect(&connection_params, Alpn::Http1_1)
+            .await
+            .expect("can connect");
+
+        assert_eq!(info.route_type, RouteType::TlsProxy);
+
+        make_http_request_response_over(stream).await
+    }
 }
@@ -0,0 +1,115 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use boring_signal::error::ErrorStack;
+use boring_signal::ssl::{SslConnectorBuilder, SslSignatureAlgorithm, SslVersion};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    Tls1_2,
+    Tls1_3,
+}
+
+impl From<TlsVersion> for SslVersion {
+    fn from(value: TlsVersion) -> Self {
+        match value {
+            TlsVersion::Tls1_2 => SslVersion::TLS1_2,
+            TlsVersion::Tls1_3 => SslVersion::TLS1_3,
+        }
+    }
+}
+
+/// Restrictions on the TLS connections made by a transport connector.
+///
+/// The default allows TLS 1.2 and 1.3 with BoringSSL's default cipher suites
+/// and signature algorithms. Deployments with stricter requirements can, for
+/// example, require TLS 1.3 with [`TlsPolicy::tls1_3_only`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsPolicy {
+    pub min_version: TlsVersion,
+    pub max_version: TlsVersion,
+    /// Cipher suites to offer for TLS 1.2, in OpenSSL's cipher list format.
+    ///
+    /// BoringSSL doesn't allow configuring TLS 1.3 cipher suites, so this has
+    /// no effect on TLS 1.3 connections. If `None`, BoringSSL's defaults are
+    /// used.
+    pub tls1_2_cipher_list: Option<String>,
+    /// The only signature algorithms accepted from servers, in order of
+    /// preference.
+    ///
+    /// If `None`, BoringSSL's defaults are used.
+    pub signature_algorithms: Option<Vec<SslSignatureAlgorithm>>,
+}
+
+impl Default for TlsPolicy {
+    fn default() -> Self {
+        Self {
+            min_version: TlsVersion::Tls1_2,
+            max_version: TlsVersion::Tls1_3,
+            tls1_2_cipher_list: None,
+            signature_algorithms: None,
+        }
+    }
+}
+
+impl TlsPolicy {
+    pub fn tls1_3_only() -> Self {
+        Self {
+            min_version: TlsVersion::Tls1_3,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn apply_to_connector(
+        &self,
+        connector: &mut SslConnectorBuilder,
+    ) -> Result<(), ErrorStack> {
+        let Self {
+            min_version,
+            max_version,
+            tls1_2_cipher_list,
+            signature_algorithms,
+        } = self;
+        connector.set_min_proto_version(Some((*min_version).into()))?;
+        connector.set_max_proto_version(Some((*max_version).into()))?;
+        if let Some(cipher_list) = tls1_2_cipher_list {
+            connector.set_cipher_list(cipher_list)?;
+        }
+        if let Some(signature_algorithms) = signature_algorithms {
+            connector.set_verify_algorithm_prefs(signature_algorithms)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use boring_signal::ssl::SslMethod;
+
+    use super::*;
+
+    #[test]
+    fn applies_to_connector() {
+        let mut connector =
+            boring_signal::ssl::SslConnector::builder(SslMethod::tls_client()).expect("valid");
+        TlsPolicy::default()
+            .apply_to_connector(&mut connector)
+            .expect("valid");
+        TlsPolicy {
+            tls1_2_cipher_list: Some("ECDHE-ECDSA-AES128-GCM-SHA256".to_owned()),
+            signature_algorithms: Some(vec![SslSignatureAlgorithm::ECDSA_SECP256R1_SHA256]),
+            ..TlsPolicy::tls1_3_only()
+        }
+        .apply_to_connector(&mut connector)
+        .expect("valid");
+
+        TlsPolicy {
+            tls1_2_cipher_list: Some("NOT-A-CIPHER".to_owned()),
+            ..TlsPolicy::default()
+        }
+        .apply_to_connector(&mut connector)
+        .expect_err("invalid cipher list");
+    }
+}
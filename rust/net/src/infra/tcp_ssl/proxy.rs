@@ -163,7 +163,7 @@ pub(crate) mod testutil {
     /// Starts a TCP server that proxies TLS connections to an upstream server.
     ///
     /// Proxies TCP connections to `upstream_addr`.
-    pub(super) fn localhost_tcp_proxy(
+    pub(crate) fn localhost_tcp_proxy(
         upstream_addr: SocketAddr,
     ) -> (SocketAddr, impl Future<Output = ()>) {
         let TcpServer {
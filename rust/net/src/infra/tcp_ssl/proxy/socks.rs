@@ -79,6 +79,7 @@ impl TransportConnector for SocksConnector {
             RouteType::SocksProxy,
             proxy_host.as_deref(),
             *proxy_port,
+            connection_params.traffic_class,
         )
         .await?;
         let is_ipv6 = tcp_stream
@@ -228,6 +229,7 @@ mod test {
     use crate::infra::host::Host;
     use crate::infra::tcp_ssl::proxy::testutil::{TcpServer, TlsServer, PROXY_HOSTNAME};
     use crate::infra::tcp_ssl::testutil::{SERVER_CERTIFICATE, SERVER_HOSTNAME};
+    use crate::infra::TrafficClass;
 
     /// Authentication method.
     #[derive(Default)]
@@ -402,6 +404,8 @@ mod test {
             certs: crate::infra::certs::RootCertificates::FromDer(std::borrow::Cow::Borrowed(
                 SERVER_CERTIFICATE.cert.der(),
             )),
+            proxy: None,
+            traffic_class: TrafficClass::default(),
         };
         let mut connect = connector.connect(&connection_params, Alpn::Http1_1);
 
@@ -525,6 +529,8 @@ mod test {
             certs: crate::infra::certs::RootCertificates::FromDer(std::borrow::Cow::Borrowed(
                 SERVER_CERTIFICATE.cert.der(),
             )),
+            proxy: None,
+            traffic_class: TrafficClass::default(),
         };
         let connect = connector.connect(&connection_params, Alpn::Http1_1);
 
@@ -15,7 +15,7 @@ use crate::infra::certs::RootCertificates;
 use crate::infra::dns::DnsResolver;
 use crate::infra::errors::TransportConnectError;
 use crate::infra::host::Host;
-use crate::infra::tcp_ssl::{connect_tcp, connect_tls, ssl_config};
+use crate::infra::tcp_ssl::{connect_tcp, connect_tls, ssl_config, TlsPolicy};
 use crate::infra::{
     Alpn, ConnectionInfo, RouteType, StreamAndInfo, TransportConnectionParams, TransportConnector,
 };
@@ -36,6 +36,9 @@ pub struct TlsProxyConnector {
     proxy_port: NonZeroU16,
     pub(crate) proxy_certs: RootCertificates,
     use_tls_for_proxy: ShouldUseTls,
+    /// Applies to the connection to the destination, and to the proxy itself
+    /// when connecting to it over TLS.
+    pub tls_policy: TlsPolicy,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -58,6 +61,7 @@ impl TransportConnector for TlsProxyConnector {
             RouteType::TlsProxy,
             self.proxy_host.as_deref(),
             self.proxy_port,
+            connection_params.traffic_class,
         )
         .await?;
 
@@ -71,7 +75,7 @@ impl TransportConnector for TlsProxyConnector {
                 // This won't always work, but it's enough to connect to proxies
                 // by hostnames.
                 let sni = self.proxy_host.to_string();
-                let ssl_config = ssl_config(&self.proxy_certs, &sni, None)?;
+                let ssl_config = ssl_config(&self.proxy_certs, &sni, None, &self.tls_policy)?;
                 Either::Left(tokio_boring_signal::connect(ssl_config, &sni, tcp_stream).await?)
             }
             ShouldUseTls::No => {
@@ -84,7 +88,8 @@ impl TransportConnector for TlsProxyConnector {
             }
         };
 
-        let tls_stream = connect_tls(inner_stream, connection_params, alpn).await?;
+        let tls_stream =
+            connect_tls(inner_stream, connection_params, alpn, &self.tls_policy).await?;
 
         Ok(StreamAndInfo(
             tls_stream,
@@ -113,6 +118,7 @@ impl TlsProxyConnector {
             // is also TLS-encrypted.
             proxy_certs: RootCertificates::Native,
             use_tls_for_proxy,
+            tls_policy: TlsPolicy::default(),
         }
     }
 
@@ -150,6 +156,7 @@ mod test {
     use crate::infra::tcp_ssl::proxy::testutil::{
         localhost_tcp_proxy, localhost_tls_proxy, PROXY_CERTIFICATE, PROXY_HOSTNAME,
     };
+    use crate::infra::TrafficClass;
 
     #[tokio::test]
     async fn connect_through_proxy() {
@@ -183,6 +190,8 @@ mod test {
             tcp_host: Host::Domain("localhost".into()),
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            proxy: None,
+            traffic_class: TrafficClass::default(),
         };
 
         let StreamAndInfo(stream, info) = connector
@@ -226,6 +235,8 @@ mod test {
             tcp_host: Host::Domain("localhost".into()),
             port: addr.port().try_into().expect("bound port"),
             certs: RootCertificates::FromDer(Cow::Borrowed(SERVER_CERTIFICATE.cert.der())),
+            proxy: None,
+            traffic_class: TrafficClass::default(),
         };
 
         let StreamAndInfo(stream, info) = connector
@@ -24,15 +24,17 @@ use tungstenite::handshake::client::generate_key;
 use tungstenite::protocol::CloseFrame;
 use tungstenite::{http, Message};
 
+use crate::enclave::ProtocolVersion;
 use crate::infra::errors::LogSafeDisplay;
 use crate::infra::host::Host;
 use crate::infra::service::ServiceConnector;
-use crate::infra::ws::error::{HttpFormatError, ProtocolError, SpaceError};
+use crate::infra::ws::error::{HttpFormatError, ProtocolError, ResponseLimitError, SpaceError};
 use crate::infra::{
     Alpn, AsyncDuplexStream, ConnectionInfo, ConnectionParams, StreamAndInfo, TransportConnector,
 };
-use crate::utils::timeout;
+use crate::utils::{timeout, Redacted};
 
+pub mod close;
 pub mod error;
 pub use error::{Error, WebSocketConnectError};
 
@@ -62,6 +64,101 @@ pub struct WebSocketConfig {
     /// How long to allow the connection to be idle before the server is assumed
     /// to have become unavailable.
     pub max_idle_time: Duration,
+    /// Bounds on how much data the server is allowed to send.
+    pub response_limits: ResponseLimits,
+}
+
+impl WebSocketConfig {
+    /// [`Self::ws_config`] with its message and frame sizes capped by
+    /// [`ResponseLimits::max_frame_size`].
+    fn limited_ws_config(&self) -> tungstenite::protocol::WebSocketConfig {
+        let max = self.response_limits.max_frame_size;
+        let cap = |limit: Option<usize>| Some(limit.map_or(max, |limit| limit.min(max)));
+        let mut ws_config = self.ws_config;
+        ws_config.max_message_size = cap(ws_config.max_message_size);
+        ws_config.max_frame_size = cap(ws_config.max_frame_size);
+        ws_config
+    }
+}
+
+/// Bounds on the data a server can send over a websocket.
+///
+/// These protect memory-constrained clients from pathological or malicious
+/// responses. Exceeding any of them fails the request with a
+/// [`WebSocketServiceError::ResponseLimit`] error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResponseLimits {
+    /// The largest single message, after reassembling fragments, that will be
+    /// accepted.
+    pub max_frame_size: usize,
+    /// The largest total size of a response that spans multiple messages.
+    pub max_response_size: usize,
+    /// The most messages a single response is allowed to span.
+    pub max_frames_per_response: usize,
+}
+
+impl ResponseLimits {
+    const DEFAULT_MAX_FRAME_SIZE: usize = 16 << 20;
+    const DEFAULT_MAX_RESPONSE_SIZE: usize = 64 << 20;
+    const DEFAULT_MAX_FRAMES_PER_RESPONSE: usize = 4096;
+}
+
+impl Default for ResponseLimits {
+    fn default() -> Self {
+        Self {
+            max_frame_size: Self::DEFAULT_MAX_FRAME_SIZE,
+            max_response_size: Self::DEFAULT_MAX_RESPONSE_SIZE,
+            max_frames_per_response: Self::DEFAULT_MAX_FRAMES_PER_RESPONSE,
+        }
+    }
+}
+
+/// Tracks the frames of a single multi-frame response against [`ResponseLimits`].
+#[derive(Debug)]
+pub(crate) struct ResponseLimiter {
+    limits: ResponseLimits,
+    frames: usize,
+    total_size: usize,
+}
+
+impl ResponseLimiter {
+    pub(crate) fn new(limits: ResponseLimits) -> Self {
+        Self {
+            limits,
+            frames: 0,
+            total_size: 0,
+        }
+    }
+
+    /// Accounts for one more frame of `size` bytes, failing if that puts the
+    /// response over any of the limits.
+    pub(crate) fn record_frame(&mut self, size: usize) -> Result<(), ResponseLimitError> {
+        let ResponseLimits {
+            max_frame_size,
+            max_response_size,
+            max_frames_per_response,
+        } = self.limits;
+        if size > max_frame_size {
+            return Err(ResponseLimitError::FrameTooLarge {
+                size,
+                max_size: max_frame_size,
+            });
+        }
+        self.frames += 1;
+        if self.frames > max_frames_per_response {
+            return Err(ResponseLimitError::TooManyFrames {
+                max_frames: max_frames_per_response,
+            });
+        }
+        self.total_size = self.total_size.saturating_add(size);
+        if self.total_size > max_response_size {
+            return Err(ResponseLimitError::ResponseTooLarge {
+                size: self.total_size,
+                max_size: max_response_size,
+            });
+        }
+        Ok(())
+    }
 }
 
 /// [`ServiceConnector`] for services that wrap a websocket connection.
@@ -90,6 +187,7 @@ pub enum WebSocketServiceError {
     Io(std::io::Error),
     Protocol(tungstenite::error::ProtocolError),
     Capacity(SpaceError),
+    ResponseLimit(ResponseLimitError),
     Http(http::Response<Option<Vec<u8>>>),
     HttpFormat(http::Error),
     Url(tungstenite::error::UrlError),
@@ -107,6 +205,7 @@ impl Display for WebSocketServiceError {
                 write!(f, "websocket protocol: {}", ProtocolError::from(p.clone()))
             }
             WebSocketServiceError::Capacity(e) => write!(f, "capacity error: {e}"),
+            WebSocketServiceError::ResponseLimit(e) => write!(f, "response limit: {e}"),
             WebSocketServiceError::Http(response) => write!(f, "HTTP error: {}", response.status()),
             WebSocketServiceError::HttpFormat(e) => {
                 write!(f, "HTTP format error: {}", HttpFormatError::from(e))
@@ -124,6 +223,10 @@ impl From<tungstenite::Error> for WebSocketServiceError {
             tungstenite::Error::AlreadyClosed => Self::ChannelClosed,
             tungstenite::Error::Io(e) => Self::Io(e),
             tungstenite::Error::Protocol(e) => Self::Protocol(e),
+            tungstenite::Error::Capacity(tungstenite::error::CapacityError::MessageTooLong {
+                size,
+                max_size,
+            }) => Self::ResponseLimit(ResponseLimitError::FrameTooLarge { size, max_size }),
             tungstenite::Error::Capacity(e) => Self::Capacity(e.into()),
             tungstenite::Error::WriteBufferFull(_) => Self::Capacity(SpaceError::SendQueueFull),
             tungstenite::Error::Url(e) => Self::Url(e),
@@ -144,7 +247,7 @@ where
     WebSocketServiceError: Into<E>,
 {
     type Service = WebSocketClient<T::Stream, E>;
-    type Channel = (WebSocketStream<T::Stream>, ConnectionInfo);
+    type Channel = (WebSocketStream<T::Stream>, ConnectionInfo, http::HeaderMap);
     type ConnectError = WebSocketConnectError;
 
     async fn connect_channel(
@@ -154,7 +257,7 @@ where
         let connect_future = connect_websocket(
             connection_params,
             self.cfg.endpoint.clone(),
-            self.cfg.ws_config,
+            self.cfg.limited_ws_config(),
             &self.transport_connector,
         );
         timeout(
@@ -167,11 +270,14 @@ where
     }
 
     fn start_service(&self, channel: Self::Channel) -> (Self::Service, CancellationToken) {
+        let (stream, connection_info, upgrade_response_headers) = channel;
         start_ws_service(
-            channel.0,
-            channel.1,
+            stream,
+            connection_info,
+            upgrade_response_headers,
             self.cfg.keep_alive_interval,
             self.cfg.max_idle_time,
+            self.cfg.response_limits,
         )
     }
 }
@@ -179,8 +285,10 @@ where
 fn start_ws_service<S: AsyncDuplexStream, E>(
     channel: WebSocketStream<S>,
     connection_info: ConnectionInfo,
+    upgrade_response_headers: http::HeaderMap,
     keep_alive_interval: Duration,
     max_idle_time: Duration,
+    response_limits: ResponseLimits,
 ) -> (WebSocketClient<S, E>, CancellationToken) {
     let service_cancellation = CancellationToken::new();
     let (ws_sink, ws_stream) = channel.split();
@@ -203,6 +311,8 @@ fn start_ws_service<S: AsyncDuplexStream, E>(
             ws_client_writer,
             ws_client_reader,
             connection_info,
+            upgrade_response_headers,
+            response_limits,
         },
         service_cancellation,
     )
@@ -301,6 +411,10 @@ where
                     Message::Binary(b) => return Ok(NextOrClose::Next(b.into())),
                     Message::Ping(_) | Message::Pong(_) => continue,
                     Message::Close(close_frame) => {
+                        log::debug!(
+                            "websocket closed by the server: {:?}",
+                            close_frame.as_ref().map(RedactedCloseFrame)
+                        );
                         self.service_cancellation.cancel();
                         return Ok(NextOrClose::Close(close_frame));
                     }
@@ -331,12 +445,25 @@ where
     result.map_err(Into::into)
 }
 
+// The endpoint is left out because it can contain account identifiers.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "connect_websocket",
+        skip_all,
+        fields(
+            route = %connection_params.route_type,
+            host = %connection_params.http_host,
+        ),
+        err(Display),
+    )
+)]
 async fn connect_websocket<T: TransportConnector>(
     connection_params: &ConnectionParams,
     endpoint: PathAndQuery,
     ws_config: tungstenite::protocol::WebSocketConfig,
     transport_connector: &T,
-) -> Result<(WebSocketStream<T::Stream>, ConnectionInfo), WebSocketConnectError> {
+) -> Result<(WebSocketStream<T::Stream>, ConnectionInfo, http::HeaderMap), WebSocketConnectError> {
     let StreamAndInfo(ssl_stream, remote_address) = transport_connector
         .connect(&connection_params.transport, Alpn::Http1_1)
         .await?;
@@ -367,7 +494,7 @@ async fn connect_websocket<T: TransportConnector>(
         .http_request_decorator
         .decorate_request(request_builder);
 
-    let (ws_stream, _response) = tokio_tungstenite::client_async_with_config(
+    let (ws_stream, response) = tokio_tungstenite::client_async_with_config(
         request_builder.body(()).expect("can get request body"),
         ssl_stream,
         Some(ws_config),
@@ -375,7 +502,7 @@ async fn connect_websocket<T: TransportConnector>(
     .await
     .map_err(|e| handle_ws_error(connection_params, e))?;
 
-    Ok((ws_stream, remote_address))
+    Ok((ws_stream, remote_address, response.into_parts().0.headers))
 }
 
 fn handle_ws_error(
@@ -405,6 +532,16 @@ pub(crate) enum TextOrBinary {
     Binary(Vec<u8>),
 }
 
+#[cfg(feature = "tracing")]
+impl TextOrBinary {
+    fn len(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Binary(binary) => binary.len(),
+        }
+    }
+}
+
 impl From<String> for TextOrBinary {
     fn from(value: String) -> Self {
         Self::Text(value)
@@ -432,20 +569,25 @@ pub struct WebSocketClient<S, E> {
     pub(crate) ws_client_writer: WebSocketClientWriter<S, E>,
     pub(crate) ws_client_reader: WebSocketClientReader<S, E>,
     pub(crate) connection_info: ConnectionInfo,
+    /// Headers the server sent in its response to the upgrade request.
+    pub(crate) upgrade_response_headers: http::HeaderMap,
+    pub(crate) response_limits: ResponseLimits,
 }
 
 impl<S: AsyncDuplexStream, E> WebSocketClient<S, E>
 where
     WebSocketServiceError: Into<E>,
 {
-    #[cfg(test)]
+    #[cfg(any(test, feature = "test-support"))]
     pub(crate) fn new_fake(channel: WebSocketStream<S>, connection_info: ConnectionInfo) -> Self {
         const VERY_LARGE_TIMEOUT: Duration = Duration::from_secs(u32::MAX as u64);
         let (client, _service_status) = start_ws_service(
             channel,
             connection_info,
+            http::HeaderMap::new(),
             VERY_LARGE_TIMEOUT,
             VERY_LARGE_TIMEOUT,
+            ResponseLimits::default(),
         );
         client
     }
@@ -454,6 +596,8 @@ where
     ///
     /// An error is returned if the send fails.
     pub(crate) async fn send(&mut self, item: TextOrBinary) -> Result<(), E> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(len = item.len(), "sending websocket frame");
         self.ws_client_writer.send(item).await
     }
 
@@ -467,7 +611,16 @@ where
     /// Returns the next text or binary message received on the wrapped socket.
     /// If the next response received is a [`Message::Close`], returns `None`.
     pub(crate) async fn receive(&mut self) -> Result<NextOrClose<TextOrBinary>, E> {
-        self.ws_client_reader.next().await
+        let received = self.ws_client_reader.next().await;
+        #[cfg(feature = "tracing")]
+        match &received {
+            Ok(NextOrClose::Next(item)) => {
+                tracing::trace!(len = item.len(), "received websocket frame")
+            }
+            Ok(NextOrClose::Close(_)) => tracing::trace!("received websocket close"),
+            Err(_) => {}
+        }
+        received
     }
 }
 
@@ -504,6 +657,7 @@ pub type DefaultStream = tokio_boring_signal::SslStream<tokio::net::TcpStream>;
 pub struct AttestedConnection<S> {
     websocket: WebSocketClient<S, WebSocketServiceError>,
     client_connection: ClientConnection,
+    protocol_version: ProtocolVersion,
 }
 
 impl<S> AttestedConnection<S> {
@@ -511,6 +665,15 @@ impl<S> AttestedConnection<S> {
         &self.websocket.connection_info.address
     }
 
+    /// The version of the enclave protocol agreed on with the server.
+    pub(crate) fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    pub(crate) fn response_limits(&self) -> ResponseLimits {
+        self.websocket.response_limits
+    }
+
     pub(crate) fn handshake_hash(&self) -> &[u8] {
         &self.client_connection.handshake_hash
     }
@@ -536,12 +699,37 @@ pub(crate) async fn run_attested_interaction<
 }
 
 #[derive(Clone, Eq, PartialEq)]
-#[cfg_attr(test, derive(Debug))]
 pub(crate) enum NextOrClose<T> {
     Next(T),
     Close(Option<CloseFrame<'static>>),
 }
 
+impl<T: Debug> Debug for NextOrClose<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Next(t) => f.debug_tuple("Next").field(t).finish(),
+            Self::Close(frame) => f
+                .debug_tuple("Close")
+                .field(&frame.as_ref().map(RedactedCloseFrame))
+                .finish(),
+        }
+    }
+}
+
+/// Formats a [`CloseFrame`] without its reason, which servers may fill with
+/// details of the request being rejected.
+pub(crate) struct RedactedCloseFrame<'a>(pub(crate) &'a CloseFrame<'a>);
+
+impl Debug for RedactedCloseFrame<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let CloseFrame { code, reason } = self.0;
+        f.debug_struct("CloseFrame")
+            .field("code", code)
+            .field("reason", &Redacted(reason))
+            .finish()
+    }
+}
+
 impl<T> NextOrClose<T> {
     pub fn next_or<E>(self, failure: E) -> Result<T, E> {
         match self {
@@ -566,6 +754,10 @@ where
     S: AsyncDuplexStream,
 {
     /// Connect to remote host and verify remote attestation.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "attestation", skip_all, err(Debug))
+    )]
     pub(crate) async fn connect(
         mut websocket: WebSocketClient<S, WebSocketServiceError>,
         new_handshake: impl FnOnce(&[u8]) -> enclave::Result<enclave::Handshake>,
@@ -575,9 +767,19 @@ where
         Ok(Self {
             websocket,
             client_connection,
+            protocol_version: ProtocolVersion::BASELINE,
         })
     }
 
+    /// Records the protocol version negotiated when the connection was
+    /// established.
+    pub(crate) fn with_protocol_version(self, protocol_version: ProtocolVersion) -> Self {
+        Self {
+            protocol_version,
+            ..self
+        }
+    }
+
     pub(crate) async fn send(
         &mut self,
         request: impl prost::Message,
@@ -693,8 +895,10 @@ pub(crate) mod testutil {
         start_ws_service(
             channel,
             mock_connection_info(),
+            http::HeaderMap::new(),
             WS_KEEP_ALIVE_INTERVAL,
             WS_MAX_IDLE_INTERVAL,
+            ResponseLimits::default(),
         )
         .0
     }
@@ -816,11 +1020,16 @@ mod test {
     use futures_util::{pin_mut, poll};
     use nonzero_ext::nonzero;
     use test_case::test_matrix;
+    use tungstenite::protocol::frame::coding::{CloseCode, Data, OpCode};
+    use tungstenite::protocol::frame::Frame;
 
     use super::testutil::*;
     use super::*;
     use crate::infra::certs::RootCertificates;
-    use crate::infra::{HttpRequestDecoratorSeq, RouteType, TransportConnectionParams};
+    use crate::infra::{
+        HttpRequestDecoratorSeq, RouteType, TrafficClass, TransportConnectionParams,
+    };
+    use crate::timeouts::{WS_KEEP_ALIVE_INTERVAL, WS_MAX_IDLE_INTERVAL};
 
     impl<T: Debug> NextOrClose<T> {
         pub(crate) fn unwrap_next(self) -> T
@@ -836,6 +1045,19 @@ mod test {
 
     const MESSAGE_TEXT: &str = "text";
 
+    #[test]
+    #[cfg(not(feature = "unredacted-logs"))]
+    fn close_reason_is_redacted() {
+        let close = NextOrClose::<()>::Close(Some(CloseFrame {
+            code: CloseCode::Library(4003),
+            reason: "bad number +18005550100".into(),
+        }));
+        assert_eq!(
+            format!("{close:?}"),
+            "Close(Some(CloseFrame { code: Library(4003), reason: <redacted> }))"
+        );
+    }
+
     #[tokio::test]
     async fn websocket_client_sends_pong_on_server_ping() {
         let (mut server, mut client) = fake_websocket().await;
@@ -1003,6 +1225,8 @@ mod test {
                 tcp_host: Host::Domain(Arc::clone(&hostname)),
                 port: nonzero!(443u16),
                 certs: RootCertificates::Signal,
+                proxy: None,
+                traffic_class: TrafficClass::default(),
             },
             http_host: hostname,
             http_request_decorator: HttpRequestDecoratorSeq::default(),
@@ -1059,4 +1283,163 @@ mod test {
             );
         }
     }
+
+    const SMALL_LIMITS: ResponseLimits = ResponseLimits {
+        max_frame_size: 10,
+        max_response_size: 25,
+        max_frames_per_response: 4,
+    };
+
+    #[test]
+    fn response_limiter_rejects_large_frame() {
+        let mut limiter = ResponseLimiter::new(SMALL_LIMITS);
+        limiter.record_frame(10).expect("at limit");
+        assert_eq!(
+            limiter.record_frame(11),
+            Err(ResponseLimitError::FrameTooLarge {
+                size: 11,
+                max_size: 10
+            })
+        );
+    }
+
+    #[test]
+    fn response_limiter_rejects_large_response() {
+        let mut limiter = ResponseLimiter::new(SMALL_LIMITS);
+        limiter.record_frame(10).expect("under limit");
+        limiter.record_frame(10).expect("under limit");
+        assert_eq!(
+            limiter.record_frame(6),
+            Err(ResponseLimitError::ResponseTooLarge {
+                size: 26,
+                max_size: 25
+            })
+        );
+    }
+
+    #[test]
+    fn response_limiter_rejects_too_many_frames() {
+        let mut limiter = ResponseLimiter::new(SMALL_LIMITS);
+        for _ in 0..4 {
+            limiter.record_frame(1).expect("under limit");
+        }
+        assert_eq!(
+            limiter.record_frame(1),
+            Err(ResponseLimitError::TooManyFrames { max_frames: 4 })
+        );
+    }
+
+    #[test]
+    fn frame_limit_caps_tungstenite_config() {
+        let config = WebSocketConfig {
+            ws_config: tungstenite::protocol::WebSocketConfig::default(),
+            endpoint: PathAndQuery::from_static("/"),
+            max_connection_time: Duration::from_secs(1),
+            keep_alive_interval: Duration::from_secs(1),
+            max_idle_time: Duration::from_secs(1),
+            response_limits: SMALL_LIMITS,
+        };
+        let ws_config = config.limited_ws_config();
+        assert_eq!(ws_config.max_message_size, Some(10));
+        assert_eq!(ws_config.max_frame_size, Some(10));
+    }
+
+    async fn send_in_frames(
+        server: &mut WebSocketStream<tokio::io::DuplexStream>,
+        frames: &[&[u8]],
+    ) {
+        for (i, frame) in frames.iter().enumerate() {
+            let opcode = if i == 0 {
+                OpCode::Data(Data::Binary)
+            } else {
+                OpCode::Data(Data::Continue)
+            };
+            let is_final = i == frames.len() - 1;
+            server
+                .send(Message::Frame(Frame::message(
+                    frame.to_vec(),
+                    opcode,
+                    is_final,
+                )))
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn limited_websocket() -> (
+        WebSocketStream<tokio::io::DuplexStream>,
+        WebSocketClient<tokio::io::DuplexStream, WebSocketServiceError>,
+    ) {
+        let config = WebSocketConfig {
+            ws_config: tungstenite::protocol::WebSocketConfig::default(),
+            endpoint: PathAndQuery::from_static("/"),
+            max_connection_time: Duration::from_secs(1),
+            keep_alive_interval: WS_KEEP_ALIVE_INTERVAL,
+            max_idle_time: WS_MAX_IDLE_INTERVAL,
+            response_limits: SMALL_LIMITS,
+        };
+        let (client, server) = tokio::io::duplex(1024);
+        let req = url::Url::parse("ws://localhost:8080/").unwrap();
+        let (client_res, server_res) = tokio::join!(
+            tokio_tungstenite::client_async_with_config(
+                req,
+                client,
+                Some(config.limited_ws_config())
+            ),
+            tokio_tungstenite::accept_async(server)
+        );
+        let (client, _) = client_res.unwrap();
+        let (client, _cancellation) = start_ws_service(
+            client,
+            mock_connection_info(),
+            http::HeaderMap::new(),
+            config.keep_alive_interval,
+            config.max_idle_time,
+            config.response_limits,
+        );
+        (server_res.unwrap(), client)
+    }
+
+    #[tokio::test]
+    async fn continuation_frames_are_reassembled() {
+        let (mut server, mut client) = limited_websocket().await;
+        send_in_frames(&mut server, &[b"abc", b"def", b"ghij"]).await;
+        assert_eq!(
+            client.receive().await.unwrap(),
+            NextOrClose::Next(TextOrBinary::Binary(b"abcdefghij".to_vec()))
+        );
+    }
+
+    #[tokio::test]
+    async fn continuation_frames_count_against_the_message_limit() {
+        let (mut server, mut client) = limited_websocket().await;
+        // Every frame is well under the limit, but the message isn't.
+        send_in_frames(&mut server, &[b"abcd", b"efgh", b"ijkl"]).await;
+        assert_matches!(
+            client.receive().await,
+            Err(WebSocketServiceError::ResponseLimit(
+                ResponseLimitError::FrameTooLarge {
+                    size: 12,
+                    max_size: 10
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn oversized_message_is_a_response_limit_error() {
+        let error = WebSocketServiceError::from(tungstenite::Error::Capacity(
+            tungstenite::error::CapacityError::MessageTooLong {
+                size: 11,
+                max_size: 10,
+            },
+        ));
+        assert_matches!(
+            error,
+            WebSocketServiceError::ResponseLimit(ResponseLimitError::FrameTooLarge {
+                size: 11,
+                max_size: 10
+            })
+        );
+    }
 }
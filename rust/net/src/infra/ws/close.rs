@@ -0,0 +1,167 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Interpreting the close frames sent by Signal's websocket services.
+//!
+//! When a request fails, the enclave services close the websocket with a code
+//! of 4000 plus a gRPC status code, and sometimes details in the reason. The
+//! codes in [`CommonCloseCode`] mean the same thing for every service; each
+//! service can also define its own codes. A service's error type implements
+//! [`CloseFrameError`], and [`error_for_close`] picks the right error for a
+//! frame.
+
+use tungstenite::protocol::CloseFrame;
+
+use crate::infra::ws::RedactedCloseFrame;
+
+/// Close codes shared by Signal's websocket services.
+#[repr(u16)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, num_enum::TryFromPrimitive, strum::IntoStaticStr)]
+pub enum CommonCloseCode {
+    InvalidArgument = 4003,
+    RateLimitExceeded = 4008,
+    ServerInternalError = 4013,
+    ServerUnavailable = 4014,
+}
+
+/// A failure signaled with one of the [`CommonCloseCode`]s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommonClose {
+    /// The request was rejected; the server may explain why.
+    InvalidArgument {
+        server_reason: String,
+    },
+    RateLimited {
+        retry_after_seconds: u32,
+    },
+    /// The server failed; `reason` names the close code.
+    Server {
+        reason: &'static str,
+    },
+}
+
+/// The reason sent with [`CommonCloseCode::RateLimitExceeded`].
+#[derive(serde::Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+pub(crate) struct RateLimitExceededResponse {
+    pub(crate) retry_after_seconds: u32,
+}
+
+impl CommonClose {
+    /// Interprets a close frame with one of the [`CommonCloseCode`]s.
+    ///
+    /// Returns `None` for other codes, and for rate limit closes without a
+    /// valid retry delay.
+    pub fn parse(code: u16, reason: &str) -> Option<Self> {
+        let code = CommonCloseCode::try_from(code).ok()?;
+        Some(match code {
+            CommonCloseCode::InvalidArgument => Self::InvalidArgument {
+                server_reason: reason.to_owned(),
+            },
+            CommonCloseCode::RateLimitExceeded => {
+                let RateLimitExceededResponse {
+                    retry_after_seconds,
+                } = serde_json::from_str(reason).ok()?;
+                Self::RateLimited {
+                    retry_after_seconds,
+                }
+            }
+            CommonCloseCode::ServerInternalError | CommonCloseCode::ServerUnavailable => {
+                Self::Server {
+                    reason: code.into(),
+                }
+            }
+        })
+    }
+}
+
+/// An error type that a service's close frames can be mapped to.
+pub(crate) trait CloseFrameError: Sized {
+    /// Interprets close codes specific to this service.
+    ///
+    /// These are checked before the [`CommonCloseCode`]s, so a service can
+    /// also override the meaning of a common code.
+    fn from_service_close(code: u16, reason: &str) -> Option<Self> {
+        let _ = (code, reason);
+        None
+    }
+
+    fn from_common_close(close: CommonClose) -> Self;
+
+    /// The error for a close frame that wasn't understood, or a close
+    /// without a frame.
+    fn unexpected_close() -> Self;
+}
+
+/// Produces the error for the server closing the connection with `frame`.
+///
+/// Callers that expect the server to close the connection once it is done
+/// should check for a normal close first.
+pub(crate) fn error_for_close<E: CloseFrameError>(frame: Option<CloseFrame<'_>>) -> E {
+    let Some(frame) = frame else {
+        return E::unexpected_close();
+    };
+    let code = u16::from(frame.code);
+    if let Some(error) = E::from_service_close(code, &frame.reason) {
+        return error;
+    }
+    if let Some(close) = CommonClose::parse(code, &frame.reason) {
+        return E::from_common_close(close);
+    }
+    log::warn!(
+        "got unexpected websocket close: {:?}",
+        RedactedCloseFrame(&frame)
+    );
+    E::unexpected_close()
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+    use tungstenite::protocol::frame::coding::CloseCode;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Special(String),
+        Common(CommonClose),
+        Unexpected,
+    }
+
+    impl CloseFrameError for TestError {
+        fn from_service_close(code: u16, reason: &str) -> Option<Self> {
+            (code == 4100).then(|| Self::Special(reason.to_owned()))
+        }
+
+        fn from_common_close(close: CommonClose) -> Self {
+            Self::Common(close)
+        }
+
+        fn unexpected_close() -> Self {
+            Self::Unexpected
+        }
+    }
+
+    fn frame(code: u16, reason: &'static str) -> Option<CloseFrame<'static>> {
+        Some(CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.into(),
+        })
+    }
+
+    #[test_case(frame(4100, "special") => TestError::Special("special".to_owned()); "service code")]
+    #[test_case(frame(4003, "bad") => TestError::Common(CommonClose::InvalidArgument { server_reason: "bad".to_owned() }); "invalid argument")]
+    #[test_case(frame(4008, r#"{"retry_after_seconds":30}"#) => TestError::Common(CommonClose::RateLimited { retry_after_seconds: 30 }); "rate limited")]
+    #[test_case(frame(4008, "not json") => TestError::Unexpected; "rate limited without delay")]
+    #[test_case(frame(4013, "") => TestError::Common(CommonClose::Server { reason: "ServerInternalError" }); "internal error")]
+    #[test_case(frame(4014, "") => TestError::Common(CommonClose::Server { reason: "ServerUnavailable" }); "unavailable")]
+    #[test_case(frame(4999, "") => TestError::Unexpected; "unknown code")]
+    #[test_case(frame(1000, "") => TestError::Unexpected; "normal")]
+    #[test_case(None => TestError::Unexpected; "no frame")]
+    fn maps_close_frames(frame: Option<CloseFrame<'static>>) -> TestError {
+        error_for_close(frame)
+    }
+}
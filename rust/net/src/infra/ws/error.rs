@@ -9,8 +9,11 @@
 
 use std::borrow::Borrow;
 
+use tokio::time::Instant;
+
+use crate::infra::attempt_log::ConnectionStage;
 use crate::infra::connection_manager::{ErrorClass, ErrorClassifier};
-use crate::infra::errors::{LogSafeDisplay, TransportConnectError};
+use crate::infra::errors::{LogSafeDisplay, RetryLater, TransportConnectError};
 
 /// Errors that can occur when connecting a websocket.
 #[derive(Debug, thiserror::Error)]
@@ -46,19 +49,43 @@ impl std::fmt::Display for WebSocketConnectError {
 
 impl LogSafeDisplay for WebSocketConnectError {}
 
+impl WebSocketConnectError {
+    /// The delay requested by the server, if it rejected the connection as rate limited or
+    /// unavailable.
+    pub fn retry_later(&self) -> Option<RetryLater> {
+        match self {
+            WebSocketConnectError::RejectedByServer(response) => {
+                RetryLater::from_response(response.status(), response.headers())
+            }
+            _ => None,
+        }
+    }
+}
+
 impl ErrorClassifier for WebSocketConnectError {
     fn classify(&self) -> ErrorClass {
+        if let Some(retry_later) = self.retry_later() {
+            return ErrorClass::RetryAt(Instant::now() + retry_later.retry_after);
+        }
         match self {
             WebSocketConnectError::RejectedByServer(response)
                 // is_client_error means 4XX error. 5XX errors can be intermittent.
                 if response.status().is_client_error() =>
             {
-                // TODO: handle StatusCode::TOO_MANY_REQUESTS to produce RetryError::WaitUntil(Instant)
                 ErrorClass::Fatal
             }
             _ => ErrorClass::Intermittent,
         }
     }
+
+    fn stage_reached(&self) -> Option<ConnectionStage> {
+        match self {
+            WebSocketConnectError::Transport(e) => e.stage_reached(),
+            WebSocketConnectError::Timeout => None,
+            WebSocketConnectError::WebSocketError(_)
+            | WebSocketConnectError::RejectedByServer(_) => Some(ConnectionStage::Http),
+        }
+    }
 }
 
 /// Mirror of [`tungstenite::error::Error`].
@@ -108,6 +135,24 @@ pub enum SpaceError {
     SendQueueFull,
 }
 
+/// A server response exceeded one of the configured
+/// [`ResponseLimits`](super::ResponseLimits).
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error, displaydoc::Display)]
+pub enum ResponseLimitError {
+    /// frame of {size} bytes exceeds the limit of {max_size} bytes
+    FrameTooLarge { size: usize, max_size: usize },
+    /// response of at least {size} bytes exceeds the limit of {max_size} bytes
+    ResponseTooLarge { size: usize, max_size: usize },
+    /// response exceeded the limit of {max_frames} frames
+    TooManyFrames { max_frames: usize },
+    /// repeated field {field} has {len} entries, exceeding the limit of {max_len}
+    TooManyRepeatedFields {
+        field: &'static str,
+        len: usize,
+        max_len: usize,
+    },
+}
+
 /// Mirror of [`tungstenite::error::ProtocolError`].
 ///
 /// Provides a user-data-free [`std::fmt::Display`] implementation.
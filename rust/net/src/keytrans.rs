@@ -0,0 +1,410 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for the key transparency endpoints on the chat server.
+//!
+//! Requests are sent over an existing [`ChatService`] connection as
+//! protobuf-encoded [`SearchRequest`]s and [`MonitorRequest`]s. Every response
+//! is checked with [`libsignal_keytrans`] before any value is returned, and
+//! the verified state of the log (the last tree head seen and the monitoring
+//! data for each key) is kept in a caller-provided [`LogStore`].
+
+use std::num::NonZeroU64;
+use std::time::Duration;
+
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use libsignal_core::{Aci, E164};
+use libsignal_keytrans::{
+    Consistency, KeyTransparency, LogStore, MonitorKey, MonitorRequest, MonitorResponse,
+    PublicConfig, SearchRequest, SearchResponse,
+};
+use prost::Message;
+
+use crate::chat::{ChatService, ChatServiceError, Request};
+
+mod self_monitor;
+pub use self_monitor::{AccountExpectations, AccountField, Discrepancy, DiscrepancyKind};
+
+const SEARCH_PATH: &str = "/v1/key-transparency/search";
+const MONITOR_PATH: &str = "/v1/key-transparency/monitor";
+const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const SEARCH_KEY_PREFIX_ACI: &[u8] = b"a";
+const SEARCH_KEY_PREFIX_E164: &[u8] = b"n";
+const SEARCH_KEY_PREFIX_USERNAME_HASH: &[u8] = b"u";
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum Error {
+    /// chat service error: {0}
+    ChatService(#[from] ChatServiceError),
+    /// request failed with status {0}
+    RequestFailed(StatusCode),
+    /// invalid response received from the server
+    InvalidResponse,
+    /// key transparency verification failed: {0}
+    Verification(#[from] libsignal_keytrans::Error),
+    /// no monitoring data is stored for a requested key
+    NotMonitored,
+}
+
+/// An identifier that can be looked up in the key transparency log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchKey {
+    Aci(Aci),
+    E164(E164),
+    UsernameHash(Box<[u8]>),
+}
+
+impl SearchKey {
+    /// The key used to look up this identifier in the log.
+    ///
+    /// Each kind of identifier has its own prefix, so that different kinds
+    /// can never map to the same entry.
+    pub fn as_search_key(&self) -> Vec<u8> {
+        match self {
+            Self::Aci(aci) => [SEARCH_KEY_PREFIX_ACI, &aci.service_id_binary()].concat(),
+            Self::E164(e164) => [
+                SEARCH_KEY_PREFIX_E164,
+                format!("+{}", NonZeroU64::from(*e164)).as_bytes(),
+            ]
+            .concat(),
+            Self::UsernameHash(hash) => [SEARCH_KEY_PREFIX_USERNAME_HASH, hash].concat(),
+        }
+    }
+}
+
+/// A tree head the client trusts without having to verify it, such as one
+/// shipped with the app.
+///
+/// Every response is checked to be consistent with this head, which prevents
+/// the server from presenting this client with a view of the log that other
+/// clients don't see.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DistinguishedTreeHead {
+    pub tree_size: u64,
+    pub root: [u8; 32],
+}
+
+/// Client for the key transparency endpoints on the chat server.
+pub struct KeyTransparencyClient<'a> {
+    chat: &'a (dyn ChatService + Send + Sync),
+    kt: KeyTransparency,
+    distinguished: Option<DistinguishedTreeHead>,
+}
+
+impl<'a> KeyTransparencyClient<'a> {
+    pub fn new(chat: &'a (dyn ChatService + Send + Sync), config: PublicConfig) -> Self {
+        Self {
+            chat,
+            kt: KeyTransparency { config },
+            distinguished: None,
+        }
+    }
+
+    /// Requires every verified response to be consistent with `head`.
+    pub fn with_distinguished_tree_head(self, head: DistinguishedTreeHead) -> Self {
+        Self {
+            distinguished: Some(head),
+            ..self
+        }
+    }
+
+    /// Looks up `key` in the log, verifies the result, and returns the value
+    /// stored for it.
+    ///
+    /// If `version` is `None`, the latest version is returned. Monitoring data
+    /// for the key is added to `store`; pass `owned` for keys that belong to
+    /// this account.
+    pub async fn search(
+        &self,
+        store: &mut (dyn LogStore + Send),
+        key: &SearchKey,
+        version: Option<u32>,
+        owned: bool,
+    ) -> Result<Vec<u8>, Error> {
+        let request = SearchRequest {
+            search_key: key.as_search_key(),
+            version,
+            consistency: Some(self.consistency(store)?),
+        };
+        let response: SearchResponse = self.send(SEARCH_PATH, &request).await?;
+
+        self.kt.verify_search(store, &request, &response, owned)?;
+        self.verify_distinguished(store, response.tree_head.as_ref())?;
+
+        let value = response.value.ok_or(Error::InvalidResponse)?;
+        Ok(value.value)
+    }
+
+    /// Checks that the entries for `keys` haven't changed since they were last
+    /// seen, updating the monitoring data in `store`.
+    ///
+    /// Every key must have been returned by [`Self::search`] with the same
+    /// `store` first.
+    pub async fn monitor(
+        &self,
+        store: &mut (dyn LogStore + Send),
+        keys: &[SearchKey],
+    ) -> Result<(), Error> {
+        let mut owned_keys = vec![];
+        let mut contact_keys = vec![];
+        for key in keys {
+            let search_key = key.as_search_key();
+            let data = store
+                .get_data(&search_key)
+                .map_err(libsignal_keytrans::Error::from)?
+                .ok_or(Error::NotMonitored)?;
+            let monitor_key = MonitorKey {
+                search_key,
+                entries: data.entries(),
+            };
+            if data.owned {
+                owned_keys.push(monitor_key);
+            } else {
+                contact_keys.push(monitor_key);
+            }
+        }
+
+        let request = MonitorRequest {
+            owned_keys,
+            contact_keys,
+            consistency: Some(self.consistency(store)?),
+        };
+        let response: MonitorResponse = self.send(MONITOR_PATH, &request).await?;
+
+        self.kt.verify_monitor(store, &request, &response)?;
+        self.verify_distinguished(store, response.tree_head.as_ref())
+    }
+
+    fn consistency(&self, store: &dyn LogStore) -> Result<Consistency, Error> {
+        let last = store
+            .get_last_tree_head()
+            .map_err(libsignal_keytrans::Error::from)?
+            .map(|(head, _root)| head.tree_size);
+        Ok(Consistency {
+            last,
+            distinguished: self.distinguished.map(|head| head.tree_size),
+        })
+    }
+
+    fn verify_distinguished(
+        &self,
+        store: &mut dyn LogStore,
+        full_tree_head: Option<&libsignal_keytrans::FullTreeHead>,
+    ) -> Result<(), Error> {
+        let Some(DistinguishedTreeHead { tree_size, root }) = self.distinguished else {
+            return Ok(());
+        };
+        let full_tree_head = full_tree_head.ok_or(Error::InvalidResponse)?;
+        Ok(self
+            .kt
+            .verify_distinguished(store, full_tree_head, tree_size, root)?)
+    }
+
+    async fn send<R: Message + Default>(
+        &self,
+        path: &'static str,
+        message: &impl Message,
+    ) -> Result<R, Error> {
+        let headers = HeaderMap::from_iter([(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static(PROTOBUF_CONTENT_TYPE),
+        )]);
+        let request = Request {
+            method: Method::POST,
+            body: Some(message.encode_to_vec().into_boxed_slice()),
+            headers,
+            path: PathAndQuery::from_static(path),
+        };
+
+        let response = self.chat.send(request, REQUEST_TIMEOUT).await?;
+        if !response.status.is_success() {
+            return Err(Error::RequestFailed(response.status));
+        }
+        let body = response.body.unwrap_or_default();
+        R::decode(&*body).map_err(|_| Error::InvalidResponse)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use hex_literal::hex;
+    use libsignal_keytrans::{DeploymentMode, MemoryLogStore, TreeHead, VrfPublicKey};
+
+    use super::*;
+    use crate::chat::testutil::FakeChat;
+    use crate::chat::Response;
+
+    fn response(status: StatusCode, body: Vec<u8>) -> Response {
+        Response {
+            status,
+            message: None,
+            body: Some(body.into_boxed_slice()),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn config() -> PublicConfig {
+        PublicConfig {
+            mode: DeploymentMode::ContactMonitoring,
+            signature_key: ed25519_dalek::VerifyingKey::from_bytes(&hex!(
+                "61eae8fe6373577e6473c5bb65a43b4d86190d78b2e5dc48fa6f253253438fb9"
+            ))
+            .expect("valid key"),
+            vrf_key: VrfPublicKey::try_from(hex!(
+                "ee964d1552c57c4b8dd9b3f409e6cfd7fb3691966014dbe89f652de7d0a67ca2"
+            ))
+            .expect("valid key"),
+        }
+    }
+
+    const ACI: Aci = Aci::from_uuid_bytes(hex!("b81b5b821e8d4eec8ec5e6513834a9f3"));
+
+    #[test]
+    fn search_keys_are_prefixed() {
+        assert_eq!(
+            SearchKey::Aci(ACI).as_search_key(),
+            hex!("61 b81b5b821e8d4eec8ec5e6513834a9f3")
+        );
+        assert_eq!(
+            SearchKey::E164(E164::new(NonZeroU64::new(18005550100).expect("nonzero")))
+                .as_search_key(),
+            b"n+18005550100"
+        );
+        assert_eq!(
+            SearchKey::UsernameHash([1, 2, 3].into()).as_search_key(),
+            b"u\x01\x02\x03"
+        );
+    }
+
+    #[tokio::test]
+    async fn search_sends_consistency_parameters() {
+        let chat = FakeChat::responding(response(
+            StatusCode::OK,
+            SearchResponse::default().encode_to_vec(),
+        ));
+        let client = KeyTransparencyClient::new(&chat, config()).with_distinguished_tree_head(
+            DistinguishedTreeHead {
+                tree_size: 2,
+                root: [0; 32],
+            },
+        );
+        let mut store = MemoryLogStore::default();
+        store
+            .set_last_tree_head(
+                TreeHead {
+                    tree_size: 4,
+                    ..Default::default()
+                },
+                [0; 32],
+            )
+            .expect("can store");
+
+        let result = client
+            .search(&mut store, &SearchKey::Aci(ACI), Some(1), false)
+            .await;
+        assert_matches!(result, Err(Error::Verification(_)));
+
+        let requests = chat.requests();
+        let [request] = &requests[..] else {
+            panic!("expected one request, got {requests:?}");
+        };
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.path, SEARCH_PATH);
+        let sent = SearchRequest::decode(request.body.as_deref().expect("has body"))
+            .expect("valid request");
+        assert_eq!(
+            sent,
+            SearchRequest {
+                search_key: SearchKey::Aci(ACI).as_search_key(),
+                version: Some(1),
+                consistency: Some(Consistency {
+                    last: Some(4),
+                    distinguished: Some(2),
+                }),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn search_reports_server_errors() {
+        let chat = FakeChat::responding(response(StatusCode::NOT_FOUND, vec![]));
+        let client = KeyTransparencyClient::new(&chat, config());
+        assert_matches!(
+            client
+                .search(
+                    &mut MemoryLogStore::default(),
+                    &SearchKey::Aci(ACI),
+                    None,
+                    false
+                )
+                .await,
+            Err(Error::RequestFailed(StatusCode::NOT_FOUND))
+        );
+
+        let chat = FakeChat::responding(response(StatusCode::OK, vec![0xff; 3]));
+        let client = KeyTransparencyClient::new(&chat, config());
+        assert_matches!(
+            client
+                .search(
+                    &mut MemoryLogStore::default(),
+                    &SearchKey::Aci(ACI),
+                    None,
+                    false
+                )
+                .await,
+            Err(Error::InvalidResponse)
+        );
+    }
+
+    #[tokio::test]
+    async fn self_monitor_reports_missing_entries() {
+        let chat = FakeChat::responding(response(StatusCode::NOT_FOUND, vec![]));
+        let client = KeyTransparencyClient::new(&chat, config());
+        let expected = AccountExpectations {
+            aci: ACI,
+            identity_key: *libsignal_protocol::IdentityKeyPair::generate(&mut rand::thread_rng())
+                .identity_key(),
+            e164: None,
+            username_hash: Some([1; 32].into()),
+        };
+
+        let discrepancies = client
+            .self_monitor(&mut MemoryLogStore::default(), &expected)
+            .await
+            .expect("can check");
+        assert_eq!(
+            discrepancies,
+            [
+                Discrepancy {
+                    field: AccountField::IdentityKey,
+                    kind: DiscrepancyKind::Missing,
+                },
+                Discrepancy {
+                    field: AccountField::UsernameHash,
+                    kind: DiscrepancyKind::Missing,
+                },
+            ]
+        );
+        assert_eq!(chat.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn monitor_requires_known_keys() {
+        let chat = FakeChat::responding(response(StatusCode::OK, vec![]));
+        let client = KeyTransparencyClient::new(&chat, config());
+        assert_matches!(
+            client
+                .monitor(&mut MemoryLogStore::default(), &[SearchKey::Aci(ACI)])
+                .await,
+            Err(Error::NotMonitored)
+        );
+        assert!(chat.requests().is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Checking the log's view of this account.
+//!
+//! Each identifier belonging to the account maps to a value in the log: the
+//! ACI to the account's identity key, and the phone number and username hash
+//! to the ACI. [`KeyTransparencyClient::self_monitor`] looks up the latest
+//! version of each one, verifying the results as usual, and reports any value
+//! that doesn't match what the account expects. Since the server can't change
+//! a mapping without it appearing in the log, running this periodically lets
+//! the owner notice if someone else's key has been published for them.
+
+use http::StatusCode;
+use libsignal_core::{Aci, E164};
+use libsignal_keytrans::LogStore;
+use libsignal_protocol::IdentityKey;
+
+use super::{Error, KeyTransparencyClient, SearchKey};
+
+/// What this account expects the log to contain.
+#[derive(Clone, Debug)]
+pub struct AccountExpectations {
+    pub aci: Aci,
+    pub identity_key: IdentityKey,
+    /// The account's phone number, if it can be looked up by number.
+    pub e164: Option<E164>,
+    /// The hash of the account's username, if it has one.
+    pub username_hash: Option<Box<[u8]>>,
+}
+
+/// Which identifier a [`Discrepancy`] is about.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AccountField {
+    /// The ACI's identity key.
+    IdentityKey,
+    /// The ACI the phone number maps to.
+    E164,
+    /// The ACI the username hash maps to.
+    UsernameHash,
+}
+
+/// How the log's view of an identifier differs from the expected one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiscrepancyKind {
+    /// The identifier isn't in the log at all.
+    Missing,
+    /// The identifier maps to a different value than expected.
+    Mismatch { expected: Vec<u8>, actual: Vec<u8> },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub field: AccountField,
+    pub kind: DiscrepancyKind,
+}
+
+impl AccountExpectations {
+    fn entries(&self) -> Vec<(AccountField, SearchKey, Vec<u8>)> {
+        let aci_value = self.aci.service_id_binary();
+        let mut entries = vec![(
+            AccountField::IdentityKey,
+            SearchKey::Aci(self.aci),
+            self.identity_key.serialize().into_vec(),
+        )];
+        if let Some(e164) = self.e164 {
+            entries.push((AccountField::E164, SearchKey::E164(e164), aci_value.clone()));
+        }
+        if let Some(hash) = &self.username_hash {
+            entries.push((
+                AccountField::UsernameHash,
+                SearchKey::UsernameHash(hash.clone()),
+                aci_value,
+            ));
+        }
+        entries
+    }
+}
+
+impl KeyTransparencyClient<'_> {
+    /// Checks that the log's view of this account matches `expected`.
+    ///
+    /// Every lookup is verified, and the account's keys are monitored as owned
+    /// keys in `store`. Returns every discrepancy found, or an empty list if
+    /// the log matches; errors are only returned if the check couldn't be
+    /// completed.
+    pub async fn self_monitor(
+        &self,
+        store: &mut (dyn LogStore + Send),
+        expected: &AccountExpectations,
+    ) -> Result<Vec<Discrepancy>, Error> {
+        let mut discrepancies = vec![];
+        for (field, key, expected_value) in expected.entries() {
+            let kind = match self.search(store, &key, None, true).await {
+                Ok(actual) if actual == expected_value => continue,
+                Ok(actual) => DiscrepancyKind::Mismatch {
+                    expected: expected_value,
+                    actual,
+                },
+                Err(Error::RequestFailed(StatusCode::NOT_FOUND)) => DiscrepancyKind::Missing,
+                Err(e) => return Err(e),
+            };
+            discrepancies.push(Discrepancy { field, kind });
+        }
+        Ok(discrepancies)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroU64;
+
+    use hex_literal::hex;
+    use libsignal_protocol::KeyPair;
+
+    use super::*;
+
+    #[test]
+    fn entries_map_identifiers_to_expected_values() {
+        let aci = Aci::from_uuid_bytes(hex!("b81b5b821e8d4eec8ec5e6513834a9f3"));
+        let identity_key = IdentityKey::new(KeyPair::generate(&mut rand::thread_rng()).public_key);
+        let e164 = E164::new(NonZeroU64::new(18005550100).expect("nonzero"));
+
+        let mut expected = AccountExpectations {
+            aci,
+            identity_key,
+            e164: None,
+            username_hash: None,
+        };
+        let fields = |expected: &AccountExpectations| -> Vec<AccountField> {
+            expected.entries().into_iter().map(|(f, _, _)| f).collect()
+        };
+        assert_eq!(fields(&expected), [AccountField::IdentityKey]);
+
+        expected.e164 = Some(e164);
+        expected.username_hash = Some([1; 32].into());
+        assert_eq!(
+            fields(&expected),
+            [
+                AccountField::IdentityKey,
+                AccountField::E164,
+                AccountField::UsernameHash
+            ]
+        );
+
+        let entries = expected.entries();
+        assert_eq!(entries[0].1, SearchKey::Aci(aci));
+        assert_eq!(entries[0].2, &*identity_key.serialize());
+        assert_eq!(entries[1].1, SearchKey::E164(e164));
+        assert_eq!(entries[1].2, aci.service_id_binary());
+        assert_eq!(entries[2].2, aci.service_id_binary());
+    }
+}
@@ -3,14 +3,35 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+pub mod attachments;
 pub mod auth;
+pub mod backups;
+pub mod cdn;
 pub mod cdsi;
 pub mod chat;
+pub mod clock;
+pub mod device_sync;
 pub mod enclave;
 pub mod env;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+pub mod groups;
 pub mod infra;
+pub mod keytrans;
+pub mod messages;
+pub mod prekeys;
+pub mod profiles;
 pub mod proto;
+pub mod provisioning;
+pub mod push;
+pub mod receipts;
+pub mod registration;
+pub mod remote_config;
+pub mod route_discovery;
+pub mod sender_certificate;
+pub mod storage;
 pub mod svr;
+pub mod svr2;
 pub mod svr3;
 pub mod timeouts;
 pub mod utils;
@@ -0,0 +1,619 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Sending messages to all of another account's devices.
+//!
+//! [`send_message`] encrypts a message for each of the recipient's devices
+//! and sends the results together over an authenticated chat connection. The
+//! server rejects the send if the list of devices is out of date (409) or if
+//! some of them have been re-registered since their sessions were started
+//! (410). Either way, the affected sessions are archived, new sessions are
+//! started from freshly fetched prekey bundles, and the message is encrypted
+//! and sent again.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use libsignal_core::{DeviceId, ProtocolAddress, ServiceId};
+use libsignal_protocol::{
+    message_encrypt, CiphertextMessageType, IdentityKeyStore, SessionStore, SignalProtocolError,
+    Timestamp,
+};
+use rand::{CryptoRng, Rng};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::chat::{ChatService, ChatServiceError, Request};
+use crate::clock::Clock;
+use crate::prekeys::{self, DeviceSelection, DeviceSessionError};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times a message is sent before giving up on the recipient's
+/// devices settling down.
+const MAX_ATTEMPTS: usize = 3;
+
+// The server's envelope types for the two kinds of message produced by
+// `message_encrypt`.
+const ENVELOPE_TYPE_CIPHERTEXT: u8 = 1;
+const ENVELOPE_TYPE_PREKEY_BUNDLE: u8 = 3;
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum Error {
+    /// chat service error: {0}
+    ChatService(#[from] ChatServiceError),
+    /// could not fetch prekeys: {0}
+    PreKeys(prekeys::Error),
+    /// could not start a session with device {0}: {1}
+    SessionSetup(DeviceId, DeviceSessionError),
+    /// the identity key is not trusted for {0}
+    UntrustedIdentity(ProtocolAddress),
+    /// protocol error: {0}
+    Protocol(SignalProtocolError),
+    /// the recipient does not exist
+    NotFound,
+    /// request failed with status {0}
+    RequestFailed(StatusCode),
+    /// invalid response received from the server
+    InvalidResponse,
+    /// the recipient's devices were still changing after {0} attempts
+    TooManyAttempts(usize),
+}
+
+impl From<SignalProtocolError> for Error {
+    fn from(value: SignalProtocolError) -> Self {
+        match value.underlying_error() {
+            SignalProtocolError::UntrustedIdentity(address) => {
+                Self::UntrustedIdentity(address.clone())
+            }
+            _ => Self::Protocol(value),
+        }
+    }
+}
+
+impl From<prekeys::Error> for Error {
+    fn from(value: prekeys::Error) -> Self {
+        match value {
+            prekeys::Error::ChatService(e) => Self::ChatService(e),
+            prekeys::Error::NotFound => Self::NotFound,
+            e => Self::PreKeys(e),
+        }
+    }
+}
+
+/// A message to send with [`send_message`].
+#[derive(Clone, Copy, Debug)]
+pub struct OutgoingMessage<'a> {
+    pub destination: ServiceId,
+    /// The serialized and padded `Content`.
+    pub contents: &'a [u8],
+    pub timestamp: Timestamp,
+    /// Only deliver to devices that are currently connected, as for typing
+    /// indicators.
+    pub online: bool,
+    /// Whether the recipient should be woken up to process the message.
+    pub urgent: bool,
+}
+
+/// The result of a successful [`send_message`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct SendOutcome {
+    /// The devices the message was sent to, to be passed as `known_devices`
+    /// the next time.
+    pub devices: Vec<DeviceId>,
+    /// Set when sending to the local account if it has other devices that
+    /// should be sent a sync message.
+    pub needs_sync: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendRequest {
+    messages: Vec<DeviceMessage>,
+    online: bool,
+    urgent: bool,
+    timestamp: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceMessage {
+    #[serde(rename = "type")]
+    envelope_type: u8,
+    destination_device_id: u32,
+    destination_registration_id: u32,
+    content: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendResponse {
+    #[serde(default)]
+    needs_sync: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MismatchedDevices {
+    #[serde(default)]
+    missing_devices: Vec<u32>,
+    #[serde(default)]
+    extra_devices: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StaleDevices {
+    stale_devices: Vec<u32>,
+}
+
+/// Encrypts `message` for each of the destination's devices and sends it
+/// over `chat`, which must be authenticated as the sender.
+///
+/// `known_devices` are the devices the message was last sent to; if empty,
+/// all of the destination's devices are looked up. Sessions are started as
+/// needed, and when the server reports that the devices or sessions are out
+/// of date they are brought up to date and the message is sent again, up to a
+/// fixed number of attempts.
+pub async fn send_message(
+    chat: &(dyn ChatService + Send + Sync),
+    message: OutgoingMessage<'_>,
+    known_devices: &[DeviceId],
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    clock: &dyn Clock,
+    rng: &mut (impl Rng + CryptoRng),
+) -> Result<SendOutcome, Error> {
+    let destination = message.destination;
+    let address = |device_id| ProtocolAddress::new(destination.service_id_string(), device_id);
+    let mut devices = BTreeSet::from_iter(known_devices.iter().copied());
+
+    for _ in 0..MAX_ATTEMPTS {
+        start_missing_sessions(
+            chat,
+            destination,
+            &mut devices,
+            session_store,
+            identity_store,
+            clock,
+            rng,
+        )
+        .await?;
+
+        let mut messages = Vec::with_capacity(devices.len());
+        for &device_id in &devices {
+            messages.push(
+                encrypt_for_device(
+                    &address(device_id),
+                    message.contents,
+                    session_store,
+                    identity_store,
+                    clock,
+                )
+                .await?,
+            );
+        }
+        let request = SendRequest {
+            messages,
+            online: message.online,
+            urgent: message.urgent,
+            timestamp: message.timestamp.epoch_millis(),
+        };
+
+        let response = chat
+            .send(send_request(destination, &request), REQUEST_TIMEOUT)
+            .await?;
+        let body = response.body.unwrap_or_default();
+        // Devices whose sessions are out of date, either because the server
+        // says they were re-registered or because they weren't expected.
+        let outdated = match response.status {
+            status if status.is_success() => {
+                let SendResponse { needs_sync } = if body.is_empty() {
+                    SendResponse::default()
+                } else {
+                    parse_body(&body)?
+                };
+                return Ok(SendOutcome {
+                    devices: devices.into_iter().collect(),
+                    needs_sync,
+                });
+            }
+            StatusCode::CONFLICT => {
+                let MismatchedDevices {
+                    missing_devices,
+                    extra_devices,
+                } = parse_body(&body)?;
+                for device_id in &extra_devices {
+                    devices.remove(&parse_device_id(*device_id)?);
+                }
+                for device_id in &missing_devices {
+                    devices.insert(parse_device_id(*device_id)?);
+                }
+                // Any session with a missing device predates its current
+                // registration.
+                [missing_devices, extra_devices].concat()
+            }
+            StatusCode::GONE => {
+                let StaleDevices { stale_devices } = parse_body(&body)?;
+                stale_devices
+            }
+            StatusCode::NOT_FOUND => return Err(Error::NotFound),
+            status => return Err(Error::RequestFailed(status)),
+        };
+        for device_id in outdated {
+            archive_session(&address(parse_device_id(device_id)?), session_store).await?;
+        }
+    }
+    Err(Error::TooManyAttempts(MAX_ATTEMPTS))
+}
+
+/// Starts sessions with any of `devices` that don't have a usable one.
+///
+/// If no devices are known, every device the server knows about is added.
+/// Devices the server says don't exist are removed.
+async fn start_missing_sessions(
+    chat: &(dyn ChatService + Send + Sync),
+    destination: ServiceId,
+    devices: &mut BTreeSet<DeviceId>,
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    clock: &dyn Clock,
+    rng: &mut (impl Rng + CryptoRng),
+) -> Result<(), Error> {
+    let now = clock.now();
+    let mut needed = vec![];
+    for &device_id in devices.iter() {
+        let address = ProtocolAddress::new(destination.service_id_string(), device_id);
+        let usable = match session_store.load_session(&address).await? {
+            Some(record) => record.has_usable_sender_chain(now)?,
+            None => false,
+        };
+        if !usable {
+            needed.push(device_id);
+        }
+    }
+
+    let selection = match (devices.is_empty(), &needed[..]) {
+        (true, _) => DeviceSelection::All,
+        (false, []) => return Ok(()),
+        (false, needed) => DeviceSelection::Only(needed),
+    };
+    let outcome = prekeys::establish_sessions_with_access(
+        chat,
+        destination,
+        selection,
+        None,
+        session_store,
+        identity_store,
+        clock,
+        rng,
+    )
+    .await?;
+
+    devices.extend(outcome.established);
+    for (device_id, error) in outcome.failed {
+        match error {
+            DeviceSessionError::NotFound => {
+                devices.remove(&device_id);
+            }
+            DeviceSessionError::UntrustedIdentity(address) => {
+                return Err(Error::UntrustedIdentity(address))
+            }
+            error => return Err(Error::SessionSetup(device_id, error)),
+        }
+    }
+    if devices.is_empty() {
+        return Err(Error::NotFound);
+    }
+    Ok(())
+}
+
+async fn encrypt_for_device(
+    address: &ProtocolAddress,
+    contents: &[u8],
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    clock: &dyn Clock,
+) -> Result<DeviceMessage, Error> {
+    let ciphertext = message_encrypt(
+        contents,
+        address,
+        session_store,
+        identity_store,
+        clock.now(),
+    )
+    .await?;
+    let envelope_type = match ciphertext.message_type() {
+        CiphertextMessageType::Whisper => ENVELOPE_TYPE_CIPHERTEXT,
+        CiphertextMessageType::PreKey => ENVELOPE_TYPE_PREKEY_BUNDLE,
+        CiphertextMessageType::SenderKey | CiphertextMessageType::Plaintext => {
+            unreachable!("not produced by message_encrypt")
+        }
+    };
+    let destination_registration_id = session_store
+        .load_session(address)
+        .await?
+        .ok_or_else(|| SignalProtocolError::SessionNotFound(address.clone()))?
+        .remote_registration_id()?;
+
+    Ok(DeviceMessage {
+        envelope_type,
+        destination_device_id: address.device_id().into(),
+        destination_registration_id,
+        content: BASE64_STANDARD.encode(ciphertext.serialize()),
+    })
+}
+
+/// Archives the current session with `address`, if there is one, so that the
+/// next send starts a new one.
+async fn archive_session(
+    address: &ProtocolAddress,
+    session_store: &mut dyn SessionStore,
+) -> Result<(), Error> {
+    if let Some(mut record) = session_store.load_session(address).await? {
+        record.archive_current_state()?;
+        session_store.store_session(address, &record).await?;
+    }
+    Ok(())
+}
+
+fn send_request(destination: ServiceId, request: &SendRequest) -> Request {
+    let path = format!("/v1/messages/{}", destination.service_id_string());
+    Request {
+        method: Method::PUT,
+        body: Some(
+            serde_json::to_vec(request)
+                .expect("can serialize")
+                .into_boxed_slice(),
+        ),
+        headers: HeaderMap::from_iter([(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )]),
+        path: PathAndQuery::try_from(path).expect("valid path"),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    serde_json::from_slice(body).map_err(|_| Error::InvalidResponse)
+}
+
+fn parse_device_id(device_id: u32) -> Result<DeviceId, Error> {
+    DeviceId::try_from(device_id).map_err(|_| Error::InvalidResponse)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use std::time::SystemTime;
+
+    use assert_matches::assert_matches;
+    use libsignal_core::Aci;
+    use libsignal_protocol::{IdentityKeyPair, InMemIdentityKeyStore, InMemSessionStore};
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::chat::testutil::{json_response, FakeChat};
+    use crate::clock::ManualClock;
+    use crate::prekeys::testutil::device_json;
+
+    const DESTINATION: Aci = Aci::from_uuid_bytes([0x11; 16]);
+
+    fn now() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    /// Serves prekey bundles for `devices` and answers message sends with
+    /// `send_responses` in order, then with success.
+    struct FakeServer {
+        chat: FakeChat,
+    }
+
+    impl FakeServer {
+        fn new(
+            devices: &[u32],
+            send_responses: impl IntoIterator<Item = (StatusCode, serde_json::Value)>,
+        ) -> Self {
+            let identity = IdentityKeyPair::generate(&mut OsRng);
+            let devices = devices.to_vec();
+            let send_responses = Mutex::new(VecDeque::from_iter(send_responses));
+            let chat = FakeChat::new(move |request| {
+                let (status, body) = if request.method == Method::GET {
+                    match prekeys(&identity, &devices, prekey_device(request)) {
+                        Some(body) => (StatusCode::OK, body),
+                        None => (StatusCode::NOT_FOUND, serde_json::Value::Null),
+                    }
+                } else {
+                    assert_eq!(
+                        request.path.path(),
+                        format!("/v1/messages/{}", DESTINATION.service_id_string())
+                    );
+                    send_responses
+                        .lock()
+                        .expect("not poisoned")
+                        .pop_front()
+                        .unwrap_or((StatusCode::OK, serde_json::json!({"needsSync": false})))
+                };
+                json_response(status, &body)
+            });
+            Self { chat }
+        }
+
+        /// The device each prekey request asked for, in order.
+        fn prekey_requests(&self) -> Vec<String> {
+            self.chat
+                .requests()
+                .iter()
+                .filter(|request| request.method == Method::GET)
+                .map(|request| prekey_device(request).to_owned())
+                .collect()
+        }
+
+        fn sent_device_ids(&self) -> Vec<Vec<u64>> {
+            self.chat
+                .requests()
+                .iter()
+                .filter(|request| request.method != Method::GET)
+                .map(|request| {
+                    let sent: serde_json::Value =
+                        serde_json::from_slice(request.body.as_deref().expect("has body"))
+                            .expect("valid json");
+                    sent["messages"]
+                        .as_array()
+                        .expect("has messages")
+                        .iter()
+                        .map(|m| m["destinationDeviceId"].as_u64().expect("has device"))
+                        .collect()
+                })
+                .collect()
+        }
+    }
+
+    fn prekey_device(request: &Request) -> &str {
+        request.path.path().rsplit('/').next().expect("non-empty")
+    }
+
+    fn prekeys(
+        identity: &IdentityKeyPair,
+        devices: &[u32],
+        device: &str,
+    ) -> Option<serde_json::Value> {
+        let devices = devices
+            .iter()
+            .filter(|id| device == "*" || device == id.to_string())
+            .map(|&id| device_json(identity, id, true))
+            .collect::<Vec<_>>();
+        (!devices.is_empty()).then(|| {
+            serde_json::json!({
+                "identityKey": BASE64_STANDARD.encode(identity.identity_key().serialize()),
+                "devices": devices,
+            })
+        })
+    }
+
+    fn stores() -> (InMemSessionStore, InMemIdentityKeyStore) {
+        (
+            InMemSessionStore::new(),
+            InMemIdentityKeyStore::new(IdentityKeyPair::generate(&mut OsRng), 5678),
+        )
+    }
+
+    fn device(id: u8) -> DeviceId {
+        DeviceId::new(id).expect("valid")
+    }
+
+    async fn send(
+        server: &FakeServer,
+        known_devices: &[DeviceId],
+        session_store: &mut InMemSessionStore,
+    ) -> Result<SendOutcome, Error> {
+        let (_, mut identity_store) = stores();
+        send_message(
+            &server.chat,
+            OutgoingMessage {
+                destination: DESTINATION.into(),
+                contents: b"hello",
+                timestamp: Timestamp::from_epoch_millis(1000),
+                online: false,
+                urgent: true,
+            },
+            known_devices,
+            session_store,
+            &mut identity_store,
+            &ManualClock::new(now()),
+            &mut OsRng,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn looks_up_devices_when_none_are_known() {
+        let server = FakeServer::new(&[1, 2], []);
+        let (mut session_store, _) = stores();
+
+        let outcome = send(&server, &[], &mut session_store).await.expect("sent");
+        assert_eq!(
+            outcome,
+            SendOutcome {
+                devices: vec![device(1), device(2)],
+                needs_sync: false,
+            }
+        );
+        assert_eq!(server.prekey_requests(), ["*"]);
+        assert_eq!(server.sent_device_ids(), [[1, 2]]);
+    }
+
+    #[tokio::test]
+    async fn retries_with_updated_devices() {
+        let server = FakeServer::new(
+            &[1, 2, 3],
+            [(
+                StatusCode::CONFLICT,
+                serde_json::json!({"missingDevices": [2], "extraDevices": [3]}),
+            )],
+        );
+        let (mut session_store, _) = stores();
+
+        let outcome = send(&server, &[device(1), device(3)], &mut session_store)
+            .await
+            .expect("sent");
+        assert_eq!(outcome.devices, [device(1), device(2)]);
+        assert_eq!(server.prekey_requests(), ["1", "3", "2"]);
+        assert_eq!(server.sent_device_ids(), [vec![1, 3], vec![1, 2]]);
+
+        let extra = session_store
+            .load_session(&ProtocolAddress::new(
+                DESTINATION.service_id_string(),
+                device(3),
+            ))
+            .await
+            .expect("can load")
+            .expect("archived session is kept");
+        assert!(!extra.has_usable_sender_chain(now()).expect("valid"));
+    }
+
+    #[tokio::test]
+    async fn restarts_stale_sessions() {
+        let server = FakeServer::new(
+            &[1, 2],
+            [(StatusCode::GONE, serde_json::json!({"staleDevices": [2]}))],
+        );
+        let (mut session_store, _) = stores();
+
+        send(&server, &[device(1), device(2)], &mut session_store)
+            .await
+            .expect("sent");
+        assert_eq!(server.prekey_requests(), ["1", "2", "2"]);
+        assert_eq!(server.sent_device_ids(), [[1, 2], [1, 2]]);
+    }
+
+    #[tokio::test]
+    async fn gives_up_eventually() {
+        let stale = (StatusCode::GONE, serde_json::json!({"staleDevices": [1]}));
+        let server = FakeServer::new(&[1], vec![stale; MAX_ATTEMPTS]);
+        let (mut session_store, _) = stores();
+
+        assert_matches!(
+            send(&server, &[device(1)], &mut session_store).await,
+            Err(Error::TooManyAttempts(MAX_ATTEMPTS))
+        );
+        assert_eq!(server.sent_device_ids().len(), MAX_ATTEMPTS);
+    }
+
+    #[tokio::test]
+    async fn unknown_recipient() {
+        let server = FakeServer::new(&[], []);
+        let (mut session_store, _) = stores();
+
+        assert_matches!(
+            send(&server, &[], &mut session_store).await,
+            Err(Error::NotFound)
+        );
+    }
+}
@@ -0,0 +1,513 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Starting sessions with another account's devices.
+//!
+//! [`establish_sessions`] fetches prekey bundles from the chat server over an
+//! unauthenticated connection, turns them into [`PreKeyBundle`]s, and
+//! processes each one against the caller's stores. A problem with one device
+//! doesn't stop the others; the result says which devices are ready to
+//! receive messages and why the rest aren't.
+
+use std::time::Duration;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use libsignal_core::{DeviceId, ProtocolAddress, ServiceId};
+use libsignal_protocol::{
+    kem, process_prekey_bundle, IdentityKey, IdentityKeyStore, PreKeyBundle, PublicKey,
+    SessionStore, SignalProtocolError,
+};
+use rand::{CryptoRng, Rng};
+use serde::Deserialize;
+
+use crate::chat::{ChatService, ChatServiceError, Request};
+use crate::clock::Clock;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const UNIDENTIFIED_ACCESS_KEY_HEADER: HeaderName =
+    HeaderName::from_static("unidentified-access-key");
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum Error {
+    /// chat service error: {0}
+    ChatService(#[from] ChatServiceError),
+    /// the access key was rejected
+    Unauthorized,
+    /// the account does not exist
+    NotFound,
+    /// request failed with status {0}
+    RequestFailed(StatusCode),
+    /// invalid response received from the server
+    InvalidResponse,
+}
+
+/// Why a session could not be started with a particular device.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum DeviceSessionError {
+    /// the device does not exist
+    NotFound,
+    /// the prekey bundle was malformed or improperly signed
+    InvalidBundle,
+    /// the identity key is not trusted for {0}
+    UntrustedIdentity(ProtocolAddress),
+    /// protocol error: {0}
+    Protocol(SignalProtocolError),
+}
+
+/// Which of the destination's devices to start sessions with.
+#[derive(Clone, Copy, Debug)]
+pub enum DeviceSelection<'a> {
+    /// Every device the server knows about.
+    All,
+    /// Only the listed devices.
+    Only(&'a [DeviceId]),
+}
+
+/// The result of [`establish_sessions`].
+#[derive(Debug, Default)]
+pub struct SessionSetupOutcome {
+    /// Devices that now have a session in the session store.
+    pub established: Vec<DeviceId>,
+    /// Devices that could not be set up, along with the reason.
+    pub failed: Vec<(DeviceId, DeviceSessionError)>,
+    /// Device IDs the server sent that aren't valid device IDs. These devices
+    /// were skipped.
+    pub invalid_device_ids: Vec<u32>,
+}
+
+/// The bundles in one prekey response.
+#[derive(Default)]
+struct FetchedBundles {
+    /// Bundles that can't be parsed are `None` so they can be reported against
+    /// their device.
+    bundles: Vec<(DeviceId, Option<PreKeyBundle>)>,
+    invalid_device_ids: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PreKeyResponse {
+    identity_key: String,
+    devices: Vec<DeviceBundle>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceBundle {
+    device_id: u32,
+    registration_id: u32,
+    pre_key: Option<EcPreKey>,
+    signed_pre_key: SignedPreKey,
+    pq_pre_key: Option<SignedPreKey>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EcPreKey {
+    key_id: u32,
+    public_key: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignedPreKey {
+    key_id: u32,
+    public_key: String,
+    signature: String,
+}
+
+impl DeviceBundle {
+    fn into_bundle(self, identity_key: IdentityKey) -> Option<PreKeyBundle> {
+        let Self {
+            device_id,
+            registration_id,
+            pre_key,
+            signed_pre_key,
+            pq_pre_key,
+        } = self;
+
+        let decode = |value: &str| BASE64_STANDARD.decode(value).ok();
+        let pre_key = match pre_key {
+            Some(EcPreKey { key_id, public_key }) => Some((
+                key_id.into(),
+                PublicKey::deserialize(&decode(&public_key)?).ok()?,
+            )),
+            None => None,
+        };
+        let bundle = PreKeyBundle::new(
+            registration_id,
+            DeviceId::try_from(device_id).ok()?,
+            pre_key,
+            signed_pre_key.key_id.into(),
+            PublicKey::deserialize(&decode(&signed_pre_key.public_key)?).ok()?,
+            decode(&signed_pre_key.signature)?,
+            identity_key,
+        )
+        .ok()?;
+        Some(match pq_pre_key {
+            Some(SignedPreKey {
+                key_id,
+                public_key,
+                signature,
+            }) => bundle.with_kyber_pre_key(
+                key_id.into(),
+                kem::PublicKey::deserialize(&decode(&public_key)?).ok()?,
+                decode(&signature)?,
+            ),
+            None => bundle,
+        })
+    }
+}
+
+impl From<SignalProtocolError> for DeviceSessionError {
+    fn from(value: SignalProtocolError) -> Self {
+        match value.underlying_error() {
+            SignalProtocolError::UntrustedIdentity(address) => {
+                Self::UntrustedIdentity(address.clone())
+            }
+            SignalProtocolError::SignatureValidationFailed => Self::InvalidBundle,
+            _ => Self::Protocol(value),
+        }
+    }
+}
+
+/// Fetches prekey bundles for `devices` of `destination` over `chat` and
+/// starts a session with each device.
+///
+/// `chat` should be unauthenticated; `access_key` is the destination's
+/// unidentified access key. Per-device problems are reported in the
+/// [`SessionSetupOutcome`]; an error is only returned if nothing could be
+/// fetched at all.
+pub async fn establish_sessions(
+    chat: &(dyn ChatService + Send + Sync),
+    destination: ServiceId,
+    devices: DeviceSelection<'_>,
+    access_key: &[u8; 16],
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    clock: &dyn Clock,
+    rng: &mut (impl Rng + CryptoRng),
+) -> Result<SessionSetupOutcome, Error> {
+    establish_sessions_with_access(
+        chat,
+        destination,
+        devices,
+        Some(access_key),
+        session_store,
+        identity_store,
+        clock,
+        rng,
+    )
+    .await
+}
+
+/// Like [`establish_sessions`], but `access_key` may be omitted if `chat` is
+/// authenticated.
+pub(crate) async fn establish_sessions_with_access(
+    chat: &(dyn ChatService + Send + Sync),
+    destination: ServiceId,
+    devices: DeviceSelection<'_>,
+    access_key: Option<&[u8; 16]>,
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    clock: &dyn Clock,
+    rng: &mut (impl Rng + CryptoRng),
+) -> Result<SessionSetupOutcome, Error> {
+    let mut outcome = SessionSetupOutcome::default();
+    let mut bundles = vec![];
+    match devices {
+        DeviceSelection::All => {
+            let fetched = fetch_bundles(chat, destination, "*", access_key).await?;
+            bundles = fetched.bundles;
+            outcome.invalid_device_ids = fetched.invalid_device_ids;
+        }
+        DeviceSelection::Only(device_ids) => {
+            for &device_id in device_ids {
+                match fetch_bundles(chat, destination, &device_id.to_string(), access_key).await {
+                    Ok(fetched) => {
+                        bundles.extend(
+                            fetched
+                                .bundles
+                                .into_iter()
+                                .filter(|(fetched_id, _)| *fetched_id == device_id),
+                        );
+                        outcome
+                            .invalid_device_ids
+                            .extend(fetched.invalid_device_ids);
+                    }
+                    Err(Error::NotFound) => outcome
+                        .failed
+                        .push((device_id, DeviceSessionError::NotFound)),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    for (device_id, bundle) in bundles {
+        let Some(bundle) = bundle else {
+            outcome
+                .failed
+                .push((device_id, DeviceSessionError::InvalidBundle));
+            continue;
+        };
+        let address = ProtocolAddress::new(destination.service_id_string(), device_id);
+        match process_prekey_bundle(
+            &address,
+            session_store,
+            identity_store,
+            &bundle,
+            clock.now(),
+            rng,
+        )
+        .await
+        {
+            Ok(()) => outcome.established.push(device_id),
+            Err(e) => outcome.failed.push((device_id, e.into())),
+        }
+    }
+    Ok(outcome)
+}
+
+/// Fetches the bundles at `/v2/keys/{destination}/{device}`.
+async fn fetch_bundles(
+    chat: &(dyn ChatService + Send + Sync),
+    destination: ServiceId,
+    device: &str,
+    access_key: Option<&[u8; 16]>,
+) -> Result<FetchedBundles, Error> {
+    let path = format!("/v2/keys/{}/{device}", destination.service_id_string());
+    let request = Request {
+        method: Method::GET,
+        body: None,
+        headers: HeaderMap::from_iter(access_key.map(|access_key| {
+            (
+                UNIDENTIFIED_ACCESS_KEY_HEADER,
+                HeaderValue::try_from(BASE64_STANDARD.encode(access_key))
+                    .expect("base64 is a valid header value"),
+            )
+        })),
+        path: PathAndQuery::try_from(path).expect("valid path"),
+    };
+
+    let response = chat.send(request, REQUEST_TIMEOUT).await?;
+    match response.status {
+        status if status.is_success() => {}
+        StatusCode::UNAUTHORIZED => return Err(Error::Unauthorized),
+        StatusCode::NOT_FOUND => return Err(Error::NotFound),
+        status => return Err(Error::RequestFailed(status)),
+    }
+
+    let PreKeyResponse {
+        identity_key,
+        devices,
+    } = serde_json::from_slice(&response.body.unwrap_or_default())
+        .map_err(|_| Error::InvalidResponse)?;
+    let identity_key = BASE64_STANDARD
+        .decode(identity_key)
+        .ok()
+        .and_then(|bytes| IdentityKey::decode(&bytes).ok())
+        .ok_or(Error::InvalidResponse)?;
+
+    let mut fetched = FetchedBundles::default();
+    for device in devices {
+        match DeviceId::try_from(device.device_id) {
+            Ok(device_id) => fetched
+                .bundles
+                .push((device_id, device.into_bundle(identity_key))),
+            Err(_) => fetched.invalid_device_ids.push(device.device_id),
+        }
+    }
+    Ok(fetched)
+}
+
+#[cfg(test)]
+pub(crate) mod testutil {
+    use libsignal_protocol::{IdentityKeyPair, KeyPair};
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    /// One device's entry in a prekey response, signed by `identity` unless
+    /// `valid` is false.
+    pub(crate) fn device_json(
+        identity: &IdentityKeyPair,
+        device_id: u32,
+        valid: bool,
+    ) -> serde_json::Value {
+        let signed_pre_key = KeyPair::generate(&mut OsRng);
+        let signature = if valid {
+            identity
+                .private_key()
+                .calculate_signature(&signed_pre_key.public_key.serialize(), &mut OsRng)
+                .expect("can sign")
+        } else {
+            vec![0; 64].into_boxed_slice()
+        };
+        serde_json::json!({
+            "deviceId": device_id,
+            "registrationId": 1234,
+            "preKey": {
+                "keyId": 1,
+                "publicKey": BASE64_STANDARD.encode(KeyPair::generate(&mut OsRng).public_key.serialize()),
+            },
+            "signedPreKey": {
+                "keyId": 2,
+                "publicKey": BASE64_STANDARD.encode(signed_pre_key.public_key.serialize()),
+                "signature": BASE64_STANDARD.encode(signature),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use assert_matches::assert_matches;
+    use libsignal_core::Aci;
+    use libsignal_protocol::{IdentityKeyPair, InMemIdentityKeyStore, InMemSessionStore};
+    use rand::rngs::OsRng;
+
+    use super::testutil::device_json;
+    use super::*;
+    use crate::chat::testutil::{json_response, FakeChat};
+    use crate::chat::Response;
+    use crate::clock::SystemClock;
+
+    const DESTINATION: Aci = Aci::from_uuid_bytes([0x11; 16]);
+
+    /// Serves a prekey response per device, keyed by the last path segment.
+    fn serving(responses: HashMap<&'static str, serde_json::Value>) -> FakeChat {
+        FakeChat::new(move |request| {
+            let device = request.path.path().rsplit('/').next().expect("non-empty");
+            match responses.get(device) {
+                Some(body) => json_response(StatusCode::OK, body),
+                None => Response {
+                    status: StatusCode::NOT_FOUND,
+                    message: None,
+                    body: None,
+                    headers: HeaderMap::new(),
+                },
+            }
+        })
+    }
+
+    fn stores() -> (InMemSessionStore, InMemIdentityKeyStore) {
+        (
+            InMemSessionStore::new(),
+            InMemIdentityKeyStore::new(IdentityKeyPair::generate(&mut OsRng), 5678),
+        )
+    }
+
+    #[tokio::test]
+    async fn reports_each_device() {
+        let identity = IdentityKeyPair::generate(&mut OsRng);
+        let identity_key = BASE64_STANDARD.encode(identity.identity_key().serialize());
+        let chat = serving(HashMap::from([(
+            "*",
+            serde_json::json!({
+                "identityKey": identity_key,
+                "devices": [
+                    device_json(&identity, 1, true),
+                    device_json(&identity, 2, false),
+                    device_json(&identity, 3, true),
+                    device_json(&identity, 0, true),
+                    device_json(&identity, 1000, true),
+                ],
+            }),
+        )]));
+        let (mut session_store, mut identity_store) = stores();
+
+        let outcome = establish_sessions(
+            &chat,
+            DESTINATION.into(),
+            DeviceSelection::All,
+            &[0; 16],
+            &mut session_store,
+            &mut identity_store,
+            &SystemClock,
+            &mut OsRng,
+        )
+        .await
+        .expect("fetched");
+
+        let device = |id: u8| DeviceId::new(id).expect("valid");
+        assert_eq!(outcome.established, [device(1), device(3)]);
+        assert_matches!(
+            &outcome.failed[..],
+            [(id, DeviceSessionError::InvalidBundle)] if *id == device(2)
+        );
+        assert_eq!(outcome.invalid_device_ids, [0, 1000]);
+        for id in [1, 3] {
+            let address = ProtocolAddress::new(DESTINATION.service_id_string(), device(id));
+            assert!(session_store
+                .load_session(&address)
+                .await
+                .expect("can load")
+                .is_some());
+        }
+
+        let requests = chat.requests();
+        assert_eq!(
+            requests[0].path.path(),
+            format!("/v2/keys/{}/*", DESTINATION.service_id_string())
+        );
+        assert_eq!(
+            requests[0].headers.get(UNIDENTIFIED_ACCESS_KEY_HEADER),
+            Some(&HeaderValue::from_static("AAAAAAAAAAAAAAAAAAAAAA=="))
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_devices_and_accounts() {
+        let identity = IdentityKeyPair::generate(&mut OsRng);
+        let chat = serving(HashMap::from([(
+            "1",
+            serde_json::json!({
+                "identityKey": BASE64_STANDARD.encode(identity.identity_key().serialize()),
+                "devices": [device_json(&identity, 1, true)],
+            }),
+        )]));
+        let (mut session_store, mut identity_store) = stores();
+
+        let device = |id: u8| DeviceId::new(id).expect("valid");
+        let outcome = establish_sessions(
+            &chat,
+            DESTINATION.into(),
+            DeviceSelection::Only(&[device(1), device(2)]),
+            &[0; 16],
+            &mut session_store,
+            &mut identity_store,
+            &SystemClock,
+            &mut OsRng,
+        )
+        .await
+        .expect("fetched");
+        assert_eq!(outcome.established, [device(1)]);
+        assert_matches!(
+            &outcome.failed[..],
+            [(id, DeviceSessionError::NotFound)] if *id == device(2)
+        );
+
+        assert_matches!(
+            establish_sessions(
+                &chat,
+                DESTINATION.into(),
+                DeviceSelection::All,
+                &[0; 16],
+                &mut session_store,
+                &mut identity_store,
+                &SystemClock,
+                &mut OsRng,
+            )
+            .await,
+            Err(Error::NotFound)
+        );
+    }
+}
@@ -0,0 +1,722 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for the versioned profile endpoints on the chat server.
+//!
+//! Profile fields are encrypted with the profile key before they are
+//! uploaded, so the server only ever sees ciphertext. Fetching a profile
+//! also requests an expiring profile key credential, which proves
+//! possession of the profile key when joining groups; [`ProfileClient`]
+//! builds the credential request, checks the server's response, and
+//! decrypts the profile fields, so callers only deal with plaintext.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bytes::Bytes;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderName, HeaderValue, Method};
+use libsignal_core::Aci;
+use libsignal_protocol::IdentityKey;
+use rand_core::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use signal_crypto::{Aead, Aes256Gcm};
+use zkgroup::profiles::{
+    ExpiringProfileKeyCredential, ExpiringProfileKeyCredentialResponse, ProfileKey,
+    ProfileKeyCredentialRequestContext,
+};
+use zkgroup::{ServerPublicParams, Timestamp};
+
+use crate::auth::Auth;
+use crate::cdn::{HttpEndpoint, RequestError, DEFAULT_MAX_RESPONSE_SIZE};
+use crate::clock::{system_clock, Clock};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::TransportConnector;
+use crate::utils::basic_authorization;
+
+const PROFILE_PATH: &str = "/v1/profile";
+
+const UNIDENTIFIED_ACCESS_KEY_HEADER: HeaderName =
+    HeaderName::from_static("unidentified-access-key");
+
+const NONCE_LEN: usize = 12;
+
+/// A versioned profile field encrypted under the profile key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProfileField {
+    /// The given and family names, joined with a NUL.
+    Name,
+    About,
+    AboutEmoji,
+    /// A serialized payment address, which is length-prefixed before padding
+    /// so that trailing zeros survive.
+    PaymentAddress,
+}
+
+impl ProfileField {
+    /// The lengths the plaintext is padded to, so that the ciphertext only
+    /// reveals a rough size. The shortest that fits is used.
+    pub fn padded_lengths(self) -> &'static [usize] {
+        match self {
+            ProfileField::Name => &[53, 257],
+            ProfileField::About => &[128, 254, 512],
+            ProfileField::AboutEmoji => &[32],
+            ProfileField::PaymentAddress => &[554],
+        }
+    }
+
+    /// The field's name in requests and responses.
+    pub fn name(self) -> &'static str {
+        match self {
+            ProfileField::Name => "name",
+            ProfileField::About => "about",
+            ProfileField::AboutEmoji => "aboutEmoji",
+            ProfileField::PaymentAddress => "paymentAddress",
+        }
+    }
+}
+
+/// How a profile request is authorized.
+#[derive(Clone)]
+pub enum ProfileAuth {
+    /// The target's unidentified access key, derived from their profile key.
+    AccessKey([u8; zkgroup::ACCESS_KEY_LEN]),
+    /// The requesting account's own credentials.
+    Account(Auth),
+}
+
+/// A profile name, as shown in the app.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileName {
+    pub given_name: String,
+    pub family_name: Option<String>,
+}
+
+/// A profile with all of its fields decrypted.
+#[derive(Clone)]
+pub struct DecryptedProfile {
+    pub identity_key: IdentityKey,
+    pub name: Option<ProfileName>,
+    pub about: Option<String>,
+    pub about_emoji: Option<String>,
+    /// The CDN path of the encrypted avatar, if there is one.
+    pub avatar: Option<String>,
+    /// The serialized payment address.
+    pub payment_address: Option<Vec<u8>>,
+    /// Whether anyone may send sealed sender messages to this account.
+    pub unrestricted_unidentified_access: bool,
+    pub capabilities: HashMap<String, bool>,
+    /// Present if the requested profile key matched the account's.
+    pub credential: Option<ExpiringProfileKeyCredential>,
+}
+
+/// The plaintext fields of the local account's profile, for uploading.
+#[derive(Clone, Debug, Default)]
+pub struct ProfileWrite {
+    pub name: Option<ProfileName>,
+    pub about: Option<String>,
+    pub about_emoji: Option<String>,
+    pub payment_address: Option<Vec<u8>>,
+    /// Keep the existing avatar rather than removing it.
+    pub keep_avatar: bool,
+    pub badge_ids: Vec<String>,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ProfileError {
+    /// request failed: {0}
+    Request(#[from] RequestError),
+    /// the profile key credential in the response was invalid
+    InvalidCredential,
+    /// profile field `{0}` could not be decrypted
+    DecryptionFailed(&'static str),
+    /// profile field `{0}` is too long
+    FieldTooLong(&'static str),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionedProfileResponse {
+    identity_key: String,
+    name: Option<String>,
+    about: Option<String>,
+    about_emoji: Option<String>,
+    avatar: Option<String>,
+    payment_address: Option<String>,
+    #[serde(default)]
+    unrestricted_unidentified_access: bool,
+    #[serde(default)]
+    capabilities: HashMap<String, bool>,
+    credential: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SetProfileRequest<'a> {
+    version: String,
+    name: String,
+    about: Option<String>,
+    about_emoji: Option<String>,
+    payment_address: Option<String>,
+    avatar: bool,
+    same_avatar: bool,
+    commitment: String,
+    badge_ids: &'a [String],
+}
+
+/// Encrypts and decrypts profile fields with a profile key.
+///
+/// Each field is padded with zeros, then encrypted with AES-256-GCM under a
+/// random nonce; the result is the nonce, the ciphertext, and the tag.
+pub struct ProfileCipher {
+    key: [u8; zkgroup::PROFILE_KEY_LEN],
+}
+
+impl ProfileCipher {
+    pub fn new(profile_key: &ProfileKey) -> Self {
+        Self {
+            key: profile_key.get_bytes(),
+        }
+    }
+
+    /// Pads and encrypts `plaintext` as `field`.
+    pub fn encrypt_field(
+        &self,
+        field: ProfileField,
+        plaintext: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Vec<u8>, ProfileError> {
+        let too_long = || ProfileError::FieldTooLong(field.name());
+        let mut padded = match field {
+            ProfileField::PaymentAddress => {
+                let length = u32::try_from(plaintext.len()).map_err(|_| too_long())?;
+                [&length.to_be_bytes()[..], plaintext].concat()
+            }
+            ProfileField::Name | ProfileField::About | ProfileField::AboutEmoji => {
+                plaintext.to_vec()
+            }
+        };
+        let padded_length = field
+            .padded_lengths()
+            .iter()
+            .copied()
+            .find(|length| *length >= padded.len())
+            .ok_or_else(too_long)?;
+        padded.resize(padded_length, 0);
+
+        let mut nonce = [0; NONCE_LEN];
+        rng.fill_bytes(&mut nonce);
+        let ciphertext =
+            Aes256Gcm::encrypt(&self.key, &nonce, &[], &padded).expect("valid key and nonce");
+        Ok([&nonce[..], &ciphertext].concat())
+    }
+
+    /// Decrypts `field` and removes its padding.
+    pub fn decrypt_field(
+        &self,
+        field: ProfileField,
+        input: &[u8],
+    ) -> Result<Vec<u8>, ProfileError> {
+        let failed = || ProfileError::DecryptionFailed(field.name());
+        if input.len() < NONCE_LEN {
+            return Err(failed());
+        }
+        let (nonce, ciphertext) = input.split_at(NONCE_LEN);
+        let mut plaintext =
+            Aes256Gcm::decrypt(&self.key, nonce, &[], ciphertext).map_err(|_| failed())?;
+        match field {
+            ProfileField::PaymentAddress => {
+                let (length, rest) = plaintext.split_first_chunk::<4>().ok_or_else(failed)?;
+                let length =
+                    usize::try_from(u32::from_be_bytes(*length)).expect("u32 fits in usize");
+                rest.get(..length).map(<[u8]>::to_vec).ok_or_else(failed)
+            }
+            ProfileField::Name | ProfileField::About | ProfileField::AboutEmoji => {
+                let unpadded_len = plaintext
+                    .iter()
+                    .rposition(|b| *b != 0)
+                    .map_or(0, |last| last + 1);
+                plaintext.truncate(unpadded_len);
+                Ok(plaintext)
+            }
+        }
+    }
+
+    /// Encrypts a field, returning it base64-encoded as it is uploaded.
+    fn encrypt_base64(
+        &self,
+        field: ProfileField,
+        plaintext: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<String, ProfileError> {
+        self.encrypt_field(field, plaintext, rng)
+            .map(|ciphertext| BASE64_STANDARD.encode(ciphertext))
+    }
+
+    /// Decrypts a base64-encoded field as it is downloaded.
+    fn decrypt_base64(&self, field: ProfileField, input: &str) -> Result<Vec<u8>, ProfileError> {
+        let ciphertext = BASE64_STANDARD
+            .decode(input)
+            .map_err(|_| ProfileError::DecryptionFailed(field.name()))?;
+        self.decrypt_field(field, &ciphertext)
+    }
+
+    /// Decrypts a base64-encoded string field.
+    ///
+    /// An empty field is treated as absent.
+    fn decrypt_string(
+        &self,
+        field: ProfileField,
+        input: &str,
+    ) -> Result<Option<String>, ProfileError> {
+        let plaintext = String::from_utf8(self.decrypt_base64(field, input)?)
+            .map_err(|_| ProfileError::DecryptionFailed(field.name()))?;
+        Ok(Some(plaintext).filter(|s| !s.is_empty()))
+    }
+}
+
+impl ProfileName {
+    /// The given and family names are joined with a NUL.
+    fn to_plaintext(&self) -> String {
+        match &self.family_name {
+            Some(family_name) => format!("{}\0{family_name}", self.given_name),
+            None => self.given_name.clone(),
+        }
+    }
+
+    fn from_plaintext(plaintext: String) -> Self {
+        match plaintext.split_once('\0') {
+            Some((given_name, family_name)) => Self {
+                given_name: given_name.to_owned(),
+                family_name: Some(family_name.to_owned()).filter(|name| !name.is_empty()),
+            },
+            None => Self {
+                given_name: plaintext,
+                family_name: None,
+            },
+        }
+    }
+}
+
+impl ProfileAuth {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        match self {
+            ProfileAuth::AccessKey(access_key) => headers.insert(
+                UNIDENTIFIED_ACCESS_KEY_HEADER,
+                HeaderValue::try_from(BASE64_STANDARD.encode(access_key))
+                    .expect("base64 is a valid header value"),
+            ),
+            ProfileAuth::Account(auth) => headers.insert(
+                http::header::AUTHORIZATION,
+                basic_authorization(&auth.username, auth.password.expose()),
+            ),
+        };
+        headers
+    }
+}
+
+/// The path for fetching a versioned profile along with a credential.
+fn versioned_profile_path(
+    aci: Aci,
+    profile_key: &ProfileKey,
+    context: &ProfileKeyCredentialRequestContext,
+) -> PathAndQuery {
+    let request = hex::encode(zkgroup::serialize(&context.get_request()));
+    PathAndQuery::from_str(&format!(
+        "{PROFILE_PATH}/{}/{}/{request}?credentialType=expiringProfileKey",
+        aci.service_id_string(),
+        profile_key_version(aci, profile_key),
+    ))
+    .expect("valid path")
+}
+
+/// The hex-encoded version of `profile_key`, which identifies it when fetching
+/// or setting a versioned profile.
+pub fn profile_key_version(aci: Aci, profile_key: &ProfileKey) -> String {
+    String::from_utf8(zkgroup::serialize(
+        &profile_key.get_profile_key_version(aci),
+    ))
+    .expect("versions are hex")
+}
+
+/// The serialized commitment to `profile_key`, uploaded along with a new
+/// versioned profile so that the server can issue credentials for it.
+pub fn profile_key_commitment(aci: Aci, profile_key: &ProfileKey) -> Vec<u8> {
+    zkgroup::serialize(&profile_key.get_commitment(aci))
+}
+
+impl VersionedProfileResponse {
+    fn decrypt(
+        self,
+        cipher: &ProfileCipher,
+        credential: impl FnOnce(
+            &ExpiringProfileKeyCredentialResponse,
+        ) -> Option<ExpiringProfileKeyCredential>,
+    ) -> Result<DecryptedProfile, ProfileError> {
+        let Self {
+            identity_key,
+            name,
+            about,
+            about_emoji,
+            avatar,
+            payment_address,
+            unrestricted_unidentified_access,
+            capabilities,
+            credential: credential_response,
+        } = self;
+
+        let identity_key = BASE64_STANDARD
+            .decode(identity_key)
+            .ok()
+            .and_then(|bytes| IdentityKey::decode(&bytes).ok())
+            .ok_or(RequestError::InvalidResponse)?;
+
+        let credential = credential_response
+            .map(|response| {
+                let response = BASE64_STANDARD
+                    .decode(response)
+                    .ok()
+                    .and_then(|bytes| zkgroup::deserialize(&bytes).ok())
+                    .ok_or(ProfileError::InvalidCredential)?;
+                credential(&response).ok_or(ProfileError::InvalidCredential)
+            })
+            .transpose()?;
+
+        let decrypt_string = |field, value: Option<String>| match value {
+            Some(value) => cipher.decrypt_string(field, &value),
+            None => Ok(None),
+        };
+
+        Ok(DecryptedProfile {
+            identity_key,
+            name: decrypt_string(ProfileField::Name, name)?.map(ProfileName::from_plaintext),
+            about: decrypt_string(ProfileField::About, about)?,
+            about_emoji: decrypt_string(ProfileField::AboutEmoji, about_emoji)?,
+            avatar,
+            payment_address: payment_address
+                .map(|address| cipher.decrypt_base64(ProfileField::PaymentAddress, &address))
+                .transpose()?,
+            unrestricted_unidentified_access,
+            capabilities,
+            credential,
+        })
+    }
+}
+
+impl<'a> SetProfileRequest<'a> {
+    fn new(
+        aci: Aci,
+        profile_key: &ProfileKey,
+        profile: &'a ProfileWrite,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Self, ProfileError> {
+        let ProfileWrite {
+            name,
+            about,
+            about_emoji,
+            payment_address,
+            keep_avatar,
+            badge_ids,
+        } = profile;
+        let cipher = ProfileCipher::new(profile_key);
+
+        // The name is always uploaded, even if empty, so that its length is
+        // hidden.
+        let name = cipher.encrypt_base64(
+            ProfileField::Name,
+            name.as_ref()
+                .map(ProfileName::to_plaintext)
+                .unwrap_or_default()
+                .as_bytes(),
+            rng,
+        )?;
+        let about = about
+            .as_deref()
+            .map(|about| cipher.encrypt_base64(ProfileField::About, about.as_bytes(), rng))
+            .transpose()?;
+        let about_emoji = about_emoji
+            .as_deref()
+            .map(|emoji| cipher.encrypt_base64(ProfileField::AboutEmoji, emoji.as_bytes(), rng))
+            .transpose()?;
+        let payment_address = payment_address
+            .as_deref()
+            .map(|address| cipher.encrypt_base64(ProfileField::PaymentAddress, address, rng))
+            .transpose()?;
+
+        Ok(Self {
+            version: profile_key_version(aci, profile_key),
+            name,
+            about,
+            about_emoji,
+            payment_address,
+            avatar: false,
+            same_avatar: *keep_avatar,
+            commitment: BASE64_STANDARD.encode(profile_key_commitment(aci, profile_key)),
+            badge_ids,
+        })
+    }
+}
+
+/// Client for the versioned profile endpoints on the chat server.
+pub struct ProfileClient<C, T> {
+    endpoint: HttpEndpoint<C, T>,
+    server_public_params: ServerPublicParams,
+    clock: Arc<dyn Clock>,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> ProfileClient<C, T> {
+    pub fn new(
+        connection_manager: C,
+        transport_connector: T,
+        server_public_params: ServerPublicParams,
+    ) -> Self {
+        Self {
+            endpoint: HttpEndpoint::new(
+                connection_manager,
+                transport_connector,
+                DEFAULT_MAX_RESPONSE_SIZE,
+            ),
+            server_public_params,
+            clock: system_clock(),
+        }
+    }
+
+    /// Uses `clock` instead of the system clock to check credential
+    /// expirations.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Fetches and decrypts the profile for `aci` at the version matching
+    /// `profile_key`, along with an expiring profile key credential.
+    pub async fn get_profile(
+        &self,
+        aci: Aci,
+        profile_key: ProfileKey,
+        auth: &ProfileAuth,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<DecryptedProfile, ProfileError> {
+        let mut randomness = [0; zkgroup::RANDOMNESS_LEN];
+        rng.fill_bytes(&mut randomness);
+        let context = self
+            .server_public_params
+            .create_profile_key_credential_request_context(randomness, aci, profile_key);
+
+        let (_parts, body) = self
+            .endpoint
+            .send(
+                Method::GET,
+                versioned_profile_path(aci, &profile_key, &context),
+                auth.headers(),
+                Bytes::new(),
+            )
+            .await?;
+        let response: VersionedProfileResponse =
+            serde_json::from_slice(&body).map_err(|_| RequestError::InvalidResponse)?;
+
+        let now = Timestamp::from(self.clock.now());
+        response.decrypt(&ProfileCipher::new(&profile_key), |credential| {
+            self.server_public_params
+                .receive_expiring_profile_key_credential(&context, credential, now)
+                .ok()
+        })
+    }
+
+    /// Encrypts and uploads a new version of the local account's profile.
+    ///
+    /// Uploading a new avatar is not supported; the existing one is kept or
+    /// removed according to [`ProfileWrite::keep_avatar`].
+    pub async fn set_profile(
+        &self,
+        auth: &Auth,
+        aci: Aci,
+        profile_key: ProfileKey,
+        profile: &ProfileWrite,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(), ProfileError> {
+        let request = SetProfileRequest::new(aci, &profile_key, profile, rng)?;
+        let body = serde_json::to_vec(&request).expect("can serialize");
+
+        let mut headers = ProfileAuth::Account(auth.clone()).headers();
+        headers.insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        self.endpoint
+            .send(
+                Method::PUT,
+                PathAndQuery::from_static(PROFILE_PATH),
+                headers,
+                Bytes::from(body),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use libsignal_protocol::IdentityKeyPair;
+    use rand::rngs::OsRng;
+    use zkgroup::ServerSecretParams;
+
+    use super::*;
+
+    const ACI: Aci = Aci::from_uuid_bytes([0xaa; 16]);
+    const PROFILE_KEY_BYTES: [u8; 32] = [0x42; 32];
+
+    #[test]
+    fn string_fields_round_trip() {
+        let cipher = ProfileCipher::new(&ProfileKey::create(PROFILE_KEY_BYTES));
+        let encrypted = cipher
+            .encrypt_base64(ProfileField::About, b"hello", &mut OsRng)
+            .expect("fits");
+        let decoded = BASE64_STANDARD.decode(&encrypted).expect("base64");
+        assert_eq!(decoded.len(), NONCE_LEN + 128 + 16);
+        assert_eq!(
+            cipher
+                .decrypt_string(ProfileField::About, &encrypted)
+                .expect("valid"),
+            Some("hello".to_owned())
+        );
+
+        let other_cipher = ProfileCipher::new(&ProfileKey::create([0x43; 32]));
+        assert_matches!(
+            other_cipher.decrypt_string(ProfileField::About, &encrypted),
+            Err(ProfileError::DecryptionFailed("about"))
+        );
+
+        assert_matches!(
+            cipher.encrypt_field(ProfileField::AboutEmoji, &[b'x'; 33], &mut OsRng),
+            Err(ProfileError::FieldTooLong("aboutEmoji"))
+        );
+    }
+
+    #[test]
+    fn payment_address_round_trip() {
+        let cipher = ProfileCipher::new(&ProfileKey::create(PROFILE_KEY_BYTES));
+        let address = [1, 2, 3, 0, 0];
+        let encrypted = cipher
+            .encrypt_field(ProfileField::PaymentAddress, &address, &mut OsRng)
+            .expect("fits");
+        assert_eq!(encrypted.len(), NONCE_LEN + 554 + 16);
+        assert_eq!(
+            cipher
+                .decrypt_field(ProfileField::PaymentAddress, &encrypted)
+                .expect("valid"),
+            address
+        );
+
+        // Other fields drop trailing zeros along with the padding.
+        let encrypted = cipher
+            .encrypt_field(ProfileField::About, &address, &mut OsRng)
+            .expect("fits");
+        assert_eq!(
+            cipher
+                .decrypt_field(ProfileField::About, &encrypted)
+                .expect("valid"),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn names() {
+        for name in [
+            ProfileName {
+                given_name: "Alice".to_owned(),
+                family_name: Some("Smith".to_owned()),
+            },
+            ProfileName {
+                given_name: "Bob".to_owned(),
+                family_name: None,
+            },
+        ] {
+            assert_eq!(ProfileName::from_plaintext(name.to_plaintext()), name);
+        }
+    }
+
+    #[test]
+    fn decrypts_written_profile_with_credential() {
+        let profile_key = ProfileKey::create(PROFILE_KEY_BYTES);
+        let profile = ProfileWrite {
+            name: Some(ProfileName {
+                given_name: "Alice".to_owned(),
+                family_name: None,
+            }),
+            about_emoji: Some("🦀".to_owned()),
+            payment_address: Some(vec![5; 10]),
+            ..Default::default()
+        };
+        let request =
+            SetProfileRequest::new(ACI, &profile_key, &profile, &mut OsRng).expect("fits");
+        assert_eq!(request.version.len(), 64);
+
+        let server_secret_params = ServerSecretParams::generate([1; 32]);
+        let server_public_params = server_secret_params.get_public_params();
+        let context = server_public_params.create_profile_key_credential_request_context(
+            [2; 32],
+            ACI,
+            profile_key,
+        );
+        let path = versioned_profile_path(ACI, &profile_key, &context);
+        assert!(path.path().starts_with(&format!(
+            "/v1/profile/{}/{}/",
+            ACI.service_id_string(),
+            request.version
+        )));
+
+        let expiration = Timestamp::from_epoch_seconds(17 * 24 * 60 * 60);
+        let credential_response = server_secret_params
+            .issue_expiring_profile_key_credential(
+                [3; 32],
+                &context.get_request(),
+                ACI,
+                profile_key.get_commitment(ACI),
+                expiration,
+            )
+            .expect("valid request");
+
+        let response = VersionedProfileResponse {
+            identity_key: BASE64_STANDARD.encode(
+                IdentityKeyPair::generate(&mut OsRng)
+                    .identity_key()
+                    .serialize(),
+            ),
+            name: Some(request.name),
+            about: request.about,
+            about_emoji: request.about_emoji,
+            avatar: None,
+            payment_address: request.payment_address,
+            unrestricted_unidentified_access: false,
+            capabilities: HashMap::new(),
+            credential: Some(BASE64_STANDARD.encode(zkgroup::serialize(&credential_response))),
+        };
+        let decrypted = response
+            .decrypt(&ProfileCipher::new(&profile_key), |credential| {
+                server_public_params
+                    .receive_expiring_profile_key_credential(
+                        &context,
+                        credential,
+                        expiration.sub_seconds(24 * 60 * 60),
+                    )
+                    .ok()
+            })
+            .expect("valid");
+
+        assert_eq!(decrypted.name, profile.name);
+        assert_eq!(decrypted.about, None);
+        assert_eq!(decrypted.about_emoji, profile.about_emoji);
+        assert_eq!(decrypted.payment_address, profile.payment_address);
+        assert_eq!(
+            decrypted.credential.map(|credential| credential.aci()),
+            Some(ACI)
+        );
+    }
+}
@@ -5,3 +5,10 @@
 
 pub(crate) mod cds2;
 pub mod chat_websocket;
+pub(crate) mod device_sync;
+pub mod groups;
+pub(crate) mod limits;
+pub(crate) mod provisioning;
+pub(crate) mod receipts;
+pub(crate) mod storage;
+pub(crate) mod svr2;
@@ -0,0 +1,188 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Limits on protobuf messages received from the server.
+//!
+//! Decoding allocates roughly in proportion to the size of the input, so a
+//! bound on the encoded size bounds the memory a single message can claim.
+//! Repeated fields are also checked against a limit on their length, since
+//! callers often do per-entry work (like parsing each header) that a message
+//! packed with tiny entries could make expensive.
+//!
+//! Responses fetched over HTTP are already bounded by the HTTP client's
+//! maximum response size; these limits are for messages arriving over
+//! long-lived websocket connections.
+
+use crate::infra::ws::error::ResponseLimitError;
+use crate::proto::cds2::ClientResponse;
+use crate::proto::chat_websocket::WebSocketMessage;
+
+/// Bounds for decoding one kind of server-provided message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct DecodeLimits {
+    /// The largest encoded message that will be decoded.
+    pub(crate) max_message_size: usize,
+    /// The most entries any repeated field, including those of nested
+    /// messages, may have.
+    pub(crate) max_repeated_field_len: usize,
+}
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub(crate) enum DecodeError {
+    /// {0}
+    Limit(ResponseLimitError),
+    /// invalid protobuf: {0}
+    Invalid(prost::DecodeError),
+}
+
+/// A message whose repeated fields can be checked against
+/// [`DecodeLimits::max_repeated_field_len`].
+pub(crate) trait RepeatedFields {
+    /// Calls `visit` with the name and length of each repeated field,
+    /// including those of nested messages.
+    fn visit_repeated_fields(&self, visit: &mut dyn FnMut(&'static str, usize));
+}
+
+impl DecodeLimits {
+    /// Checks that a message, or the part of one received so far, is within
+    /// [`Self::max_message_size`].
+    pub(crate) fn check_size(&self, size: usize) -> Result<(), ResponseLimitError> {
+        if size > self.max_message_size {
+            return Err(ResponseLimitError::ResponseTooLarge {
+                size,
+                max_size: self.max_message_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the repeated fields of an already-decoded `message`.
+    pub(crate) fn check_repeated_fields(
+        &self,
+        message: &impl RepeatedFields,
+    ) -> Result<(), ResponseLimitError> {
+        let mut result = Ok(());
+        message.visit_repeated_fields(&mut |field, len| {
+            if result.is_ok() && len > self.max_repeated_field_len {
+                result = Err(ResponseLimitError::TooManyRepeatedFields {
+                    field,
+                    len,
+                    max_len: self.max_repeated_field_len,
+                });
+            }
+        });
+        result
+    }
+
+    /// Decodes `buf` as an `M` if it fits within these limits.
+    ///
+    /// The size is checked before decoding starts.
+    pub(crate) fn decode<M: prost::Message + Default + RepeatedFields>(
+        &self,
+        buf: &[u8],
+    ) -> Result<M, DecodeError> {
+        self.check_size(buf.len()).map_err(DecodeError::Limit)?;
+        let message = M::decode(buf).map_err(DecodeError::Invalid)?;
+        self.check_repeated_fields(&message)
+            .map_err(DecodeError::Limit)?;
+        Ok(message)
+    }
+}
+
+impl RepeatedFields for WebSocketMessage {
+    fn visit_repeated_fields(&self, visit: &mut dyn FnMut(&'static str, usize)) {
+        let Self {
+            r#type: _,
+            request,
+            response,
+        } = self;
+        if let Some(request) = request {
+            visit("request.headers", request.headers.len());
+        }
+        if let Some(response) = response {
+            visit("response.headers", response.headers.len());
+        }
+    }
+}
+
+impl RepeatedFields for ClientResponse {
+    fn visit_repeated_fields(&self, _visit: &mut dyn FnMut(&'static str, usize)) {
+        // The triples are packed into a single bytes field.
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use prost::Message as _;
+
+    use super::*;
+    use crate::proto::chat_websocket::WebSocketRequestMessage;
+
+    const LIMITS: DecodeLimits = DecodeLimits {
+        max_message_size: 100,
+        max_repeated_field_len: 2,
+    };
+
+    fn request_with_headers(count: usize) -> WebSocketMessage {
+        WebSocketMessage {
+            request: Some(WebSocketRequestMessage {
+                headers: vec!["a:b".to_owned(); count],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_within_limits() {
+        let message = request_with_headers(2);
+        assert_eq!(
+            LIMITS
+                .decode::<WebSocketMessage>(&message.encode_to_vec())
+                .expect("within limits"),
+            message
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_message() {
+        let encoded = WebSocketMessage {
+            request: Some(WebSocketRequestMessage {
+                body: Some(vec![0; LIMITS.max_message_size]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+        .encode_to_vec();
+        assert_matches!(
+            LIMITS.decode::<WebSocketMessage>(&encoded),
+            Err(DecodeError::Limit(ResponseLimitError::ResponseTooLarge { size, max_size: 100 }))
+                if size == encoded.len()
+        );
+    }
+
+    #[test]
+    fn rejects_too_many_repeated_entries() {
+        assert_matches!(
+            LIMITS.decode::<WebSocketMessage>(&request_with_headers(3).encode_to_vec()),
+            Err(DecodeError::Limit(
+                ResponseLimitError::TooManyRepeatedFields {
+                    field: "request.headers",
+                    len: 3,
+                    max_len: 2,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_message() {
+        assert_matches!(
+            LIMITS.decode::<WebSocketMessage>(&[0xff]),
+            Err(DecodeError::Invalid(_))
+        );
+    }
+}
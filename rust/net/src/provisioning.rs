@@ -0,0 +1,605 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Linking a new device to an existing account.
+//!
+//! 1. The new device generates a [`ProvisioningCipher`] and connects to the
+//!    provisioning websocket (see [`provisioning_service`]). The server sends
+//!    it a [`ProvisioningEvent::Address`].
+//! 2. The new device shows [`ProvisioningCipher::provisioning_url`] as a QR
+//!    code, which the primary device scans and parses with
+//!    [`parse_provisioning_url`].
+//! 3. The primary device encrypts the account's [`ProvisioningData`] with
+//!    [`encrypt_provisioning_envelope`] and sends it to the address.
+//! 4. The new device receives a [`ProvisioningEvent::Envelope`] and decrypts it
+//!    with [`ProvisioningCipher::decrypt`].
+
+use std::fmt;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use futures_util::Stream;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac as _};
+use libsignal_core::{Aci, Pni, ServiceId, E164};
+use libsignal_protocol::{IdentityKey, IdentityKeyPair, KeyPair, PrivateKey, PublicKey};
+use prost::Message as _;
+use rand::{CryptoRng, Rng};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt as _;
+use uuid::Uuid;
+
+use crate::chat::server_requests::ResponseEnvelopeSender;
+use crate::chat::ws::{ChatOverWebSocketServiceConnector, ServerEvent as WsServerEvent};
+use crate::chat::{ChatService, ChatServiceError};
+use crate::env::DomainConfig;
+use crate::infra::connection_manager::MultiRouteConnectionManager;
+use crate::infra::service::Service;
+use crate::infra::ws::WebSocketClientConnector;
+use crate::infra::{AsyncDuplexStream, EndpointConnection, TransportConnector};
+use crate::proto::provisioning::{ProvisionEnvelope, ProvisionMessage, ProvisioningAddress};
+use crate::timeouts::MULTI_ROUTE_CONNECTION_TIMEOUT;
+use crate::utils::Redacted;
+
+const PROVISIONING_VERSION: u8 = 1;
+const HKDF_INFO: &[u8] = b"TextSecure Provisioning Message";
+const IV_LEN: usize = 16;
+const MAC_LEN: usize = 32;
+const URL_PREFIX: &str = "sgnl://linkdevice?";
+
+/// Anything that can go wrong while provisioning.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ProvisioningError {
+    /// unsupported provisioning envelope version {0}
+    BadVersion(u8),
+    /// provisioning envelope was malformed
+    InvalidEnvelope,
+    /// provisioning envelope failed authentication
+    BadMac,
+    /// provisioning message was invalid: {0}
+    InvalidMessage(&'static str),
+    /// provisioning URL was invalid
+    InvalidUrl,
+}
+
+/// The account data a primary device sends to a newly-linked device.
+pub struct ProvisioningData {
+    pub aci: Aci,
+    pub pni: Option<Pni>,
+    pub number: E164,
+    pub aci_identity_key_pair: IdentityKeyPair,
+    pub pni_identity_key_pair: Option<IdentityKeyPair>,
+    /// Authorizes the new device to register with the server.
+    pub provisioning_code: String,
+    pub profile_key: Option<[u8; 32]>,
+    pub master_key: Option<[u8; 32]>,
+    pub read_receipts: bool,
+    pub user_agent: Option<String>,
+    pub provisioning_version: Option<u32>,
+}
+
+impl fmt::Debug for ProvisioningData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProvisioningData")
+            .field("aci", &Redacted(&self.aci))
+            .field("pni", &Redacted(&self.pni))
+            .field("number", &Redacted(&self.number))
+            .field("aci_identity_key_pair", &"<redacted>")
+            .field("pni_identity_key_pair", &"<redacted>")
+            .field("provisioning_code", &Redacted(&self.provisioning_code))
+            .field("profile_key", &self.profile_key.map(|_| "<redacted>"))
+            .field("master_key", &self.master_key.map(|_| "<redacted>"))
+            .field("read_receipts", &self.read_receipts)
+            .field("user_agent", &self.user_agent)
+            .field("provisioning_version", &self.provisioning_version)
+            .finish()
+    }
+}
+
+impl From<&ProvisioningData> for ProvisionMessage {
+    fn from(value: &ProvisioningData) -> Self {
+        let ProvisioningData {
+            aci,
+            pni,
+            number,
+            aci_identity_key_pair,
+            pni_identity_key_pair,
+            provisioning_code,
+            profile_key,
+            master_key,
+            read_receipts,
+            user_agent,
+            provisioning_version,
+        } = value;
+        Self {
+            aci_identity_key_public: Some(aci_identity_key_pair.identity_key().serialize().into()),
+            aci_identity_key_private: Some(aci_identity_key_pair.private_key().serialize()),
+            number: Some(number.to_string()),
+            provisioning_code: Some(provisioning_code.clone()),
+            user_agent: user_agent.clone(),
+            profile_key: profile_key.map(Vec::from),
+            read_receipts: Some(*read_receipts),
+            aci: Some(aci.service_id_string()),
+            provisioning_version: *provisioning_version,
+            pni: pni.map(|pni| Uuid::from(pni).to_string()),
+            pni_identity_key_public: pni_identity_key_pair
+                .as_ref()
+                .map(|pair| pair.identity_key().serialize().into()),
+            pni_identity_key_private: pni_identity_key_pair
+                .as_ref()
+                .map(|pair| pair.private_key().serialize()),
+            master_key: master_key.map(Vec::from),
+        }
+    }
+}
+
+impl TryFrom<ProvisionMessage> for ProvisioningData {
+    type Error = ProvisioningError;
+
+    fn try_from(value: ProvisionMessage) -> Result<Self, Self::Error> {
+        use ProvisioningError::InvalidMessage;
+
+        fn key_pair(
+            public: Option<Vec<u8>>,
+            private: Option<Vec<u8>>,
+        ) -> Result<Option<IdentityKeyPair>, ProvisioningError> {
+            let (public, private) = match (public, private) {
+                (None, None) => return Ok(None),
+                (Some(public), Some(private)) => (public, private),
+                _ => return Err(InvalidMessage("incomplete identity key pair")),
+            };
+            let identity_key =
+                IdentityKey::decode(&public).map_err(|_| InvalidMessage("invalid identity key"))?;
+            let private_key = PrivateKey::deserialize(&private)
+                .map_err(|_| InvalidMessage("invalid identity private key"))?;
+            Ok(Some(IdentityKeyPair::new(identity_key, private_key)))
+        }
+
+        fn key_32(
+            bytes: Option<Vec<u8>>,
+            what: &'static str,
+        ) -> Result<Option<[u8; 32]>, ProvisioningError> {
+            bytes
+                .map(|bytes| bytes.try_into().map_err(|_| InvalidMessage(what)))
+                .transpose()
+        }
+
+        let ProvisionMessage {
+            aci_identity_key_public,
+            aci_identity_key_private,
+            number,
+            provisioning_code,
+            user_agent,
+            profile_key,
+            read_receipts,
+            aci,
+            provisioning_version,
+            pni,
+            pni_identity_key_public,
+            pni_identity_key_private,
+            master_key,
+        } = value;
+
+        Ok(Self {
+            aci: ServiceId::parse_as_aci(&aci.ok_or(InvalidMessage("missing ACI"))?)
+                .map_err(|_| InvalidMessage("invalid ACI"))?,
+            pni: pni
+                .map(|pni| ServiceId::parse_as_pni(&pni))
+                .transpose()
+                .map_err(|_| InvalidMessage("invalid PNI"))?,
+            number: number
+                .ok_or(InvalidMessage("missing number"))?
+                .parse()
+                .map_err(|_| InvalidMessage("invalid number"))?,
+            aci_identity_key_pair: key_pair(aci_identity_key_public, aci_identity_key_private)?
+                .ok_or(InvalidMessage("missing ACI identity key pair"))?,
+            pni_identity_key_pair: key_pair(pni_identity_key_public, pni_identity_key_private)?,
+            provisioning_code: provisioning_code
+                .ok_or(InvalidMessage("missing provisioning code"))?,
+            profile_key: key_32(profile_key, "invalid profile key")?,
+            master_key: key_32(master_key, "invalid master key")?,
+            read_receipts: read_receipts.unwrap_or_default(),
+            user_agent,
+            provisioning_version,
+        })
+    }
+}
+
+/// The keys for a single attempt to link a new device.
+pub struct ProvisioningCipher {
+    key_pair: KeyPair,
+}
+
+impl ProvisioningCipher {
+    pub fn new<R: Rng + CryptoRng>(rng: &mut R) -> Self {
+        Self {
+            key_pair: KeyPair::generate(rng),
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.key_pair.public_key
+    }
+
+    /// The URL to show as a QR code for the primary device to scan.
+    ///
+    /// `address` is the one sent by the server in
+    /// [`ProvisioningEvent::Address`].
+    pub fn provisioning_url(&self, address: &str) -> String {
+        let public_key = BASE64_STANDARD.encode(self.public_key().serialize());
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("uuid", address)
+            .append_pair("pub_key", &public_key)
+            .finish();
+        format!("{URL_PREFIX}{query}")
+    }
+
+    /// Decrypts a serialized envelope received in
+    /// [`ProvisioningEvent::Envelope`].
+    pub fn decrypt(&self, envelope: &[u8]) -> Result<ProvisioningData, ProvisioningError> {
+        let ProvisionEnvelope { public_key, body } =
+            ProvisionEnvelope::decode(envelope).map_err(|_| ProvisioningError::InvalidEnvelope)?;
+        let their_public_key = public_key
+            .and_then(|key| PublicKey::deserialize(&key).ok())
+            .ok_or(ProvisioningError::InvalidEnvelope)?;
+        let body = body.ok_or(ProvisioningError::InvalidEnvelope)?;
+
+        let (&version, _) = body
+            .split_first()
+            .ok_or(ProvisioningError::InvalidEnvelope)?;
+        if version != PROVISIONING_VERSION {
+            return Err(ProvisioningError::BadVersion(version));
+        }
+        if body.len() < 1 + IV_LEN + MAC_LEN {
+            return Err(ProvisioningError::InvalidEnvelope);
+        }
+        let (authenticated, their_mac) = body.split_at(body.len() - MAC_LEN);
+        let (iv, ciphertext) = authenticated[1..].split_at(IV_LEN);
+
+        let keys = ProvisioningKeys::derive(&self.key_pair.private_key, &their_public_key)?;
+        keys.mac(authenticated)
+            .verify_slice(their_mac)
+            .map_err(|_| ProvisioningError::BadMac)?;
+        let plaintext = signal_crypto::aes_256_cbc_decrypt(ciphertext, &keys.cipher_key, iv)
+            .map_err(|_| ProvisioningError::InvalidEnvelope)?;
+
+        ProvisionMessage::decode(plaintext.as_slice())
+            .map_err(|_| ProvisioningError::InvalidMessage("not a provisioning message"))?
+            .try_into()
+    }
+}
+
+/// Parses a URL produced by [`ProvisioningCipher::provisioning_url`], returning
+/// the address to send the provisioning message to and the new device's key.
+pub fn parse_provisioning_url(url: &str) -> Result<(String, PublicKey), ProvisioningError> {
+    let query = url
+        .strip_prefix(URL_PREFIX)
+        .ok_or(ProvisioningError::InvalidUrl)?;
+    let mut address = None;
+    let mut public_key = None;
+    for (name, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match &*name {
+            "uuid" => address = Some(value.into_owned()),
+            "pub_key" => public_key = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+    let public_key = BASE64_STANDARD
+        .decode(public_key.ok_or(ProvisioningError::InvalidUrl)?)
+        .ok()
+        .and_then(|key| PublicKey::deserialize(&key).ok())
+        .ok_or(ProvisioningError::InvalidUrl)?;
+    Ok((address.ok_or(ProvisioningError::InvalidUrl)?, public_key))
+}
+
+/// Encrypts `data` for the new device whose key was scanned from its
+/// provisioning URL, returning a serialized envelope.
+pub fn encrypt_provisioning_envelope<R: Rng + CryptoRng>(
+    their_public_key: &PublicKey,
+    data: &ProvisioningData,
+    rng: &mut R,
+) -> Vec<u8> {
+    let ephemeral = KeyPair::generate(rng);
+    let keys = ProvisioningKeys::derive(&ephemeral.private_key, their_public_key)
+        .expect("our keys are valid");
+
+    let iv: [u8; IV_LEN] = rng.gen();
+    let plaintext = ProvisionMessage::from(data).encode_to_vec();
+    let ciphertext = signal_crypto::aes_256_cbc_encrypt(&plaintext, &keys.cipher_key, &iv)
+        .expect("key and IV have the right lengths");
+
+    let mut body = [[PROVISIONING_VERSION].as_slice(), &iv, &ciphertext].concat();
+    let mac = keys.mac(&body).finalize().into_bytes();
+    body.extend_from_slice(&mac);
+
+    ProvisionEnvelope {
+        public_key: Some(ephemeral.public_key.serialize().into()),
+        body: Some(body),
+    }
+    .encode_to_vec()
+}
+
+struct ProvisioningKeys {
+    cipher_key: [u8; 32],
+    mac_key: [u8; 32],
+}
+
+impl ProvisioningKeys {
+    fn derive(ours: &PrivateKey, theirs: &PublicKey) -> Result<Self, ProvisioningError> {
+        let shared_secret = ours
+            .calculate_agreement(theirs)
+            .map_err(|_| ProvisioningError::InvalidEnvelope)?;
+        let mut derived = [0; 64];
+        Hkdf::<Sha256>::new(None, &shared_secret)
+            .expand(HKDF_INFO, &mut derived)
+            .expect("valid output length");
+        let (cipher_key, mac_key) = derived.split_at(32);
+        Ok(Self {
+            cipher_key: cipher_key.try_into().expect("split in half"),
+            mac_key: mac_key.try_into().expect("split in half"),
+        })
+    }
+
+    fn mac(&self, authenticated: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.mac_key).expect("HMAC can take key of any size");
+        mac.update(authenticated);
+        mac
+    }
+}
+
+/// An event on the provisioning websocket.
+pub enum ProvisioningEvent {
+    /// The address the primary device should send the envelope to.
+    Address {
+        address: String,
+        send_ack: ResponseEnvelopeSender,
+    },
+    /// A serialized envelope to decrypt with [`ProvisioningCipher::decrypt`].
+    Envelope {
+        envelope: Vec<u8>,
+        send_ack: ResponseEnvelopeSender,
+    },
+    Stopped(ChatServiceError),
+}
+
+impl fmt::Debug for ProvisioningEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address {
+                address,
+                send_ack: _,
+            } => f
+                .debug_struct("Address")
+                .field("address", &Redacted(address))
+                .finish(),
+            Self::Envelope {
+                envelope,
+                send_ack: _,
+            } => f
+                .debug_struct("Envelope")
+                .field("envelope", &format_args!("{} bytes", envelope.len()))
+                .finish(),
+            Self::Stopped(error) => f.debug_tuple("Stopped").field(error).finish(),
+        }
+    }
+}
+
+/// Creates the endpoint for provisioning websockets on the chat server.
+pub fn provisioning_endpoint_connection(
+    chat_domain_config: &DomainConfig,
+    user_agent: &str,
+    network_change_event: &crate::utils::ObservableEvent,
+) -> EndpointConnection<MultiRouteConnectionManager> {
+    crate::chat::endpoint_connection_for_path(
+        crate::env::constants::PROVISIONING_WEB_SOCKET_PATH,
+        chat_domain_config,
+        user_agent,
+        network_change_event,
+    )
+}
+
+/// Creates an unauthenticated provisioning websocket service.
+///
+/// Requests from the server are delivered to `incoming_tx`; pass the receiving
+/// end to [`stream_provisioning_events`].
+pub fn provisioning_service<T: TransportConnector + 'static>(
+    endpoint: &EndpointConnection<MultiRouteConnectionManager>,
+    transport_connector: T,
+    incoming_tx: mpsc::Sender<WsServerEvent<T::Stream>>,
+) -> impl ChatService {
+    Service::new(
+        ChatOverWebSocketServiceConnector::new(
+            WebSocketClientConnector::new(transport_connector, endpoint.config.clone()),
+            incoming_tx,
+        ),
+        endpoint.manager.clone(),
+        MULTI_ROUTE_CONNECTION_TIMEOUT,
+    )
+}
+
+/// Interprets requests from the server on a provisioning websocket.
+pub fn stream_provisioning_events(
+    receiver: mpsc::Receiver<WsServerEvent<impl AsyncDuplexStream + 'static>>,
+) -> impl Stream<Item = ProvisioningEvent> {
+    ReceiverStream::new(receiver).filter_map(|request| match request {
+        WsServerEvent::Stopped(error) => Some(ProvisioningEvent::Stopped(error)),
+        WsServerEvent::Request {
+            request_proto,
+            response_sender,
+        } => {
+            if request_proto.verb() != http::Method::PUT.as_str() {
+                log::error!(
+                    "provisioning request used unexpected verb {}",
+                    request_proto.verb()
+                );
+                return None;
+            }
+            let send_ack: ResponseEnvelopeSender =
+                Box::new(|status| Box::pin(response_sender.send_response(status)));
+            let body = request_proto.body.as_deref().unwrap_or_default();
+
+            match request_proto.path() {
+                "/v1/address" => match ProvisioningAddress::decode(body) {
+                    Ok(ProvisioningAddress {
+                        address: Some(address),
+                    }) => Some(ProvisioningEvent::Address { address, send_ack }),
+                    _ => {
+                        log::error!("server sent an invalid provisioning address");
+                        None
+                    }
+                },
+                "/v1/message" => Some(ProvisioningEvent::Envelope {
+                    envelope: body.to_vec(),
+                    send_ack,
+                }),
+                unknown_path => {
+                    log::error!("server sent an unknown provisioning request: {unknown_path}");
+                    None
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use futures_util::StreamExt as _;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::chat::RequestProto;
+
+    fn example_data() -> ProvisioningData {
+        let mut rng = OsRng;
+        ProvisioningData {
+            aci: Aci::from(Uuid::from_bytes([0xaa; 16])),
+            pni: Some(Pni::from(Uuid::from_bytes([0xbb; 16]))),
+            number: "+18005550100".parse().unwrap(),
+            aci_identity_key_pair: IdentityKeyPair::generate(&mut rng),
+            pni_identity_key_pair: Some(IdentityKeyPair::generate(&mut rng)),
+            provisioning_code: "123456".to_owned(),
+            profile_key: Some([1; 32]),
+            master_key: Some([2; 32]),
+            read_receipts: true,
+            user_agent: Some("OWI".to_owned()),
+            provisioning_version: Some(1),
+        }
+    }
+
+    #[test]
+    fn url_round_trip() {
+        let cipher = ProvisioningCipher::new(&mut OsRng);
+        let url = cipher.provisioning_url("a+b/c=");
+        assert!(url.starts_with("sgnl://linkdevice?uuid="));
+
+        let (address, public_key) = parse_provisioning_url(&url).expect("valid");
+        assert_eq!(address, "a+b/c=");
+        assert_eq!(&public_key, cipher.public_key());
+
+        assert_matches!(
+            parse_provisioning_url("https://signal.org"),
+            Err(ProvisioningError::InvalidUrl)
+        );
+    }
+
+    #[test]
+    fn envelope_round_trip() {
+        let cipher = ProvisioningCipher::new(&mut OsRng);
+        let data = example_data();
+
+        let envelope = encrypt_provisioning_envelope(cipher.public_key(), &data, &mut OsRng);
+        let decrypted = cipher.decrypt(&envelope).expect("can decrypt");
+
+        assert_eq!(decrypted.aci, data.aci);
+        assert_eq!(decrypted.pni, data.pni);
+        assert_eq!(decrypted.number, data.number);
+        assert_eq!(
+            decrypted.aci_identity_key_pair.serialize(),
+            data.aci_identity_key_pair.serialize()
+        );
+        assert_eq!(
+            decrypted.pni_identity_key_pair.map(|pair| pair.serialize()),
+            data.pni_identity_key_pair.map(|pair| pair.serialize())
+        );
+        assert_eq!(decrypted.provisioning_code, data.provisioning_code);
+        assert_eq!(decrypted.profile_key, data.profile_key);
+        assert_eq!(decrypted.master_key, data.master_key);
+        assert!(decrypted.read_receipts);
+        assert_eq!(decrypted.user_agent, data.user_agent);
+        assert_eq!(decrypted.provisioning_version, data.provisioning_version);
+    }
+
+    #[test]
+    fn envelope_for_someone_else_fails() {
+        let cipher = ProvisioningCipher::new(&mut OsRng);
+        let other = ProvisioningCipher::new(&mut OsRng);
+
+        let envelope =
+            encrypt_provisioning_envelope(other.public_key(), &example_data(), &mut OsRng);
+        assert_matches!(cipher.decrypt(&envelope), Err(ProvisioningError::BadMac));
+    }
+
+    #[test]
+    fn tampered_envelope_fails() {
+        let cipher = ProvisioningCipher::new(&mut OsRng);
+        let envelope =
+            encrypt_provisioning_envelope(cipher.public_key(), &example_data(), &mut OsRng);
+
+        let mut decoded = ProvisionEnvelope::decode(envelope.as_slice()).unwrap();
+        let body = decoded.body.as_mut().unwrap();
+        body[0] = 2;
+        assert_matches!(
+            cipher.decrypt(&decoded.encode_to_vec()),
+            Err(ProvisioningError::BadVersion(2))
+        );
+        body[0] = PROVISIONING_VERSION;
+        body[20] ^= 1;
+        assert_matches!(
+            cipher.decrypt(&decoded.encode_to_vec()),
+            Err(ProvisioningError::BadMac)
+        );
+    }
+
+    #[tokio::test]
+    async fn interprets_provisioning_requests() {
+        let (tx, rx) = mpsc::channel::<WsServerEvent<tokio::io::DuplexStream>>(4);
+        let request = |id, path: &str, body: Vec<u8>| {
+            WsServerEvent::fake(RequestProto {
+                verb: Some("PUT".to_owned()),
+                path: Some(path.to_owned()),
+                body: Some(body),
+                headers: vec![],
+                id: Some(id),
+            })
+        };
+        tx.send(request(
+            1,
+            "/v1/address",
+            ProvisioningAddress {
+                address: Some("address".to_owned()),
+            }
+            .encode_to_vec(),
+        ))
+        .await
+        .unwrap();
+        tx.send(request(2, "/v1/unknown", vec![])).await.unwrap();
+        tx.send(request(3, "/v1/message", vec![1, 2, 3]))
+            .await
+            .unwrap();
+        drop(tx);
+
+        let events: Vec<_> = stream_provisioning_events(rx).collect().await;
+        assert_matches!(
+            events.as_slice(),
+            [
+                ProvisioningEvent::Address { address, .. },
+                ProvisioningEvent::Envelope { envelope, .. },
+            ] if address == "address" && envelope == &[1, 2, 3]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Interpreting push notifications from the chat server.
+//!
+//! Push notifications only wake the app up; they never carry messages. The
+//! chat server doesn't encrypt them, since the only content is the kind of
+//! notification and, for some kinds, an opaque token. On APNs the payload is a
+//! JSON object whose top-level custom key says what kind of notification it
+//! is; a payload with only the `aps` dictionary is a new-message wake-up. On
+//! FCM it is a data message with a single entry, keyed the same way, with
+//! `newMessageAlert` used for new messages.
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::utils::Redacted;
+
+const NEW_MESSAGE_KEY: &str = "newMessageAlert";
+const ATTEMPT_LOGIN_KEY: &str = "attemptLoginContext";
+const CHALLENGE_KEY: &str = "challenge";
+const RATE_LIMIT_CHALLENGE_KEY: &str = "rateLimitChallenge";
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PushPayloadError {
+    /// APNs payload was not a JSON object
+    InvalidJson,
+}
+
+/// What a push notification asks the app to do.
+///
+/// Notifications this client doesn't recognize are treated as
+/// [`NewMessage`](Self::NewMessage), since connecting to fetch messages is
+/// always a safe response to being woken up.
+#[derive(Clone, PartialEq, Eq)]
+pub enum PushNotification {
+    /// There are queued messages; connect to the chat server to fetch them.
+    NewMessage,
+    /// Another device is trying to register with this account's number; the
+    /// context identifies the attempt.
+    AttemptLogin { context: String },
+    /// A token to submit to prove that this device can receive pushes, as
+    /// part of registration or verification.
+    Challenge { token: String },
+    /// A token to submit to lift a rate limit on sending messages.
+    RateLimitChallenge { token: String },
+}
+
+impl fmt::Debug for PushNotification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NewMessage => f.write_str("NewMessage"),
+            Self::AttemptLogin { context } => f
+                .debug_struct("AttemptLogin")
+                .field("context", &Redacted(context))
+                .finish(),
+            Self::Challenge { token } => f
+                .debug_struct("Challenge")
+                .field("token", &Redacted(token))
+                .finish(),
+            Self::RateLimitChallenge { token } => f
+                .debug_struct("RateLimitChallenge")
+                .field("token", &Redacted(token))
+                .finish(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ApnsPayload {
+    #[serde(rename = "attemptLoginContext")]
+    attempt_login_context: Option<String>,
+    challenge: Option<String>,
+    #[serde(rename = "rateLimitChallenge")]
+    rate_limit_challenge: Option<String>,
+}
+
+impl PushNotification {
+    /// Parses the JSON payload of an APNs notification.
+    pub fn from_apns_payload(payload: &[u8]) -> Result<Self, PushPayloadError> {
+        let ApnsPayload {
+            attempt_login_context,
+            challenge,
+            rate_limit_challenge,
+        } = serde_json::from_slice(payload).map_err(|_| PushPayloadError::InvalidJson)?;
+
+        Ok(if let Some(context) = attempt_login_context {
+            Self::AttemptLogin { context }
+        } else if let Some(token) = challenge {
+            Self::Challenge { token }
+        } else if let Some(token) = rate_limit_challenge {
+            Self::RateLimitChallenge { token }
+        } else {
+            Self::NewMessage
+        })
+    }
+
+    /// Interprets the data entries of an FCM message.
+    pub fn from_fcm_data<'a>(data: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        data.into_iter()
+            .find_map(|(key, value)| match key {
+                NEW_MESSAGE_KEY => Some(Self::NewMessage),
+                ATTEMPT_LOGIN_KEY => Some(Self::AttemptLogin {
+                    context: value.to_owned(),
+                }),
+                CHALLENGE_KEY => Some(Self::Challenge {
+                    token: value.to_owned(),
+                }),
+                RATE_LIMIT_CHALLENGE_KEY => Some(Self::RateLimitChallenge {
+                    token: value.to_owned(),
+                }),
+                _ => None,
+            })
+            .unwrap_or(Self::NewMessage)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(r#"{"aps":{"mutable-content":1,"alert":{"loc-key":"APN_Message"}}}"# => PushNotification::NewMessage)]
+    #[test_case(r#"{"aps":{"content-available":1}}"# => PushNotification::NewMessage)]
+    #[test_case(r#"{"aps":{"mutable-content":1},"attemptLoginContext":"ctx"}"# => PushNotification::AttemptLogin { context: "ctx".to_owned() })]
+    #[test_case(r#"{"aps":{"content-available":1},"challenge":"abc"}"# => PushNotification::Challenge { token: "abc".to_owned() })]
+    #[test_case(r#"{"aps":{"content-available":1},"rateLimitChallenge":"def"}"# => PushNotification::RateLimitChallenge { token: "def".to_owned() })]
+    #[test_case(r#"{"aps":{"content-available":1},"somethingNew":1}"# => PushNotification::NewMessage)]
+    fn parses_apns_payload(json: &str) -> PushNotification {
+        PushNotification::from_apns_payload(json.as_bytes()).expect("valid")
+    }
+
+    #[test]
+    fn rejects_bad_apns_payloads() {
+        assert_matches!(
+            PushNotification::from_apns_payload(b"not json"),
+            Err(PushPayloadError::InvalidJson)
+        );
+        assert_matches!(
+            PushNotification::from_apns_payload(br#"{"challenge":5}"#),
+            Err(PushPayloadError::InvalidJson)
+        );
+    }
+
+    #[test_case(&[("newMessageAlert", "")] => PushNotification::NewMessage)]
+    #[test_case(&[("attemptLoginContext", "ctx")] => PushNotification::AttemptLogin { context: "ctx".to_owned() })]
+    #[test_case(&[("challenge", "abc")] => PushNotification::Challenge { token: "abc".to_owned() })]
+    #[test_case(&[("rateLimitChallenge", "def")] => PushNotification::RateLimitChallenge { token: "def".to_owned() })]
+    #[test_case(&[("somethingNew", "x")] => PushNotification::NewMessage)]
+    #[test_case(&[] => PushNotification::NewMessage)]
+    fn parses_fcm_data(data: &[(&str, &str)]) -> PushNotification {
+        PushNotification::from_fcm_data(data.iter().copied())
+    }
+}
@@ -0,0 +1,293 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Batching of outgoing delivery, read, and viewed receipts.
+//!
+//! A receipt can acknowledge any number of messages from the same sender, so
+//! rather than sending one receipt per message as each is processed, a
+//! [`ReceiptBatcher`] holds on to receipts for a short window and combines
+//! those for the same recipient and kind into a single [`ReceiptBatch`].
+//!
+//! The batcher doesn't keep time itself. Callers pass the current time in,
+//! wake up at [`ReceiptBatcher::next_deadline`] to collect the batches that
+//! are due, and send each one with [`crate::messages::send_message`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use libsignal_core::ServiceId;
+use libsignal_protocol::Timestamp;
+use prost::Message as _;
+use tokio::time::Instant;
+
+use crate::messages::OutgoingMessage;
+use crate::proto::receipts::{receipt_message, Content, ReceiptMessage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ReceiptKind {
+    Delivery,
+    Read,
+    Viewed,
+}
+
+/// Receipts of one kind for any number of messages from one sender.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceiptBatch {
+    /// The sender of the acknowledged messages.
+    pub recipient: ServiceId,
+    pub kind: ReceiptKind,
+    /// The timestamps of the acknowledged messages, in ascending order.
+    pub message_timestamps: Vec<Timestamp>,
+}
+
+/// Collects receipts and releases them in batches.
+///
+/// The first receipt queued for a recipient and kind starts a window; when it
+/// ends, everything queued for that recipient and kind in the meantime is
+/// released together. A batch that reaches
+/// [`ReceiptBatcher::MAX_TIMESTAMPS_PER_BATCH`] is released right away.
+#[derive(Debug)]
+pub struct ReceiptBatcher {
+    window: Duration,
+    pending: BTreeMap<(ServiceId, ReceiptKind), PendingBatch>,
+}
+
+#[derive(Debug)]
+struct PendingBatch {
+    deadline: Instant,
+    message_timestamps: BTreeSet<Timestamp>,
+}
+
+impl ReceiptBatch {
+    /// The serialized `Content` carrying this receipt, before padding.
+    pub fn content(&self) -> Vec<u8> {
+        let r#type = match self.kind {
+            ReceiptKind::Delivery => receipt_message::Type::Delivery,
+            ReceiptKind::Read => receipt_message::Type::Read,
+            ReceiptKind::Viewed => receipt_message::Type::Viewed,
+        };
+        Content {
+            receipt_message: Some(ReceiptMessage {
+                r#type: Some(r#type.into()),
+                timestamp: self
+                    .message_timestamps
+                    .iter()
+                    .map(Timestamp::epoch_millis)
+                    .collect(),
+            }),
+        }
+        .encode_to_vec()
+    }
+
+    /// Describes the receipt as a message to send, given its padded
+    /// `contents`.
+    ///
+    /// Receipts aren't urgent; recipients can pick them up the next time
+    /// they're online.
+    pub fn outgoing_message<'a>(
+        &self,
+        contents: &'a [u8],
+        timestamp: Timestamp,
+    ) -> OutgoingMessage<'a> {
+        OutgoingMessage {
+            destination: self.recipient,
+            contents,
+            timestamp,
+            online: false,
+            urgent: false,
+        }
+    }
+}
+
+impl ReceiptBatcher {
+    /// The most messages acknowledged by a single batch, which keeps the
+    /// encrypted receipt a modest size.
+    pub const MAX_TIMESTAMPS_PER_BATCH: usize = 500;
+
+    /// Creates a batcher that holds receipts for up to `window`.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Queues a receipt for the message sent by `recipient` at
+    /// `message_timestamp`.
+    ///
+    /// Returns the batch if this receipt filled it up. Duplicate receipts are
+    /// ignored.
+    pub fn add(
+        &mut self,
+        recipient: ServiceId,
+        kind: ReceiptKind,
+        message_timestamp: Timestamp,
+        now: Instant,
+    ) -> Option<ReceiptBatch> {
+        let key = (recipient, kind);
+        let pending = self.pending.entry(key).or_insert_with(|| PendingBatch {
+            deadline: now + self.window,
+            message_timestamps: BTreeSet::new(),
+        });
+        pending.message_timestamps.insert(message_timestamp);
+        if pending.message_timestamps.len() < Self::MAX_TIMESTAMPS_PER_BATCH {
+            return None;
+        }
+        let pending = self.pending.remove(&key).expect("just inserted");
+        Some(pending.into_batch(key))
+    }
+
+    /// When the earliest pending batch is due, if any receipts are queued.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending.values().map(|pending| pending.deadline).min()
+    }
+
+    /// Removes and returns the batches whose windows have ended by `now`.
+    pub fn take_due(&mut self, now: Instant) -> Vec<ReceiptBatch> {
+        let due = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        due.into_iter()
+            .map(|key| {
+                let pending = self.pending.remove(&key).expect("present");
+                pending.into_batch(key)
+            })
+            .collect()
+    }
+
+    /// Removes and returns every pending batch regardless of its window, as
+    /// when the app is about to go into the background.
+    pub fn flush(&mut self) -> Vec<ReceiptBatch> {
+        std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|(key, pending)| pending.into_batch(key))
+            .collect()
+    }
+}
+
+impl PendingBatch {
+    fn into_batch(self, (recipient, kind): (ServiceId, ReceiptKind)) -> ReceiptBatch {
+        ReceiptBatch {
+            recipient,
+            kind,
+            message_timestamps: self.message_timestamps.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use libsignal_core::Aci;
+
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(2);
+    const ALICE: Aci = Aci::from_uuid_bytes([0xaa; 16]);
+    const BOB: Aci = Aci::from_uuid_bytes([0xbb; 16]);
+
+    fn ts(millis: u64) -> Timestamp {
+        Timestamp::from_epoch_millis(millis)
+    }
+
+    #[test]
+    fn combines_receipts_within_window() {
+        let start = Instant::now();
+        let mut batcher = ReceiptBatcher::new(WINDOW);
+        assert_eq!(batcher.next_deadline(), None);
+
+        for (timestamp, offset) in [(30, 0), (10, 1), (20, 1), (10, 1)] {
+            let now = start + Duration::from_secs(offset);
+            assert_eq!(
+                batcher.add(ALICE.into(), ReceiptKind::Read, ts(timestamp), now),
+                None
+            );
+        }
+        batcher.add(
+            ALICE.into(),
+            ReceiptKind::Delivery,
+            ts(40),
+            start + Duration::from_secs(1),
+        );
+        batcher.add(
+            BOB.into(),
+            ReceiptKind::Read,
+            ts(50),
+            start + Duration::from_secs(1),
+        );
+        assert_eq!(batcher.next_deadline(), Some(start + WINDOW));
+
+        assert!(batcher
+            .take_due(start + WINDOW - Duration::from_millis(1))
+            .is_empty());
+        assert_eq!(
+            batcher.take_due(start + WINDOW),
+            [ReceiptBatch {
+                recipient: ALICE.into(),
+                kind: ReceiptKind::Read,
+                message_timestamps: vec![ts(10), ts(20), ts(30)],
+            }]
+        );
+        assert_eq!(
+            batcher.next_deadline(),
+            Some(start + Duration::from_secs(1) + WINDOW)
+        );
+
+        let rest = batcher.flush();
+        assert_eq!(
+            rest.iter()
+                .map(|batch| (batch.recipient, batch.kind))
+                .collect::<Vec<_>>(),
+            [
+                (ServiceId::from(ALICE), ReceiptKind::Delivery),
+                (ServiceId::from(BOB), ReceiptKind::Read)
+            ]
+        );
+        assert_eq!(batcher.next_deadline(), None);
+    }
+
+    #[test]
+    fn releases_full_batches_immediately() {
+        let now = Instant::now();
+        let mut batcher = ReceiptBatcher::new(WINDOW);
+        for i in 1..ReceiptBatcher::MAX_TIMESTAMPS_PER_BATCH {
+            assert_eq!(
+                batcher.add(ALICE.into(), ReceiptKind::Delivery, ts(i as u64), now),
+                None
+            );
+        }
+        let batch = batcher
+            .add(ALICE.into(), ReceiptKind::Delivery, ts(0), now)
+            .expect("full");
+        assert_eq!(
+            batch.message_timestamps.len(),
+            ReceiptBatcher::MAX_TIMESTAMPS_PER_BATCH
+        );
+        assert_eq!(batcher.next_deadline(), None);
+    }
+
+    #[test]
+    fn encodes_receipt_content() {
+        let batch = ReceiptBatch {
+            recipient: ALICE.into(),
+            kind: ReceiptKind::Viewed,
+            message_timestamps: vec![ts(1), ts(2)],
+        };
+        let content = Content::decode(&batch.content()[..]).expect("valid");
+        assert_eq!(
+            content.receipt_message,
+            Some(ReceiptMessage {
+                r#type: Some(receipt_message::Type::Viewed.into()),
+                timestamp: vec![1, 2],
+            })
+        );
+
+        let message = batch.outgoing_message(b"padded", ts(3));
+        assert_eq!(message.destination, ServiceId::from(ALICE));
+        assert!(!message.urgent);
+    }
+}
@@ -0,0 +1,1025 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for the registration endpoints on the chat server.
+//!
+//! Registering an account starts with a verification session for a phone
+//! number, created with [`RegistrationClient::create_session`]. The server
+//! may ask for a captcha or push challenge before it will send a verification
+//! code; once a code has been submitted and the session is verified,
+//! [`RegistrationClient::register_account`] creates the account. Sessions
+//! expire, and every step is rate-limited, so failures that the app needs to
+//! act on are reported as specific [`RegistrationError`]s.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use bytes::Bytes;
+use hmac::{Hmac, Mac as _};
+use http::response::Parts;
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use libsignal_core::{Aci, Pni, ServiceId, E164};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::Sha256;
+use subtle::ConstantTimeEq as _;
+
+use crate::auth::{HttpBasicAuth, SecretBytes};
+use crate::cdn::{check_status, HttpEndpoint, RequestError, DEFAULT_MAX_RESPONSE_SIZE};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::errors::RetryLater;
+use crate::infra::TransportConnector;
+use crate::utils::{basic_authorization, Redacted};
+
+const SESSION_PATH: &str = "/v1/verification/session";
+const REGISTRATION_PATH: &str = "/v1/registration";
+
+/// Returned by the server when a verification code couldn't be sent.
+const TRANSPORT_FAILED_STATUS: u16 = 418;
+/// Returned by the server when the SMS or voice provider rejected the request.
+const EXTERNAL_SERVICE_FAILED_STATUS: u16 = 440;
+
+/// The server's view of a verification session.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationSession {
+    /// Identifies the session in later requests.
+    pub id: String,
+    /// Seconds until an SMS code may be requested, or `None` if it may not.
+    pub next_sms: Option<u32>,
+    /// Seconds until a voice code may be requested, or `None` if it may not.
+    pub next_call: Option<u32>,
+    /// Seconds until a code may be submitted, or `None` if it may not.
+    pub next_verification_attempt: Option<u32>,
+    pub allowed_to_request_code: bool,
+    /// Challenges that must be completed before a code will be sent.
+    #[serde(default)]
+    pub requested_information: Vec<Challenge>,
+    pub verified: bool,
+}
+
+/// A challenge the server can require before sending a verification code.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Challenge {
+    /// Answered with [`ChallengeResponse::PushChallenge`].
+    PushChallenge,
+    /// Answered with [`ChallengeResponse::Captcha`].
+    Captcha,
+    /// A challenge this client doesn't know about.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A solved [`Challenge`], to be submitted to the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChallengeResponse {
+    /// The token from a completed captcha.
+    Captcha(String),
+    /// The token delivered to the app by push notification.
+    PushChallenge(String),
+}
+
+impl ChallengeResponse {
+    /// The challenge this responds to.
+    pub fn challenge(&self) -> Challenge {
+        match self {
+            ChallengeResponse::Captcha(_) => Challenge::Captcha,
+            ChallengeResponse::PushChallenge(_) => Challenge::PushChallenge,
+        }
+    }
+}
+
+/// How a verification code should be delivered.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum VerificationTransport {
+    Sms,
+    Voice,
+}
+
+/// A push token, used for push challenges and to speed up later verification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PushToken {
+    Fcm(String),
+    Apn(String),
+}
+
+/// The password-based credentials an account is registered with.
+#[derive(Clone)]
+pub struct RegistrationAuth {
+    pub number: E164,
+    pub password: SecretBytes<String>,
+}
+
+impl fmt::Debug for RegistrationAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegistrationAuth")
+            .field("number", &Redacted(&self.number))
+            .field("password", &self.password)
+            .finish()
+    }
+}
+
+/// Credentials for SVR2, returned when an account is registration-locked.
+#[derive(Clone, Deserialize, PartialEq, Eq)]
+pub struct Svr2Credentials {
+    pub username: String,
+    pub password: SecretBytes<String>,
+}
+
+impl fmt::Debug for Svr2Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Svr2Credentials")
+            .field("username", &Redacted(&self.username))
+            .field("password", &self.password)
+            .finish()
+    }
+}
+
+impl HttpBasicAuth for Svr2Credentials {
+    fn username(&self) -> &str {
+        &self.username
+    }
+
+    fn password(&self) -> &str {
+        self.password.expose()
+    }
+}
+
+/// Proof that the client knows an account's master key, which the server
+/// requires before re-registering a registration-locked account.
+///
+/// The token is HMAC-SHA256 of `"Registration Lock"`, keyed with the master
+/// key, and is sent to the server hex-encoded.
+#[derive(Clone, Copy)]
+pub struct RegistrationLockToken([u8; 32]);
+
+impl RegistrationLockToken {
+    const LABEL: &'static [u8] = b"Registration Lock";
+
+    /// Derives the token from the account's master key, such as one restored
+    /// with [`Svr2Connection::restore`](crate::svr2::Svr2Connection::restore).
+    pub fn from_master_key(master_key: &[u8; 32]) -> Self {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(master_key).expect("HMAC can take key of any size");
+        mac.update(Self::LABEL);
+        Self(mac.finalize().into_bytes().into())
+    }
+
+    /// Parses the hex encoding used by the registration endpoints.
+    pub fn from_hex(hex_token: &str) -> Option<Self> {
+        let mut token = [0; 32];
+        hex::decode_to_slice(hex_token, &mut token).ok()?;
+        Some(Self(token))
+    }
+
+    /// The hex encoding used by the registration endpoints.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Checks, in constant time, whether this token was derived from
+    /// `master_key`.
+    pub fn verify(&self, master_key: &[u8; 32]) -> bool {
+        Self::from_master_key(master_key).0.ct_eq(&self.0).into()
+    }
+}
+
+impl From<[u8; 32]> for RegistrationLockToken {
+    fn from(value: [u8; 32]) -> Self {
+        Self(value)
+    }
+}
+
+impl PartialEq for RegistrationLockToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl Eq for RegistrationLockToken {}
+
+impl fmt::Debug for RegistrationLockToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RegistrationLockToken")
+            .field(&Redacted(self.to_hex()))
+            .finish()
+    }
+}
+
+/// The request to create an account after its session has been verified.
+#[derive(Clone, Default)]
+pub struct RegisterAccountRequest {
+    pub session_id: String,
+    /// Register even if an existing device could transfer its data instead.
+    pub skip_device_transfer: bool,
+    /// The registration lock token derived from the master key recovered
+    /// from SVR, if the account has a registration lock.
+    pub registration_lock: Option<RegistrationLockToken>,
+    /// The account attributes, not including the registration lock.
+    pub account_attributes: serde_json::Map<String, serde_json::Value>,
+    /// The identity keys and pre-keys for the account, as top-level fields
+    /// of the request.
+    pub keys: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The newly-registered account.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterAccountResponse {
+    #[serde(rename = "uuid")]
+    pub aci: Aci,
+    #[serde(deserialize_with = "deserialize_pni")]
+    pub pni: Pni,
+    pub number: E164,
+    #[serde(default)]
+    pub storage_capable: bool,
+    /// Whether this replaced an existing account for the same number.
+    #[serde(default)]
+    pub reregistration: bool,
+}
+
+/// Anything that can go wrong while registering.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum RegistrationError {
+    /// request failed: {0}
+    Request(#[from] RequestError),
+    /// the session does not exist or has expired
+    SessionNotFound,
+    /// the request was rejected as invalid
+    InvalidRequest,
+    /// rate limited; retry after {retry_after:?}
+    RateLimited {
+        retry_after: Option<Duration>,
+        /// The current state of the session, if the server sent it.
+        session: Option<Box<RegistrationSession>>,
+    },
+    /// a challenge must be completed first
+    ChallengeRequired(Box<RegistrationSession>),
+    /// the request is not allowed in the session's current state
+    InvalidSessionState,
+    /// the verification code could not be sent
+    CodeDeliveryFailed {
+        /// If true, retrying with the same transport will not help.
+        permanent: bool,
+        reason: Option<String>,
+    },
+    /// the session has not been verified
+    NotVerified,
+    /// an existing device can transfer its data; set `skip_device_transfer` to register anyway
+    DeviceTransferPossible,
+    /// the account is registration-locked for another {time_remaining:?}
+    RegistrationLocked {
+        time_remaining: Duration,
+        /// Credentials for recovering the registration lock token from SVR2.
+        svr2_credentials: Option<Svr2Credentials>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateSession<'a> {
+    number: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    push_token: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    push_token_type: Option<&'static str>,
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateSession<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    captcha: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    push_challenge: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct RequestCode<'a> {
+    transport: VerificationTransport,
+    client: &'a str,
+}
+
+#[derive(Serialize)]
+struct SubmitCode<'a> {
+    code: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisterAccount<'a> {
+    session_id: &'a str,
+    skip_device_transfer: bool,
+    account_attributes: serde_json::Map<String, serde_json::Value>,
+    #[serde(flatten)]
+    keys: &'a serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CodeDeliveryFailure {
+    #[serde(default)]
+    permanent_failure: bool,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistrationLockFailure {
+    /// In milliseconds.
+    time_remaining: u64,
+    svr2_credentials: Option<Svr2Credentials>,
+}
+
+impl PushToken {
+    fn parts(&self) -> (&str, &'static str) {
+        match self {
+            PushToken::Fcm(token) => (token, "fcm"),
+            PushToken::Apn(token) => (token, "apn"),
+        }
+    }
+}
+
+impl<'a> From<&'a ChallengeResponse> for UpdateSession<'a> {
+    fn from(value: &'a ChallengeResponse) -> Self {
+        match value {
+            ChallengeResponse::Captcha(token) => Self {
+                captcha: Some(token),
+                ..Default::default()
+            },
+            ChallengeResponse::PushChallenge(token) => Self {
+                push_challenge: Some(token),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a RegisterAccountRequest> for RegisterAccount<'a> {
+    fn from(value: &'a RegisterAccountRequest) -> Self {
+        let RegisterAccountRequest {
+            session_id,
+            skip_device_transfer,
+            registration_lock,
+            account_attributes,
+            keys,
+        } = value;
+        let mut account_attributes = account_attributes.clone();
+        if let Some(registration_lock) = registration_lock {
+            account_attributes.insert(
+                "registrationLock".to_owned(),
+                registration_lock.to_hex().into(),
+            );
+        }
+        Self {
+            session_id,
+            skip_device_transfer: *skip_device_transfer,
+            account_attributes,
+            keys,
+        }
+    }
+}
+
+/// PNIs are sent as bare UUIDs in registration responses.
+fn deserialize_pni<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pni, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    ServiceId::parse_as_pni(&s).map_err(serde::de::Error::custom)
+}
+
+fn retry_after(parts: &Parts) -> Option<Duration> {
+    RetryLater::from_response(parts.status, &parts.headers)
+        .map(|retry_later| retry_later.retry_after)
+}
+
+/// The fallback for statuses without a registration-specific meaning.
+fn generic_error(parts: &Parts) -> RegistrationError {
+    check_status(parts)
+        .err()
+        .unwrap_or(RequestError::UnexpectedStatus(parts.status))
+        .into()
+}
+
+/// Maps failures from the verification session endpoints.
+fn session_error(parts: &Parts, body: &[u8]) -> RegistrationError {
+    let session = || {
+        serde_json::from_slice::<RegistrationSession>(body)
+            .ok()
+            .map(Box::new)
+    };
+    match parts.status {
+        StatusCode::NOT_FOUND => RegistrationError::SessionNotFound,
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+            RegistrationError::InvalidRequest
+        }
+        StatusCode::CONFLICT => match session() {
+            Some(session) if !session.requested_information.is_empty() => {
+                RegistrationError::ChallengeRequired(session)
+            }
+            _ => RegistrationError::InvalidSessionState,
+        },
+        StatusCode::TOO_MANY_REQUESTS => RegistrationError::RateLimited {
+            retry_after: retry_after(parts),
+            session: session(),
+        },
+        status
+            if status.as_u16() == TRANSPORT_FAILED_STATUS
+                || status.as_u16() == EXTERNAL_SERVICE_FAILED_STATUS =>
+        {
+            let failure = serde_json::from_slice::<CodeDeliveryFailure>(body).ok();
+            RegistrationError::CodeDeliveryFailed {
+                permanent: failure.as_ref().is_some_and(|f| f.permanent_failure),
+                reason: failure.and_then(|f| f.reason),
+            }
+        }
+        _ => generic_error(parts),
+    }
+}
+
+/// Maps failures from the account registration endpoint.
+fn register_error(parts: &Parts, body: &[u8]) -> RegistrationError {
+    match parts.status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+            RegistrationError::InvalidRequest
+        }
+        StatusCode::FORBIDDEN => RegistrationError::NotVerified,
+        StatusCode::CONFLICT => RegistrationError::DeviceTransferPossible,
+        StatusCode::LOCKED => match serde_json::from_slice::<RegistrationLockFailure>(body) {
+            Ok(failure) => RegistrationError::RegistrationLocked {
+                time_remaining: Duration::from_millis(failure.time_remaining),
+                svr2_credentials: failure.svr2_credentials,
+            },
+            Err(_) => RequestError::InvalidResponse.into(),
+        },
+        StatusCode::TOO_MANY_REQUESTS => RegistrationError::RateLimited {
+            retry_after: retry_after(parts),
+            session: None,
+        },
+        _ => generic_error(parts),
+    }
+}
+
+/// Client for the registration endpoints on the chat server.
+pub struct RegistrationClient<C, T> {
+    endpoint: HttpEndpoint<C, T>,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> RegistrationClient<C, T> {
+    pub fn new(connection_manager: C, transport_connector: T) -> Self {
+        Self {
+            endpoint: HttpEndpoint::new(
+                connection_manager,
+                transport_connector,
+                DEFAULT_MAX_RESPONSE_SIZE,
+            ),
+        }
+    }
+
+    /// Starts a new verification session for `number`.
+    pub async fn create_session(
+        &self,
+        number: E164,
+        push_token: Option<&PushToken>,
+    ) -> Result<RegistrationSession, RegistrationError> {
+        let (push_token, push_token_type) = push_token.map(PushToken::parts).unzip();
+        let body = CreateSession {
+            number: number.to_string(),
+            push_token,
+            push_token_type,
+        };
+        self.send_json(
+            Method::POST,
+            PathAndQuery::from_static(SESSION_PATH),
+            HeaderMap::new(),
+            Some(&body),
+            session_error,
+        )
+        .await
+    }
+
+    /// Fetches the current state of an existing session.
+    pub async fn resume_session(
+        &self,
+        session_id: &str,
+    ) -> Result<RegistrationSession, RegistrationError> {
+        self.send_json(
+            Method::GET,
+            session_path(session_id, "")?,
+            HeaderMap::new(),
+            None::<&()>,
+            session_error,
+        )
+        .await
+    }
+
+    /// Submits a solved challenge.
+    pub async fn submit_challenge(
+        &self,
+        session_id: &str,
+        response: &ChallengeResponse,
+    ) -> Result<RegistrationSession, RegistrationError> {
+        self.send_json(
+            Method::PATCH,
+            session_path(session_id, "")?,
+            HeaderMap::new(),
+            Some(&UpdateSession::from(response)),
+            session_error,
+        )
+        .await
+    }
+
+    /// Asks the server to send a verification code.
+    ///
+    /// `client` identifies the kind of app, so that the message can include
+    /// an app-specific hash (e.g. `"android-2021-03"`). If `language` is
+    /// provided, it is sent as the `Accept-Language` for the message.
+    pub async fn request_verification_code(
+        &self,
+        session_id: &str,
+        transport: VerificationTransport,
+        client: &str,
+        language: Option<&str>,
+    ) -> Result<RegistrationSession, RegistrationError> {
+        let mut headers = HeaderMap::new();
+        if let Some(language) = language {
+            let language =
+                HeaderValue::from_str(language).map_err(|_| RegistrationError::InvalidRequest)?;
+            headers.insert(http::header::ACCEPT_LANGUAGE, language);
+        }
+        self.send_json(
+            Method::POST,
+            session_path(session_id, "/code")?,
+            headers,
+            Some(&RequestCode { transport, client }),
+            session_error,
+        )
+        .await
+    }
+
+    /// Submits the verification code the user received.
+    ///
+    /// An incorrect code is not an error; check
+    /// [`RegistrationSession::verified`] in the result.
+    pub async fn submit_verification_code(
+        &self,
+        session_id: &str,
+        code: &str,
+    ) -> Result<RegistrationSession, RegistrationError> {
+        self.send_json(
+            Method::PUT,
+            session_path(session_id, "/code")?,
+            HeaderMap::new(),
+            Some(&SubmitCode { code }),
+            session_error,
+        )
+        .await
+    }
+
+    /// Creates the account for a verified session.
+    ///
+    /// If the account has a registration lock, this fails with
+    /// [`RegistrationError::RegistrationLocked`] until the request includes
+    /// the token recovered from SVR2.
+    pub async fn register_account(
+        &self,
+        auth: &RegistrationAuth,
+        request: &RegisterAccountRequest,
+    ) -> Result<RegisterAccountResponse, RegistrationError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            http::header::AUTHORIZATION,
+            basic_authorization(&auth.number.to_string(), auth.password.expose()),
+        );
+        self.send_json(
+            Method::POST,
+            PathAndQuery::from_static(REGISTRATION_PATH),
+            headers,
+            Some(&RegisterAccount::from(request)),
+            register_error,
+        )
+        .await
+    }
+
+    async fn send_json<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path_and_query: PathAndQuery,
+        mut headers: HeaderMap,
+        body: Option<&impl Serialize>,
+        map_error: fn(&Parts, &[u8]) -> RegistrationError,
+    ) -> Result<R, RegistrationError> {
+        let body = match body {
+            Some(body) => {
+                headers.insert(
+                    http::header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+                Bytes::from(serde_json::to_vec(body).expect("can serialize"))
+            }
+            None => Bytes::new(),
+        };
+        let (parts, body) = self
+            .endpoint
+            .send_unchecked(method, path_and_query, headers, body)
+            .await?;
+        if !parts.status.is_success() {
+            return Err(map_error(&parts, &body));
+        }
+        serde_json::from_slice(&body).map_err(|_| RequestError::InvalidResponse.into())
+    }
+}
+
+impl RegistrationError {
+    /// The state of the session sent along with this error, if any.
+    pub fn session(&self) -> Option<&RegistrationSession> {
+        match self {
+            RegistrationError::ChallengeRequired(session)
+            | RegistrationError::RateLimited {
+                session: Some(session),
+                ..
+            } => Some(session),
+            _ => None,
+        }
+    }
+}
+
+/// A verification session that is kept up to date with every response.
+///
+/// Failures often come with the new state of the session (for example, the
+/// challenges that are now required), so that state is stored before the
+/// error is returned.
+pub struct VerificationSession<'a, C, T> {
+    client: &'a RegistrationClient<C, T>,
+    state: RegistrationSession,
+}
+
+impl<'a, C: ConnectionManager, T: TransportConnector> VerificationSession<'a, C, T> {
+    /// Starts a new session for `number`.
+    pub async fn create(
+        client: &'a RegistrationClient<C, T>,
+        number: E164,
+        push_token: Option<&PushToken>,
+    ) -> Result<Self, RegistrationError> {
+        let state = client.create_session(number, push_token).await?;
+        Ok(Self { client, state })
+    }
+
+    /// Picks up an existing session, e.g. after the app was restarted.
+    pub async fn resume(
+        client: &'a RegistrationClient<C, T>,
+        session_id: &str,
+    ) -> Result<Self, RegistrationError> {
+        let state = client.resume_session(session_id).await?;
+        Ok(Self { client, state })
+    }
+
+    /// The last state of the session reported by the server.
+    pub fn state(&self) -> &RegistrationSession {
+        &self.state
+    }
+
+    /// The challenges that must be answered before a code will be sent.
+    pub fn required_challenges(&self) -> &[Challenge] {
+        &self.state.requested_information
+    }
+
+    /// Fetches the latest state of the session.
+    pub async fn refresh(&mut self) -> Result<(), RegistrationError> {
+        let result = self.client.resume_session(&self.state.id).await;
+        update_state(&mut self.state, result)
+    }
+
+    /// Submits a solved challenge.
+    pub async fn submit_challenge(
+        &mut self,
+        response: &ChallengeResponse,
+    ) -> Result<(), RegistrationError> {
+        let result = self.client.submit_challenge(&self.state.id, response).await;
+        update_state(&mut self.state, result)
+    }
+
+    /// Asks the server to send a verification code; see
+    /// [`RegistrationClient::request_verification_code`].
+    ///
+    /// If a challenge is required first, this fails with
+    /// [`RegistrationError::ChallengeRequired`], and
+    /// [`Self::required_challenges`] lists the options.
+    pub async fn request_verification_code(
+        &mut self,
+        transport: VerificationTransport,
+        client: &str,
+        language: Option<&str>,
+    ) -> Result<(), RegistrationError> {
+        let result = self
+            .client
+            .request_verification_code(&self.state.id, transport, client, language)
+            .await;
+        update_state(&mut self.state, result)
+    }
+
+    /// Submits the verification code the user received, returning whether
+    /// the session is now verified.
+    pub async fn submit_verification_code(
+        &mut self,
+        code: &str,
+    ) -> Result<bool, RegistrationError> {
+        let result = self
+            .client
+            .submit_verification_code(&self.state.id, code)
+            .await;
+        update_state(&mut self.state, result)?;
+        Ok(self.state.verified)
+    }
+}
+
+/// Stores the session state from a response, whether it succeeded or not.
+fn update_state(
+    state: &mut RegistrationSession,
+    result: Result<RegistrationSession, RegistrationError>,
+) -> Result<(), RegistrationError> {
+    match result {
+        Ok(new_state) => {
+            *state = new_state;
+            Ok(())
+        }
+        Err(e) => {
+            if let Some(new_state) = e.session() {
+                *state = new_state.clone();
+            }
+            Err(e)
+        }
+    }
+}
+
+fn session_path(session_id: &str, suffix: &str) -> Result<PathAndQuery, RegistrationError> {
+    // Session IDs are URL-safe base64, so anything else can't be a real session.
+    if session_id.is_empty()
+        || !session_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'=')
+    {
+        return Err(RegistrationError::SessionNotFound);
+    }
+    Ok(
+        PathAndQuery::from_str(&format!("{SESSION_PATH}/{session_id}{suffix}"))
+            .expect("validated characters"),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use hex_literal::hex;
+
+    use super::*;
+
+    const SESSION_JSON: &str = r#"{
+        "id": "c2Vzc2lvbg",
+        "nextSms": 60,
+        "nextCall": null,
+        "nextVerificationAttempt": null,
+        "allowedToRequestCode": false,
+        "requestedInformation": ["pushChallenge", "somethingNew"],
+        "verified": false
+    }"#;
+
+    fn response_parts(status: u16, headers: &[(&str, &str)]) -> Parts {
+        let mut builder = http::Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).expect("valid").into_parts().0
+    }
+
+    #[test]
+    fn parses_session() {
+        let session: RegistrationSession = serde_json::from_str(SESSION_JSON).expect("valid JSON");
+        assert_eq!(
+            session,
+            RegistrationSession {
+                id: "c2Vzc2lvbg".to_owned(),
+                next_sms: Some(60),
+                next_call: None,
+                next_verification_attempt: None,
+                allowed_to_request_code: false,
+                requested_information: vec![Challenge::PushChallenge, Challenge::Unknown],
+                verified: false,
+            }
+        );
+    }
+
+    #[test]
+    fn session_errors() {
+        assert_matches!(
+            session_error(&response_parts(404, &[]), b""),
+            RegistrationError::SessionNotFound
+        );
+        assert_matches!(
+            session_error(&response_parts(409, &[]), SESSION_JSON.as_bytes()),
+            RegistrationError::ChallengeRequired(session) if session.id == "c2Vzc2lvbg"
+        );
+        assert_matches!(
+            session_error(&response_parts(409, &[]), b""),
+            RegistrationError::InvalidSessionState
+        );
+        assert_matches!(
+            session_error(
+                &response_parts(429, &[("retry-after", "30")]),
+                SESSION_JSON.as_bytes()
+            ),
+            RegistrationError::RateLimited {
+                retry_after: Some(retry_after),
+                session: Some(_),
+            } if retry_after == Duration::from_secs(30)
+        );
+        assert_matches!(
+            session_error(
+                &response_parts(440, &[]),
+                br#"{"reason": "providerRejected", "permanentFailure": true}"#
+            ),
+            RegistrationError::CodeDeliveryFailed {
+                permanent: true,
+                reason: Some(reason),
+            } if reason == "providerRejected"
+        );
+        assert_matches!(
+            session_error(&response_parts(503, &[]), b""),
+            RegistrationError::Request(RequestError::UnexpectedStatus(
+                StatusCode::SERVICE_UNAVAILABLE
+            ))
+        );
+    }
+
+    #[test]
+    fn registration_lock_error() {
+        assert_matches!(
+            register_error(
+                &response_parts(423, &[]),
+                br#"{"timeRemaining": 86400000, "svr2Credentials": {"username": "u", "password": "p"}}"#
+            ),
+            RegistrationError::RegistrationLocked {
+                time_remaining,
+                svr2_credentials: Some(Svr2Credentials { username, .. }),
+            } if time_remaining == Duration::from_secs(86400) && username == "u"
+        );
+        assert_matches!(
+            register_error(&response_parts(423, &[]), b"{}"),
+            RegistrationError::Request(RequestError::InvalidResponse)
+        );
+    }
+
+    #[test]
+    fn registration_lock_token_vectors() {
+        let master_key: [u8; 32] = std::array::from_fn(|i| i as u8);
+        let token = RegistrationLockToken::from_master_key(&master_key);
+        assert_eq!(
+            token.as_bytes(),
+            &hex!("0c5f865eea5eebe9ef4fa8c4d99cb91de301bebb5b3b91bf775da1a57c725975")
+        );
+
+        let token = RegistrationLockToken::from_master_key(&[0x42; 32]);
+        assert_eq!(
+            token.to_hex(),
+            "45b43bb819964ad8ba1c7bcb42a3175eeaf7dd8d2f95728f811517c20dfe72e0"
+        );
+    }
+
+    #[test]
+    fn registration_lock_token_round_trip_and_verify() {
+        let master_key = [0x42; 32];
+        let token = RegistrationLockToken::from_master_key(&master_key);
+
+        assert_eq!(
+            RegistrationLockToken::from_hex(&token.to_hex()),
+            Some(token)
+        );
+        assert_eq!(RegistrationLockToken::from_hex("not hex"), None);
+        assert_eq!(RegistrationLockToken::from_hex("abcd"), None);
+
+        assert!(token.verify(&master_key));
+        assert!(!token.verify(&[0x43; 32]));
+    }
+
+    #[test]
+    fn register_request_includes_registration_lock() {
+        let request = RegisterAccountRequest {
+            session_id: "c2Vzc2lvbg".to_owned(),
+            registration_lock: Some([0xaa; 32].into()),
+            account_attributes: serde_json::from_str(r#"{"fetchesMessages": true}"#)
+                .expect("valid JSON"),
+            keys: serde_json::from_str(r#"{"aciIdentityKey": "BQ"}"#).expect("valid JSON"),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(RegisterAccount::from(&request)).expect("can serialize");
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "sessionId": "c2Vzc2lvbg",
+                "skipDeviceTransfer": false,
+                "accountAttributes": {
+                    "fetchesMessages": true,
+                    "registrationLock": hex::encode([0xaa; 32]),
+                },
+                "aciIdentityKey": "BQ",
+            })
+        );
+    }
+
+    #[test]
+    fn parses_register_response() {
+        let response: RegisterAccountResponse = serde_json::from_str(
+            r#"{
+                "uuid": "8c78cd2a-16ff-427d-83dc-1a5e36ce713d",
+                "pni": "99d5a8c6-2b4f-4a6e-9d0b-52c5a3e2b5a1",
+                "number": "+18005550100",
+                "storageCapable": true
+            }"#,
+        )
+        .expect("valid JSON");
+        assert_eq!(
+            response.aci,
+            Aci::from(uuid::uuid!("8c78cd2a-16ff-427d-83dc-1a5e36ce713d"))
+        );
+        assert_eq!(
+            response.pni,
+            Pni::from(uuid::uuid!("99d5a8c6-2b4f-4a6e-9d0b-52c5a3e2b5a1"))
+        );
+        assert_eq!(response.number.to_string(), "+18005550100");
+        assert!(!response.reregistration);
+    }
+
+    #[test]
+    fn challenge_responses() {
+        let update = |response: ChallengeResponse| {
+            serde_json::to_value(UpdateSession::from(&response)).expect("can serialize")
+        };
+        assert_eq!(
+            update(ChallengeResponse::Captcha("solved".to_owned())),
+            serde_json::json!({"captcha": "solved"})
+        );
+        assert_eq!(
+            update(ChallengeResponse::PushChallenge("pushed".to_owned())),
+            serde_json::json!({"pushChallenge": "pushed"})
+        );
+        assert_eq!(
+            ChallengeResponse::PushChallenge("pushed".to_owned()).challenge(),
+            Challenge::PushChallenge
+        );
+    }
+
+    #[test]
+    fn errors_update_session_state() {
+        let mut state: RegistrationSession =
+            serde_json::from_str(SESSION_JSON).expect("valid JSON");
+        state.requested_information.clear();
+
+        let challenged = session_error(&response_parts(409, &[]), SESSION_JSON.as_bytes());
+        assert_matches!(
+            update_state(&mut state, Err(challenged)),
+            Err(RegistrationError::ChallengeRequired(_))
+        );
+        assert_eq!(
+            state.requested_information,
+            [Challenge::PushChallenge, Challenge::Unknown]
+        );
+
+        let verified = RegistrationSession {
+            verified: true,
+            requested_information: vec![],
+            ..state.clone()
+        };
+        assert_matches!(update_state(&mut state, Ok(verified.clone())), Ok(()));
+        assert_eq!(state, verified);
+
+        assert_matches!(
+            update_state(&mut state, Err(RegistrationError::SessionNotFound)),
+            Err(RegistrationError::SessionNotFound)
+        );
+        assert_eq!(state, verified);
+    }
+
+    #[test]
+    fn rejects_malformed_session_ids() {
+        assert_matches!(
+            session_path("c2Vzc2lvbg", "/code"),
+            Ok(path) if path == "/v1/verification/session/c2Vzc2lvbg/code"
+        );
+        assert_matches!(
+            session_path("", ""),
+            Err(RegistrationError::SessionNotFound)
+        );
+        assert_matches!(
+            session_path("../../v2/keys", ""),
+            Err(RegistrationError::SessionNotFound)
+        );
+    }
+}
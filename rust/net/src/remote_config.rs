@@ -0,0 +1,301 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Fetching remote config flags from the chat server.
+//!
+//! The server publishes a set of named flags, each of which is enabled or
+//! disabled and may carry a string value. [`RemoteConfigStore`] keeps the most
+//! recently fetched set, uses the server's ETag to avoid downloading it again
+//! when nothing has changed, and notifies subscribers when it does change, so
+//! that values like enclave IDs can be picked up without a new release.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use serde::Deserialize;
+
+use crate::chat::{ChatService, ChatServiceError, Request};
+use crate::utils::{EventSubscription, ObservableEvent};
+
+const CONFIG_PATH: &str = "/v1/config";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum Error {
+    /// chat service error: {0}
+    ChatService(#[from] ChatServiceError),
+    /// request failed with status {0}
+    RequestFailed(StatusCode),
+    /// invalid response received from the server
+    InvalidResponse,
+}
+
+/// A single remote config flag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteConfigEntry {
+    pub enabled: bool,
+    pub value: Option<String>,
+}
+
+/// A snapshot of the server's remote config flags.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RemoteConfig {
+    entries: HashMap<String, RemoteConfigEntry>,
+}
+
+#[derive(Deserialize)]
+struct ConfigResponse {
+    config: Vec<ConfigResponseEntry>,
+}
+
+#[derive(Deserialize)]
+struct ConfigResponseEntry {
+    name: String,
+    enabled: bool,
+    value: Option<String>,
+}
+
+impl RemoteConfig {
+    pub fn get(&self, name: &str) -> Option<&RemoteConfigEntry> {
+        self.entries.get(name)
+    }
+
+    /// Whether the flag `name` is present and enabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|entry| entry.enabled)
+    }
+
+    /// The value of `name`, if the flag is enabled and has one.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.get(name)
+            .filter(|entry| entry.enabled)
+            .and_then(|entry| entry.value.as_deref())
+    }
+
+    /// The value of `name` parsed as a `T`.
+    ///
+    /// A value that fails to parse is logged and treated as absent, so that a
+    /// bad flag on the server can't break the client.
+    pub fn parse<T: FromStr>(&self, name: &str) -> Option<T> {
+        let value = self.value(name)?;
+        let parsed = value.parse().ok();
+        if parsed.is_none() {
+            log::warn!("remote config value for {name} could not be parsed");
+        }
+        parsed
+    }
+
+    fn from_json(body: &[u8]) -> Result<Self, Error> {
+        let ConfigResponse { config } =
+            serde_json::from_slice(body).map_err(|_| Error::InvalidResponse)?;
+        let entries = config
+            .into_iter()
+            .map(
+                |ConfigResponseEntry {
+                     name,
+                     enabled,
+                     value,
+                 }| (name, RemoteConfigEntry { enabled, value }),
+            )
+            .collect();
+        Ok(Self { entries })
+    }
+}
+
+/// Keeps the latest [`RemoteConfig`] fetched from the server.
+#[derive(Default)]
+pub struct RemoteConfigStore {
+    state: Mutex<Option<CachedConfig>>,
+    changed: ObservableEvent,
+}
+
+struct CachedConfig {
+    etag: Option<HeaderValue>,
+    config: Arc<RemoteConfig>,
+}
+
+impl RemoteConfigStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The most recently fetched config, if any fetch has succeeded.
+    pub fn current(&self) -> Option<Arc<RemoteConfig>> {
+        self.state
+            .lock()
+            .expect("not poisoned")
+            .as_ref()
+            .map(|cached| Arc::clone(&cached.config))
+    }
+
+    /// Registers `callback` to run whenever a fetch produces a different
+    /// config.
+    ///
+    /// The callback runs synchronously during [`Self::refresh`]; it should use
+    /// [`Self::current`] to get the new values.
+    pub fn subscribe(&self, callback: Box<dyn FnMut() + Send>) -> EventSubscription {
+        self.changed.subscribe(callback)
+    }
+
+    /// Fetches the config over `chat`, which must be authenticated.
+    ///
+    /// Returns whether the config changed. If the server reports that the
+    /// cached copy is still current, nothing is downloaded.
+    pub async fn refresh(&self, chat: &(dyn ChatService + Send + Sync)) -> Result<bool, Error> {
+        let etag = self
+            .state
+            .lock()
+            .expect("not poisoned")
+            .as_ref()
+            .and_then(|cached| cached.etag.clone());
+
+        let response = chat.send(config_request(etag), REQUEST_TIMEOUT).await?;
+        let config = match response.status {
+            StatusCode::NOT_MODIFIED => return Ok(false),
+            status if status.is_success() => {
+                RemoteConfig::from_json(&response.body.unwrap_or_default())?
+            }
+            status => return Err(Error::RequestFailed(status)),
+        };
+        let etag = response.headers.get(http::header::ETAG).cloned();
+
+        let changed = {
+            let mut guard = self.state.lock().expect("not poisoned");
+            let changed = guard
+                .as_ref()
+                .map_or(true, |cached| *cached.config != config);
+            *guard = Some(CachedConfig {
+                etag,
+                config: Arc::new(config),
+            });
+            changed
+        };
+        if changed {
+            self.changed.fire();
+        }
+        Ok(changed)
+    }
+}
+
+fn config_request(etag: Option<HeaderValue>) -> Request {
+    let mut headers = HeaderMap::new();
+    if let Some(etag) = etag {
+        headers.insert(http::header::IF_NONE_MATCH, etag);
+    }
+    Request {
+        method: Method::GET,
+        body: None,
+        headers,
+        path: PathAndQuery::from_static(CONFIG_PATH),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::chat::testutil::FakeChat;
+    use crate::chat::Response;
+
+    fn response(status: StatusCode, etag: &'static str, body: &str) -> Response {
+        Response {
+            status,
+            message: None,
+            body: Some(body.as_bytes().into()),
+            headers: HeaderMap::from_iter([(http::header::ETAG, HeaderValue::from_static(etag))]),
+        }
+    }
+
+    const CONFIG_JSON: &str = r#"{"config":[
+        {"name":"global.cdsi.enclave","enabled":true,"value":"0f6db3"},
+        {"name":"global.retries","enabled":true,"value":"three"},
+        {"name":"global.disabled","enabled":false,"value":"5"},
+        {"name":"global.flag","enabled":true}
+    ]}"#;
+
+    #[test]
+    fn typed_values() {
+        let config = RemoteConfig::from_json(CONFIG_JSON.as_bytes()).expect("valid");
+        assert!(config.is_enabled("global.flag"));
+        assert!(!config.is_enabled("global.disabled"));
+        assert!(!config.is_enabled("global.missing"));
+        assert_eq!(config.value("global.cdsi.enclave"), Some("0f6db3"));
+        assert_eq!(config.value("global.flag"), None);
+        assert_eq!(config.parse::<u32>("global.disabled"), None);
+        assert_eq!(config.parse::<u32>("global.retries"), None);
+        assert_eq!(
+            config.parse::<String>("global.cdsi.enclave").as_deref(),
+            Some("0f6db3")
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_uses_etag_and_notifies_on_change() {
+        let chat = FakeChat::responding_in_order([
+            response(StatusCode::OK, "\"v1\"", CONFIG_JSON),
+            response(StatusCode::NOT_MODIFIED, "\"v1\"", ""),
+            response(StatusCode::OK, "\"v2\"", CONFIG_JSON),
+            response(StatusCode::OK, "\"v3\"", r#"{"config":[]}"#),
+        ]);
+        let store = RemoteConfigStore::new();
+        let notifications = Arc::new(AtomicUsize::new(0));
+        let _subscription = store.subscribe(Box::new({
+            let notifications = Arc::clone(&notifications);
+            move || {
+                notifications.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+        assert_eq!(store.current(), None);
+
+        assert_matches!(store.refresh(&chat).await, Ok(true));
+        assert!(store.current().expect("fetched").is_enabled("global.flag"));
+        assert_matches!(store.refresh(&chat).await, Ok(false));
+        // Same contents under a new ETag.
+        assert_matches!(store.refresh(&chat).await, Ok(false));
+        assert_matches!(store.refresh(&chat).await, Ok(true));
+        assert!(!store.current().expect("fetched").is_enabled("global.flag"));
+        assert_eq!(notifications.load(Ordering::Relaxed), 2);
+
+        let if_none_match = chat
+            .requests()
+            .iter()
+            .map(|request| request.headers.get(http::header::IF_NONE_MATCH).cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            if_none_match,
+            [
+                None,
+                Some(HeaderValue::from_static("\"v1\"")),
+                Some(HeaderValue::from_static("\"v1\"")),
+                Some(HeaderValue::from_static("\"v2\"")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_refresh_keeps_config() {
+        let chat = FakeChat::responding_in_order([
+            response(StatusCode::OK, "\"v1\"", CONFIG_JSON),
+            response(StatusCode::INTERNAL_SERVER_ERROR, "\"v2\"", ""),
+            response(StatusCode::OK, "\"v3\"", "not json"),
+        ]);
+        let store = RemoteConfigStore::new();
+        store.refresh(&chat).await.expect("success");
+        assert_matches!(
+            store.refresh(&chat).await,
+            Err(Error::RequestFailed(StatusCode::INTERNAL_SERVER_ERROR))
+        );
+        assert_matches!(store.refresh(&chat).await, Err(Error::InvalidResponse));
+        assert!(store.current().expect("kept").is_enabled("global.flag"));
+    }
+}
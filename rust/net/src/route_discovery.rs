@@ -0,0 +1,457 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Server-provided routes for reaching Signal's services when the built-in
+//! ones are blocked.
+//!
+//! The server can hand out a [`RouteDocument`] listing extra domain fronts,
+//! TLS proxies, and alternate ports. The document is signed with a pinned
+//! Curve25519 key, so it can also be passed along through untrusted channels;
+//! it's only accepted if the signature verifies and it hasn't expired. Each
+//! document lists routes for a single service, and replaces an earlier
+//! document for that service only if it was issued later. Its routes are
+//! merged into a connection manager with
+//! [`MultiRouteConnectionManager::with_discovered_routes`](crate::infra::connection_manager::MultiRouteConnectionManager::with_discovered_routes).
+//!
+//! A signed document is a JSON object with base64-encoded `document` and
+//! `signature` fields, where the signature covers the decoded `document`
+//! bytes. Those bytes are themselves JSON:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "service": "chat",
+//!   "issuedAt": 1733097600,
+//!   "expiresAt": 1735689600,
+//!   "routes": [
+//!     {"type": "front", "sni": "cdn.example", "host": "reflector.example", "priority": 10},
+//!     {"type": "tlsProxy", "host": "proxy.example", "port": 443},
+//!     {"type": "direct", "address": "192.0.2.1", "port": 8443, "priority": -1}
+//!   ]
+//! }
+//! ```
+//!
+//! `service` is one of `chat`, `cdsi`, or `svr3`. Routes with a higher
+//! `priority` are tried first; the default is 0.
+
+use std::net::IpAddr;
+use std::num::NonZeroU16;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use libsignal_protocol::PublicKey;
+use nonzero_ext::nonzero;
+use serde::Deserialize;
+
+use crate::env::DomainConfig;
+use crate::infra::certs::RootCertificates;
+use crate::infra::host::Host;
+use crate::infra::{
+    ConnectionParams, HttpRequestDecorator, RouteType, TrafficClass, TransportConnectionParams,
+};
+
+const DOCUMENT_VERSION: u32 = 1;
+const DEFAULT_PORT: NonZeroU16 = nonzero!(443u16);
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum RouteDocumentError {
+    /// route document was malformed
+    Malformed,
+    /// route document signature was invalid
+    InvalidSignature,
+    /// unsupported route document version {0}
+    UnsupportedVersion(u32),
+    /// route document has expired
+    Expired,
+    /// route document is not newer than the one in use
+    Stale,
+}
+
+impl From<RouteDocumentError> for std::io::Error {
+    fn from(value: RouteDocumentError) -> Self {
+        Self::new(std::io::ErrorKind::InvalidData, value.to_string())
+    }
+}
+
+/// A way to reach a service, from a [`RouteDocument`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiscoveredRoute {
+    /// Connect to the service's own host name on `port`, or to `address`
+    /// without a DNS lookup.
+    Direct {
+        address: Option<IpAddr>,
+        port: NonZeroU16,
+    },
+    /// Connect to `sni` and ask the front to forward requests to `http_host`.
+    Front {
+        sni: Arc<str>,
+        http_host: Arc<str>,
+        port: NonZeroU16,
+    },
+    /// Connect to the service through the TLS proxy at `host`.
+    TlsProxy {
+        host: Host<Arc<str>>,
+        port: NonZeroU16,
+    },
+}
+
+impl DiscoveredRoute {
+    /// The parameters for reaching the service described by `domain_config`
+    /// over this route.
+    pub fn connection_params(&self, domain_config: &DomainConfig) -> ConnectionParams {
+        match self {
+            Self::Direct { address, port } => {
+                let mut params = domain_config.direct_connection_params();
+                match address {
+                    Some(ip) => params.with_address_override(*ip, *port),
+                    None => {
+                        params.transport.port = *port;
+                        params
+                    }
+                }
+            }
+            Self::Front {
+                sni,
+                http_host,
+                port,
+            } => ConnectionParams {
+                route_type: RouteType::DiscoveredFront,
+                transport: TransportConnectionParams {
+                    sni: Arc::clone(sni),
+                    tcp_host: Host::Domain(Arc::clone(sni)),
+                    port: *port,
+                    certs: RootCertificates::Native,
+                    proxy: None,
+                    traffic_class: TrafficClass::default(),
+                },
+                http_host: Arc::clone(http_host),
+                http_request_decorator: HttpRequestDecorator::PathPrefix(domain_config.proxy_path)
+                    .into(),
+                connection_confirmation_header: domain_config
+                    .confirmation_header_name
+                    .map(http::HeaderName::from_static),
+            },
+            Self::TlsProxy { host, port } => domain_config
+                .direct_connection_params()
+                .via_proxy((host.clone(), *port)),
+        }
+    }
+}
+
+/// The service a [`RouteDocument`] provides routes for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RouteService {
+    Chat,
+    Cdsi,
+    /// All of the SVR3 enclaves.
+    Svr3,
+}
+
+/// A verified list of routes, ordered from most to least preferred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteDocument {
+    pub service: RouteService,
+    pub issued_at: SystemTime,
+    pub expires_at: SystemTime,
+    pub routes: Vec<DiscoveredRoute>,
+}
+
+#[derive(Deserialize)]
+struct SignedDocumentJson {
+    document: String,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentJson {
+    version: u32,
+    service: RouteService,
+    issued_at: u64,
+    expires_at: u64,
+    routes: Vec<RouteJson>,
+}
+
+#[derive(Deserialize)]
+struct RouteJson {
+    #[serde(default)]
+    priority: i32,
+    #[serde(flatten)]
+    route: RouteKindJson,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum RouteKindJson {
+    Direct {
+        address: Option<IpAddr>,
+        port: NonZeroU16,
+    },
+    Front {
+        sni: String,
+        host: String,
+        port: Option<NonZeroU16>,
+    },
+    TlsProxy {
+        host: String,
+        port: Option<NonZeroU16>,
+    },
+}
+
+impl From<RouteKindJson> for DiscoveredRoute {
+    fn from(value: RouteKindJson) -> Self {
+        match value {
+            RouteKindJson::Direct { address, port } => Self::Direct { address, port },
+            RouteKindJson::Front { sni, host, port } => Self::Front {
+                sni: sni.into(),
+                http_host: host.into(),
+                port: port.unwrap_or(DEFAULT_PORT),
+            },
+            RouteKindJson::TlsProxy { host, port } => Self::TlsProxy {
+                host: Host::parse_as_ip_or_domain(&host),
+                port: port.unwrap_or(DEFAULT_PORT),
+            },
+        }
+    }
+}
+
+impl RouteDocument {
+    /// Checks the signature on `signed_document` against `key` and parses
+    /// the routes.
+    ///
+    /// Fails if the document has expired as of `now`.
+    pub fn verify(
+        signed_document: &[u8],
+        key: &PublicKey,
+        now: SystemTime,
+    ) -> Result<Self, RouteDocumentError> {
+        let SignedDocumentJson {
+            document,
+            signature,
+        } = serde_json::from_slice(signed_document).map_err(|_| RouteDocumentError::Malformed)?;
+        let document = BASE64_STANDARD
+            .decode(document)
+            .map_err(|_| RouteDocumentError::Malformed)?;
+        let signature = BASE64_STANDARD
+            .decode(signature)
+            .map_err(|_| RouteDocumentError::Malformed)?;
+        if !key.verify_signature(&document, &signature).unwrap_or(false) {
+            return Err(RouteDocumentError::InvalidSignature);
+        }
+
+        let DocumentJson {
+            version,
+            service,
+            issued_at,
+            expires_at,
+            routes,
+        } = serde_json::from_slice(&document).map_err(|_| RouteDocumentError::Malformed)?;
+        if version != DOCUMENT_VERSION {
+            return Err(RouteDocumentError::UnsupportedVersion(version));
+        }
+        let expires_at = SystemTime::UNIX_EPOCH + Duration::from_secs(expires_at);
+        if expires_at <= now {
+            return Err(RouteDocumentError::Expired);
+        }
+
+        let mut routes = routes;
+        // A stable sort keeps the server's order for routes with the same priority.
+        routes.sort_by_key(|route| std::cmp::Reverse(route.priority));
+        Ok(Self {
+            service,
+            issued_at: SystemTime::UNIX_EPOCH + Duration::from_secs(issued_at),
+            expires_at,
+            routes: routes.into_iter().map(|route| route.route.into()).collect(),
+        })
+    }
+
+    /// Fails unless the document was issued after `issued_at`, the issue time
+    /// of the document currently in use for the same service.
+    ///
+    /// This keeps an older document that hasn't expired yet from replacing a
+    /// newer one.
+    pub fn check_newer_than(
+        &self,
+        issued_at: Option<SystemTime>,
+    ) -> Result<(), RouteDocumentError> {
+        match issued_at {
+            Some(issued_at) if self.issued_at <= issued_at => Err(RouteDocumentError::Stale),
+            _ => Ok(()),
+        }
+    }
+
+    /// How long the document can still be used, as of `now`.
+    pub fn time_remaining(&self, now: SystemTime) -> Duration {
+        self.expires_at.duration_since(now).unwrap_or_default()
+    }
+
+    /// The parameters for each route to the service described by
+    /// `domain_config`, in order of preference.
+    pub fn connection_params(&self, domain_config: &DomainConfig) -> Vec<ConnectionParams> {
+        self.routes
+            .iter()
+            .map(|route| route.connection_params(domain_config))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use libsignal_protocol::KeyPair;
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::env::PROD;
+
+    const DOCUMENT: &str = r#"{
+        "version": 1,
+        "service": "chat",
+        "issuedAt": 1800000000,
+        "expiresAt": 2000000000,
+        "routes": [
+            {"type": "direct", "address": "192.0.2.1", "port": 8443, "priority": -1},
+            {"type": "tlsProxy", "host": "proxy.example", "port": 4433},
+            {"type": "front", "sni": "cdn.example", "host": "reflector.example", "priority": 10}
+        ]
+    }"#;
+
+    fn sign(key_pair: &KeyPair, document: &[u8]) -> Vec<u8> {
+        let signature = key_pair
+            .calculate_signature(document, &mut OsRng)
+            .expect("can sign");
+        serde_json::json!({
+            "document": BASE64_STANDARD.encode(document),
+            "signature": BASE64_STANDARD.encode(signature),
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn before_expiration() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_900_000_000)
+    }
+
+    #[test]
+    fn parses_routes_in_priority_order() {
+        let key_pair = KeyPair::generate(&mut OsRng);
+        let document = RouteDocument::verify(
+            &sign(&key_pair, DOCUMENT.as_bytes()),
+            &key_pair.public_key,
+            before_expiration(),
+        )
+        .expect("valid");
+
+        assert_eq!(document.service, RouteService::Chat);
+        assert_eq!(
+            document.issued_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000)
+        );
+        assert_eq!(
+            document.routes,
+            [
+                DiscoveredRoute::Front {
+                    sni: "cdn.example".into(),
+                    http_host: "reflector.example".into(),
+                    port: nonzero!(443u16),
+                },
+                DiscoveredRoute::TlsProxy {
+                    host: Host::Domain("proxy.example".into()),
+                    port: nonzero!(4433u16),
+                },
+                DiscoveredRoute::Direct {
+                    address: Some("192.0.2.1".parse().expect("valid")),
+                    port: nonzero!(8443u16),
+                },
+            ]
+        );
+        assert_eq!(
+            document.time_remaining(before_expiration()),
+            Duration::from_secs(100_000_000)
+        );
+
+        let chat = &PROD.chat_domain_config;
+        let params = document.connection_params(chat);
+        assert_eq!(params[0].route_type, RouteType::DiscoveredFront);
+        assert_eq!(&*params[0].transport.sni, "cdn.example");
+        assert_eq!(&*params[0].http_host, "reflector.example");
+        assert_eq!(params[1].route_type, RouteType::TlsProxy);
+        assert_eq!(
+            params[1].transport.proxy,
+            Some((Host::Domain("proxy.example".into()), nonzero!(4433u16)))
+        );
+        assert_eq!(params[2].route_type, RouteType::Direct);
+        assert_eq!(&*params[2].transport.sni, chat.hostname);
+        assert_eq!(params[2].transport.port, nonzero!(8443u16));
+    }
+
+    #[test]
+    fn rejects_bad_documents() {
+        let key_pair = KeyPair::generate(&mut OsRng);
+        let now = before_expiration();
+
+        let other_key = KeyPair::generate(&mut OsRng).public_key;
+        assert_matches!(
+            RouteDocument::verify(&sign(&key_pair, DOCUMENT.as_bytes()), &other_key, now),
+            Err(RouteDocumentError::InvalidSignature)
+        );
+
+        let too_late = SystemTime::UNIX_EPOCH + Duration::from_secs(2_000_000_000);
+        assert_matches!(
+            RouteDocument::verify(
+                &sign(&key_pair, DOCUMENT.as_bytes()),
+                &key_pair.public_key,
+                too_late
+            ),
+            Err(RouteDocumentError::Expired)
+        );
+
+        let future_version = br#"{
+            "version": 2, "service": "chat", "issuedAt": 0, "expiresAt": 2000000000, "routes": []
+        }"#;
+        assert_matches!(
+            RouteDocument::verify(&sign(&key_pair, future_version), &key_pair.public_key, now),
+            Err(RouteDocumentError::UnsupportedVersion(2))
+        );
+
+        assert_matches!(
+            RouteDocument::verify(b"not json", &key_pair.public_key, now),
+            Err(RouteDocumentError::Malformed)
+        );
+
+        let unknown_service = br#"{
+            "version": 1, "service": "mail", "issuedAt": 0, "expiresAt": 2000000000, "routes": []
+        }"#;
+        assert_matches!(
+            RouteDocument::verify(&sign(&key_pair, unknown_service), &key_pair.public_key, now),
+            Err(RouteDocumentError::Malformed)
+        );
+    }
+
+    #[test]
+    fn only_newer_documents_replace_older_ones() {
+        let key_pair = KeyPair::generate(&mut OsRng);
+        let document = RouteDocument::verify(
+            &sign(&key_pair, DOCUMENT.as_bytes()),
+            &key_pair.public_key,
+            before_expiration(),
+        )
+        .expect("valid");
+
+        let issued_at = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        assert_matches!(document.check_newer_than(None), Ok(()));
+        assert_matches!(document.check_newer_than(issued_at(1_700_000_000)), Ok(()));
+        assert_matches!(
+            document.check_newer_than(issued_at(1_800_000_000)),
+            Err(RouteDocumentError::Stale)
+        );
+        assert_matches!(
+            document.check_newer_than(issued_at(1_850_000_000)),
+            Err(RouteDocumentError::Stale)
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Fetching and caching sealed sender certificates.
+//!
+//! The chat server issues short-lived [`SenderCertificate`]s that vouch for
+//! this device's identity inside sealed sender messages. Two kinds are
+//! available: one that includes the account's phone number and one that
+//! doesn't. [`SenderCertificateStore`] keeps one of each and fetches a new one
+//! shortly before the cached one expires.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use http::uri::PathAndQuery;
+use http::{HeaderMap, Method, StatusCode};
+use libsignal_protocol::SenderCertificate;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::chat::{ChatService, ChatServiceError, Request};
+use crate::clock::{system_clock, Clock};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long before expiration a cached certificate is replaced, so that it
+/// doesn't expire while a message is in flight.
+const REFRESH_BEFORE_EXPIRATION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum Error {
+    /// chat service error: {0}
+    ChatService(#[from] ChatServiceError),
+    /// request failed with status {0}
+    RequestFailed(StatusCode),
+    /// invalid response received from the server
+    InvalidResponse,
+}
+
+/// Which kind of certificate to request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SenderCertificateKind {
+    /// Includes the account's phone number, for recipients who can see it.
+    WithE164,
+    /// Identifies the account only by its ACI.
+    WithoutE164,
+}
+
+impl SenderCertificateKind {
+    fn path(self) -> PathAndQuery {
+        PathAndQuery::from_static(match self {
+            Self::WithE164 => "/v1/certificate/delivery?includeE164=true",
+            Self::WithoutE164 => "/v1/certificate/delivery?includeE164=false",
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct CertificateResponse {
+    certificate: String,
+}
+
+/// Keeps the current sender certificates for this device.
+pub struct SenderCertificateStore {
+    certificates: Mutex<HashMap<SenderCertificateKind, SenderCertificate>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for SenderCertificateStore {
+    fn default() -> Self {
+        Self::with_clock(system_clock())
+    }
+}
+
+impl SenderCertificateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `clock` instead of the system clock to decide when certificates
+    /// need to be replaced.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            certificates: Mutex::default(),
+            clock,
+        }
+    }
+
+    /// Returns a certificate of the given kind that won't expire soon,
+    /// fetching a new one over `chat` if necessary.
+    ///
+    /// `chat` must be authenticated as the device the certificate is for.
+    /// Concurrent callers share a single fetch.
+    pub async fn get_valid_certificate(
+        &self,
+        chat: &(dyn ChatService + Send + Sync),
+        kind: SenderCertificateKind,
+    ) -> Result<SenderCertificate, Error> {
+        // Holding the lock while fetching keeps concurrent senders from all
+        // requesting new certificates at once.
+        let mut certificates = self.certificates.lock().await;
+        let now = self.clock.now();
+        if let Some(certificate) = certificates.get(&kind) {
+            if !needs_refresh(certificate, now) {
+                return Ok(certificate.clone());
+            }
+        }
+
+        let certificate = fetch_certificate(chat, kind).await?;
+        if needs_refresh(&certificate, now) {
+            log::warn!("server issued a {kind:?} sender certificate that expires soon");
+        }
+        certificates.insert(kind, certificate.clone());
+        Ok(certificate)
+    }
+
+    /// Drops any cached certificates, such as after the account's phone
+    /// number changes.
+    pub async fn clear(&self) {
+        self.certificates.lock().await.clear();
+    }
+}
+
+fn needs_refresh(certificate: &SenderCertificate, now: SystemTime) -> bool {
+    let expiration = certificate
+        .expiration()
+        .expect("always succeeds for a parsed certificate");
+    SystemTime::from(expiration) <= now + REFRESH_BEFORE_EXPIRATION
+}
+
+async fn fetch_certificate(
+    chat: &(dyn ChatService + Send + Sync),
+    kind: SenderCertificateKind,
+) -> Result<SenderCertificate, Error> {
+    let request = Request {
+        method: Method::GET,
+        body: None,
+        headers: HeaderMap::new(),
+        path: kind.path(),
+    };
+    let response = chat.send(request, REQUEST_TIMEOUT).await?;
+    if !response.status.is_success() {
+        return Err(Error::RequestFailed(response.status));
+    }
+
+    let CertificateResponse { certificate } =
+        serde_json::from_slice(&response.body.unwrap_or_default())
+            .map_err(|_| Error::InvalidResponse)?;
+    let certificate = BASE64_STANDARD
+        .decode(certificate)
+        .map_err(|_| Error::InvalidResponse)?;
+    SenderCertificate::deserialize(&certificate).map_err(|_| Error::InvalidResponse)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex as SyncMutex;
+
+    use assert_matches::assert_matches;
+    use libsignal_protocol::{DeviceId, KeyPair, ServerCertificate, Timestamp};
+    use rand::rngs::OsRng;
+
+    use super::*;
+    use crate::chat::testutil::{json_response, FakeChat};
+    use crate::chat::Response;
+    use crate::clock::ManualClock;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Issues certificates that expire at the time in `expiration` when
+    /// they're requested.
+    fn issuing_until(expiration: Arc<SyncMutex<SystemTime>>) -> FakeChat {
+        FakeChat::new(move |request| {
+            let include_e164 = request.path.query() == Some("includeE164=true");
+            let expiration_millis = expiration
+                .lock()
+                .expect("not poisoned")
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .expect("after the epoch")
+                .as_millis();
+
+            let trust_root = KeyPair::generate(&mut OsRng);
+            let server_key = KeyPair::generate(&mut OsRng);
+            let server_certificate = ServerCertificate::new(
+                1,
+                server_key.public_key,
+                &trust_root.private_key,
+                &mut OsRng,
+            )
+            .expect("valid");
+            let certificate = SenderCertificate::new(
+                "9d0652a3-dcc3-4d11-975f-74d61598733f".to_owned(),
+                include_e164.then(|| "+18005550100".to_owned()),
+                KeyPair::generate(&mut OsRng).public_key,
+                DeviceId::try_from(1u32).expect("valid"),
+                Timestamp::from_epoch_millis(expiration_millis.try_into().expect("fits")),
+                server_certificate,
+                &server_key.private_key,
+                &mut OsRng,
+            )
+            .expect("valid");
+
+            json_response(
+                StatusCode::OK,
+                &serde_json::json!({
+                    "certificate": BASE64_STANDARD.encode(certificate.serialized().expect("valid")),
+                }),
+            )
+        })
+    }
+
+    #[tokio::test]
+    async fn caches_each_kind_until_near_expiration() {
+        let now = SystemTime::UNIX_EPOCH + 1000 * DAY;
+        let expiration = Arc::new(SyncMutex::new(now + DAY));
+        let chat = issuing_until(expiration.clone());
+        let clock = Arc::new(ManualClock::new(now));
+        let store = SenderCertificateStore::with_clock(clock.clone());
+
+        let with_e164 = store
+            .get_valid_certificate(&chat, SenderCertificateKind::WithE164)
+            .await
+            .expect("success");
+        assert_eq!(
+            with_e164.sender_e164().expect("valid"),
+            Some("+18005550100")
+        );
+        let without_e164 = store
+            .get_valid_certificate(&chat, SenderCertificateKind::WithoutE164)
+            .await
+            .expect("success");
+        assert_eq!(without_e164.sender_e164().expect("valid"), None);
+
+        clock.set(now + DAY / 2);
+        let cached = store
+            .get_valid_certificate(&chat, SenderCertificateKind::WithE164)
+            .await
+            .expect("success");
+        assert_eq!(
+            cached.serialized().expect("valid"),
+            with_e164.serialized().expect("valid")
+        );
+        assert_eq!(chat.request_paths().len(), 2);
+
+        *expiration.lock().expect("not poisoned") = now + 2 * DAY;
+        clock.set(now + DAY - REFRESH_BEFORE_EXPIRATION);
+        let refreshed = store
+            .get_valid_certificate(&chat, SenderCertificateKind::WithE164)
+            .await
+            .expect("success");
+        assert_ne!(
+            refreshed.serialized().expect("valid"),
+            with_e164.serialized().expect("valid")
+        );
+        assert_eq!(
+            chat.request_paths(),
+            [
+                "/v1/certificate/delivery?includeE164=true",
+                "/v1/certificate/delivery?includeE164=false",
+                "/v1/certificate/delivery?includeE164=true",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_is_reported() {
+        let chat = FakeChat::responding(Response {
+            status: StatusCode::UNAUTHORIZED,
+            message: None,
+            body: None,
+            headers: HeaderMap::new(),
+        });
+
+        assert_matches!(
+            SenderCertificateStore::new()
+                .get_valid_certificate(&chat, SenderCertificateKind::WithoutE164)
+                .await,
+            Err(Error::RequestFailed(StatusCode::UNAUTHORIZED))
+        );
+    }
+}
@@ -0,0 +1,663 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for the storage service, which syncs contacts, groups, and account
+//! settings between a user's devices.
+//!
+//! The service stores a manifest listing the IDs of the current records, plus
+//! the records themselves, all encrypted with keys derived from the account's
+//! master key: the [`StorageKey`] encrypts the manifest, and each record has
+//! its own [`ItemKey`]. The manifest is versioned, and a write only succeeds
+//! if it is based on the latest version; otherwise the caller gets back the
+//! newer manifest with [`StorageError::Conflict`], and can use
+//! [`Manifest::diff`] to find out which records to fetch before trying again.
+//!
+//! Records are opaque to this module; callers encode and decode the
+//! `StorageRecord` protobuf themselves.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use bytes::Bytes;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac as _};
+use http::uri::PathAndQuery;
+use http::{HeaderMap, HeaderValue, Method, StatusCode};
+use prost::Message as _;
+use rand_core::{CryptoRng, RngCore};
+use sha2::Sha256;
+use signal_crypto::{Aes256GcmDecryption, Aes256GcmEncryption};
+
+use crate::auth::HttpBasicAuth;
+use crate::cdn::{check_status, HttpEndpoint, RequestError};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::TransportConnector;
+use crate::proto::storage::manifest_record::{identifier, Identifier};
+use crate::proto::storage::{
+    ManifestRecord, ReadOperation, StorageItem, StorageItems, StorageManifest, WriteOperation,
+};
+use crate::utils::basic_authorization;
+
+const MANIFEST_PATH: &str = "/v1/storage/manifest";
+const WRITE_PATH: &str = "/v1/storage";
+const READ_PATH: &str = "/v1/storage/read";
+const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+/// The largest manifest or batch of records accepted from the storage service.
+///
+/// A manifest lists the ID of every record (around 20 bytes each), and a read
+/// can return hundreds of records of up to a few kilobytes each, so accounts
+/// with many contacts and groups easily go past the 1 MiB allowed for other
+/// responses.
+const MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+const STORAGE_KEY_INFO: &[u8] = b"Storage Service Encryption";
+const ITEM_KEY_HKDF_INFO_PREFIX: &[u8] = b"20240801_SIGNAL_STORAGE_SERVICE_ITEM_";
+const NONCE_LEN: usize = Aes256GcmEncryption::NONCE_SIZE;
+const TAG_LEN: usize = Aes256GcmEncryption::TAG_SIZE;
+
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum StorageError {
+    /// request failed: {0}
+    Request(#[from] RequestError),
+    /// storage was changed by another device
+    Conflict(Box<Manifest>),
+    /// storage data could not be decrypted
+    DecryptionFailed,
+    /// storage data was malformed
+    InvalidData,
+}
+
+fn hmac_sha256(key: &[u8], input: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(input);
+    mac.finalize().into_bytes().into()
+}
+
+/// Encrypts `plaintext` as `nonce || ciphertext || tag`.
+fn encrypt(key: &[u8; 32], plaintext: &[u8], rng: &mut (impl RngCore + CryptoRng)) -> Vec<u8> {
+    let mut nonce = [0; NONCE_LEN];
+    rng.fill_bytes(&mut nonce);
+    let mut output = [nonce.as_slice(), plaintext].concat();
+    let mut gcm = Aes256GcmEncryption::new(key, &nonce, &[]).expect("valid key and nonce");
+    gcm.encrypt(&mut output[NONCE_LEN..]);
+    output.extend_from_slice(&gcm.compute_tag());
+    output
+}
+
+fn decrypt(key: &[u8; 32], ciphertext: &[u8]) -> Result<Vec<u8>, StorageError> {
+    if ciphertext.len() < NONCE_LEN + TAG_LEN {
+        return Err(StorageError::DecryptionFailed);
+    }
+    let (nonce, rest) = ciphertext.split_at(NONCE_LEN);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+    let mut plaintext = ciphertext.to_vec();
+    let mut gcm = Aes256GcmDecryption::new(key, nonce, &[]).expect("valid key and nonce");
+    gcm.decrypt(&mut plaintext);
+    gcm.verify_tag(tag)
+        .map_err(|_| StorageError::DecryptionFailed)?;
+    Ok(plaintext)
+}
+
+/// The root key for storage service data, derived from the master key.
+#[derive(Clone)]
+pub struct StorageKey([u8; 32]);
+
+impl StorageKey {
+    pub fn from_master_key(master_key: &[u8; 32]) -> Self {
+        Self(hmac_sha256(master_key, STORAGE_KEY_INFO))
+    }
+
+    /// The key for the manifest at `version`.
+    pub fn manifest_key(&self, version: u64) -> ManifestKey {
+        ManifestKey(hmac_sha256(
+            &self.0,
+            format!("Manifest_{version}").as_bytes(),
+        ))
+    }
+
+    /// The key for the record with `raw_id`, for manifests without a record
+    /// IKM.
+    pub fn item_key(&self, raw_id: &[u8]) -> ItemKey {
+        let info = format!("Item_{}", BASE64_STANDARD.encode(raw_id));
+        ItemKey(hmac_sha256(&self.0, info.as_bytes()))
+    }
+}
+
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StorageKey(<redacted>)")
+    }
+}
+
+/// Encrypts the manifest at one particular version.
+pub struct ManifestKey([u8; 32]);
+
+impl ManifestKey {
+    pub fn encrypt(&self, plaintext: &[u8], rng: &mut (impl RngCore + CryptoRng)) -> Vec<u8> {
+        encrypt(&self.0, plaintext, rng)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, StorageError> {
+        decrypt(&self.0, ciphertext)
+    }
+}
+
+/// Encrypts one record.
+pub struct ItemKey([u8; 32]);
+
+impl ItemKey {
+    /// The key for the record with `raw_id`, for manifests with a record IKM.
+    pub fn from_record_ikm(record_ikm: &[u8; 32], raw_id: &[u8]) -> Self {
+        let mut key = [0; 32];
+        Hkdf::<Sha256>::new(None, record_ikm)
+            .expand_multi_info(&[ITEM_KEY_HKDF_INFO_PREFIX, raw_id], &mut key)
+            .expect("valid output length");
+        Self(key)
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8], rng: &mut (impl RngCore + CryptoRng)) -> Vec<u8> {
+        encrypt(&self.0, plaintext, rng)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, StorageError> {
+        decrypt(&self.0, ciphertext)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StorageRecordType {
+    Contact,
+    GroupV1,
+    GroupV2,
+    Account,
+    StoryDistributionList,
+    CallLink,
+    /// A type this client doesn't know about, which must still be preserved.
+    Other(i32),
+}
+
+impl From<i32> for StorageRecordType {
+    fn from(value: i32) -> Self {
+        match identifier::Type::try_from(value) {
+            Ok(identifier::Type::Contact) => Self::Contact,
+            Ok(identifier::Type::Groupv1) => Self::GroupV1,
+            Ok(identifier::Type::Groupv2) => Self::GroupV2,
+            Ok(identifier::Type::Account) => Self::Account,
+            Ok(identifier::Type::StoryDistributionList) => Self::StoryDistributionList,
+            Ok(identifier::Type::CallLink) => Self::CallLink,
+            Ok(identifier::Type::Unknown) | Err(_) => Self::Other(value),
+        }
+    }
+}
+
+impl From<StorageRecordType> for i32 {
+    fn from(value: StorageRecordType) -> Self {
+        let known = match value {
+            StorageRecordType::Contact => identifier::Type::Contact,
+            StorageRecordType::GroupV1 => identifier::Type::Groupv1,
+            StorageRecordType::GroupV2 => identifier::Type::Groupv2,
+            StorageRecordType::Account => identifier::Type::Account,
+            StorageRecordType::StoryDistributionList => identifier::Type::StoryDistributionList,
+            StorageRecordType::CallLink => identifier::Type::CallLink,
+            StorageRecordType::Other(value) => return value,
+        };
+        known.into()
+    }
+}
+
+/// Identifies one record in storage.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StorageId {
+    pub raw: Vec<u8>,
+    pub record_type: StorageRecordType,
+}
+
+impl StorageId {
+    /// A fresh ID for a new or changed record; records are never modified in
+    /// place.
+    pub fn generate(record_type: StorageRecordType, rng: &mut (impl RngCore + CryptoRng)) -> Self {
+        let mut raw = vec![0; 16];
+        rng.fill_bytes(&mut raw);
+        Self { raw, record_type }
+    }
+}
+
+/// A record and its ID, in plaintext.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageRecord {
+    pub id: StorageId,
+    /// A serialized `StorageRecord` protobuf.
+    pub data: Vec<u8>,
+}
+
+/// The decrypted manifest: which records make up a particular version of
+/// storage.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: u64,
+    pub source_device: u32,
+    pub identifiers: Vec<StorageId>,
+    pub record_ikm: Option<[u8; 32]>,
+}
+
+impl fmt::Debug for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Manifest")
+            .field("version", &self.version)
+            .field("source_device", &self.source_device)
+            .field("identifiers", &self.identifiers.len())
+            .field("record_ikm", &self.record_ikm.map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// The records added and removed between two manifests.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ManifestDiff {
+    pub added: Vec<StorageId>,
+    pub removed: Vec<StorageId>,
+}
+
+impl Manifest {
+    /// The key for the record with `raw_id` in this manifest.
+    pub fn item_key(&self, storage_key: &StorageKey, raw_id: &[u8]) -> ItemKey {
+        match &self.record_ikm {
+            Some(record_ikm) => ItemKey::from_record_ikm(record_ikm, raw_id),
+            None => storage_key.item_key(raw_id),
+        }
+    }
+
+    /// The records in `newer` but not in `self`, and vice versa.
+    pub fn diff(&self, newer: &Manifest) -> ManifestDiff {
+        let old_ids = HashSet::<&StorageId>::from_iter(&self.identifiers);
+        let new_ids = HashSet::<&StorageId>::from_iter(&newer.identifiers);
+        ManifestDiff {
+            added: newer
+                .identifiers
+                .iter()
+                .filter(|id| !old_ids.contains(id))
+                .cloned()
+                .collect(),
+            removed: self
+                .identifiers
+                .iter()
+                .filter(|id| !new_ids.contains(id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn encrypt(
+        &self,
+        storage_key: &StorageKey,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> StorageManifest {
+        let Self {
+            version,
+            source_device,
+            identifiers,
+            record_ikm,
+        } = self;
+        let record = ManifestRecord {
+            version: *version,
+            source_device: *source_device,
+            identifiers: identifiers
+                .iter()
+                .map(|id| Identifier {
+                    raw: id.raw.clone(),
+                    r#type: id.record_type.into(),
+                })
+                .collect(),
+            record_ikm: record_ikm.map(Vec::from).unwrap_or_default(),
+        };
+        StorageManifest {
+            version: *version,
+            value: storage_key
+                .manifest_key(*version)
+                .encrypt(&record.encode_to_vec(), rng),
+        }
+    }
+
+    fn decrypt(storage_key: &StorageKey, manifest: &StorageManifest) -> Result<Self, StorageError> {
+        let plaintext = storage_key
+            .manifest_key(manifest.version)
+            .decrypt(&manifest.value)?;
+        let ManifestRecord {
+            version,
+            source_device,
+            identifiers,
+            record_ikm,
+        } = ManifestRecord::decode(plaintext.as_slice()).map_err(|_| StorageError::InvalidData)?;
+        if version != manifest.version {
+            return Err(StorageError::InvalidData);
+        }
+        let record_ikm = match record_ikm.as_slice() {
+            [] => None,
+            ikm => Some(ikm.try_into().map_err(|_| StorageError::InvalidData)?),
+        };
+        Ok(Self {
+            version,
+            source_device,
+            identifiers: identifiers
+                .into_iter()
+                .map(|Identifier { raw, r#type }| StorageId {
+                    raw,
+                    record_type: r#type.into(),
+                })
+                .collect(),
+            record_ikm,
+        })
+    }
+}
+
+/// Client for the storage service.
+pub struct StorageClient<C, T> {
+    endpoint: HttpEndpoint<C, T>,
+}
+
+impl<C: ConnectionManager, T: TransportConnector> StorageClient<C, T> {
+    /// Creates a client; `connection_manager` should connect to the storage
+    /// service host.
+    pub fn new(connection_manager: C, transport_connector: T) -> Self {
+        Self {
+            endpoint: HttpEndpoint::new(connection_manager, transport_connector, MAX_RESPONSE_SIZE),
+        }
+    }
+
+    /// Fetches and decrypts the latest manifest, or `None` if nothing has been
+    /// stored yet.
+    pub async fn fetch_manifest(
+        &self,
+        auth: &impl HttpBasicAuth,
+        storage_key: &StorageKey,
+    ) -> Result<Option<Manifest>, StorageError> {
+        self.fetch_manifest_at(auth, storage_key, PathAndQuery::from_static(MANIFEST_PATH))
+            .await
+    }
+
+    /// Like [`Self::fetch_manifest`], but returns `None` unless the latest
+    /// manifest is newer than `version`.
+    pub async fn fetch_manifest_if_newer(
+        &self,
+        auth: &impl HttpBasicAuth,
+        storage_key: &StorageKey,
+        version: u64,
+    ) -> Result<Option<Manifest>, StorageError> {
+        let path = PathAndQuery::from_str(&format!("{MANIFEST_PATH}/version/{version}"))
+            .expect("valid path");
+        self.fetch_manifest_at(auth, storage_key, path).await
+    }
+
+    /// Fetches and decrypts the records with `ids`, which must be listed in
+    /// `manifest`.
+    ///
+    /// Records that no longer exist are left out of the result.
+    pub async fn read_records(
+        &self,
+        auth: &impl HttpBasicAuth,
+        storage_key: &StorageKey,
+        manifest: &Manifest,
+        ids: &[StorageId],
+    ) -> Result<Vec<StorageRecord>, StorageError> {
+        let request = ReadOperation {
+            read_key: ids.iter().map(|id| id.raw.clone()).collect(),
+        };
+        let (_parts, body) = self
+            .endpoint
+            .send(
+                Method::PUT,
+                PathAndQuery::from_static(READ_PATH),
+                headers(auth),
+                Bytes::from(request.encode_to_vec()),
+            )
+            .await?;
+        let items = StorageItems::decode(body).map_err(|_| RequestError::InvalidResponse)?;
+
+        items
+            .items
+            .into_iter()
+            .map(|StorageItem { key, value }| {
+                let id = ids
+                    .iter()
+                    .find(|id| id.raw == key)
+                    .ok_or(StorageError::InvalidData)?;
+                let data = manifest.item_key(storage_key, &key).decrypt(&value)?;
+                Ok(StorageRecord {
+                    id: id.clone(),
+                    data,
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces storage with `manifest`, inserting `inserts` and deleting
+    /// `deletes`.
+    ///
+    /// `manifest.version` must be exactly one more than the latest version;
+    /// otherwise this fails with [`StorageError::Conflict`], carrying the
+    /// latest manifest.
+    pub async fn write(
+        &self,
+        auth: &impl HttpBasicAuth,
+        storage_key: &StorageKey,
+        manifest: &Manifest,
+        inserts: &[StorageRecord],
+        deletes: &[StorageId],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(), StorageError> {
+        let request = WriteOperation {
+            manifest: Some(manifest.encrypt(storage_key, rng)),
+            insert_item: inserts
+                .iter()
+                .map(|record| StorageItem {
+                    key: record.id.raw.clone(),
+                    value: manifest
+                        .item_key(storage_key, &record.id.raw)
+                        .encrypt(&record.data, rng),
+                })
+                .collect(),
+            delete_key: deletes.iter().map(|id| id.raw.clone()).collect(),
+            clear_all: false,
+        };
+        let (parts, body) = self
+            .endpoint
+            .send_unchecked(
+                Method::PUT,
+                PathAndQuery::from_static(WRITE_PATH),
+                headers(auth),
+                Bytes::from(request.encode_to_vec()),
+            )
+            .await?;
+        if parts.status == StatusCode::CONFLICT {
+            let latest =
+                StorageManifest::decode(body).map_err(|_| RequestError::InvalidResponse)?;
+            return Err(StorageError::Conflict(Box::new(Manifest::decrypt(
+                storage_key,
+                &latest,
+            )?)));
+        }
+        check_status(&parts)?;
+        Ok(())
+    }
+
+    async fn fetch_manifest_at(
+        &self,
+        auth: &impl HttpBasicAuth,
+        storage_key: &StorageKey,
+        path: PathAndQuery,
+    ) -> Result<Option<Manifest>, StorageError> {
+        let (parts, body) = match self
+            .endpoint
+            .send(Method::GET, path, headers(auth), Bytes::new())
+            .await
+        {
+            Ok(response) => response,
+            Err(RequestError::NotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if parts.status == StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+        let manifest = StorageManifest::decode(body).map_err(|_| RequestError::InvalidResponse)?;
+        Manifest::decrypt(storage_key, &manifest).map(Some)
+    }
+}
+
+fn headers(auth: &impl HttpBasicAuth) -> HeaderMap {
+    HeaderMap::from_iter([
+        (
+            http::header::AUTHORIZATION,
+            basic_authorization(auth.username(), auth.password()),
+        ),
+        (
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static(PROTOBUF_CONTENT_TYPE),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use assert_matches::assert_matches;
+    use hex_literal::hex;
+    use rand::rngs::OsRng;
+
+    use super::*;
+
+    const MASTER_KEY: [u8; 32] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        25, 26, 27, 28, 29, 30, 31,
+    ];
+    const RAW_ID: [u8; 16] = [0xaa; 16];
+
+    #[test]
+    fn key_derivation() {
+        let storage_key = StorageKey::from_master_key(&MASTER_KEY);
+        assert_eq!(
+            storage_key.0,
+            hex!("d9fd23ee99c148ad879f62609297139e6a36f415b1ad779d23c687b1fa6d36d9")
+        );
+        assert_eq!(
+            storage_key.manifest_key(7).0,
+            hex!("d728bafc43d9bd972c5dba170ecc2f2c53f000ab0d3478e7b265d2bf09f566ae")
+        );
+        assert_eq!(
+            storage_key.item_key(&RAW_ID).0,
+            hex!("de153cf35f9e7f320b3fa632e7213c862a3f518e25ba246cd76df81735cf1b7a")
+        );
+        assert_eq!(
+            ItemKey::from_record_ikm(&[0x11; 32], &RAW_ID).0,
+            hex!("dbbbff84916ddcea21303deb198566cbf658a55535bb20a90342acaa46adc463")
+        );
+    }
+
+    #[test]
+    fn record_encryption_round_trip() {
+        let key = StorageKey::from_master_key(&MASTER_KEY).item_key(&RAW_ID);
+        let ciphertext = key.encrypt(b"record", &mut OsRng);
+        assert_eq!(ciphertext.len(), NONCE_LEN + b"record".len() + TAG_LEN);
+        assert_eq!(key.decrypt(&ciphertext).expect("valid"), b"record");
+
+        let other_key = StorageKey::from_master_key(&[0; 32]).item_key(&RAW_ID);
+        assert_matches!(
+            other_key.decrypt(&ciphertext),
+            Err(StorageError::DecryptionFailed)
+        );
+        assert_matches!(
+            key.decrypt(&ciphertext[..NONCE_LEN]),
+            Err(StorageError::DecryptionFailed)
+        );
+    }
+
+    fn id(byte: u8, record_type: StorageRecordType) -> StorageId {
+        StorageId {
+            raw: vec![byte; 16],
+            record_type,
+        }
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let storage_key = StorageKey::from_master_key(&MASTER_KEY);
+        let manifest = Manifest {
+            version: 3,
+            source_device: 2,
+            identifiers: vec![
+                id(1, StorageRecordType::Contact),
+                id(2, StorageRecordType::Account),
+                id(3, StorageRecordType::Other(99)),
+            ],
+            record_ikm: Some([0x11; 32]),
+        };
+
+        let encrypted = manifest.encrypt(&storage_key, &mut OsRng);
+        assert_eq!(encrypted.version, 3);
+        assert_eq!(
+            Manifest::decrypt(&storage_key, &encrypted).expect("valid"),
+            manifest
+        );
+
+        // The version is bound to the key.
+        let mut wrong_version = encrypted;
+        wrong_version.version = 4;
+        assert_matches!(
+            Manifest::decrypt(&storage_key, &wrong_version),
+            Err(StorageError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn item_key_depends_on_record_ikm() {
+        let storage_key = StorageKey::from_master_key(&MASTER_KEY);
+        let mut manifest = Manifest {
+            version: 1,
+            source_device: 1,
+            identifiers: vec![],
+            record_ikm: None,
+        };
+        assert_eq!(
+            manifest.item_key(&storage_key, &RAW_ID).0,
+            storage_key.item_key(&RAW_ID).0
+        );
+        manifest.record_ikm = Some([0x11; 32]);
+        assert_eq!(
+            manifest.item_key(&storage_key, &RAW_ID).0,
+            ItemKey::from_record_ikm(&[0x11; 32], &RAW_ID).0
+        );
+    }
+
+    #[test]
+    fn manifest_diff() {
+        let old = Manifest {
+            version: 1,
+            source_device: 1,
+            identifiers: vec![
+                id(1, StorageRecordType::Contact),
+                id(2, StorageRecordType::Contact),
+            ],
+            record_ikm: None,
+        };
+        let new = Manifest {
+            version: 2,
+            identifiers: vec![
+                id(2, StorageRecordType::Contact),
+                id(3, StorageRecordType::GroupV2),
+            ],
+            ..old.clone()
+        };
+
+        assert_eq!(
+            old.diff(&new),
+            ManifestDiff {
+                added: vec![id(3, StorageRecordType::GroupV2)],
+                removed: vec![id(1, StorageRecordType::Contact)],
+            }
+        );
+        assert_eq!(new.diff(&new), ManifestDiff::default());
+    }
+}
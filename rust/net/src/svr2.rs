@@ -0,0 +1,407 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Client for SVR2, which stores the master key used for registration lock.
+//!
+//! New data should go to SVR3 (see [`crate::svr3`]); this exists for clients
+//! that still need to read and write the copy kept in SVR2 during the
+//! transition. The PIN pipeline matches [`signal_pin::svr2_pin_hash`]: the
+//! PIN hash's access key guards the backup, and its encryption key protects
+//! the stored master key.
+
+use std::num::NonZeroU32;
+
+pub use signal_pin::PinHash;
+use thiserror::Error;
+
+use crate::auth::HttpBasicAuth;
+use crate::enclave::{EnclaveEndpointConnection, SgxPreQuantum};
+use crate::infra::connection_manager::ConnectionManager;
+use crate::infra::errors::LogSafeDisplay;
+use crate::infra::ws::close::{error_for_close, CloseFrameError, CommonClose};
+use crate::infra::ws::{
+    AttestedConnection, AttestedConnectionError, DefaultStream, WebSocketConnectError,
+    WebSocketServiceError,
+};
+use crate::infra::{AsyncDuplexStream, TransportConnector};
+use crate::proto::svr2::{
+    backup_response, request, response, restore_response, BackupRequest, DeleteRequest, Request,
+    Response, RestoreRequest,
+};
+use crate::registration::RegistrationLockToken;
+
+/// Anything that can go wrong talking to SVR2.
+#[derive(Debug, Error, displaydoc::Display)]
+#[ignore_extra_doc_attributes]
+pub enum Error {
+    /// Connection error: {0}
+    Connect(WebSocketConnectError),
+    /// Network error: {0}
+    Service(#[from] WebSocketServiceError),
+    /// Protocol error after establishing a connection: {0}
+    Protocol(&'static str),
+    /// Enclave attestation failed: {0}
+    AttestationError(attest::enclave::Error),
+    /// Connect timed out
+    ConnectionTimedOut,
+    /// The server rejected the request as invalid
+    RequestInvalid,
+    /// Rate limited; retry after {retry_after_seconds} seconds
+    RateLimited { retry_after_seconds: u32 },
+    /// Server error: {reason}
+    Server { reason: &'static str },
+    /// Failure to restore data. {tries_remaining} tries remaining.
+    ///
+    /// This is caused by an incorrect PIN.
+    RestoreFailed { tries_remaining: u32 },
+    /// No data is stored for this account
+    ///
+    /// This could mean either the data was never backed up or all of the
+    /// tries were used up.
+    DataMissing,
+    /// PIN error: {0}
+    Pin(#[from] signal_pin::Error),
+}
+
+impl LogSafeDisplay for Error {}
+
+impl From<crate::svr::Error> for Error {
+    fn from(err: crate::svr::Error) -> Self {
+        use crate::svr::Error as SvrError;
+        match err {
+            SvrError::WebSocketConnect(inner) => Self::Connect(inner),
+            SvrError::WebSocket(inner) => Self::Service(inner),
+            SvrError::Protocol => Self::Protocol("general SVR protocol error"),
+            SvrError::AttestationError(inner) => Self::AttestationError(inner),
+            SvrError::ConnectionTimedOut => Self::ConnectionTimedOut,
+        }
+    }
+}
+
+impl CloseFrameError for Error {
+    fn from_common_close(close: CommonClose) -> Self {
+        match close {
+            CommonClose::InvalidArgument { server_reason: _ } => Self::RequestInvalid,
+            CommonClose::RateLimited {
+                retry_after_seconds,
+            } => Self::RateLimited {
+                retry_after_seconds,
+            },
+            CommonClose::Server { reason } => Self::Server { reason },
+        }
+    }
+
+    fn unexpected_close() -> Self {
+        Self::Protocol("connection closed before response")
+    }
+}
+
+impl From<AttestedConnectionError> for Error {
+    fn from(err: AttestedConnectionError) -> Self {
+        Self::from(crate::svr::Error::from(err))
+    }
+}
+
+/// An attested connection to SVR2 for a single account.
+pub struct Svr2Connection<S = DefaultStream> {
+    inner: AttestedConnection<S>,
+    username: String,
+    group_id: u64,
+}
+
+impl<S: AsyncDuplexStream> Svr2Connection<S> {
+    /// Connects to `endpoint` and verifies remote attestation.
+    pub async fn connect<C, T>(
+        endpoint: &EnclaveEndpointConnection<SgxPreQuantum, C>,
+        transport_connector: T,
+        auth: impl HttpBasicAuth,
+    ) -> Result<Self, Error>
+    where
+        C: ConnectionManager,
+        T: TransportConnector<Stream = S>,
+    {
+        let username = auth.username().to_owned();
+        let group_id = endpoint.params.raft_config.group_id;
+        let inner = endpoint.connect(auth, transport_connector).await?;
+        Ok(Self {
+            inner,
+            username,
+            group_id,
+        })
+    }
+
+    /// Hashes a PIN for use with this account and enclave.
+    ///
+    /// `normalized_pin` is the UTF-8 encoding of the PIN, which *must* already
+    /// be normalized.
+    pub fn pin_hash(&self, normalized_pin: &[u8]) -> Result<PinHash, Error> {
+        Ok(signal_pin::svr2_pin_hash(
+            normalized_pin,
+            &self.username,
+            self.group_id,
+        )?)
+    }
+
+    /// Stores `master_key`, guarded by `pin_hash`, replacing any existing
+    /// backup.
+    ///
+    /// After `max_tries` restores with the wrong PIN, the data is deleted.
+    /// The enclave accepts at most 255 tries.
+    pub async fn backup(
+        &mut self,
+        pin_hash: &PinHash,
+        master_key: &[u8; 32],
+        max_tries: NonZeroU32,
+    ) -> Result<(), Error> {
+        let request = request::Inner::Backup(BackupRequest {
+            data: pin_hash.encrypt_master_key(master_key).to_vec(),
+            pin: pin_hash.access_key.to_vec(),
+            max_tries: max_tries.get(),
+        });
+        let response::Inner::Backup(response) = self.run(request).await? else {
+            return Err(Error::Protocol("unexpected response to backup"));
+        };
+        match response.status() {
+            backup_response::Status::Ok => Ok(()),
+            backup_response::Status::RequestInvalid => Err(Error::RequestInvalid),
+            backup_response::Status::Unset => Err(Error::Protocol("unset backup status")),
+        }
+    }
+
+    /// Retrieves the master key guarded by `pin_hash`.
+    ///
+    /// An incorrect PIN uses up one try and fails with
+    /// [`Error::RestoreFailed`].
+    pub async fn restore(&mut self, pin_hash: &PinHash) -> Result<[u8; 32], Error> {
+        let request = request::Inner::Restore(RestoreRequest {
+            pin: pin_hash.access_key.to_vec(),
+        });
+        let response::Inner::Restore(response) = self.run(request).await? else {
+            return Err(Error::Protocol("unexpected response to restore"));
+        };
+        match response.status() {
+            restore_response::Status::Ok => {
+                let encrypted = response
+                    .data
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| Error::Protocol("restored data has the wrong length"))?;
+                Ok(pin_hash.decrypt_master_key(encrypted)?)
+            }
+            restore_response::Status::Missing => Err(Error::DataMissing),
+            restore_response::Status::PinMismatch => Err(Error::RestoreFailed {
+                tries_remaining: response.tries,
+            }),
+            restore_response::Status::RequestInvalid => Err(Error::RequestInvalid),
+            restore_response::Status::Unset => Err(Error::Protocol("unset restore status")),
+        }
+    }
+
+    /// Restores the master key and derives the registration lock token from
+    /// it, for re-registering a registration-locked account.
+    pub async fn restore_registration_lock(
+        &mut self,
+        pin_hash: &PinHash,
+    ) -> Result<RegistrationLockToken, Error> {
+        let master_key = self.restore(pin_hash).await?;
+        Ok(RegistrationLockToken::from_master_key(&master_key))
+    }
+
+    /// Deletes any data stored for this account.
+    pub async fn delete(&mut self) -> Result<(), Error> {
+        let request = request::Inner::Delete(DeleteRequest {});
+        match self.run(request).await? {
+            response::Inner::Delete(_) => Ok(()),
+            _ => Err(Error::Protocol("unexpected response to delete")),
+        }
+    }
+
+    async fn run(&mut self, request: request::Inner) -> Result<response::Inner, Error> {
+        self.inner
+            .send(Request {
+                inner: Some(request),
+            })
+            .await?;
+        let response: Response = self.inner.receive().await?.next_or_else(error_for_close)?;
+        response.inner.ok_or(Error::Protocol("empty response"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use assert_matches::assert_matches;
+    use nonzero_ext::nonzero;
+    use prost::Message as _;
+    use tokio::io::DuplexStream;
+    use tungstenite::protocol::frame::coding::CloseCode;
+    use tungstenite::protocol::CloseFrame;
+
+    use super::*;
+    use crate::enclave::loopback::{LoopbackEnclave, LoopbackReply};
+
+    const MASTER_KEY: [u8; 32] = [0x42; 32];
+
+    struct Stored {
+        pin: Vec<u8>,
+        data: Vec<u8>,
+        tries: u32,
+    }
+
+    /// A minimal SVR2 enclave that keeps one backup.
+    fn fake_svr2(state: Arc<Mutex<Option<Stored>>>) -> impl FnMut(Vec<u8>) -> LoopbackReply {
+        move |message| {
+            let request = Request::decode(message.as_slice()).expect("valid request");
+            let mut state = state.lock().expect("not poisoned");
+            let response = match request.inner.expect("has request") {
+                request::Inner::Backup(BackupRequest {
+                    data,
+                    pin,
+                    max_tries,
+                }) => {
+                    *state = Some(Stored {
+                        pin,
+                        data,
+                        tries: max_tries,
+                    });
+                    response::Inner::Backup(crate::proto::svr2::BackupResponse {
+                        status: backup_response::Status::Ok.into(),
+                    })
+                }
+                request::Inner::Restore(RestoreRequest { pin }) => {
+                    let mut restore = crate::proto::svr2::RestoreResponse::default();
+                    match &mut *state {
+                        None => restore.set_status(restore_response::Status::Missing),
+                        Some(stored) if stored.pin == pin => {
+                            restore.set_status(restore_response::Status::Ok);
+                            restore.data = stored.data.clone();
+                            restore.tries = stored.tries;
+                        }
+                        Some(stored) => {
+                            stored.tries -= 1;
+                            restore.set_status(restore_response::Status::PinMismatch);
+                            restore.tries = stored.tries;
+                            if stored.tries == 0 {
+                                *state = None;
+                            }
+                        }
+                    }
+                    response::Inner::Restore(restore)
+                }
+                request::Inner::Delete(_) => {
+                    *state = None;
+                    response::Inner::Delete(crate::proto::svr2::DeleteResponse {})
+                }
+            };
+            LoopbackReply::message(
+                Response {
+                    inner: Some(response),
+                }
+                .encode_to_vec(),
+            )
+        }
+    }
+
+    async fn connect(state: Arc<Mutex<Option<Stored>>>) -> Svr2Connection<DuplexStream> {
+        let inner = LoopbackEnclave::new(&mut rand::thread_rng())
+            .connect_in_memory(fake_svr2(state))
+            .await
+            .expect("connected");
+        Svr2Connection {
+            inner,
+            username: "username".to_owned(),
+            group_id: 12345,
+        }
+    }
+
+    fn pin_hash(pin: &[u8]) -> PinHash {
+        signal_pin::svr2_pin_hash(pin, "username", 12345).expect("can hash")
+    }
+
+    #[tokio::test]
+    async fn backup_and_restore() {
+        let state = Arc::default();
+        let mut connection = connect(Arc::clone(&state)).await;
+        let pin_hash = connection.pin_hash(b"1234").expect("can hash");
+
+        connection
+            .backup(&pin_hash, &MASTER_KEY, nonzero!(10u32))
+            .await
+            .expect("backed up");
+        assert_eq!(
+            connection.restore(&pin_hash).await.expect("restored"),
+            MASTER_KEY
+        );
+        assert_eq!(
+            connection
+                .restore_registration_lock(&pin_hash)
+                .await
+                .expect("restored"),
+            RegistrationLockToken::from_master_key(&MASTER_KEY)
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_pin_reports_tries_remaining() {
+        let state = Arc::default();
+        let mut connection = connect(Arc::clone(&state)).await;
+
+        connection
+            .backup(&pin_hash(b"1234"), &MASTER_KEY, nonzero!(2u32))
+            .await
+            .expect("backed up");
+
+        assert_matches!(
+            connection.restore(&pin_hash(b"0000")).await,
+            Err(Error::RestoreFailed { tries_remaining: 1 })
+        );
+        assert_matches!(
+            connection.restore(&pin_hash(b"0000")).await,
+            Err(Error::RestoreFailed { tries_remaining: 0 })
+        );
+        assert_matches!(
+            connection.restore(&pin_hash(b"1234")).await,
+            Err(Error::DataMissing)
+        );
+    }
+
+    #[tokio::test]
+    async fn close_codes_become_typed_errors() {
+        let mut connection = Svr2Connection {
+            inner: LoopbackEnclave::new(&mut rand::thread_rng())
+                .connect_in_memory(|_| {
+                    LoopbackReply::close(Some(CloseFrame {
+                        code: CloseCode::Bad(4008),
+                        reason: r#"{"retry_after_seconds":60}"#.into(),
+                    }))
+                })
+                .await
+                .expect("connected"),
+            username: "username".to_owned(),
+            group_id: 12345,
+        };
+        assert_matches!(
+            connection.delete().await,
+            Err(Error::RateLimited {
+                retry_after_seconds: 60
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_removes_backup() {
+        let state = Arc::default();
+        let mut connection = connect(Arc::clone(&state)).await;
+        let pin_hash = pin_hash(b"1234");
+
+        connection
+            .backup(&pin_hash, &MASTER_KEY, nonzero!(10u32))
+            .await
+            .expect("backed up");
+        connection.delete().await.expect("deleted");
+        assert_matches!(connection.restore(&pin_hash).await, Err(Error::DataMissing));
+    }
+}
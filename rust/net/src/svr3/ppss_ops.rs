@@ -17,8 +17,10 @@ use libsignal_svr3::{
     MAX_ROTATION_STEPS,
 };
 use rand_core::CryptoRngCore;
+use tokio::time::Instant;
 
 use super::{Error, OpaqueMaskedShareSet};
+use crate::enclave::events::{EnclaveEventSink, EnclaveInteraction, InteractionOutcome};
 use crate::enclave::{ArrayIsh, IntoConnectionResults, PpssSetup};
 use crate::infra::host::Host;
 use crate::infra::ws::{run_attested_interaction, AttestedConnection, NextOrClose};
@@ -30,6 +32,7 @@ pub async fn do_backup<S: AsyncDuplexStream + 'static, Env: PpssSetup<S>>(
     secret: [u8; 32],
     max_tries: NonZeroU32,
     rng: &mut (impl CryptoRngCore + Send),
+    events: &dyn EnclaveEventSink,
 ) -> Result<OpaqueMaskedShareSet, Error> {
     let ConnectionContext {
         mut connections,
@@ -48,15 +51,14 @@ pub async fn do_backup<S: AsyncDuplexStream + 'static, Env: PpssSetup<S>>(
         max_tries,
         rng,
     )?;
-    let futures = connections
-        .iter_mut()
-        .zip(&backup.requests)
-        .map(|(connection, request)| run_attested_interaction(connection, request));
-    let results = join_all(futures)
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>();
-    collect_responses(results?, addresses.iter())?;
+    run_round(
+        "backup",
+        &mut connections,
+        &addresses,
+        &backup.requests,
+        events,
+    )
+    .await?;
     Ok(OpaqueMaskedShareSet::new(backup.masked_secret))
 }
 
@@ -65,6 +67,7 @@ pub async fn do_restore<S: AsyncDuplexStream + 'static>(
     password: &str,
     share_set: OpaqueMaskedShareSet,
     rng: &mut (impl CryptoRngCore + Send),
+    events: &dyn EnclaveEventSink,
 ) -> Result<EvaluationResult, Error> {
     let ConnectionContext {
         mut connections,
@@ -78,17 +81,14 @@ pub async fn do_restore<S: AsyncDuplexStream + 'static>(
     let masked_secret: MaskedSecret = share_set.into_inner();
 
     let restore1 = Restore1::new(masked_secret.server_ids.as_ref(), password.as_bytes(), rng);
-    let responses1 = {
-        let futures = connections
-            .iter_mut()
-            .zip(&restore1.requests)
-            .map(|(connection, request)| run_attested_interaction(connection, request));
-        let results = join_all(futures)
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>();
-        collect_responses(results?, addresses.iter())?
-    };
+    let responses1 = run_round(
+        "restore1",
+        &mut connections,
+        &addresses,
+        &restore1.requests,
+        events,
+    )
+    .await?;
 
     let handshake_hashes = connections
         .iter()
@@ -96,17 +96,14 @@ pub async fn do_restore<S: AsyncDuplexStream + 'static>(
         .collect::<Vec<_>>();
     let restore2 = restore1.restore2(&responses1, &handshake_hashes, rng)?;
     let tries_remaining = restore2.tries_remaining;
-    let responses2 = {
-        let futures = connections
-            .iter_mut()
-            .zip(&restore2.requests)
-            .map(|(connection, request)| run_attested_interaction(connection, request));
-        let results = join_all(futures)
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>();
-        collect_responses(results?, addresses.iter())?
-    };
+    let responses2 = run_round(
+        "restore2",
+        &mut connections,
+        &addresses,
+        &restore2.requests,
+        events,
+    )
+    .await?;
     let output = restore2.restore(&responses2)?;
 
     Ok(EvaluationResult {
@@ -117,6 +114,7 @@ pub async fn do_restore<S: AsyncDuplexStream + 'static>(
 
 pub async fn do_remove<S: AsyncDuplexStream + 'static>(
     connect_results: impl IntoConnectionResults<Stream = S>,
+    events: &dyn EnclaveEventSink,
 ) -> Result<(), Error> {
     let ConnectionContext {
         mut connections,
@@ -129,20 +127,20 @@ pub async fn do_remove<S: AsyncDuplexStream + 'static>(
         log::debug!("Connection failure '{:?}' will be ignored.", &err);
     }
 
-    let futures = connections
-        .iter_mut()
-        .zip(Remove4::requests())
-        .map(|(connection, request)| run_attested_interaction(connection, request));
-    let results = join_all(futures)
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>();
-    let _responses = collect_responses(results?, addresses.iter())?;
+    let _responses = run_round(
+        "remove",
+        &mut connections,
+        &addresses,
+        Remove4::requests(),
+        events,
+    )
+    .await?;
     Ok(())
 }
 
 pub async fn do_query<S: AsyncDuplexStream + 'static>(
     connect_results: impl IntoConnectionResults<Stream = S>,
+    events: &dyn EnclaveEventSink,
 ) -> Result<u32, Error> {
     let ConnectionContext {
         mut connections,
@@ -153,15 +151,14 @@ pub async fn do_query<S: AsyncDuplexStream + 'static>(
         return Err(err);
     }
 
-    let futures = connections
-        .iter_mut()
-        .zip(Query4::requests())
-        .map(|(connection, request)| run_attested_interaction(connection, request));
-    let results = join_all(futures)
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>();
-    let responses = collect_responses(results?, addresses.iter())?;
+    let responses = run_round(
+        "query",
+        &mut connections,
+        &addresses,
+        Query4::requests(),
+        events,
+    )
+    .await?;
     Ok(Query4::finalize(&responses)?)
 }
 
@@ -169,6 +166,7 @@ pub async fn do_rotate<S: AsyncDuplexStream + 'static>(
     connect_results: impl IntoConnectionResults<Stream = S>,
     share_set: OpaqueMaskedShareSet,
     rng: &mut (impl CryptoRngCore + Send),
+    events: &dyn EnclaveEventSink,
 ) -> Result<(), Error> {
     let ConnectionContext {
         mut connections,
@@ -187,15 +185,8 @@ pub async fn do_rotate<S: AsyncDuplexStream + 'static>(
             break;
         }
         let requests = rotation_machine.requests();
-        let futures = connections
-            .iter_mut()
-            .zip(&requests)
-            .map(|(connection, request)| run_attested_interaction(connection, request));
-        let results = join_all(futures)
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>();
-        let responses = collect_responses(results?, addresses.iter())?;
+        let responses =
+            run_round("rotate", &mut connections, &addresses, &requests, events).await?;
         rotation_machine.handle_responses(responses.as_ref())?;
     }
     if rotation_machine.is_done() {
@@ -233,6 +224,40 @@ impl<S: AsyncDuplexStream + 'static> ConnectionContext<S> {
     }
 }
 
+/// Sends one request to each enclave and collects the responses, reporting on
+/// each interaction to `events` as it finishes.
+async fn run_round<S: AsyncDuplexStream + 'static>(
+    operation: &'static str,
+    connections: &mut [AttestedConnection<S>],
+    addresses: &[Host<Arc<str>>],
+    requests: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    events: &dyn EnclaveEventSink,
+) -> Result<Vec<Vec<u8>>, Error> {
+    let futures = connections.iter_mut().zip(requests).zip(addresses).map(
+        |((connection, request), enclave)| async move {
+            let started = Instant::now();
+            let result = run_attested_interaction(connection, request).await;
+            let outcome = match &result {
+                Ok(NextOrClose::Next(_)) => InteractionOutcome::Response,
+                Ok(NextOrClose::Close(_)) => InteractionOutcome::Closed,
+                Err(_) => InteractionOutcome::Failed,
+            };
+            events.on_interaction(&EnclaveInteraction {
+                operation,
+                enclave,
+                elapsed: started.elapsed(),
+                outcome,
+            });
+            result
+        },
+    );
+    let results = join_all(futures)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>();
+    collect_responses(results?, addresses)
+}
+
 fn collect_responses<'a>(
     results: impl IntoIterator<Item = NextOrClose<Vec<u8>>>,
     addresses: impl IntoIterator<Item = &'a Host<impl AsRef<str> + 'a>>,
@@ -255,6 +280,9 @@ mod test {
 
     use super::*;
     use crate::enclave::Error;
+    use crate::infra::ws::testutil::{
+        fake_websocket, run_attested_server, websocket_test_client, AttestedServerOutput,
+    };
     use crate::infra::ws::DefaultStream;
 
     struct TestEnv;
@@ -292,6 +320,7 @@ mod test {
             [0; 32],
             nonzero!(1u32),
             &mut rng,
+            &(),
         )
         .await;
         assert_matches!(result, Err(crate::svr3::Error::ConnectionTimedOut));
@@ -305,6 +334,7 @@ mod test {
             "",
             OpaqueMaskedShareSet::default(),
             &mut rng,
+            &(),
         )
         .await;
         assert_matches!(result, Err(crate::svr3::Error::ConnectionTimedOut));
@@ -312,14 +342,69 @@ mod test {
 
     #[tokio::test]
     async fn do_query_fails_with_the_first_error() {
-        let result = do_query(NotConnectedResults).await;
+        let result = do_query(NotConnectedResults, &()).await;
         assert_matches!(result, Err(crate::svr3::Error::ConnectionTimedOut));
     }
 
     #[tokio::test]
     async fn do_remove_does_not_fail_on_bad_connections() {
-        do_remove(NotConnectedResults)
+        do_remove(NotConnectedResults, &())
             .await
             .expect("Should ignore connection errors");
     }
+
+    #[derive(Default)]
+    struct RecordingSink(std::sync::Mutex<Vec<(&'static str, InteractionOutcome)>>);
+
+    impl EnclaveEventSink for RecordingSink {
+        fn on_interaction(&self, interaction: &EnclaveInteraction<'_>) {
+            self.0
+                .lock()
+                .expect("not poisoned")
+                .push((interaction.operation, interaction.outcome));
+        }
+    }
+
+    async fn attested_connection(
+        on_message: impl FnMut(NextOrClose<Vec<u8>>) -> AttestedServerOutput + Send + 'static,
+    ) -> AttestedConnection<tokio::io::DuplexStream> {
+        let (server, client) = fake_websocket().await;
+        tokio::spawn(run_attested_server(
+            server,
+            attest::sgx_session::testutil::private_key(),
+            on_message,
+        ));
+        AttestedConnection::connect(websocket_test_client(client), |_attestation| {
+            attest::sgx_session::testutil::handshake_from_tests_data()
+        })
+        .await
+        .expect("can connect")
+    }
+
+    #[tokio::test]
+    async fn run_round_reports_each_interaction() {
+        let mut connections = vec![
+            attested_connection(|message| AttestedServerOutput::message(message.unwrap_next()))
+                .await,
+            attested_connection(|_message| AttestedServerOutput::close(None)).await,
+        ];
+        let addresses = connections
+            .iter()
+            .map(|c| c.remote_address().clone())
+            .collect::<Vec<_>>();
+        let sink = RecordingSink::default();
+
+        let result = run_round("test", &mut connections, &addresses, [b"request"; 2], &sink).await;
+        assert_matches!(result, Err(crate::svr3::Error::Protocol(_)));
+
+        let mut reports = sink.0.into_inner().expect("not poisoned");
+        reports.sort_by_key(|(_, outcome)| *outcome as u8);
+        assert_eq!(
+            reports,
+            [
+                ("test", InteractionOutcome::Response),
+                ("test", InteractionOutcome::Closed)
+            ]
+        );
+    }
 }
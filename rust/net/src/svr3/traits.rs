@@ -14,6 +14,7 @@ use libsignal_svr3::EvaluationResult;
 use rand_core::CryptoRngCore;
 
 use super::{ppss_ops, Error, OpaqueMaskedShareSet};
+use crate::enclave::events::EnclaveEventSink;
 use crate::enclave::PpssSetup;
 use crate::infra::AsyncDuplexStream;
 
@@ -64,6 +65,13 @@ pub trait Svr3Connect {
     type Stream;
     type Env: PpssSetup<Self::Stream>;
     async fn connect(&self) -> <Self::Env as PpssSetup<Self::Stream>>::ConnectionResults;
+
+    /// Where to report on each request sent to an enclave.
+    ///
+    /// Reports are discarded by default.
+    fn event_sink(&self) -> &dyn EnclaveEventSink {
+        &()
+    }
 }
 
 #[async_trait]
@@ -85,6 +93,7 @@ where
             secret,
             max_tries,
             rng,
+            self.event_sink(),
         )
         .await
     }
@@ -102,7 +111,14 @@ where
         share_set: OpaqueMaskedShareSet,
         rng: &mut (impl CryptoRngCore + Send),
     ) -> Result<EvaluationResult, Error> {
-        ppss_ops::do_restore(self.connect().await, password, share_set, rng).await
+        ppss_ops::do_restore(
+            self.connect().await,
+            password,
+            share_set,
+            rng,
+            self.event_sink(),
+        )
+        .await
     }
 }
 
@@ -113,7 +129,7 @@ where
     T::Stream: AsyncDuplexStream + 'static,
 {
     async fn remove(&self) -> Result<(), Error> {
-        ppss_ops::do_remove(self.connect().await).await
+        ppss_ops::do_remove(self.connect().await, self.event_sink()).await
     }
 }
 
@@ -124,7 +140,7 @@ where
     T::Stream: AsyncDuplexStream + 'static,
 {
     async fn query(&self) -> Result<u32, Error> {
-        ppss_ops::do_query(self.connect().await).await
+        ppss_ops::do_query(self.connect().await, self.event_sink()).await
     }
 }
 
@@ -139,6 +155,6 @@ where
         share_set: OpaqueMaskedShareSet,
         rng: &mut (impl CryptoRngCore + Send),
     ) -> Result<(), Error> {
-        ppss_ops::do_rotate(self.connect().await, share_set, rng).await
+        ppss_ops::do_rotate(self.connect().await, share_set, rng, self.event_sink()).await
     }
 }
@@ -3,10 +3,10 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
-use std::future;
 use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
+use std::{fmt, future};
 
 use base64::prelude::{Engine as _, BASE64_STANDARD};
 use futures_util::stream::FuturesUnordered;
@@ -20,6 +20,23 @@ pub(crate) fn basic_authorization(username: &str, password: &str) -> HeaderValue
     HeaderValue::try_from(auth).expect("valid header value")
 }
 
+/// Wrapper whose [`Debug`](fmt::Debug) impl hides the wrapped value.
+///
+/// Used for fields that could identify a user or grant access to an account,
+/// so that logging a containing value with `{:?}` doesn't leak them. The
+/// `unredacted-logs` feature shows the value instead.
+pub(crate) struct Redacted<T>(pub T);
+
+impl<T: fmt::Debug> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if cfg!(feature = "unredacted-logs") {
+            self.0.fmt(f)
+        } else {
+            f.write_str("<redacted>")
+        }
+    }
+}
+
 /// Requires a `Future` to complete before the specified duration has elapsed.
 ///
 /// Takes in a future whose return type is `Result<T, E>`, a `duration` timeout,
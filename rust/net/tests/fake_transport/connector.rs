@@ -67,6 +67,8 @@ impl TransportConnector for FakeTransportConnector {
             port,
             sni: _,
             certs: _,
+            proxy: _,
+            traffic_class: _,
         } = connection_params;
         let fake_host = FakeTransportTarget {
             host: tcp_host.clone(),
@@ -153,7 +153,7 @@ impl FakeDeps {
 
         let auth = Auth {
             username: "user".to_owned(),
-            password: "password".to_owned(),
+            password: "password".to_owned().into(),
         };
 
         const RECEIVE_STORIES: bool = true;
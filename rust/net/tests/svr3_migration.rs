@@ -35,6 +35,7 @@ use assert_matches::assert_matches;
 use async_trait::async_trait;
 use base64::prelude::{Engine as _, BASE64_STANDARD};
 use libsignal_net::auth::Auth;
+use libsignal_net::clock::SystemClock;
 use libsignal_net::enclave::{EnclaveEndpoint, EnclaveKind, Error, PpssSetup, Sgx};
 use libsignal_net::env::Svr3Env;
 use libsignal_net::infra::tcp_ssl::DirectConnector;
@@ -208,13 +209,13 @@ async fn svr3_single_enclave_migration() {
 
     log::info!("Creating clients...");
     let prev_uid = random_bytes(&mut rng);
-    let prev_auth = Auth::from_uid_and_secret(prev_uid, enclave_secret);
+    let prev_auth = Auth::from_uid_and_secret(prev_uid, enclave_secret, &SystemClock);
     let prev_client = ValidatingClient::new(FullClient {
         auth: prev_auth.clone(),
     });
 
     let current_uid = random_bytes(&mut rng);
-    let current_auth = Auth::from_uid_and_secret(current_uid, enclave_secret);
+    let current_auth = Auth::from_uid_and_secret(current_uid, enclave_secret, &SystemClock);
     let current_client = ValidatingClient::new(FullClient { auth: current_auth });
 
     assert_ne!(&prev_uid, &current_uid);
@@ -324,13 +325,13 @@ async fn svr3_full_migration() {
 
     log::info!("Creating clients...");
     let prev_uid = random_bytes(&mut rng);
-    let prev_auth = Auth::from_uid_and_secret(prev_uid, enclave_secret);
+    let prev_auth = Auth::from_uid_and_secret(prev_uid, enclave_secret, &SystemClock);
     let prev_client = FullClient {
         auth: prev_auth.clone(),
     };
 
     let current_uid = random_bytes(&mut rng);
-    let current_auth = Auth::from_uid_and_secret(current_uid, enclave_secret);
+    let current_auth = Auth::from_uid_and_secret(current_uid, enclave_secret, &SystemClock);
     let current_client = FullClient { auth: current_auth };
 
     assert_ne!(&prev_uid, &current_uid);
@@ -17,6 +17,30 @@ pub fn hash(c: &mut Criterion) {
         b.iter(|| signal_pin::PinHash::create(password, &salt).unwrap());
     });
 
+    let mut group = c.benchmark_group("mobile");
+    // Each iteration takes a noticeable fraction of a second.
+    group.sample_size(10);
+    group.bench_function("svr_hash_recommended", |b| {
+        b.iter(|| {
+            signal_pin::PinHash::create_with_params(
+                password,
+                &salt,
+                &signal_pin::Argon2Params::RECOMMENDED_MOBILE,
+            )
+            .unwrap()
+        });
+    });
+    group.bench_function("verification_hash_recommended", |b| {
+        b.iter(|| {
+            signal_pin::local_pin_hash_with_params(
+                password,
+                &signal_pin::Argon2Params::RECOMMENDED_MOBILE,
+            )
+            .unwrap()
+        });
+    });
+    group.finish();
+
     c.bench_function("verification_hash", |b| {
         b.iter(|| signal_pin::local_pin_hash(password).unwrap());
     });
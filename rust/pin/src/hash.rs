@@ -13,6 +13,8 @@
 //!   2. Creating a [PHC-string encoded](https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md#specification)
 //!      password hash of the pin that can be stored locally and validated against the pin later.
 //!
+//! Both mechanisms can also be used with custom Argon2id parameters; see [`Argon2Params`].
+//!
 //! In either case, all pins are UTF-8 encoded bytes that must be normalized *before* being provided
 //! to this library. Normalizing a string pin requires the following steps:
 //!  1. The string should be trimmed for leading and trailing whitespace.
@@ -25,10 +27,9 @@ use argon2::password_hash::{rand_core, Salt, SaltString};
 use argon2::{
     Algorithm, Argon2, ParamsBuilder, PasswordHash, PasswordHasher, PasswordVerifier, Version,
 };
-use hkdf::Hkdf;
-use sha2::Sha256;
 
 use crate::error::Result;
+use crate::Argon2Params;
 
 #[derive(Clone, Debug)]
 pub struct PinHash {
@@ -49,17 +50,20 @@ impl PinHash {
     /// * `pin` - UTF-8 encoding of the pin. The pin *must* be normalized first.
     /// * `salt` - An arbitrary 32 byte value that should be unique to the user
     pub fn create(pin: &[u8], salt: &[u8; 32]) -> Result<PinHash> {
-        let hasher = Argon2::new(
-            Algorithm::Argon2id,
-            Version::V0x13,
-            ParamsBuilder::new()
-                .m_cost(1024 * 16) // 16 MiB
-                .p_cost(1)
-                .t_cost(32)
-                .output_len(64)
-                .build()
-                .expect("valid params"),
-        );
+        Self::create_with_params(pin, salt, &Argon2Params::SVR)
+    }
+
+    /// Like [`PinHash::create`], but with custom Argon2id parameters.
+    ///
+    /// The service doesn't know which parameters were used, so anything other than
+    /// [`Argon2Params::SVR`] must be recorded by the client (for example, with the encoding
+    /// provided by [`Argon2Params`]) to be able to recreate the hash later.
+    pub fn create_with_params(
+        pin: &[u8],
+        salt: &[u8; 32],
+        params: &Argon2Params,
+    ) -> Result<PinHash> {
+        let hasher = params.hasher(64)?;
         let mut output_key_material = [0u8; 64];
         hasher.hash_password_into(pin, salt, &mut output_key_material)?;
         Ok(PinHash {
@@ -75,15 +79,13 @@ impl PinHash {
     /// Create a salt from a username and the group id of the SVR service. This
     /// function should always be used to create pin salts for SVR2.
     ///
+    /// Equivalent to [`crate::svr2_salt`], which documents the derivation.
+    ///
     /// # Arguments
     /// * `username` - The Basic Auth username credential retrieved from the chat service and used to authenticate with the SVR service
     /// * `group_id` - The attested group id returned by the SVR service
     pub fn make_salt(username: &str, group_id: u64) -> [u8; 32] {
-        let mut out = [0u8; 32];
-        Hkdf::<Sha256>::new(Some(&group_id.to_be_bytes()), username.as_bytes())
-            .expand(&[], &mut out)
-            .expect("should expand");
-        out
+        crate::svr2_salt(username, group_id)
     }
 }
 
@@ -114,6 +116,18 @@ fn local_pin_hash_with_salt<'a>(pin: &[u8], salt: impl Into<Salt<'a>>) -> Result
     Ok(hash.to_string())
 }
 
+/// Create a PHC encoded Argon2id password hash string with the given parameters. Like the output
+/// of `local_pin_hash`, this string may be verified later with `verify_local_pin_hash`.
+///
+/// # Arguments
+/// * `pin` - UTF-8 encoding of the pin. The pin *must* be normalized first.
+/// * `params` - The Argon2id parameters to use, which are included in the output.
+pub fn local_pin_hash_with_params(pin: &[u8], params: &Argon2Params) -> Result<String> {
+    let salt = SaltString::generate(&mut rand_core::OsRng);
+    let hash = params.hasher(32)?.hash_password(pin, &salt)?;
+    Ok(hash.to_string())
+}
+
 /// Verify an encoded password hash against a pin
 ///
 /// # Arguments
@@ -131,7 +145,7 @@ mod test {
     use sha2::Sha256;
 
     use super::*;
-    use crate::hash::{local_pin_hash, verify_local_pin_hash, PinHash};
+    use crate::hash::{local_pin_hash, local_pin_hash_with_params, verify_local_pin_hash, PinHash};
 
     fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
         type HmacSha256 = Hmac<Sha256>;
@@ -229,6 +243,38 @@ mod test {
         assert!(!verify_local_pin_hash(&phc_string, b"wrongpin").unwrap());
     }
 
+    #[test]
+    fn custom_params() {
+        let salt = hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let svr = PinHash::create_with_params(b"password", &salt, &Argon2Params::SVR)
+            .expect("should hash");
+        assert_eq!(
+            svr.access_key,
+            hex!("ab7e8499d21f80a6600b3b9ee349ac6d72c07e3359fe885a934ba7aa844429f8")
+        );
+
+        let cheap = Argon2Params {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 2,
+        };
+        let custom = PinHash::create_with_params(b"password", &salt, &cheap).expect("should hash");
+        assert_ne!(custom.access_key, svr.access_key);
+    }
+
+    #[test]
+    fn verify_with_params() {
+        let params = Argon2Params {
+            memory_kib: 64,
+            iterations: 2,
+            parallelism: 1,
+        };
+        let phc_string = local_pin_hash_with_params(b"hunter2", &params).expect("should hash");
+        assert!(phc_string.starts_with("$argon2id$v=19$m=64,t=2,p=1$"));
+        assert!(verify_local_pin_hash(&phc_string, b"hunter2").unwrap());
+        assert!(!verify_local_pin_hash(&phc_string, b"wrongpin").unwrap());
+    }
+
     #[test]
     fn known_salt() {
         let username = "username";
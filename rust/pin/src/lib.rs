@@ -5,6 +5,12 @@
 
 mod error;
 mod hash;
+mod params;
+mod strength;
+mod svr2;
 
 pub use error::{Error, Result};
-pub use hash::{local_pin_hash, verify_local_pin_hash, PinHash};
+pub use hash::{local_pin_hash, local_pin_hash_with_params, verify_local_pin_hash, PinHash};
+pub use params::Argon2Params;
+pub use strength::{estimate_strength, PinStrength, PinStrengthLevel, PinWeakness, MIN_PIN_LENGTH};
+pub use svr2::{svr2_pin_hash, svr2_salt, ENCRYPTED_MASTER_KEY_LEN};
@@ -0,0 +1,133 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use argon2::password_hash::{Error as DecodingError, Ident, PasswordHash};
+use argon2::{Algorithm, Argon2, Params, ParamsBuilder, Version};
+
+use crate::error::{Error, Result};
+
+/// Cost parameters for Argon2id.
+///
+/// These are encoded in the same format as the parameters of a [PHC
+/// string](https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md#specification),
+/// without a salt or hash, e.g. `$argon2id$v=19$m=16384,t=32,p=1`. This lets a
+/// client that verifies a hash later find out which parameters were used to
+/// create it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// Memory size in KiB.
+    pub memory_kib: u32,
+    /// Number of passes over memory.
+    pub iterations: u32,
+    /// Degree of parallelism (number of lanes).
+    pub parallelism: u32,
+}
+
+impl Argon2Params {
+    /// The parameters used by [`crate::PinHash::create`].
+    pub const SVR: Self = Self {
+        memory_kib: 16 * 1024,
+        iterations: 32,
+        parallelism: 1,
+    };
+
+    /// The second recommended option from [RFC 9106 section
+    /// 4](https://www.rfc-editor.org/rfc/rfc9106.html#section-4), for
+    /// environments where memory is constrained, such as mobile devices.
+    pub const RECOMMENDED_MOBILE: Self = Self {
+        memory_kib: 64 * 1024,
+        iterations: 3,
+        parallelism: 4,
+    };
+
+    pub(crate) fn hasher(&self, output_len: usize) -> Result<Argon2<'static>> {
+        let Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        } = *self;
+        let params = ParamsBuilder::new()
+            .m_cost(memory_kib)
+            .t_cost(iterations)
+            .p_cost(parallelism)
+            .output_len(output_len)
+            .build()?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+impl Display for Argon2Params {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        } = self;
+        write!(
+            f,
+            "${}$v={}$m={memory_kib},t={iterations},p={parallelism}",
+            Algorithm::Argon2id.ident(),
+            u32::from(Version::V0x13),
+        )
+    }
+}
+
+impl FromStr for Argon2Params {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let encoded = PasswordHash::new(s)?;
+        if encoded.algorithm != Ident::new_unwrap("argon2id") {
+            return Err(DecodingError::Algorithm.into());
+        }
+        if encoded.version != Some(Version::V0x13.into()) {
+            return Err(DecodingError::Version.into());
+        }
+        if encoded.salt.is_some() || encoded.hash.is_some() {
+            return Err(DecodingError::PhcStringTrailingData.into());
+        }
+        let params = Params::try_from(&encoded)?;
+        Ok(Self {
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encoding_round_trips() {
+        let encoded = Argon2Params::SVR.to_string();
+        assert_eq!(encoded, "$argon2id$v=19$m=16384,t=32,p=1");
+        assert_eq!(encoded.parse::<Argon2Params>(), Ok(Argon2Params::SVR));
+
+        let encoded = Argon2Params::RECOMMENDED_MOBILE.to_string();
+        assert_eq!(
+            encoded.parse::<Argon2Params>(),
+            Ok(Argon2Params::RECOMMENDED_MOBILE)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_encodings() {
+        for encoded in [
+            "",
+            "m=16384,t=32,p=1",
+            "$argon2i$v=19$m=16384,t=32,p=1",
+            "$argon2id$v=16$m=16384,t=32,p=1",
+            "$argon2id$v=19$m=16384,t=0,p=1",
+            "$argon2id$v=19$m=16384,t=32,p=1$ICEiIyQlJicoKSorLC0uLw",
+        ] {
+            assert!(encoded.parse::<Argon2Params>().is_err(), "{encoded}");
+        }
+    }
+}
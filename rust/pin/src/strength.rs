@@ -0,0 +1,240 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Estimating how easy a PIN is to guess.
+//!
+//! An attacker trying PINs against a Secure Value Recovery service only gets a
+//! handful of guesses, so what matters is not the number of possible PINs but
+//! whether this one is among the first that would be tried: common PINs,
+//! repeated or sequential digits, keypad patterns, and dates.
+
+/// How guessable a PIN is, from worst to best.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PinStrengthLevel {
+    /// Among the first PINs an attacker would try.
+    VeryWeak,
+    /// Follows a pattern that narrows down the search considerably.
+    Weak,
+    /// No known pattern, but a small search space.
+    Acceptable,
+    /// No known pattern and a large search space.
+    Strong,
+}
+
+/// A pattern found in a PIN that makes it easier to guess.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PinWeakness {
+    /// Shorter than [`MIN_PIN_LENGTH`].
+    TooShort,
+    /// One of the most commonly chosen PINs.
+    Common,
+    /// A short group of characters repeated, like `1111` or `1212`.
+    Repeated,
+    /// Consecutive characters in order, like `1234`, `9876`, or `abcd`.
+    Sequence,
+    /// A straight line on a phone keypad, like `2580`.
+    KeypadPattern,
+    /// Looks like a date or a year, like `1987` or `311299`.
+    Date,
+}
+
+/// The result of [`estimate_strength`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PinStrength {
+    pub level: PinStrengthLevel,
+    /// Every weakness that was found, in the order they're listed in
+    /// [`PinWeakness`].
+    pub weaknesses: Vec<PinWeakness>,
+}
+
+/// The shortest PIN that isn't considered [`PinWeakness::TooShort`].
+pub const MIN_PIN_LENGTH: usize = 4;
+
+/// The numeric PIN length at which a PIN with no weaknesses is considered
+/// [`PinStrengthLevel::Strong`].
+const STRONG_NUMERIC_PIN_LENGTH: usize = 6;
+
+/// The most commonly chosen PINs, one per line, most common first within each
+/// length.
+///
+/// The four-digit PINs are the top twenty from DataGenetics' analysis of 3.4
+/// million leaked PINs (<https://www.datagenetics.com/blog/september32012/>);
+/// the six-digit PINs are the most frequent ones reported by Wang et al.,
+/// "Understanding Human-Chosen PINs: Characteristics, Distribution and
+/// Security" (AsiaCCS 2017). Many also match a pattern below, but are listed
+/// anyway so that the list can be checked on its own.
+const COMMON_PINS: &str = include_str!("common_pins.txt");
+
+/// Lines on a 3x4 phone keypad, in one direction. Patterns are also checked in
+/// reverse.
+const KEYPAD_LINES: &[&str] = &["2580", "147", "369", "159", "357", "741852963", "147258369"];
+
+impl PinStrength {
+    /// Whether the PIN should be accepted without a warning.
+    pub fn is_acceptable(&self) -> bool {
+        self.level >= PinStrengthLevel::Acceptable
+    }
+}
+
+/// Estimates how easy `pin` is to guess.
+///
+/// `pin` should be normalized as described in [`crate::PinHash`] first, so
+/// that the result matches what will actually be hashed. Letters are compared
+/// case-insensitively.
+pub fn estimate_strength(pin: &str) -> PinStrength {
+    let chars: Vec<char> = pin.chars().flat_map(char::to_lowercase).collect();
+    let is_numeric = chars.iter().all(char::is_ascii_digit);
+
+    let checks: [(PinWeakness, bool); 6] = [
+        (PinWeakness::TooShort, chars.len() < MIN_PIN_LENGTH),
+        (PinWeakness::Common, is_common(&chars)),
+        (PinWeakness::Repeated, is_repeated(&chars)),
+        (PinWeakness::Sequence, is_sequence(&chars)),
+        (
+            PinWeakness::KeypadPattern,
+            is_numeric && is_keypad_pattern(&chars),
+        ),
+        (PinWeakness::Date, is_numeric && is_date(&chars)),
+    ];
+    let weaknesses: Vec<PinWeakness> = checks
+        .into_iter()
+        .filter_map(|(weakness, found)| found.then_some(weakness))
+        .collect();
+
+    let level = if weaknesses.iter().any(|w| *w != PinWeakness::Date) {
+        PinStrengthLevel::VeryWeak
+    } else if !weaknesses.is_empty() {
+        PinStrengthLevel::Weak
+    } else if is_numeric && chars.len() < STRONG_NUMERIC_PIN_LENGTH {
+        PinStrengthLevel::Acceptable
+    } else {
+        PinStrengthLevel::Strong
+    };
+
+    PinStrength { level, weaknesses }
+}
+
+fn is_common(chars: &[char]) -> bool {
+    COMMON_PINS
+        .lines()
+        .any(|common| common.chars().eq(chars.iter().copied()))
+}
+
+/// Checks whether `chars` is a shorter group repeated at least twice.
+fn is_repeated(chars: &[char]) -> bool {
+    (1..=chars.len() / 2).any(|period| {
+        chars.len() % period == 0 && chars.iter().zip(&chars[period..]).all(|(a, b)| a == b)
+    })
+}
+
+/// Checks whether every character is one more (or one less) than the last.
+fn is_sequence(chars: &[char]) -> bool {
+    let steps: Vec<i64> = chars
+        .windows(2)
+        .map(|pair| i64::from(u32::from(pair[1])) - i64::from(u32::from(pair[0])))
+        .collect();
+    !steps.is_empty() && (steps.iter().all(|s| *s == 1) || steps.iter().all(|s| *s == -1))
+}
+
+fn is_keypad_pattern(chars: &[char]) -> bool {
+    let pin: String = chars.iter().collect();
+    let reversed: String = chars.iter().rev().collect();
+    KEYPAD_LINES
+        .iter()
+        .any(|line| chars.len() >= 3 && (line.contains(&pin) || line.contains(&reversed)))
+}
+
+fn is_date(chars: &[char]) -> bool {
+    let digits: Vec<u32> = chars.iter().filter_map(|c| c.to_digit(10)).collect();
+    let number =
+        |range: std::ops::Range<usize>| digits[range].iter().fold(0, |acc, d| acc * 10 + d);
+    let is_day_month = |day: u32, month: u32| (1..=12).contains(&month) && (1..=31).contains(&day);
+    let is_year = |year: u32| (1900..=2099).contains(&year);
+
+    match digits.len() {
+        // MMDD, DDMM, or YYYY
+        4 => {
+            is_day_month(number(0..2), number(2..4))
+                || is_day_month(number(2..4), number(0..2))
+                || is_year(number(0..4))
+        }
+        // DDMMYY, MMDDYY, or YYMMDD
+        6 => {
+            is_day_month(number(0..2), number(2..4))
+                || is_day_month(number(2..4), number(0..2))
+                || is_day_month(number(4..6), number(2..4))
+        }
+        // DDMMYYYY, MMDDYYYY, or YYYYMMDD
+        8 => {
+            (is_year(number(4..8))
+                && (is_day_month(number(0..2), number(2..4))
+                    || is_day_month(number(2..4), number(0..2))))
+                || (is_year(number(0..4)) && is_day_month(number(6..8), number(4..6)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_case::test_case;
+
+    use super::PinStrengthLevel::*;
+    use super::PinWeakness::*;
+    use super::*;
+
+    #[test_case("123", VeryWeak, &[TooShort, Sequence])]
+    #[test_case("1234", VeryWeak, &[Common, Sequence])]
+    #[test_case("0000", VeryWeak, &[Common, Repeated])]
+    #[test_case("1212", VeryWeak, &[Common, Repeated, Date])]
+    #[test_case("123123", VeryWeak, &[Common, Repeated, Date])]
+    #[test_case("9876", VeryWeak, &[Sequence])]
+    #[test_case("abcdef", VeryWeak, &[Sequence])]
+    #[test_case("2580", VeryWeak, &[KeypadPattern])]
+    #[test_case("0852", VeryWeak, &[KeypadPattern])]
+    #[test_case("6969", VeryWeak, &[Common, Repeated])]
+    #[test_case("1004", VeryWeak, &[Common, Date])]
+    #[test_case("Password", VeryWeak, &[Common])]
+    #[test_case("1987", Weak, &[Date])]
+    #[test_case("3112", Weak, &[Date])]
+    #[test_case("311299", Weak, &[Date])]
+    #[test_case("19870412", Weak, &[Date])]
+    #[test_case("8316", Acceptable, &[])]
+    #[test_case("83164", Acceptable, &[])]
+    #[test_case("831649", Strong, &[])]
+    #[test_case("correct horse", Strong, &[])]
+    fn estimates(pin: &str, level: PinStrengthLevel, weaknesses: &[PinWeakness]) {
+        assert_eq!(
+            estimate_strength(pin),
+            PinStrength {
+                level,
+                weaknesses: weaknesses.to_vec()
+            }
+        );
+    }
+
+    #[test_case("2000", &[Common, Date])]
+    #[test_case("520520", &[Common, Repeated, Date])]
+    #[test_case("147258", &[Common, KeypadPattern])]
+    #[test_case("iloveyou", &[Common])]
+    fn common(pin: &str, weaknesses: &[PinWeakness]) {
+        assert_eq!(estimate_strength(pin).weaknesses, weaknesses);
+    }
+
+    #[test]
+    fn common_pins_are_normalized() {
+        for pin in COMMON_PINS.lines() {
+            assert!(!pin.is_empty());
+            assert_eq!(pin, pin.trim().to_lowercase(), "{pin:?}");
+        }
+    }
+
+    #[test]
+    fn acceptable() {
+        assert!(!estimate_strength("1111").is_acceptable());
+        assert!(!estimate_strength("1987").is_acceptable());
+        assert!(estimate_strength("8316").is_acceptable());
+    }
+}
@@ -0,0 +1,179 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! The complete pipeline for protecting a master key with a PIN in SVR2.
+//!
+//! 1. The (normalized) PIN is hashed with [`Argon2Params::SVR`], using a salt
+//!    made by [`svr2_salt`] from the account's SVR2 username and the enclave's
+//!    group ID. See [`svr2_pin_hash`].
+//! 2. The resulting [`PinHash::access_key`] is stored in the enclave as the
+//!    password guarding the backup.
+//! 3. The master key is encrypted with [`PinHash::encrypt_master_key`], and the
+//!    48-byte result is stored in the enclave as the backup's value.
+//!
+//! Restoring reverses the last step with [`PinHash::decrypt_master_key`].
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use crate::{Argon2Params, Error, PinHash, Result};
+
+/// The size of the output of [`PinHash::encrypt_master_key`].
+pub const ENCRYPTED_MASTER_KEY_LEN: usize = 48;
+
+const SIV_IV_LEN: usize = 16;
+const AUTH_KEY_LABEL: &[u8] = b"auth";
+const ENCRYPTION_KEY_LABEL: &[u8] = b"enc";
+
+/// Creates the salt for hashing a PIN for SVR2.
+///
+/// The salt is HKDF-SHA256 with the big-endian encoding of `group_id` as the
+/// HKDF salt, `username` as the input key material, and no info, expanded to
+/// 32 bytes.
+///
+/// # Arguments
+/// * `username` - The Basic Auth username credential retrieved from the chat service and used to
+///   authenticate with the SVR service
+/// * `group_id` - The attested group id returned by the SVR service
+pub fn svr2_salt(username: &str, group_id: u64) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&group_id.to_be_bytes()), username.as_bytes())
+        .expand(&[], &mut out)
+        .expect("should expand");
+    out
+}
+
+/// Hashes a PIN for SVR2, combining [`svr2_salt`] and [`PinHash::create`].
+///
+/// # Arguments
+/// * `pin` - UTF-8 encoding of the pin. The pin *must* be normalized first.
+/// * `username` - As for [`svr2_salt`]
+/// * `group_id` - As for [`svr2_salt`]
+pub fn svr2_pin_hash(pin: &[u8], username: &str, group_id: u64) -> Result<PinHash> {
+    PinHash::create_with_params(pin, &svr2_salt(username, group_id), &Argon2Params::SVR)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Hmac<Sha256> {
+    let mut hmac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key size");
+    hmac.update(data);
+    hmac
+}
+
+fn hmac_sha256_bytes(key: &[u8], data: &[u8]) -> [u8; 32] {
+    hmac_sha256(key, data).finalize().into_bytes().into()
+}
+
+impl PinHash {
+    /// Encrypts a master key for storage in SVR2.
+    ///
+    /// This is a deterministic HMAC-SHA256-based SIV construction:
+    ///
+    /// - `K_a = HMAC(encryption_key, "auth")`, `K_e = HMAC(encryption_key, "enc")`
+    /// - `IV = HMAC(K_a, master_key)[..16]`
+    /// - `C = HMAC(K_e, IV) XOR master_key`
+    ///
+    /// The result is `IV || C`.
+    pub fn encrypt_master_key(&self, master_key: &[u8; 32]) -> [u8; ENCRYPTED_MASTER_KEY_LEN] {
+        let auth_key = hmac_sha256_bytes(&self.encryption_key, AUTH_KEY_LABEL);
+        let cipher_key = hmac_sha256_bytes(&self.encryption_key, ENCRYPTION_KEY_LABEL);
+
+        let mut out = [0u8; ENCRYPTED_MASTER_KEY_LEN];
+        let (iv, ciphertext) = out.split_at_mut(SIV_IV_LEN);
+        iv.copy_from_slice(&hmac_sha256_bytes(&auth_key, master_key)[..SIV_IV_LEN]);
+        let keystream = hmac_sha256_bytes(&cipher_key, iv);
+        for ((c, k), m) in ciphertext.iter_mut().zip(keystream).zip(master_key) {
+            *c = k ^ m;
+        }
+        out
+    }
+
+    /// Decrypts a master key produced by [`PinHash::encrypt_master_key`].
+    ///
+    /// Fails if `encrypted` wasn't produced with the same PIN hash.
+    pub fn decrypt_master_key(
+        &self,
+        encrypted: &[u8; ENCRYPTED_MASTER_KEY_LEN],
+    ) -> Result<[u8; 32]> {
+        let auth_key = hmac_sha256_bytes(&self.encryption_key, AUTH_KEY_LABEL);
+        let cipher_key = hmac_sha256_bytes(&self.encryption_key, ENCRYPTION_KEY_LABEL);
+
+        let (iv, ciphertext) = encrypted.split_at(SIV_IV_LEN);
+        let keystream = hmac_sha256_bytes(&cipher_key, iv);
+        let mut master_key = [0u8; 32];
+        for ((m, k), c) in master_key.iter_mut().zip(keystream).zip(ciphertext) {
+            *m = k ^ c;
+        }
+
+        hmac_sha256(&auth_key, &master_key)
+            .verify_truncated_left(iv)
+            .map_err(|_| Error::MasterKeyDecryptionFailed)?;
+        Ok(master_key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hex_literal::hex;
+
+    use super::*;
+
+    const USERNAME: &str = "username";
+    const GROUP_ID: u64 = 3862621253427332054;
+
+    #[test]
+    fn known_pipeline() {
+        assert_eq!(
+            svr2_salt(USERNAME, GROUP_ID),
+            hex!("d6159ba30f90b6eb6ccf1ec844427f052baaf0705da849767471744cdb3f8a5e"),
+        );
+        assert_eq!(
+            svr2_salt(USERNAME, GROUP_ID),
+            PinHash::make_salt(USERNAME, GROUP_ID)
+        );
+
+        let hash = svr2_pin_hash(b"1234", USERNAME, GROUP_ID).expect("should hash");
+        assert_eq!(
+            hash.encryption_key,
+            hex!("d4b370cb80b309fcc4098f6e962dfe2d5aa062d4a3199bf1c83710547d02640b")
+        );
+        assert_eq!(
+            hash.access_key,
+            hex!("682eb6f2377080c0bc44289526be9f990d423d848431e412303bd01fa26fd5ae")
+        );
+
+        let master_key = hex!("404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f");
+        let encrypted = hash.encrypt_master_key(&master_key);
+        assert_eq!(
+            encrypted,
+            hex!("dd9f44ffd490288e7c0718db357e881b1327156af56c803d273b92e3fae0c4bb595df3454bf00b61b3985798322e4e01")
+        );
+        assert_eq!(hash.decrypt_master_key(&encrypted), Ok(master_key));
+    }
+
+    #[test]
+    fn decrypt_rejects_wrong_key() {
+        let hash = PinHash {
+            encryption_key: [1; 32],
+            access_key: [2; 32],
+        };
+        let other = PinHash {
+            encryption_key: [3; 32],
+            access_key: [2; 32],
+        };
+        let encrypted = hash.encrypt_master_key(&[4; 32]);
+        assert_eq!(
+            other.decrypt_master_key(&encrypted),
+            Err(Error::MasterKeyDecryptionFailed)
+        );
+
+        let mut modified = encrypted;
+        modified[20] ^= 1;
+        assert_eq!(
+            hash.decrypt_master_key(&modified),
+            Err(Error::MasterKeyDecryptionFailed)
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::scalar::Scalar;
+use poksho::{BatchVerifier, PointArgs, ScalarArgs};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+fn random_scalar() -> Scalar {
+    let mut bytes = [0; 64];
+    OsRng.fill_bytes(&mut bytes);
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+fn bench_batch_verification(c: &mut Criterion) {
+    let st = poksho::statement! {
+        scalars: [a],
+        points: [A, B, H],
+        A = a * G;
+        B = a * H;
+    };
+
+    let mut group = c.benchmark_group("verify");
+    for batch_size in [1, 8, 64] {
+        let inputs: Vec<(PointArgs, Vec<u8>, Vec<u8>)> = (0..batch_size)
+            .map(|_| {
+                let a = random_scalar();
+                let h = random_scalar() * RISTRETTO_BASEPOINT_POINT;
+                let mut scalar_args = ScalarArgs::new();
+                scalar_args.add("a", a);
+                let mut point_args = PointArgs::new();
+                point_args.add("A", a * RISTRETTO_BASEPOINT_POINT);
+                point_args.add("B", a * h);
+                point_args.add("H", h);
+                let mut randomness = [0; 32];
+                OsRng.fill_bytes(&mut randomness);
+
+                let proof = st
+                    .prove(&scalar_args, &point_args, b"message", &randomness)
+                    .expect("valid proof");
+                let batchable_proof = st
+                    .prove_batchable(&scalar_args, &point_args, b"message", &randomness)
+                    .expect("valid proof");
+                (point_args, proof, batchable_proof)
+            })
+            .collect();
+
+        group.bench_function(BenchmarkId::new("individually", batch_size), |b| {
+            b.iter(|| {
+                for (point_args, proof, _) in &inputs {
+                    st.verify_proof(proof, point_args, b"message")
+                        .expect("valid");
+                }
+            })
+        });
+
+        group.bench_function(BenchmarkId::new("batched", batch_size), |b| {
+            b.iter(|| {
+                let mut batch = BatchVerifier::new();
+                for (point_args, _, batchable_proof) in &inputs {
+                    batch.add(&st, batchable_proof, point_args, b"message");
+                }
+                batch.verify().expect("valid");
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_batch_verification);
+criterion_main!(benches);
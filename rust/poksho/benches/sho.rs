@@ -61,9 +61,15 @@ fn bench_poksho_api_mac_sha256(c: &mut Criterion) {
     bench_poksho_api::<poksho::ShoHmacSha256, _>(&mut group);
 }
 
+fn bench_poksho_api_shake256(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ShoShake256");
+    bench_poksho_api::<poksho::ShoShake256, _>(&mut group);
+}
+
 criterion_group!(
     benches,
     bench_poksho_api_sha256,
-    bench_poksho_api_mac_sha256
+    bench_poksho_api_mac_sha256,
+    bench_poksho_api_shake256
 );
 criterion_main!(benches);
@@ -0,0 +1,314 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+// Verification of many proofs at once.
+//
+// Textbook batch verification of Schnorr proofs checks a random linear combination of the
+// verification equations R_i = F_i(s_i) - h_i*A_i, which requires the commitments R_i. Compact
+// POKSHO proofs contain the challenge h_i instead, so BatchVerifier takes proofs created with
+// Statement::prove_batchable, which send R_i in its place. The challenges are then just hashes,
+// and every equation of every proof is folded into a single multiscalar multiplication:
+//
+//   sum_i sum_j z_ij * (F_i(s_i) - h_i*A_i - R_i)_j == 0
+//
+// The weights z_ij are 128-bit scalars derived by hashing the whole batch, so they can't be known
+// before every proof in it has been fixed; a batch containing an invalid proof passes with
+// probability about 2^-128. Terms on the base point are merged across proofs. As in
+// Statement::verify_proof, constant-time arithmetic is used throughout, since some points passed
+// to a verifier may be derived from secrets.
+//
+// If the combined check fails, each proof is checked on its own to find the ones that failed.
+
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{IsIdentity, MultiscalarMul};
+
+use crate::args::PointArgs;
+use crate::errors::PokshoError;
+use crate::shoapi::ShoApi;
+use crate::shohmacsha256::ShoHmacSha256;
+use crate::statement::{PreparedProof, Statement};
+
+const WEIGHTS_LABEL: &[u8] = b"POKSHO_Ristretto_SHOHMACSHA256_BatchWeights";
+
+struct BatchItem<'a> {
+    statement: &'a Statement,
+    proof: &'a [u8],
+    point_args: &'a PointArgs,
+    message: &'a [u8],
+}
+
+/// Collects proofs to be verified together with [`BatchVerifier::verify`].
+#[derive(Default)]
+pub struct BatchVerifier<'a> {
+    items: Vec<BatchItem<'a>>,
+}
+
+/// The items in a batch that failed to verify.
+#[derive(Debug)]
+pub struct BatchVerificationFailure {
+    /// The index of each failed item, in the order it was added, along with the reason it failed.
+    pub failures: Vec<(usize, PokshoError)>,
+}
+
+impl<'a> BatchVerifier<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a proof created by [`Statement::prove_batchable`] to the batch; the arguments are the
+    /// same as for [`Statement::verify_batchable_proof`].
+    ///
+    /// Returns the index of the item in the batch.
+    pub fn add(
+        &mut self,
+        statement: &'a Statement,
+        proof: &'a [u8],
+        point_args: &'a PointArgs,
+        message: &'a [u8],
+    ) -> usize {
+        self.items.push(BatchItem {
+            statement,
+            proof,
+            point_args,
+            message,
+        });
+        self.items.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Verifies every proof in the batch.
+    ///
+    /// An empty batch always succeeds.
+    pub fn verify(&self) -> Result<(), BatchVerificationFailure> {
+        let sho = Statement::protocol_sho();
+        let mut failures = vec![];
+        let mut prepared: Vec<(usize, &BatchItem, PreparedProof)> = vec![];
+        for (i, item) in self.items.iter().enumerate() {
+            let BatchItem {
+                statement,
+                proof,
+                point_args,
+                message,
+            } = item;
+            match statement.prepare_batchable_proof(sho.clone(), proof, point_args, message) {
+                Ok(p) => prepared.push((i, item, p)),
+                Err(e) => failures.push((i, e)),
+            }
+        }
+
+        if !Self::check_combined(&prepared) {
+            failures.extend(prepared.iter().filter_map(|(i, item, p)| {
+                item.statement
+                    .check_batchable_proof(p)
+                    .err()
+                    .map(|e| (*i, e))
+            }));
+            failures.sort_by_key(|(i, _)| *i);
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(BatchVerificationFailure { failures })
+        }
+    }
+
+    /// Checks a random linear combination of the verification equations of every proof.
+    fn check_combined(prepared: &[(usize, &BatchItem, PreparedProof)]) -> bool {
+        // The recomputed challenges bind each statement, its points, and its commitments; the
+        // responses are bound by the proofs themselves.
+        let mut weights_sho = ShoHmacSha256::new(WEIGHTS_LABEL);
+        let mut num_weights = 0;
+        for (_, item, p) in prepared {
+            weights_sho.absorb(p.challenge().as_bytes());
+            weights_sho.absorb(item.proof);
+            num_weights += item.statement.num_equations();
+        }
+        weights_sho.ratchet();
+        let weights: Vec<Scalar> = weights_sho
+            .squeeze_and_ratchet(num_weights * 16)
+            .chunks_exact(16)
+            .map(|chunk| {
+                Scalar::from(u128::from_le_bytes(
+                    chunk.try_into().expect("correct length"),
+                ))
+            })
+            .collect();
+
+        let mut base_point_scalar = Scalar::ZERO;
+        let mut scalars = vec![];
+        let mut points = vec![];
+        let mut remaining_weights = &weights[..];
+        for (_, item, p) in prepared {
+            let (item_weights, rest) = remaining_weights.split_at(item.statement.num_equations());
+            remaining_weights = rest;
+            item.statement.add_weighted_terms(
+                p,
+                item_weights,
+                &mut base_point_scalar,
+                &mut scalars,
+                &mut points,
+            );
+        }
+
+        RistrettoPoint::multiscalar_mul(
+            std::iter::once(base_point_scalar).chain(scalars),
+            std::iter::once(RISTRETTO_BASEPOINT_POINT).chain(points),
+        )
+        .is_identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(non_snake_case)]
+
+    use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+    use curve25519_dalek::scalar::Scalar;
+
+    use super::*;
+    use crate::args::ScalarArgs;
+
+    fn signature_statement() -> Statement {
+        let mut st = Statement::new();
+        st.add("A", &[("a", "G")]);
+        st
+    }
+
+    fn keypair(seed: u8) -> (ScalarArgs, PointArgs) {
+        let a = Scalar::from(u64::from(seed) + 1);
+        let mut scalar_args = ScalarArgs::new();
+        scalar_args.add("a", a);
+        let mut point_args = PointArgs::new();
+        point_args.add("A", a * RISTRETTO_BASEPOINT_POINT);
+        (scalar_args, point_args)
+    }
+
+    #[test]
+    fn batch_reports_failing_items() {
+        let st = signature_statement();
+        let keys: Vec<_> = (0..4).map(keypair).collect();
+        let proofs: Vec<Vec<u8>> = keys
+            .iter()
+            .map(|(scalar_args, point_args)| {
+                st.prove_batchable(scalar_args, point_args, b"message", &[0; 32])
+                    .expect("valid proof")
+            })
+            .collect();
+
+        let mut batch = BatchVerifier::new();
+        assert!(batch.verify().is_ok());
+        for ((_, point_args), proof) in keys.iter().zip(&proofs) {
+            batch.add(&st, proof, point_args, b"message");
+        }
+        assert_eq!(batch.len(), 4);
+        batch.verify().expect("all valid");
+
+        // Swap the keys for items 1 and 2, and use a different message for item 3.
+        let mut batch = BatchVerifier::new();
+        batch.add(&st, &proofs[0], &keys[0].1, b"message");
+        batch.add(&st, &proofs[1], &keys[2].1, b"message");
+        batch.add(&st, &proofs[2], &keys[1].1, b"message");
+        batch.add(&st, &proofs[3], &keys[3].1, b"other message");
+        let failure = batch.verify().expect_err("some invalid");
+        let failed: Vec<usize> = failure.failures.iter().map(|(i, _)| *i).collect();
+        assert_eq!(failed, [1, 2, 3]);
+        assert!(failure
+            .failures
+            .iter()
+            .all(|(_, e)| matches!(e, PokshoError::VerificationFailure)));
+    }
+
+    #[test]
+    fn batch_with_several_statements() {
+        let sig = signature_statement();
+        let dleq = crate::statement! {
+            scalars: [a],
+            points: [A, B, H],
+            A = a * G;
+            B = a * H;
+        };
+
+        let (scalar_args, mut dleq_points) = keypair(3);
+        let H = Scalar::from(99u64) * RISTRETTO_BASEPOINT_POINT;
+        dleq_points.add("H", H);
+        dleq_points.add("B", Scalar::from(4u64) * H);
+        let dleq_proof = dleq
+            .prove_batchable(&scalar_args, &dleq_points, b"dleq", &[2; 32])
+            .expect("valid proof");
+
+        let (scalar_args, sig_points) = keypair(5);
+        let sig_proof = sig
+            .prove_batchable(&scalar_args, &sig_points, b"sig", &[3; 32])
+            .expect("valid proof");
+
+        let mut batch = BatchVerifier::new();
+        batch.add(&dleq, &dleq_proof, &dleq_points, b"dleq");
+        batch.add(&sig, &sig_proof, &sig_points, b"sig");
+        batch.verify().expect("all valid");
+
+        // Checking a proof against the wrong statement fails without affecting the other item.
+        let mut batch = BatchVerifier::new();
+        batch.add(&sig, &dleq_proof, &dleq_points, b"dleq");
+        batch.add(&sig, &sig_proof, &sig_points, b"sig");
+        let failure = batch.verify().expect_err("some invalid");
+        assert_eq!(failure.failures.len(), 1);
+        assert_eq!(failure.failures[0].0, 0);
+
+        // A response that has been tampered with is caught by the combined check, since the
+        // challenge doesn't cover it.
+        let mut tampered = sig_proof.clone();
+        tampered[32] ^= 1;
+        let mut batch = BatchVerifier::new();
+        batch.add(&dleq, &dleq_proof, &dleq_points, b"dleq");
+        batch.add(&sig, &tampered, &sig_points, b"sig");
+        let failure = batch.verify().expect_err("some invalid");
+        let failed: Vec<usize> = failure.failures.iter().map(|(i, _)| *i).collect();
+        assert_eq!(failed, [1]);
+    }
+
+    #[test]
+    fn compact_proofs_are_not_batchable() {
+        let st = signature_statement();
+        let (scalar_args, point_args) = keypair(1);
+        let proof = st
+            .prove(&scalar_args, &point_args, b"", &[1; 32])
+            .expect("valid proof");
+
+        let mut batch = BatchVerifier::new();
+        batch.add(&st, &proof, &point_args, b"");
+        assert!(batch.verify().is_err());
+    }
+
+    #[test]
+    fn batch_matches_individual_verification() {
+        let st = signature_statement();
+        let (scalar_args, point_args) = keypair(7);
+        let proof = st
+            .prove_batchable(&scalar_args, &point_args, b"", &[1; 32])
+            .expect("valid proof");
+
+        let mut batch = BatchVerifier::new();
+        batch.add(&st, &proof, &point_args, b"");
+        assert!(batch.verify().is_ok());
+        assert!(st.verify_batchable_proof(&proof, &point_args, b"").is_ok());
+
+        let mut batch = BatchVerifier::new();
+        batch.add(&st, &proof[..32], &point_args, b"");
+        assert!(batch.verify().is_err());
+        assert!(st
+            .verify_batchable_proof(&proof[..32], &point_args, b"")
+            .is_err());
+    }
+}
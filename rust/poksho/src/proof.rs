@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use curve25519_dalek::ristretto::CompressedRistretto;
 use curve25519_dalek::scalar::Scalar;
 
 use crate::scalar::*;
@@ -41,3 +42,51 @@ impl Proof {
         bytes
     }
 }
+
+/// A Schnorr proof that sends the commitments instead of the challenge.
+///
+/// This is larger than [`Proof`] (one point per equation instead of a single scalar), but the
+/// verification equations can be checked directly, so many proofs can be verified at once with
+/// [`crate::BatchVerifier`].
+pub struct BatchableProof {
+    pub commitment: G2,
+    pub response: G1,
+}
+
+impl BatchableProof {
+    /// Parses a proof for a statement with `num_equations` equations.
+    pub fn from_slice(bytes: &[u8], num_equations: usize) -> Option<Self> {
+        let num_elements = bytes.len() / 32;
+        if num_elements * 32 != bytes.len()
+            || !(num_equations + 1..=num_equations + 256).contains(&num_elements)
+        {
+            return None;
+        }
+        let (commitment_bytes, response_bytes) = bytes.split_at(num_equations * 32);
+
+        let commitment = commitment_bytes
+            .chunks_exact(32)
+            .map(|chunk| CompressedRistretto::from_slice(chunk).ok()?.decompress())
+            .collect::<Option<G2>>()?;
+        let response = response_bytes
+            .chunks_exact(32)
+            .map(scalar_from_slice_canonical)
+            .collect::<Option<G1>>()?;
+        Some(BatchableProof {
+            commitment,
+            response,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::<u8>::with_capacity((self.commitment.len() + self.response.len()) * 32);
+        for point in &self.commitment {
+            bytes.extend_from_slice(point.compress().as_bytes());
+        }
+        for scalar in &self.response {
+            bytes.extend_from_slice(scalar.as_bytes());
+        }
+        bytes
+    }
+}
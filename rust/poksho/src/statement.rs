@@ -99,6 +99,21 @@ use crate::shoapi::ShoApi;
 use crate::shohmacsha256::ShoHmacSha256;
 use crate::simple_types::*;
 
+const PROTOCOL_LABEL: &[u8] = b"POKSHO_Ristretto_SHOHMACSHA256";
+
+/// A [`BatchableProof`] along with its recomputed challenge and the points it's checked against.
+pub(crate) struct PreparedProof {
+    proof: BatchableProof,
+    challenge: Scalar,
+    all_points: Vec<RistrettoPoint>,
+}
+
+impl PreparedProof {
+    pub(crate) fn challenge(&self) -> &Scalar {
+        &self.challenge
+    }
+}
+
 type ScalarIndex = u8;
 type PointIndex = u8;
 
@@ -178,6 +193,56 @@ impl Statement {
         message: &[u8],
         randomness: &[u8], // must be 32 bytes
     ) -> Result<Vec<u8>, PokshoError> {
+        let (_commitment, challenge, response) =
+            self.prove_impl(scalar_args, point_args, message, randomness)?;
+        let proof = Proof {
+            challenge,
+            response,
+        };
+
+        // Verify before returning, since a bad proof could indicate
+        // a glitched/faulty response that leaks private keys, or incorrect inputs
+        let proof_bytes = proof.to_bytes();
+        match self.verify_proof(&proof_bytes, point_args, message) {
+            Err(VerificationFailure) => Err(ProofCreationVerificationFailure),
+            Err(e) => Err(e),
+            Ok(_) => Ok(proof_bytes),
+        }
+    }
+
+    /// Like [`Self::prove`], but produces a [`BatchableProof`], which can be verified with
+    /// [`Self::verify_batchable_proof`] or as part of a [`crate::BatchVerifier`].
+    pub fn prove_batchable(
+        &self,
+        scalar_args: &ScalarArgs,
+        point_args: &PointArgs,
+        message: &[u8],
+        randomness: &[u8], // must be 32 bytes
+    ) -> Result<Vec<u8>, PokshoError> {
+        let (commitment, _challenge, response) =
+            self.prove_impl(scalar_args, point_args, message, randomness)?;
+        let proof = BatchableProof {
+            commitment,
+            response,
+        };
+
+        // Verify before returning, as in prove()
+        let proof_bytes = proof.to_bytes();
+        match self.verify_batchable_proof(&proof_bytes, point_args, message) {
+            Err(VerificationFailure) => Err(ProofCreationVerificationFailure),
+            Err(e) => Err(e),
+            Ok(_) => Ok(proof_bytes),
+        }
+    }
+
+    // Computes the commitment, challenge, and response shared by both proof encodings
+    fn prove_impl(
+        &self,
+        scalar_args: &ScalarArgs,
+        point_args: &PointArgs,
+        message: &[u8],
+        randomness: &[u8],
+    ) -> Result<(G2, Scalar, G1), PokshoError> {
         if randomness.len() != 32 {
             return Err(PokshoError::BadArgs);
         }
@@ -185,7 +250,7 @@ impl Statement {
         let all_points = self.sort_points(point_args)?;
 
         // Absorb the protocol label L, description of statement D, and point values A
-        let mut sho = ShoHmacSha256::new(b"POKSHO_Ristretto_SHOHMACSHA256"); // L
+        let mut sho = Self::protocol_sho(); // L
         sho.absorb(&self.to_bytes()); // D
         for point in &all_points {
             // A
@@ -228,19 +293,7 @@ impl Statement {
             response.push(nonce[i] + (g1[i] * challenge));
         }
 
-        let proof = Proof {
-            challenge,
-            response,
-        };
-
-        // Verify before returning, since a bad proof could indicate
-        // a glitched/faulty response that leaks private keys, or incorrect inputs
-        let proof_bytes = proof.to_bytes();
-        match self.verify_proof(&proof_bytes, point_args, message) {
-            Err(VerificationFailure) => Err(ProofCreationVerificationFailure),
-            Err(e) => Err(e),
-            Ok(_) => Ok(proof_bytes),
-        }
+        Ok((commitment, challenge, response))
     }
 
     pub fn verify_proof(
@@ -248,6 +301,23 @@ impl Statement {
         proof_bytes: &[u8],
         point_args: &PointArgs,
         message: &[u8],
+    ) -> Result<(), PokshoError> {
+        self.verify_proof_with_sho(Self::protocol_sho(), proof_bytes, point_args, message)
+    }
+
+    /// The SHO state after absorbing the protocol label L, which is the same for every proof.
+    pub(crate) fn protocol_sho() -> ShoHmacSha256 {
+        ShoHmacSha256::new(PROTOCOL_LABEL)
+    }
+
+    /// Like [`Self::verify_proof`], but starting from a copy of [`Self::protocol_sho`] that the
+    /// caller has already computed.
+    pub(crate) fn verify_proof_with_sho(
+        &self,
+        mut sho: ShoHmacSha256,
+        proof_bytes: &[u8],
+        point_args: &PointArgs,
+        message: &[u8],
     ) -> Result<(), PokshoError> {
         let proof = Proof::from_slice(proof_bytes).ok_or(VerificationFailure)?;
         if proof.response.len() != self.scalar_vec.len() {
@@ -255,8 +325,8 @@ impl Statement {
         }
         let all_points = self.sort_points(point_args)?;
 
-        // Absorb the protocol label L, statement description D, and point values A
-        let mut sho = ShoHmacSha256::new(b"POKSHO_Ristretto_SHOHMACSHA256"); // L
+        // Absorb the statement description D and point values A
+        // (the protocol label L has already been absorbed)
         sho.absorb(&self.to_bytes()); // D
         for point in &all_points {
             // A
@@ -291,6 +361,120 @@ impl Statement {
         }
     }
 
+    /// Verifies a proof created by [`Self::prove_batchable`] on its own.
+    pub fn verify_batchable_proof(
+        &self,
+        proof_bytes: &[u8],
+        point_args: &PointArgs,
+        message: &[u8],
+    ) -> Result<(), PokshoError> {
+        let prepared =
+            self.prepare_batchable_proof(Self::protocol_sho(), proof_bytes, point_args, message)?;
+        self.check_batchable_proof(&prepared)
+    }
+
+    /// Parses a [`BatchableProof`] and recomputes its challenge, which is everything that's needed
+    /// to check its verification equations.
+    ///
+    /// `sho` is a copy of [`Self::protocol_sho`].
+    pub(crate) fn prepare_batchable_proof(
+        &self,
+        mut sho: ShoHmacSha256,
+        proof_bytes: &[u8],
+        point_args: &PointArgs,
+        message: &[u8],
+    ) -> Result<PreparedProof, PokshoError> {
+        let proof = BatchableProof::from_slice(proof_bytes, self.equations.len())
+            .ok_or(VerificationFailure)?;
+        if proof.response.len() != self.scalar_vec.len() {
+            return Err(VerificationFailure);
+        }
+        let all_points = self.sort_points(point_args)?;
+
+        // Absorb the statement description D and point values A
+        // (the protocol label L has already been absorbed)
+        sho.absorb(&self.to_bytes()); // D
+        for point in &all_points {
+            // A
+            sho.absorb(&point.compress().to_bytes());
+        }
+        sho.ratchet();
+
+        // Reconstruct challenge from commitment and message
+        // The commitment is at the start of the proof, already compressed
+        sho.absorb(&proof_bytes[..self.equations.len() * 32]); // R
+        sho.absorb_and_ratchet(message); // M
+        let challenge = scalar_from_slice_wide(&sho.squeeze_and_ratchet(64));
+
+        Ok(PreparedProof {
+            proof,
+            challenge,
+            all_points,
+        })
+    }
+
+    /// Checks the verification equations R = F(s) - h*A of a single proof.
+    pub(crate) fn check_batchable_proof(
+        &self,
+        prepared: &PreparedProof,
+    ) -> Result<(), PokshoError> {
+        let PreparedProof {
+            proof,
+            challenge,
+            all_points,
+        } = prepared;
+        let commitment =
+            self.homomorphism_with_subtraction(&proof.response, all_points, Some(*challenge));
+        if commitment == proof.commitment {
+            Ok(())
+        } else {
+            Err(VerificationFailure)
+        }
+    }
+
+    /// Appends the terms of `weight_j * (F(s) - h*A - R)_j` for each equation j to `scalars` and
+    /// `points`, so that a random linear combination of many proofs' verification equations can
+    /// be checked with a single multiscalar multiplication.
+    ///
+    /// Terms on the base point G are added to `base_point_scalar` instead, since they can be
+    /// combined across proofs.
+    pub(crate) fn add_weighted_terms(
+        &self,
+        prepared: &PreparedProof,
+        weights: &[Scalar],
+        base_point_scalar: &mut Scalar,
+        scalars: &mut Vec<Scalar>,
+        points: &mut Vec<RistrettoPoint>,
+    ) {
+        assert_eq!(weights.len(), self.equations.len());
+        let PreparedProof {
+            proof,
+            challenge,
+            all_points,
+        } = prepared;
+        for ((e, weight), commitment) in self.equations.iter().zip(weights).zip(&proof.commitment) {
+            let terms = e
+                .rhs
+                .iter()
+                .map(|Term { scalar, point }| (weight * proof.response[*scalar as usize], *point))
+                .chain(std::iter::once((-(weight * challenge), e.lhs)));
+            for (scalar, point) in terms {
+                if point == 0 {
+                    *base_point_scalar += scalar;
+                } else {
+                    scalars.push(scalar);
+                    points.push(all_points[point as usize]);
+                }
+            }
+            scalars.push(-weight);
+            points.push(*commitment);
+        }
+    }
+
+    pub(crate) fn num_equations(&self) -> usize {
+        self.equations.len()
+    }
+
     fn add_scalar(
         &mut self,
         scalar_name: impl Into<Cow<'static, str>>,
@@ -435,12 +619,112 @@ impl Default for Statement {
     }
 }
 
+#[doc(hidden)]
+pub mod __private {
+    /// A scalar declared in [`crate::statement!`].
+    #[derive(Clone, Copy)]
+    pub struct ScalarName(pub &'static str);
+
+    /// A point declared in [`crate::statement!`].
+    #[derive(Clone, Copy)]
+    pub struct PointName(pub &'static str);
+}
+
+/// Builds a [`Statement`] whose variable names are checked at compile time.
+///
+/// Every scalar and point has to be declared before it is used in an equation; the base point `G`
+/// is always available. Using an undeclared name, or a scalar where a point is expected (or vice
+/// versa), is a compile error rather than a proof that never verifies.
+///
+/// ```
+/// let st = poksho::statement! {
+///     scalars: [a],
+///     points: [A, B, H],
+///     A = a * G;
+///     B = a * H;
+/// };
+///
+/// // equivalent to:
+/// let mut st = poksho::Statement::new();
+/// st.add("A", &[("a", "G")]);
+/// st.add("B", &[("a", "H")]);
+/// ```
+///
+/// ```compile_fail
+/// let st = poksho::statement! {
+///     scalars: [a],
+///     points: [A],
+///     A = b * G; // `b` was never declared
+/// };
+/// ```
+///
+/// ```compile_fail
+/// let st = poksho::statement! {
+///     scalars: [a],
+///     points: [A],
+///     A = G * a; // terms are written scalar * point
+/// };
+/// ```
+///
+/// Statements with names that aren't valid identifiers, or with a number of terms only known at
+/// runtime, can still be built with [`Statement::add`].
+#[macro_export]
+macro_rules! statement {
+    (@point G) => {
+        $crate::statement::__private::PointName("G")
+    };
+    (@point $p:ident) => {{
+        let point: $crate::statement::__private::PointName = $p;
+        point
+    }};
+    (@term $s:ident * $p:ident) => {{
+        let scalar: $crate::statement::__private::ScalarName = $s;
+        (scalar.0, $crate::statement!(@point $p).0)
+    }};
+    (
+        scalars: [$($scalar:ident),* $(,)?],
+        points: [$($point:ident),* $(,)?],
+        $($lhs:ident = $s0:ident * $p0:ident $(+ $s:ident * $p:ident)*;)+
+    ) => {{
+        $(
+            #[allow(non_snake_case, unused_variables)]
+            let $scalar = $crate::statement::__private::ScalarName(stringify!($scalar));
+        )*
+        $(
+            #[allow(non_snake_case, unused_variables)]
+            let $point = $crate::statement::__private::PointName(stringify!($point));
+        )*
+        let mut st = $crate::Statement::new();
+        $(
+            st.add($crate::statement!(@point $lhs).0, &[
+                $crate::statement!(@term $s0 * $p0),
+                $($crate::statement!(@term $s * $p)),*
+            ]);
+        )+
+        st
+    }};
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(non_snake_case)]
 
     use super::*;
 
+    #[test]
+    fn test_statement_macro() {
+        let st = crate::statement! {
+            scalars: [a, b],
+            points: [A, B, H],
+            A = a * G;
+            B = a * H + b * G;
+        };
+        let mut expected = Statement::new();
+        expected.add("A", &[("a", "G")]);
+        expected.add("B", &[("a", "H"), ("b", "G")]);
+        assert_eq!(st.to_bytes(), expected.to_bytes());
+    }
+
     #[test]
     fn test_statement_encoding() {
         let mut s = Statement::new();
@@ -13,6 +13,18 @@ fn main() {
     ];
     let mut prost_build = prost_build::Config::new();
     prost_build.protoc_arg("--experimental_allow_proto3_optional");
+    // Session state holds root, chain, and message keys, so erase it (and all
+    // of its nested chains) as soon as each copy is dropped.
+    prost_build.type_attribute(
+        ".signal.proto.storage.SessionStructure",
+        "#[derive(zeroize::Zeroize, zeroize::ZeroizeOnDrop)]",
+    );
+    // A whole session record carries the serialized bytes of every archived
+    // session, so it needs the same treatment.
+    prost_build.type_attribute(
+        ".signal.proto.storage.RecordStructure",
+        "#[derive(zeroize::Zeroize, zeroize::ZeroizeOnDrop)]",
+    );
     prost_build
         .compile_protos(&protos, &["src"])
         .expect("Protobufs in src are valid");
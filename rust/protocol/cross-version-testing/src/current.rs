@@ -10,7 +10,7 @@ use libsignal_protocol_current::*;
 use rand::{thread_rng, Rng};
 
 fn address(id: &str) -> ProtocolAddress {
-    ProtocolAddress::new(id.into(), 1.into())
+    ProtocolAddress::new(id.into(), DeviceId::new(1).expect("valid"))
 }
 
 pub struct LibSignalProtocolCurrent(InMemSignalProtocolStore);
@@ -50,7 +50,7 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
             .calculate_signature(&signed_pre_key_public, &mut csprng)
             .expect("can calculate signatures");
 
-        let device_id: u32 = csprng.gen();
+        let device_id = DeviceId::new(csprng.gen_range(1..=DeviceId::MAX)).expect("in range");
         let pre_key_id: u32 = csprng.gen();
         let signed_pre_key_id: u32 = csprng.gen();
 
@@ -60,7 +60,7 @@ impl super::LibSignalProtocolStore for LibSignalProtocolCurrent {
                 .now_or_never()
                 .expect("synchronous")
                 .expect("can fetch registration id"),
-            device_id.into(),
+            device_id,
             Some((pre_key_id.into(), pre_key_pair.public_key)),
             signed_pre_key_id.into(),
             signed_pre_key_pair.public_key,
@@ -48,7 +48,10 @@ impl super::LibSignalProtocolStore for LibSignalProtocolV21 {
             .calculate_signature(&signed_pre_key_public, &mut csprng)
             .expect("can calculate signatures");
 
-        let device_id: u32 = csprng.gen();
+        let device_id = libsignal_protocol_current::DeviceId::new(
+            csprng.gen_range(1..=libsignal_protocol_current::DeviceId::MAX),
+        )
+        .expect("in range");
         let pre_key_id: u32 = csprng.gen();
         let signed_pre_key_id: u32 = csprng.gen();
 
@@ -58,7 +61,7 @@ impl super::LibSignalProtocolStore for LibSignalProtocolV21 {
                 .now_or_never()
                 .expect("synchronous")
                 .expect("can fetch registration id"),
-            device_id.into(),
+            device_id,
             Some((
                 pre_key_id.into(),
                 pre_key_pair.public_key.serialize()[..]
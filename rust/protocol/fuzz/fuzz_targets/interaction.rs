@@ -5,7 +5,6 @@
 
 #![no_main]
 
-
 use std::time::SystemTime;
 
 use futures_util::FutureExt;
@@ -78,7 +77,7 @@ impl Participant {
 
         let their_pre_key_bundle = PreKeyBundle::new(
             them.store.get_local_registration_id().await.unwrap(),
-            1.into(), // device id
+            DeviceId::new(1).expect("valid"), // device id
             pre_key_info,
             signed_pre_key_id,
             their_signed_pre_key_pair.public_key,
@@ -191,7 +190,10 @@ fuzz_target!(|data: (u64, &[u8])| {
 
         let mut alice = Participant {
             name: "alice",
-            address: ProtocolAddress::new("+14151111111".to_owned(), 1.into()),
+            address: ProtocolAddress::new(
+                "+14151111111".to_owned(),
+                DeviceId::new(1).expect("valid"),
+            ),
             store: InMemSignalProtocolStore::new(
                 IdentityKeyPair::generate(&mut csprng),
                 csprng.gen(),
@@ -203,7 +205,10 @@ fuzz_target!(|data: (u64, &[u8])| {
         };
         let mut bob = Participant {
             name: "bob",
-            address: ProtocolAddress::new("+14151111112".to_owned(), 1.into()),
+            address: ProtocolAddress::new(
+                "+14151111112".to_owned(),
+                DeviceId::new(1).expect("valid"),
+            ),
             store: InMemSignalProtocolStore::new(
                 IdentityKeyPair::generate(&mut csprng),
                 csprng.gen(),
@@ -235,7 +240,10 @@ fuzz_target!(|data: (u64, &[u8])| {
                         // We're not testing that.
                         me.archive_session(&them.address).await
                     } else {
-                        info!("{}: archiving LIMITED at {}/{}", me.name, me.archive_count, them.archive_count);
+                        info!(
+                            "{}: archiving LIMITED at {}/{}",
+                            me.name, me.archive_count, them.archive_count
+                        );
                     }
                 }
                 1..=32 => me.receive_messages(&them.address, &mut csprng).await,
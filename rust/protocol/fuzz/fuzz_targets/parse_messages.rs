@@ -0,0 +1,14 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libsignal_protocol::fuzzing;
+
+fuzz_target!(|data: &[u8]| {
+    fuzzing::parse_protocol_messages(data);
+    fuzzing::parse_sealed_sender(data);
+});
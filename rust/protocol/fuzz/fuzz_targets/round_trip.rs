@@ -0,0 +1,23 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use libsignal_protocol::fuzzing;
+
+fn round_trip(u: &mut Unstructured<'_>) -> Result<()> {
+    match u.int_in_range(0..=2)? {
+        0 => fuzzing::round_trip_pre_key_signal_message(&u.arbitrary()?),
+        1 => fuzzing::round_trip_sender_key_message(&u.arbitrary()?),
+        _ => fuzzing::round_trip_unidentified_sender_message_content(&u.arbitrary()?),
+    }
+    Ok(())
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _: Result<_> = round_trip(&mut Unstructured::new(data));
+});
@@ -0,0 +1,419 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Parsing of the envelopes the server delivers messages in.
+//!
+//! An [`Envelope`] is only produced once its fields agree with each other:
+//! messages that aren't sealed sender come with a complete source address,
+//! sealed sender messages come without one, timestamps are in range, and the
+//! content parses as the kind of message the envelope's type says it is.
+
+use displaydoc::Display;
+use prost::Message;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::proto::service::envelope;
+use crate::{
+    proto, sealed_sender_message_version, CiphertextMessage, DeviceId, Pni, ProtocolAddress,
+    ServiceId, SignalProtocolError, Timestamp,
+};
+
+/// The largest timestamp accepted in an envelope, which is also the largest
+/// one a JavaScript `Date` can represent.
+const MAX_TIMESTAMP_MILLIS: u64 = 8_640_000_000_000_000;
+
+#[derive(Debug, Display, Error)]
+pub enum EnvelopeError {
+    /// envelope protobuf encoding was invalid
+    InvalidProtobufEncoding,
+    /// unsupported envelope type {0}
+    UnsupportedType(i32),
+    /// envelope is missing its {0}
+    MissingField(&'static str),
+    /// envelope has an invalid {0}
+    InvalidField(&'static str),
+    /// envelope has only one of a source service ID and device ID
+    IncompleteSource,
+    /// sealed sender envelope must not have a source
+    UnexpectedSource,
+    /// server delivery receipt must not have content
+    UnexpectedContent,
+    /// envelope content is not a valid {0} message: {1}
+    InvalidContent(&'static str, #[source] SignalProtocolError),
+}
+
+/// The sender of a message that isn't sealed sender.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EnvelopeSource {
+    pub service_id: ServiceId,
+    pub device_id: DeviceId,
+}
+
+impl EnvelopeSource {
+    /// The address to decrypt the message from.
+    pub fn address(&self) -> ProtocolAddress {
+        ProtocolAddress::new(self.service_id.service_id_string(), self.device_id)
+    }
+}
+
+/// What an envelope carries, already parsed according to its type.
+#[derive(Debug)]
+pub enum EnvelopeContent {
+    /// A message to decrypt with the source's session or sender key.
+    Ciphertext(CiphertextMessage),
+    /// A sealed sender message, still to be decrypted to learn its sender.
+    SealedSender(Box<[u8]>),
+    /// A delivery receipt generated by the server on the source's behalf.
+    ServerDeliveryReceipt,
+}
+
+impl EnvelopeContent {
+    /// The number the server uses for this kind of content in the envelope's
+    /// `type` field.
+    pub fn envelope_type(&self) -> u8 {
+        let envelope_type = match self {
+            Self::Ciphertext(CiphertextMessage::SignalMessage(_)) => envelope::Type::Ciphertext,
+            Self::Ciphertext(CiphertextMessage::PreKeySignalMessage(_)) => {
+                envelope::Type::PrekeyBundle
+            }
+            Self::Ciphertext(CiphertextMessage::SenderKeyMessage(_)) => {
+                envelope::Type::SenderkeyMessage
+            }
+            Self::Ciphertext(CiphertextMessage::PlaintextContent(_)) => {
+                envelope::Type::PlaintextContent
+            }
+            Self::SealedSender(_) => envelope::Type::UnidentifiedSender,
+            Self::ServerDeliveryReceipt => envelope::Type::Receipt,
+        };
+        envelope_type as u8
+    }
+
+    /// The content as it appeared in the envelope; empty for a server delivery
+    /// receipt.
+    pub fn serialized(&self) -> &[u8] {
+        match self {
+            Self::Ciphertext(message) => message.serialize(),
+            Self::SealedSender(message) => message,
+            Self::ServerDeliveryReceipt => &[],
+        }
+    }
+}
+
+/// A validated envelope from the server.
+#[derive(Debug)]
+pub struct Envelope {
+    /// Present for every kind of content except sealed sender.
+    pub source: Option<EnvelopeSource>,
+    /// The local account identity the message was sent to, if the server said.
+    pub destination: Option<ServiceId>,
+    /// The timestamp assigned by the sender.
+    pub timestamp: Timestamp,
+    /// When the server received the message.
+    pub server_timestamp: Option<Timestamp>,
+    /// The server's identifier for the message, used to acknowledge it.
+    pub server_guid: Option<Uuid>,
+    pub content: EnvelopeContent,
+    pub urgent: bool,
+    pub story: bool,
+    pub ephemeral: bool,
+    /// Set if the local account's PNI has changed.
+    pub updated_pni: Option<Pni>,
+    pub report_spam_token: Option<Box<[u8]>>,
+}
+
+impl TryFrom<&[u8]> for Envelope {
+    type Error = EnvelopeError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let proto = proto::service::Envelope::decode(value)
+            .map_err(|_| EnvelopeError::InvalidProtobufEncoding)?;
+        Self::try_from(proto)
+    }
+}
+
+impl TryFrom<proto::service::Envelope> for Envelope {
+    type Error = EnvelopeError;
+
+    fn try_from(proto: proto::service::Envelope) -> Result<Self, Self::Error> {
+        let proto::service::Envelope {
+            r#type,
+            source_service_id,
+            source_device,
+            destination_service_id,
+            timestamp,
+            content,
+            server_guid,
+            server_timestamp,
+            ephemeral,
+            urgent,
+            updated_pni,
+            story,
+            report_spam_token,
+        } = proto;
+
+        let raw_type = r#type.unwrap_or_default();
+        let envelope_type = envelope::Type::try_from(raw_type)
+            .map_err(|_| EnvelopeError::UnsupportedType(raw_type))?;
+        let source = parse_source(source_service_id.as_deref(), source_device)?;
+        let content = content.unwrap_or_default();
+
+        let parsed_content = match envelope_type {
+            envelope::Type::UnidentifiedSender => {
+                if source.is_some() {
+                    return Err(EnvelopeError::UnexpectedSource);
+                }
+                if content.is_empty() {
+                    return Err(EnvelopeError::MissingField("content"));
+                }
+                sealed_sender_message_version(&content)
+                    .map_err(|e| EnvelopeError::InvalidContent("sealed sender", e))?;
+                EnvelopeContent::SealedSender(content.into_boxed_slice())
+            }
+            envelope::Type::Receipt => {
+                if !content.is_empty() {
+                    return Err(EnvelopeError::UnexpectedContent);
+                }
+                EnvelopeContent::ServerDeliveryReceipt
+            }
+            envelope::Type::Ciphertext => EnvelopeContent::Ciphertext(
+                CiphertextMessage::SignalMessage(parse_content("signal", content)?),
+            ),
+            envelope::Type::PrekeyBundle => EnvelopeContent::Ciphertext(
+                CiphertextMessage::PreKeySignalMessage(parse_content("prekey", content)?),
+            ),
+            envelope::Type::SenderkeyMessage => EnvelopeContent::Ciphertext(
+                CiphertextMessage::SenderKeyMessage(parse_content("sender key", content)?),
+            ),
+            envelope::Type::PlaintextContent => EnvelopeContent::Ciphertext(
+                CiphertextMessage::PlaintextContent(parse_content("plaintext", content)?),
+            ),
+            envelope::Type::Unknown | envelope::Type::KeyExchange => {
+                return Err(EnvelopeError::UnsupportedType(envelope_type.into()))
+            }
+        };
+        if source.is_none() && !matches!(parsed_content, EnvelopeContent::SealedSender(_)) {
+            return Err(EnvelopeError::MissingField("source"));
+        }
+
+        let timestamp = parse_timestamp("timestamp", timestamp)?
+            .ok_or(EnvelopeError::MissingField("timestamp"))?;
+        let server_timestamp = parse_timestamp("server timestamp", server_timestamp)?;
+        let server_guid = server_guid
+            .as_deref()
+            .map(|guid| {
+                Uuid::parse_str(guid).map_err(|_| EnvelopeError::InvalidField("server GUID"))
+            })
+            .transpose()?;
+        let destination = destination_service_id
+            .as_deref()
+            .map(|id| {
+                ServiceId::parse_from_service_id_string(id)
+                    .ok_or(EnvelopeError::InvalidField("destination service ID"))
+            })
+            .transpose()?;
+        let updated_pni = updated_pni
+            .as_deref()
+            .map(|id| {
+                Pni::parse_from_service_id_string(id)
+                    .ok_or(EnvelopeError::InvalidField("updated PNI"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            source,
+            destination,
+            timestamp,
+            server_timestamp,
+            server_guid,
+            content: parsed_content,
+            urgent: urgent.unwrap_or(true),
+            story: story.unwrap_or_default(),
+            ephemeral: ephemeral.unwrap_or_default(),
+            updated_pni,
+            report_spam_token: report_spam_token
+                .filter(|token| !token.is_empty())
+                .map(Vec::into_boxed_slice),
+        })
+    }
+}
+
+fn parse_source(
+    service_id: Option<&str>,
+    device_id: Option<u32>,
+) -> Result<Option<EnvelopeSource>, EnvelopeError> {
+    match (service_id, device_id) {
+        (None, None) => Ok(None),
+        (Some(service_id), Some(device_id)) => Ok(Some(EnvelopeSource {
+            service_id: ServiceId::parse_from_service_id_string(service_id)
+                .ok_or(EnvelopeError::InvalidField("source service ID"))?,
+            device_id: DeviceId::try_from(device_id)
+                .map_err(|_| EnvelopeError::InvalidField("source device ID"))?,
+        })),
+        (Some(_), None) | (None, Some(_)) => Err(EnvelopeError::IncompleteSource),
+    }
+}
+
+fn parse_timestamp(
+    field: &'static str,
+    millis: Option<u64>,
+) -> Result<Option<Timestamp>, EnvelopeError> {
+    match millis {
+        None => Ok(None),
+        Some(millis) if millis == 0 || millis > MAX_TIMESTAMP_MILLIS => {
+            Err(EnvelopeError::InvalidField(field))
+        }
+        Some(millis) => Ok(Some(Timestamp::from_epoch_millis(millis))),
+    }
+}
+
+fn parse_content<T>(kind: &'static str, content: Vec<u8>) -> Result<T, EnvelopeError>
+where
+    T: for<'a> TryFrom<&'a [u8], Error = SignalProtocolError>,
+{
+    if content.is_empty() {
+        return Err(EnvelopeError::MissingField("content"));
+    }
+    T::try_from(&content[..]).map_err(|e| EnvelopeError::InvalidContent(kind, e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = "9d0652a3-dcc3-4d11-975f-74d61598733f";
+    const PLAINTEXT: [u8; 2] = [0xC0, 0x80];
+
+    fn plaintext_envelope() -> proto::service::Envelope {
+        proto::service::Envelope {
+            r#type: Some(envelope::Type::PlaintextContent.into()),
+            source_service_id: Some(SOURCE.to_owned()),
+            source_device: Some(2),
+            timestamp: Some(1000),
+            content: Some(PLAINTEXT.to_vec()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_valid_envelope() {
+        let mut proto = plaintext_envelope();
+        proto.server_guid = Some(SOURCE.to_owned());
+        let envelope = Envelope::try_from(&proto.encode_to_vec()[..]).expect("valid");
+
+        let source = envelope.source.expect("has source");
+        assert_eq!(source.address().name(), SOURCE);
+        assert_eq!(
+            source.address().device_id(),
+            DeviceId::new(2).expect("valid")
+        );
+        assert_eq!(envelope.timestamp, Timestamp::from_epoch_millis(1000));
+        assert_eq!(
+            envelope.server_guid,
+            Some(Uuid::parse_str(SOURCE).expect("valid"))
+        );
+        assert!(envelope.urgent);
+        assert!(matches!(
+            envelope.content,
+            EnvelopeContent::Ciphertext(CiphertextMessage::PlaintextContent(_))
+        ));
+        assert_eq!(
+            envelope.content.envelope_type(),
+            envelope::Type::PlaintextContent as u8
+        );
+        assert_eq!(envelope.content.serialized(), PLAINTEXT);
+    }
+
+    #[test]
+    fn parses_sealed_sender_and_receipts() {
+        let sealed_sender = proto::service::Envelope {
+            r#type: Some(envelope::Type::UnidentifiedSender.into()),
+            timestamp: Some(1000),
+            content: Some(vec![0x22, 1, 2, 3]),
+            ..Default::default()
+        };
+        let envelope = Envelope::try_from(sealed_sender.clone()).expect("valid");
+        assert_eq!(envelope.source, None);
+        assert!(matches!(envelope.content, EnvelopeContent::SealedSender(_)));
+        assert_eq!(
+            envelope.content.envelope_type(),
+            envelope::Type::UnidentifiedSender as u8
+        );
+        assert_eq!(envelope.content.serialized(), [0x22, 1, 2, 3]);
+
+        let with_source = proto::service::Envelope {
+            source_service_id: Some(SOURCE.to_owned()),
+            source_device: Some(1),
+            ..sealed_sender
+        };
+        assert!(matches!(
+            Envelope::try_from(with_source),
+            Err(EnvelopeError::UnexpectedSource)
+        ));
+
+        let receipt = proto::service::Envelope {
+            r#type: Some(envelope::Type::Receipt.into()),
+            content: None,
+            ..plaintext_envelope()
+        };
+        let content = Envelope::try_from(receipt).expect("valid").content;
+        assert!(matches!(content, EnvelopeContent::ServerDeliveryReceipt));
+        assert_eq!(content.envelope_type(), envelope::Type::Receipt as u8);
+        assert_eq!(content.serialized(), []);
+    }
+
+    #[test]
+    fn rejects_inconsistent_envelopes() {
+        let check = |modify: fn(&mut proto::service::Envelope)| {
+            let mut proto = plaintext_envelope();
+            modify(&mut proto);
+            Envelope::try_from(proto).expect_err("should be rejected")
+        };
+
+        assert!(matches!(
+            check(|e| e.source_device = None),
+            EnvelopeError::IncompleteSource
+        ));
+        assert!(matches!(
+            check(|e| {
+                e.source_service_id = None;
+                e.source_device = None;
+            }),
+            EnvelopeError::MissingField("source")
+        ));
+        assert!(matches!(
+            check(|e| e.source_device = Some(0)),
+            EnvelopeError::InvalidField("source device ID")
+        ));
+        assert!(matches!(
+            check(|e| e.source_service_id = Some("not a uuid".to_owned())),
+            EnvelopeError::InvalidField("source service ID")
+        ));
+        assert!(matches!(
+            check(|e| e.timestamp = None),
+            EnvelopeError::MissingField("timestamp")
+        ));
+        assert!(matches!(
+            check(|e| e.server_timestamp = Some(MAX_TIMESTAMP_MILLIS + 1)),
+            EnvelopeError::InvalidField("server timestamp")
+        ));
+        assert!(matches!(
+            check(|e| e.content = None),
+            EnvelopeError::MissingField("content")
+        ));
+        assert!(matches!(
+            check(|e| e.r#type = Some(envelope::Type::Ciphertext.into())),
+            EnvelopeError::InvalidContent("signal", _)
+        ));
+        assert!(matches!(
+            check(|e| e.r#type = Some(envelope::Type::KeyExchange.into())),
+            EnvelopeError::UnsupportedType(2)
+        ));
+        assert!(matches!(
+            check(|e| e.r#type = Some(42)),
+            EnvelopeError::UnsupportedType(42)
+        ));
+    }
+}
@@ -10,7 +10,7 @@ use thiserror::Error;
 use uuid::Uuid;
 
 use crate::curve::KeyType;
-use crate::kem;
+use crate::{kem, CiphertextMessageType, ProtocolAddress};
 
 pub type Result<T> = std::result::Result<T, SignalProtocolError>;
 
@@ -101,6 +101,19 @@ pub enum SignalProtocolError {
     BadKEMKeyLength(kem::KeyType, usize),
     /// bad KEM ciphertext length <{1}> for key with type <{0}>
     BadKEMCiphertextLength(kem::KeyType, usize),
+
+    /// {context}: {error}
+    WithContext {
+        context: Box<ErrorContext>,
+        #[source]
+        error: Box<SignalProtocolError>,
+    },
+}
+
+impl From<crate::InvalidDeviceId> for SignalProtocolError {
+    fn from(e: crate::InvalidDeviceId) -> Self {
+        Self::InvalidArgument(e.to_string())
+    }
 }
 
 impl SignalProtocolError {
@@ -111,4 +124,166 @@ impl SignalProtocolError {
     ) -> impl FnOnce(E) -> Self {
         move |error| Self::ApplicationCallbackError(method, Box::new(error))
     }
+
+    /// Attaches `context` to this error, unless it already has a more specific one.
+    pub(crate) fn with_context(self, context: &ErrorContext) -> Self {
+        match self {
+            Self::WithContext { .. } => self,
+            error => Self::WithContext {
+                context: Box::new(context.clone()),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// What the failing entry point was working on, if it recorded that.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::WithContext { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The error without any attached [`ErrorContext`], for matching on its kind.
+    pub fn underlying_error(&self) -> &Self {
+        match self {
+            Self::WithContext { error, .. } => error.underlying_error(),
+            error => error,
+        }
+    }
+
+    /// Like [`Self::underlying_error`], but takes ownership.
+    pub fn without_context(self) -> Self {
+        match self {
+            Self::WithContext { error, .. } => error.without_context(),
+            error => error,
+        }
+    }
+}
+
+/// Describes what an entry point was working on when it failed.
+///
+/// Errors like [`SignalProtocolError::InvalidMessage`] don't say which peer or
+/// session they came from. The session cipher and sealed sender entry points
+/// attach one of these to the errors they return, as
+/// [`SignalProtocolError::WithContext`], so that reports from the field can be
+/// matched up with the conversation involved. Logging is left to the caller.
+#[derive(Clone, Debug)]
+pub struct ErrorContext {
+    operation: &'static str,
+    remote_address: Option<ProtocolAddress>,
+    message_type: Option<CiphertextMessageType>,
+    has_session: Option<bool>,
+}
+
+impl ErrorContext {
+    pub fn new(operation: &'static str) -> Self {
+        Self {
+            operation,
+            remote_address: None,
+            message_type: None,
+            has_session: None,
+        }
+    }
+
+    pub fn with_address(mut self, remote_address: &ProtocolAddress) -> Self {
+        self.remote_address = Some(remote_address.clone());
+        self
+    }
+
+    pub fn with_message_type(mut self, message_type: CiphertextMessageType) -> Self {
+        self.message_type = Some(message_type);
+        self
+    }
+
+    pub fn with_session(mut self, has_session: bool) -> Self {
+        self.has_session = Some(has_session);
+        self
+    }
+
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+
+    pub fn remote_address(&self) -> Option<&ProtocolAddress> {
+        self.remote_address.as_ref()
+    }
+
+    pub fn message_type(&self) -> Option<CiphertextMessageType> {
+        self.message_type
+    }
+
+    /// Whether a session with the remote address existed when the operation
+    /// started, if that was checked.
+    pub fn has_session(&self) -> Option<bool> {
+        self.has_session
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed", self.operation)?;
+        if let Some(remote_address) = &self.remote_address {
+            write!(f, " for {remote_address}")?;
+        }
+        if let Some(message_type) = self.message_type {
+            write!(f, " with {message_type:?} message")?;
+        }
+        match self.has_session {
+            Some(true) => write!(f, " (existing session)"),
+            Some(false) => write!(f, " (no session)"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DeviceId;
+
+    #[test]
+    fn error_context_display() {
+        let address =
+            ProtocolAddress::new("alice".to_owned(), DeviceId::try_from(2u32).expect("valid"));
+        assert_eq!(
+            ErrorContext::new("message_decrypt_signal")
+                .with_address(&address)
+                .with_message_type(CiphertextMessageType::Whisper)
+                .with_session(false)
+                .to_string(),
+            "message_decrypt_signal failed for alice.2 with Whisper message (no session)"
+        );
+        assert_eq!(
+            ErrorContext::new("sealed_sender_decrypt").to_string(),
+            "sealed_sender_decrypt failed"
+        );
+    }
+
+    #[test]
+    fn error_context_is_attached_once() {
+        let inner = ErrorContext::new("message_decrypt_signal")
+            .with_message_type(CiphertextMessageType::Whisper);
+        let outer = ErrorContext::new("sealed_sender_decrypt");
+        let error = SignalProtocolError::InvalidMessage(CiphertextMessageType::Whisper, "bad MAC")
+            .with_context(&inner)
+            .with_context(&outer);
+
+        assert_eq!(
+            error.context().map(ErrorContext::operation),
+            Some("message_decrypt_signal")
+        );
+        assert_eq!(
+            error.to_string(),
+            "message_decrypt_signal failed with Whisper message: invalid Whisper message: bad MAC"
+        );
+        assert!(matches!(
+            error.underlying_error(),
+            SignalProtocolError::InvalidMessage(CiphertextMessageType::Whisper, "bad MAC")
+        ));
+        assert!(matches!(
+            error.without_context(),
+            SignalProtocolError::InvalidMessage(CiphertextMessageType::Whisper, "bad MAC")
+        ));
+    }
 }
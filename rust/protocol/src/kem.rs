@@ -19,6 +19,23 @@
 //! KEM protocol. Calls to `PublicKey::deserialize()` and `SecretKey::deserialize()`
 //! will use this to ensure the key is used for the correct KEM protocol.
 //!
+//! # Adding a KEM
+//! Session code only ever sees [`PublicKey`], [`SecretKey`], and serialized
+//! ciphertexts, so a new KEM (including a hybrid one) only needs changes in
+//! this module:
+//!
+//! 1. Implement the private `Parameters` trait in a new submodule.
+//! 2. Add a [`KeyType`] variant, and map it in `KeyType::value`,
+//!    `KeyType::parameters`, and `TryFrom<u8> for KeyType`.
+//!
+//! The type byte is the only versioning in the serialized form, so a new
+//! parameter set (even a revision of an existing one, such as ML-KEM versus
+//! round-3 Kyber) must get a byte that has never been used before. Ciphertexts
+//! carry the same byte and are only decapsulated by a secret key of the same
+//! type; a mismatch is a [`SignalProtocolError::WrongKEMKeyType`] error, never
+//! a silent fallback, so a peer can't be tricked into a weaker KEM that happens
+//! to share key or ciphertext lengths with a stronger one.
+//!
 //! # Example
 //! Basic usage:
 //! ```
@@ -581,6 +598,39 @@ mod tests {
         assert_eq!(ss_for_recipient, ss_for_sender);
     }
 
+    #[test]
+    fn test_key_type_bytes_round_trip() {
+        let key_types = [
+            KeyType::Kyber768,
+            KeyType::Kyber1024,
+            #[cfg(feature = "mlkem1024")]
+            KeyType::MLKEM1024,
+        ];
+        for key_type in key_types {
+            assert_eq!(
+                KeyType::try_from(key_type.value()).expect("known type"),
+                key_type
+            );
+        }
+        let mut values = key_types.map(|key_type| key_type.value());
+        values.sort();
+        assert!(
+            values.windows(2).all(|pair| pair[0] != pair[1]),
+            "type bytes must be unique: {values:?}"
+        );
+    }
+
+    #[test]
+    fn test_ciphertext_bound_to_key_type() {
+        let kyber768 = KeyPair::generate(KeyType::Kyber768);
+        let kyber1024 = KeyPair::generate(KeyType::Kyber1024);
+        let (_ss, ct) = kyber768.public_key.encapsulate();
+        assert!(matches!(
+            kyber1024.secret_key.decapsulate(&ct),
+            Err(SignalProtocolError::WrongKEMKeyType(0x07, 0x08))
+        ));
+    }
+
     #[test]
     fn test_dyn_parameters_consts() {
         assert_eq!(
@@ -25,8 +25,11 @@
 mod consts;
 mod crypto;
 mod curve;
+mod envelope;
 pub mod error;
 mod fingerprint;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
 mod group_cipher;
 mod identity_key;
 pub mod incremental_mac;
@@ -40,12 +43,15 @@ mod session;
 mod session_cipher;
 mod state;
 mod storage;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
 mod timestamp;
 mod utils;
 
 pub use curve::{KeyPair, PrivateKey, PublicKey};
+pub use envelope::{Envelope, EnvelopeContent, EnvelopeError, EnvelopeSource};
 use error::Result;
-pub use error::SignalProtocolError;
+pub use error::{ErrorContext, SignalProtocolError};
 pub use fingerprint::{DisplayableFingerprint, Fingerprint, ScannableFingerprint};
 pub use group_cipher::{
     create_sender_key_distribution_message, group_decrypt, group_encrypt,
@@ -53,7 +59,8 @@ pub use group_cipher::{
 };
 pub use identity_key::{IdentityKey, IdentityKeyPair};
 pub use libsignal_core::{
-    Aci, DeviceId, Pni, ProtocolAddress, ServiceId, ServiceIdFixedWidthBinaryBytes, ServiceIdKind,
+    Aci, DeviceId, InvalidDeviceId, Pni, ProtocolAddress, ServiceId,
+    ServiceIdFixedWidthBinaryBytes, ServiceIdKind, E164,
 };
 pub use protocol::{
     extract_decryption_error_message_from_serialized_content, CiphertextMessage,
@@ -66,8 +73,10 @@ pub use ratchet::{
 };
 pub use sealed_sender::{
     sealed_sender_decrypt, sealed_sender_decrypt_to_usmc, sealed_sender_encrypt,
-    sealed_sender_encrypt_from_usmc, sealed_sender_multi_recipient_encrypt, ContentHint,
-    SealedSenderDecryptionResult, SealedSenderV2SentMessage, SealedSenderV2SentMessageRecipient,
+    sealed_sender_encrypt_from_usmc, sealed_sender_message_version,
+    sealed_sender_multi_recipient_encrypt, sealed_sender_rewrap_v1_as_v2, ContentHint,
+    SealedSenderDecryptionResult, SealedSenderMessageVersion, SealedSenderV2Destination,
+    SealedSenderV2RoutingInfo, SealedSenderV2SentMessage, SealedSenderV2SentMessageRecipient,
     SenderCertificate, ServerCertificate, UnidentifiedSenderMessageContent,
 };
 pub use sender_keys::SenderKeyRecord;
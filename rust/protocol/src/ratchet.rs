@@ -7,6 +7,7 @@ mod keys;
 mod params;
 
 use rand::{CryptoRng, Rng};
+use zeroize::Zeroizing;
 
 pub(crate) use self::keys::{ChainKey, MessageKeys, RootKey};
 pub use self::params::{AliceSignalProtocolParameters, BobSignalProtocolParameters};
@@ -32,9 +33,9 @@ fn message_version(has_kyber: bool) -> u8 {
 }
 
 fn derive_keys_with_label(label: &[u8], secret_input: &[u8]) -> (RootKey, ChainKey) {
-    let mut secrets = [0; 64];
+    let mut secrets = Zeroizing::new([0; 64]);
     hkdf::Hkdf::<sha2::Sha256>::new(None, secret_input)
-        .expand(label, &mut secrets)
+        .expand(label, &mut secrets[..])
         .expect("valid length");
     let (root_key_bytes, chain_key_bytes) = secrets.split_at(32);
 
@@ -52,7 +53,10 @@ pub(crate) fn initialize_alice_session<R: Rng + CryptoRng>(
 
     let sending_ratchet_key = KeyPair::generate(&mut csprng);
 
-    let mut secrets = Vec::with_capacity(32 * 5);
+    // Room for the discontinuity bytes, up to four agreements, and a Kyber
+    // shared secret, so that the buffer never reallocates and leaves a copy
+    // behind.
+    let mut secrets = Zeroizing::new(Vec::with_capacity(32 * 6));
 
     secrets.extend_from_slice(&[0xFFu8; 32]); // "discontinuity bytes"
 
@@ -114,7 +118,10 @@ pub(crate) fn initialize_bob_session(
 ) -> Result<SessionState> {
     let local_identity = parameters.our_identity_key_pair().identity_key();
 
-    let mut secrets = Vec::with_capacity(32 * 5);
+    // Room for the discontinuity bytes, up to four agreements, and a Kyber
+    // shared secret, so that the buffer never reallocates and leaves a copy
+    // behind.
+    let mut secrets = Zeroizing::new(Vec::with_capacity(32 * 6));
 
     secrets.extend_from_slice(&[0xFFu8; 32]); // "discontinuity bytes"
 
@@ -6,9 +6,12 @@
 use std::fmt;
 
 use arrayref::array_ref;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use crate::{crypto, PrivateKey, PublicKey, Result};
 
+/// The keys for a single message, erased once the message has been handled.
+#[derive(Zeroize, ZeroizeOnDrop)]
 pub(crate) struct MessageKeys {
     cipher_key: [u8; 32],
     mac_key: [u8; 32],
@@ -18,9 +21,9 @@ pub(crate) struct MessageKeys {
 
 impl MessageKeys {
     pub(crate) fn derive_keys(input_key_material: &[u8], counter: u32) -> Self {
-        let mut okm = [0; 80];
+        let mut okm = Zeroizing::new([0; 80]);
         hkdf::Hkdf::<sha2::Sha256>::new(None, input_key_material)
-            .expand(b"WhisperMessageKeys", &mut okm)
+            .expand(b"WhisperMessageKeys", &mut okm[..])
             .expect("valid output length");
 
         MessageKeys {
@@ -61,7 +64,10 @@ impl MessageKeys {
     }
 }
 
-#[derive(Clone, Debug)]
+/// A sending or receiving chain key. Each step along the chain erases the
+/// previous key when it is dropped, so that earlier message keys can't be
+/// rederived.
+#[derive(Clone, Debug, Zeroize, ZeroizeOnDrop)]
 pub(crate) struct ChainKey {
     key: [u8; 32],
     index: u32,
@@ -87,24 +93,24 @@ impl ChainKey {
 
     pub(crate) fn next_chain_key(&self) -> Self {
         Self {
-            key: self.calculate_base_material(Self::CHAIN_KEY_SEED),
+            key: *self.calculate_base_material(Self::CHAIN_KEY_SEED),
             index: self.index + 1,
         }
     }
 
     pub(crate) fn message_keys(&self) -> MessageKeys {
         MessageKeys::derive_keys(
-            &self.calculate_base_material(Self::MESSAGE_KEY_SEED),
+            &*self.calculate_base_material(Self::MESSAGE_KEY_SEED),
             self.index,
         )
     }
 
-    fn calculate_base_material(&self, seed: [u8; 1]) -> [u8; 32] {
-        crypto::hmac_sha256(&self.key, &seed)
+    fn calculate_base_material(&self, seed: [u8; 1]) -> Zeroizing<[u8; 32]> {
+        Zeroizing::new(crypto::hmac_sha256(&self.key, &seed))
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Zeroize, ZeroizeOnDrop)]
 pub(crate) struct RootKey {
     key: [u8; 32],
 }
@@ -123,10 +129,10 @@ impl RootKey {
         their_ratchet_key: &PublicKey,
         our_ratchet_key: &PrivateKey,
     ) -> Result<(RootKey, ChainKey)> {
-        let shared_secret = our_ratchet_key.calculate_agreement(their_ratchet_key)?;
-        let mut derived_secret_bytes = [0; 64];
+        let shared_secret = Zeroizing::new(our_ratchet_key.calculate_agreement(their_ratchet_key)?);
+        let mut derived_secret_bytes = Zeroizing::new([0; 64]);
         hkdf::Hkdf::<sha2::Sha256>::new(Some(&self.key), &shared_secret)
-            .expand(b"WhisperRatchet", &mut derived_secret_bytes)
+            .expand(b"WhisperRatchet", &mut derived_secret_bytes[..])
             .expect("valid output length");
 
         Ok((
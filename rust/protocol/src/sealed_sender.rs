@@ -18,9 +18,9 @@ use subtle::ConstantTimeEq;
 
 use crate::{
     crypto, curve, message_encrypt, proto, session_cipher, Aci, CiphertextMessageType, DeviceId,
-    Direction, IdentityKey, IdentityKeyPair, IdentityKeyStore, KeyPair, KyberPreKeyStore,
-    PreKeySignalMessage, PreKeyStore, PrivateKey, ProtocolAddress, PublicKey, Result, ServiceId,
-    ServiceIdFixedWidthBinaryBytes, SessionRecord, SessionStore, SignalMessage,
+    Direction, ErrorContext, IdentityKey, IdentityKeyPair, IdentityKeyStore, KeyPair,
+    KyberPreKeyStore, PreKeySignalMessage, PreKeyStore, PrivateKey, ProtocolAddress, PublicKey,
+    Result, ServiceId, ServiceIdFixedWidthBinaryBytes, SessionRecord, SessionStore, SignalMessage,
     SignalProtocolError, SignedPreKeyStore, Timestamp,
 };
 
@@ -47,9 +47,6 @@ const REVOKED_SERVER_CERTIFICATE_KEY_IDS: &[u32] = &[0xDEADC357];
 // TODO: move this into a RegistrationId strong type.
 const VALID_REGISTRATION_ID_MASK: u16 = 0x3FFF;
 
-// TODO: validate this as part of constructing DeviceId.
-const MAX_VALID_DEVICE_ID: u32 = 127;
-
 impl ServerCertificate {
     pub fn deserialize(data: &[u8]) -> Result<Self> {
         let pb = proto::sealed_sender::ServerCertificate::decode(data)
@@ -185,7 +182,7 @@ impl SenderCertificate {
         let sender_device_id: DeviceId = certificate_data
             .sender_device
             .ok_or(SignalProtocolError::InvalidProtobufEncoding)?
-            .into();
+            .try_into()?;
         let expiration = certificate_data
             .expires
             .map(Timestamp::from_epoch_millis)
@@ -802,7 +799,15 @@ pub async fn sealed_sender_encrypt<R: Rng + CryptoRng>(
         ContentHint::Default,
         None,
     )?;
-    sealed_sender_encrypt_from_usmc(destination, &usmc, identity_store, rng).await
+    sealed_sender_encrypt_from_usmc(destination, &usmc, identity_store, rng)
+        .await
+        .map_err(|e| {
+            e.with_context(
+                &ErrorContext::new("sealed_sender_encrypt")
+                    .with_address(destination)
+                    .with_message_type(message.message_type()),
+            )
+        })
 }
 
 /// This method implements the single-key single-recipient [KEM] described in [this Signal blog
@@ -1395,14 +1400,8 @@ where
                 their_registration_id |= 0x8000;
             }
 
-            let device_id: u32 = destination.device_id().into();
-            if device_id == 0 || device_id > MAX_VALID_DEVICE_ID {
-                return Err(SignalProtocolError::InvalidState(
-                    "sealed_sender_multi_recipient_encrypt",
-                    format!("destination {destination} has invalid device ID"),
-                ));
-            }
-            serialized.push(device_id.try_into().expect("just checked range"));
+            // DeviceId is always in range for a single byte.
+            serialized.push(destination.device_id().into());
             serialized.extend_from_slice(&their_registration_id.to_be_bytes());
         }
 
@@ -1509,6 +1508,35 @@ pub struct SealedSenderV2SentMessageRecipient<'a> {
     c_and_at: &'a [u8],
 }
 
+/// A recipient of a Sealed Sender v2 SentMessage with at least one device.
+///
+/// See [`SealedSenderV2RoutingInfo`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SealedSenderV2Destination {
+    pub service_id: ServiceId,
+    /// The recipient's devices and their registration IDs.
+    pub devices: Vec<(DeviceId, u16)>,
+}
+
+/// Everything in a Sealed Sender v2 SentMessage that can be read without decrypting anything.
+///
+/// This is exactly what the server learns from the message, so it's suitable both for deciding
+/// where to deliver (or whether to drop) a message and for checking what a client reveals.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SealedSenderV2RoutingInfo {
+    /// The version byte at the head of the message.
+    pub version: u8,
+    /// Recipients with at least one device, in the order they first appear.
+    pub destinations: Vec<SealedSenderV2Destination>,
+    /// Recipients listed without any devices, in the order they appear.
+    ///
+    /// These are members of the group that the sender deliberately isn't sending to, typically
+    /// because they're sending to them some other way.
+    pub excluded_recipients: Vec<ServiceId>,
+    /// The length of the encrypted payload shared by all recipients.
+    pub shared_payload_len: usize,
+}
+
 /// A parsed representation of a Sealed Sender v2 SentMessage.
 ///
 /// This only parses enough to fan out the message as a series of ReceivedMessages.
@@ -1588,20 +1616,19 @@ impl<'a> SealedSenderV2SentMessage<'a> {
             };
             let mut devices = Vec::new();
             loop {
-                let device_id: u32 = advance::<1>(&mut remaining)?[0].into();
+                let device_id = advance::<1>(&mut remaining)?[0];
                 if device_id == 0 {
                     if !devices.is_empty() {
                         return Err(SignalProtocolError::InvalidProtobufEncoding);
                     }
                     break;
                 }
-                if device_id > MAX_VALID_DEVICE_ID {
-                    return Err(SignalProtocolError::InvalidProtobufEncoding);
-                }
+                let device_id = DeviceId::new(device_id)
+                    .map_err(|_| SignalProtocolError::InvalidProtobufEncoding)?;
                 let registration_id_and_has_more =
                     u16::from_be_bytes(*advance::<2>(&mut remaining)?);
                 devices.push((
-                    device_id.into(),
+                    device_id,
                     registration_id_and_has_more & VALID_REGISTRATION_ID_MASK,
                 ));
                 let has_more = (registration_id_and_has_more & 0x8000) != 0;
@@ -1732,6 +1759,88 @@ impl<'a> SealedSenderV2SentMessage<'a> {
         self.offset_within_full_message(self.shared_bytes.as_ptr())
             .expect("constructed correctly")
     }
+
+    /// Lists every recipient device along with its registration ID.
+    ///
+    /// Devices are grouped by recipient, in the same order as [`Self::recipients`].
+    pub fn recipient_devices(&self) -> impl Iterator<Item = (ServiceId, DeviceId, u16)> + '_ {
+        self.recipients.iter().flat_map(|(&service_id, recipient)| {
+            recipient
+                .devices
+                .iter()
+                .map(move |&(device_id, registration_id)| (service_id, device_id, registration_id))
+        })
+    }
+
+    /// Collects the message's unauthenticated routing data.
+    ///
+    /// Nothing is decrypted; see [`SealedSenderV2RoutingInfo`].
+    pub fn routing_info(&self) -> SealedSenderV2RoutingInfo {
+        let (destinations, excluded_recipients): (Vec<_>, Vec<_>) = self
+            .recipients
+            .iter()
+            .partition(|(_, recipient)| !recipient.devices.is_empty());
+        SealedSenderV2RoutingInfo {
+            version: self.version,
+            destinations: destinations
+                .into_iter()
+                .map(|(&service_id, recipient)| SealedSenderV2Destination {
+                    service_id,
+                    devices: recipient.devices.clone(),
+                })
+                .collect(),
+            excluded_recipients: excluded_recipients
+                .into_iter()
+                .map(|(&service_id, _)| service_id)
+                .collect(),
+            shared_payload_len: self.shared_bytes.len(),
+        }
+    }
+
+    /// Produces the ReceivedMessage for every recipient that has at least one device.
+    ///
+    /// Recipients that were only listed as excluded are skipped.
+    pub fn received_messages(&self) -> impl Iterator<Item = (ServiceId, Vec<u8>)> + '_ {
+        self.recipients
+            .iter()
+            .filter(|(_, recipient)| !recipient.devices.is_empty())
+            .map(|(&service_id, recipient)| {
+                let message = self
+                    .received_message_parts_for_recipient(recipient)
+                    .as_ref()
+                    .concat();
+                (service_id, message)
+            })
+    }
+
+    /// Re-serializes this message as a SentMessage addressed only to `service_id`.
+    ///
+    /// The result always uses the ServiceId-based format, and parses back to a message whose only
+    /// recipient is `service_id` with the same devices. Returns `None` if `service_id` is not one
+    /// of the recipients.
+    pub fn single_recipient_view(&self, service_id: &ServiceId) -> Option<Vec<u8>> {
+        let recipient = self.recipients.get(service_id)?;
+
+        let mut serialized = vec![SEALED_SENDER_V2_SERVICE_ID_FULL_VERSION];
+        prost::encode_length_delimiter(1, &mut serialized).expect("can always resize a Vec");
+        serialized.extend_from_slice(&service_id.service_id_fixed_width_binary());
+        if recipient.devices.is_empty() {
+            serialized.push(0);
+        } else {
+            let mut devices = recipient.devices.iter().peekable();
+            while let Some(&(device_id, registration_id)) = devices.next() {
+                let mut registration_id_and_has_more = registration_id;
+                if devices.peek().is_some() {
+                    registration_id_and_has_more |= 0x8000;
+                }
+                serialized.push(device_id.into());
+                serialized.extend_from_slice(&registration_id_and_has_more.to_be_bytes());
+            }
+            serialized.extend_from_slice(recipient.c_and_at);
+        }
+        serialized.extend_from_slice(self.shared_bytes);
+        Some(serialized)
+    }
 }
 
 /// Decrypt the payload of a sealed-sender message in either the v1 or v2 format.
@@ -1876,6 +1985,92 @@ pub async fn sealed_sender_decrypt_to_usmc(
     }
 }
 
+/// The format of a serialized sealed sender message.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SealedSenderMessageVersion {
+    /// A single-recipient message, as produced by [`sealed_sender_encrypt`].
+    V1,
+    /// A message for one recipient or a multi-recipient SentMessage, as produced by
+    /// [`sealed_sender_multi_recipient_encrypt`].
+    V2,
+}
+
+/// Determines the format of a serialized sealed sender message without decrypting it.
+pub fn sealed_sender_message_version(ciphertext: &[u8]) -> Result<SealedSenderMessageVersion> {
+    let Some(first_byte) = ciphertext.first() else {
+        return Err(SignalProtocolError::InvalidSealedSenderMessage(
+            "Message was empty".to_owned(),
+        ));
+    };
+    match first_byte >> 4 {
+        0 | SEALED_SENDER_V1_MAJOR_VERSION => Ok(SealedSenderMessageVersion::V1),
+        SEALED_SENDER_V2_MAJOR_VERSION => Ok(SealedSenderMessageVersion::V2),
+        version => Err(SignalProtocolError::UnknownSealedSenderVersion(version)),
+    }
+}
+
+/// Re-encrypts a v1 sealed sender message as a single-recipient v2 message with the same
+/// contents.
+///
+/// Only the recipient can open a v1 message, so `identity_store` must be the recipient's. The
+/// result can be decrypted by the same recipient with [`sealed_sender_decrypt_to_usmc`], and
+/// authenticates the same sender as the original. Messages that are already v2 are returned
+/// unchanged.
+pub async fn sealed_sender_rewrap_v1_as_v2<R: Rng + CryptoRng>(
+    ciphertext: &[u8],
+    identity_store: &dyn IdentityKeyStore,
+    rng: &mut R,
+) -> Result<Vec<u8>> {
+    if sealed_sender_message_version(ciphertext)? == SealedSenderMessageVersion::V2 {
+        return Ok(ciphertext.to_vec());
+    }
+
+    let usmc = sealed_sender_decrypt_to_usmc(ciphertext, identity_store).await?;
+    let our_identity = identity_store.get_identity_key_pair().await?;
+    let sender_identity = IdentityKey::new(usmc.sender()?.key()?);
+
+    let m: [u8; sealed_sender_v2::MESSAGE_KEY_LEN] = rng.gen();
+    let keys = sealed_sender_v2::DerivedKeys::new(&m);
+    let e = keys.derive_e();
+
+    let mut encrypted_message = usmc.serialized()?.to_vec();
+    let symmetric_authentication_tag = Aes256GcmSiv::new(&keys.derive_k().into())
+        .encrypt_in_place_detached(
+            // There's no nonce because the key is already one-use.
+            &aes_gcm_siv::Nonce::default(),
+            // And there's no associated data.
+            &[],
+            &mut encrypted_message,
+        )
+        .expect("AES-GCM-SIV encryption should not fail with a just-computed key");
+    encrypted_message.extend_from_slice(&symmetric_authentication_tag);
+
+    // Encrypt M to ourselves, as the recipient, and compute the tag the sender would have: the
+    // agreement between the two identity keys is the same from either side.
+    let c = sealed_sender_v2::apply_agreement_xor(
+        &e,
+        our_identity.public_key(),
+        Direction::Sending,
+        &m,
+    )?;
+    let at = sealed_sender_v2::compute_authentication_tag(
+        &our_identity,
+        &sender_identity,
+        Direction::Receiving,
+        &e.public_key,
+        &c,
+    )?;
+
+    // This is the same format as the messages produced by
+    // SealedSenderV2SentMessage::received_message_parts_for_recipient.
+    let mut serialized = vec![SEALED_SENDER_V2_UUID_FULL_VERSION];
+    serialized.extend_from_slice(&c);
+    serialized.extend_from_slice(&at);
+    serialized.extend_from_slice(e.public_key.public_key_bytes()?);
+    serialized.extend_from_slice(&encrypted_message);
+    Ok(serialized)
+}
+
 #[derive(Debug)]
 pub struct SealedSenderDecryptionResult {
     pub sender_uuid: String,
@@ -1925,6 +2120,44 @@ pub async fn sealed_sender_decrypt(
 ) -> Result<SealedSenderDecryptionResult> {
     let usmc = sealed_sender_decrypt_to_usmc(ciphertext, identity_store).await?;
 
+    // The sender is left out; it's what sealed sender hides.
+    let mut context = ErrorContext::new("sealed_sender_decrypt");
+    if let Ok(message_type) = usmc.msg_type() {
+        context = context.with_message_type(message_type);
+    }
+
+    sealed_sender_decrypt_usmc(
+        &usmc,
+        trust_root,
+        timestamp,
+        local_e164,
+        local_uuid,
+        local_device_id,
+        identity_store,
+        session_store,
+        pre_key_store,
+        signed_pre_key_store,
+        kyber_pre_key_store,
+    )
+    .await
+    // Replace the session cipher's context, which names the sender.
+    .map_err(|e| e.without_context().with_context(&context))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn sealed_sender_decrypt_usmc(
+    usmc: &UnidentifiedSenderMessageContent,
+    trust_root: &PublicKey,
+    timestamp: Timestamp,
+    local_e164: Option<String>,
+    local_uuid: String,
+    local_device_id: DeviceId,
+    identity_store: &mut dyn IdentityKeyStore,
+    session_store: &mut dyn SessionStore,
+    pre_key_store: &mut dyn PreKeyStore,
+    signed_pre_key_store: &dyn SignedPreKeyStore,
+    kyber_pre_key_store: &mut dyn KyberPreKeyStore,
+) -> Result<SealedSenderDecryptionResult> {
     if !usmc.sender()?.validate(trust_root, timestamp)? {
         return Err(SignalProtocolError::InvalidSealedSenderMessage(
             "trust root validation failed".to_string(),
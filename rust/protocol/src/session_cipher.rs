@@ -11,9 +11,10 @@ use crate::consts::{MAX_FORWARD_JUMPS, MAX_UNACKNOWLEDGED_SESSION_AGE};
 use crate::ratchet::{ChainKey, MessageKeys};
 use crate::state::{InvalidSessionError, SessionState};
 use crate::{
-    session, CiphertextMessage, CiphertextMessageType, Direction, IdentityKeyStore, KeyPair,
-    KyberPayload, KyberPreKeyStore, PreKeySignalMessage, PreKeyStore, ProtocolAddress, PublicKey,
-    Result, SessionRecord, SessionStore, SignalMessage, SignalProtocolError, SignedPreKeyStore,
+    session, CiphertextMessage, CiphertextMessageType, Direction, ErrorContext, IdentityKeyStore,
+    KeyPair, KyberPayload, KyberPreKeyStore, PreKeySignalMessage, PreKeyStore, ProtocolAddress,
+    PublicKey, Result, SessionRecord, SessionStore, SignalMessage, SignalProtocolError,
+    SignedPreKeyStore,
 };
 
 pub async fn message_encrypt(
@@ -23,10 +24,36 @@ pub async fn message_encrypt(
     identity_store: &mut dyn IdentityKeyStore,
     now: SystemTime,
 ) -> Result<CiphertextMessage> {
-    let mut session_record = session_store
+    let context = ErrorContext::new("message_encrypt").with_address(remote_address);
+    let session_record = session_store
         .load_session(remote_address)
-        .await?
-        .ok_or_else(|| SignalProtocolError::SessionNotFound(remote_address.clone()))?;
+        .await
+        .map_err(|e| e.with_context(&context))?;
+    let context = context.with_session(session_record.is_some());
+    let session_record = session_record
+        .ok_or_else(|| SignalProtocolError::SessionNotFound(remote_address.clone()))
+        .map_err(|e| e.with_context(&context))?;
+
+    message_encrypt_with_record(
+        ptext,
+        remote_address,
+        session_record,
+        session_store,
+        identity_store,
+        now,
+    )
+    .await
+    .map_err(|e| e.with_context(&context))
+}
+
+async fn message_encrypt_with_record(
+    ptext: &[u8],
+    remote_address: &ProtocolAddress,
+    mut session_record: SessionRecord,
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    now: SystemTime,
+) -> Result<CiphertextMessage> {
     let session_state = session_record
         .session_state_mut()
         .ok_or_else(|| SignalProtocolError::SessionNotFound(remote_address.clone()))?;
@@ -178,10 +205,16 @@ pub async fn message_decrypt<R: Rng + CryptoRng>(
             )
             .await
         }
-        _ => Err(SignalProtocolError::InvalidArgument(format!(
-            "message_decrypt cannot be used to decrypt {:?} messages",
-            ciphertext.message_type()
-        ))),
+        _ => {
+            let context = ErrorContext::new("message_decrypt")
+                .with_address(remote_address)
+                .with_message_type(ciphertext.message_type());
+            Err(SignalProtocolError::InvalidArgument(format!(
+                "message_decrypt cannot be used to decrypt {:?} messages",
+                ciphertext.message_type()
+            ))
+            .with_context(&context))
+        }
     }
 }
 
@@ -196,11 +229,42 @@ pub async fn message_decrypt_prekey<R: Rng + CryptoRng>(
     kyber_pre_key_store: &mut dyn KyberPreKeyStore,
     csprng: &mut R,
 ) -> Result<Vec<u8>> {
-    let mut session_record = session_store
+    let context = ErrorContext::new("message_decrypt_prekey")
+        .with_address(remote_address)
+        .with_message_type(CiphertextMessageType::PreKey);
+    let session_record = session_store
         .load_session(remote_address)
-        .await?
-        .unwrap_or_else(SessionRecord::new_fresh);
+        .await
+        .map_err(|e| e.with_context(&context))?;
+    let context = context.with_session(session_record.is_some());
+
+    message_decrypt_prekey_with_record(
+        ciphertext,
+        remote_address,
+        session_record.unwrap_or_else(SessionRecord::new_fresh),
+        session_store,
+        identity_store,
+        pre_key_store,
+        signed_pre_key_store,
+        kyber_pre_key_store,
+        csprng,
+    )
+    .await
+    .map_err(|e| e.with_context(&context))
+}
 
+#[allow(clippy::too_many_arguments)]
+async fn message_decrypt_prekey_with_record<R: Rng + CryptoRng>(
+    ciphertext: &PreKeySignalMessage,
+    remote_address: &ProtocolAddress,
+    mut session_record: SessionRecord,
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    pre_key_store: &mut dyn PreKeyStore,
+    signed_pre_key_store: &dyn SignedPreKeyStore,
+    kyber_pre_key_store: &mut dyn KyberPreKeyStore,
+    csprng: &mut R,
+) -> Result<Vec<u8>> {
     // Make sure we log the session state if we fail to process the pre-key.
     let pre_key_used_or_err = session::process_prekey(
         ciphertext,
@@ -263,11 +327,38 @@ pub async fn message_decrypt_signal<R: Rng + CryptoRng>(
     identity_store: &mut dyn IdentityKeyStore,
     csprng: &mut R,
 ) -> Result<Vec<u8>> {
-    let mut session_record = session_store
+    let context = ErrorContext::new("message_decrypt_signal")
+        .with_address(remote_address)
+        .with_message_type(CiphertextMessageType::Whisper);
+    let session_record = session_store
         .load_session(remote_address)
-        .await?
-        .ok_or_else(|| SignalProtocolError::SessionNotFound(remote_address.clone()))?;
+        .await
+        .map_err(|e| e.with_context(&context))?;
+    let context = context.with_session(session_record.is_some());
+    let session_record = session_record
+        .ok_or_else(|| SignalProtocolError::SessionNotFound(remote_address.clone()))
+        .map_err(|e| e.with_context(&context))?;
+
+    message_decrypt_signal_with_record(
+        ciphertext,
+        remote_address,
+        session_record,
+        session_store,
+        identity_store,
+        csprng,
+    )
+    .await
+    .map_err(|e| e.with_context(&context))
+}
 
+async fn message_decrypt_signal_with_record<R: Rng + CryptoRng>(
+    ciphertext: &SignalMessage,
+    remote_address: &ProtocolAddress,
+    mut session_record: SessionRecord,
+    session_store: &mut dyn SessionStore,
+    identity_store: &mut dyn IdentityKeyStore,
+    csprng: &mut R,
+) -> Result<Vec<u8>> {
     let ptext = decrypt_message_with_record(
         remote_address,
         &mut session_record,
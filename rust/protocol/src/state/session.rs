@@ -8,6 +8,7 @@ use std::time::{Duration, SystemTime};
 
 use prost::Message;
 use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
 
 use crate::proto::storage::{session_structure, RecordStructure, SessionStructure};
 use crate::ratchet::{ChainKey, MessageKeys, RootKey};
@@ -259,7 +260,7 @@ impl SessionState {
     ) -> Result<Option<ChainKey>, InvalidSessionError> {
         match self.get_receiver_chain(sender)? {
             None => Ok(None),
-            Some((chain, _)) => match chain.chain_key {
+            Some((chain, _)) => match chain.chain_key.as_ref() {
                 None => Err(InvalidSessionError("missing receiver chain key")),
                 Some(c) => {
                     let chain_key_bytes = c.key[..]
@@ -385,16 +386,13 @@ impl SessionState {
             if let Some(position) = message_key_idx {
                 let message_key = chain_and_index.0.message_keys.remove(position);
 
-                let cipher_key_bytes = message_key
-                    .cipher_key
+                let cipher_key_bytes = message_key.cipher_key[..]
                     .try_into()
                     .map_err(|_| InvalidSessionError("invalid message cipher key"))?;
-                let mac_key_bytes = message_key
-                    .mac_key
+                let mac_key_bytes = message_key.mac_key[..]
                     .try_into()
                     .map_err(|_| InvalidSessionError("invalid message MAC key"))?;
-                let iv_bytes = message_key
-                    .iv
+                let iv_bytes = message_key.iv[..]
                     .try_into()
                     .map_err(|_| InvalidSessionError("invalid message IV"))?;
 
@@ -582,7 +580,7 @@ impl From<&SessionState> for SessionStructure {
 #[derive(Clone)]
 pub struct SessionRecord {
     current_session: Option<SessionState>,
-    previous_sessions: Vec<Vec<u8>>,
+    previous_sessions: Vec<Zeroizing<Vec<u8>>>,
 }
 
 impl SessionRecord {
@@ -601,12 +599,15 @@ impl SessionRecord {
     }
 
     pub fn deserialize(bytes: &[u8]) -> Result<Self, SignalProtocolError> {
-        let record = RecordStructure::decode(bytes)
+        let mut record = RecordStructure::decode(bytes)
             .map_err(|_| InvalidSessionError("failed to decode session record protobuf"))?;
 
         Ok(Self {
-            current_session: record.current_session.map(|s| s.into()),
-            previous_sessions: record.previous_sessions,
+            current_session: record.current_session.take().map(|s| s.into()),
+            previous_sessions: std::mem::take(&mut record.previous_sessions)
+                .into_iter()
+                .map(Zeroizing::new)
+                .collect(),
         })
     }
 
@@ -683,7 +684,7 @@ impl SessionRecord {
             }
             current_session.clear_unacknowledged_pre_key_message();
             self.previous_sessions
-                .insert(0, current_session.session.encode_to_vec());
+                .insert(0, Zeroizing::new(current_session.session.encode_to_vec()));
             true
         } else {
             false
@@ -700,7 +701,11 @@ impl SessionRecord {
     pub fn serialize(&self) -> Result<Vec<u8>, SignalProtocolError> {
         let record = RecordStructure {
             current_session: self.current_session.as_ref().map(|s| s.into()),
-            previous_sessions: self.previous_sessions.clone(),
+            previous_sessions: self
+                .previous_sessions
+                .iter()
+                .map(|bytes| bytes.to_vec())
+                .collect(),
         };
         Ok(record.encode_to_vec())
     }
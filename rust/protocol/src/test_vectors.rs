@@ -0,0 +1,507 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Deterministic generation of protocol test vectors.
+//!
+//! Every generator here takes a 32-byte seed and produces the same output for
+//! the same seed, so test suites for the app-facing bridges and independent
+//! implementations of the protocol can regenerate their fixtures instead of
+//! checking in opaque binary blobs. Changing the output for an existing seed
+//! breaks anyone who has pinned it, so the generators should be treated like
+//! a wire format; the tests pin a hash of the output for a fixed seed.
+//!
+//! The exception is [`pqxdh_session_transcript`]: Kyber key generation and
+//! encapsulation can't be seeded, so its output differs from run to run. The
+//! transcript still includes everything needed to decrypt it.
+
+use std::time::{Duration, SystemTime};
+
+use rand::{CryptoRng, Rng, SeedableRng as _};
+use rand_chacha::ChaCha20Rng;
+use uuid::Uuid;
+
+use crate::{
+    create_sender_key_distribution_message, group_encrypt, kem, message_decrypt, message_encrypt,
+    process_prekey_bundle, sealed_sender_multi_recipient_encrypt, Aci, CiphertextMessageType,
+    ContentHint, DeviceId, GenericSignedPreKey as _, IdentityKeyPair, IdentityKeyStore as _,
+    InMemSignalProtocolStore, KeyPair, KyberPreKeyRecord, KyberPreKeyStore as _, PreKeyBundle,
+    PreKeyRecord, PreKeyStore as _, ProtocolAddress, Result, SenderCertificate,
+    SenderKeyDistributionMessage, ServerCertificate, SessionStore as _, SignedPreKeyRecord,
+    SignedPreKeyStore as _, Timestamp, UnidentifiedSenderMessageContent,
+};
+
+const DEVICE_ID: DeviceId = match DeviceId::new(1) {
+    Ok(id) => id,
+    Err(_) => unreachable!(),
+};
+
+/// The time at which every vector is generated.
+pub fn generation_time() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+}
+
+/// A conversation between Alice and Bob, starting from Bob's pre-key bundle.
+pub struct SessionTranscript {
+    pub alice_address: ProtocolAddress,
+    pub alice_identity: IdentityKeyPair,
+    pub bob_address: ProtocolAddress,
+    pub bob_identity: IdentityKeyPair,
+    pub bob_pre_key_bundle: PreKeyBundle,
+    pub bob_pre_key: PreKeyRecord,
+    pub bob_signed_pre_key: SignedPreKeyRecord,
+    /// Present for sessions set up with PQXDH.
+    pub bob_kyber_pre_key: Option<KyberPreKeyRecord>,
+    /// The messages, in the order they were sent and received.
+    pub messages: Vec<TranscriptMessage>,
+}
+
+pub struct TranscriptMessage {
+    /// `true` if Alice sent this message, `false` if Bob did.
+    pub from_alice: bool,
+    pub plaintext: Vec<u8>,
+    pub message_type: CiphertextMessageType,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Generates a session transcript of `message_count` messages.
+///
+/// Alice starts the session with Bob's X3DH pre-key bundle, and the two then
+/// take turns sending messages; each message is decrypted before the next one
+/// is sent.
+pub async fn session_transcript(seed: [u8; 32], message_count: usize) -> Result<SessionTranscript> {
+    generate_session_transcript(seed, message_count, false).await
+}
+
+/// Like [`session_transcript`], but Bob's pre-key bundle includes a Kyber
+/// pre-key, so the session is set up with PQXDH.
+///
+/// Unlike the other generators, the output isn't determined by `seed`.
+pub async fn pqxdh_session_transcript(
+    seed: [u8; 32],
+    message_count: usize,
+) -> Result<SessionTranscript> {
+    generate_session_transcript(seed, message_count, true).await
+}
+
+async fn generate_session_transcript(
+    seed: [u8; 32],
+    message_count: usize,
+    use_kyber: bool,
+) -> Result<SessionTranscript> {
+    let mut rng = ChaCha20Rng::from_seed(seed);
+
+    let alice_address = ProtocolAddress::new("alice".to_owned(), DEVICE_ID);
+    let bob_address = ProtocolAddress::new("bob".to_owned(), DEVICE_ID);
+    let mut alice_store = new_store(&mut rng)?;
+    let mut bob_store = new_store(&mut rng)?;
+
+    let (mut bob_pre_key_bundle, bob_pre_key, bob_signed_pre_key) =
+        create_pre_key_bundle(&mut bob_store, &mut rng).await?;
+    let bob_kyber_pre_key = if use_kyber {
+        let kyber_pre_key = create_kyber_pre_key(&mut bob_store, &mut rng).await?;
+        bob_pre_key_bundle = bob_pre_key_bundle.with_kyber_pre_key(
+            kyber_pre_key.id()?,
+            kyber_pre_key.public_key()?,
+            kyber_pre_key.signature()?,
+        );
+        Some(kyber_pre_key)
+    } else {
+        None
+    };
+    process_prekey_bundle(
+        &bob_address,
+        &mut alice_store.session_store,
+        &mut alice_store.identity_store,
+        &bob_pre_key_bundle,
+        generation_time(),
+        &mut rng,
+    )
+    .await?;
+
+    let mut messages = Vec::with_capacity(message_count);
+    for i in 0..message_count {
+        let from_alice = i % 2 == 0;
+        let (sender, sender_address, recipient, recipient_address) = if from_alice {
+            (
+                &mut alice_store,
+                &alice_address,
+                &mut bob_store,
+                &bob_address,
+            )
+        } else {
+            (
+                &mut bob_store,
+                &bob_address,
+                &mut alice_store,
+                &alice_address,
+            )
+        };
+
+        let plaintext = format!("message {i}").into_bytes();
+        let ciphertext = message_encrypt(
+            &plaintext,
+            recipient_address,
+            &mut sender.session_store,
+            &mut sender.identity_store,
+            generation_time(),
+        )
+        .await?;
+        let decrypted = message_decrypt(
+            &ciphertext,
+            sender_address,
+            &mut recipient.session_store,
+            &mut recipient.identity_store,
+            &mut recipient.pre_key_store,
+            &recipient.signed_pre_key_store,
+            &mut recipient.kyber_pre_key_store,
+            &mut rng,
+        )
+        .await?;
+        debug_assert_eq!(decrypted, plaintext);
+
+        messages.push(TranscriptMessage {
+            from_alice,
+            plaintext,
+            message_type: ciphertext.message_type(),
+            ciphertext: ciphertext.serialize().to_vec(),
+        });
+    }
+
+    Ok(SessionTranscript {
+        alice_address,
+        alice_identity: alice_store.get_identity_key_pair().await?,
+        bob_address,
+        bob_identity: bob_store.get_identity_key_pair().await?,
+        bob_pre_key_bundle,
+        bob_pre_key,
+        bob_signed_pre_key,
+        bob_kyber_pre_key,
+        messages,
+    })
+}
+
+/// A sealed sender v2 message sent to several recipients.
+pub struct SealedSenderV2Vector {
+    pub trust_root: KeyPair,
+    pub sender_address: ProtocolAddress,
+    pub sender_identity: IdentityKeyPair,
+    pub sender_certificate: SenderCertificate,
+    /// Lets the recipients decrypt the sender key message in `usmc`.
+    pub sender_key_distribution_message: SenderKeyDistributionMessage,
+    pub recipients: Vec<(ProtocolAddress, IdentityKeyPair)>,
+    /// The message inside the sealed sender envelope.
+    pub usmc: UnidentifiedSenderMessageContent,
+    /// The multi-recipient message as sent to the server.
+    pub sent_message: Vec<u8>,
+}
+
+/// Generates a sealed sender v2 message for `recipient_count` recipients.
+///
+/// The content is a sender key message for a group; the sender has a session
+/// with every recipient.
+pub async fn sealed_sender_v2_message(
+    seed: [u8; 32],
+    recipient_count: usize,
+) -> Result<SealedSenderV2Vector> {
+    let mut rng = ChaCha20Rng::from_seed(seed);
+
+    let trust_root = KeyPair::generate(&mut rng);
+    let server_key = KeyPair::generate(&mut rng);
+    let server_certificate =
+        ServerCertificate::new(1, server_key.public_key, &trust_root.private_key, &mut rng)?;
+
+    let mut sender_store = new_store(&mut rng)?;
+    let sender_identity = sender_store.get_identity_key_pair().await?;
+    let sender_aci = Aci::from_uuid_bytes(rng.gen());
+    let sender_address = ProtocolAddress::new(sender_aci.service_id_string(), DEVICE_ID);
+    let sender_certificate = SenderCertificate::new(
+        sender_aci.service_id_string(),
+        None,
+        *sender_identity.public_key(),
+        DEVICE_ID,
+        Timestamp::from_epoch_millis(u64::MAX),
+        server_certificate,
+        &server_key.private_key,
+        &mut rng,
+    )?;
+
+    let mut recipients = Vec::with_capacity(recipient_count);
+    for _ in 0..recipient_count {
+        let mut recipient_store = new_store(&mut rng)?;
+        let recipient_aci = Aci::from_uuid_bytes(rng.gen());
+        let recipient_address = ProtocolAddress::new(recipient_aci.service_id_string(), DEVICE_ID);
+        let (bundle, _, _) = create_pre_key_bundle(&mut recipient_store, &mut rng).await?;
+        process_prekey_bundle(
+            &recipient_address,
+            &mut sender_store.session_store,
+            &mut sender_store.identity_store,
+            &bundle,
+            generation_time(),
+            &mut rng,
+        )
+        .await?;
+        recipients.push((
+            recipient_address,
+            recipient_store.get_identity_key_pair().await?,
+        ));
+    }
+
+    let distribution_id = Uuid::from_bytes(rng.gen());
+    let sender_key_distribution_message = create_sender_key_distribution_message(
+        &sender_address,
+        distribution_id,
+        &mut sender_store,
+        &mut rng,
+    )
+    .await?;
+    let sender_key_message = group_encrypt(
+        &mut sender_store,
+        &sender_address,
+        distribution_id,
+        b"group message",
+        &mut rng,
+    )
+    .await?;
+    let usmc = UnidentifiedSenderMessageContent::new(
+        CiphertextMessageType::SenderKey,
+        sender_certificate.clone(),
+        sender_key_message.serialized().to_vec(),
+        ContentHint::Implicit,
+        Some(rng.gen::<[u8; 32]>().to_vec()),
+    )?;
+
+    let mut sessions = Vec::with_capacity(recipients.len());
+    for (address, _) in &recipients {
+        sessions.push(
+            sender_store
+                .load_session(address)
+                .await?
+                .expect("session was just created"),
+        );
+    }
+    let sent_message = sealed_sender_multi_recipient_encrypt(
+        &recipients
+            .iter()
+            .map(|(address, _)| address)
+            .collect::<Vec<_>>(),
+        &sessions.iter().collect::<Vec<_>>(),
+        [],
+        &usmc,
+        &sender_store.identity_store,
+        &mut rng,
+    )
+    .await?;
+
+    Ok(SealedSenderV2Vector {
+        trust_root,
+        sender_address,
+        sender_identity,
+        sender_certificate,
+        sender_key_distribution_message,
+        recipients,
+        usmc,
+        sent_message,
+    })
+}
+
+fn new_store<R: Rng + CryptoRng>(rng: &mut R) -> Result<InMemSignalProtocolStore> {
+    InMemSignalProtocolStore::new(IdentityKeyPair::generate(rng), rng.gen_range(1..=0x3FFF))
+}
+
+/// Creates an X3DH-only pre-key bundle for the owner of `store`, saving the
+/// private halves of the pre-keys in it.
+async fn create_pre_key_bundle<R: Rng + CryptoRng>(
+    store: &mut InMemSignalProtocolStore,
+    rng: &mut R,
+) -> Result<(PreKeyBundle, PreKeyRecord, SignedPreKeyRecord)> {
+    let identity = store.get_identity_key_pair().await?;
+    let pre_key_pair = KeyPair::generate(rng);
+    let signed_pre_key_pair = KeyPair::generate(rng);
+    let signed_pre_key_signature = identity
+        .private_key()
+        .calculate_signature(&signed_pre_key_pair.public_key.serialize(), rng)?;
+    let pre_key_id = rng.gen::<u32>().into();
+    let signed_pre_key_id = rng.gen::<u32>().into();
+
+    let bundle = PreKeyBundle::new(
+        store.get_local_registration_id().await?,
+        DEVICE_ID,
+        Some((pre_key_id, pre_key_pair.public_key)),
+        signed_pre_key_id,
+        signed_pre_key_pair.public_key,
+        signed_pre_key_signature.to_vec(),
+        *identity.identity_key(),
+    )?;
+
+    let pre_key = PreKeyRecord::new(pre_key_id, &pre_key_pair);
+    let signed_pre_key = SignedPreKeyRecord::new(
+        signed_pre_key_id,
+        Timestamp::from_epoch_millis(rng.gen()),
+        &signed_pre_key_pair,
+        &signed_pre_key_signature,
+    );
+    store.save_pre_key(pre_key_id, &pre_key).await?;
+    store
+        .save_signed_pre_key(signed_pre_key_id, &signed_pre_key)
+        .await?;
+
+    Ok((bundle, pre_key, signed_pre_key))
+}
+
+/// Creates a Kyber pre-key for the owner of `store` and saves it there.
+///
+/// Only the signature and metadata come from `rng`.
+async fn create_kyber_pre_key<R: Rng + CryptoRng>(
+    store: &mut InMemSignalProtocolStore,
+    rng: &mut R,
+) -> Result<KyberPreKeyRecord> {
+    let identity = store.get_identity_key_pair().await?;
+    let key_pair = kem::KeyPair::generate(kem::KeyType::Kyber1024);
+    let signature = identity
+        .private_key()
+        .calculate_signature(&key_pair.public_key.serialize(), rng)?;
+    let id = rng.gen::<u32>().into();
+
+    let record = KyberPreKeyRecord::new(
+        id,
+        Timestamp::from_epoch_millis(rng.gen()),
+        &key_pair,
+        &signature,
+    );
+    store.save_kyber_pre_key(id, &record).await?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::FutureExt as _;
+    use sha2::{Digest as _, Sha256};
+
+    use super::*;
+    use crate::PreKeySignalMessage;
+
+    /// Hashes length-prefixed `parts`, for pinning the output of a generator.
+    fn digest<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    fn transcript_digest(transcript: &SessionTranscript) -> String {
+        let records = [
+            transcript.alice_identity.serialize().into_vec(),
+            transcript.bob_identity.serialize().into_vec(),
+            transcript.bob_pre_key.serialize().expect("can serialize"),
+            transcript
+                .bob_signed_pre_key
+                .serialize()
+                .expect("can serialize"),
+        ];
+        digest(
+            records.iter().map(Vec::as_slice).chain(
+                transcript
+                    .messages
+                    .iter()
+                    .map(|message| message.ciphertext.as_slice()),
+            ),
+        )
+    }
+
+    #[test]
+    fn session_transcript_is_deterministic() {
+        let first = session_transcript([1; 32], 4)
+            .now_or_never()
+            .expect("sync")
+            .expect("can generate");
+        let second = session_transcript([1; 32], 4)
+            .now_or_never()
+            .expect("sync")
+            .expect("can generate");
+        let other = session_transcript([2; 32], 4)
+            .now_or_never()
+            .expect("sync")
+            .expect("can generate");
+
+        assert_eq!(transcript_digest(&first), transcript_digest(&second));
+        assert_ne!(transcript_digest(&first), transcript_digest(&other));
+        assert!(first.bob_kyber_pre_key.is_none());
+        assert_eq!(
+            first
+                .messages
+                .iter()
+                .map(|message| (message.from_alice, message.message_type))
+                .collect::<Vec<_>>(),
+            [
+                (true, CiphertextMessageType::PreKey),
+                (false, CiphertextMessageType::Whisper),
+                (true, CiphertextMessageType::Whisper),
+                (false, CiphertextMessageType::Whisper),
+            ]
+        );
+    }
+
+    #[test]
+    fn session_transcript_is_pinned() {
+        let transcript = session_transcript([1; 32], 4)
+            .now_or_never()
+            .expect("sync")
+            .expect("can generate");
+        assert_eq!(
+            transcript_digest(&transcript),
+            "52836a7afec70db11c90e7b49899ad656030bb024cf1f43360b3c6f657bbdc5f"
+        );
+    }
+
+    #[test]
+    fn pqxdh_session_transcript_uses_kyber() {
+        let transcript = pqxdh_session_transcript([1; 32], 2)
+            .now_or_never()
+            .expect("sync")
+            .expect("can generate");
+        let kyber_pre_key = transcript.bob_kyber_pre_key.expect("PQXDH");
+
+        let first = &transcript.messages[0];
+        assert_eq!(first.message_type, CiphertextMessageType::PreKey);
+        let message = PreKeySignalMessage::try_from(&first.ciphertext[..]).expect("valid");
+        assert_eq!(
+            message.kyber_pre_key_id(),
+            Some(kyber_pre_key.id().expect("valid"))
+        );
+        assert_eq!(
+            transcript.messages[1].message_type,
+            CiphertextMessageType::Whisper
+        );
+    }
+
+    #[test]
+    fn sealed_sender_v2_message_is_deterministic() {
+        let first = sealed_sender_v2_message([1; 32], 3)
+            .now_or_never()
+            .expect("sync")
+            .expect("can generate");
+        let second = sealed_sender_v2_message([1; 32], 3)
+            .now_or_never()
+            .expect("sync")
+            .expect("can generate");
+        assert_eq!(first.sent_message, second.sent_message);
+        assert_eq!(first.recipients.len(), 3);
+
+        assert_eq!(
+            digest([
+                &first.trust_root.public_key.serialize()[..],
+                first
+                    .sender_certificate
+                    .serialized()
+                    .expect("can serialize"),
+                first.sender_key_distribution_message.serialized(),
+                &first.sent_message,
+            ]),
+            "725702cd2c2cfb00354ee5faf407186ce3dbf841be276a615af397a4504ecea8"
+        );
+    }
+}
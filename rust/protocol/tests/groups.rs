@@ -19,7 +19,8 @@ use uuid::Uuid;
 fn group_no_send_session() -> Result<(), SignalProtocolError> {
     let mut csprng = OsRng;
 
-    let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+    let sender_address =
+        ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
     let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
     let mut alice_store = test_in_memory_protocol_store()?;
@@ -43,7 +44,7 @@ fn group_no_recv_session() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let device_id: DeviceId = 1.into();
+        let device_id = DeviceId::new(1).expect("valid");
         let sender_address = ProtocolAddress::new("+14159999111".to_owned(), device_id);
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
@@ -90,7 +91,8 @@ fn group_basic_encrypt_decrypt() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+        let sender_address =
+            ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
         let mut alice_store = test_in_memory_protocol_store()?;
@@ -146,9 +148,9 @@ fn group_sealed_sender() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
-        let carol_device_id: DeviceId = 1.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
+        let carol_device_id = DeviceId::new(1).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
 
@@ -349,10 +351,10 @@ fn group_sealed_sender_multiple_devices() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
-        let carol_device_id: DeviceId = 1.into();
-        let carol2_device_id: DeviceId = 2.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
+        let carol_device_id = DeviceId::new(1).expect("valid");
+        let carol2_device_id = DeviceId::new(2).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
 
@@ -582,10 +584,10 @@ fn group_sealed_sender_multiple_devices_and_excluded_recipients() -> Result<(),
     async {
         let mut csprng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
-        let carol_device_id: DeviceId = 1.into();
-        let carol2_device_id: DeviceId = 2.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
+        let carol_device_id = DeviceId::new(1).expect("valid");
+        let carol2_device_id = DeviceId::new(2).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
 
@@ -848,7 +850,8 @@ fn group_large_messages() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+        let sender_address =
+            ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
         let mut alice_store = test_in_memory_protocol_store()?;
@@ -906,7 +909,8 @@ fn group_basic_ratchet() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+        let sender_address =
+            ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
         let mut alice_store = test_in_memory_protocol_store()?;
@@ -1009,7 +1013,8 @@ fn group_late_join() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+        let sender_address =
+            ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
         let mut alice_store = test_in_memory_protocol_store()?;
@@ -1076,7 +1081,8 @@ fn group_out_of_order() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+        let sender_address =
+            ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
         let mut alice_store = test_in_memory_protocol_store()?;
@@ -1146,7 +1152,8 @@ fn group_too_far_in_the_future() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+        let sender_address =
+            ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
         let mut alice_store = test_in_memory_protocol_store()?;
@@ -1209,7 +1216,8 @@ fn group_message_key_limit() -> Result<(), SignalProtocolError> {
     async {
         let mut csprng = OsRng;
 
-        let sender_address = ProtocolAddress::new("+14159999111".to_owned(), 1.into());
+        let sender_address =
+            ProtocolAddress::new("+14159999111".to_owned(), DeviceId::new(1).expect("valid"));
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
         let mut alice_store = test_in_memory_protocol_store()?;
@@ -0,0 +1,119 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Checks that advancing the ratchet doesn't leave stale keys in freed heap
+//! memory.
+//!
+//! This test binary installs an allocator that scans every block as it is
+//! freed for a watched key. Keep it to a single test, since the watch is
+//! global.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use futures_util::FutureExt;
+use libsignal_protocol::*;
+
+mod support;
+use support::*;
+
+struct ScanningAllocator;
+
+static WATCHING: AtomicBool = AtomicBool::new(false);
+static NEEDLE: [AtomicU64; 4] = [
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+    AtomicU64::new(0),
+];
+static SIGHTINGS: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static ALLOCATOR: ScanningAllocator = ScanningAllocator;
+
+unsafe impl GlobalAlloc for ScanningAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if WATCHING.load(Ordering::SeqCst) {
+            let mut needle = [0u8; 32];
+            for (chunk, word) in needle.chunks_exact_mut(8).zip(&NEEDLE) {
+                chunk.copy_from_slice(&word.load(Ordering::SeqCst).to_ne_bytes());
+            }
+            let block = std::slice::from_raw_parts(ptr, layout.size());
+            if block.windows(needle.len()).any(|window| window == needle) {
+                SIGHTINGS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+fn watch_for(key: [u8; 32]) {
+    for (chunk, word) in key.chunks_exact(8).zip(&NEEDLE) {
+        word.store(
+            u64::from_ne_bytes(chunk.try_into().expect("8 bytes")),
+            Ordering::SeqCst,
+        );
+    }
+    SIGHTINGS.store(0, Ordering::SeqCst);
+    WATCHING.store(true, Ordering::SeqCst);
+}
+
+fn stop_watching() -> usize {
+    WATCHING.store(false, Ordering::SeqCst);
+    SIGHTINGS.load(Ordering::SeqCst)
+}
+
+#[test]
+fn decrypting_erases_stale_receiver_chain_key() -> Result<(), SignalProtocolError> {
+    async {
+        let (alice_session_record, bob_session_record) = initialize_sessions_v3()?;
+
+        let alice_address =
+            ProtocolAddress::new("+14159999999".to_owned(), DeviceId::new(1).expect("valid"));
+        let bob_address =
+            ProtocolAddress::new("+14158888888".to_owned(), DeviceId::new(1).expect("valid"));
+
+        let mut alice_store = TestStoreBuilder::new().store;
+        let mut bob_store = TestStoreBuilder::new().store;
+        alice_store
+            .store_session(&bob_address, &alice_session_record)
+            .await?;
+        bob_store
+            .store_session(&alice_address, &bob_session_record)
+            .await?;
+        drop((alice_session_record, bob_session_record));
+
+        let message = encrypt(&mut bob_store, &alice_address, "hello").await?;
+        let CiphertextMessage::SignalMessage(signal_message) = &message else {
+            panic!("session is already established");
+        };
+        let stale_chain_key: [u8; 32] = alice_store
+            .load_session(&bob_address)
+            .await?
+            .expect("session exists")
+            .get_receiver_chain_key_bytes(signal_message.sender_ratchet_key())?
+            .expect("chain exists")[..]
+            .try_into()
+            .expect("32-byte chain key");
+
+        watch_for(stale_chain_key);
+        let plaintext = decrypt(&mut alice_store, &bob_address, &message).await?;
+        drop((alice_store, bob_store, message));
+        let sightings = stop_watching();
+
+        assert_eq!(plaintext, b"hello");
+        assert_eq!(
+            sightings, 0,
+            "stale chain key was left in freed memory {sightings} time(s)"
+        );
+        Ok(())
+    }
+    .now_or_never()
+    .expect("sync")
+}
@@ -88,7 +88,7 @@ fn test_sender_cert() -> Result<(), SignalProtocolError> {
     let server_cert =
         ServerCertificate::new(1, server_key.public_key, &trust_root.private_key, &mut rng)?;
 
-    let device_id: DeviceId = 42.into();
+    let device_id = DeviceId::new(42).expect("valid");
     let expires = Timestamp::from_epoch_millis(1605722925);
 
     let sender_cert = SenderCertificate::new(
@@ -137,8 +137,8 @@ fn test_sealed_sender() -> Result<(), SignalProtocolError> {
     async {
         let mut rng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
         let bob_e164 = "+14151114444".to_owned();
@@ -244,7 +244,7 @@ fn test_sealed_sender() -> Result<(), SignalProtocolError> {
         )
         .await;
 
-        match bob_ptext {
+        match bob_ptext.map_err(SignalProtocolError::without_context) {
             Err(SignalProtocolError::InvalidSealedSenderMessage(_)) => { /* ok */ }
             Err(err) => {
                 panic!("Unexpected error {}", err)
@@ -284,7 +284,7 @@ fn test_sealed_sender() -> Result<(), SignalProtocolError> {
         )
         .await;
 
-        match bob_ptext {
+        match bob_ptext.map_err(SignalProtocolError::without_context) {
             Err(SignalProtocolError::InvalidSealedSenderMessage(_)) => { /* ok */ }
             Err(err) => {
                 panic!("Unexpected error {}", err)
@@ -305,8 +305,8 @@ fn test_sender_key_in_sealed_sender() -> Result<(), SignalProtocolError> {
     async {
         let mut rng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
 
@@ -315,7 +315,7 @@ fn test_sender_key_in_sealed_sender() -> Result<(), SignalProtocolError> {
 
         let distribution_id = Uuid::from_u128(0xd1d1d1d1_7000_11eb_b32a_33b8a8a487a6);
 
-        let device_id: DeviceId = 1.into();
+        let device_id = DeviceId::new(1).expect("valid");
         let alice_uuid_address = ProtocolAddress::new(alice_uuid.clone(), device_id);
         let bob_uuid_address = ProtocolAddress::new(bob_uuid.clone(), bob_device_id);
 
@@ -421,8 +421,8 @@ fn test_sealed_sender_multi_recipient() -> Result<(), SignalProtocolError> {
     async {
         let mut rng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
         let bob_e164 = "+14151114444".to_owned();
@@ -583,7 +583,7 @@ fn test_sealed_sender_multi_recipient() -> Result<(), SignalProtocolError> {
         )
         .await;
 
-        match bob_ptext {
+        match bob_ptext.map_err(SignalProtocolError::without_context) {
             Err(SignalProtocolError::InvalidSealedSenderMessage(_)) => { /* ok */ }
             Err(err) => {
                 panic!("Unexpected error {}", err)
@@ -644,7 +644,7 @@ fn test_sealed_sender_multi_recipient() -> Result<(), SignalProtocolError> {
         )
         .await;
 
-        match bob_ptext {
+        match bob_ptext.map_err(SignalProtocolError::without_context) {
             Err(SignalProtocolError::InvalidSealedSenderMessage(_)) => { /* ok */ }
             Err(err) => {
                 panic!("Unexpected error {}", err)
@@ -666,8 +666,8 @@ fn test_sealed_sender_multi_recipient_encrypt_with_archived_session(
     async {
         let mut rng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
 
@@ -771,15 +771,15 @@ fn test_sealed_sender_multi_recipient_encrypt_with_bad_registration_id(
     async {
         let mut rng = OsRng;
 
-        let alice_device_id = 23;
-        let bob_device_id = 42;
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
 
         let alice_uuid = "9d0652a3-dcc3-4d11-975f-74d61598733f".to_string();
         let bob_uuid = "796abedb-ca4e-4f18-8803-1fde5b921f9f".to_string();
 
-        let bob_uuid_address = ProtocolAddress::new(bob_uuid.clone(), bob_device_id.into());
+        let bob_uuid_address = ProtocolAddress::new(bob_uuid.clone(), bob_device_id);
 
         let mut alice_store = support::test_in_memory_protocol_store()?;
         let mut bob_store =
@@ -811,7 +811,7 @@ fn test_sealed_sender_multi_recipient_encrypt_with_bad_registration_id(
             alice_uuid.clone(),
             Some(alice_e164.clone()),
             alice_pubkey,
-            alice_device_id.into(),
+            alice_device_id,
             expires,
             server_cert,
             &server_key.private_key,
@@ -867,15 +867,16 @@ fn test_decryption_error_in_sealed_sender() -> Result<(), SignalProtocolError> {
     async {
         let mut rng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
 
         let alice_e164 = "+14151111111".to_owned();
 
         let alice_uuid = "9d0652a3-dcc3-4d11-975f-74d61598733f".to_string();
         let bob_uuid = "796abedb-ca4e-4f18-8803-1fde5b921f9f".to_string();
 
-        let alice_uuid_address = ProtocolAddress::new(alice_uuid.clone(), 1.into());
+        let alice_uuid_address =
+            ProtocolAddress::new(alice_uuid.clone(), DeviceId::new(1).expect("valid"));
         let bob_uuid_address = ProtocolAddress::new(bob_uuid.clone(), bob_device_id);
 
         let mut alice_store = support::test_in_memory_protocol_store()?;
@@ -1013,8 +1014,8 @@ fn test_sealed_sender_multi_recipient_redundant_empty_devices() -> Result<(), Si
     async {
         let mut csprng = OsRng;
 
-        let alice_device_id: DeviceId = 23.into();
-        let bob_device_id: DeviceId = 42.into();
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
 
         let alice_uuid = "9d0652a3-dcc3-4d11-975f-74d61598733f".to_string();
         let bob_uuid = "796abedb-ca4e-4f18-8803-1fde5b921f9f".to_string();
@@ -1102,3 +1103,383 @@ fn test_sealed_sender_multi_recipient_redundant_empty_devices() -> Result<(), Si
     .now_or_never()
     .expect("sync")
 }
+
+#[test]
+fn test_sealed_sender_multi_recipient_fan_out() -> Result<(), SignalProtocolError> {
+    async {
+        let mut csprng = OsRng;
+
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
+
+        let alice_uuid = "9d0652a3-dcc3-4d11-975f-74d61598733f".to_string();
+        let bob_uuid = "796abedb-ca4e-4f18-8803-1fde5b921f9f".to_string();
+        let carol_uuid = "38381c3b-2606-4ca7-9310-7cb927f2ab4a".to_string();
+
+        let bob_uuid_address = ProtocolAddress::new(bob_uuid.clone(), bob_device_id);
+        let bob_service_id = ServiceId::parse_from_service_id_string(&bob_uuid).expect("valid");
+        let carol_service_id = ServiceId::parse_from_service_id_string(&carol_uuid).expect("valid");
+
+        let mut alice_store = support::test_in_memory_protocol_store()?;
+        let mut bob_store = support::test_in_memory_protocol_store()?;
+
+        let alice_pubkey = *alice_store.get_identity_key_pair().await?.public_key();
+
+        let bob_pre_key_bundle = create_pre_key_bundle(&mut bob_store, &mut csprng).await?;
+
+        process_prekey_bundle(
+            &bob_uuid_address,
+            &mut alice_store.session_store,
+            &mut alice_store.identity_store,
+            &bob_pre_key_bundle,
+            SystemTime::now(),
+            &mut csprng,
+        )
+        .await?;
+
+        let trust_root = KeyPair::generate(&mut csprng);
+        let server_key = KeyPair::generate(&mut csprng);
+
+        let server_cert = ServerCertificate::new(
+            1,
+            server_key.public_key,
+            &trust_root.private_key,
+            &mut csprng,
+        )?;
+
+        let sender_cert = SenderCertificate::new(
+            alice_uuid.clone(),
+            None,
+            alice_pubkey,
+            alice_device_id,
+            Timestamp::from_epoch_millis(1605722925),
+            server_cert,
+            &server_key.private_key,
+            &mut csprng,
+        )?;
+
+        let alice_usmc = UnidentifiedSenderMessageContent::new(
+            CiphertextMessageType::SenderKey,
+            sender_cert,
+            vec![],
+            ContentHint::Implicit,
+            Some([42].to_vec()),
+        )?;
+
+        let recipients = [&bob_uuid_address];
+        let alice_ctext = sealed_sender_multi_recipient_encrypt(
+            &recipients,
+            &alice_store
+                .session_store
+                .load_existing_sessions(&recipients)?,
+            [carol_service_id],
+            &alice_usmc,
+            &alice_store.identity_store,
+            &mut csprng,
+        )
+        .await?;
+
+        let parsed = SealedSenderV2SentMessage::parse(&alice_ctext)?;
+        let bob_registration_id = bob_store.get_local_registration_id().await?;
+        assert_eq!(
+            parsed.recipient_devices().collect::<Vec<_>>(),
+            [(
+                bob_service_id,
+                bob_device_id,
+                bob_registration_id.try_into().expect("valid")
+            )]
+        );
+
+        let routing_info = parsed.routing_info();
+        assert_eq!(
+            routing_info.destinations,
+            [SealedSenderV2Destination {
+                service_id: bob_service_id,
+                devices: vec![(
+                    bob_device_id,
+                    bob_registration_id.try_into().expect("valid")
+                )],
+            }]
+        );
+        assert_eq!(routing_info.excluded_recipients, [carol_service_id]);
+        assert_eq!(
+            parsed.offset_of_shared_bytes() + routing_info.shared_payload_len,
+            alice_ctext.len()
+        );
+
+        // Carol was excluded, so only Bob gets a message.
+        let received = parsed.received_messages().collect::<Vec<_>>();
+        assert_eq!(received.len(), 1);
+        let (service_id, bob_ctext) = &received[0];
+        assert_eq!(service_id, &bob_service_id);
+        let bob_usmc = sealed_sender_decrypt_to_usmc(bob_ctext, &bob_store.identity_store).await?;
+        assert_eq!(bob_usmc.serialized()?, alice_usmc.serialized()?);
+
+        // A single-recipient view fans out to the same message.
+        let bob_view = parsed
+            .single_recipient_view(&bob_service_id)
+            .expect("Bob is a recipient");
+        let bob_view = SealedSenderV2SentMessage::parse(&bob_view)?;
+        assert_eq!(bob_view.recipients.len(), 1);
+        assert_eq!(
+            bob_view.recipient_devices().collect::<Vec<_>>(),
+            parsed.recipient_devices().collect::<Vec<_>>()
+        );
+        assert_eq!(bob_view.received_messages().collect::<Vec<_>>(), received);
+
+        let carol_view = parsed
+            .single_recipient_view(&carol_service_id)
+            .expect("Carol is listed");
+        let carol_view = SealedSenderV2SentMessage::parse(&carol_view)?;
+        assert!(carol_view.recipients[0].devices.is_empty());
+        assert_eq!(carol_view.received_messages().count(), 0);
+
+        let dave_service_id =
+            ServiceId::parse_from_service_id_string("d7ec63bc-d4fa-4c1c-b9ad-3a1b5d5e0e4e")
+                .expect("valid");
+        assert!(parsed.single_recipient_view(&dave_service_id).is_none());
+
+        Ok(())
+    }
+    .now_or_never()
+    .expect("sync")
+}
+
+#[test]
+fn test_sealed_sender_rewrap_v1_as_v2() -> Result<(), SignalProtocolError> {
+    async {
+        let mut rng = OsRng;
+
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
+
+        let alice_uuid = "9d0652a3-dcc3-4d11-975f-74d61598733f".to_string();
+        let bob_uuid = "796abedb-ca4e-4f18-8803-1fde5b921f9f".to_string();
+
+        let bob_uuid_address = ProtocolAddress::new(bob_uuid.clone(), bob_device_id);
+
+        let mut alice_store = support::test_in_memory_protocol_store()?;
+        let mut bob_store = support::test_in_memory_protocol_store()?;
+
+        let alice_pubkey = *alice_store.get_identity_key_pair().await?.public_key();
+
+        let bob_pre_key_bundle = create_pre_key_bundle(&mut bob_store, &mut rng).await?;
+
+        process_prekey_bundle(
+            &bob_uuid_address,
+            &mut alice_store.session_store,
+            &mut alice_store.identity_store,
+            &bob_pre_key_bundle,
+            SystemTime::now(),
+            &mut rng,
+        )
+        .await?;
+
+        let trust_root = KeyPair::generate(&mut rng);
+        let server_key = KeyPair::generate(&mut rng);
+
+        let server_cert =
+            ServerCertificate::new(1, server_key.public_key, &trust_root.private_key, &mut rng)?;
+
+        let expires = Timestamp::from_epoch_millis(1605722925);
+
+        let sender_cert = SenderCertificate::new(
+            alice_uuid.clone(),
+            None,
+            alice_pubkey,
+            alice_device_id,
+            expires,
+            server_cert,
+            &server_key.private_key,
+            &mut rng,
+        )?;
+
+        let alice_ptext = vec![1, 2, 3, 23, 99];
+        let v1_ctext = sealed_sender_encrypt(
+            &bob_uuid_address,
+            &sender_cert,
+            &alice_ptext,
+            &mut alice_store.session_store,
+            &mut alice_store.identity_store,
+            SystemTime::now(),
+            &mut rng,
+        )
+        .await?;
+        assert_eq!(
+            sealed_sender_message_version(&v1_ctext)?,
+            SealedSenderMessageVersion::V1
+        );
+
+        let v2_ctext =
+            sealed_sender_rewrap_v1_as_v2(&v1_ctext, &bob_store.identity_store, &mut rng).await?;
+        assert_eq!(
+            sealed_sender_message_version(&v2_ctext)?,
+            SealedSenderMessageVersion::V2
+        );
+        assert_eq!(
+            sealed_sender_rewrap_v1_as_v2(&v2_ctext, &bob_store.identity_store, &mut rng).await?,
+            v2_ctext
+        );
+
+        // Only Bob can rewrap a message sent to him.
+        assert!(
+            sealed_sender_rewrap_v1_as_v2(&v1_ctext, &alice_store.identity_store, &mut rng)
+                .await
+                .is_err()
+        );
+
+        let bob_ptext = sealed_sender_decrypt(
+            &v2_ctext,
+            &trust_root.public_key,
+            expires.sub_millis(1),
+            None,
+            bob_uuid.clone(),
+            bob_device_id,
+            &mut bob_store.identity_store,
+            &mut bob_store.session_store,
+            &mut bob_store.pre_key_store,
+            &bob_store.signed_pre_key_store,
+            &mut bob_store.kyber_pre_key_store,
+        )
+        .await?;
+
+        assert_eq!(bob_ptext.message, alice_ptext);
+        assert_eq!(bob_ptext.sender_uuid, alice_uuid);
+        assert_eq!(bob_ptext.device_id, alice_device_id);
+
+        assert!(sealed_sender_message_version(&[]).is_err());
+        assert!(matches!(
+            sealed_sender_message_version(&[0x70]),
+            Err(SignalProtocolError::UnknownSealedSenderVersion(7))
+        ));
+
+        Ok(())
+    }
+    .now_or_never()
+    .expect("sync")
+}
+
+#[test]
+fn test_sealed_sender_in_envelope() -> Result<(), SignalProtocolError> {
+    async {
+        let mut rng = OsRng;
+
+        let alice_device_id = DeviceId::new(23).expect("valid");
+        let bob_device_id = DeviceId::new(42).expect("valid");
+
+        let alice_uuid = "9d0652a3-dcc3-4d11-975f-74d61598733f".to_string();
+        let bob_uuid = "796abedb-ca4e-4f18-8803-1fde5b921f9f".to_string();
+
+        let bob_uuid_address = ProtocolAddress::new(bob_uuid.clone(), bob_device_id);
+
+        let mut alice_store = support::test_in_memory_protocol_store()?;
+        let mut bob_store = support::test_in_memory_protocol_store()?;
+
+        let alice_pubkey = *alice_store.get_identity_key_pair().await?.public_key();
+
+        let bob_pre_key_bundle = create_pre_key_bundle(&mut bob_store, &mut rng).await?;
+
+        process_prekey_bundle(
+            &bob_uuid_address,
+            &mut alice_store.session_store,
+            &mut alice_store.identity_store,
+            &bob_pre_key_bundle,
+            SystemTime::now(),
+            &mut rng,
+        )
+        .await?;
+
+        let trust_root = KeyPair::generate(&mut rng);
+        let server_key = KeyPair::generate(&mut rng);
+
+        let server_cert =
+            ServerCertificate::new(1, server_key.public_key, &trust_root.private_key, &mut rng)?;
+
+        let expires = Timestamp::from_epoch_millis(1605722925);
+
+        let sender_cert = SenderCertificate::new(
+            alice_uuid.clone(),
+            None,
+            alice_pubkey,
+            alice_device_id,
+            expires,
+            server_cert,
+            &server_key.private_key,
+            &mut rng,
+        )?;
+
+        // Builds the envelope the server would deliver the message to Bob in.
+        let envelope_for = |content: &[u8]| {
+            let mut envelope = Vec::new();
+            // Envelope.Type.UNIDENTIFIED_SENDER
+            prost::encoding::int32::encode(1, &6, &mut envelope);
+            prost::encoding::uint64::encode(5, &1605722000, &mut envelope);
+            prost::encoding::bytes::encode(8, &content.to_vec(), &mut envelope);
+            prost::encoding::string::encode(13, &bob_uuid, &mut envelope);
+            envelope
+        };
+
+        let alice_ptext = vec![1, 2, 3, 23, 99];
+        let v1_ctext = sealed_sender_encrypt(
+            &bob_uuid_address,
+            &sender_cert,
+            &alice_ptext,
+            &mut alice_store.session_store,
+            &mut alice_store.identity_store,
+            SystemTime::now(),
+            &mut rng,
+        )
+        .await?;
+        let v2_ctext =
+            sealed_sender_rewrap_v1_as_v2(&v1_ctext, &bob_store.identity_store, &mut rng).await?;
+
+        for ctext in [&v1_ctext, &v2_ctext] {
+            let envelope =
+                Envelope::try_from(&envelope_for(ctext)[..]).expect("valid sealed sender envelope");
+            assert_eq!(envelope.source, None);
+            assert_eq!(
+                envelope.destination,
+                ServiceId::parse_from_service_id_string(&bob_uuid)
+            );
+            assert_eq!(envelope.timestamp, Timestamp::from_epoch_millis(1605722000));
+            assert!(matches!(envelope.content, EnvelopeContent::SealedSender(_)));
+            assert_eq!(envelope.content.envelope_type(), 6);
+            assert_eq!(envelope.content.serialized(), &ctext[..]);
+        }
+
+        // Both versions wrap the same message, so only one can be decrypted.
+        let envelope = Envelope::try_from(&envelope_for(&v2_ctext)[..]).expect("valid");
+        let bob_ptext = sealed_sender_decrypt(
+            envelope.content.serialized(),
+            &trust_root.public_key,
+            expires.sub_millis(1),
+            None,
+            bob_uuid.clone(),
+            bob_device_id,
+            &mut bob_store.identity_store,
+            &mut bob_store.session_store,
+            &mut bob_store.pre_key_store,
+            &bob_store.signed_pre_key_store,
+            &mut bob_store.kyber_pre_key_store,
+        )
+        .await?;
+        assert_eq!(bob_ptext.message, alice_ptext);
+        assert_eq!(bob_ptext.sender_uuid, alice_uuid);
+
+        // The content is checked against the sealed sender message versions
+        // before the envelope is handed off.
+        let mut unknown_version = v1_ctext.clone();
+        unknown_version[0] = 0x70;
+        assert!(matches!(
+            Envelope::try_from(&envelope_for(&unknown_version)[..]),
+            Err(EnvelopeError::InvalidContent(
+                "sealed sender",
+                SignalProtocolError::UnknownSealedSenderVersion(7)
+            ))
+        ));
+
+        Ok(())
+    }
+    .now_or_never()
+    .expect("sync")
+}
@@ -48,9 +48,9 @@ fn test_basic_prekey() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let bob_device_id: DeviceId = 1.into();
+            let bob_device_id = DeviceId::new(1).expect("valid");
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
+            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
             let bob_address = ProtocolAddress::new("+14151111112".to_owned(), bob_device_id);
 
             let mut bob_store_builder = TestStoreBuilder::new();
@@ -172,7 +172,8 @@ fn test_basic_prekey() -> TestResult {
             assert!(matches!(
                 decrypt(&mut bob_store_builder.store, &alice_address, &outgoing_message)
                     .await
-                    .unwrap_err(),
+                    .unwrap_err()
+                    .without_context(),
                 SignalProtocolError::UntrustedIdentity(a) if a == alice_address
             ));
 
@@ -255,12 +256,15 @@ fn test_chain_jump_over_limit() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
             let alice_store = &mut alice_store_builder.store;
 
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
             process_prekey_bundle(
                 &bob_address,
@@ -319,9 +323,9 @@ fn test_chain_jump_over_limit_with_self() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let device_id_1: DeviceId = 1.into();
+            let device_id_1 = DeviceId::new(1).expect("valid");
             let a1_address = ProtocolAddress::new("+14151111111".to_owned(), device_id_1);
-            let device_id_2: DeviceId = 2.into();
+            let device_id_2 = DeviceId::new(2).expect("valid");
             let a2_address = ProtocolAddress::new("+14151111111".to_owned(), device_id_2);
 
             let a1_store = &mut a1_store_builder.store;
@@ -368,14 +372,16 @@ fn test_chain_jump_over_limit_with_self() -> TestResult {
 fn test_bad_signed_pre_key_signature() -> TestResult {
     async {
         let mut csprng = OsRng;
-        let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+        let bob_address =
+            ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
         let mut alice_store = TestStoreBuilder::new().store;
         let bob_store_builder = TestStoreBuilder::new()
             .with_pre_key(31337.into())
             .with_signed_pre_key(22.into());
 
-        let good_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+        let good_bundle =
+            bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
         for bit in 0..8 * good_bundle
             .signed_pre_key_signature()
@@ -453,12 +459,15 @@ fn test_repeat_bundle_message() -> TestResult {
     ) -> TestResult {
         async {
             let mut csprng = OsRng;
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
             let alice_store = &mut alice_store_builder.store;
 
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
             process_prekey_bundle(
                 &bob_address,
@@ -587,10 +596,13 @@ fn test_bad_message_bundle() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
             let pre_key_id = bob_pre_key_bundle.pre_key_id()?.expect("has pre key id");
 
             let alice_store = &mut alice_store_builder.store;
@@ -687,12 +699,15 @@ fn test_optional_one_time_prekey() -> TestResult {
     ) -> TestResult {
         async {
             let mut csprng = OsRng;
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
             let alice_store = &mut alice_store_builder.store;
 
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
             process_prekey_bundle(
                 &bob_address,
@@ -762,8 +777,10 @@ fn test_message_key_limits() -> TestResult {
         async {
             let (alice_session_record, bob_session_record) = sessions;
 
-            let alice_address = ProtocolAddress::new("+14159999999".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14158888888".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14159999999".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14158888888".to_owned(), DeviceId::new(1).expect("valid"));
 
             let mut alice_store = TestStoreBuilder::new().store;
             let mut bob_store = TestStoreBuilder::new().store;
@@ -807,8 +824,12 @@ fn test_message_key_limits() -> TestResult {
             let err = decrypt(&mut bob_store, &alice_address, &inflight[5])
                 .await
                 .unwrap_err();
+            let context = err.context().expect("has context");
+            assert_eq!(context.operation(), "message_decrypt_signal");
+            assert_eq!(context.remote_address(), Some(&alice_address));
+            assert_eq!(context.has_session(), Some(true));
             assert!(matches!(
-                err,
+                err.without_context(),
                 SignalProtocolError::DuplicatedMessage(2300, 5)
             ));
             Ok(())
@@ -856,11 +877,15 @@ fn test_basic_simultaneous_initiate() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
-            let alice_pre_key_bundle = alice_store_builder.make_bundle_with_latest_keys(1.into());
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let alice_pre_key_bundle =
+                alice_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
             let alice_store = &mut alice_store_builder.store;
             let bob_store = &mut bob_store_builder.store;
@@ -1030,11 +1055,15 @@ fn test_simultaneous_initiate_with_lossage() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
-            let alice_pre_key_bundle = alice_store_builder.make_bundle_with_latest_keys(1.into());
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let alice_pre_key_bundle =
+                alice_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
             let alice_store = &mut alice_store_builder.store;
             let bob_store = &mut bob_store_builder.store;
@@ -1184,11 +1213,15 @@ fn test_simultaneous_initiate_lost_message() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
-            let alice_pre_key_bundle = alice_store_builder.make_bundle_with_latest_keys(1.into());
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let alice_pre_key_bundle =
+                alice_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
             let alice_store = &mut alice_store_builder.store;
             let bob_store = &mut bob_store_builder.store;
@@ -1345,13 +1378,16 @@ fn test_simultaneous_initiate_repeated_messages() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
             for _ in 0..15 {
-                let alice_pre_key_bundle =
-                    alice_store_builder.make_bundle_with_latest_keys(1.into());
-                let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+                let alice_pre_key_bundle = alice_store_builder
+                    .make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
+                let bob_pre_key_bundle = bob_store_builder
+                    .make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
                 process_prekey_bundle(
                     &bob_address,
@@ -1599,15 +1635,18 @@ fn test_simultaneous_initiate_lost_message_repeated_messages() -> TestResult {
         async {
             let mut csprng = OsRng;
 
-            let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-            let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+            let alice_address =
+                ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+            let bob_address =
+                ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
             let mut alice_store_builder = TestStoreBuilder::new();
             add_keys(&mut alice_store_builder);
             let mut bob_store_builder = TestStoreBuilder::new();
             add_keys(&mut bob_store_builder);
 
-            let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+            let bob_pre_key_bundle =
+                bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
             process_prekey_bundle(
                 &bob_address,
@@ -1629,10 +1668,10 @@ fn test_simultaneous_initiate_lost_message_repeated_messages() -> TestResult {
                 add_keys(&mut alice_store_builder);
                 add_keys(&mut bob_store_builder);
 
-                let alice_pre_key_bundle =
-                    alice_store_builder.make_bundle_with_latest_keys((i + 2).into());
-                let bob_pre_key_bundle =
-                    bob_store_builder.make_bundle_with_latest_keys((i + 2).into());
+                let alice_pre_key_bundle = alice_store_builder
+                    .make_bundle_with_latest_keys(DeviceId::new(i + 2).expect("valid"));
+                let bob_pre_key_bundle = bob_store_builder
+                    .make_bundle_with_latest_keys(DeviceId::new(i + 2).expect("valid"));
 
                 process_prekey_bundle(
                     &bob_address,
@@ -1909,8 +1948,10 @@ fn test_simultaneous_initiate_lost_message_repeated_messages() -> TestResult {
 fn test_zero_is_a_valid_prekey_id() -> TestResult {
     async {
         let mut csprng = OsRng;
-        let alice_address = ProtocolAddress::new("+14151111111".to_owned(), 1.into());
-        let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+        let alice_address =
+            ProtocolAddress::new("+14151111111".to_owned(), DeviceId::new(1).expect("valid"));
+        let bob_address =
+            ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
         let mut alice_store = TestStoreBuilder::new().store;
         let mut bob_store_builder = TestStoreBuilder::new()
@@ -1918,7 +1959,8 @@ fn test_zero_is_a_valid_prekey_id() -> TestResult {
             .with_signed_pre_key(0.into())
             .with_kyber_pre_key(0.into());
 
-        let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+        let bob_pre_key_bundle =
+            bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
         process_prekey_bundle(
             &bob_address,
@@ -1976,7 +2018,8 @@ fn test_unacknowledged_sessions_eventually_expire() -> TestResult {
         const WELL_PAST_EXPIRATION: Duration = Duration::from_secs(60 * 60 * 24 * 90);
 
         let mut csprng = OsRng;
-        let bob_address = ProtocolAddress::new("+14151111112".to_owned(), 1.into());
+        let bob_address =
+            ProtocolAddress::new("+14151111112".to_owned(), DeviceId::new(1).expect("valid"));
 
         let mut alice_store = TestStoreBuilder::new().store;
         let bob_store_builder = TestStoreBuilder::new()
@@ -1984,7 +2027,8 @@ fn test_unacknowledged_sessions_eventually_expire() -> TestResult {
             .with_signed_pre_key(0.into())
             .with_kyber_pre_key(0.into());
 
-        let bob_pre_key_bundle = bob_store_builder.make_bundle_with_latest_keys(1.into());
+        let bob_pre_key_bundle =
+            bob_store_builder.make_bundle_with_latest_keys(DeviceId::new(1).expect("valid"));
 
         process_prekey_bundle(
             &bob_address,
@@ -2047,7 +2091,10 @@ fn test_unacknowledged_sessions_eventually_expire() -> TestResult {
         .await
         .unwrap_err();
         assert!(
-            matches!(&error, SignalProtocolError::SessionNotFound(addr) if addr == &bob_address),
+            matches!(
+                error.underlying_error(),
+                SignalProtocolError::SessionNotFound(addr) if addr == &bob_address
+            ),
             "{:?}",
             error
         );
@@ -2063,8 +2110,10 @@ fn run_session_interaction(alice_session: SessionRecord, bob_session: SessionRec
     async {
         use rand::seq::SliceRandom;
 
-        let alice_address = ProtocolAddress::new("+14159999999".to_owned(), 1.into());
-        let bob_address = ProtocolAddress::new("+14158888888".to_owned(), 1.into());
+        let alice_address =
+            ProtocolAddress::new("+14159999999".to_owned(), DeviceId::new(1).expect("valid"));
+        let bob_address =
+            ProtocolAddress::new("+14158888888".to_owned(), DeviceId::new(1).expect("valid"));
 
         let mut alice_store = TestStoreBuilder::new().store;
         let mut bob_store = TestStoreBuilder::new().store;
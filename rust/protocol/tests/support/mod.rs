@@ -84,14 +84,14 @@ pub async fn create_pre_key_bundle<R: Rng + CryptoRng>(
         .private_key()
         .calculate_signature(&kyber_pre_key_public, &mut csprng)?;
 
-    let device_id: u32 = csprng.gen();
+    let device_id = DeviceId::new(csprng.gen_range(1..=DeviceId::MAX)).expect("in range");
     let pre_key_id: u32 = csprng.gen();
     let signed_pre_key_id: u32 = csprng.gen();
     let kyber_pre_key_id: u32 = csprng.gen();
 
     let pre_key_bundle = PreKeyBundle::new(
         store.get_local_registration_id().await?,
-        device_id.into(),
+        device_id,
         Some((pre_key_id.into(), pre_key_pair.public_key)),
         signed_pre_key_id.into(),
         signed_pre_key_pair.public_key,
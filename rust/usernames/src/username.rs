@@ -132,6 +132,44 @@ impl Username {
         })
     }
 
+    /// The nickname, capitalized the way the user chose.
+    pub fn nickname(&self) -> &str {
+        &self.nickname
+    }
+
+    /// The username with the nickname in lowercase, which is the form that
+    /// gets hashed.
+    pub fn canonical(&self) -> String {
+        format!(
+            "{}.{}",
+            self.nickname.to_ascii_lowercase(),
+            self.discriminator
+        )
+    }
+
+    /// Returns this username with the nickname capitalized as in
+    /// `nickname_case`, for display.
+    ///
+    /// Returns `None` if `nickname_case` is not this username's nickname with
+    /// different capitalization. The result always has the same
+    /// [`hash`](Self::hash) as `self`.
+    pub fn display_case(&self, nickname_case: &str) -> Option<Self> {
+        if !nickname_case.eq_ignore_ascii_case(&self.nickname) {
+            return None;
+        }
+        Some(Self {
+            nickname: nickname_case.to_owned(),
+            discriminator: self.discriminator,
+            scalars: self.scalars.clone(),
+        })
+    }
+
+    /// Checks whether `other` is this username, possibly capitalized
+    /// differently; that is, whether the two have the same hash.
+    pub fn matches_canonically(&self, other: &str) -> bool {
+        Self::new(other).is_ok_and(|other| other.scalars == self.scalars)
+    }
+
     pub fn hash(&self) -> [u8; 32] {
         *Self::hash_from_scalars(&self.scalars).compress().as_bytes()
     }
@@ -378,6 +416,20 @@ mod test {
         }
     }
 
+    #[test]
+    fn display_case_keeps_canonical_hash() {
+        let username = Username::new("joeperson.42").expect("valid");
+        let cased = username.display_case("JoePerson").expect("same nickname");
+        assert_eq!(cased.to_string(), "JoePerson.42");
+        assert_eq!(cased.nickname(), "JoePerson");
+        assert_eq!(cased.canonical(), "joeperson.42");
+        assert_eq!(cased.hash(), username.hash());
+        assert!(cased.matches_canonically("JOEPERSON.42"));
+        assert!(!cased.matches_canonically("JoePerson.43"));
+
+        assert!(username.display_case("JoePersons").is_none());
+    }
+
     #[test]
     fn no_discriminator() {
         assert_eq!(
@@ -26,7 +26,7 @@ use crate::common::serialization::ReservedByte;
 use crate::common::sho::Sho;
 use crate::common::simple_types::*;
 use crate::generic_server_params::{GenericServerPublicParams, GenericServerSecretParams};
-use crate::{ZkGroupVerificationFailure, SECONDS_PER_DAY};
+use crate::ZkGroupVerificationFailure;
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
 struct BackupIdPoint(RistrettoPoint);
@@ -252,16 +252,7 @@ impl BackupAuthCredentialPresentation {
         current_time: Timestamp,
         server_params: &GenericServerSecretParams,
     ) -> Result<(), ZkGroupVerificationFailure> {
-        let acceptable_start_time = self
-            .redemption_time
-            .checked_sub_seconds(SECONDS_PER_DAY)
-            .ok_or(ZkGroupVerificationFailure)?;
-        let acceptable_end_time = self
-            .redemption_time
-            .checked_add_seconds(2 * SECONDS_PER_DAY)
-            .ok_or(ZkGroupVerificationFailure)?;
-
-        if !(acceptable_start_time..=acceptable_end_time).contains(&current_time) {
+        if !self.redemption_time.is_redeemable_at(current_time) {
             return Err(ZkGroupVerificationFailure);
         }
 
@@ -3,11 +3,13 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+mod group_call;
 pub mod group_params;
 mod group_send_endorsement;
 pub mod profile_key_ciphertext;
 pub mod uuid_ciphertext;
 
+pub use group_call::{GroupCallRingId, GroupCallRingProof, GROUP_CALL_RING_PROOF_LEN};
 pub use group_params::{GroupMasterKey, GroupPublicParams, GroupSecretParams};
 pub use group_send_endorsement::{
     GroupSendDerivedKeyPair, GroupSendEndorsement, GroupSendEndorsementsResponse,
@@ -0,0 +1,116 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Identifiers and proofs for ringing group calls.
+//!
+//! The calling server assigns each group call an opaque *era id*. Clients
+//! ringing the other members of the group refer to that era by a
+//! [`GroupCallRingId`], and attach a [`GroupCallRingProof`] showing that the
+//! ring came from someone who knows the group's secret params.
+//!
+//! Ring proofs are only ever checked by other members of the group, using
+//! [`GroupSecretParams::verify_group_call_ring_proof`]; neither the chat server
+//! nor the calling server sees the group's secret params, so there is no
+//! server-side implementation of the proof. Every client uses this module, and
+//! the known-answer tests in `tests/group_call_ring.rs` pin the derivation so
+//! that clients on different releases keep agreeing.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::api::groups::GroupSecretParams;
+use crate::common::errors::*;
+use crate::common::sho::*;
+
+pub const GROUP_CALL_RING_PROOF_LEN: usize = 32;
+
+/// The [`Sho`] label for deriving a [`GroupCallRingProof`] from a group's master key.
+///
+/// Changing this would make rings from older clients fail to verify.
+const GROUP_CALL_RING_PROOF_LABEL: &[u8] =
+    b"Signal_ZKGroup_20241016_GroupMasterKey_GroupCallRingProof";
+
+/// Identifies a ring for a particular group call era.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct GroupCallRingId(i64);
+
+impl GroupCallRingId {
+    /// Derives the ring id for a call era, as assigned by the calling server.
+    ///
+    /// This is the first eight bytes of the SHA-256 hash of the era id,
+    /// interpreted as a big-endian signed integer.
+    pub fn from_era_id(era_id: &str) -> Self {
+        let hash = Sha256::digest(era_id.as_bytes());
+        let (prefix, _) = hash.split_at(std::mem::size_of::<i64>());
+        Self(i64::from_be_bytes(
+            prefix.try_into().expect("correct length"),
+        ))
+    }
+
+    /// Checks whether this ring id belongs to the given call era.
+    pub fn matches_era_id(&self, era_id: &str) -> bool {
+        *self == Self::from_era_id(era_id)
+    }
+}
+
+impl From<i64> for GroupCallRingId {
+    fn from(value: i64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<GroupCallRingId> for i64 {
+    fn from(value: GroupCallRingId) -> Self {
+        value.0
+    }
+}
+
+/// Shows that a ring was sent by a member of the group.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct GroupCallRingProof {
+    bytes: [u8; GROUP_CALL_RING_PROOF_LEN],
+}
+
+impl GroupCallRingProof {
+    pub fn new(bytes: [u8; GROUP_CALL_RING_PROOF_LEN]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn get_bytes(&self) -> [u8; GROUP_CALL_RING_PROOF_LEN] {
+        self.bytes
+    }
+}
+
+impl GroupSecretParams {
+    /// Creates a proof that `ringer` is ringing the group for `ring_id`.
+    pub fn create_group_call_ring_proof(
+        &self,
+        ring_id: GroupCallRingId,
+        ringer: libsignal_core::Aci,
+    ) -> GroupCallRingProof {
+        let mut sho = Sho::new(GROUP_CALL_RING_PROOF_LABEL, &self.get_master_key().bytes);
+        sho.absorb_and_ratchet(&i64::from(ring_id).to_be_bytes());
+        sho.absorb_and_ratchet(&ringer.service_id_binary());
+        let mut bytes = [0u8; GROUP_CALL_RING_PROOF_LEN];
+        bytes.copy_from_slice(&sho.squeeze(GROUP_CALL_RING_PROOF_LEN));
+        GroupCallRingProof { bytes }
+    }
+
+    /// Checks a proof produced by [`Self::create_group_call_ring_proof`].
+    pub fn verify_group_call_ring_proof(
+        &self,
+        ring_id: GroupCallRingId,
+        ringer: libsignal_core::Aci,
+        proof: &GroupCallRingProof,
+    ) -> Result<(), ZkGroupVerificationFailure> {
+        let expected = self.create_group_call_ring_proof(ring_id, ringer);
+        if bool::from(expected.bytes.ct_eq(&proof.bytes)) {
+            Ok(())
+        } else {
+            Err(ZkGroupVerificationFailure)
+        }
+    }
+}
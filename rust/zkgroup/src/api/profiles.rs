@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+pub mod access_key;
 pub mod expiring_profile_key_credential;
 pub mod expiring_profile_key_credential_response;
 pub mod profile_key;
@@ -12,6 +13,7 @@ pub mod profile_key_credential_request;
 pub mod profile_key_credential_request_context;
 pub mod profile_key_version;
 
+pub use access_key::AccessKey;
 pub use expiring_profile_key_credential::ExpiringProfileKeyCredential;
 pub use expiring_profile_key_credential_response::ExpiringProfileKeyCredentialResponse;
 pub use profile_key::ProfileKey;
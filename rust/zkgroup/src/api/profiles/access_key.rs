@@ -0,0 +1,70 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use subtle::ConstantTimeEq;
+
+use crate::common::constants::*;
+use crate::profiles::ProfileKey;
+
+/// The unidentified access key (UAK) for an account.
+///
+/// Knowing an account's access key lets a sender deliver sealed sender
+/// messages to it and lets contact discovery return its ACI. It's derived
+/// from the account's profile key; keeping it as its own type, rather than as
+/// a bare 16-byte array, makes it harder to pass some other key in its place.
+#[derive(Copy, Clone)]
+pub struct AccessKey([u8; ACCESS_KEY_LEN]);
+
+impl AccessKey {
+    /// Derives the access key for the account with the given profile key.
+    pub fn derive_from(profile_key: &ProfileKey) -> Self {
+        Self(profile_key.derive_access_key())
+    }
+
+    /// Wraps an access key that was derived elsewhere, for example by an app
+    /// that has already computed it.
+    pub fn from_bytes(bytes: [u8; ACCESS_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; ACCESS_KEY_LEN] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for AccessKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AccessKey([REDACTED])")
+    }
+}
+
+impl PartialEq for AccessKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+}
+
+impl Eq for AccessKey {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_matches_profile_key() {
+        let profile_key = ProfileKey::create([0x42; PROFILE_KEY_LEN]);
+        let access_key = AccessKey::derive_from(&profile_key);
+        assert_eq!(access_key.as_bytes(), &profile_key.derive_access_key());
+        assert_eq!(
+            access_key,
+            AccessKey::from_bytes(profile_key.derive_access_key())
+        );
+        assert_ne!(
+            access_key,
+            AccessKey::derive_from(&ProfileKey::create([0x43; PROFILE_KEY_LEN]))
+        );
+        assert_eq!(format!("{access_key:?}"), "AccessKey([REDACTED])");
+    }
+}
@@ -87,6 +87,11 @@ impl ProfileKey {
         }
     }
 
+    /// Derives the unidentified access key for this profile key.
+    ///
+    /// Prefer [`AccessKey::derive_from`](api::profiles::AccessKey::derive_from)
+    /// when the key is going to be passed along, so it can't be mixed up with
+    /// other 16-byte values.
     pub fn derive_access_key(&self) -> [u8; ACCESS_KEY_LEN] {
         let nonce = &[0u8; AESGCM_NONCE_LEN];
         let mut cipher = Aes256GcmEncryption::new(&self.bytes, nonce, &[]).unwrap();
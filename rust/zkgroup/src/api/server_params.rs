@@ -166,14 +166,7 @@ impl ServerSecretParams {
         redemption_time: Timestamp,
         current_time: Timestamp,
     ) -> Result<(), ZkGroupVerificationFailure> {
-        let acceptable_start_time = redemption_time
-            .checked_sub_seconds(SECONDS_PER_DAY)
-            .ok_or(ZkGroupVerificationFailure)?;
-        let acceptable_end_time = redemption_time
-            .checked_add_seconds(2 * SECONDS_PER_DAY)
-            .ok_or(ZkGroupVerificationFailure)?;
-
-        if !(acceptable_start_time..=acceptable_end_time).contains(&current_time) {
+        if !redemption_time.is_redeemable_at(current_time) {
             return Err(ZkGroupVerificationFailure);
         }
 
@@ -523,14 +516,10 @@ impl ServerPublicParams {
             response.credential_expiration_time,
         )?;
 
-        if !response.credential_expiration_time.is_day_aligned() {
-            return Err(ZkGroupVerificationFailure);
-        }
-        let days_remaining = response
+        if !response
             .credential_expiration_time
-            .saturating_seconds_since(current_time)
-            / SECONDS_PER_DAY;
-        if days_remaining == 0 || days_remaining > 7 {
+            .is_acceptable_expiration_at(current_time)
+        {
             return Err(ZkGroupVerificationFailure);
         }
 
@@ -3,6 +3,9 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 //
 
+use std::ops::RangeInclusive;
+use std::time::SystemTime;
+
 use curve25519_dalek_signal::scalar::Scalar;
 use partial_default::PartialDefault;
 use serde::{Deserialize, Serialize};
@@ -73,6 +76,64 @@ impl Timestamp {
         self.0 % SECONDS_PER_DAY == 0
     }
 
+    /// The start of the UTC day containing `self`.
+    #[inline]
+    pub const fn start_of_day(&self) -> Self {
+        Self(self.0 - self.0 % SECONDS_PER_DAY)
+    }
+
+    /// The times at which a credential with this redemption time may be
+    /// presented: from a day before it through two days after, so that the
+    /// whole of the redemption day is covered with a day of clock skew on
+    /// either side.
+    ///
+    /// Returns `None` if the window doesn't fit in a `Timestamp`.
+    pub fn redemption_window(&self) -> Option<RangeInclusive<Self>> {
+        Some(
+            self.checked_sub_seconds(SECONDS_PER_DAY)?
+                ..=self.checked_add_seconds(2 * SECONDS_PER_DAY)?,
+        )
+    }
+
+    /// Whether a credential with redemption time `self` may be presented at
+    /// `current_time`.
+    pub fn is_redeemable_at(&self, current_time: Timestamp) -> bool {
+        self.redemption_window()
+            .is_some_and(|window| window.contains(&current_time))
+    }
+
+    /// The day-aligned redemption times whose credentials may be presented at
+    /// `current_time`, earliest first.
+    ///
+    /// This is normally three days, centered on the current one; exactly at
+    /// midnight UTC it is four, since the window is inclusive at both ends.
+    pub fn redeemable_days_at(current_time: Timestamp) -> impl Iterator<Item = Self> {
+        let today = current_time.start_of_day();
+        (-2..=1)
+            .filter_map(move |days: i64| {
+                let offset = days.unsigned_abs() * SECONDS_PER_DAY;
+                if days < 0 {
+                    today.checked_sub_seconds(offset)
+                } else {
+                    today.checked_add_seconds(offset)
+                }
+            })
+            .filter(move |day| day.is_redeemable_at(current_time))
+    }
+
+    /// Whether an expiring credential that expires at `self` is acceptable to
+    /// receive at `current_time`.
+    ///
+    /// The expiration must be day-aligned, so that the server can't use it to
+    /// fingerprint the client, and between one and seven whole days away.
+    pub fn is_acceptable_expiration_at(&self, current_time: Timestamp) -> bool {
+        if !self.is_day_aligned() {
+            return false;
+        }
+        let days_remaining = self.saturating_seconds_since(current_time) / SECONDS_PER_DAY;
+        (1..=7).contains(&days_remaining)
+    }
+
     #[inline]
     pub fn to_be_bytes(self) -> [u8; 8] {
         self.0.to_be_bytes()
@@ -86,6 +147,19 @@ impl Timestamp {
     }
 }
 
+/// Truncates to whole seconds; times before the epoch become the epoch itself.
+///
+/// This is how a [`SystemTime`] from an app-provided clock becomes the
+/// `current_time` of credential checks.
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Self(
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_secs()),
+        )
+    }
+}
+
 impl From<Timestamp> for std::time::SystemTime {
     fn from(Timestamp(seconds): Timestamp) -> Self {
         std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds)
@@ -132,4 +206,76 @@ mod tests {
             Ok(_) => unreachable!(),
         }
     }
+
+    const MIDNIGHT: Timestamp = Timestamp::from_epoch_seconds(100 * SECONDS_PER_DAY);
+
+    #[test]
+    fn redemption_window_boundaries() {
+        assert_eq!(MIDNIGHT.add_seconds(1).start_of_day(), MIDNIGHT);
+        assert_eq!(
+            MIDNIGHT.sub_seconds(1).start_of_day(),
+            MIDNIGHT.sub_seconds(SECONDS_PER_DAY)
+        );
+
+        assert!(MIDNIGHT.is_redeemable_at(MIDNIGHT.sub_seconds(SECONDS_PER_DAY)));
+        assert!(!MIDNIGHT.is_redeemable_at(MIDNIGHT.sub_seconds(SECONDS_PER_DAY + 1)));
+        assert!(MIDNIGHT.is_redeemable_at(MIDNIGHT.add_seconds(2 * SECONDS_PER_DAY)));
+        assert!(!MIDNIGHT.is_redeemable_at(MIDNIGHT.add_seconds(2 * SECONDS_PER_DAY + 1)));
+        assert!(
+            !Timestamp::from_epoch_seconds(0).is_redeemable_at(Timestamp::from_epoch_seconds(0))
+        );
+    }
+
+    #[test]
+    fn redeemable_days() {
+        let days = |now: Timestamp| {
+            Timestamp::redeemable_days_at(now)
+                .map(|day| {
+                    (day.epoch_seconds() as i64 - MIDNIGHT.epoch_seconds() as i64)
+                        / SECONDS_PER_DAY as i64
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(days(MIDNIGHT), [-2, -1, 0, 1]);
+        assert_eq!(days(MIDNIGHT.add_seconds(1)), [-1, 0, 1]);
+        assert_eq!(days(MIDNIGHT.sub_seconds(1)), [-2, -1, 0]);
+
+        for now in [MIDNIGHT, MIDNIGHT.add_seconds(1), MIDNIGHT.sub_seconds(1)] {
+            assert!(Timestamp::redeemable_days_at(now).all(|day| day.is_redeemable_at(now)));
+        }
+    }
+
+    #[test]
+    fn acceptable_expirations() {
+        let now = MIDNIGHT.add_seconds(60);
+        assert!(!MIDNIGHT
+            .add_seconds(SECONDS_PER_DAY)
+            .is_acceptable_expiration_at(now));
+        assert!(MIDNIGHT
+            .add_seconds(2 * SECONDS_PER_DAY)
+            .is_acceptable_expiration_at(now));
+        assert!(MIDNIGHT
+            .add_seconds(7 * SECONDS_PER_DAY)
+            .is_acceptable_expiration_at(now));
+        // Only whole days remaining are counted.
+        assert!(MIDNIGHT
+            .add_seconds(8 * SECONDS_PER_DAY)
+            .is_acceptable_expiration_at(now));
+        assert!(!MIDNIGHT
+            .add_seconds(9 * SECONDS_PER_DAY)
+            .is_acceptable_expiration_at(now));
+        assert!(!MIDNIGHT
+            .add_seconds(2 * SECONDS_PER_DAY + 1)
+            .is_acceptable_expiration_at(now));
+    }
+
+    #[test]
+    fn from_system_time() {
+        let time = SystemTime::from(MIDNIGHT) + std::time::Duration::from_millis(1500);
+        assert_eq!(Timestamp::from(time), MIDNIGHT.add_seconds(1));
+        assert_eq!(
+            Timestamp::from(SystemTime::UNIX_EPOCH - std::time::Duration::from_secs(1)),
+            Timestamp::from_epoch_seconds(0)
+        );
+    }
 }
@@ -9,6 +9,8 @@ pub mod api;
 pub mod common;
 /// cbindgen:ignore
 pub mod crypto;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
 pub use api::*;
 pub use common::constants::*;
 pub use common::errors::*;
@@ -0,0 +1,156 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+//! Deterministic generation of zkgroup test vectors.
+//!
+//! All the randomness used by a generator is derived from its 32-byte seed,
+//! so the same seed always produces the same output. Test suites for the
+//! app-facing bridges and independent implementations can use these to
+//! regenerate their fixtures instead of checking in serialized credentials,
+//! so the output for a given seed must not change; the tests pin a hash of it.
+
+use libsignal_core::Aci;
+
+use crate::common::sho::Sho;
+use crate::groups::{GroupMasterKey, GroupSecretParams};
+use crate::profiles::{ExpiringProfileKeyCredential, ProfileKey};
+use crate::{
+    RandomnessBytes, ServerPublicParams, ServerSecretParams, Timestamp, RANDOMNESS_LEN,
+    SECONDS_PER_DAY,
+};
+
+/// The expiration of every credential issued here, which is day-aligned.
+pub const CREDENTIAL_EXPIRATION: Timestamp = Timestamp::from_epoch_seconds(1_700_006_400);
+
+/// The time at which credentials are received and presented, two days before
+/// [`CREDENTIAL_EXPIRATION`].
+pub const CURRENT_TIME: Timestamp = CREDENTIAL_EXPIRATION.sub_seconds(2 * SECONDS_PER_DAY);
+
+/// The full issuance and presentation of an expiring profile key credential.
+pub struct ProfileKeyCredentialVector {
+    pub server_secret_params: ServerSecretParams,
+    pub server_public_params: ServerPublicParams,
+    pub group_secret_params: GroupSecretParams,
+    pub aci: Aci,
+    pub profile_key: ProfileKey,
+    /// The serialized `ProfileKeyCredentialRequest` sent to the server.
+    pub request: Vec<u8>,
+    /// The serialized `ExpiringProfileKeyCredentialResponse` sent back.
+    pub response: Vec<u8>,
+    pub credential: ExpiringProfileKeyCredential,
+    /// The serialized `ExpiringProfileKeyCredentialPresentation`, for the
+    /// group described by `group_secret_params`.
+    pub presentation: Vec<u8>,
+}
+
+/// Generates server params, then issues and presents a profile key
+/// credential with them.
+pub fn profile_key_credential(seed: [u8; 32]) -> ProfileKeyCredentialVector {
+    let mut sho = Sho::new(b"Signal_ZKGroup_TestVectors_ProfileKeyCredential", &seed);
+
+    let server_secret_params = ServerSecretParams::generate(next_randomness(&mut sho));
+    let server_public_params = server_secret_params.get_public_params();
+    let group_secret_params =
+        GroupSecretParams::derive_from_master_key(GroupMasterKey::new(next_randomness(&mut sho)));
+    let aci = Aci::from_uuid_bytes(
+        sho.squeeze(16)
+            .try_into()
+            .expect("squeezed the right length"),
+    );
+    let profile_key = ProfileKey::create(next_randomness(&mut sho));
+
+    let context = server_public_params.create_profile_key_credential_request_context(
+        next_randomness(&mut sho),
+        aci,
+        profile_key,
+    );
+    let request = context.get_request();
+    let response = server_secret_params
+        .issue_expiring_profile_key_credential(
+            next_randomness(&mut sho),
+            &request,
+            aci,
+            profile_key.get_commitment(aci),
+            CREDENTIAL_EXPIRATION,
+        )
+        .expect("request is valid");
+    let credential = server_public_params
+        .receive_expiring_profile_key_credential(&context, &response, CURRENT_TIME)
+        .expect("response is valid");
+    let presentation = server_public_params.create_expiring_profile_key_credential_presentation(
+        next_randomness(&mut sho),
+        group_secret_params,
+        credential,
+    );
+
+    ProfileKeyCredentialVector {
+        server_secret_params,
+        server_public_params,
+        group_secret_params,
+        aci,
+        profile_key,
+        request: crate::serialize(&request),
+        response: crate::serialize(&response),
+        credential,
+        presentation: crate::serialize(&presentation),
+    }
+}
+
+fn next_randomness(sho: &mut Sho) -> RandomnessBytes {
+    sho.squeeze(RANDOMNESS_LEN)
+        .try_into()
+        .expect("squeezed the right length")
+}
+
+#[cfg(test)]
+mod test {
+    use sha2::{Digest as _, Sha256};
+
+    use super::*;
+    use crate::profiles::ExpiringProfileKeyCredentialPresentation;
+
+    #[test]
+    fn profile_key_credential_is_deterministic_and_valid() {
+        let vector = profile_key_credential([1; 32]);
+        assert_eq!(
+            vector.presentation,
+            profile_key_credential([1; 32]).presentation
+        );
+        assert_ne!(
+            vector.presentation,
+            profile_key_credential([2; 32]).presentation
+        );
+
+        let presentation: ExpiringProfileKeyCredentialPresentation =
+            crate::deserialize(&vector.presentation).expect("valid");
+        vector
+            .server_secret_params
+            .verify_expiring_profile_key_credential_presentation(
+                vector.group_secret_params.get_public_params(),
+                &presentation,
+                CURRENT_TIME,
+            )
+            .expect("presentation is valid");
+    }
+
+    #[test]
+    fn profile_key_credential_is_pinned() {
+        let vector = profile_key_credential([1; 32]);
+        let mut hasher = Sha256::new();
+        for part in [
+            crate::serialize(&vector.server_public_params),
+            vector.request,
+            vector.response,
+            vector.presentation,
+        ] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "8f27bbce549669da45cd5e4ce06dc31a67e362ccec629325bd84a540a448ff56"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+use hex_literal::hex;
+use zkgroup::groups::{GroupCallRingId, GroupCallRingProof, GroupMasterKey, GroupSecretParams};
+use zkgroup::{RandomnessBytes, RANDOMNESS_LEN, UUID_LEN};
+
+const ERA_ID: &str = "1b0b2b6c3f0e4ad4";
+
+#[test]
+fn test_ring_id_from_era_id() {
+    let ring_id = GroupCallRingId::from_era_id(ERA_ID);
+    assert!(ring_id.matches_era_id(ERA_ID));
+    assert!(!ring_id.matches_era_id("some other era"));
+    assert_eq!(ring_id, GroupCallRingId::from(i64::from(ring_id)));
+}
+
+#[test]
+fn test_ring_proof() {
+    let randomness: RandomnessBytes = [0x42u8; RANDOMNESS_LEN];
+    let group_secret_params = GroupSecretParams::generate(randomness);
+    let ringer = libsignal_core::Aci::from_uuid_bytes([0x04u8; UUID_LEN]);
+    let ring_id = GroupCallRingId::from_era_id(ERA_ID);
+
+    let proof = group_secret_params.create_group_call_ring_proof(ring_id, ringer);
+    group_secret_params
+        .verify_group_call_ring_proof(ring_id, ringer, &proof)
+        .expect("valid proof");
+
+    // Members of the same group derive the same params from the master key.
+    let same_group =
+        GroupSecretParams::derive_from_master_key(group_secret_params.get_master_key());
+    same_group
+        .verify_group_call_ring_proof(ring_id, ringer, &proof)
+        .expect("valid proof");
+
+    let other_ring_id = GroupCallRingId::from_era_id("some other era");
+    assert!(group_secret_params
+        .verify_group_call_ring_proof(other_ring_id, ringer, &proof)
+        .is_err());
+
+    let other_ringer = libsignal_core::Aci::from_uuid_bytes([0x05u8; UUID_LEN]);
+    assert!(group_secret_params
+        .verify_group_call_ring_proof(ring_id, other_ringer, &proof)
+        .is_err());
+
+    let other_group = GroupSecretParams::generate([0x43u8; RANDOMNESS_LEN]);
+    assert!(other_group
+        .verify_group_call_ring_proof(ring_id, ringer, &proof)
+        .is_err());
+}
+
+#[test]
+fn test_ring_id_known_answer() {
+    // The first eight bytes of SHA-256(era id), big-endian.
+    assert_eq!(
+        GroupCallRingId::from_era_id(ERA_ID),
+        GroupCallRingId::from(0x235346ac3acec703)
+    );
+    assert_eq!(
+        GroupCallRingId::from_era_id(""),
+        GroupCallRingId::from(0xe3b0c44298fc1c14u64 as i64)
+    );
+}
+
+#[test]
+fn test_ring_proof_known_answer() {
+    let master_key = GroupMasterKey::new([0x42u8; 32]);
+    let group_secret_params = GroupSecretParams::derive_from_master_key(master_key);
+    let ringer = libsignal_core::Aci::from_uuid_bytes([0x04u8; UUID_LEN]);
+    let ring_id = GroupCallRingId::from(0x0102_0304_0506_0708);
+
+    let proof = group_secret_params.create_group_call_ring_proof(ring_id, ringer);
+    let expected = GroupCallRingProof::new(hex!(
+        "4558521379952ffdc9a9107b1104c762fc91939a92dd4a8032ac5f0c90c2c1ff"
+    ));
+    assert_eq!(proof.get_bytes(), expected.get_bytes());
+    group_secret_params
+        .verify_group_call_ring_proof(ring_id, ringer, &expected)
+        .expect("valid proof");
+}
@@ -0,0 +1,35 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+import Foundation
+import SignalFfi
+
+/// Opt-in timing of calls into libsignal's native code.
+///
+/// While enabled, every call records how long it ran, and asynchronous calls additionally record how long they waited before starting to run. Timings are aggregated per operation, so they can be collected in the field to find slow or blocked operations.
+public enum BridgeInstrumentation {
+    /// Turns recording on or off.
+    ///
+    /// Turning recording off does not discard timings collected so far; use ``reset()`` for that.
+    public static func setEnabled(_ enabled: Bool) {
+        failOnError(signal_instrumentation_set_enabled(enabled))
+    }
+
+    /// Discards all recorded timings.
+    public static func reset() {
+        failOnError(signal_instrumentation_reset())
+    }
+
+    /// Returns the timings recorded so far, as a JSON object keyed by operation name (such as `SessionCipher_EncryptMessage`).
+    ///
+    /// Each operation has `duration` and `queue_latency` histograms (the latter is empty for synchronous operations). Each histogram has `count`, `total_micros`, and `max_micros` fields, plus a `buckets` array: `buckets[0]` counts durations under 1µs, and `buckets[i]` counts durations from 2^(i-1)µs up to 2^i µs. The last bucket also counts anything longer.
+    public static func snapshotJSON() -> String {
+        failOnError {
+            try invokeFnReturningString {
+                signal_instrumentation_snapshot_json($0)
+            }
+        }
+    }
+}
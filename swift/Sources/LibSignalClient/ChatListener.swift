@@ -24,7 +24,8 @@ extension ConnectionEventsListener {
 public protocol ChatListener: ConnectionEventsListener<AuthenticatedChatService> {
     /// Called when the server delivers an incoming message to the client.
     ///
-    /// `serverDeliveryTimestamp` is in milliseconds.
+    /// `envelope` is the server's serialized envelope; use ``Envelope/init(bytes:)`` to validate and
+    /// parse it rather than decoding it by hand. `serverDeliveryTimestamp` is in milliseconds.
     ///
     /// If `sendAck` is not called, the server will leave this message in the message queue and
     /// attempt to deliver it again in the future.
@@ -12,13 +12,25 @@ public enum KeyFormat: UInt8, CaseIterable {
     case keySpecific = 1
 }
 
+public enum DeviceTransferKeyType: CaseIterable {
+    // RSA is the default for backward compatibility
+    case rsa
+    // Much faster to generate than RSA
+    case ecdsaP256
+}
+
 public struct DeviceTransferKey {
     public let privateKey: [UInt8]
 
-    public static func generate(formattedAs keyFormat: KeyFormat = .pkcs8) -> Self {
+    public static func generate(formattedAs keyFormat: KeyFormat = .pkcs8, ofType keyType: DeviceTransferKeyType = .rsa) -> Self {
         let privateKey = failOnError {
             try invokeFnReturningArray {
-                signal_device_transfer_generate_private_key_with_format($0, keyFormat.rawValue)
+                switch keyType {
+                case .rsa:
+                    return signal_device_transfer_generate_private_key_with_format($0, keyFormat.rawValue)
+                case .ecdsaP256:
+                    return signal_device_transfer_generate_ec_private_key_with_format($0, keyFormat.rawValue)
+                }
             }
         }
 
@@ -43,4 +55,36 @@ public struct DeviceTransferKey {
             }
         }
     }
+
+    /// Generates a self-signed certificate with explicit subject fields, validity, and serial number.
+    ///
+    /// - Parameters:
+    ///   - backdateSeconds: How far in the past the certificate's validity starts, to allow for
+    ///     clock skew between the two devices.
+    ///   - randomSerialNumber: Whether to use a random serial number instead of zero.
+    public func generateCertificate(
+        commonName: String,
+        organization: String?,
+        organizationalUnit: String?,
+        daysTilExpire: Int,
+        backdateSeconds: Int,
+        randomSerialNumber: Bool
+    ) -> [UInt8] {
+        return self.privateKey.withUnsafeBorrowedBuffer { privateKeyBuffer in
+            failOnError {
+                try invokeFnReturningArray {
+                    signal_device_transfer_generate_certificate_with_params(
+                        $0,
+                        privateKeyBuffer,
+                        commonName,
+                        organization,
+                        organizationalUnit,
+                        UInt32(daysTilExpire),
+                        UInt32(backdateSeconds),
+                        randomSerialNumber
+                    )
+                }
+            }
+        }
+    }
 }
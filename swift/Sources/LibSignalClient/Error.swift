@@ -51,6 +51,8 @@ public enum SignalError: Error {
     case unsupportedMediaInput(String)
     case callbackError(String)
     case webSocketError(String)
+    /// A server rejected a request with the given HTTP status.
+    case httpError(status: UInt16, message: String)
     case connectionTimeoutError(String)
     case connectionFailed(String)
     case networkProtocolError(String)
@@ -87,6 +89,12 @@ internal func checkError(_ error: SignalFfiErrorRef?) throws {
     case SignalErrorCodeInvalidState:
         throw SignalError.invalidState(errStr)
     case SignalErrorCodeInternalError:
+        // Include the Rust backtrace for panics, so it ends up in crash reports.
+        if let backtrace = try? invokeFnReturningString(fn: {
+            signal_error_get_backtrace(error, $0)
+        }) {
+            throw SignalError.internalError("\(errStr)\n\(backtrace)")
+        }
         throw SignalError.internalError(errStr)
     case SignalErrorCodeNullParameter:
         throw SignalError.nullParameter(errStr)
@@ -177,6 +185,11 @@ internal func checkError(_ error: SignalFfiErrorRef?) throws {
     case SignalErrorCodeCallbackError:
         throw SignalError.callbackError(errStr)
     case SignalErrorCodeWebSocket:
+        if let status = try? invokeFnReturningInteger(fn: {
+            signal_error_get_http_status(error, $0)
+        }) {
+            throw SignalError.httpError(status: status, message: errStr)
+        }
         throw SignalError.webSocketError(errStr)
     case SignalErrorCodeConnectionTimedOut:
         throw SignalError.connectionTimeoutError(errStr)
@@ -0,0 +1,38 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+import Foundation
+import SignalFfi
+
+/// Opt-in accounting of native objects held by Swift wrappers, to help diagnose native memory growth.
+///
+/// While enabled, libsignal tracks every native object it hands to Swift until the wrapper is deinitialized.
+public enum HandleAccounting {
+    /// Turns tracking on or off.
+    ///
+    /// Only objects created while tracking is on are counted. Turning tracking off forgets all tracked objects.
+    public static func setEnabled(_ enabled: Bool) {
+        failOnError(signal_handle_accounting_set_enabled(enabled))
+    }
+
+    /// Logs (through the ``LibsignalLogger``) any tracked object that stays alive longer than `threshold`, once per object.
+    ///
+    /// Pass `nil` to turn off leak detection. The threshold is rounded down to whole seconds.
+    public static func setLeakThreshold(_ threshold: TimeInterval?) {
+        let seconds = threshold.map { UInt32(max(0, min($0, TimeInterval(UInt32.max)))) } ?? 0
+        failOnError(signal_handle_accounting_set_leak_threshold_seconds(seconds))
+    }
+
+    /// Returns the live tracked objects as a JSON object keyed by type name (such as `SessionRecord`).
+    ///
+    /// Each type has `count`, `approximate_bytes`, and `oldest_age_secs` fields. Sizes only count each object itself, not any data it owns.
+    public static func snapshotJSON() -> String {
+        failOnError {
+            try invokeFnReturningString {
+                signal_handle_accounting_snapshot_json($0)
+            }
+        }
+    }
+}
@@ -39,6 +39,11 @@ public protocol LibsignalLogger: Sendable {
     /// This method may be called on any thread, and will be called synchronously from the middle of complicated operations; endeavor to make it quick!
     func log(level: LibsignalLogLevel, file: UnsafePointer<CChar>?, line: UInt32, message: UnsafePointer<CChar>)
 
+    /// Requests that a log message be output at the given log level, with the Rust module that produced it.
+    ///
+    /// The default implementation drops `target` and calls `log(level:file:line:message:)`. As with that method, the C strings are only valid for the duration of the call.
+    func log(level: LibsignalLogLevel, target: UnsafePointer<CChar>, file: UnsafePointer<CChar>?, line: UInt32, message: UnsafePointer<CChar>)
+
     /// Requests that the log be flushed.
     ///
     /// This may be called before a fatal error, so it should be handled synchronously if possible, even if that causes a delay.
@@ -55,6 +60,10 @@ public protocol LibsignalLogger: Sendable {
 }
 
 extension LibsignalLogger {
+    public func log(level: LibsignalLogLevel, target: UnsafePointer<CChar>, file: UnsafePointer<CChar>?, line: UInt32, message: UnsafePointer<CChar>) {
+        log(level: level, file: file, line: line, message: message)
+    }
+
     public func logFatal(file: UnsafePointer<CChar>?, line: UInt32, message: UnsafePointer<CChar>) -> Never {
         log(level: .error, file: file, line: line, message: Thread.callStackSymbols.joined(separator: "\n"))
         log(level: .error, file: file, line: line, message: message)
@@ -68,12 +77,12 @@ extension LibsignalLogger {
         let opaqueBridge = Unmanaged.passRetained(bridge)
         let success = signal_init_logger(level.asFFI, SignalFfiLogger(
             ctx: opaqueBridge.toOpaque(),
-            log: { ctx, _, ffiLevel, file, line, message in
+            log: { ctx, target, ffiLevel, file, line, message in
                 let bridge: LoggerBridge = Unmanaged.fromOpaque(ctx!).takeUnretainedValue()
                 // Unknown log levels might have personal info in them, so map them to something low.
                 let level = LibsignalLogLevel(ffiLevel) ?? .debug
                 "".withCString { emptyStringPtr in
-                    bridge.logger.log(level: level, file: file, line: line, message: message ?? emptyStringPtr)
+                    bridge.logger.log(level: level, target: target ?? emptyStringPtr, file: file, line: line, message: message ?? emptyStringPtr)
                 }
             },
             flush: { ctx in
@@ -92,6 +101,24 @@ extension LibsignalLogger {
     }
 }
 
+/// Overrides the most verbose level logged for `target` and any modules within it, or removes the override if `level` is `nil`.
+///
+/// A target is a Rust crate or module path, such as `libsignal_net` or `libsignal_net::chat`. When several overrides match, the most specific one wins.
+public func setLibsignalLogLevel(_ level: LibsignalLogLevel?, forTarget target: String) {
+    if let level {
+        _ = signal_set_log_target_level(target, level.asFFI)
+    } else {
+        _ = signal_clear_log_target_level(target)
+    }
+}
+
+/// Limits `target` and any modules within it to `maxPerSecond` logs each second, or removes the limit if `maxPerSecond` is `nil`.
+///
+/// Logs over the limit are dropped, and a warning reporting how many were dropped is logged once the target is logging again.
+public func setLibsignalLogRateLimit(_ maxPerSecond: UInt32?, forTarget target: String) {
+    _ = signal_set_log_target_rate_limit(target, maxPerSecond ?? 0)
+}
+
 /// A context-pointer-compatible wrapper around a logger.
 internal class LoggerBridge {
     let logger: any LibsignalLogger
@@ -22,6 +22,29 @@ public class Net {
         case production = 1
     }
 
+    /// Settings for the background threads libsignal uses for network operations.
+    ///
+    /// The defaults use one worker thread per CPU core. On constrained devices, consider limiting
+    /// the number of worker threads, or running everything on a single thread with
+    /// `useCurrentThread`.
+    public struct AsyncRuntimeConfig {
+        /// The number of worker threads, or `nil` to use one per CPU core.
+        ///
+        /// Ignored if `useCurrentThread` is set.
+        public var workerThreads: Int?
+        /// The name given to libsignal's threads.
+        public var threadName: String
+        /// Whether to run all operations on a single background thread instead of a pool of
+        /// worker threads.
+        public var useCurrentThread: Bool
+
+        public init(workerThreads: Int? = nil, threadName: String = "libsignal-tokio-worker", useCurrentThread: Bool = false) {
+            self.workerThreads = workerThreads
+            self.threadName = threadName
+            self.useCurrentThread = useCurrentThread
+        }
+    }
+
     /// An SVR3 client providing backup and restore functionality.
     public let svr3: Svr3Client
 
@@ -32,6 +55,24 @@ public class Net {
         self.svr3 = Svr3Client(self.asyncContext, self.connectionManager)
     }
 
+    /// Creates a new `Net` instance whose background threads are configured by `runtimeConfig`.
+    ///
+    /// - Throws: if the threads could not be started.
+    public init(env: Environment, userAgent: String, runtimeConfig: AsyncRuntimeConfig) throws {
+        self.asyncContext = try TokioAsyncContext(config: runtimeConfig)
+        self.connectionManager = ConnectionManager(env: env, userAgent: userAgent)
+        self.svr3 = Svr3Client(self.asyncContext, self.connectionManager)
+    }
+
+    /// Cancels all outstanding operations and stops libsignal's background threads.
+    ///
+    /// Blocks for up to `timeout` seconds while cancelled operations finish, throwing
+    /// `CancellationError`. Operations started afterwards are cancelled the same way. Calling this
+    /// more than once has no further effect.
+    public func shutdown(timeout: TimeInterval) {
+        self.asyncContext.shutdown(timeout: timeout)
+    }
+
     /// Sets the proxy host to be used for all new connections (until overridden).
     ///
     /// Sets a domain name and port to be used to proxy all new outgoing connections. The proxy can
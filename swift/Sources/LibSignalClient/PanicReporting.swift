@@ -0,0 +1,47 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+import Foundation
+import SignalFfi
+
+/// Information about a panic (an internal error) in libsignal.
+public struct LibsignalPanicReport: Sendable {
+    public var message: String
+    /// The location of the panic in the Rust sources, as `file:line:column`.
+    public var location: String?
+    /// A symbolized Rust backtrace from the point of the panic, if one could be captured.
+    public var backtrace: String?
+}
+
+/// Registers a closure to be told about every panic in libsignal, e.g. to forward it to a crash reporter, or removes it if `reporter` is `nil`.
+///
+/// Panics in bridged calls are also thrown as ``SignalError/internalError(_:)``, but the closure sees panics on background threads as well. It is called synchronously on the panicking thread, and must return quickly.
+public func setLibsignalPanicReporter(_ reporter: (@Sendable (LibsignalPanicReport) -> Void)?) {
+    guard let reporter else {
+        signal_clear_panic_reporter()
+        return
+    }
+    // A panic on another thread may still be using a previous reporter, so reporters are never released.
+    let opaqueReporter = Unmanaged.passRetained(PanicReporterBox(reporter)).toOpaque()
+    signal_set_panic_reporter(SignalFfiPanicReporter(
+        ctx: opaqueReporter,
+        report: { ctx, message, location, backtrace in
+            let box: PanicReporterBox = Unmanaged.fromOpaque(ctx!).takeUnretainedValue()
+            box.reporter(LibsignalPanicReport(
+                message: message.map { String(cString: $0) } ?? "",
+                location: location.map { String(cString: $0) },
+                backtrace: backtrace.map { String(cString: $0) }
+            ))
+        }
+    ))
+}
+
+/// A context-pointer-compatible wrapper around a panic reporter.
+private final class PanicReporterBox {
+    let reporter: @Sendable (LibsignalPanicReport) -> Void
+    init(_ reporter: @escaping @Sendable (LibsignalPanicReport) -> Void) {
+        self.reporter = reporter
+    }
+}
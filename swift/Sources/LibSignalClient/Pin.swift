@@ -46,6 +46,74 @@ public func verifyLocalPin<Bytes: ContiguousBytes>(_ pin: Bytes, againstEncodedH
     }
 }
 
+/// How easy a pin is to guess, as estimated by ``estimatePinStrength(_:)``.
+public struct PinStrength: Equatable {
+    /// How guessable a pin is, from worst to best.
+    public enum Level: UInt8, Comparable {
+        /// Among the first pins an attacker would try.
+        case veryWeak = 0
+        /// Follows a pattern that narrows down the search considerably.
+        case weak = 1
+        /// No known pattern, but a small search space.
+        case acceptable = 2
+        /// No known pattern and a large search space.
+        case strong = 3
+
+        public static func < (lhs: Self, rhs: Self) -> Bool {
+            return lhs.rawValue < rhs.rawValue
+        }
+    }
+
+    /// The patterns found in a pin that make it easier to guess.
+    public struct Weaknesses: OptionSet {
+        public let rawValue: UInt32
+
+        public init(rawValue: UInt32) {
+            self.rawValue = rawValue
+        }
+
+        /// Shorter than four characters.
+        public static let tooShort = Self(rawValue: 1 << 0)
+        /// One of the most commonly chosen pins.
+        public static let common = Self(rawValue: 1 << 1)
+        /// A short group of characters repeated, like `1111` or `1212`.
+        public static let repeated = Self(rawValue: 1 << 2)
+        /// Consecutive characters in order, like `1234`, `9876`, or `abcd`.
+        public static let sequence = Self(rawValue: 1 << 3)
+        /// A straight line on a phone keypad, like `2580`.
+        public static let keypadPattern = Self(rawValue: 1 << 4)
+        /// Looks like a date or a year, like `1987` or `311299`.
+        public static let date = Self(rawValue: 1 << 5)
+    }
+
+    public var level: Level
+    public var weaknesses: Weaknesses
+
+    /// Whether the pin should be accepted without a warning.
+    public var isAcceptable: Bool {
+        return self.level >= .acceptable
+    }
+}
+
+/// Estimate how easy a pin is to guess, so that weak pins can be warned about the same way on every
+/// platform.
+///
+/// - parameter pin: A normalized pin
+/// - returns: The estimated strength, and the patterns that make the pin easier to guess
+public func estimatePinStrength(_ pin: String) -> PinStrength {
+    let level = failOnError {
+        try invokeFnReturningInteger {
+            signal_pin_estimate_strength_level($0, pin)
+        }
+    }
+    let weaknesses = failOnError {
+        try invokeFnReturningInteger {
+            signal_pin_estimate_strength_weaknesses($0, pin)
+        }
+    }
+    return PinStrength(level: PinStrength.Level(rawValue: level)!, weaknesses: PinStrength.Weaknesses(rawValue: weaknesses))
+}
+
 /// A hash of the pin that can be used to interact with a Secure Value Recovery service.
 public class PinHash: NativeHandleOwner {
     override internal class func destroyNativeHandle(_ handle: OpaquePointer) -> SignalFfiErrorRef? {
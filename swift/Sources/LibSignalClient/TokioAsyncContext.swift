@@ -13,6 +13,23 @@ internal class TokioAsyncContext: NativeHandleOwner {
         self.init(owned: handle!)
     }
 
+    convenience init(config: Net.AsyncRuntimeConfig) throws {
+        var handle: OpaquePointer?
+        try checkError(signal_tokio_async_context_new_with_config(&handle, UInt32(config.workerThreads ?? 0), config.threadName, config.useCurrentThread))
+        self.init(owned: handle!)
+    }
+
+    /// Cancels all outstanding tasks and stops the runtime's threads.
+    ///
+    /// Cancelled tasks are given up to `timeout` seconds to complete. Tasks started afterwards are
+    /// cancelled immediately.
+    internal func shutdown(timeout: TimeInterval) {
+        let timeoutMillis = UInt32(clamping: Int64(max(timeout, 0) * 1000))
+        withNativeHandle { context in
+            failOnError(signal_tokio_async_context_shutdown(context, timeoutMillis))
+        }
+    }
+
     override internal class func destroyNativeHandle(_ handle: OpaquePointer) -> SignalFfiErrorRef? {
         signal_tokio_async_context_destroy(handle)
     }
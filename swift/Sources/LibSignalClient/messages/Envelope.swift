@@ -0,0 +1,181 @@
+//
+// Copyright 2024 Signal Messenger, LLC.
+// SPDX-License-Identifier: AGPL-3.0-only
+//
+
+import Foundation
+import SignalFfi
+
+/// An envelope delivered by the server, checked for consistency before any of its content is
+/// decrypted.
+///
+/// Messages that aren't sealed sender always have a complete source address, and sealed sender
+/// messages never do. The content has already been checked to parse as the kind of message
+/// ``contentType`` says it is.
+public class Envelope: NativeHandleOwner {
+    /// The server's `Envelope.Type` values that can appear in a valid envelope.
+    public enum ContentType: UInt8 {
+        case ciphertext = 1
+        case preKeyBundle = 3
+        case serverDeliveryReceipt = 5
+        case sealedSender = 6
+        case senderKeyMessage = 7
+        case plaintextContent = 8
+    }
+
+    override internal class func destroyNativeHandle(_ handle: OpaquePointer) -> SignalFfiErrorRef? {
+        return signal_envelope_destroy(handle)
+    }
+
+    public convenience init<Bytes: ContiguousBytes>(bytes: Bytes) throws {
+        var result: OpaquePointer?
+        try bytes.withUnsafeBorrowedBuffer {
+            try checkError(signal_envelope_deserialize(&result, $0))
+        }
+        self.init(owned: result!)
+    }
+
+    /// The sender, for every kind of content except ``ContentType/sealedSender``.
+    public var sourceServiceId: ServiceId? {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningOptionalString {
+                    signal_envelope_get_source_service_id($0, nativeHandle)
+                }.map { try ServiceId.parseFrom(serviceIdString: $0) }
+            }
+        }
+    }
+
+    /// The sender's device, present whenever ``sourceServiceId`` is.
+    public var sourceDeviceId: UInt32? {
+        let id = withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningInteger {
+                    signal_envelope_get_source_device_id($0, nativeHandle)
+                }
+            }
+        }
+        return id == 0xFFFF_FFFF ? nil : id
+    }
+
+    /// The local account identity the message was sent to, if the server said.
+    public var destinationServiceId: ServiceId? {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningOptionalString {
+                    signal_envelope_get_destination_service_id($0, nativeHandle)
+                }.map { try ServiceId.parseFrom(serviceIdString: $0) }
+            }
+        }
+    }
+
+    /// The timestamp assigned by the sender, in milliseconds since the epoch.
+    public var timestamp: UInt64 {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningInteger {
+                    signal_envelope_get_timestamp($0, nativeHandle)
+                }
+            }
+        }
+    }
+
+    /// When the server received the message, in milliseconds since the epoch.
+    public var serverTimestamp: UInt64? {
+        let timestamp = withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningInteger {
+                    signal_envelope_get_server_timestamp($0, nativeHandle)
+                }
+            }
+        }
+        return timestamp == 0 ? nil : timestamp
+    }
+
+    /// The server's identifier for the message.
+    public var serverGuid: UUID? {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningOptionalString {
+                    signal_envelope_get_server_guid($0, nativeHandle)
+                }.flatMap { UUID(uuidString: $0) }
+            }
+        }
+    }
+
+    public var contentType: ContentType {
+        let rawValue = withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningInteger {
+                    signal_envelope_get_type($0, nativeHandle)
+                }
+            }
+        }
+        return ContentType(rawValue: rawValue)!
+    }
+
+    /// The serialized message, to be decrypted according to ``contentType``.
+    ///
+    /// Empty for a ``ContentType/serverDeliveryReceipt``.
+    public var content: [UInt8] {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningArray {
+                    signal_envelope_get_content($0, nativeHandle)
+                }
+            }
+        }
+    }
+
+    public var isUrgent: Bool {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningBool {
+                    signal_envelope_get_urgent($0, nativeHandle)
+                }
+            }
+        }
+    }
+
+    public var isStory: Bool {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningBool {
+                    signal_envelope_get_story($0, nativeHandle)
+                }
+            }
+        }
+    }
+
+    public var isEphemeral: Bool {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningBool {
+                    signal_envelope_get_ephemeral($0, nativeHandle)
+                }
+            }
+        }
+    }
+
+    /// Set if the local account's PNI has changed.
+    public var updatedPni: Pni? {
+        return withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningOptionalString {
+                    signal_envelope_get_updated_pni($0, nativeHandle)
+                }.map { try Pni.parseFrom(serviceIdString: $0) }
+            }
+        }
+    }
+
+    public var reportSpamToken: [UInt8]? {
+        let token = withNativeHandle { nativeHandle in
+            failOnError {
+                try invokeFnReturningArray {
+                    signal_envelope_get_report_spam_token($0, nativeHandle)
+                }
+            }
+        }
+        return token.isEmpty ? nil : token
+    }
+}
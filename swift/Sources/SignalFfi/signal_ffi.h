@@ -212,6 +212,8 @@ typedef struct SignalAes256GcmSiv SignalAes256GcmSiv;
 
 typedef struct SignalCdsiLookup SignalCdsiLookup;
 
+typedef struct SignalCdsiLookupResult SignalCdsiLookupResult;
+
 typedef struct SignalChat SignalChat;
 
 typedef struct SignalCiphertextMessage SignalCiphertextMessage;
@@ -220,6 +222,8 @@ typedef struct SignalConnectionManager SignalConnectionManager;
 
 typedef struct SignalDecryptionErrorMessage SignalDecryptionErrorMessage;
 
+typedef struct SignalEnvelope SignalEnvelope;
+
 typedef struct SignalFingerprint SignalFingerprint;
 
 typedef struct SignalHsmEnclaveClient SignalHsmEnclaveClient;
@@ -439,6 +443,13 @@ typedef struct {
   SignalLogFlushCallback flush;
 } SignalFfiLogger;
 
+typedef void (*SignalPanicCallback)(void *ctx, const char *message, const char *location, const char *backtrace);
+
+typedef struct {
+  void *ctx;
+  SignalPanicCallback report;
+} SignalFfiPanicReporter;
+
 typedef struct {
   unsigned char *base;
   size_t length;
@@ -567,6 +578,21 @@ typedef struct {
   SignalCancellationId cancellation_id;
 } SignalCPromiseFfiCdsiLookupResponse;
 
+/**
+ * A C callback used to report the results of Rust futures.
+ *
+ * cbindgen will produce independent C types like `SignalCPromisei32` and
+ * `SignalCPromiseProtocolAddress`.
+ *
+ * This derives Copy because it behaves like a C type; nevertheless, a promise should still only be
+ * completed once.
+ */
+typedef struct {
+  void (*complete)(SignalFfiError *error, SignalCdsiLookupResult *const *result, const void *context);
+  const void *context;
+  SignalCancellationId cancellation_id;
+} SignalCPromiseCdsiLookupResult;
+
 typedef struct {
   uint8_t raw_ip_type;
   double duration_secs;
@@ -693,8 +719,12 @@ SignalFfiError *signal_error_get_retry_after_seconds(const SignalFfiError *err,
 
 SignalFfiError *signal_error_get_tries_remaining(const SignalFfiError *err, uint32_t *out);
 
+SignalFfiError *signal_error_get_http_status(const SignalFfiError *err, uint16_t *out);
+
 SignalFfiError *signal_error_get_unknown_fields(const SignalFfiError *err, SignalStringArray *out);
 
+SignalFfiError *signal_error_get_backtrace(const SignalFfiError *err, const char **out);
+
 void signal_error_free(SignalFfiError *err);
 
 SignalFfiError *signal_identitykeypair_deserialize(SignalPrivateKey **private_key, SignalPublicKey **public_key, SignalBorrowedBuffer input);
@@ -703,6 +733,28 @@ SignalFfiError *signal_sealed_session_cipher_decrypt(SignalOwnedBuffer *out, con
 
 bool signal_init_logger(SignalLogLevel max_level, SignalFfiLogger logger);
 
+bool signal_set_log_target_level(const char *target, SignalLogLevel max_level);
+
+bool signal_clear_log_target_level(const char *target);
+
+bool signal_set_log_target_rate_limit(const char *target, uint32_t max_per_second);
+
+void signal_set_panic_reporter(SignalFfiPanicReporter reporter);
+
+void signal_clear_panic_reporter(void);
+
+SignalFfiError *signal_handle_accounting_set_enabled(bool enabled);
+
+SignalFfiError *signal_handle_accounting_set_leak_threshold_seconds(uint32_t threshold_secs);
+
+SignalFfiError *signal_handle_accounting_snapshot_json(const char **out);
+
+SignalFfiError *signal_instrumentation_set_enabled(bool enabled);
+
+SignalFfiError *signal_instrumentation_reset(void);
+
+SignalFfiError *signal_instrumentation_snapshot_json(const char **out);
+
 SignalFfiError *signal_aes256_gcm_siv_destroy(SignalAes256GcmSiv *p);
 
 SignalFfiError *signal_aes256_ctr32_destroy(SignalAes256Ctr32 *p);
@@ -821,6 +873,8 @@ SignalFfiError *signal_kyber_secret_key_destroy(SignalKyberSecretKey *p);
 
 SignalFfiError *signal_kyber_secret_key_clone(SignalKyberSecretKey **new_obj, const SignalKyberSecretKey *obj);
 
+SignalFfiError *signal_envelope_destroy(SignalEnvelope *p);
+
 SignalFfiError *signal_hkdf_derive(SignalBorrowedMutableBuffer output, SignalBorrowedBuffer ikm, SignalBorrowedBuffer label, SignalBorrowedBuffer salt);
 
 SignalFfiError *signal_service_id_service_id_binary(SignalOwnedBuffer *out, const SignalServiceIdFixedWidthBinaryBytes *value);
@@ -1147,6 +1201,38 @@ SignalFfiError *signal_sealed_sender_multi_recipient_message_for_single_recipien
 
 SignalFfiError *signal_sealed_session_cipher_decrypt_to_usmc(SignalUnidentifiedSenderMessageContent **out, SignalBorrowedBuffer ctext, const SignalIdentityKeyStore *identity_store);
 
+SignalFfiError *signal_sealed_sender_message_version(uint8_t *out, SignalBorrowedBuffer ctext);
+
+SignalFfiError *signal_envelope_deserialize(SignalEnvelope **out, SignalBorrowedBuffer data);
+
+SignalFfiError *signal_envelope_get_source_service_id(const char **out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_source_device_id(uint32_t *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_destination_service_id(const char **out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_timestamp(uint64_t *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_server_timestamp(uint64_t *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_server_guid(const char **out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_type(uint8_t *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_content(SignalOwnedBuffer *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_urgent(bool *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_story(bool *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_ephemeral(bool *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_updated_pni(const char **out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_envelope_get_report_spam_token(SignalOwnedBuffer *out, const SignalEnvelope *envelope);
+
+SignalFfiError *signal_sealed_session_cipher_rewrap_v1_as_v2(SignalOwnedBuffer *out, SignalBorrowedBuffer ctext, const SignalIdentityKeyStore *identity_store);
+
 SignalFfiError *signal_sender_key_distribution_message_create(SignalSenderKeyDistributionMessage **out, const SignalProtocolAddress *sender, const uint8_t (*distribution_id)[16], const SignalSenderKeyStore *store);
 
 SignalFfiError *signal_process_sender_key_distribution_message(const SignalProtocolAddress *sender, const SignalSenderKeyDistributionMessage *sender_key_distribution_message, const SignalSenderKeyStore *store);
@@ -1159,8 +1245,14 @@ SignalFfiError *signal_device_transfer_generate_private_key(SignalOwnedBuffer *o
 
 SignalFfiError *signal_device_transfer_generate_private_key_with_format(SignalOwnedBuffer *out, uint8_t key_format);
 
+SignalFfiError *signal_device_transfer_generate_ec_private_key(SignalOwnedBuffer *out);
+
+SignalFfiError *signal_device_transfer_generate_ec_private_key_with_format(SignalOwnedBuffer *out, uint8_t key_format);
+
 SignalFfiError *signal_device_transfer_generate_certificate(SignalOwnedBuffer *out, SignalBorrowedBuffer private_key, const char *name, uint32_t days_to_expire);
 
+SignalFfiError *signal_device_transfer_generate_certificate_with_params(SignalOwnedBuffer *out, SignalBorrowedBuffer private_key, const char *common_name, const char *organization, const char *organizational_unit, uint32_t days_to_expire, uint32_t backdate_seconds, bool random_serial_number);
+
 SignalFfiError *signal_cds2_client_state_new(SignalSgxClientState **out, SignalBorrowedBuffer mrenclave, SignalBorrowedBuffer attestation_msg, uint64_t current_timestamp);
 
 SignalFfiError *signal_hsm_enclave_client_destroy(SignalHsmEnclaveClient *p);
@@ -1453,8 +1545,16 @@ SignalFfiError *signal_connection_manager_set_proxy(const SignalConnectionManage
 
 SignalFfiError *signal_connection_manager_clear_proxy(const SignalConnectionManager *connection_manager);
 
+SignalFfiError *signal_connection_manager_set_tls1_3_only(const SignalConnectionManager *connection_manager, bool tls1_3_only);
+
 SignalFfiError *signal_connection_manager_on_network_change(const SignalConnectionManager *connection_manager);
 
+SignalFfiError *signal_connection_manager_set_clock_offset(const SignalConnectionManager *connection_manager, int32_t offset_seconds);
+
+SignalFfiError *signal_connection_manager_apply_route_document(const SignalConnectionManager *connection_manager, SignalBorrowedBuffer signed_document, const SignalPublicKey *key);
+
+SignalFfiError *signal_connection_attempts_snapshot_json(const char **out);
+
 SignalFfiError *signal_create_otp(const char **out, const char *username, SignalBorrowedBuffer secret);
 
 SignalFfiError *signal_create_otp_from_base64(const char **out, const char *username, const char *secret);
@@ -1489,6 +1589,14 @@ SignalFfiError *signal_cdsi_lookup_token(SignalOwnedBuffer *out, const SignalCds
 
 SignalFfiError *signal_cdsi_lookup_complete(SignalCPromiseFfiCdsiLookupResponse *promise, const SignalTokioAsyncContext *async_runtime, const SignalCdsiLookup *lookup);
 
+SignalFfiError *signal_cdsi_lookup_result_destroy(SignalCdsiLookupResult *p);
+
+SignalFfiError *signal_cdsi_lookup_lookup(SignalCPromiseCdsiLookupResult *promise, const SignalTokioAsyncContext *async_runtime, const SignalConnectionManager *connection_manager, const char *username, const char *password, const SignalLookupRequest *request);
+
+SignalFfiError *signal_cdsi_lookup_result_token(SignalOwnedBuffer *out, const SignalCdsiLookupResult *result);
+
+SignalFfiError *signal_cdsi_lookup_result_take_response(SignalFfiCdsiLookupResponse *out, const SignalCdsiLookupResult *result);
+
 SignalFfiError *signal_chat_destroy(SignalChat *p);
 
 SignalFfiError *signal_http_request_destroy(SignalHttpRequest *p);
@@ -1527,8 +1635,12 @@ SignalFfiError *signal_tokio_async_context_destroy(SignalTokioAsyncContext *p);
 
 SignalFfiError *signal_tokio_async_context_new(SignalTokioAsyncContext **out);
 
+SignalFfiError *signal_tokio_async_context_new_with_config(SignalTokioAsyncContext **out, uint32_t worker_threads, const char *thread_name, bool use_current_thread);
+
 SignalFfiError *signal_tokio_async_context_cancel(const SignalTokioAsyncContext *context, uint64_t raw_cancellation_id);
 
+SignalFfiError *signal_tokio_async_context_shutdown(const SignalTokioAsyncContext *context, uint32_t timeout_millis);
+
 SignalFfiError *signal_pin_hash_destroy(SignalPinHash *p);
 
 SignalFfiError *signal_pin_hash_clone(SignalPinHash **new_obj, const SignalPinHash *obj);
@@ -1545,6 +1657,10 @@ SignalFfiError *signal_pin_local_hash(const char **out, SignalBorrowedBuffer pin
 
 SignalFfiError *signal_pin_verify_local_hash(bool *out, const char *encoded_hash, SignalBorrowedBuffer pin);
 
+SignalFfiError *signal_pin_estimate_strength_level(uint8_t *out, const char *pin);
+
+SignalFfiError *signal_pin_estimate_strength_weaknesses(uint32_t *out, const char *pin);
+
 SignalFfiError *signal_svr2_client_new(SignalSgxClientState **out, SignalBorrowedBuffer mrenclave, SignalBorrowedBuffer attestation_msg, uint64_t current_timestamp);
 
 SignalFfiError *signal_incremental_mac_destroy(SignalIncrementalMac *p);
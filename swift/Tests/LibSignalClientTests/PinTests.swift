@@ -33,6 +33,18 @@ class PinTests: TestCaseBase {
         XCTAssertFalse(try! verifyLocalPin(Array("badpassword".utf8), againstEncodedHash: hash))
     }
 
+    func testEstimateStrength() {
+        let weak = estimatePinStrength("1234")
+        XCTAssertEqual(weak.level, .veryWeak)
+        XCTAssertEqual(weak.weaknesses, [.common, .sequence])
+        XCTAssertFalse(weak.isAcceptable)
+
+        let strong = estimatePinStrength("831649")
+        XCTAssertEqual(strong.level, .strong)
+        XCTAssertEqual(strong.weaknesses, [])
+        XCTAssertTrue(strong.isAcceptable)
+    }
+
     func testKnown() {
         let pin = Array("password".utf8)
         // echo "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f" | xxd -r -p | base64
@@ -463,20 +463,33 @@ class PublicAPITests: TestCaseBase {
     }
 
     func testDeviceTransferKey() {
-        for keyFormat in KeyFormat.allCases {
-            let deviceKey = DeviceTransferKey.generate(formattedAs: keyFormat)
-
-            /*
-             Anything encoded in an ASN.1 SEQUENCE starts with 0x30 when encoded
-             as DER. (This test could be better.)
-             */
-            let key = deviceKey.privateKeyMaterial()
-            XCTAssert(key.count > 0)
-            XCTAssertEqual(key[0], 0x30)
-
-            let cert = deviceKey.generateCertificate("name", 30)
-            XCTAssert(cert.count > 0)
-            XCTAssertEqual(cert[0], 0x30)
+        for keyType in DeviceTransferKeyType.allCases {
+            for keyFormat in KeyFormat.allCases {
+                let deviceKey = DeviceTransferKey.generate(formattedAs: keyFormat, ofType: keyType)
+
+                /*
+                 Anything encoded in an ASN.1 SEQUENCE starts with 0x30 when encoded
+                 as DER. (This test could be better.)
+                 */
+                let key = deviceKey.privateKeyMaterial()
+                XCTAssert(key.count > 0)
+                XCTAssertEqual(key[0], 0x30)
+
+                let cert = deviceKey.generateCertificate("name", 30)
+                XCTAssert(cert.count > 0)
+                XCTAssertEqual(cert[0], 0x30)
+
+                let certWithParams = deviceKey.generateCertificate(
+                    commonName: "name",
+                    organization: "Org",
+                    organizationalUnit: nil,
+                    daysTilExpire: 30,
+                    backdateSeconds: 60 * 60,
+                    randomSerialNumber: true
+                )
+                XCTAssert(certWithParams.count > 0)
+                XCTAssertEqual(certWithParams[0], 0x30)
+            }
         }
     }
 
@@ -551,4 +564,34 @@ class PublicAPITests: TestCaseBase {
             XCTAssertEqual(bundle.kyberPreKeySignature, kyberPreKeySignature)
         }
     }
+
+    func testEnvelope() throws {
+        // A PLAINTEXT_CONTENT envelope from 9d0652a3-dcc3-4d11-975f-74d61598733f.2 at timestamp 1000,
+        // with a server GUID.
+        let plaintextEnvelope = [UInt8](fromHexString: "08085a2439643036353261332d646363332d346431312d393735662d373464363135393837333366380228e8074202c0804a2463346431366432662d346235632d346431322d613334632d356434626466613064393565")!
+        let envelope = try Envelope(bytes: plaintextEnvelope)
+        XCTAssertEqual(envelope.contentType, .plaintextContent)
+        XCTAssertEqual(envelope.sourceServiceId, try Aci.parseFrom(serviceIdString: "9d0652a3-dcc3-4d11-975f-74d61598733f"))
+        XCTAssertEqual(envelope.sourceDeviceId, 2)
+        XCTAssertNil(envelope.destinationServiceId)
+        XCTAssertEqual(envelope.timestamp, 1000)
+        XCTAssertNil(envelope.serverTimestamp)
+        XCTAssertEqual(envelope.serverGuid, UUID(uuidString: "c4d16d2f-4b5c-4d12-a34c-5d4bdfa0d95e"))
+        XCTAssert(envelope.isUrgent)
+        XCTAssertFalse(envelope.isStory)
+        XCTAssertFalse(envelope.isEphemeral)
+        XCTAssertNil(envelope.updatedPni)
+        XCTAssertNil(envelope.reportSpamToken)
+        XCTAssertEqual(envelope.content, [0xC0, 0x80])
+        XCTAssertEqual(try PlaintextContent(bytes: envelope.content).serialize(), envelope.content)
+
+        // The same, but missing the source device ID.
+        let incompleteSourceEnvelope = [UInt8](fromHexString: "08085a2439643036353261332d646363332d346431312d393735662d37346436313539383733336628e8074202c080")!
+        XCTAssertThrowsError(try Envelope(bytes: incompleteSourceEnvelope)) { error in
+            guard case SignalError.invalidArgument(_) = error else {
+                XCTFail("unexpected error: \(error)")
+                return
+            }
+        }
+    }
 }